@@ -1,6 +1,9 @@
 #![no_std]
 
-use starstream::{PublicKey, effect, token_import, utxo_import};
+use starstream::{Fixed, PublicKey, effect, token_import, utxo_import};
+
+/// Balances held by [`Savings`], to the millionth.
+pub type Balance = Fixed<6>;
 
 // "starstream:example_contract" should probably be something content-addressed
 #[link(wasm_import_module = "starstream_utxo:example_contract")]
@@ -25,6 +28,10 @@ unsafe extern "C" {
     );
 
     safe fn starstream_new_Stackless_new(start_arg: u32) -> Stackless;
+
+    safe fn starstream_new_Savings_new(owner: PublicKey, balance: Balance) -> Savings;
+    safe fn starstream_query_Savings_get_balance(utxo: Savings) -> Balance;
+    safe fn starstream_mutate_Savings_accrue(utxo: Savings, rate: Balance) -> Balance;
 }
 
 effect!(pub effect MyEffect(supply: u32) -> ());
@@ -154,3 +161,28 @@ impl Stackless {
         starstream_new_Stackless_new(start_arg)
     }
 }
+
+utxo_import! {
+    "starstream_utxo:example_contract";
+    Savings;
+    starstream_status_Savings;
+    starstream_resume_Savings;
+    ();
+}
+
+impl Savings {
+    #[inline]
+    pub fn new(owner: PublicKey, balance: Balance) -> Self {
+        starstream_new_Savings_new(owner, balance)
+    }
+
+    #[inline]
+    pub fn get_balance(self) -> Balance {
+        starstream_query_Savings_get_balance(self)
+    }
+
+    #[inline]
+    pub fn accrue(self, rate: Balance) -> Balance {
+        starstream_mutate_Savings_accrue(self, rate)
+    }
+}
@@ -5,7 +5,7 @@
 
 use core::ops::Coroutine;
 
-use example_contract::{StarNft, StarNftIntermediate};
+use example_contract::{Balance, StarNft, StarNftIntermediate};
 use starstream::{
     PublicKey, Token, TokenStorage, Utxo, assert_tx_signed_by, eprintln, token_export,
 };
@@ -172,6 +172,36 @@ impl StarNftMint {
     }
 }
 
+// A fixed-point balance that earns interest. Floats aren't available on
+// chain, so the balance is kept in millionths.
+pub struct Savings {
+    owner: PublicKey,
+    balance: Balance,
+}
+
+impl Savings {
+    pub fn new(owner: PublicKey, balance: Balance, sleep: fn(&mut Savings)) {
+        let mut this = Savings { owner, balance };
+        loop {
+            sleep(&mut this);
+        }
+    }
+
+    pub fn get_balance(&self) -> Balance {
+        self.balance
+    }
+
+    pub fn accrue(&mut self, rate: Balance) -> Balance {
+        assert_tx_signed_by(self.owner);
+        let interest = self.balance.checked_mul(rate).expect("interest overflow");
+        self.balance = self
+            .balance
+            .checked_add(interest)
+            .expect("balance overflow");
+        self.balance
+    }
+}
+
 token_export! {
     for StarNftIntermediate;
     bind fn starstream_bind_StarNft(this: Self) -> TokenStorage {
@@ -258,6 +288,21 @@ pub extern "C" fn starstream_mutate_PayToPublicKeyHash_attach(
     this.attach::<StarNft>(i)
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn starstream_new_Savings_new(owner: PublicKey, balance: Balance) {
+    Savings::new(owner, balance, starstream::sleep_mut::<(), Savings>)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn starstream_query_Savings_get_balance(this: &Savings) -> Balance {
+    this.get_balance()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn starstream_mutate_Savings_accrue(this: &mut Savings, rate: Balance) -> Balance {
+    this.accrue(rate)
+}
+
 // ----------------------------------------------------------------------------
 // Coordination script
 
@@ -301,6 +346,18 @@ pub extern "C" fn star_split(
     example_contract::StarToken::new(owner, amount)
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn savings_open(owner: PublicKey, balance: Balance) -> example_contract::Savings {
+    example_contract::Savings::new(owner, balance)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn savings_accrue(savings: example_contract::Savings, rate: Balance) -> Balance {
+    let balance = savings.accrue(rate);
+    eprintln!("savings balance is now {balance}");
+    balance
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn new_nft() -> example_contract::StarNftMint {
     example_contract::StarNftMint::new(u64::MAX)
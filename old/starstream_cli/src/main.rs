@@ -16,6 +16,12 @@ enum Args {
         /// The Wasm output file.
         #[arg(short = 'o')]
         output_file: Option<PathBuf>,
+        /// Enable items marked `#[when(FEATURE)]`. May be repeated.
+        #[arg(long = "feature")]
+        features: Vec<String>,
+        /// Fold constant subexpressions, like `2 + 3`, before codegen.
+        #[arg(long = "fold-constants")]
+        fold_constants: bool,
     },
     /// Run a coordination script from a Starstream Wasm module.
     Run {
@@ -49,6 +55,8 @@ fn main() {
         Args::Compile {
             compile_file,
             output_file,
+            features,
+            fold_constants,
         } => {
             let source_code =
                 std::fs::read_to_string(&compile_file).expect("Error reading Starstream input");
@@ -59,9 +67,10 @@ fn main() {
             for error in errors {
                 error.eprint(&source).unwrap();
             }
-            let Some(ast) = ast else {
+            let Some(mut ast) = ast else {
                 std::process::exit(1);
             };
+            ast.strip_disabled_items(&features.iter().map(String::as_str).collect::<Vec<_>>());
 
             let (ast, mut symbols) = match starstream_compiler::do_scope_analysis(ast) {
                 Ok(success) => success,
@@ -74,7 +83,7 @@ fn main() {
                 }
             };
 
-            let ast = match starstream_compiler::do_type_inference(ast, &mut symbols) {
+            let mut ast = match starstream_compiler::do_type_inference(ast, &mut symbols) {
                 Ok((ast, warnings)) => {
                     for warning in warnings {
                         ariadne::Report::from(&warning).eprint(&source).unwrap();
@@ -89,12 +98,16 @@ fn main() {
                 }
             };
 
-            let (module, errors) = starstream_compiler::compile(&ast, symbols);
-            for error in errors {
-                error.eprint(&source).unwrap();
-            }
-            let Some(module) = module else {
-                std::process::exit(1);
+            starstream_compiler::Optimizations { fold_constants }.apply(&mut ast, &symbols);
+
+            let module = match starstream_compiler::compile(&ast, symbols) {
+                Ok(module) => module,
+                Err(errors) => {
+                    for error in errors {
+                        error.eprint(&source).unwrap();
+                    }
+                    std::process::exit(1);
+                }
             };
 
             if let Some(output_file) = output_file {
@@ -109,7 +122,8 @@ fn main() {
         } => {
             let mut transaction = Transaction::new();
             let coordination_code = transaction.code_cache().load_file(&module);
-            transaction.run_coordination_script(&coordination_code, &entry, Vec::new());
+            let result =
+                transaction.run_coordination_script(&coordination_code, &entry, Vec::new());
             if let Some(output_mermaid) = output_mermaid {
                 std::fs::write(output_mermaid, transaction.to_mermaid_diagram()).unwrap();
             }
@@ -125,6 +139,10 @@ fn main() {
                 )
                 .unwrap();
             }
+            if let Err(error) = result {
+                eprintln!("Transaction failed: {error}");
+                std::process::exit(1);
+            }
         }
     }
 }
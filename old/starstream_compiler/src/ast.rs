@@ -2,11 +2,62 @@
 
 use crate::{symbols::SymbolId, typechecking::ComparableType};
 use chumsky::span::SimpleSpan;
+use std::{cell::OnceCell, rc::Rc};
 
 /// The root type of a Starstream source file.
 #[derive(Clone, Debug, Default)]
 pub struct StarstreamProgram {
-    pub items: Vec<ProgramItem>,
+    pub items: Vec<Item>,
+}
+
+/// A top-level item, along with its doc comment and attribute.
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub node: ProgramItem,
+    /// The byte range of the item in the source, doc comment and attribute
+    /// included.
+    pub span: SimpleSpan,
+    /// The feature the item is conditional on, from a `#[when(feature)]`
+    /// attribute.
+    pub when: Option<Identifier>,
+    /// The `///` doc comment before the item.
+    pub docs: Option<String>,
+}
+
+impl StarstreamProgram {
+    /// Drop the items whose `#[when(feature)]` names a feature that isn't
+    /// in `features`. Items without the attribute are always kept.
+    pub fn strip_disabled_items(&mut self, features: &[&str]) {
+        self.items.retain(|item| match &item.when {
+            Some(feature) => features.contains(&feature.raw.as_str()),
+            None => true,
+        });
+    }
+
+    /// The index of the item whose span contains `offset`.
+    pub fn item_at(&self, offset: usize) -> Option<usize> {
+        self.items
+            .iter()
+            .position(|item| item.span.start <= offset && offset < item.span.end)
+    }
+
+    /// Replace the item at `index` with one re-parsed from the edited source,
+    /// shifting the spans of the items after it to match.
+    ///
+    /// Only the item spans are shifted. Spans inside later items still point
+    /// into the old source until those items are re-parsed too.
+    pub fn replace_item(&mut self, index: usize, item: Item) {
+        let delta = item.span.end as isize - self.items[index].span.end as isize;
+
+        self.items[index] = item;
+
+        for item in &mut self.items[index + 1..] {
+            item.span = SimpleSpan::from(
+                item.span.start.wrapping_add_signed(delta)
+                    ..item.span.end.wrapping_add_signed(delta),
+            );
+        }
+    }
 }
 
 /// A coordination script, UTXO, or token definition block.
@@ -18,7 +69,13 @@ pub enum ProgramItem {
     Utxo(Utxo),
     Token(Token),
     TypeDef(TypeDef),
-    Constant { name: Identifier, value: f64 },
+    /// `const NAME: ty = expr;`, folded at compile time. Without a type,
+    /// the constant is a `u32`.
+    Constant {
+        name: Identifier,
+        ty: Option<TypeArg>,
+        value: Spanned<Expr>,
+    },
 }
 
 /// `utxo Name { ... }`
@@ -33,8 +90,8 @@ pub enum UtxoItem {
     Main(Main),
     Impl(Impl),
     Storage(Storage),
-    Yield(TypeArg),
-    Resume(TypeArg),
+    Yield(Spanned<TypeArg>),
+    Resume(Spanned<TypeArg>),
 }
 
 #[derive(Clone, Debug)]
@@ -69,9 +126,18 @@ pub struct Mint(pub Block, pub Identifier);
 #[derive(Clone, Debug)]
 pub struct Impl {
     pub name: Identifier,
+    pub constants: Vec<AssociatedConst>,
     pub definitions: Vec<FnDef>,
 }
 
+/// `const NAME: ty = value;` inside an `impl`, referenced as `Type::NAME`.
+#[derive(Clone, Debug)]
+pub struct AssociatedConst {
+    pub name: Identifier,
+    pub ty: TypeArg,
+    pub value: Spanned<Expr>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Script {
     pub definitions: Vec<FnDef>,
@@ -80,6 +146,11 @@ pub struct Script {
 #[derive(Clone, Debug)]
 pub struct Storage {
     pub bindings: TypedBindings,
+    /// `= expr` default for each of `bindings`, evaluated in declaration order
+    /// at the start of `main`.
+    pub initializers: Vec<Option<Spanned<Expr>>>,
+    /// The `///` doc comment before each of `bindings`.
+    pub docs: Vec<Option<String>>,
 }
 
 #[derive(Clone, Debug)]
@@ -105,6 +176,8 @@ pub struct FnDef {
     pub output: Option<TypeArg>,
     pub body: Block,
     pub effects: Vec<Identifier>,
+    /// The `///` doc comment before the definition.
+    pub docs: Option<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -117,6 +190,9 @@ pub enum EffectDecl {
 #[derive(Clone, Debug)]
 pub enum AbiElem {
     FnDecl(FnDecl),
+    /// `fn foo(x: T) { ... }`, a default implementation that impls inherit
+    /// unless they override it.
+    FnDef(FnDef),
     EffectDecl(EffectDecl),
 }
 
@@ -165,8 +241,21 @@ pub enum TypeDefRhs {
 #[derive(Clone, Debug)]
 pub struct Object(pub TypedBindings);
 
+/// The cases of an enum, each with its fields and optional explicit
+/// discriminant. Cases without one follow the previous case, starting at 0.
 #[derive(Clone, Debug)]
-pub struct Variant(pub Vec<(Identifier, TypedBindings)>);
+pub struct Variant(pub Vec<(Identifier, TypedBindings, Option<u64>)>);
+
+impl Variant {
+    /// The discriminant of each case, in declaration order.
+    pub fn discriminants(&self) -> impl Iterator<Item = u64> + '_ {
+        self.0.iter().scan(0u64, |next, (_, _, explicit)| {
+            let discriminant = explicit.unwrap_or(*next);
+            *next = discriminant.wrapping_add(1);
+            Some(discriminant)
+        })
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TypeRef(pub Identifier);
@@ -187,6 +276,8 @@ pub enum TypeArg {
     I32,
     U64,
     I64,
+    U128,
+    I128,
     String,
     Intermediate {
         abi: Box<TypeArg>,
@@ -196,8 +287,45 @@ pub enum TypeArg {
     TypeApplication(TypeRef, Vec<TypeArg>),
     FnType(FnType),
     Ref(Box<TypeArg>),
+    /// `(T, U, ...)` with at least two elements.
+    Tuple(Vec<TypeArg>),
+    /// `[T; N]`
+    Array(Box<TypeArg>, ArrayLen),
+}
+
+/// The length `N` of an array type `[T; N]`, a constant expression.
+///
+/// Copies of the type share the value, which typechecking fills in once it
+/// evaluates `expr`.
+#[derive(Clone, Debug)]
+pub struct ArrayLen {
+    pub expr: Box<Spanned<Expr>>,
+    pub value: Rc<OnceCell<usize>>,
 }
 
+impl ArrayLen {
+    pub fn new(expr: Spanned<Expr>) -> Self {
+        Self {
+            expr: Box::new(expr),
+            value: Rc::default(),
+        }
+    }
+
+    /// The evaluated length, 0 until typechecking evaluates it, or if it
+    /// can't be.
+    pub fn value(&self) -> usize {
+        self.value.get().copied().unwrap_or(0)
+    }
+}
+
+impl PartialEq for ArrayLen {
+    fn eq(&self, other: &Self) -> bool {
+        self.value.get() == other.value.get()
+    }
+}
+
+impl Eq for ArrayLen {}
+
 impl PartialEq for TypeArg {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -217,6 +345,8 @@ impl PartialEq for TypeArg {
             (Self::TypeApplication(l0, l1), Self::TypeApplication(r0, r1)) => l0 == r0 && l1 == r1,
             (Self::FnType(l0), Self::FnType(r0)) => l0 == r0,
             (Self::Ref(l0), Self::Ref(r0)) => l0 == r0,
+            (Self::Tuple(l0), Self::Tuple(r0)) => l0 == r0,
+            (Self::Array(l0, l1), Self::Array(r0, r1)) => l0 == r0 && l1 == r1,
             _ => core::mem::discriminant(self) == core::mem::discriminant(other),
         }
     }
@@ -240,12 +370,37 @@ pub enum Statement {
         var: FieldAccessExpression,
         expr: Spanned<Expr>,
     },
-    /// `with { a... } catch (b) { c... } ...`
-    With(Block, Vec<(EffectHandler, Block)>),
     /// `while (a) { b... }`
     While(Spanned<Expr>, LoopBody),
     /// `loop { a... }`
     Loop(LoopBody),
+    /// `for a in b..c { d... }`
+    For {
+        var: Identifier,
+        start: Spanned<Expr>,
+        end: Spanned<Expr>,
+        body: LoopBody,
+    },
+    /// `'a: while ...`, `'a: loop ...` or `'a: for ...`
+    Labeled(LoopLabel, Box<Statement>),
+    /// `break ['a] [v];`
+    Break {
+        label: Option<LoopLabel>,
+        value: Option<Spanned<Expr>>,
+        span: SimpleSpan,
+    },
+    /// `continue ['a];`
+    Continue {
+        label: Option<LoopLabel>,
+        span: SimpleSpan,
+    },
+}
+
+/// `'a`, naming a loop for `break` and `continue`.
+#[derive(Clone, Debug)]
+pub struct LoopLabel {
+    pub name: String,
+    pub span: SimpleSpan,
 }
 
 #[derive(Clone, Debug)]
@@ -313,6 +468,20 @@ pub enum BlockExpr {
     IfThenElse(Box<Spanned<Expr>>, Box<Block>, Option<Box<Block>>),
     /// `{ a... }`
     Block(Block),
+    /// `try { a... } with B::c(d) { e... } ...`, the value of `a...`
+    TryWith(Box<Block>, Vec<(EffectHandler, Block)>),
+    /// `loop { a... }`, the value of whichever `break v;` ends it
+    Loop(LoopBody),
+    /// `match (a) { B(c, ...) => { d... }, ... }`
+    Match(Box<Spanned<Expr>>, Vec<MatchArm>),
+}
+
+/// `A(b, ...) => { c... }`, binding the fields of the enum case `A` in order.
+#[derive(Clone, Debug)]
+pub struct MatchArm {
+    pub variant: Identifier,
+    pub bindings: Vec<Identifier>,
+    pub body: Block,
 }
 
 #[derive(Clone, Debug)]
@@ -322,6 +491,11 @@ pub enum FieldAccessExpression {
         base: Box<FieldAccessExpression>,
         field: IdentifierExpr,
     },
+    /// `a[b]`
+    Index {
+        base: Box<FieldAccessExpression>,
+        index: Box<Spanned<Expr>>,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -336,8 +510,12 @@ pub enum PrimaryExpr {
         literal: u32,
         ty: Option<ComparableType>,
     },
+    /// `5u64` literal, typed by its suffix
+    TypedNumber(i128, TypeArg),
     /// `true` or `false` literal
     Bool(bool),
+    /// `'a'` literal, as its codepoint
+    Char(u32),
     /// `a`
     Ident(IdentifierExpr),
     /// `a::b::c`
@@ -385,6 +563,9 @@ pub enum Block {
 #[derive(Clone, Debug)]
 pub struct Arguments {
     pub xs: Vec<Spanned<Expr>>,
+    /// `name: value` arguments, which always come after the positional ones.
+    /// Scope resolution moves them into `xs` in the callee's parameter order.
+    pub named: Vec<(Identifier, Spanned<Expr>)>,
 }
 
 #[derive(Clone, Debug)]
@@ -6,14 +6,14 @@ use chumsky::span::SimpleSpan;
 use wasm_encoder::{
     BlockType, CodeSection, ConstExpr, DataSection, Encode, EntityType, ExportSection, FuncType,
     FunctionSection, GlobalSection, GlobalType, ImportSection, InstructionSink, MemArg,
-    MemorySection, MemoryType, Module, RefType, TypeSection, ValType,
+    MemorySection, MemoryType, Module, TypeSection, ValType,
 };
 
 use crate::{
     ast::*,
     symbols::{
-        AbiInfo, ArgOrConst, EffectHandlers, FuncInfo, SymbolId, SymbolInformation, Symbols,
-        VarInfo,
+        AbiInfo, ArgOrConst, ConstValue, EffectHandlers, FuncInfo, SymbolId, SymbolInformation,
+        Symbols, VarInfo,
     },
     typechecking::{ComparableType, PrimitiveType, TypeVar},
 };
@@ -22,10 +22,13 @@ const GLOBAL_FRAME_PTR: u32 = 0;
 const GLOBAL_STACK_PTR: u32 = 1;
 
 /// Compile a Starstream AST to a binary WebAssembly module.
-pub fn compile<'a>(
-    program: &'a StarstreamProgram,
+///
+/// Programs that typecheck but use something codegen doesn't support yet are
+/// reported as diagnostics, like errors from the earlier passes.
+pub fn compile(
+    program: &StarstreamProgram,
     symbols: Symbols,
-) -> (Option<Vec<u8>>, Vec<Report<'a>>) {
+) -> Result<Vec<u8>, Vec<Report<'static>>> {
     let mut compiler = Compiler::new(symbols);
     compiler.visit_program(program);
     compiler.finish()
@@ -47,6 +50,8 @@ enum StaticType {
     // U16,
     U32,
     U64,
+    I128,
+    U128,
     F32,
     F64,
     // Char,
@@ -62,7 +67,9 @@ enum StaticType {
     // https://component-model.bytecodealliance.org/design/wit.html#results
     // Result(Box<StaticType>, Box<StaticType>),
     // https://component-model.bytecodealliance.org/design/wit.html#tuples
-    // Tuple(Vec<StaticType>),
+    Tuple(Vec<StaticType>),
+    /// `[T; N]`, stored inline wherever it lives.
+    Array(Box<StaticType>, usize),
 
     // User-defined types
     Record(Record),
@@ -79,6 +86,15 @@ pub struct Record {
     offsets: HashMap<String, (usize, Box<StaticType>)>,
 }
 
+impl Record {
+    /// The size of the fields, which are laid out one after the other.
+    fn size(&self) -> usize {
+        self.offsets
+            .values()
+            .fold(0, |size, (_, ty)| size.saturating_add(ty.mem_size()))
+    }
+}
+
 impl StaticType {
     fn stack_intermediate(&self) -> Intermediate {
         match self {
@@ -88,18 +104,26 @@ impl StaticType {
             StaticType::I64 => Intermediate::StackI64,
             StaticType::U32 => Intermediate::StackU32,
             StaticType::U64 => Intermediate::StackU64,
+            StaticType::I128 => Intermediate::StackI128,
+            StaticType::U128 => Intermediate::StackU128,
             StaticType::F32 => Intermediate::StackF32,
             StaticType::F64 => Intermediate::StackF64,
             StaticType::StrRef => Intermediate::StackStrRef,
             StaticType::Resource(_) => Intermediate::StackExternRef,
 
             StaticType::Reference(_) => Intermediate::StackI64,
-            s @ StaticType::Record(_) => Intermediate::StackPtr(s.clone()),
-            _ => todo!(),
+            s @ (StaticType::Record(_) | StaticType::Array(..)) => {
+                Intermediate::StackPtr(s.clone())
+            }
+            StaticType::Tuple(elems) => {
+                Intermediate::StackTuple(elems.iter().map(|e| e.stack_intermediate()).collect())
+            }
+            // already reported by `Compiler::new`
+            StaticType::Function(_) => Intermediate::Error,
         }
     }
 
-    fn lower(&self) -> &'static [ValType] {
+    fn lower(&self) -> Vec<ValType> {
         self.stack_intermediate().stack_types()
     }
 
@@ -113,12 +137,22 @@ impl StaticType {
             ComparableType::Primitive(PrimitiveType::I32) => StaticType::I32,
             ComparableType::Primitive(PrimitiveType::U64) => StaticType::U64,
             ComparableType::Primitive(PrimitiveType::I64) => StaticType::I64,
+            ComparableType::Primitive(PrimitiveType::U128) => StaticType::U128,
+            ComparableType::Primitive(PrimitiveType::I128) => StaticType::I128,
             ComparableType::Primitive(PrimitiveType::F32) => StaticType::F32,
             ComparableType::Primitive(PrimitiveType::F64) => StaticType::F64,
             ComparableType::Primitive(PrimitiveType::Bool) => StaticType::Bool,
             ComparableType::Primitive(PrimitiveType::StrRef) => StaticType::I32,
             ComparableType::Intermediate => StaticType::I64,
-            ComparableType::FnType(_, _) => todo!(),
+            ComparableType::FnType(inputs, output) => {
+                StaticType::Function(Rc::new(StarFunctionType {
+                    params: inputs
+                        .iter()
+                        .map(|ty| StaticType::from_canonical_type(ty, type_vars))
+                        .collect(),
+                    results: vec![StaticType::from_canonical_type(output, type_vars)],
+                }))
+            }
             ComparableType::Utxo(_symbol_id, _) => StaticType::I64,
             ComparableType::Var(type_var) => {
                 StaticType::from_canonical_type(type_vars.get(type_var).unwrap(), type_vars)
@@ -127,6 +161,18 @@ impl StaticType {
                 StaticType::Reference(Box::new(StaticType::from_canonical_type(ty, type_vars)))
             }
             ComparableType::Void => StaticType::Void,
+            ComparableType::Array(elem, len) => StaticType::Array(
+                Box::new(StaticType::from_canonical_type(elem, type_vars)),
+                *len,
+            ),
+            // tuples are flattened onto the stack, so they can be returned
+            // as multiple values
+            ComparableType::Product(pairs) if is_tuple(pairs) => StaticType::Tuple(
+                pairs
+                    .iter()
+                    .map(|(_, ty)| StaticType::from_canonical_type(ty, type_vars))
+                    .collect(),
+            ),
             // represent product types as pointers to linear memory
             ComparableType::Product(pairs) => {
                 let mut offsets = HashMap::new();
@@ -136,12 +182,34 @@ impl StaticType {
                     let ty_mem_size = ty.mem_size();
                     offsets.insert(name.clone(), (offset, Box::new(ty)));
 
-                    offset += ty_mem_size;
+                    offset = offset.saturating_add(ty_mem_size);
                 }
 
                 StaticType::Record(Record { offsets })
             }
-            _ => todo!("from_canonical_type({:?})", ty),
+            // enums without fields are represented by their discriminant
+            ComparableType::Sum(variants)
+                if variants.iter().all(
+                    |(_, fields)| matches!(fields, ComparableType::Product(f) if f.is_empty()),
+                ) =>
+            {
+                StaticType::I64
+            }
+            // otherwise they're flattened onto the stack like tuples, the
+            // discriminant followed by the fields of every case
+            ComparableType::Sum(variants) => StaticType::Tuple(
+                std::iter::once(StaticType::I64)
+                    .chain(variants.iter().flat_map(|(_, fields)| {
+                        match fields {
+                            ComparableType::Product(fields) => fields
+                                .iter()
+                                .map(|(_, ty)| StaticType::from_canonical_type(ty, type_vars))
+                                .collect(),
+                            _ => vec![],
+                        }
+                    }))
+                    .collect(),
+            ),
         }
     }
 
@@ -153,13 +221,22 @@ impl StaticType {
             StaticType::I64 => 8,
             StaticType::U32 => 4,
             StaticType::U64 => 8,
+            StaticType::I128 => 16,
+            StaticType::U128 => 16,
             StaticType::F32 => 4,
             StaticType::F64 => 8,
             StaticType::StrRef => 4,
             StaticType::Reference(_static_type) => 4,
             StaticType::Record(_record) => 4,
-            StaticType::Resource(_resource_type) => todo!(),
-            StaticType::Function(_star_function_type) => todo!(),
+            // saturating, so that sizes too large for memory can be reported
+            // instead of overflowing
+            StaticType::Tuple(elems) => elems
+                .iter()
+                .fold(0, |size, elem| size.saturating_add(elem.mem_size())),
+            StaticType::Array(elem, len) => elem.mem_size().saturating_mul(*len),
+            // neither can be stored in linear memory, and function values
+            // are already reported by `Compiler::new`
+            StaticType::Resource(_) | StaticType::Function(_) => 0,
         }
     }
 }
@@ -188,6 +265,10 @@ enum Intermediate {
     StackI64,
     /// `(i64)` But use unsigned math where relevant.
     StackU64,
+    /// `(i64 i64)` Low half first.
+    StackI128,
+    /// `(i64 i64)` Low half first. But use unsigned math where relevant.
+    StackU128,
     /// `(f32)`
     StackF32,
     /// `(f64)`
@@ -198,23 +279,31 @@ enum Intermediate {
 
     /// pointer to linear memory
     StackPtr(StaticType),
+    /// `(...)` The values of each element, in order.
+    StackTuple(Vec<Intermediate>),
 }
 
 impl Intermediate {
-    fn stack_types(&self) -> &'static [ValType] {
+    fn stack_types(&self) -> Vec<ValType> {
         match self {
-            Intermediate::Void | Intermediate::Error => &[],
-            Intermediate::StackBool => &[ValType::I32],
-            Intermediate::StackI32 => &[ValType::I32],
-            Intermediate::StackI64 => &[ValType::I64],
-            Intermediate::StackU32 => &[ValType::I32],
-            Intermediate::StackU64 => &[ValType::I64],
-            Intermediate::StackF32 => &[ValType::F32],
-            Intermediate::StackF64 => &[ValType::F64],
-            Intermediate::StackStrRef => &[ValType::I32, ValType::I32],
-            Intermediate::StackExternRef => &[ValType::EXTERNREF],
-            Intermediate::StackPtr(_) => &[ValType::I32],
-            _ => todo!("Intermediate::stack_types({self:?})"),
+            Intermediate::Void
+            | Intermediate::Error
+            | Intermediate::ConstNull
+            | Intermediate::ConstFunction(_) => vec![],
+            Intermediate::StackBool => vec![ValType::I32],
+            Intermediate::StackI32 => vec![ValType::I32],
+            Intermediate::StackI64 => vec![ValType::I64],
+            Intermediate::StackU32 => vec![ValType::I32],
+            Intermediate::StackU64 => vec![ValType::I64],
+            Intermediate::StackI128 | Intermediate::StackU128 => vec![ValType::I64, ValType::I64],
+            Intermediate::StackF32 => vec![ValType::F32],
+            Intermediate::StackF64 => vec![ValType::F64],
+            Intermediate::StackStrRef => vec![ValType::I32, ValType::I32],
+            Intermediate::StackExternRef => vec![ValType::EXTERNREF],
+            Intermediate::StackPtr(_) => vec![ValType::I32],
+            Intermediate::StackTuple(elems) => {
+                elems.iter().flat_map(Intermediate::stack_types).collect()
+            }
         }
     }
 
@@ -223,20 +312,6 @@ impl Intermediate {
     }
 }
 
-impl From<ValType> for Intermediate {
-    fn from(value: ValType) -> Self {
-        match value {
-            ValType::I32 => Intermediate::StackI32,
-            ValType::I64 => Intermediate::StackI64,
-            ValType::F32 => Intermediate::StackF32,
-            ValType::F64 => Intermediate::StackF64,
-            ValType::V128 => todo!(),
-            ValType::Ref(RefType::EXTERNREF) => Intermediate::StackExternRef,
-            ValType::Ref(_) => todo!(),
-        }
-    }
-}
-
 #[derive(Debug, Clone)]
 struct StarFunctionType {
     params: Vec<StaticType>,
@@ -246,8 +321,8 @@ struct StarFunctionType {
 impl StarFunctionType {
     fn lower(&self) -> FuncType {
         FuncType::new(
-            self.params.iter().flat_map(|p| p.lower()).copied(),
-            self.results.iter().flat_map(|p| p.lower()).copied(),
+            self.params.iter().flat_map(|p| p.lower()),
+            self.results.iter().flat_map(|p| p.lower()),
         )
     }
 }
@@ -308,6 +383,57 @@ impl Compiler {
     fn new(mut symbols_table: Symbols) -> Compiler {
         let mut this = Compiler::default();
 
+        // Values of function type can't be lowered yet. Report them once
+        // here instead of wherever their types get lowered.
+        let mut fn_values = symbols_table
+            .vars
+            .values()
+            .filter(|var| {
+                var.info
+                    .ty
+                    .as_ref()
+                    .is_some_and(|ty| has_fn_type(ty, &symbols_table.type_vars))
+            })
+            .map(|var| (&var.source, var.span))
+            .chain(
+                symbols_table
+                    .functions
+                    .values()
+                    .filter(|f| {
+                        f.info
+                            .output_canonical_ty
+                            .as_ref()
+                            .is_some_and(|ty| has_fn_type(ty, &symbols_table.type_vars))
+                    })
+                    .map(|f| (&f.source, f.span)),
+            )
+            .chain(
+                symbols_table
+                    .effects
+                    .values()
+                    .filter(|effect| {
+                        effect
+                            .info
+                            .inputs_canonical_ty
+                            .iter()
+                            .chain(&effect.info.output_canonical_ty)
+                            .any(|ty| has_fn_type(ty, &symbols_table.type_vars))
+                    })
+                    .map(|effect| (&effect.source, effect.span)),
+            )
+            .collect::<Vec<_>>();
+        fn_values.sort_by_key(|(_, span)| span.map(|span| span.start));
+        for (name, span) in fn_values {
+            Report::build(
+                ReportKind::Error,
+                span.map_or(0..0, |span| span.into_range()),
+            )
+            .with_message(format_args!(
+                "{name}: values of function type are not supported yet"
+            ))
+            .push(&mut this);
+        }
+
         // Function indices in calls, exports, etc. are based on the combined
         // imports + declared functions list. The easiest way to handle this is
         // to know the whole list of imported functions before compiling. Do
@@ -358,6 +484,13 @@ impl Compiler {
         for (_f_id, f_info) in fns.iter_mut() {
             cache_required_effect_handlers(&symbols_table.interfaces, f_info);
 
+            let is_list_method = f_info.info.is_imported.is_some()
+                && f_info
+                    .info
+                    .mangled_name
+                    .as_ref()
+                    .is_some_and(|name| name.starts_with("starstream_list_"));
+
             if f_info.source == "resume" && f_info.info.mangled_name.is_some() {
                 let index = this.import_function(
                     "starstream_utxo:this",
@@ -400,7 +533,10 @@ impl Compiler {
                 );
 
                 f_info.info.index.replace(index);
-            } else if f_info.source == "new" && f_info.info.mangled_name.is_some() {
+            } else if f_info.source == "new"
+                && f_info.info.mangled_name.is_some()
+                && !is_list_method
+            {
                 let index = this.import_function(
                     "starstream_utxo:this",
                     f_info.info.mangled_name.as_ref().unwrap(),
@@ -534,8 +670,10 @@ impl Compiler {
 
                 this.global_scope_functions
                     .insert(f_info.source.clone(), index);
-            } else if ["spend", "burn", "amount", "type", "mint"].contains(&f_info.source.as_str())
-                && f_info.info.is_imported.is_some()
+            } else if (["spend", "burn", "amount", "type", "mint"]
+                .contains(&f_info.source.as_str())
+                && f_info.info.is_imported.is_some())
+                || is_list_method
             {
                 let map_canonical_to_static_type = |ty: &TypeArg| {
                     StaticType::from_canonical_type(
@@ -677,7 +815,22 @@ impl Compiler {
                 var_info.info.frame_offset.replace(offset);
 
                 // TODO: consider alignment?
-                offset += wasm_ty.mem_size() as u32;
+                let Some(next) = u32::try_from(wasm_ty.mem_size())
+                    .ok()
+                    .and_then(|size| offset.checked_add(size))
+                else {
+                    Report::build(
+                        ReportKind::Error,
+                        var_info.span.map_or(0..0, |span| span.into_range()),
+                    )
+                    .with_message(format_args!(
+                        "{} is too large to fit in memory",
+                        var_info.source
+                    ))
+                    .push(&mut this);
+                    break;
+                };
+                offset = next;
             }
 
             f_info.info.frame_size = offset;
@@ -695,7 +848,11 @@ impl Compiler {
         this
     }
 
-    fn finish(mut self) -> (Option<Vec<u8>>, Vec<Report<'static>>) {
+    fn finish(mut self) -> Result<Vec<u8>, Vec<Report<'static>>> {
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
         for _ in [GLOBAL_FRAME_PTR, GLOBAL_STACK_PTR] {
             self.globals.global(
                 GlobalType {
@@ -740,9 +897,7 @@ impl Compiler {
             }
         }
 
-        // TODO: return None if the errors were fatal.
-        let module = self.to_module();
-        (Some(module.finish()), self.errors)
+        Ok(self.to_module().finish())
     }
 
     fn to_module(&self) -> Module {
@@ -846,7 +1001,7 @@ impl Compiler {
 
     fn visit_program(&mut self, program: &StarstreamProgram) {
         for item in &program.items {
-            self.visit_item(item);
+            self.visit_item(&item.node);
         }
     }
 
@@ -857,7 +1012,8 @@ impl Compiler {
             ProgramItem::Token(token) => self.visit_token(token),
             ProgramItem::Abi(_abi) => {}
             ProgramItem::TypeDef(_) => {}
-            _ => self.todo(format!("ProgramItem::{:?}", item)),
+            // constants are folded during typechecking and inlined at use sites
+            ProgramItem::Constant { .. } => {}
         }
     }
 
@@ -1027,6 +1183,9 @@ impl Compiler {
                 Block::Chain { head, tail } => {
                     match &**head {
                         ExprOrStatement::Statement(statement) => {
+                            // e.g. the value of a `try` used as a statement
+                            self.drop_intermediate(func, last);
+                            last = Intermediate::Void;
                             self.visit_statement(func, statement, effect_handlers);
                         }
                         ExprOrStatement::Expr(expr) => {
@@ -1082,122 +1241,694 @@ impl Compiler {
                 ty: _,
                 value,
             } => {
-                let var_info = self.symbols_table.vars.get(&var.uid.unwrap()).unwrap();
-                let wasm_local_index = var_info.info.wasm_local_index;
-                let frame_offset = var_info.info.frame_offset;
-                let ty = var_info.info.ty.clone();
+                self.bind_var(func, var, |this, func| {
+                    this.visit_expr(func, value, effect_handlers)
+                });
+            }
+            Statement::Assign { var, expr } => {
+                let im = self.visit_field_access_expr(func, var, Some(expr), effect_handlers);
 
-                if var_info.info.is_captured {
-                    func.instructions().global_get(GLOBAL_FRAME_PTR);
+                assert!(matches!(im, Intermediate::Void));
+            }
+            Statement::While(cond, body) => {
+                let cond = LoopCond::Expr(cond);
+                let im = self.visit_loop(func, None, Some(cond), None, body, effect_handlers);
+                self.drop_intermediate(func, im);
+            }
+            Statement::Loop(body) => {
+                let im = self.visit_loop(func, None, None, None, body, effect_handlers);
+                self.drop_intermediate(func, im);
+            }
+            Statement::For {
+                var,
+                start,
+                end,
+                body,
+            } => {
+                let im = self.visit_for(func, None, var, start, end, body, effect_handlers);
+                self.drop_intermediate(func, im);
+            }
+            Statement::Labeled(label, statement) => match &**statement {
+                Statement::For {
+                    var,
+                    start,
+                    end,
+                    body,
+                } => {
+                    let im =
+                        self.visit_for(func, Some(label), var, start, end, body, effect_handlers);
+                    self.drop_intermediate(func, im);
                 }
+                Statement::While(cond, body) => {
+                    let cond = LoopCond::Expr(cond);
+                    let im =
+                        self.visit_loop(func, Some(label), Some(cond), None, body, effect_handlers);
+                    self.drop_intermediate(func, im);
+                }
+                Statement::Loop(body) => {
+                    let im = self.visit_loop(func, Some(label), None, None, body, effect_handlers);
+                    self.drop_intermediate(func, im);
+                }
+                statement => self.visit_statement(func, statement, effect_handlers),
+            },
+            Statement::Break {
+                label,
+                value,
+                span: _,
+            } => {
+                let im = value
+                    .as_ref()
+                    .map(|value| self.visit_expr(func, value, effect_handlers));
 
-                let im = self.visit_expr(func, value, effect_handlers);
+                if let Some(index) = self.enclosing_loop(func, label.as_ref()) {
+                    if let Some(im) = im.filter(|im| im.stack_size() > 0) {
+                        self.store_break_value(func, index, im);
+                    }
+                    func.loops[index].broken = true;
 
-                if matches!(im, Intermediate::Error) {
-                    Report::build(ReportKind::Error, 0..0)
-                        .with_message(format_args!("can't assign expression to variable"))
-                        .push(self);
+                    // the enclosing `block` is one level out from the `loop`
+                    let depth = func.block_depth - func.loops[index].depth;
+                    func.instructions().br(depth + 1);
+                }
+            }
+            Statement::Continue { label, span: _ } => {
+                if let Some(index) = self.enclosing_loop(func, label.as_ref()) {
+                    let depth = func.block_depth - func.loops[index].continue_depth;
+                    func.instructions().br(depth);
+                }
+            }
+            _ => self.todo(format!("Statement::{:?}", statement)),
+        }
+    }
+
+    /// Store the value pushed by `value` in the local or frame slot of `var`.
+    fn bind_var(
+        &mut self,
+        func: &mut Function,
+        var: &Identifier,
+        value: impl FnOnce(&mut Self, &mut Function) -> Intermediate,
+    ) {
+        let var_info = self.symbols_table.vars.get(&var.uid.unwrap()).unwrap();
+        let wasm_local_index = var_info.info.wasm_local_index;
+        let frame_offset = var_info.info.frame_offset;
+        let ty = var_info.info.ty.clone();
+
+        if var_info.info.is_captured {
+            func.instructions().global_get(GLOBAL_FRAME_PTR);
+        }
+
+        let im = value(self, func);
+
+        if matches!(im, Intermediate::Error) {
+            Report::build(ReportKind::Error, 0..0)
+                .with_message(format_args!("can't assign expression to variable"))
+                .push(self);
 
-                    return;
+            return;
+        }
+
+        let current_fn_info = self
+            .symbols_table
+            .functions
+            .get(&func.fn_id.unwrap())
+            .unwrap();
+
+        if let Some(wasm_local_index) = wasm_local_index {
+            let first = func
+                .local_slots
+                .get(&var.uid.unwrap())
+                .copied()
+                .unwrap_or(wasm_local_index as u32)
+                + current_fn_info.info.effect_handlers.len() as u32;
+            let num_slots = im.stack_size() as u32;
+
+            // the last value is on top of the stack
+            for slot in (first..first + num_slots).rev() {
+                func.instructions().local_set(slot);
+            }
+        } else if let Some(frame_offset) = frame_offset {
+            let static_type =
+                StaticType::from_canonical_type(&ty.unwrap(), &self.symbols_table.type_vars);
+            let _im = self.visit_mem(
+                func,
+                Some(static_type.stack_intermediate()),
+                frame_offset as usize,
+                &static_type,
+            );
+        }
+    }
+
+    /// Compile the handlers of a `try` into their own functions, then the
+    /// body inline, whose value is that of the whole expression.
+    fn visit_try_with(
+        &mut self,
+        func: &mut Function,
+        block: &Block,
+        handlers: &[(EffectHandler, Block)],
+        effect_handlers: &EffectHandlers,
+    ) -> Intermediate {
+        let mut effect_handlers = effect_handlers.clone();
+
+        for (decl, body) in handlers {
+            let fn_id = decl.ident.uid.unwrap();
+            let f_info = self.symbols_table.functions.get(&fn_id).unwrap();
+            let saved_frame = f_info.info.saved_frame_local_index.unwrap();
+
+            let index = f_info.info.index.unwrap();
+
+            effect_handlers.insert(
+                *f_info.info.is_effect_handler.as_ref().unwrap(),
+                ArgOrConst::Const(decl.ident.uid.unwrap()),
+            );
+
+            let mut func = self.get_function_body(index);
+
+            func.instructions()
+                // save frame pointer
+                .global_get(GLOBAL_FRAME_PTR)
+                .local_set(saved_frame)
+                // set frame pointer to received frame
+                //
+                // this way can always reference captured variables to
+                // the frame pointer
+                .local_get(0)
+                .global_set(GLOBAL_FRAME_PTR);
+
+            let im = self.visit_block(&mut func, body, &effect_handlers);
+
+            self.drop_intermediate(&mut func, im);
+            func.instructions()
+                // restore frame pointer
+                .local_get(saved_frame)
+                .global_set(GLOBAL_FRAME_PTR)
+                .end();
+
+            self.replace_function_body(index, func);
+        }
+
+        self.visit_block(func, block, &effect_handlers)
+    }
+
+    /// `for i in a..b { ... }` as `let i = a; while (i < b) { ...; i = i + 1; }`,
+    /// with `continue` still going through the increment, and `b` evaluated
+    /// only once, before the loop. Scope resolution rejects assigning to `i`.
+    #[allow(clippy::too_many_arguments)]
+    fn visit_for(
+        &mut self,
+        func: &mut Function,
+        label: Option<&LoopLabel>,
+        var: &Identifier,
+        start: &Spanned<Expr>,
+        end: &Spanned<Expr>,
+        body: &LoopBody,
+        effect_handlers: &EffectHandlers,
+    ) -> Intermediate {
+        self.visit_statement(
+            func,
+            &Statement::BindVar {
+                var: var.clone(),
+                mutable: true,
+                ty: None,
+                value: start.clone(),
+            },
+            effect_handlers,
+        );
+
+        let end_im = self.visit_expr(func, end, effect_handlers);
+        if matches!(end_im, Intermediate::Error) {
+            return Intermediate::Error;
+        }
+        let end_local = func.store_locals(None, &end_im.stack_types());
+
+        let var_info = self.symbols_table.vars.get(&var.uid.unwrap()).unwrap();
+        let ty = var_info.info.ty.clone();
+        let span = var.span.unwrap_or(start.span);
+        let place = FieldAccessExpression::PrimaryExpr(PrimaryExpr::Ident(IdentifierExpr {
+            name: var.clone(),
+            args: None,
+        }));
+        let value = Spanned {
+            node: Expr::PrimaryExpr(place.clone()),
+            span,
+        };
+
+        let below = Spanned {
+            node: Expr::LessThan(Box::new(value.clone()), Box::new(end.clone())),
+            span: end.span,
+        };
+        let step = Statement::Assign {
+            var: place,
+            expr: Spanned {
+                node: Expr::Add(
+                    Box::new(value),
+                    Box::new(Spanned {
+                        node: Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(
+                            PrimaryExpr::Number { literal: 1, ty },
+                        )),
+                        span,
+                    }),
+                ),
+                span,
+            },
+        };
+
+        let cond = LoopCond::Hoisted {
+            cond: &below,
+            rhs_local: end_local,
+            rhs: end_im,
+        };
+        self.visit_loop(func, label, Some(cond), Some(&step), body, effect_handlers)
+    }
+
+    /// `block { loop { ... br 0 } }`, so `br 0` from the body continues and
+    /// `br 1` breaks.
+    ///
+    /// With a `step`, the body gets a `block` of its own that `continue`
+    /// breaks out of, so the step runs before going around again.
+    ///
+    /// The value of `break v;` goes through locals rather than the `block`'s
+    /// result, since its type is only known once a `break` is compiled.
+    fn visit_loop(
+        &mut self,
+        func: &mut Function,
+        label: Option<&LoopLabel>,
+        cond: Option<LoopCond<'_>>,
+        step: Option<&Statement>,
+        body: &LoopBody,
+        effect_handlers: &EffectHandlers,
+    ) -> Intermediate {
+        func.instructions().block(BlockType::Empty);
+        func.instructions().loop_(BlockType::Empty);
+        func.block_depth += 2;
+        func.loops.push(EnclosingLoop {
+            label: label.map(|label| label.name.clone()),
+            depth: func.block_depth,
+            continue_depth: func.block_depth + u32::from(step.is_some()),
+            value: None,
+            // `while` also ends when its condition is false
+            broken: cond.is_some(),
+        });
+
+        if let Some(cond) = cond {
+            let im = match cond {
+                LoopCond::Expr(cond) => self.visit_expr(func, cond, effect_handlers),
+                LoopCond::Hoisted {
+                    cond,
+                    rhs_local,
+                    rhs,
+                } => {
+                    let (Expr::LessThan(lhs, _)
+                    | Expr::GreaterThan(lhs, _)
+                    | Expr::LessEq(lhs, _)
+                    | Expr::GreaterEq(lhs, _)) = &cond.node
+                    else {
+                        unreachable!("only comparisons are hoisted");
+                    };
+                    let lhs = self.visit_expr(func, lhs, effect_handlers);
+                    for i in 0..rhs.stack_size() as u32 {
+                        func.instructions().local_get(rhs_local + i);
+                    }
+                    self.visit_comparison(func, &cond.node, lhs, rhs)
                 }
+            };
 
-                let current_fn_info = self
-                    .symbols_table
-                    .functions
-                    .get(&func.fn_id.unwrap())
-                    .unwrap();
+            assert!(matches!(im, Intermediate::StackBool));
 
-                if let Some(wasm_local_index) = wasm_local_index {
-                    func.instructions().local_set(
-                        wasm_local_index as u32 + current_fn_info.info.effect_handlers.len() as u32,
-                    );
-                } else if let Some(frame_offset) = frame_offset {
-                    let static_type = StaticType::from_canonical_type(
-                        &ty.unwrap(),
-                        &self.symbols_table.type_vars,
-                    );
-                    let _im = self.visit_mem(
-                        func,
-                        Some(static_type.stack_intermediate()),
-                        frame_offset as usize,
-                        &static_type,
-                    );
+            // leave once the condition is false
+            func.instructions().i32_eqz().br_if(1);
+        }
+
+        if step.is_some() {
+            func.instructions().block(BlockType::Empty);
+            func.block_depth += 1;
+        }
+
+        let body = match body {
+            LoopBody::Statement(statement) => {
+                self.visit_statement(func, statement, effect_handlers);
+                Intermediate::Void
+            }
+            LoopBody::Block(block) => self.visit_block(func, block, effect_handlers),
+            LoopBody::Expr(expr) => self.visit_expr(func, expr, effect_handlers),
+        };
+
+        assert!(matches!(body, Intermediate::Void));
+        self.drop_intermediate(func, body);
+
+        if let Some(step) = step {
+            func.instructions().end();
+            func.block_depth -= 1;
+            self.visit_statement(func, step, effect_handlers);
+        }
+
+        func.instructions().br(0).end().end();
+        let target = func.loops.pop().unwrap();
+        func.block_depth -= 2;
+
+        match target.value {
+            Some((first_local, im)) => {
+                for i in 0..im.stack_size() as u32 {
+                    func.instructions().local_get(first_local + i);
                 }
+                im
             }
-            Statement::Assign { var, expr } => {
-                let im = self.visit_field_access_expr(func, var, Some(expr), effect_handlers);
+            None if target.broken => Intermediate::Void,
+            None => {
+                // nothing breaks out, so the loop has no value to leave
+                func.instructions().unreachable();
+                Intermediate::Void
+            }
+        }
+    }
 
-                assert!(matches!(im, Intermediate::Void));
+    /// `op`, one of `<`, `>`, `<=` and `>=`, on operands already on the stack.
+    fn visit_comparison(
+        &mut self,
+        func: &mut Function,
+        op: &Expr,
+        lhs: Intermediate,
+        rhs: Intermediate,
+    ) -> Intermediate {
+        match (lhs, rhs) {
+            (Intermediate::Error, _) | (_, Intermediate::Error) => {
+                return Intermediate::Error;
             }
-            Statement::While(cond, body) => {
-                func.instructions().block(BlockType::Empty);
-                func.instructions().loop_(BlockType::Empty);
+            (Intermediate::StackI32, Intermediate::StackI32) => match op {
+                Expr::LessThan(_, _) => {
+                    func.instructions().i32_lt_s();
+                }
+                Expr::GreaterThan(_, _) => {
+                    func.instructions().i32_gt_s();
+                }
+                Expr::LessEq(_, _) => {
+                    func.instructions().i32_le_s();
+                }
+                Expr::GreaterEq(_, _) => {
+                    func.instructions().i32_ge_s();
+                }
+                _ => {
+                    return Intermediate::Error;
+                }
+            },
+            (Intermediate::StackU32, Intermediate::StackU32) => match op {
+                Expr::LessThan(_, _) => {
+                    func.instructions().i32_lt_u();
+                }
+                Expr::GreaterThan(_, _) => {
+                    func.instructions().i32_gt_u();
+                }
+                Expr::LessEq(_, _) => {
+                    func.instructions().i32_le_u();
+                }
+                Expr::GreaterEq(_, _) => {
+                    func.instructions().i32_ge_u();
+                }
+                _ => {
+                    return Intermediate::Error;
+                }
+            },
+            (Intermediate::StackI64, Intermediate::StackI64) => match op {
+                Expr::LessThan(_, _) => {
+                    func.instructions().i64_lt_s();
+                }
+                Expr::GreaterThan(_, _) => {
+                    func.instructions().i64_gt_s();
+                }
+                Expr::LessEq(_, _) => {
+                    func.instructions().i64_le_s();
+                }
+                Expr::GreaterEq(_, _) => {
+                    func.instructions().i64_ge_s();
+                }
+                _ => {
+                    return Intermediate::Error;
+                }
+            },
+            (Intermediate::StackU64, Intermediate::StackU64) => match op {
+                Expr::LessThan(_, _) => {
+                    func.instructions().i64_lt_u();
+                }
+                Expr::GreaterThan(_, _) => {
+                    func.instructions().i64_gt_u();
+                }
+                Expr::LessEq(_, _) => {
+                    func.instructions().i64_le_u();
+                }
+                Expr::GreaterEq(_, _) => {
+                    func.instructions().i64_ge_u();
+                }
+                _ => {
+                    return Intermediate::Error;
+                }
+            },
+            (Intermediate::StackI128, Intermediate::StackI128) => cmp_128(func, op, true),
+            (Intermediate::StackU128, Intermediate::StackU128) => cmp_128(func, op, false),
+            (lhs, rhs) => {
+                self.todo(format!("Expr::LessThan({:?}, {:?})", lhs, rhs));
+                return Intermediate::Error;
+            }
+        };
 
-                let im = self.visit_expr(func, cond, effect_handlers);
+        Intermediate::StackBool
+    }
 
-                assert!(matches!(im, Intermediate::StackBool));
+    /// The cases of the enum `constructor` builds, or `None` if it isn't the
+    /// constructor of one.
+    fn enum_cases(&self, constructor: SymbolId) -> Option<Vec<EnumCase>> {
+        let f_info = self.symbols_table.functions.get(&constructor)?;
+        let Some(TypeArg::TypeRef(TypeRef(enum_name))) = &f_info.info.output_ty else {
+            return None;
+        };
+        let enum_info = self.symbols_table.types.get(&enum_name.uid?)?;
+        let Some(TypeDefRhs::Variant(variant)) = enum_info.info.type_def() else {
+            return None;
+        };
 
-                func.instructions().br_if(1);
+        if !enum_info.info.declarations.contains(&constructor) {
+            return None;
+        }
 
-                let body = match body {
-                    LoopBody::Statement(statement) => {
-                        self.visit_statement(func, statement, effect_handlers);
-                        Intermediate::Void
-                    }
-                    LoopBody::Block(block) => self.visit_block(func, block, effect_handlers),
-                    LoopBody::Expr(expr) => self.visit_expr(func, expr, effect_handlers),
-                };
+        let cases = variant
+            .0
+            .iter()
+            .zip(variant.discriminants())
+            .map(|((name, fields, _), discriminant)| EnumCase {
+                name: name.raw.clone(),
+                discriminant,
+                fields: fields
+                    .values
+                    .iter()
+                    .map(|(_, ty)| {
+                        StaticType::from_canonical_type(
+                            &ty.canonical_form(&self.symbols_table),
+                            &self.symbols_table.type_vars,
+                        )
+                    })
+                    .collect(),
+            })
+            .collect();
 
-                assert!(matches!(body, Intermediate::Void));
-                self.drop_intermediate(func, body);
+        Some(cases)
+    }
 
-                func.instructions().br(0).end().end();
+    /// The discriminant of case `name`, then the fields of every case, with
+    /// `args` for those of `name` and zeros for the rest.
+    fn visit_enum_constructor(
+        &mut self,
+        func: &mut Function,
+        name: &str,
+        span: SimpleSpan,
+        cases: &[EnumCase],
+        args: &[Spanned<Expr>],
+        effect_handlers: &EffectHandlers,
+    ) -> Intermediate {
+        let discriminant = cases
+            .iter()
+            .find(|case| case.name == name)
+            .map(|case| case.discriminant)
+            .unwrap();
+        func.instructions().i64_const(discriminant as i64);
+
+        let mut elems = vec![Intermediate::StackI64];
+
+        for case in cases {
+            if case.name == name {
+                for arg in args {
+                    let _im = self.visit_expr(func, arg, effect_handlers);
+                }
+            } else {
+                for ty in case.fields.iter().flat_map(StaticType::lower) {
+                    if !func.push_zero(ty) {
+                        Report::build(ReportKind::Error, span.into_range())
+                            .with_message(format_args!(
+                                "{name}: no zero value of {ty:?} for the fields of {}",
+                                case.name
+                            ))
+                            .push(self);
+                        return Intermediate::Error;
+                    }
+                }
             }
-            Statement::With(block, handlers) => {
-                let mut effect_handlers = effect_handlers.clone();
 
-                for (decl, body) in handlers {
-                    let fn_id = decl.ident.uid.unwrap();
-                    let f_info = self.symbols_table.functions.get(&fn_id).unwrap();
-                    let saved_frame = f_info.info.saved_frame_local_index.unwrap();
+            elems.extend(case.fields.iter().map(StaticType::stack_intermediate));
+        }
 
-                    let index = f_info.info.index.unwrap();
+        Intermediate::StackTuple(elems)
+    }
 
-                    effect_handlers.insert(
-                        *f_info.info.is_effect_handler.as_ref().unwrap(),
-                        ArgOrConst::Const(decl.ident.uid.unwrap()),
-                    );
+    /// `block { block { ... } block { ... } ... unreachable }`, with a `block`
+    /// per arm that's skipped with `br_if 0` unless the discriminant is that
+    /// of its case, and otherwise leaves the `match` with `br 1`.
+    ///
+    /// The scrutinee is kept in locals for the arms to compare against and
+    /// bind fields from, and like `break v;`, the value of the arms goes
+    /// through locals too.
+    fn visit_match(
+        &mut self,
+        func: &mut Function,
+        scrutinee: &Spanned<Expr>,
+        arms: &[MatchArm],
+        effect_handlers: &EffectHandlers,
+    ) -> Intermediate {
+        let im = self.visit_expr(func, scrutinee, effect_handlers);
 
-                    let mut func = self.get_function_body(index);
+        if matches!(im, Intermediate::Error) {
+            return Intermediate::Error;
+        }
 
-                    func.instructions()
-                        // save frame pointer
-                        .global_get(GLOBAL_FRAME_PTR)
-                        .local_set(saved_frame)
-                        // set frame pointer to received frame
-                        //
-                        // this way can always reference captured variables to
-                        // the frame pointer
-                        .local_get(0)
-                        .global_set(GLOBAL_FRAME_PTR);
-
-                    let im = self.visit_block(&mut func, body, &effect_handlers);
-
-                    self.drop_intermediate(&mut func, im);
-                    func.instructions()
-                        // restore frame pointer
-                        .local_get(saved_frame)
-                        .global_set(GLOBAL_FRAME_PTR)
-                        .end();
+        let scrutinee = func.store_locals(None, &im.stack_types());
 
-                    self.replace_function_body(index, func);
+        let cases = arms
+            .iter()
+            .find_map(|arm| arm.variant.uid)
+            .and_then(|constructor| self.enum_cases(constructor))
+            .unwrap_or_default();
+
+        func.instructions().block(BlockType::Empty);
+        func.block_depth += 1;
+
+        let mut value: Option<(u32, Intermediate)> = None;
+
+        for arm in arms {
+            // the fields of each case follow the discriminant, in order
+            let mut slot = scrutinee + 1;
+            let mut case = None;
+            for candidate in &cases {
+                if candidate.name == arm.variant.raw {
+                    case = Some(candidate);
+                    break;
                 }
+                slot += candidate
+                    .fields
+                    .iter()
+                    .map(|ty| ty.lower().len() as u32)
+                    .sum::<u32>();
+            }
+
+            let Some(case) = case else {
+                self.todo(format!("match arm {}", arm.variant.raw));
+                continue;
+            };
+
+            func.instructions()
+                .block(BlockType::Empty)
+                .local_get(scrutinee)
+                .i64_const(case.discriminant as i64)
+                .i64_ne()
+                .br_if(0);
+            func.block_depth += 1;
+
+            for (binding, ty) in arm.bindings.iter().zip(&case.fields) {
+                let num_slots = ty.lower().len() as u32;
+
+                self.bind_var(func, binding, |_, func| {
+                    for local in slot..slot + num_slots {
+                        func.instructions().local_get(local);
+                    }
+                    ty.stack_intermediate()
+                });
 
-                let im = self.visit_block(func, block, &effect_handlers);
+                slot += num_slots;
+            }
+
+            let im = self.visit_block(func, &arm.body, effect_handlers);
 
+            if im.stack_size() > 0 {
+                let first_local = value.as_ref().map(|(first, _)| *first);
+                let first_local = func.store_locals(first_local, &im.stack_types());
+                value.get_or_insert((first_local, im));
+            } else {
                 self.drop_intermediate(func, im);
             }
-            _ => self.todo(format!("Statement::{:?}", statement)),
+
+            func.instructions().br(1).end();
+            func.block_depth -= 1;
+        }
+
+        // the typechecker made sure one of the arms matches
+        func.instructions().unreachable().end();
+        func.block_depth -= 1;
+
+        match value {
+            Some((first_local, im)) => {
+                for i in 0..im.stack_size() as u32 {
+                    func.instructions().local_get(first_local + i);
+                }
+                im
+            }
+            None => Intermediate::Void,
+        }
+    }
+
+    /// Move the value of `break v;` off the stack into the locals of the
+    /// loop at `index`, allocating them on its first `break`.
+    fn store_break_value(&mut self, func: &mut Function, index: usize, im: Intermediate) {
+        let first_local = func.loops[index].value.as_ref().map(|(first, _)| *first);
+        let first_local = func.store_locals(first_local, &im.stack_types());
+
+        func.loops[index].value.get_or_insert((first_local, im));
+    }
+
+    /// Index in [`Function::loops`] of the loop a `break` or `continue` jumps
+    /// to.
+    fn enclosing_loop(&mut self, func: &Function, label: Option<&LoopLabel>) -> Option<usize> {
+        let target = func.loops.iter().rposition(|target| match label {
+            Some(label) => target.label.as_ref() == Some(&label.name),
+            None => true,
+        });
+
+        if target.is_none() {
+            // scope resolution should have caught this
+            self.todo("break or continue without a matching loop".to_string());
+        }
+
+        target
+    }
+
+    /// Evaluate the right side of `&&` or `||` inside the branch opened by
+    /// the caller, returning whether it was a bool.
+    fn visit_short_circuit_rhs(
+        &mut self,
+        func: &mut Function,
+        rhs: &Spanned<Expr>,
+        op: &str,
+        effect_handlers: &EffectHandlers,
+    ) -> bool {
+        func.block_depth += 1;
+        let rhs_ty = self.visit_expr(func, rhs, effect_handlers);
+        func.block_depth -= 1;
+
+        match rhs_ty {
+            Intermediate::Error => false,
+            Intermediate::StackBool => true,
+            rhs_ty => {
+                Report::build(ReportKind::Error, rhs.span.into_range())
+                    .with_message(format_args!(
+                        "type mismatch: `{op}` requires bools, but right side was {rhs_ty:?}"
+                    ))
+                    .push(self);
+                false
+            }
         }
     }
 
@@ -1226,6 +1957,11 @@ impl Compiler {
                         func.instructions().i64_eq();
                         Intermediate::StackBool
                     }
+                    (Intermediate::StackI128, Intermediate::StackI128)
+                    | (Intermediate::StackU128, Intermediate::StackU128) => {
+                        eq_128(func, false);
+                        Intermediate::StackBool
+                    }
                     (Intermediate::StackF64, Intermediate::StackF64) => {
                         func.instructions().f64_eq();
                         Intermediate::StackBool
@@ -1251,6 +1987,11 @@ impl Compiler {
                         func.instructions().i64_ne();
                         Intermediate::StackBool
                     }
+                    (Intermediate::StackI128, Intermediate::StackI128)
+                    | (Intermediate::StackU128, Intermediate::StackU128) => {
+                        eq_128(func, true);
+                        Intermediate::StackBool
+                    }
                     (Intermediate::StackF64, Intermediate::StackF64) => {
                         func.instructions().f64_ne();
                         Intermediate::StackBool
@@ -1270,15 +2011,29 @@ impl Compiler {
                         func.instructions().f64_add();
                         Intermediate::StackF64
                     }
-                    (Intermediate::StackI32, Intermediate::StackI32)
-                    | (Intermediate::StackU32, Intermediate::StackU32) => {
+                    (Intermediate::StackI32, Intermediate::StackI32) => {
                         func.instructions().i32_add();
-                        Intermediate::StackI32 // TODO: separate branch that produces StackU32
+                        Intermediate::StackI32
                     }
-                    (Intermediate::StackI64, Intermediate::StackI64)
-                    | (Intermediate::StackU64, Intermediate::StackU64) => {
+                    (Intermediate::StackU32, Intermediate::StackU32) => {
+                        func.instructions().i32_add();
+                        Intermediate::StackU32
+                    }
+                    (Intermediate::StackI64, Intermediate::StackI64) => {
                         func.instructions().i64_add();
-                        Intermediate::StackI64 // TODO: separate branch that produces StackU64
+                        Intermediate::StackI64
+                    }
+                    (Intermediate::StackU64, Intermediate::StackU64) => {
+                        func.instructions().i64_add();
+                        Intermediate::StackU64
+                    }
+                    (Intermediate::StackI128, Intermediate::StackI128) => {
+                        add_128(func);
+                        Intermediate::StackI128
+                    }
+                    (Intermediate::StackU128, Intermediate::StackU128) => {
+                        add_128(func);
+                        Intermediate::StackU128
                     }
                     (lhs, rhs) => {
                         self.todo(format!("Expr::Add({:?}, {:?})", lhs, rhs));
@@ -1295,15 +2050,29 @@ impl Compiler {
                         func.instructions().f64_sub();
                         Intermediate::StackF64
                     }
-                    (Intermediate::StackI32, Intermediate::StackI32)
-                    | (Intermediate::StackU32, Intermediate::StackU32) => {
+                    (Intermediate::StackI32, Intermediate::StackI32) => {
                         func.instructions().i32_sub();
-                        Intermediate::StackI32 // TODO: separate branch that produces StackU32
+                        Intermediate::StackI32
                     }
-                    (Intermediate::StackI64, Intermediate::StackI64)
-                    | (Intermediate::StackU64, Intermediate::StackU64) => {
+                    (Intermediate::StackU32, Intermediate::StackU32) => {
+                        func.instructions().i32_sub();
+                        Intermediate::StackU32
+                    }
+                    (Intermediate::StackI64, Intermediate::StackI64) => {
+                        func.instructions().i64_sub();
+                        Intermediate::StackI64
+                    }
+                    (Intermediate::StackU64, Intermediate::StackU64) => {
                         func.instructions().i64_sub();
-                        Intermediate::StackI64 // TODO: separate branch that produces StackU64
+                        Intermediate::StackU64
+                    }
+                    (Intermediate::StackI128, Intermediate::StackI128) => {
+                        sub_128(func);
+                        Intermediate::StackI128
+                    }
+                    (Intermediate::StackU128, Intermediate::StackU128) => {
+                        sub_128(func);
+                        Intermediate::StackU128
                     }
                     (lhs, rhs) => {
                         self.todo(format!("Expr::Sub({:?}, {:?})", lhs, rhs));
@@ -1320,15 +2089,21 @@ impl Compiler {
                         func.instructions().f64_mul();
                         Intermediate::StackF64
                     }
-                    (Intermediate::StackI32, Intermediate::StackI32)
-                    | (Intermediate::StackU32, Intermediate::StackU32) => {
+                    (Intermediate::StackI32, Intermediate::StackI32) => {
                         func.instructions().i32_mul();
-                        Intermediate::StackI32 // TODO: separate branch that produces StackU32
+                        Intermediate::StackI32
                     }
-                    (Intermediate::StackI64, Intermediate::StackI64)
-                    | (Intermediate::StackU64, Intermediate::StackU64) => {
+                    (Intermediate::StackU32, Intermediate::StackU32) => {
+                        func.instructions().i32_mul();
+                        Intermediate::StackU32
+                    }
+                    (Intermediate::StackI64, Intermediate::StackI64) => {
+                        func.instructions().i64_mul();
+                        Intermediate::StackI64
+                    }
+                    (Intermediate::StackU64, Intermediate::StackU64) => {
                         func.instructions().i64_mul();
-                        Intermediate::StackI64 // TODO: separate branch that produces StackU64
+                        Intermediate::StackU64
                     }
                     (lhs, rhs) => {
                         self.todo(format!("Expr::Mul({:?}, {:?})", lhs, rhs));
@@ -1336,7 +2111,64 @@ impl Compiler {
                     }
                 }
             }
-            // TODO: Div
+            Expr::Div(lhs, rhs) => {
+                let lhs = self.visit_expr(func, lhs, effect_handlers);
+                let rhs = self.visit_expr(func, rhs, effect_handlers);
+                match (lhs, rhs) {
+                    (Intermediate::Error, _) | (_, Intermediate::Error) => Intermediate::Error,
+                    (Intermediate::StackF64, Intermediate::StackF64) => {
+                        func.instructions().f64_div();
+                        Intermediate::StackF64
+                    }
+                    (Intermediate::StackI32, Intermediate::StackI32) => {
+                        func.instructions().i32_div_s();
+                        Intermediate::StackI32
+                    }
+                    (Intermediate::StackU32, Intermediate::StackU32) => {
+                        func.instructions().i32_div_u();
+                        Intermediate::StackU32
+                    }
+                    (Intermediate::StackI64, Intermediate::StackI64) => {
+                        func.instructions().i64_div_s();
+                        Intermediate::StackI64
+                    }
+                    (Intermediate::StackU64, Intermediate::StackU64) => {
+                        func.instructions().i64_div_u();
+                        Intermediate::StackU64
+                    }
+                    (lhs, rhs) => {
+                        self.todo(format!("Expr::Div({:?}, {:?})", lhs, rhs));
+                        Intermediate::Error
+                    }
+                }
+            }
+            Expr::Mod(lhs, rhs) => {
+                let lhs = self.visit_expr(func, lhs, effect_handlers);
+                let rhs = self.visit_expr(func, rhs, effect_handlers);
+                match (lhs, rhs) {
+                    (Intermediate::Error, _) | (_, Intermediate::Error) => Intermediate::Error,
+                    (Intermediate::StackI32, Intermediate::StackI32) => {
+                        func.instructions().i32_rem_s();
+                        Intermediate::StackI32
+                    }
+                    (Intermediate::StackU32, Intermediate::StackU32) => {
+                        func.instructions().i32_rem_u();
+                        Intermediate::StackU32
+                    }
+                    (Intermediate::StackI64, Intermediate::StackI64) => {
+                        func.instructions().i64_rem_s();
+                        Intermediate::StackI64
+                    }
+                    (Intermediate::StackU64, Intermediate::StackU64) => {
+                        func.instructions().i64_rem_u();
+                        Intermediate::StackU64
+                    }
+                    (lhs, rhs) => {
+                        self.todo(format!("Expr::Mod({:?}, {:?})", lhs, rhs));
+                        Intermediate::Error
+                    }
+                }
+            }
             Expr::BitNot(operand) => match self.visit_expr(func, operand, effect_handlers) {
                 Intermediate::Error => Intermediate::Error,
                 Intermediate::StackI32 => {
@@ -1364,57 +2196,138 @@ impl Compiler {
                     self.todo(format!("Expr::BitNot({:?})", other));
                     Intermediate::Error
                 }
-            },
-            Expr::BitAnd(lhs, rhs) => {
+            },
+            Expr::BitAnd(lhs, rhs) => {
+                let lhs = self.visit_expr(func, lhs, effect_handlers);
+                let rhs = self.visit_expr(func, rhs, effect_handlers);
+                match (lhs, rhs) {
+                    (Intermediate::Error, _) | (_, Intermediate::Error) => Intermediate::Error,
+                    (Intermediate::StackI32, Intermediate::StackI32) => {
+                        func.instructions().i32_and();
+                        Intermediate::StackI32
+                    }
+                    (Intermediate::StackU32, Intermediate::StackU32) => {
+                        func.instructions().i32_and();
+                        Intermediate::StackU32
+                    }
+                    (Intermediate::StackI64, Intermediate::StackI64) => {
+                        func.instructions().i64_and();
+                        Intermediate::StackI64
+                    }
+                    (Intermediate::StackU64, Intermediate::StackU64) => {
+                        func.instructions().i64_and();
+                        Intermediate::StackU64
+                    }
+                    other => {
+                        self.todo(format!("Expr::BitAnd({:?})", other));
+                        Intermediate::Error
+                    }
+                }
+            }
+            Expr::BitOr(lhs, rhs) => {
+                let lhs = self.visit_expr(func, lhs, effect_handlers);
+                let rhs = self.visit_expr(func, rhs, effect_handlers);
+                match (lhs, rhs) {
+                    (Intermediate::Error, _) | (_, Intermediate::Error) => Intermediate::Error,
+                    (Intermediate::StackI32, Intermediate::StackI32) => {
+                        func.instructions().i32_or();
+                        Intermediate::StackI32
+                    }
+                    (Intermediate::StackU32, Intermediate::StackU32) => {
+                        func.instructions().i32_or();
+                        Intermediate::StackU32
+                    }
+                    (Intermediate::StackI64, Intermediate::StackI64) => {
+                        func.instructions().i64_or();
+                        Intermediate::StackI64
+                    }
+                    (Intermediate::StackU64, Intermediate::StackU64) => {
+                        func.instructions().i64_or();
+                        Intermediate::StackU64
+                    }
+                    other => {
+                        self.todo(format!("Expr::BitOr({:?})", other));
+                        Intermediate::Error
+                    }
+                }
+            }
+            Expr::BitXor(lhs, rhs) => {
+                let lhs = self.visit_expr(func, lhs, effect_handlers);
+                let rhs = self.visit_expr(func, rhs, effect_handlers);
+                match (lhs, rhs) {
+                    (Intermediate::Error, _) | (_, Intermediate::Error) => Intermediate::Error,
+                    (Intermediate::StackI32, Intermediate::StackI32) => {
+                        func.instructions().i32_xor();
+                        Intermediate::StackI32
+                    }
+                    (Intermediate::StackU32, Intermediate::StackU32) => {
+                        func.instructions().i32_xor();
+                        Intermediate::StackU32
+                    }
+                    (Intermediate::StackI64, Intermediate::StackI64) => {
+                        func.instructions().i64_xor();
+                        Intermediate::StackI64
+                    }
+                    (Intermediate::StackU64, Intermediate::StackU64) => {
+                        func.instructions().i64_xor();
+                        Intermediate::StackU64
+                    }
+                    other => {
+                        self.todo(format!("Expr::BitXor({:?})", other));
+                        Intermediate::Error
+                    }
+                }
+            }
+            Expr::LShift(lhs, rhs) => {
                 let lhs = self.visit_expr(func, lhs, effect_handlers);
                 let rhs = self.visit_expr(func, rhs, effect_handlers);
                 match (lhs, rhs) {
                     (Intermediate::Error, _) | (_, Intermediate::Error) => Intermediate::Error,
                     (Intermediate::StackI32, Intermediate::StackI32) => {
-                        func.instructions().i32_and();
+                        func.instructions().i32_shl();
                         Intermediate::StackI32
                     }
                     (Intermediate::StackU32, Intermediate::StackU32) => {
-                        func.instructions().i32_add();
+                        func.instructions().i32_shl();
                         Intermediate::StackU32
                     }
                     (Intermediate::StackI64, Intermediate::StackI64) => {
-                        func.instructions().i64_and();
+                        func.instructions().i64_shl();
                         Intermediate::StackI64
                     }
                     (Intermediate::StackU64, Intermediate::StackU64) => {
-                        func.instructions().i64_and();
+                        func.instructions().i64_shl();
                         Intermediate::StackU64
                     }
-                    other => {
-                        self.todo(format!("Expr::BitAnd({:?})", other));
+                    (lhs, rhs) => {
+                        self.todo(format!("Expr::LShift({:?}, {:?})", lhs, rhs));
                         Intermediate::Error
                     }
                 }
             }
-            Expr::BitOr(lhs, rhs) => {
+            Expr::RShift(lhs, rhs) => {
                 let lhs = self.visit_expr(func, lhs, effect_handlers);
                 let rhs = self.visit_expr(func, rhs, effect_handlers);
                 match (lhs, rhs) {
                     (Intermediate::Error, _) | (_, Intermediate::Error) => Intermediate::Error,
                     (Intermediate::StackI32, Intermediate::StackI32) => {
-                        func.instructions().i32_or();
+                        func.instructions().i32_shr_s();
                         Intermediate::StackI32
                     }
                     (Intermediate::StackU32, Intermediate::StackU32) => {
-                        func.instructions().i32_or();
+                        func.instructions().i32_shr_u();
                         Intermediate::StackU32
                     }
                     (Intermediate::StackI64, Intermediate::StackI64) => {
-                        func.instructions().i64_or();
+                        func.instructions().i64_shr_s();
                         Intermediate::StackI64
                     }
                     (Intermediate::StackU64, Intermediate::StackU64) => {
-                        func.instructions().i64_or();
+                        func.instructions().i64_shr_u();
                         Intermediate::StackU64
                     }
-                    other => {
-                        self.todo(format!("Expr::BitOr({:?})", other));
+                    (lhs, rhs) => {
+                        self.todo(format!("Expr::RShift({:?}, {:?})", lhs, rhs));
                         Intermediate::Error
                     }
                 }
@@ -1426,108 +2339,46 @@ impl Compiler {
                 let lhs = self.visit_expr(func, lhs, effect_handlers);
                 let rhs = self.visit_expr(func, rhs, effect_handlers);
 
-                match (lhs, rhs) {
-                    (Intermediate::Error, _) | (_, Intermediate::Error) => {
-                        return Intermediate::Error;
-                    }
-                    (Intermediate::StackI32, Intermediate::StackI32) => match e {
-                        Expr::LessThan(_, _) => {
-                            func.instructions().i32_lt_s();
-                        }
-                        Expr::GreaterThan(_, _) => {
-                            func.instructions().i32_gt_s();
-                        }
-                        Expr::LessEq(_, _) => {
-                            func.instructions().i32_le_s();
-                        }
-                        Expr::GreaterEq(_, _) => {
-                            func.instructions().i32_ge_s();
-                        }
-                        _ => {
-                            return Intermediate::Error;
-                        }
-                    },
-                    (Intermediate::StackU32, Intermediate::StackU32) => match e {
-                        Expr::LessThan(_, _) => {
-                            func.instructions().i32_lt_u();
-                        }
-                        Expr::GreaterThan(_, _) => {
-                            func.instructions().i32_gt_u();
-                        }
-                        Expr::LessEq(_, _) => {
-                            func.instructions().i32_le_u();
-                        }
-                        Expr::GreaterEq(_, _) => {
-                            func.instructions().i32_ge_u();
-                        }
-                        _ => {
-                            return Intermediate::Error;
-                        }
-                    },
-                    (lhs, rhs) => {
-                        self.todo(format!("Expr::LessThan({:?}, {:?})", lhs, rhs));
-                        return Intermediate::Error;
-                    }
-                };
-
-                Intermediate::StackBool
+                self.visit_comparison(func, e, lhs, rhs)
             }
+            // Short-circuiting: the right side is only evaluated inside a
+            // branch, since `select` would evaluate both sides.
             Expr::And(lhs, rhs) => match self.visit_expr(func, lhs, effect_handlers) {
-                // Short-circuiting.
                 Intermediate::Error => Intermediate::Error,
                 Intermediate::StackBool => {
                     func.instructions().if_(BlockType::Result(ValType::I32));
-                    match self.visit_expr(func, rhs, effect_handlers) {
-                        Intermediate::Error => return Intermediate::Error,
-                        Intermediate::StackBool => {}
-                        rhs => {
-                            Report::build(ReportKind::Error, 0..0)
-                                .with_message(format_args!(
-                                    "type mismatch: `&&` requires bools, but right side was {rhs:?}"
-                                ))
-                                .push(self);
-                            return Intermediate::Error;
-                        }
+                    if !self.visit_short_circuit_rhs(func, rhs, "&&", effect_handlers) {
+                        return Intermediate::Error;
                     }
                     func.instructions().else_().i32_const(0).end();
                     Intermediate::StackBool
                 }
-                lhs => {
-                    Report::build(ReportKind::Error, 0..0)
+                lhs_ty => {
+                    Report::build(ReportKind::Error, lhs.span.into_range())
                         .with_message(format_args!(
-                            "type mismatch: `&&` requires bools, but left side was {lhs:?}"
+                            "type mismatch: `&&` requires bools, but left side was {lhs_ty:?}"
                         ))
                         .push(self);
                     Intermediate::Error
                 }
             },
             Expr::Or(lhs, rhs) => match self.visit_expr(func, lhs, effect_handlers) {
-                // Short-circuiting.
                 Intermediate::Error => Intermediate::Error,
                 Intermediate::StackBool => {
                     func.instructions()
                         .if_(BlockType::Result(ValType::I32))
                         .i32_const(1)
                         .else_();
-                    match self.visit_expr(func, rhs, effect_handlers) {
-                        Intermediate::Error => return Intermediate::Error,
-                        Intermediate::StackBool => {}
-                        rhs => {
-                            Report::build(ReportKind::Error, 0..0)
-                                .with_message(format_args!(
-                                    "type mismatch: `&&` requires bools, but right side was {rhs:?}"
-                                ))
-                                .push(self);
-                            return Intermediate::Error;
-                        }
+                    if !self.visit_short_circuit_rhs(func, rhs, "||", effect_handlers) {
+                        return Intermediate::Error;
                     }
                     func.instructions().end();
                     Intermediate::StackBool
                 }
-                lhs => {
-                    Report::build(ReportKind::Error, 0..0)
+                lhs_ty => {
+                    Report::build(ReportKind::Error, lhs.span.into_range())
                         .with_message(format_args!(
-                            "type mismatch: `&&` requires bools, but left side was {lhs:?}"
+                            "type mismatch: `||` requires bools, but left side was {lhs_ty:?}"
                         ))
                         .push(self);
                     Intermediate::Error
@@ -1536,12 +2387,22 @@ impl Compiler {
             Expr::BlockExpr(BlockExpr::Block(block)) => {
                 self.visit_block(func, block, effect_handlers)
             }
+            Expr::BlockExpr(BlockExpr::TryWith(block, handlers)) => {
+                self.visit_try_with(func, block, handlers, effect_handlers)
+            }
+            Expr::BlockExpr(BlockExpr::Loop(body)) => {
+                self.visit_loop(func, None, None, None, body, effect_handlers)
+            }
+            Expr::BlockExpr(BlockExpr::Match(scrutinee, arms)) => {
+                self.visit_match(func, scrutinee, arms, effect_handlers)
+            }
             Expr::BlockExpr(BlockExpr::IfThenElse(cond, if_, else_)) => {
                 match self.visit_expr(func, cond, effect_handlers) {
                     Intermediate::Error => Intermediate::Error,
                     Intermediate::StackBool => {
                         // TODO: handle non-Void if blocks.
                         func.instructions().if_(BlockType::Empty);
+                        func.block_depth += 1;
                         let im = self.visit_block(func, if_, effect_handlers);
                         self.drop_intermediate(func, im);
                         if let Some(else_) = else_ {
@@ -1550,6 +2411,7 @@ impl Compiler {
                             self.drop_intermediate(func, im);
                         }
                         func.instructions().end();
+                        func.block_depth -= 1;
                         Intermediate::Void
                     }
                     other => {
@@ -1563,6 +2425,25 @@ impl Compiler {
                 }
             }
 
+            Expr::Neg(operand) => match self.visit_expr(func, operand, effect_handlers) {
+                Intermediate::Error => Intermediate::Error,
+                Intermediate::StackI32 => {
+                    func.instructions().i32_const(-1).i32_mul();
+                    Intermediate::StackI32
+                }
+                Intermediate::StackI64 => {
+                    func.instructions().i64_const(-1).i64_mul();
+                    Intermediate::StackI64
+                }
+                Intermediate::StackI128 => {
+                    neg_128(func);
+                    Intermediate::StackI128
+                }
+                other => {
+                    self.todo(format!("Expr::Neg({:?})", other));
+                    Intermediate::Error
+                }
+            },
             Expr::Not(e) => match self.visit_expr(func, e, effect_handlers) {
                 // Short-circuiting.
                 Intermediate::Error => Intermediate::Error,
@@ -1662,6 +2543,63 @@ impl Compiler {
                     }
                 }
             }
+            FieldAccessExpression::Index { base, index } => {
+                let receiver = self.visit_field_access_expr(func, base, None, effect_handlers);
+
+                let Intermediate::StackPtr(StaticType::Array(elem, len)) = receiver else {
+                    if !matches!(receiver, Intermediate::Error) {
+                        self.todo(format!("Index {:?}[..]", receiver));
+                    }
+                    return Intermediate::Error;
+                };
+
+                match self.visit_expr(func, index, effect_handlers) {
+                    Intermediate::StackU32 | Intermediate::StackI32 => {}
+                    Intermediate::Error => return Intermediate::Error,
+                    other => {
+                        Report::build(ReportKind::Error, index.span.into_range())
+                            .with_message(format_args!(
+                                "type mismatch: array index must be u32, found {other:?}"
+                            ))
+                            .push(self);
+                        return Intermediate::Error;
+                    }
+                }
+
+                // the whole array has to be addressable, or the offsets below
+                // would wrap around
+                let size = elem.mem_size().saturating_mul(len);
+                let (Ok(len), Ok(elem_size), Ok(_)) = (
+                    u32::try_from(len),
+                    u32::try_from(elem.mem_size()),
+                    u32::try_from(size),
+                ) else {
+                    Report::build(ReportKind::Error, index.span.into_range())
+                        .with_message(format_args!(
+                            "array of {size} bytes is too large to fit in memory"
+                        ))
+                        .push(self);
+                    return Intermediate::Error;
+                };
+
+                // trap on out of range indices, then offset the array's
+                // address to the element's
+                let index = func.add_local(ValType::I32);
+                func.instructions()
+                    .local_tee(index)
+                    .i32_const(len.cast_signed())
+                    .i32_ge_u()
+                    .if_(BlockType::Empty)
+                    .unreachable()
+                    .end()
+                    .local_get(index)
+                    .i32_const(elem_size.cast_signed())
+                    .i32_mul()
+                    .i32_add();
+
+                let rhs = rhs.map(|expr| self.visit_expr(func, expr, effect_handlers));
+                self.visit_mem(func, rhs, 0, &elem)
+            }
         }
     }
 
@@ -1672,8 +2610,16 @@ impl Compiler {
         offset: usize,
         ty: &StaticType,
     ) -> Intermediate {
+        let Ok(offset) = u32::try_from(offset) else {
+            Report::build(ReportKind::Error, 0..0)
+                .with_message(format_args!(
+                    "field at offset {offset} is too far to fit in memory"
+                ))
+                .push(self);
+            return Intermediate::Error;
+        };
         let offset = MemArg {
-            offset: offset as u64,
+            offset: u64::from(offset),
             // TODO:
             align: 0,
             memory_index: 0,
@@ -1686,6 +2632,8 @@ impl Compiler {
                 (Intermediate::StackU32, StaticType::U32) => {}
                 (Intermediate::StackU64, StaticType::U64) => {}
                 (Intermediate::StackI64, StaticType::I64) => {}
+                (Intermediate::StackU128, StaticType::U128) => {}
+                (Intermediate::StackI128, StaticType::I128) => {}
                 (Intermediate::StackBool, StaticType::Bool) => {}
                 (expected, found) => {
                     Report::build(ReportKind::Error, 0..0)
@@ -1717,6 +2665,45 @@ impl Compiler {
                     ty.stack_intermediate()
                 }
             }
+            StaticType::I128 | StaticType::U128 => {
+                let high_offset = MemArg {
+                    offset: offset.offset + 8,
+                    ..offset
+                };
+                let address = func.add_local(ValType::I32);
+
+                if let Some(Intermediate::StackI128 | Intermediate::StackU128) = rhs {
+                    let low = func.add_local(ValType::I64);
+                    let high = func.add_local(ValType::I64);
+                    func.instructions()
+                        .local_set(high)
+                        .local_set(low)
+                        .local_set(address)
+                        .local_get(address)
+                        .local_get(low)
+                        .i64_store(offset)
+                        .local_get(address)
+                        .local_get(high)
+                        .i64_store(high_offset);
+                    Intermediate::Void
+                } else {
+                    func.instructions()
+                        .local_tee(address)
+                        .i64_load(offset)
+                        .local_get(address)
+                        .i64_load(high_offset);
+                    ty.stack_intermediate()
+                }
+            }
+            // arrays are accessed in place, so this is just their address
+            StaticType::Array(..) if rhs.is_none() => {
+                if offset.offset != 0 {
+                    func.instructions()
+                        .i32_const((offset.offset as u32).cast_signed())
+                        .i32_add();
+                }
+                ty.stack_intermediate()
+            }
             ty => {
                 self.todo(format!("record field access of ty {:?}", ty));
 
@@ -1753,12 +2740,42 @@ impl Compiler {
                         func.instructions().i64_const(*literal as i64);
                         Intermediate::StackU64
                     }
+                    StaticType::I128 => {
+                        func.instructions().i64_const(*literal as i64).i64_const(0);
+                        Intermediate::StackI128
+                    }
+                    StaticType::U128 => {
+                        func.instructions().i64_const(*literal as i64).i64_const(0);
+                        Intermediate::StackU128
+                    }
                     ty => {
                         self.todo(format!("numeric literal of ty {:?}", ty));
                         Intermediate::Error
                     }
                 }
             }
+            PrimaryExpr::TypedNumber(literal, ty) => match ty {
+                TypeArg::U32 => {
+                    func.instructions().i32_const(*literal as i32);
+                    Intermediate::StackU32
+                }
+                TypeArg::I32 => {
+                    func.instructions().i32_const(*literal as i32);
+                    Intermediate::StackI32
+                }
+                TypeArg::U64 => {
+                    func.instructions().i64_const(*literal as i64);
+                    Intermediate::StackU64
+                }
+                TypeArg::I64 => {
+                    func.instructions().i64_const(*literal as i64);
+                    Intermediate::StackI64
+                }
+                ty => {
+                    self.todo(format!("numeric literal of ty {:?}", ty));
+                    Intermediate::Error
+                }
+            },
             PrimaryExpr::Bool(true) => {
                 func.instructions().i32_const(1);
                 Intermediate::StackBool
@@ -1767,6 +2784,10 @@ impl Compiler {
                 func.instructions().i32_const(0);
                 Intermediate::StackBool
             }
+            PrimaryExpr::Char(codepoint) => {
+                func.instructions().i32_const(*codepoint as i32);
+                Intermediate::StackU32
+            }
             PrimaryExpr::Ident(ident)
             | PrimaryExpr::Namespace {
                 namespaces: _,
@@ -1787,10 +2808,23 @@ impl Compiler {
                             fn_info = self.symbols_table.functions.get(&proxy).unwrap();
                         };
 
+                        let cases = self.enum_cases(ident.name.uid.unwrap());
+
                         if let Some(index) = fn_info.info.index {
                             effect_handlers_required = fn_info.info.effect_handlers.clone();
 
                             Intermediate::ConstFunction(index)
+                        } else if let Some(cases) =
+                            cases.filter(|cases| cases.iter().any(|case| !case.fields.is_empty()))
+                        {
+                            return self.visit_enum_constructor(
+                                func,
+                                &ident.name.raw,
+                                ident.name.span.unwrap(),
+                                &cases,
+                                &args.xs,
+                                effect_handlers,
+                            );
                         } else if let Some(constant_value) = fn_info.info.is_constant {
                             // TODO: other types
                             func.instructions().i64_const(constant_value as i64);
@@ -1829,6 +2863,52 @@ impl Compiler {
                         None,
                     )
                 } else {
+                    if let Some(const_info) =
+                        self.symbols_table.constants.get(&ident.name.uid.unwrap())
+                    {
+                        let value = const_info.info.value.map_or(0, ConstValue::bits);
+                        let ty = const_info.info.ty.as_ref().map(|ty| {
+                            StaticType::from_canonical_type(ty, &self.symbols_table.type_vars)
+                        });
+
+                        return match ty {
+                            Some(StaticType::I64) => {
+                                func.instructions().i64_const(value);
+                                Intermediate::StackI64
+                            }
+                            Some(StaticType::U64) => {
+                                func.instructions().i64_const(value);
+                                Intermediate::StackU64
+                            }
+                            Some(StaticType::U32) => {
+                                func.instructions().i32_const(value as i32);
+                                Intermediate::StackU32
+                            }
+                            Some(StaticType::Bool) => {
+                                func.instructions().i32_const(value as i32);
+                                Intermediate::StackBool
+                            }
+                            Some(StaticType::I32) => {
+                                func.instructions().i32_const(value as i32);
+                                Intermediate::StackI32
+                            }
+                            _ => {
+                                // The typechecker only lets integer and bool
+                                // constants through.
+                                Report::build(
+                                    ReportKind::Error,
+                                    ident.name.span.unwrap().into_range(),
+                                )
+                                .with_message(format_args!(
+                                    "constant {} is not an integer or bool",
+                                    ident.name.raw
+                                ))
+                                .push(self);
+                                Intermediate::Error
+                            }
+                        };
+                    }
+
                     // Not a function call, so look in the variable table.
                     let Some(var_info) = self.symbols_table.vars.get(&ident.name.uid.unwrap())
                     else {
@@ -1861,11 +2941,18 @@ impl Compiler {
                             .functions
                             .get(&func.fn_id.unwrap())
                             .unwrap();
-                        func.instructions().local_get(
-                            // TODO: kind of duplicated code
-                            var_info.info.wasm_local_index.unwrap() as u32
-                                + (current_fn_info.info.effect_handlers.len() as u32) * 3,
-                        );
+                        // TODO: kind of duplicated code
+                        let first = func
+                            .local_slots
+                            .get(&ident.name.uid.unwrap())
+                            .copied()
+                            .unwrap_or(var_info.info.wasm_local_index.unwrap() as u32)
+                            + (current_fn_info.info.effect_handlers.len() as u32) * 3;
+                        let num_slots = static_type.lower().len() as u32;
+
+                        for slot in first..first + num_slots {
+                            func.instructions().local_get(slot);
+                        }
                     }
 
                     StaticType::from_canonical_type(&ty, &self.symbols_table.type_vars)
@@ -1922,6 +3009,12 @@ impl Compiler {
             }
             PrimaryExpr::Yield(expr) => self.visit_yield(func, expr, effect_handlers),
             PrimaryExpr::Tuple(elems) if elems.is_empty() => Intermediate::Void,
+            PrimaryExpr::Tuple(elems) => Intermediate::StackTuple(
+                elems
+                    .iter()
+                    .map(|elem| self.visit_expr(func, elem, effect_handlers))
+                    .collect(),
+            ),
             _ => {
                 self.todo(format!("PrimaryExpr::{:?}", primary));
                 Intermediate::Error
@@ -1950,7 +3043,29 @@ impl Compiler {
             .index
             .unwrap();
 
+        // the utxo yields its storage, which lives at address 0 (see below)
+        let storage_len = match utxo_info
+            .info
+            .storage_ty
+            .as_ref()
+            .map(|ty| StaticType::from_canonical_type(ty, &self.symbols_table.type_vars))
+        {
+            Some(StaticType::Record(record)) => record.size(),
+            Some(ty) => ty.mem_size(),
+            None => 0,
+        };
+
         let utxo_name = utxo_info.source.clone();
+        let Ok(storage_len) = u32::try_from(storage_len) else {
+            Report::build(
+                ReportKind::Error,
+                utxo_info.span.map_or(0..0, |span| span.into_range()),
+            )
+            .with_message(format_args!("storage of {utxo_name} is too large"))
+            .push(self);
+            return Intermediate::Error;
+        };
+
         let ptr = self.alloc_constant(utxo_name.as_bytes());
         let len = utxo_name.len();
 
@@ -1977,7 +3092,7 @@ impl Compiler {
         // data
         instructions.i32_const(0);
         // data_len
-        instructions.i32_const(0);
+        instructions.i32_const(storage_len.cast_signed());
         // resume_arg
         instructions.i32_const(0);
         // resume_arg_len
@@ -2057,6 +3172,8 @@ impl Compiler {
                         (StaticType::I64, Intermediate::StackI64) => {}
                         (StaticType::U64, Intermediate::StackI64) => {}
                         (StaticType::U64, Intermediate::StackU64) => {}
+                        (StaticType::I128, Intermediate::StackI128) => {}
+                        (StaticType::U128, Intermediate::StackU128) => {}
                         (StaticType::StrRef, Intermediate::StackStrRef) => {}
                         (StaticType::Bool, Intermediate::StackBool) => {}
                         (StaticType::Reference(_s), Intermediate::Void) => {
@@ -2103,7 +3220,7 @@ impl Compiler {
                 }
                 func.instructions().call(id);
                 match func_type.results.first() {
-                    // TODO: handle functions with multiple results
+                    // tuple results are already a single StaticType::Tuple
                     Some(r) => r.stack_intermediate(),
                     None => Intermediate::Void,
                 }
@@ -2243,6 +3360,139 @@ fn function_exit(frame_size: u32, saved_frame_local_index: u32, function: &mut F
         .global_set(GLOBAL_FRAME_PTR);
 }
 
+/// Move the two 128-bit operands on the stack into fresh locals, returning
+/// `(lhs_low, lhs_high, rhs_low, rhs_high)`.
+fn pop_128_operands(func: &mut Function) -> (u32, u32, u32, u32) {
+    let lhs_low = func.add_local(ValType::I64);
+    let lhs_high = func.add_local(ValType::I64);
+    let rhs_low = func.add_local(ValType::I64);
+    let rhs_high = func.add_local(ValType::I64);
+
+    func.instructions()
+        .local_set(rhs_high)
+        .local_set(rhs_low)
+        .local_set(lhs_high)
+        .local_set(lhs_low);
+
+    (lhs_low, lhs_high, rhs_low, rhs_high)
+}
+
+/// Wrapping 128-bit addition, carrying out of the low half.
+fn add_128(func: &mut Function) {
+    let (lhs_low, lhs_high, rhs_low, rhs_high) = pop_128_operands(func);
+
+    func.instructions()
+        // low = lhs_low + rhs_low
+        .local_get(lhs_low)
+        .local_get(rhs_low)
+        .i64_add()
+        .local_tee(rhs_low)
+        // high = lhs_high + rhs_high + (low < lhs_low)
+        .local_get(lhs_high)
+        .local_get(rhs_high)
+        .i64_add()
+        .local_get(rhs_low)
+        .local_get(lhs_low)
+        .i64_lt_u()
+        .i64_extend_i32_u()
+        .i64_add();
+}
+
+/// Wrapping 128-bit subtraction, borrowing from the high half.
+fn sub_128(func: &mut Function) {
+    let (lhs_low, lhs_high, rhs_low, rhs_high) = pop_128_operands(func);
+
+    func.instructions()
+        // low = lhs_low - rhs_low
+        .local_get(lhs_low)
+        .local_get(rhs_low)
+        .i64_sub()
+        // high = lhs_high - rhs_high - (lhs_low < rhs_low)
+        .local_get(lhs_high)
+        .local_get(rhs_high)
+        .i64_sub()
+        .local_get(lhs_low)
+        .local_get(rhs_low)
+        .i64_lt_u()
+        .i64_extend_i32_u()
+        .i64_sub();
+}
+
+/// Wrapping 128-bit negation, borrowing from the high half unless the low half is zero.
+fn neg_128(func: &mut Function) {
+    let low = func.add_local(ValType::I64);
+    let high = func.add_local(ValType::I64);
+
+    func.instructions()
+        .local_set(high)
+        .local_set(low)
+        // low = 0 - low
+        .i64_const(0)
+        .local_get(low)
+        .i64_sub()
+        // high = 0 - high - (low != 0)
+        .i64_const(0)
+        .local_get(high)
+        .i64_sub()
+        .local_get(low)
+        .i64_const(0)
+        .i64_ne()
+        .i64_extend_i32_u()
+        .i64_sub();
+}
+
+/// 128-bit ordering: the high halves decide unless they are equal, in which
+/// case the low halves are compared unsigned.
+fn cmp_128(func: &mut Function, op: &Expr, signed: bool) {
+    let (lhs_low, lhs_high, rhs_low, rhs_high) = pop_128_operands(func);
+
+    let mut instructions = func.instructions();
+    instructions.local_get(lhs_low).local_get(rhs_low);
+    match op {
+        Expr::LessThan(_, _) => instructions.i64_lt_u(),
+        Expr::GreaterThan(_, _) => instructions.i64_gt_u(),
+        Expr::LessEq(_, _) => instructions.i64_le_u(),
+        _ => instructions.i64_ge_u(),
+    };
+
+    // when the high halves differ, `<=` and `>=` are strict
+    instructions.local_get(lhs_high).local_get(rhs_high);
+    match (op, signed) {
+        (Expr::LessThan(_, _) | Expr::LessEq(_, _), true) => instructions.i64_lt_s(),
+        (Expr::LessThan(_, _) | Expr::LessEq(_, _), false) => instructions.i64_lt_u(),
+        (_, true) => instructions.i64_gt_s(),
+        (_, false) => instructions.i64_gt_u(),
+    };
+
+    instructions
+        .local_get(lhs_high)
+        .local_get(rhs_high)
+        .i64_eq()
+        .select();
+}
+
+fn eq_128(func: &mut Function, negate: bool) {
+    let (lhs_low, lhs_high, rhs_low, rhs_high) = pop_128_operands(func);
+
+    let mut instructions = func.instructions();
+    instructions.local_get(lhs_low).local_get(rhs_low);
+    if negate {
+        instructions
+            .i64_ne()
+            .local_get(lhs_high)
+            .local_get(rhs_high)
+            .i64_ne()
+            .i32_or();
+    } else {
+        instructions
+            .i64_eq()
+            .local_get(lhs_high)
+            .local_get(rhs_high)
+            .i64_eq()
+            .i32_and();
+    }
+}
+
 fn add_builtin_assert(this: &mut Compiler) {
     let mut function = Function::new(&[ValType::I32]);
 
@@ -2373,6 +3623,47 @@ pub struct Function {
     locals: Vec<(u32, ValType)>,
     bytes: Vec<u8>,
     pub fn_id: Option<SymbolId>,
+    /// First wasm local of each variable, for types spanning several locals.
+    local_slots: HashMap<SymbolId, u32>,
+    /// Number of wasm blocks the code being emitted is nested in.
+    block_depth: u32,
+    /// Enclosing loops, innermost last.
+    loops: Vec<EnclosingLoop>,
+}
+
+/// A case of an enum, as laid out by [`Compiler::enum_cases`].
+struct EnumCase {
+    name: String,
+    discriminant: u64,
+    fields: Vec<StaticType>,
+}
+
+/// What a loop checks before each iteration, ending once it's false.
+enum LoopCond<'a> {
+    /// `while (cond)`
+    Expr(&'a Spanned<Expr>),
+    /// A comparison whose right side was evaluated once before the loop, into
+    /// the locals starting at `rhs_local`, like the end of a `for`.
+    Hoisted {
+        cond: &'a Spanned<Expr>,
+        rhs_local: u32,
+        rhs: Intermediate,
+    },
+}
+
+/// A loop being emitted, for `break` and `continue` to find.
+struct EnclosingLoop {
+    label: Option<String>,
+    /// Block depth of the `loop` instruction.
+    depth: u32,
+    /// Block depth `continue` branches to, the `loop` itself unless there's a
+    /// step to run first.
+    continue_depth: u32,
+    /// First local holding the value of `break v;`, and its type, once a
+    /// `break` with a value has been compiled.
+    value: Option<(u32, Intermediate)>,
+    /// Whether anything jumps past the loop, otherwise it never ends.
+    broken: bool,
 }
 
 impl Function {
@@ -2384,6 +3675,37 @@ impl Function {
         this
     }
 
+    /// Move values of `stack_types` off the stack into the locals starting at
+    /// `first_local`, allocating them if there are none yet.
+    fn store_locals(&mut self, first_local: Option<u32>, stack_types: &[ValType]) -> u32 {
+        let first_local = first_local.unwrap_or_else(|| {
+            let first_local = self.num_locals;
+            for ty in stack_types {
+                self.add_local(*ty);
+            }
+            first_local
+        });
+
+        for i in (0..stack_types.len() as u32).rev() {
+            self.instructions().local_set(first_local + i);
+        }
+
+        first_local
+    }
+
+    /// Push the zero value of `ty`, to fill the fields of the cases of an
+    /// enum other than the one being built. False if `ty` has none.
+    fn push_zero(&mut self, ty: ValType) -> bool {
+        match ty {
+            ValType::I32 => self.instructions().i32_const(0),
+            ValType::I64 => self.instructions().i64_const(0),
+            ValType::F32 => self.instructions().f32_const(0f32.into()),
+            ValType::F64 => self.instructions().f64_const(0f64.into()),
+            _ => return false,
+        };
+        true
+    }
+
     fn add_local(&mut self, ty: ValType) -> u32 {
         let id = self.num_locals;
         self.num_locals += 1;
@@ -2413,6 +3735,31 @@ impl wasm_encoder::Encode for Function {
     }
 }
 
+/// Products whose fields are named by position, as inferred for tuple
+/// expressions and `(T, U)` types.
+fn is_tuple(pairs: &[(String, ComparableType)]) -> bool {
+    !pairs.is_empty()
+        && pairs
+            .iter()
+            .enumerate()
+            .all(|(i, (name, _))| *name == i.to_string())
+}
+
+/// Whether a value of this type holds a function, which codegen can't lower.
+fn has_fn_type(ty: &ComparableType, type_vars: &HashMap<TypeVar, ComparableType>) -> bool {
+    match ty {
+        ComparableType::FnType(..) => true,
+        ComparableType::Var(var) => type_vars
+            .get(var)
+            .is_some_and(|ty| has_fn_type(ty, type_vars)),
+        ComparableType::Ref(ty) | ComparableType::Array(ty, _) => has_fn_type(ty, type_vars),
+        ComparableType::Product(fields) | ComparableType::Sum(fields) => {
+            fields.iter().any(|(_, ty)| has_fn_type(ty, type_vars))
+        }
+        _ => false,
+    }
+}
+
 fn build_func(
     fn_id: SymbolId,
     f_info: &mut SymbolInformation<FuncInfo>,
@@ -2466,6 +3813,23 @@ fn build_func(
         .saved_frame_local_index
         .replace(function.add_local(ValType::I32));
 
+    // same numbering as `wasm_local_index`, but leaving room for values that
+    // take more than one local
+    let mut slot = 0;
+    for local in &f_info.info.locals {
+        let var_info = vars.get(local).unwrap();
+
+        if var_info.info.is_storage.is_some() || var_info.info.is_captured {
+            continue;
+        }
+
+        let offset = if var_info.info.is_argument { 0 } else { 1 };
+        function.local_slots.insert(*local, slot + offset);
+        slot += var_info.info.ty.as_ref().map_or(1, |ty| {
+            StaticType::from_canonical_type(ty, type_vars).lower().len() as u32
+        });
+    }
+
     for local in &f_info.info.locals {
         let var_info = vars.get(local).unwrap();
 
@@ -2477,11 +3841,12 @@ fn build_func(
             continue;
         }
 
-        let val_type =
-            StaticType::from_canonical_type(var_info.info.ty.as_ref().unwrap(), type_vars).lower()
-                [0];
+        let val_types =
+            StaticType::from_canonical_type(var_info.info.ty.as_ref().unwrap(), type_vars).lower();
 
-        function.add_local(val_type);
+        for val_type in val_types {
+            function.add_local(val_type);
+        }
     }
     (ty, function)
 }
@@ -2527,17 +3892,90 @@ mod tests {
             })
             .unwrap();
 
-        let (wasm, compile_errors) = compile(&program, symbols);
-        assert!(
-            compile_errors.is_empty(),
-            "compile errors: {compile_errors:?}"
-        );
-        let wasm = wasm.expect("compilation failed");
+        let wasm = compile(&program, symbols)
+            .unwrap_or_else(|errors| panic!("compile errors: {errors:?}"));
 
         let exports = export_names(&wasm);
         assert!(exports.iter().any(|e| e == "main"), "exports: {exports:?}");
     }
 
+    #[test]
+    fn compile_unsupported_reports_diagnostics() {
+        let errors = |src: &str| {
+            let errors = crate::compile_to_wasm(src, &[]).unwrap_err();
+            crate::format_reports(src, &errors)
+        };
+
+        // reported by codegen itself
+        let src = "
+            utxo U {
+                Resume u32
+
+                main {
+                    yield;
+                }
+            }
+        ";
+        assert!(errors(src).contains("resuming utxo with data"));
+
+        // function values typecheck, but can't be lowered yet
+        let src = "
+            script {
+                fn f(g: (x: u32) -> u32) {}
+            }
+        ";
+        assert!(errors(src).contains("values of function type are not supported yet"));
+
+        // arrays that don't fit in the 32-bit address space
+        let src = "
+            utxo U {
+                storage {
+                    values: [u32; 1000000 * 1000000];
+                }
+
+                main {
+                    storage.values[0] = 1;
+                    yield;
+                }
+            }
+        ";
+        assert!(errors(src).contains("too large to fit in memory"));
+    }
+
+    #[test]
+    fn compile_when_features() {
+        let src = "
+            #[when(debug)]
+            script {
+                fn debug_main() {}
+            }
+
+            #[when(release)]
+            script {
+                fn release_main() {}
+            }
+
+            script {
+                fn main() {}
+            }
+        ";
+
+        let exports = |features: &[&str]| {
+            let wasm = crate::compile_to_wasm(src, features).unwrap();
+            let mut exports = export_names(&wasm);
+            exports.retain(|e| e.ends_with("main"));
+            exports.sort();
+            exports
+        };
+
+        assert_eq!(exports(&[]), ["main"]);
+        assert_eq!(exports(&["debug"]), ["debug_main", "main"]);
+        assert_eq!(
+            exports(&["release", "debug"]),
+            ["debug_main", "main", "release_main"]
+        );
+    }
+
     #[test]
     fn compile_pay_to_public_key_hash() {
         let src = include_str!("../../grammar/examples/pay_to_public_key_hash.star");
@@ -2556,6 +3994,12 @@ mod tests {
         test_example(src);
     }
 
+    #[test]
+    fn compile_u128() {
+        let src = include_str!("../../grammar/examples/u128.star");
+        test_example(src);
+    }
+
     #[test]
     fn compile_token_binding() {
         let src = include_str!("../../grammar/examples/tokens.star");
@@ -2587,17 +4031,13 @@ mod tests {
             })
             .unwrap();
 
-        let (wasm, compile_errors) = compile(&program, symbols);
-        assert!(
-            compile_errors.is_empty(),
-            "compile errors: {compile_errors:?}"
-        );
-
-        for e in compile_errors {
-            e.eprint(ariadne::Source::from(src)).unwrap();
-        }
-
-        let wasm = wasm.expect("compilation failed");
+        let wasm = compile(&program, symbols)
+            .map_err(|errors| {
+                for e in &errors {
+                    e.eprint(ariadne::Source::from(src)).unwrap();
+                }
+            })
+            .unwrap();
 
         let exports = export_names(&wasm);
         assert!(exports.iter().any(|e| e == "main"), "exports: {exports:?}");
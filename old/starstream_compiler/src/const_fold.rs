@@ -0,0 +1,494 @@
+//! Constant folding of literal-only subexpressions.
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::{
+        AbiElem, Block, BlockExpr, Expr, ExprOrStatement, FieldAccessExpression, FnDef, LoopBody,
+        PrimaryExpr, ProgramItem, Spanned, StarstreamProgram, Statement, TokenItem, TypeArg,
+        UtxoItem,
+    },
+    symbols::Symbols,
+    typechecking::{
+        ComparableType, ConstEvaluator, Folded, PrimitiveType, TypeVar, to_const_value,
+    },
+};
+
+/// Rewrite arithmetic and boolean subexpressions whose operands are all
+/// literals into the literal they evaluate to, e.g. `2 + 3` into `5` and
+/// `true && x` into `x`.
+///
+/// Meant to run after typechecking, whose `symbols` give the type of each
+/// number literal. Operators are evaluated like `const` items are, and an
+/// integer result is only folded if it fits that type, so that anything that
+/// would overflow or trap is left to happen at runtime. Nothing that calls,
+/// raises or yields is ever dropped.
+pub fn fold_constants(program: &mut StarstreamProgram, symbols: &Symbols) {
+    let folder = Folder {
+        type_vars: &symbols.type_vars,
+    };
+
+    for item in &mut program.items {
+        match &mut item.node {
+            ProgramItem::Script(script) => folder.fold_fn_defs(&mut script.definitions),
+            ProgramItem::Utxo(utxo) => {
+                for item in &mut utxo.items {
+                    match item {
+                        UtxoItem::Main(main) => folder.fold_block(&mut main.block),
+                        UtxoItem::Impl(utxo_impl) => {
+                            folder.fold_fn_defs(&mut utxo_impl.definitions)
+                        }
+                        UtxoItem::Storage(_) | UtxoItem::Yield(_) | UtxoItem::Resume(_) => (),
+                    }
+                }
+            }
+            ProgramItem::Token(token) => {
+                for item in &mut token.items {
+                    match item {
+                        TokenItem::Bind(bind) => folder.fold_block(&mut bind.0),
+                        TokenItem::Unbind(unbind) => folder.fold_block(&mut unbind.0),
+                        TokenItem::Mint(mint) => folder.fold_block(&mut mint.0),
+                    }
+                }
+            }
+            ProgramItem::Abi(abi) => {
+                for elem in &mut abi.values {
+                    if let AbiElem::FnDef(definition) = elem {
+                        folder.fold_block(&mut definition.body);
+                    }
+                }
+            }
+            // `const` items are evaluated by the typechecker already
+            ProgramItem::TypeDef(_) | ProgramItem::Constant { .. } => (),
+        }
+    }
+}
+
+struct Folder<'a> {
+    type_vars: &'a HashMap<TypeVar, ComparableType>,
+}
+
+impl Folder<'_> {
+    fn fold_fn_defs(&self, definitions: &mut [FnDef]) {
+        for definition in definitions {
+            self.fold_block(&mut definition.body);
+        }
+    }
+
+    fn fold_block(&self, mut block: &mut Block) {
+        while let Block::Chain { head, tail } = block {
+            match &mut **head {
+                ExprOrStatement::Expr(expr) => self.fold_expr(expr),
+                ExprOrStatement::Statement(statement) => self.fold_statement(statement),
+            }
+
+            block = tail;
+        }
+    }
+
+    fn fold_statement(&self, statement: &mut Statement) {
+        match statement {
+            Statement::BindVar { value, .. } => self.fold_expr(value),
+            Statement::Return(expr) | Statement::Resume(expr) => {
+                if let Some(expr) = expr {
+                    self.fold_expr(expr);
+                }
+            }
+            Statement::Assign { var, expr } => {
+                self.fold_field_access(var);
+                self.fold_expr(expr);
+            }
+            Statement::While(cond, loop_body) => {
+                self.fold_expr(cond);
+                self.fold_loop_body(loop_body);
+            }
+            Statement::Loop(loop_body) => self.fold_loop_body(loop_body),
+            Statement::For {
+                start, end, body, ..
+            } => {
+                self.fold_expr(start);
+                self.fold_expr(end);
+                self.fold_loop_body(body);
+            }
+            Statement::Labeled(_, statement) => self.fold_statement(statement),
+            Statement::Break { value, .. } => {
+                if let Some(value) = value {
+                    self.fold_expr(value);
+                }
+            }
+            Statement::Continue { .. } => (),
+        }
+    }
+
+    fn fold_loop_body(&self, loop_body: &mut LoopBody) {
+        match loop_body {
+            LoopBody::Statement(statement) => self.fold_statement(statement),
+            LoopBody::Block(block) => self.fold_block(block),
+            LoopBody::Expr(expr) => self.fold_expr(expr),
+        }
+    }
+
+    fn fold_field_access(&self, expr: &mut FieldAccessExpression) {
+        match expr {
+            FieldAccessExpression::PrimaryExpr(primary) => self.fold_primary(primary),
+            FieldAccessExpression::FieldAccess { base, field } => {
+                self.fold_field_access(base);
+
+                for arg in field.args.iter_mut().flat_map(|args| args.xs.iter_mut()) {
+                    self.fold_expr(arg);
+                }
+            }
+            FieldAccessExpression::Index { base, index } => {
+                self.fold_field_access(base);
+                self.fold_expr(index);
+            }
+        }
+    }
+
+    fn fold_primary(&self, expr: &mut PrimaryExpr) {
+        match expr {
+            PrimaryExpr::Number { .. }
+            | PrimaryExpr::TypedNumber(..)
+            | PrimaryExpr::Bool(_)
+            | PrimaryExpr::Char(_)
+            | PrimaryExpr::StringLiteral(_) => (),
+            PrimaryExpr::Ident(ident)
+            | PrimaryExpr::Raise { ident }
+            | PrimaryExpr::Namespace { ident, .. }
+            | PrimaryExpr::RaiseNamespaced { ident, .. } => {
+                for arg in ident.args.iter_mut().flat_map(|args| args.xs.iter_mut()) {
+                    self.fold_expr(arg);
+                }
+            }
+            PrimaryExpr::ParExpr(inner) => self.fold_expr(inner),
+            PrimaryExpr::Yield(inner) => {
+                if let Some(inner) = inner {
+                    self.fold_expr(inner);
+                }
+            }
+            PrimaryExpr::Object(_, fields) => {
+                for (_, value) in fields {
+                    self.fold_expr(value);
+                }
+            }
+            PrimaryExpr::Tuple(values) => {
+                for value in values {
+                    self.fold_expr(value);
+                }
+            }
+        }
+    }
+
+    fn fold_expr(&self, expr: &mut Spanned<Expr>) {
+        match &mut expr.node {
+            Expr::PrimaryExpr(field_access) => self.fold_field_access(field_access),
+            Expr::BlockExpr(BlockExpr::IfThenElse(cond, if_true, if_false)) => {
+                self.fold_expr(cond);
+                self.fold_block(if_true);
+
+                if let Some(if_false) = if_false {
+                    self.fold_block(if_false);
+                }
+            }
+            Expr::BlockExpr(BlockExpr::Block(block)) => self.fold_block(block),
+            Expr::BlockExpr(BlockExpr::TryWith(block, handlers)) => {
+                self.fold_block(block);
+
+                for (_, body) in handlers {
+                    self.fold_block(body);
+                }
+            }
+            Expr::BlockExpr(BlockExpr::Loop(loop_body)) => self.fold_loop_body(loop_body),
+            Expr::BlockExpr(BlockExpr::Match(scrutinee, arms)) => {
+                self.fold_expr(scrutinee);
+
+                for arm in arms {
+                    self.fold_block(&mut arm.body);
+                }
+            }
+            Expr::Equals(lhs, rhs)
+            | Expr::NotEquals(lhs, rhs)
+            | Expr::LessThan(lhs, rhs)
+            | Expr::GreaterThan(lhs, rhs)
+            | Expr::LessEq(lhs, rhs)
+            | Expr::GreaterEq(lhs, rhs)
+            | Expr::Add(lhs, rhs)
+            | Expr::Sub(lhs, rhs)
+            | Expr::Mul(lhs, rhs)
+            | Expr::Div(lhs, rhs)
+            | Expr::Mod(lhs, rhs)
+            | Expr::BitAnd(lhs, rhs)
+            | Expr::BitOr(lhs, rhs)
+            | Expr::BitXor(lhs, rhs)
+            | Expr::LShift(lhs, rhs)
+            | Expr::RShift(lhs, rhs)
+            | Expr::And(lhs, rhs)
+            | Expr::Or(lhs, rhs) => {
+                self.fold_expr(lhs);
+                self.fold_expr(rhs);
+            }
+            Expr::Neg(inner) | Expr::BitNot(inner) | Expr::Not(inner) => self.fold_expr(inner),
+        }
+
+        if let Some(folded) = self.fold_node(expr) {
+            expr.node = folded;
+        }
+    }
+
+    /// The literal or operand an expression with already folded operands
+    /// reduces to, if any.
+    fn fold_node(&self, expr: &Spanned<Expr>) -> Option<Expr> {
+        let bool_literal = |value: bool| Some(primary(PrimaryExpr::Bool(value)));
+
+        let (lhs, rhs) = match &expr.node {
+            // `(5)` is just `5`, which lets the enclosing expression fold too
+            Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(PrimaryExpr::ParExpr(inner)))
+                if is_literal(&inner.node) =>
+            {
+                return Some(inner.node.clone());
+            }
+            // A literal on either side either decides the result or leaves
+            // it to the other side. Deciding it drops the other side, which
+            // is only done if that has no effects, even where it would never
+            // run, so that no call, raise or yield disappears.
+            Expr::And(lhs, rhs) => {
+                return match (as_bool(&lhs.node), as_bool(&rhs.node)) {
+                    (Some(true), _) => Some(rhs.node.clone()),
+                    (Some(false), _) if is_pure(&rhs.node) => bool_literal(false),
+                    (_, Some(true)) => Some(lhs.node.clone()),
+                    (_, Some(false)) if is_pure(&lhs.node) => bool_literal(false),
+                    _ => None,
+                };
+            }
+            Expr::Or(lhs, rhs) => {
+                return match (as_bool(&lhs.node), as_bool(&rhs.node)) {
+                    (Some(false), _) => Some(rhs.node.clone()),
+                    (Some(true), _) if is_pure(&rhs.node) => bool_literal(true),
+                    (_, Some(false)) => Some(lhs.node.clone()),
+                    (_, Some(true)) if is_pure(&lhs.node) => bool_literal(true),
+                    _ => None,
+                };
+            }
+            Expr::Equals(lhs, rhs)
+            | Expr::NotEquals(lhs, rhs)
+            | Expr::LessThan(lhs, rhs)
+            | Expr::GreaterThan(lhs, rhs)
+            | Expr::LessEq(lhs, rhs)
+            | Expr::GreaterEq(lhs, rhs)
+            | Expr::Add(lhs, rhs)
+            | Expr::Sub(lhs, rhs)
+            | Expr::Mul(lhs, rhs)
+            | Expr::Div(lhs, rhs)
+            | Expr::Mod(lhs, rhs)
+            | Expr::BitAnd(lhs, rhs)
+            | Expr::BitOr(lhs, rhs)
+            | Expr::BitXor(lhs, rhs)
+            | Expr::LShift(lhs, rhs)
+            | Expr::RShift(lhs, rhs) => (&**lhs, Some(&**rhs)),
+            Expr::Neg(inner) | Expr::BitNot(inner) | Expr::Not(inner) => (&**inner, None),
+            _ => return None,
+        };
+
+        if !is_literal(&lhs.node) || !rhs.is_none_or(|rhs| is_literal(&rhs.node)) {
+            return None;
+        }
+
+        // Operands share a type, and so does an integer result. Wasm only
+        // looks at the low bits of a shift amount, unlike the evaluator.
+        let ty = number_type(&lhs.node);
+        if let (Expr::LShift(_, rhs) | Expr::RShift(_, rhs), Some(ty)) = (&expr.node, ty) {
+            let width = match self.resolve(ty)? {
+                TypeArg::U32 | TypeArg::I32 => 32,
+                _ => 64,
+            };
+            if as_number(&rhs.node)? >= width {
+                return None;
+            }
+        }
+
+        match ConstEvaluator::default().eval(expr).ok()? {
+            Folded::Bool(value) => bool_literal(value),
+            Folded::Int(value) => {
+                let ty = ty?;
+                to_const_value(Folded::Int(value), Some(&self.resolve(ty)?), expr.span).ok()?;
+
+                Some(primary(PrimaryExpr::Number {
+                    literal: u32::try_from(value).ok()?,
+                    ty: Some(ty.clone()),
+                }))
+            }
+        }
+    }
+
+    /// The integer type a number literal was inferred to have, as far as
+    /// `const` items can have it.
+    fn resolve(&self, ty: &ComparableType) -> Option<TypeArg> {
+        match ty {
+            ComparableType::Var(var) => self.resolve(self.type_vars.get(var)?),
+            ComparableType::Primitive(PrimitiveType::U32) => Some(TypeArg::U32),
+            ComparableType::Primitive(PrimitiveType::I32) => Some(TypeArg::I32),
+            ComparableType::Primitive(PrimitiveType::U64) => Some(TypeArg::U64),
+            ComparableType::Primitive(PrimitiveType::I64) => Some(TypeArg::I64),
+            _ => None,
+        }
+    }
+}
+
+fn is_literal(node: &Expr) -> bool {
+    as_number(node).is_some() || as_bool(node).is_some()
+}
+
+fn as_number(node: &Expr) -> Option<u32> {
+    match node {
+        Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(PrimaryExpr::Number {
+            literal,
+            ..
+        })) => Some(*literal),
+        _ => None,
+    }
+}
+
+fn number_type(node: &Expr) -> Option<&ComparableType> {
+    match node {
+        Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(PrimaryExpr::Number {
+            ty, ..
+        })) => ty.as_ref(),
+        _ => None,
+    }
+}
+
+fn as_bool(node: &Expr) -> Option<bool> {
+    match node {
+        Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(PrimaryExpr::Bool(value))) => {
+            Some(*value)
+        }
+        _ => None,
+    }
+}
+
+/// Whether evaluating the expression can be skipped without changing what
+/// the program does: it doesn't call, raise or yield.
+fn is_pure(node: &Expr) -> bool {
+    match node {
+        Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(primary)) => match primary {
+            PrimaryExpr::Number { .. }
+            | PrimaryExpr::TypedNumber(..)
+            | PrimaryExpr::Bool(_)
+            | PrimaryExpr::Char(_)
+            | PrimaryExpr::StringLiteral(_) => true,
+            PrimaryExpr::Ident(ident) | PrimaryExpr::Namespace { ident, .. } => {
+                ident.args.is_none()
+            }
+            PrimaryExpr::ParExpr(inner) => is_pure(&inner.node),
+            _ => false,
+        },
+        Expr::Equals(lhs, rhs)
+        | Expr::NotEquals(lhs, rhs)
+        | Expr::LessThan(lhs, rhs)
+        | Expr::GreaterThan(lhs, rhs)
+        | Expr::LessEq(lhs, rhs)
+        | Expr::GreaterEq(lhs, rhs)
+        | Expr::And(lhs, rhs)
+        | Expr::Or(lhs, rhs) => is_pure(&lhs.node) && is_pure(&rhs.node),
+        Expr::Not(inner) => is_pure(&inner.node),
+        // arithmetic can trap
+        _ => false,
+    }
+}
+
+fn primary(primary: PrimaryExpr) -> Expr {
+    Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(primary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fold_constants;
+    use crate::{do_scope_analysis, do_type_inference, pretty_print};
+    use chumsky::Parser as _;
+
+    fn fold(input: &str) -> String {
+        let program = crate::starstream_program().parse(input).unwrap();
+        let (program, mut symbols) = do_scope_analysis(program).ok().unwrap();
+        let (mut program, _) = do_type_inference(program, &mut symbols).ok().unwrap();
+
+        fold_constants(&mut program, &symbols);
+
+        pretty_print(&program)
+    }
+
+    #[test]
+    fn fold_arithmetic() {
+        let output = fold(
+            "script {
+                fn f(): u32 { 2 + 3 * 4 }
+                fn g(): u32 { (1 + 2) * (10 - 7) }
+                fn h(): bool { !(1 + 1 == 2) || 3 < 2 }
+            }",
+        );
+
+        assert!(output.contains("14"), "{output}");
+        assert!(output.contains("9"), "{output}");
+        assert!(output.contains("false"), "{output}");
+        assert!(!output.contains('+'), "{output}");
+    }
+
+    #[test]
+    fn fold_to_literal_type() {
+        let output = fold(
+            "script {
+                fn f(): u32 { 2147483647 + 1 }
+                fn g(): u64 { 65536 * 65535 }
+            }",
+        );
+
+        assert!(output.contains("2147483648"), "{output}");
+        assert!(output.contains("4294901760"), "{output}");
+        assert!(!output.contains('+'), "{output}");
+        assert!(!output.contains('*'), "{output}");
+    }
+
+    #[test]
+    fn fold_keeps_effects() {
+        let output = fold(
+            "abi Flag {
+                effect Check(u32): bool;
+            }
+
+            script {
+                fn f(): bool / { Flag } {
+                    false && raise Flag::Check(1 + 2)
+                }
+
+                fn g(): bool / { Flag } {
+                    true && raise Flag::Check(4)
+                }
+            }",
+        );
+
+        // the operands of `raise` are still folded
+        assert!(output.contains("false && raise Flag::Check(3)"), "{output}");
+        assert!(!output.contains("true &&"), "{output}");
+        assert!(output.contains("raise Flag::Check(4)"), "{output}");
+    }
+
+    #[test]
+    fn fold_leaves_runtime_expressions() {
+        let output = fold(
+            "script {
+                fn f(x: u32): u32 { x + 1 }
+                fn g(): u32 { 1 / 0 }
+                fn h(): u32 { 4294967295 + 1 }
+                fn i(x: bool): bool { x || false }
+                fn j(): i32 { 2147483647 + 1 }
+                fn k(): u32 { 256 >> 40 }
+            }",
+        );
+
+        assert!(output.contains("x + 1"), "{output}");
+        assert!(output.contains("1 / 0"), "{output}");
+        assert!(output.contains("4294967295 + 1"), "{output}");
+        assert!(output.contains("2147483647 + 1"), "{output}");
+        assert!(output.contains("256 >> 40"), "{output}");
+        assert!(!output.contains("||"), "{output}");
+    }
+}
@@ -42,6 +42,51 @@ pub enum NameResolutionError {
     InvalidCapture {
         span: SimpleSpan,
     },
+    DuplicateImpl {
+        ident: Identifier,
+        previous: SimpleSpan,
+    },
+    ConflictingAbiMethod {
+        ident: Identifier,
+        previous: SimpleSpan,
+    },
+    UndefinedLabel {
+        span: SimpleSpan,
+    },
+    BreakOutsideLoop {
+        span: SimpleSpan,
+    },
+    UninitializedStorageField {
+        span: SimpleSpan,
+    },
+    DuplicateDiscriminant {
+        ident: Identifier,
+        discriminant: u64,
+        previous: SimpleSpan,
+    },
+    UnknownArgument {
+        ident: Identifier,
+    },
+    DuplicateArgument {
+        ident: Identifier,
+        previous: SimpleSpan,
+    },
+    MissingArgument {
+        span: SimpleSpan,
+        name: String,
+    },
+    NamedArgumentsNotAllowed {
+        span: SimpleSpan,
+    },
+    NotAVariant {
+        ident: Identifier,
+    },
+    StorageInitializerWithoutMain {
+        span: SimpleSpan,
+    },
+    AssignToLoopVariable {
+        ident: Identifier,
+    },
 }
 
 #[derive(Debug)]
@@ -85,6 +130,41 @@ pub enum TypeError {
         effect_name: String,
         interface_name: String,
     },
+    NonConstantExpression {
+        span: SimpleSpan,
+    },
+    ConstDivisionByZero {
+        span: SimpleSpan,
+    },
+    ConstOverflow {
+        span: SimpleSpan,
+    },
+    UnreachableCode {
+        span: SimpleSpan,
+        return_span: Option<SimpleSpan>,
+    },
+    NonExhaustiveMatch {
+        span: SimpleSpan,
+        missing: Vec<String>,
+    },
+    WrongNumberOfBindings {
+        span: SimpleSpan,
+        expected: usize,
+        found: usize,
+    },
+    NotIndexable {
+        span: SimpleSpan,
+        found: ComparableType,
+    },
+    Unsupported128BitOperator {
+        span: SimpleSpan,
+        op: &'static str,
+        found: ComparableType,
+    },
+    NegativeArrayLength {
+        span: SimpleSpan,
+        found: i128,
+    },
 }
 
 pub struct DiagnosticAnnotation {
@@ -106,6 +186,32 @@ impl DiagnosticError for NameResolutionError {
                 abi_span: _,
             } => 2,
             NameResolutionError::InvalidCapture { span: _ } => 3,
+            NameResolutionError::DuplicateImpl {
+                ident: _,
+                previous: _,
+            } => 4,
+            NameResolutionError::ConflictingAbiMethod {
+                ident: _,
+                previous: _,
+            } => 5,
+            NameResolutionError::UndefinedLabel { span: _ } => 6,
+            NameResolutionError::BreakOutsideLoop { span: _ } => 7,
+            NameResolutionError::UninitializedStorageField { span: _ } => 8,
+            NameResolutionError::DuplicateDiscriminant {
+                ident: _,
+                discriminant: _,
+                previous: _,
+            } => 9,
+            NameResolutionError::UnknownArgument { ident: _ } => 10,
+            NameResolutionError::DuplicateArgument {
+                ident: _,
+                previous: _,
+            } => 11,
+            NameResolutionError::MissingArgument { span: _, name: _ } => 12,
+            NameResolutionError::NamedArgumentsNotAllowed { span: _ } => 13,
+            NameResolutionError::NotAVariant { ident: _ } => 14,
+            NameResolutionError::StorageInitializerWithoutMain { span: _ } => 15,
+            NameResolutionError::AssignToLoopVariable { ident: _ } => 16,
         };
 
         Code::NameResolution as u32 + offset
@@ -123,6 +229,25 @@ impl DiagnosticError for NameResolutionError {
                 abi_span: _,
             } => *def_span,
             NameResolutionError::InvalidCapture { span: ident } => *ident,
+            NameResolutionError::DuplicateImpl { ident, previous: _ }
+            | NameResolutionError::ConflictingAbiMethod { ident, previous: _ }
+            | NameResolutionError::DuplicateDiscriminant {
+                ident,
+                discriminant: _,
+                previous: _,
+            }
+            | NameResolutionError::UnknownArgument { ident }
+            | NameResolutionError::NotAVariant { ident }
+            | NameResolutionError::AssignToLoopVariable { ident }
+            | NameResolutionError::DuplicateArgument { ident, previous: _ } => {
+                ident.span.unwrap_or(SimpleSpan::from(0..0))
+            }
+            NameResolutionError::UndefinedLabel { span }
+            | NameResolutionError::BreakOutsideLoop { span }
+            | NameResolutionError::UninitializedStorageField { span }
+            | NameResolutionError::StorageInitializerWithoutMain { span }
+            | NameResolutionError::MissingArgument { span, name: _ }
+            | NameResolutionError::NamedArgumentsNotAllowed { span } => *span,
         }
     }
 
@@ -139,6 +264,52 @@ impl DiagnosticError for NameResolutionError {
             NameResolutionError::InvalidCapture { span: _ } => {
                 "function arguments can't be used in handlers yet".to_string()
             }
+            NameResolutionError::DuplicateImpl { ident, previous: _ } => {
+                format!("abi {} is already implemented", &ident.raw)
+            }
+            NameResolutionError::ConflictingAbiMethod { ident, previous: _ } => {
+                format!("method {} is provided by more than one abi", &ident.raw)
+            }
+            NameResolutionError::UndefinedLabel { span: _ } => {
+                "no enclosing loop with this label".to_string()
+            }
+            NameResolutionError::BreakOutsideLoop { span: _ } => {
+                "`break` or `continue` outside of a loop".to_string()
+            }
+            NameResolutionError::UninitializedStorageField { span: _ } => {
+                "storage field read before it is initialized".to_string()
+            }
+            NameResolutionError::DuplicateDiscriminant {
+                ident,
+                discriminant,
+                previous: _,
+            } => {
+                format!(
+                    "variant {} reuses discriminant {}",
+                    &ident.raw, discriminant
+                )
+            }
+            NameResolutionError::UnknownArgument { ident } => {
+                format!("no parameter named {}", &ident.raw)
+            }
+            NameResolutionError::DuplicateArgument { ident, previous: _ } => {
+                format!("parameter {} is passed more than once", &ident.raw)
+            }
+            NameResolutionError::MissingArgument { span: _, name } => {
+                format!("missing argument for parameter {name}")
+            }
+            NameResolutionError::NamedArgumentsNotAllowed { span: _ } => {
+                "named arguments can only be passed to functions and constructors".to_string()
+            }
+            NameResolutionError::NotAVariant { ident } => {
+                format!("{} is not a case of an enum", &ident.raw)
+            }
+            NameResolutionError::StorageInitializerWithoutMain { span: _ } => {
+                "storage initializers run at the start of `main`, but there is none".to_string()
+            }
+            NameResolutionError::AssignToLoopVariable { ident } => {
+                format!("loop variable {} can't be assigned to", &ident.raw)
+            }
         }
     }
 
@@ -165,6 +336,47 @@ impl DiagnosticError for NameResolutionError {
                 message: "defined here".to_string(),
                 color: Color::BrightRed,
             }],
+            NameResolutionError::DuplicateImpl { ident: _, previous } => {
+                vec![DiagnosticAnnotation {
+                    location: *previous,
+                    message: "first implemented here".to_string(),
+                    color: Color::BrightRed,
+                }]
+            }
+            NameResolutionError::ConflictingAbiMethod { ident: _, previous } => {
+                vec![DiagnosticAnnotation {
+                    location: *previous,
+                    message: "also provided here".to_string(),
+                    color: Color::BrightRed,
+                }]
+            }
+            NameResolutionError::DuplicateDiscriminant {
+                ident: _,
+                discriminant: _,
+                previous,
+            } => {
+                vec![DiagnosticAnnotation {
+                    location: *previous,
+                    message: "first used here".to_string(),
+                    color: Color::BrightRed,
+                }]
+            }
+            NameResolutionError::DuplicateArgument { ident: _, previous } => {
+                vec![DiagnosticAnnotation {
+                    location: *previous,
+                    message: "first passed here".to_string(),
+                    color: Color::BrightRed,
+                }]
+            }
+            NameResolutionError::UndefinedLabel { span: _ }
+            | NameResolutionError::BreakOutsideLoop { span: _ }
+            | NameResolutionError::UninitializedStorageField { span: _ }
+            | NameResolutionError::StorageInitializerWithoutMain { span: _ }
+            | NameResolutionError::UnknownArgument { ident: _ }
+            | NameResolutionError::NotAVariant { ident: _ }
+            | NameResolutionError::AssignToLoopVariable { ident: _ }
+            | NameResolutionError::MissingArgument { span: _, name: _ }
+            | NameResolutionError::NamedArgumentsNotAllowed { span: _ } => vec![],
         }
     }
 }
@@ -206,6 +418,29 @@ impl DiagnosticError for TypeError {
                 effect_name: _,
                 interface_name: _,
             } => 8,
+            TypeError::NonConstantExpression { span: _ } => 9,
+            TypeError::ConstDivisionByZero { span: _ } => 10,
+            TypeError::ConstOverflow { span: _ } => 11,
+            TypeError::UnreachableCode {
+                span: _,
+                return_span: _,
+            } => 12,
+            TypeError::NonExhaustiveMatch {
+                span: _,
+                missing: _,
+            } => 13,
+            TypeError::WrongNumberOfBindings {
+                span: _,
+                expected: _,
+                found: _,
+            } => 14,
+            TypeError::NotIndexable { span: _, found: _ } => 15,
+            TypeError::Unsupported128BitOperator {
+                span: _,
+                op: _,
+                found: _,
+            } => 16,
+            TypeError::NegativeArrayLength { span: _, found: _ } => 17,
         };
         Code::TypeError as u32 + offset
     }
@@ -240,6 +475,26 @@ impl DiagnosticError for TypeError {
                 effect_name: _,
                 interface_name: _,
             } => *span,
+            TypeError::NonConstantExpression { span } => *span,
+            TypeError::ConstDivisionByZero { span } => *span,
+            TypeError::ConstOverflow { span } => *span,
+            TypeError::UnreachableCode {
+                span,
+                return_span: _,
+            } => *span,
+            TypeError::NonExhaustiveMatch { span, missing: _ } => *span,
+            TypeError::WrongNumberOfBindings {
+                span,
+                expected: _,
+                found: _,
+            } => *span,
+            TypeError::NotIndexable { span, found: _ } => *span,
+            TypeError::Unsupported128BitOperator {
+                span,
+                op: _,
+                found: _,
+            } => *span,
+            TypeError::NegativeArrayLength { span, found: _ } => *span,
         }
     }
 
@@ -291,6 +546,38 @@ impl DiagnosticError for TypeError {
                     effect_name, interface_name
                 )
             }
+            TypeError::NonConstantExpression { span: _ } => {
+                "expression can't be evaluated at compile time".to_string()
+            }
+            TypeError::ConstDivisionByZero { span: _ } => {
+                "division by zero in constant expression".to_string()
+            }
+            TypeError::ConstOverflow { span: _ } => {
+                "overflow in constant expression".to_string()
+            }
+            TypeError::UnreachableCode {
+                span: _,
+                return_span: _,
+            } => "unreachable code".to_string(),
+            TypeError::NonExhaustiveMatch { span: _, missing } => {
+                format!("match doesn't cover {}", missing.join(", "))
+            }
+            TypeError::WrongNumberOfBindings {
+                span: _,
+                expected,
+                found,
+            } => {
+                format!("expected {} field bindings, found {}", expected, found)
+            }
+            TypeError::NotIndexable { span: _, found } => {
+                format!("expected an array, found {}", found)
+            }
+            TypeError::Unsupported128BitOperator { span: _, op, found } => {
+                format!("`{}` is not supported for {} yet", op, found)
+            }
+            TypeError::NegativeArrayLength { span: _, found } => {
+                format!("array length can't be negative, found {}", found)
+            }
         }
     }
 
@@ -346,6 +633,36 @@ impl DiagnosticError for TypeError {
                 effect_name: _,
                 interface_name: _,
             } => vec![],
+            TypeError::NonConstantExpression { span: _ } => vec![],
+            TypeError::ConstDivisionByZero { span: _ } => vec![],
+            TypeError::ConstOverflow { span: _ } => vec![],
+            TypeError::UnreachableCode {
+                span: _,
+                return_span,
+            } => return_span
+                .iter()
+                .map(|location| DiagnosticAnnotation {
+                    location: *location,
+                    message: "any code following this return is unreachable".to_string(),
+                    color: Color::Yellow,
+                })
+                .collect(),
+            TypeError::NonExhaustiveMatch {
+                span: _,
+                missing: _,
+            } => vec![],
+            TypeError::WrongNumberOfBindings {
+                span: _,
+                expected: _,
+                found: _,
+            } => vec![],
+            TypeError::NotIndexable { span: _, found: _ } => vec![],
+            TypeError::Unsupported128BitOperator {
+                span: _,
+                op: _,
+                found: _,
+            } => vec![],
+            TypeError::NegativeArrayLength { span: _, found: _ } => vec![],
         }
     }
 
@@ -361,6 +678,10 @@ impl DiagnosticError for TypeError {
                     ReportKind::Warning
                 }
             }
+            TypeError::UnreachableCode {
+                span: _,
+                return_span: _,
+            } => ReportKind::Warning,
             _ => ReportKind::Error,
         }
     }
@@ -2,17 +2,23 @@
 
 pub mod ast;
 mod codegen;
+mod const_fold;
 pub mod error;
+mod manifest;
 mod parser;
+mod pretty;
 mod scope_resolution;
 mod symbols;
 mod typechecking;
 
-use self::ast::StarstreamProgram;
+use self::ast::{Item, StarstreamProgram};
 pub use self::codegen::compile;
-pub use self::parser::starstream_program;
+pub use self::const_fold::fold_constants;
+pub use self::manifest::emit_abi_manifest;
+pub use self::parser::{starstream_program, starstream_program_item};
+pub use self::pretty::pretty_print;
 use ariadne::{Report, Source};
-use chumsky::Parser as _;
+use chumsky::{Parser as _, error::Rich, span::SimpleSpan};
 pub use scope_resolution::do_scope_analysis;
 pub use symbols::Symbols;
 pub use typechecking::do_type_inference;
@@ -51,40 +57,103 @@ pub fn format_reports(source_code: &str, errors: &[Report]) -> String {
     String::from_utf8_lossy(&output).into_owned()
 }
 
+/// Parse a source file, or report its syntax errors.
+///
+/// The program is only returned if it parsed without errors.
 pub fn parse(source_code: &str) -> (Option<StarstreamProgram>, Vec<Report>) {
+    let (ast, errors) = parse_program_collecting_errors(source_code);
+    (ast.filter(|_| errors.is_empty()), errors)
+}
+
+/// Parse a source file, reporting the syntax errors of every item rather than
+/// stopping at the first one.
+///
+/// Broken items are left out of the returned program, which is only `None`
+/// if there was nothing to recover.
+pub fn parse_program_collecting_errors(
+    source_code: &str,
+) -> (Option<StarstreamProgram>, Vec<Report<'_>>) {
     let (ast, errors) = starstream_program().parse(source_code).into_output_errors();
     let errors = errors.into_iter().map(parser::error_to_report).collect();
     (ast, errors)
 }
 
-/// Highest-level one-shot compiler from Starstream source code to WASM binary.
-pub fn starstream_to_wasm(source_code: &str) -> Result<Vec<u8>, String> {
-    let ast = match parse(source_code) {
-        (Some(ast), _) => ast,
-        (None, errors) => return Err(format_reports(source_code, &errors)),
-    };
+/// Re-parse just the item at `span` of an edited source file, along with its
+/// doc comment and attribute, for merging into the previous parse with
+/// [`StarstreamProgram::replace_item`].
+///
+/// A span that doesn't fit the source, say one left over from before the
+/// edit, is reported as an error.
+pub fn reparse_item(source_code: &str, span: SimpleSpan) -> Result<Item, Vec<Report<'_>>> {
+    if source_code.get(span.into_range()).is_none() {
+        let error = Rich::custom(
+            SimpleSpan::from(0..0),
+            format!(
+                "can't reparse {}..{}, it isn't a range of the source",
+                span.start, span.end
+            ),
+        );
+        return Err(vec![parser::error_to_report(error)]);
+    }
 
-    let (ast, mut symbols) = match do_scope_analysis(ast) {
-        Ok(ast) => ast,
-        Err(errors) => {
-            return Err(format_errors(source_code, &errors));
-        }
-    };
+    starstream_program_item(source_code, span)
+        .parse(source_code)
+        .into_result()
+        .map_err(|errors| errors.into_iter().map(parser::error_to_report).collect())
+}
 
-    let ast = match do_type_inference(ast, &mut symbols) {
-        Ok((ast, warnings)) => {
-            format_errors(source_code, &warnings);
+/// Passes between typechecking and codegen that only run when asked for.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Optimizations {
+    /// Run [`fold_constants`].
+    pub fold_constants: bool,
+}
 
-            ast
-        }
-        Err(errors) => {
-            return Err(format_errors(source_code, &errors));
+impl Optimizations {
+    /// Run the enabled passes over a typechecked program.
+    pub fn apply(self, program: &mut StarstreamProgram, symbols: &Symbols) {
+        if self.fold_constants {
+            fold_constants(program, symbols);
         }
-    };
+    }
+}
+
+/// Compile Starstream source code to a WASM module loadable by the VM,
+/// running every pass from parsing to codegen.
+///
+/// Items marked `#[when(feature)]` are only compiled if `feature` is one of
+/// `features`. Stops at the first pass that reports errors and returns its
+/// diagnostics.
+pub fn compile_to_wasm<'a>(
+    source_code: &'a str,
+    features: &[&str],
+) -> Result<Vec<u8>, Vec<Report<'a>>> {
+    compile_to_wasm_with(source_code, features, Optimizations::default())
+}
 
-    let module = match compile(&ast, symbols) {
-        (Some(module), _) => module,
-        (None, errors) => return Err(format_reports(source_code, &errors)),
+/// [`compile_to_wasm`], also running the `optimizations` asked for.
+pub fn compile_to_wasm_with<'a>(
+    source_code: &'a str,
+    features: &[&str],
+    optimizations: Optimizations,
+) -> Result<Vec<u8>, Vec<Report<'a>>> {
+    let mut ast = match parse(source_code) {
+        (Some(ast), _) => ast,
+        (None, errors) => return Err(errors),
     };
-    Ok(module)
+    ast.strip_disabled_items(features);
+
+    let (ast, mut symbols) = do_scope_analysis(ast)
+        .map_err(|errors| errors.iter().map(Report::from).collect::<Vec<_>>())?;
+
+    let (mut ast, _warnings) = do_type_inference(ast, &mut symbols)
+        .map_err(|errors| errors.iter().map(Report::from).collect::<Vec<_>>())?;
+    optimizations.apply(&mut ast, &symbols);
+
+    compile(&ast, symbols)
+}
+
+/// Highest-level one-shot compiler from Starstream source code to WASM binary.
+pub fn starstream_to_wasm(source_code: &str) -> Result<Vec<u8>, String> {
+    compile_to_wasm(source_code, &[]).map_err(|errors| format_reports(source_code, &errors))
 }
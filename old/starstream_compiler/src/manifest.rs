@@ -0,0 +1,261 @@
+//! Machine-readable description of a compiled contract's interface.
+
+use crate::{
+    ast::TypeArg,
+    pretty::pretty_type_arg,
+    symbols::{FuncInfo, SymbolId, Symbols},
+    typechecking::EffectSet,
+};
+use serde_json::{Value, json};
+use std::collections::HashSet;
+
+/// Version of the manifest schema, bumped whenever its shape changes.
+const MANIFEST_VERSION: u32 = 1;
+
+/// Describe the interface of a program for tooling outside the compiler,
+/// such as client code generators.
+///
+/// The manifest lists the program's utxos with their constructor and
+/// methods, its script functions, and its user-defined abis with their
+/// effects. Types are written as they would be in Starstream source, and
+/// every list is sorted by name so the output is stable between runs.
+pub fn emit_abi_manifest(symbols: &Symbols) -> Value {
+    let mut in_types = HashSet::new();
+    for ty in symbols.types.values() {
+        in_types.extend(ty.info.declarations.iter().copied());
+    }
+
+    let utxos = symbols
+        .utxo_types()
+        .map(|(name, utxo)| {
+            let mut constructor = Value::Null;
+            let mut methods = vec![];
+            for id in &utxo.declarations {
+                let function = &symbols.functions[id];
+                if function.info.is_main {
+                    constructor = function_json(symbols, "new", &function.info);
+                } else if function.info.is_utxo_method.is_some() {
+                    methods.push(function_json(symbols, &function.source, &function.info));
+                }
+            }
+            sort_by_name(&mut methods);
+
+            json!({
+                "name": name,
+                "abis": effect_set_names(symbols, &utxo.interfaces),
+                "yield": utxo.yield_ty.as_ref().map(pretty_type_arg),
+                "resume": utxo.resume_ty.as_ref().map(pretty_type_arg),
+                "constructor": constructor,
+                "methods": methods,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut functions = symbols
+        .functions
+        .iter()
+        .filter(|(id, function)| {
+            let info = &function.info;
+            function.span.is_some()
+                && info.mangled_name.is_some()
+                && info.is_utxo_method.is_none()
+                && info.is_effect_handler.is_none()
+                && info.is_imported.is_none()
+                && !in_types.contains(*id)
+        })
+        .map(|(_, function)| function_json(symbols, &function.source, &function.info))
+        .collect::<Vec<_>>();
+    sort_by_name(&mut functions);
+
+    let abis = symbols
+        .abis()
+        .filter(|(_, abi)| abi.is_user_defined)
+        .map(|(name, abi)| {
+            let mut abi_functions = abi
+                .fns
+                .values()
+                .map(|sig| {
+                    json!({
+                        "name": sig.name.raw,
+                        "inputs": sig.input_types.iter().map(pretty_type_arg).collect::<Vec<_>>(),
+                        "output": sig.output_type.as_ref().map(pretty_type_arg),
+                    })
+                })
+                .collect::<Vec<_>>();
+            sort_by_name(&mut abi_functions);
+
+            let effects = symbols
+                .effects_of_abi(name)
+                .into_iter()
+                .flatten()
+                .map(|(effect_name, effect)| {
+                    json!({
+                        "name": effect_name,
+                        "inputs": effect.inputs_ty.iter().map(pretty_type_arg).collect::<Vec<_>>(),
+                        "output": effect.output_ty.as_ref().map(pretty_type_arg),
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            json!({
+                "name": name,
+                "functions": abi_functions,
+                "effects": effects,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "version": MANIFEST_VERSION,
+        "utxos": utxos,
+        "functions": functions,
+        "abis": abis,
+    })
+}
+
+fn function_json(symbols: &Symbols, name: &str, info: &FuncInfo) -> Value {
+    // utxo methods take the utxo itself first, which isn't named
+    let inputs_ty = if info.is_utxo_method.is_some() {
+        &info.inputs_ty[1..]
+    } else {
+        &info.inputs_ty[..]
+    };
+    let inputs = inputs_ty
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| {
+            json!({
+                "name": info.inputs_names.get(i),
+                "type": pretty_type_arg(ty),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "name": name,
+        "export": info.mangled_name,
+        "kind": function_kind(info),
+        "inputs": inputs,
+        "output": info.output_ty.as_ref().map(output_type),
+        "effects": effect_set_names(symbols, &info.effects),
+    })
+}
+
+/// How a function is called from a coordination script, going by the prefix
+/// codegen gives its export.
+fn function_kind(info: &FuncInfo) -> &'static str {
+    let name = info.mangled_name.as_deref().unwrap_or_default();
+    if name.starts_with("starstream_new_") {
+        "new"
+    } else if name.starts_with("starstream_query_") {
+        "query"
+    } else if name.starts_with("starstream_mutate_") {
+        "mutate"
+    } else if name.starts_with("starstream_consume_") {
+        "consume"
+    } else {
+        "function"
+    }
+}
+
+/// Constructors are declared as returning a reference to the utxo, but what
+/// callers get back is a handle to it.
+fn output_type(ty: &TypeArg) -> String {
+    match ty {
+        TypeArg::Ref(inner) => pretty_type_arg(inner),
+        ty => pretty_type_arg(ty),
+    }
+}
+
+fn effect_set_names(symbols: &Symbols, effects: &EffectSet) -> Vec<String> {
+    let mut names = effects
+        .iter()
+        .map(|id: &SymbolId| symbols.interfaces[id].source.clone())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+fn sort_by_name(values: &mut [Value]) {
+    values.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::emit_abi_manifest;
+    use crate::{do_scope_analysis, do_type_inference, starstream_program};
+    use chumsky::Parser as _;
+
+    #[test]
+    fn simple_oracle_manifest() {
+        let input = include_str!("../../grammar/examples/simple_oracle.star");
+        let program = starstream_program().parse(input).unwrap();
+        let (program, mut symbols) = do_scope_analysis(program).unwrap();
+        do_type_inference(program, &mut symbols).unwrap();
+
+        let manifest = emit_abi_manifest(&symbols);
+        assert_eq!(manifest["version"], 1);
+
+        let utxos = manifest["utxos"].as_array().unwrap();
+        let names = utxos.iter().map(|u| &u["name"]).collect::<Vec<_>>();
+        assert_eq!(names, ["PayToPublicKeyHash", "SimpleOracle"]);
+
+        let pay_to_public_key_hash = &utxos[0];
+        let constructor = &pay_to_public_key_hash["constructor"];
+        assert_eq!(constructor["name"], "new");
+        assert_eq!(constructor["kind"], "new");
+        assert_eq!(
+            constructor["export"],
+            "starstream_new_PayToPublicKeyHash_new"
+        );
+        assert_eq!(
+            constructor["inputs"],
+            serde_json::json!([{ "name": "owner", "type": "PublicKey" }])
+        );
+        assert_eq!(constructor["output"], "PayToPublicKeyHash");
+        assert_eq!(
+            pay_to_public_key_hash["abis"],
+            serde_json::json!(["HasTokens"])
+        );
+        let attach_token = &pay_to_public_key_hash["methods"][0];
+        assert_eq!(attach_token["name"], "attach_token");
+        assert_eq!(
+            attach_token["inputs"],
+            serde_json::json!([{ "name": "intermediate", "type": "Intermediate<any, any>" }])
+        );
+
+        let oracle = &utxos[1];
+        assert_eq!(
+            oracle["abis"],
+            serde_json::json!(["HasOwner", "SimpleOracleAbi"])
+        );
+        let methods = oracle["methods"].as_array().unwrap();
+        let names = methods.iter().map(|m| &m["name"]).collect::<Vec<_>>();
+        assert_eq!(names, ["get_data", "get_owner"]);
+        assert_eq!(methods[1]["kind"], "query");
+        assert_eq!(
+            methods[1]["export"],
+            "starstream_query_SimpleOracle_get_owner"
+        );
+        assert_eq!(methods[1]["inputs"], serde_json::json!([]));
+        assert_eq!(methods[1]["output"], "PublicKey");
+        assert_eq!(methods[0]["output"], "Data");
+
+        let functions = manifest["functions"].as_array().unwrap();
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0]["name"], "main");
+        assert_eq!(functions[0]["output"], "PayToPublicKeyHash");
+        assert_eq!(
+            functions[0]["effects"],
+            serde_json::json!(["StarstreamEnv"])
+        );
+
+        let abis = manifest["abis"].as_array().unwrap();
+        let names = abis.iter().map(|a| &a["name"]).collect::<Vec<_>>();
+        assert_eq!(names, ["HasOwner", "HasTokens", "SimpleOracleAbi"]);
+        assert_eq!(
+            abis[0]["functions"],
+            serde_json::json!([{ "name": "get_owner", "inputs": [], "output": "PublicKey" }])
+        );
+    }
+}
@@ -19,69 +19,184 @@ pub fn error_to_report(e: Rich<char>) -> Report {
 }
 
 /// Get a Chumsky parser for a Starstream source file.
+///
+/// A broken item is reported and skipped up to the next one, so that every
+/// item gets parsed and all of their errors are reported together. The
+/// program is still produced in that case, without the broken items.
 pub fn starstream_program<'a>()
 -> impl Parser<'a, &'a str, StarstreamProgram, extra::Err<Rich<'a, char>>> {
+    let broken_item = any()
+        .and_is(item_start().not())
+        .repeated()
+        .at_least(1)
+        .to(None);
+
+    doc_comment()
+        .then(when_attribute().or_not())
+        .then(
+            program_item()
+                .map(Some)
+                .recover_with(via_parser(broken_item)),
+        )
+        .map_with(|((docs, when), node), extra| {
+            node.map(|node| Item {
+                node,
+                span: extra.span(),
+                when,
+                docs,
+            })
+        })
+        .padded_by(padding())
+        .repeated()
+        .collect::<Vec<_>>()
+        .then_ignore(end())
+        .map(|items| StarstreamProgram {
+            items: items.into_iter().flatten().collect(),
+        })
+}
+
+/// `#[when(feature)]`, making the item after it conditional on `feature`.
+fn when_attribute<'a>() -> impl Parser<'a, &'a str, Identifier, extra::Err<Rich<'a, char>>> {
+    just("#[")
+        .ignore_then(keyword("when").padded_by(padding()))
+        .ignore_then(
+            identifier()
+                .padded_by(padding())
+                .delimited_by(just('('), just(')')),
+        )
+        .then_ignore(just(']').padded_by(padding()))
+}
+
+/// Get a Chumsky parser for the single item spanning `span` of `source`,
+/// with its doc comment and attribute.
+///
+/// The rest of the source is matched verbatim rather than parsed, so spans
+/// in the item stay relative to the start of the whole file.
+///
+/// Panics if `span` isn't a range of `source`; [`crate::reparse_item`]
+/// checks it first.
+pub fn starstream_program_item<'a>(
+    source: &'a str,
+    span: SimpleSpan,
+) -> impl Parser<'a, &'a str, Item, extra::Err<Rich<'a, char>>> {
+    let item = doc_comment()
+        .then(when_attribute().or_not())
+        .then(program_item())
+        .map_with(|((docs, when), node), extra| Item {
+            node,
+            span: extra.span(),
+            when,
+            docs,
+        });
+
+    just(&source[..span.start])
+        .ignore_then(item.padded_by(padding()))
+        .then_ignore(just(&source[span.end..]))
+        .then_ignore(end())
+}
+
+/// The start of the next top-level item, where parsing picks up again after
+/// a broken one.
+///
+/// Only lines starting with an item keyword count, since items nest `const`
+/// and doc comments of their own, but those are indented.
+fn item_start<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+    text::newline()
+        .then(choice((
+            keyword("utxo"),
+            keyword("script"),
+            keyword("token"),
+            keyword("typedef"),
+            keyword("const"),
+            keyword("abi"),
+            just("///").ignored(),
+            just("#[").ignored(),
+        )))
+        .ignored()
+        .rewind()
+}
+
+fn program_item<'a>() -> impl Parser<'a, &'a str, ProgramItem, extra::Err<Rich<'a, char>>> {
     utxo()
         .map(ProgramItem::Utxo)
         .or(script().map(ProgramItem::Script))
         .or(token().map(ProgramItem::Token))
         .or(typedef().map(ProgramItem::TypeDef))
-        .or(constant().map(|(name, value)| ProgramItem::Constant { name, value }))
+        .or(constant().map(|((name, ty), value)| ProgramItem::Constant { name, ty, value }))
         .or(abi().map(ProgramItem::Abi))
-        .padded()
-        .repeated()
-        .collect::<Vec<_>>()
-        .then_ignore(end())
-        .map(|items| StarstreamProgram { items })
+        .boxed()
 }
 
 fn utxo<'a>() -> impl Parser<'a, &'a str, Utxo, extra::Err<Rich<'a, char>>> {
-    just("utxo")
-        .ignore_then(identifier().padded())
+    keyword("utxo")
+        .ignore_then(identifier().padded_by(padding()))
         .then(
             main()
                 .map(UtxoItem::Main)
                 .or(r#impl().map(UtxoItem::Impl))
                 .or(storage().map(UtxoItem::Storage))
-                .or(just("Yield")
-                    .padded()
+                .or(keyword("Yield")
+                    .padded_by(padding())
                     .ignore_then(type_arg())
-                    .map(|ty| UtxoItem::Yield(ty)))
-                .or(just("Resume")
-                    .padded()
+                    .map_with(|node, extra| {
+                        UtxoItem::Yield(Spanned {
+                            node,
+                            span: extra.span(),
+                        })
+                    }))
+                .or(keyword("Resume")
+                    .padded_by(padding())
                     .ignore_then(type_arg())
-                    .map(|ty| UtxoItem::Resume(ty)))
-                .padded()
+                    .map_with(|node, extra| {
+                        UtxoItem::Resume(Spanned {
+                            node,
+                            span: extra.span(),
+                        })
+                    }))
+                .padded_by(padding())
                 .repeated()
                 .collect::<Vec<_>>()
-                .delimited_by(just('{').padded(), just('}').padded()),
+                .delimited_by(
+                    just('{').padded_by(padding()),
+                    just('}').padded_by(padding()),
+                ),
         )
         .map(|(name, items)| Utxo { name, items })
 }
 
 fn fn_sig<'a>() -> impl Parser<'a, &'a str, FnDecl, extra::Err<Rich<'a, char>>> {
-    just("fn").ignore_then(sig()).map(FnDecl)
+    keyword("fn").ignore_then(sig()).map(FnDecl)
 }
 
 fn effect_sig<'a>() -> impl Parser<'a, &'a str, EffectDecl, extra::Err<Rich<'a, char>>> {
     choice((
-        just("effect").ignore_then(sig()).map(EffectDecl::EffectSig),
-        just("event").ignore_then(sig()).map(EffectDecl::EventSig),
-        just("error").ignore_then(sig()).map(EffectDecl::ErrorSig),
+        keyword("effect")
+            .ignore_then(sig())
+            .map(EffectDecl::EffectSig),
+        keyword("event")
+            .ignore_then(sig())
+            .map(EffectDecl::EventSig),
+        keyword("error")
+            .ignore_then(sig())
+            .map(EffectDecl::ErrorSig),
     ))
 }
 
 fn sig<'a>() -> impl Parser<'a, &'a str, Sig, extra::Err<Rich<'a, char>>> {
     identifier()
-        .padded()
+        .padded_by(padding())
         .then(
             type_arg()
-                .padded()
+                .padded_by(padding())
                 .separated_by(just(','))
                 .collect::<Vec<_>>()
                 .delimited_by(just('('), just(')')),
         )
-        .then(just(':').ignore_then(type_arg().padded()).or_not())
+        .then(
+            just(':')
+                .ignore_then(type_arg().padded_by(padding()))
+                .or_not(),
+        )
         .map(|((name, input_types), output_type)| Sig {
             name,
             input_types,
@@ -92,63 +207,77 @@ fn sig<'a>() -> impl Parser<'a, &'a str, Sig, extra::Err<Rich<'a, char>>> {
 fn fn_def<'a>() -> impl Parser<'a, &'a str, FnDef, extra::Err<Rich<'a, char>>> {
     let typed_bindings = typed_binding(type_arg())
         .map(|(name, ty)| FnArgDeclaration { name, ty })
-        .separated_by(just(',').padded())
+        .separated_by(just(',').padded_by(padding()))
         .allow_trailing()
         .collect::<Vec<_>>()
         .boxed();
 
-    just("fn")
-        .padded()
-        .ignore_then(identifier())
-        .padded()
-        .then(typed_bindings.padded().delimited_by(just('('), just(')')))
-        .then(just(':').ignore_then(type_arg().padded()).or_not())
+    doc_comment()
+        .then_ignore(keyword("fn").padded_by(padding()))
+        .then(identifier())
+        .padded_by(padding())
+        .then(
+            typed_bindings
+                .padded_by(padding())
+                .delimited_by(just('('), just(')')),
+        )
+        .then(
+            just(':')
+                .ignore_then(type_arg().padded_by(padding()))
+                .or_not(),
+        )
         .then(
             just('/')
-                .padded()
-                .then_ignore(just('{').padded())
+                .padded_by(padding())
+                .then_ignore(just('{').padded_by(padding()))
                 .ignore_then(
                     identifier()
-                        .separated_by(just(',').padded())
+                        .separated_by(just(',').padded_by(padding()))
                         .collect::<Vec<_>>(),
                 )
-                .then_ignore(just('}').padded())
+                .then_ignore(just('}').padded_by(padding()))
                 .or_not(),
         )
         .then(block())
-        .map(|((((name, inputs), output), effects), body)| FnDef {
-            ident: name,
-            inputs,
-            output,
-            body,
-            effects: effects.unwrap_or_default(),
-        })
+        .map(
+            |(((((docs, name), inputs), output), effects), body)| FnDef {
+                ident: name,
+                inputs,
+                output,
+                body,
+                effects: effects.unwrap_or_default(),
+                docs,
+            },
+        )
 }
 
 fn token<'a>() -> impl Parser<'a, &'a str, Token, extra::Err<Rich<'a, char>>> {
-    just("token")
-        .padded()
+    keyword("token")
+        .padded_by(padding())
         .ignore_then(identifier())
         .then(
-            just("bind")
-                .padded()
+            keyword("bind")
+                .padded_by(padding())
                 .ignore_then(block())
                 .map(|body| Bind(body, Identifier::new("bind", None)))
                 .map(TokenItem::Bind)
-                .or(just("unbind")
-                    .padded()
+                .or(keyword("unbind")
+                    .padded_by(padding())
                     .ignore_then(block())
                     .map(|body| Unbind(body, Identifier::new("unbind", None)))
                     .map(TokenItem::Unbind))
-                .or(just("mint")
-                    .padded()
+                .or(keyword("mint")
+                    .padded_by(padding())
                     .ignore_then(block())
                     .map(|body| Mint(body, Identifier::new("mint", None)))
                     .map(TokenItem::Mint))
-                .padded()
+                .padded_by(padding())
                 .repeated()
                 .collect::<Vec<_>>()
-                .delimited_by(just('{').padded(), just('}').padded()),
+                .delimited_by(
+                    just('{').padded_by(padding()),
+                    just('}').padded_by(padding()),
+                ),
         )
         .map(|(name, mut items)| {
             let has_mint = items.iter().any(|item| matches!(item, TokenItem::Mint(_)));
@@ -157,23 +286,24 @@ fn token<'a>() -> impl Parser<'a, &'a str, Token, extra::Err<Rich<'a, char>>> {
                 .iter()
                 .any(|item| matches!(item, TokenItem::Unbind(_)));
 
+            // missing items get an empty body, the same as `{}`
             if !has_mint {
                 items.push(TokenItem::Mint(Mint(
-                    Block::Close { semicolon: true },
+                    Block::Close { semicolon: false },
                     Identifier::new("mint", None),
                 )))
             }
 
             if !has_bind {
                 items.push(TokenItem::Bind(Bind(
-                    Block::Close { semicolon: true },
+                    Block::Close { semicolon: false },
                     Identifier::new("bind", None),
                 )))
             }
 
             if !has_unbind {
                 items.push(TokenItem::Unbind(Unbind(
-                    Block::Close { semicolon: true },
+                    Block::Close { semicolon: false },
                     Identifier::new("unbind", None),
                 )))
             }
@@ -182,78 +312,146 @@ fn token<'a>() -> impl Parser<'a, &'a str, Token, extra::Err<Rich<'a, char>>> {
         })
 }
 
+enum ImplItem {
+    Constant(AssociatedConst),
+    FnDef(FnDef),
+}
+
 fn r#impl<'a>() -> impl Parser<'a, &'a str, Impl, extra::Err<Rich<'a, char>>> {
-    just("impl")
-        .padded()
+    keyword("impl")
+        .padded_by(padding())
         .ignore_then(identifier())
         .then(
-            fn_def()
-                .padded()
+            associated_constant()
+                .map(ImplItem::Constant)
+                .or(fn_def().map(ImplItem::FnDef))
+                .padded_by(padding())
                 .repeated()
                 .collect::<Vec<_>>()
-                .delimited_by(just('{').padded(), just('}').padded()),
+                .delimited_by(
+                    just('{').padded_by(padding()),
+                    just('}').padded_by(padding()),
+                ),
         )
-        .map(|(name, definitions)| Impl { name, definitions })
+        .map(|(name, items)| {
+            let mut constants = vec![];
+            let mut definitions = vec![];
+            for item in items {
+                match item {
+                    ImplItem::Constant(constant) => constants.push(constant),
+                    ImplItem::FnDef(definition) => definitions.push(definition),
+                }
+            }
+
+            Impl {
+                name,
+                constants,
+                definitions,
+            }
+        })
+}
+
+fn associated_constant<'a>() -> impl Parser<'a, &'a str, AssociatedConst, extra::Err<Rich<'a, char>>>
+{
+    keyword("const")
+        .ignore_then(identifier().padded_by(padding()))
+        .then_ignore(just(":").padded_by(padding()))
+        .then(type_arg().padded_by(padding()))
+        .then_ignore(just("=").padded_by(padding()))
+        .then(expr(block().boxed()).padded_by(padding()))
+        .then_ignore(just(";"))
+        .map(|((name, ty), value)| AssociatedConst { name, ty, value })
 }
 
 fn script<'a>() -> impl Parser<'a, &'a str, Script, extra::Err<Rich<'a, char>>> {
-    just("script")
-        .padded()
+    keyword("script")
+        .padded_by(padding())
         .ignore_then(
             fn_def()
-                .padded()
+                .padded_by(padding())
                 .repeated()
                 .collect::<Vec<_>>()
-                .delimited_by(just('{').padded(), just('}').padded()),
+                .delimited_by(
+                    just('{').padded_by(padding()),
+                    just('}').padded_by(padding()),
+                ),
         )
         .map(|definitions| Script { definitions })
 }
 
 fn abi<'a>() -> impl Parser<'a, &'a str, Abi, extra::Err<Rich<'a, char>>> {
-    just("abi")
-        .ignore_then(identifier().padded())
+    keyword("abi")
+        .ignore_then(identifier().padded_by(padding()))
         .then(
             choice((
-                fn_sig().map(AbiElem::FnDecl),
-                effect_sig().map(AbiElem::EffectDecl),
+                fn_def().map(AbiElem::FnDef),
+                fn_sig()
+                    .map(AbiElem::FnDecl)
+                    .then_ignore(just(';').padded_by(padding())),
+                effect_sig()
+                    .map(AbiElem::EffectDecl)
+                    .then_ignore(just(';').padded_by(padding())),
             ))
-            .then_ignore(just(';').padded())
+            .padded_by(padding())
             .repeated()
             .collect::<Vec<_>>()
-            .delimited_by(just('{').padded(), just('}').padded()),
+            .delimited_by(
+                just('{').padded_by(padding()),
+                just('}').padded_by(padding()),
+            ),
         )
         .map(|(name, values)| Abi { name, values })
 }
 
 fn storage<'a>() -> impl Parser<'a, &'a str, Storage, extra::Err<Rich<'a, char>>> {
-    just("storage")
+    keyword("storage")
         .ignore_then(
-            typed_binding(type_arg())
-                .then_ignore(just(';').padded())
+            doc_comment()
+                .then(typed_binding(type_arg()))
+                .then(
+                    just('=')
+                        .padded_by(padding())
+                        .ignore_then(expr(block().boxed()).padded_by(padding()))
+                        .or_not(),
+                )
+                .then_ignore(just(';').padded_by(padding()))
                 .repeated()
                 .collect::<Vec<_>>()
-                .delimited_by(just('{').padded(), just('}').padded()),
+                .delimited_by(
+                    just('{').padded_by(padding()),
+                    just('}').padded_by(padding()),
+                ),
         )
-        .map(|values| Storage {
-            bindings: TypedBindings { values },
+        .map(|fields| {
+            let (docs_values, initializers): (Vec<_>, _) = fields.into_iter().unzip();
+            let (docs, values) = docs_values.into_iter().unzip();
+            Storage {
+                bindings: TypedBindings { values },
+                initializers,
+                docs,
+            }
         })
 }
 
 fn main<'a>() -> impl Parser<'a, &'a str, Main, extra::Err<Rich<'a, char>>> {
-    just("main")
-        .ignore_then(
+    keyword("main")
+        .map_with(|_, extra| extra.span())
+        .then(
             typed_binding(type_arg())
-                .separated_by(just(',').padded())
+                .separated_by(just(',').padded_by(padding()))
                 .collect::<Vec<_>>()
                 .map(|values| TypedBindings { values })
-                .delimited_by(just('(').padded(), just(')').padded())
+                .delimited_by(
+                    just('(').padded_by(padding()),
+                    just(')').padded_by(padding()),
+                )
                 .or_not(),
         )
         .then(block())
-        .map(|(typed_bindings, block)| Main {
+        .map(|((span, typed_bindings), block)| Main {
             type_sig: typed_bindings,
             block,
-            ident: Identifier::new("new", None),
+            ident: Identifier::new("new", Some(span)),
         })
 }
 
@@ -262,14 +460,24 @@ fn statement<'a>(
     block_parser: impl Parser<'a, &'a str, Block, extra::Err<Rich<'a, char>>> + Clone + 'a,
 ) -> impl Parser<'a, &'a str, Statement, extra::Err<Rich<'a, char>>> {
     recursive(|rec| {
-        let bind_var = just("let")
-            .padded()
-            .ignore_then(just("mut").padded().or_not().map(|x| x.is_some()))
+        let bind_var = keyword("let")
+            .padded_by(padding())
+            .ignore_then(
+                keyword("mut")
+                    .padded_by(padding())
+                    .or_not()
+                    .map(|x| x.is_some()),
+            )
             .then(identifier())
-            .then(just(":").padded().ignore_then(type_arg()).or_not())
-            .then_ignore(just('=').padded())
+            .then(
+                just(":")
+                    .padded_by(padding())
+                    .ignore_then(type_arg())
+                    .or_not(),
+            )
+            .then_ignore(just('=').padded_by(padding()))
             .then(expr_parser.clone())
-            .then_ignore(just(';').padded())
+            .then_ignore(just(';').padded_by(padding()))
             .map(|(((mutable, binding), ty), expr)| Statement::BindVar {
                 var: binding,
                 ty,
@@ -279,7 +487,7 @@ fn statement<'a>(
             .boxed();
 
         let assign = field_access_expr(expr_parser.clone())
-            .then_ignore(just('=').padded())
+            .then_ignore(just('=').padded_by(padding()))
             .then(expr_parser.clone())
             .then_ignore(just(';'))
             .map(|(var, expr)| Statement::Assign { var, expr })
@@ -295,43 +503,74 @@ fn statement<'a>(
                 .then_ignore(just(';'))
                 .map(LoopBody::Expr));
 
-        let while_statement = just("while")
-            .padded()
-            .ignore_then(
-                expr_parser
-                    .clone()
-                    .delimited_by(just('(').padded(), just(')').padded()),
-            )
+        let while_statement = keyword("while")
+            .padded_by(padding())
+            .ignore_then(expr_parser.clone().delimited_by(
+                just('(').padded_by(padding()),
+                just(')').padded_by(padding()),
+            ))
             .then(loop_body.clone())
             .map(|(cond, body)| Statement::While(cond, body))
             .boxed();
 
-        let loop_statement = just("loop")
-            .padded()
-            .ignore_then(loop_body)
+        let loop_statement = keyword("loop")
+            .padded_by(padding())
+            .ignore_then(loop_body.clone())
             .map(Statement::Loop)
             .boxed();
 
-        let try_with = just("try")
-            .ignore_then(block_parser.clone())
-            .then(
-                just("with")
-                    .ignore_then(effect_handler().padded())
-                    .then(block_parser.clone().padded())
-                    .repeated()
-                    .collect::<Vec<_>>(),
-            )
-            .map(|(body, handlers)| Statement::With(body, handlers))
+        let for_statement = keyword("for")
+            .padded_by(padding())
+            .ignore_then(identifier())
+            .then_ignore(keyword("in").padded_by(padding()))
+            .then(expr_parser.clone())
+            .then_ignore(just("..").padded_by(padding()))
+            .then(expr_parser.clone())
+            .then(loop_body)
+            .map(|(((var, start), end), body)| Statement::For {
+                var,
+                start,
+                end,
+                body,
+            })
             .boxed();
 
-        let resume = just("resume")
-            .ignore_then(expr_parser.clone().padded().or_not())
-            .then_ignore(just(';').padded())
+        let labeled = loop_label()
+            .then_ignore(just(':').padded_by(padding()))
+            .then(choice((
+                while_statement.clone(),
+                loop_statement.clone(),
+                for_statement.clone(),
+            )))
+            .map(|(label, statement)| Statement::Labeled(label, Box::new(statement)))
+            .boxed();
+
+        let break_statement = keyword("break")
+            .ignore_then(loop_label().padded_by(padding()).or_not())
+            .then(expr_parser.clone().padded_by(padding()).or_not())
+            .then_ignore(just(';').padded_by(padding()))
+            .map_with(|(label, value), extra| Statement::Break {
+                label,
+                value,
+                span: extra.span(),
+            });
+
+        let continue_statement = keyword("continue")
+            .ignore_then(loop_label().padded_by(padding()).or_not())
+            .then_ignore(just(';').padded_by(padding()))
+            .map_with(|label, extra| Statement::Continue {
+                label,
+                span: extra.span(),
+            });
+
+        let resume = keyword("resume")
+            .ignore_then(expr_parser.clone().padded_by(padding()).or_not())
+            .then_ignore(just(';').padded_by(padding()))
             .map(Statement::Resume);
 
-        let ret = just("return")
-            .ignore_then(expr_parser.clone().padded().or_not())
-            .then_ignore(just(';').padded())
+        let ret = keyword("return")
+            .ignore_then(expr_parser.clone().padded_by(padding()).or_not())
+            .then_ignore(just(';').padded_by(padding()))
             .map(Statement::Return);
 
         choice((
@@ -339,13 +578,25 @@ fn statement<'a>(
             assign,
             while_statement,
             loop_statement,
-            try_with,
+            for_statement,
+            labeled,
+            break_statement,
+            continue_statement,
             resume,
             ret,
         ))
     })
 }
 
+fn loop_label<'a>() -> impl Parser<'a, &'a str, LoopLabel, extra::Err<Rich<'a, char>>> {
+    just('\'')
+        .ignore_then(text::ident())
+        .map_with(|name: &'a str, extra| LoopLabel {
+            name: name.to_string(),
+            span: extra.span(),
+        })
+}
+
 fn effect_handler<'a>() -> impl Parser<'a, &'a str, EffectHandler, extra::Err<Rich<'a, char>>> {
     identifier()
         .then_ignore(just("::"))
@@ -367,7 +618,11 @@ fn effect_handler<'a>() -> impl Parser<'a, &'a str, EffectHandler, extra::Err<Ri
 fn typed_binding<'a>(
     type_parser: impl Parser<'a, &'a str, TypeArg, extra::Err<Rich<'a, char>>>,
 ) -> impl Parser<'a, &'a str, (Identifier, TypeArg), extra::Err<Rich<'a, char>>> {
-    identifier().then(just(':').padded().ignore_then(type_parser.padded()))
+    identifier().then(
+        just(':')
+            .padded_by(padding())
+            .ignore_then(type_parser.padded_by(padding())),
+    )
 }
 
 fn optionally_typed_binding<'a>(
@@ -375,8 +630,8 @@ fn optionally_typed_binding<'a>(
 ) -> impl Parser<'a, &'a str, (Identifier, Option<TypeArg>), extra::Err<Rich<'a, char>>> {
     identifier().then(
         just(':')
-            .padded()
-            .ignore_then(type_parser.padded())
+            .padded_by(padding())
+            .ignore_then(type_parser.padded_by(padding()))
             .or_not(),
     )
 }
@@ -385,7 +640,7 @@ fn optionally_typed_bindings<'a>(
     type_parser: impl Parser<'a, &'a str, TypeArg, extra::Err<Rich<'a, char>>>,
 ) -> impl Parser<'a, &'a str, OptionallyTypedBindings, extra::Err<Rich<'a, char>>> {
     optionally_typed_binding(type_parser)
-        .separated_by(just(',').padded())
+        .separated_by(just(',').padded_by(padding()))
         .allow_trailing()
         .collect::<Vec<_>>()
         .map(|values| OptionallyTypedBindings { values })
@@ -394,14 +649,20 @@ fn optionally_typed_bindings<'a>(
 fn expr<'a>(
     block_parser: impl Parser<'a, &'a str, Block, extra::Err<Rich<'a, char>>> + Clone + 'a,
 ) -> impl Parser<'a, &'a str, Spanned<Expr>, extra::Err<Rich<'a, char>>> {
-    let op = |c: &'static str| just(c).padded();
+    let op = |c: &'static str| just(c).padded_by(padding());
 
     recursive(|expr_parser| {
-        let atom = field_access_expr(expr_parser.clone())
+        let atom = try_with_expr(block_parser.clone())
             .map_with(|e, extra| Spanned {
-                node: Expr::PrimaryExpr(e),
+                node: Expr::BlockExpr(e),
                 span: extra.span(),
             })
+            .or(
+                field_access_expr(expr_parser.clone()).map_with(|e, extra| Spanned {
+                    node: Expr::PrimaryExpr(e),
+                    span: extra.span(),
+                }),
+            )
             .or(
                 block_expr(expr_parser, block_parser).map_with(|e, extra| Spanned {
                     node: Expr::BlockExpr(e),
@@ -433,7 +694,7 @@ fn expr<'a>(
                 span: extra.span(),
             }),
             infix(left(9), op("%"), |l, _, r, extra| Spanned {
-                node: Expr::Div(Box::new(l), Box::new(r)),
+                node: Expr::Mod(Box::new(l), Box::new(r)),
                 span: extra.span(),
             }),
             // prec = 8
@@ -468,7 +729,7 @@ fn expr<'a>(
                 span: extra.span(),
             }),
             infix(left(6), op(">="), |l, _, r, extra| Spanned {
-                node: Expr::LessThan(Box::new(l), Box::new(r)),
+                node: Expr::GreaterEq(Box::new(l), Box::new(r)),
                 span: extra.span(),
             }),
             // prec = 5
@@ -501,10 +762,14 @@ fn expr<'a>(
                 span: extra.span(),
             }),
             // prec = 0
-            infix(left(0), just("||").padded(), |l, _, r, extra| Spanned {
-                node: Expr::Or(Box::new(l), Box::new(r)),
-                span: extra.span(),
-            }),
+            infix(
+                left(0),
+                just("||").padded_by(padding()),
+                |l, _, r, extra| Spanned {
+                    node: Expr::Or(Box::new(l), Box::new(r)),
+                    span: extra.span(),
+                },
+            ),
         ))
         .boxed()
     })
@@ -518,44 +783,50 @@ fn block<'a>() -> impl Parser<'a, &'a str, Block, extra::Err<Rich<'a, char>>> {
 
     block_body.define({
         let end_block = just(';')
-            .padded()
+            .padded_by(padding())
             .or_not()
-            .then_ignore(comment().boxed().ignore_then(just('}').padded()))
+            .then_ignore(
+                comment()
+                    .boxed()
+                    .ignore_then(just('}').padded_by(padding())),
+            )
             .map(|semicolon| Block::Close {
                 semicolon: semicolon.is_some(),
             });
 
         let if_branch = if_expr(expr_parser.clone(), block_expr.clone())
-            .padded()
+            .or(try_with_expr(block_expr.clone()))
+            .or(match_expr(expr_parser.clone(), block_expr.clone()))
+            .padded_by(padding())
             .map_with(|block, extra| Spanned {
                 node: Expr::BlockExpr(block),
                 span: extra.span(),
             })
             .map(ExprOrStatement::Expr)
             .then(end_block.clone().or(block_body.clone()))
-            .padded();
+            .padded_by(padding());
 
         let expr_with_semicolon = expr_parser
             .clone()
-            .padded()
+            .padded_by(padding())
             .map(ExprOrStatement::Expr)
             .then(
                 end_block.clone().or(just(";")
                     .ignored()
-                    .padded()
+                    .padded_by(padding())
                     .ignore_then(block_body.clone())
-                    .padded()),
+                    .padded_by(padding())),
             );
 
         let statement = statement(expr_parser.clone(), block_expr.clone())
-            .padded()
+            .padded_by(padding())
             .map(ExprOrStatement::Statement)
-            .then(block_body.clone().padded().or(end_block))
+            .then(block_body.clone().padded_by(padding()).or(end_block))
             .boxed();
 
         let block_body_item = just('}')
             .to(Block::Close { semicolon: false })
-            .padded()
+            .padded_by(padding())
             .or(
                 choice((if_branch, expr_with_semicolon, statement)).map(|(x, xs)| Block::Chain {
                     head: Box::new(x),
@@ -566,7 +837,7 @@ fn block<'a>() -> impl Parser<'a, &'a str, Block, extra::Err<Rich<'a, char>>> {
         comment().boxed().ignore_then(block_body_item)
     });
 
-    block_expr.define(just('{').padded().ignore_then(block_body));
+    block_expr.define(just('{').padded_by(padding()).ignore_then(block_body));
 
     block_expr
 }
@@ -577,61 +848,178 @@ fn block_expr<'a>(
 ) -> impl Parser<'a, &'a str, BlockExpr, extra::Err<Rich<'a, char>>> {
     let parse_block = block_parser.clone().map(BlockExpr::Block);
     let if_expr = if_expr(expr_parser.clone(), block_parser.clone());
+    let match_expr = match_expr(expr_parser, block_parser.clone());
+    let loop_expr = keyword("loop")
+        .padded_by(padding())
+        .ignore_then(block_parser)
+        .map(|block| BlockExpr::Loop(LoopBody::Block(block)));
 
-    choice((parse_block, if_expr))
+    choice((parse_block, if_expr, match_expr, loop_expr))
 }
 
-fn if_expr<'a>(
+fn match_expr<'a>(
     expr_parser: impl Parser<'a, &'a str, Spanned<Expr>, extra::Err<Rich<'a, char>>> + Clone + 'a,
     block_parser: impl Parser<'a, &'a str, Block, extra::Err<Rich<'a, char>>> + Clone + 'a,
 ) -> impl Parser<'a, &'a str, BlockExpr, extra::Err<Rich<'a, char>>> + Clone {
-    just("if")
-        .ignore_then(
-            expr_parser
-                .clone()
-                .padded()
-                .delimited_by(just("(").padded(), just(")").padded()),
+    let arm = identifier()
+        .then(
+            identifier()
+                .separated_by(just(',').padded_by(padding()))
+                .allow_trailing()
+                .collect::<Vec<_>>()
+                .delimited_by(
+                    just('(').padded_by(padding()),
+                    just(')').padded_by(padding()),
+                ),
         )
-        .then(block_parser.clone().padded())
+        .then_ignore(just("=>").padded_by(padding()))
+        .then(block_parser)
+        .map(|((variant, bindings), body)| MatchArm {
+            variant,
+            bindings,
+            body,
+        });
+
+    keyword("match")
+        .ignore_then(expr_parser.padded_by(padding()).delimited_by(
+            just("(").padded_by(padding()),
+            just(")").padded_by(padding()),
+        ))
         .then(
-            just("else")
-                .padded()
-                .ignore_then(block_parser.padded())
-                .or_not(),
+            arm.separated_by(just(',').padded_by(padding()))
+                .allow_trailing()
+                .collect::<Vec<_>>()
+                .delimited_by(
+                    just('{').padded_by(padding()),
+                    just('}').padded_by(padding()),
+                ),
         )
-        .map(|((expr1, expr2), expr3)| {
-            BlockExpr::IfThenElse(Box::new(expr1), Box::new(expr2), expr3.map(Box::new))
-        })
-        .labelled("if-expr")
+        .map(|(scrutinee, arms)| BlockExpr::Match(Box::new(scrutinee), arms))
+        .labelled("match-expr")
         .boxed()
 }
 
+fn if_expr<'a>(
+    expr_parser: impl Parser<'a, &'a str, Spanned<Expr>, extra::Err<Rich<'a, char>>> + Clone + 'a,
+    block_parser: impl Parser<'a, &'a str, Block, extra::Err<Rich<'a, char>>> + Clone + 'a,
+) -> impl Parser<'a, &'a str, BlockExpr, extra::Err<Rich<'a, char>>> + Clone {
+    recursive(|if_expr| {
+        // `else if (..) { .. }` is sugar for `else { if (..) { .. } }`.
+        let else_if = if_expr.map_with(|block, extra| Block::Chain {
+            head: Box::new(ExprOrStatement::Expr(Spanned {
+                node: Expr::BlockExpr(block),
+                span: extra.span(),
+            })),
+            tail: Box::new(Block::Close { semicolon: false }),
+        });
+
+        keyword("if")
+            .ignore_then(expr_parser.clone().padded_by(padding()).delimited_by(
+                just("(").padded_by(padding()),
+                just(")").padded_by(padding()),
+            ))
+            .then(block_parser.clone().padded_by(padding()))
+            .then(
+                keyword("else")
+                    .padded_by(padding())
+                    .ignore_then(else_if.or(block_parser.clone()).padded_by(padding()))
+                    .or_not(),
+            )
+            .map(|((expr1, expr2), expr3)| {
+                BlockExpr::IfThenElse(Box::new(expr1), Box::new(expr2), expr3.map(Box::new))
+            })
+            .labelled("if-expr")
+    })
+    .boxed()
+}
+
+fn try_with_expr<'a>(
+    block_parser: impl Parser<'a, &'a str, Block, extra::Err<Rich<'a, char>>> + Clone + 'a,
+) -> impl Parser<'a, &'a str, BlockExpr, extra::Err<Rich<'a, char>>> + Clone {
+    keyword("try")
+        .ignore_then(block_parser.clone().padded_by(padding()))
+        .then(
+            keyword("with")
+                .ignore_then(effect_handler().padded_by(padding()))
+                .then(block_parser.padded_by(padding()))
+                .repeated()
+                .collect::<Vec<_>>(),
+        )
+        .map(|(body, handlers)| BlockExpr::TryWith(Box::new(body), handlers))
+        .labelled("try-expr")
+        .boxed()
+}
+
+/// What can follow a primary expression: `.b` or `[b]`.
+enum Postfix {
+    Field(IdentifierExpr),
+    Index(Spanned<Expr>),
+}
+
 fn field_access_expr<'a>(
     expr_parser: impl Parser<'a, &'a str, Spanned<Expr>, extra::Err<Rich<'a, char>>> + Clone + 'a,
 ) -> impl Parser<'a, &'a str, FieldAccessExpression, extra::Err<Rich<'a, char>>> {
-    primary_expr(expr_parser.clone())
+    // `resume` is a keyword, but also the method that resumes a UTXO
+    let resume_method = keyword("resume")
+        .map_with(|_, extra| Identifier::new("resume", Some(extra.span())))
+        .then(application(expr_parser.clone()).or_not())
+        .map(|(name, args)| IdentifierExpr { name, args });
+
+    let field = just('.')
+        .padded_by(padding())
+        .ignore_then(resume_method.or(identifier_expr(expr_parser.clone())))
+        .map(Postfix::Field);
+
+    let index = expr_parser
+        .clone()
+        .padded_by(padding())
+        .delimited_by(just('[').padded_by(padding()), just(']'))
+        .map(Postfix::Index);
+
+    primary_expr(expr_parser)
         .map(FieldAccessExpression::PrimaryExpr)
-        .foldl(
-            just('.')
-                .padded()
-                .ignore_then(identifier_expr(expr_parser))
-                .repeated(),
-            |accum, new| FieldAccessExpression::FieldAccess {
+        .foldl(field.or(index).repeated(), |accum, new| match new {
+            Postfix::Field(field) => FieldAccessExpression::FieldAccess {
                 base: Box::new(accum),
-                field: new,
+                field,
             },
-        )
+            Postfix::Index(index) => FieldAccessExpression::Index {
+                base: Box::new(accum),
+                index: Box::new(index),
+            },
+        })
 }
 
 fn application<'a>(
     expr_parser: impl Parser<'a, &'a str, Spanned<Expr>, extra::Err<Rich<'a, char>>> + Clone + 'a,
 ) -> impl Parser<'a, &'a str, Arguments, extra::Err<Rich<'a, char>>> {
-    expr_parser
-        .clone()
-        .separated_by(just(',').padded())
+    // `name: value`, but not the start of `Namespace::item`
+    let named = identifier()
+        .padded_by(padding())
+        .then_ignore(just("::").not())
+        .then_ignore(just(':'))
+        .then(expr_parser.clone().padded_by(padding()))
+        .map(|(name, value)| (Some(name), value));
+
+    named
+        .or(expr_parser.clone().map(|value| (None, value)))
+        .separated_by(just(',').padded_by(padding()))
         .allow_trailing()
         .collect::<Vec<_>>()
-        .map(|xs| Arguments { xs })
+        .try_map(|args, span| {
+            let positional = args.iter().take_while(|(name, _)| name.is_none()).count();
+            if args[positional..].iter().any(|(name, _)| name.is_none()) {
+                return Err(Rich::custom(
+                    span,
+                    "positional arguments must come before named arguments",
+                ));
+            }
+
+            let mut args = args.into_iter();
+            let xs = args.by_ref().take(positional).map(|(_, x)| x).collect();
+            let named = args.map(|(name, x)| (name.unwrap(), x)).collect();
+            Ok(Arguments { xs, named })
+        })
         .delimited_by(just('('), just(')'))
 }
 
@@ -646,37 +1034,80 @@ fn identifier_expr<'a>(
 fn primary_expr<'a>(
     expr_parser: impl Parser<'a, &'a str, Spanned<Expr>, extra::Err<Rich<'a, char>>> + Clone + 'a,
 ) -> impl Parser<'a, &'a str, PrimaryExpr, extra::Err<Rich<'a, char>>> {
+    let magnitude = choice((
+        just("0x").ignore_then(text::digits(16).to_slice().map(|s| (s, 16))),
+        just("0b").ignore_then(text::digits(2).to_slice().map(|s| (s, 2))),
+        just("0o").ignore_then(text::digits(8).to_slice().map(|s| (s, 8))),
+        text::int(10).map(|s| (s, 10)),
+    ));
+
+    let suffix = choice((
+        just("u32").to(TypeArg::U32),
+        just("i32").to(TypeArg::I32),
+        just("u64").to(TypeArg::U64),
+        just("i64").to(TypeArg::I64),
+    ));
+
     let number = just('-')
         .or_not()
-        .then(text::int(10))
-        .to_slice()
-        .map(|s: &str| s.parse().unwrap())
-        .map(|literal| PrimaryExpr::Number { literal, ty: None });
+        .then(magnitude)
+        .then(suffix.or_not())
+        .try_map(
+            |((minus, (digits, radix)), suffix): ((_, (&str, u32)), _), span| {
+                let value = i128::from_str_radix(digits, radix)
+                    .ok()
+                    .map(|value| if minus.is_some() { -value } else { value });
+
+                match suffix {
+                    None => value
+                        .and_then(|value| u32::try_from(value).ok())
+                        .map(|literal| PrimaryExpr::Number { literal, ty: None }),
+                    Some(ty) => value
+                        .filter(|value| match ty {
+                            TypeArg::U32 => u32::try_from(*value).is_ok(),
+                            TypeArg::I32 => i32::try_from(*value).is_ok(),
+                            TypeArg::U64 => u64::try_from(*value).is_ok(),
+                            _ => i64::try_from(*value).is_ok(),
+                        })
+                        .map(|literal| PrimaryExpr::TypedNumber(literal, ty)),
+                }
+                .ok_or_else(|| Rich::custom(span, "integer literal out of range"))
+            },
+        );
 
     let bool = choice((
-        just("true").to(PrimaryExpr::Bool(true)),
-        just("false").to(PrimaryExpr::Bool(false)),
+        keyword("true").to(PrimaryExpr::Bool(true)),
+        keyword("false").to(PrimaryExpr::Bool(false)),
     ));
 
     let par_expr = expr_parser
         .clone()
-        .padded()
+        .padded_by(padding())
         .delimited_by(just('('), just(')'))
         .map(|expr| PrimaryExpr::ParExpr(Box::new(expr)));
 
-    let yield_expr = just("yield")
-        .ignore_then(expr_parser.clone().padded().map(Box::new).or_not())
+    let yield_expr = keyword("yield")
+        .ignore_then(
+            expr_parser
+                .clone()
+                .padded_by(padding())
+                .map(Box::new)
+                .or_not(),
+        )
         .map(PrimaryExpr::Yield);
 
     let object = type_arg()
         .then(
             identifier()
                 .then_ignore(just(":"))
-                .then(expr_parser.clone().padded())
-                .separated_by(just(',').padded())
+                .then(expr_parser.clone().padded_by(padding()))
+                .separated_by(just(',').padded_by(padding()))
                 .allow_trailing()
                 .collect::<Vec<_>>()
-                .delimited_by(just('{').padded(), just('}').padded()),
+                .delimited_by(
+                    just('{').padded_by(padding()),
+                    just('}').padded_by(padding()),
+                ),
         )
         .map(|(ty, values)| PrimaryExpr::Object(ty, values));
 
@@ -706,7 +1137,7 @@ fn primary_expr<'a>(
             }
         });
 
-    let raise_expr = just("raise").padded().ignore_then(
+    let raise_expr = keyword("raise").padded_by(padding()).ignore_then(
         identifier()
             .map(|i| vec![i])
             .foldl(
@@ -740,10 +1171,36 @@ fn primary_expr<'a>(
         .padded_by(just('"'))
         .map(PrimaryExpr::StringLiteral);
 
+    let escape = just('\\').ignore_then(choice((
+        just('n').to('\n'),
+        just('r').to('\r'),
+        just('t').to('\t'),
+        just('0').to('\0'),
+        just('\\'),
+        just('\''),
+        just('"'),
+    )));
+
+    let char_literal = none_of("\\'\n")
+        .or(escape)
+        .repeated()
+        .collect::<Vec<char>>()
+        .delimited_by(just('\''), just('\''))
+        .try_map(|chars, span| match chars[..] {
+            [c] => Ok(PrimaryExpr::Char(c as u32)),
+            _ => Err(Rich::custom(
+                span,
+                "a character literal must contain exactly one character",
+            )),
+        });
+
     let tuple = expr_parser
-        .separated_by(just(',').padded())
+        .separated_by(just(',').padded_by(padding()))
         .collect::<Vec<_>>()
-        .delimited_by(just('(').padded(), just(')').padded())
+        .delimited_by(
+            just('(').padded_by(padding()),
+            just(')').padded_by(padding()),
+        )
         .map(|vals| PrimaryExpr::Tuple(vals));
 
     choice((
@@ -755,14 +1212,35 @@ fn primary_expr<'a>(
         object,
         ident,
         string_literal,
+        char_literal,
         tuple,
     ))
     .boxed()
 }
 
+/// Words that can't be used as identifiers.
+///
+/// `resume` is still the name of the method that resumes a UTXO, which
+/// [`field_access_expr`] accepts after a `.`.
+const KEYWORDS: &[&str] = &[
+    "break", "const", "continue", "else", "enum", "false", "fn", "for", "if", "in", "let", "loop",
+    "match", "mut", "raise", "resume", "return", "true", "try", "typedef", "while", "with",
+    "yield",
+];
+
+/// `word` as a whole identifier, so that `whiles` isn't read as `while`
+/// followed by `s`.
+fn keyword<'a>(
+    word: &'static str,
+) -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+    text::ident()
+        .filter(move |ident: &&str| *ident == word)
+        .ignored()
+}
+
 fn reserved_word<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> {
-    choice((just("enum"), just("typedef"), just("loop")))
-        .padded()
+    text::ident()
+        .filter(|ident: &&str| KEYWORDS.contains(ident))
         .ignored()
 }
 
@@ -774,46 +1252,74 @@ fn identifier<'a>() -> impl Parser<'a, &'a str, Identifier, extra::Err<Rich<'a,
 
 fn typedef<'a>() -> impl Parser<'a, &'a str, TypeDef, extra::Err<Rich<'a, char>>> {
     let typed_bindings = typed_binding(r#type_arg())
-        .separated_by(just(',').padded())
+        .separated_by(just(',').padded_by(padding()))
         .collect::<Vec<_>>()
         .boxed();
 
     let object = typed_bindings
         .clone()
-        .delimited_by(just('{').padded(), just('}').padded())
+        .delimited_by(
+            just('{').padded_by(padding()),
+            just('}').padded_by(padding()),
+        )
         .map(|values| TypeDefRhs::Object(TypedBindings { values }))
         .boxed();
 
-    let variant = just("enum")
+    let variant = keyword("enum")
         .ignore_then(
             identifier()
-                .padded()
+                .padded_by(padding())
                 .then(
                     typed_bindings
                         .map(|values| TypedBindings { values })
-                        .delimited_by(just('(').padded(), just(')').padded()),
+                        .delimited_by(
+                            just('(').padded_by(padding()),
+                            just(')').padded_by(padding()),
+                        ),
+                )
+                .then(
+                    just('=')
+                        .padded_by(padding())
+                        .ignore_then(text::int(10).try_map(|s: &str, span| {
+                            s.parse()
+                                .map_err(|_| Rich::custom(span, "discriminant out of range"))
+                        }))
+                        .padded_by(padding())
+                        .or_not(),
                 )
-                .separated_by(just(',').padded())
+                .map(|((name, bindings), discriminant)| (name, bindings, discriminant))
+                .separated_by(just(',').padded_by(padding()))
                 .collect::<Vec<_>>()
-                .delimited_by(just('{').padded(), just('}').padded()),
+                .delimited_by(
+                    just('{').padded_by(padding()),
+                    just('}').padded_by(padding()),
+                ),
         )
         .map(|values| TypeDefRhs::Variant(Variant(values)))
         .boxed();
 
     let type_arg = type_arg().map(TypeDefRhs::TypeArg).boxed();
 
-    just("typedef")
-        .ignore_then(identifier().padded())
-        .then_ignore(just("=").padded())
+    keyword("typedef")
+        .ignore_then(identifier().padded_by(padding()))
+        .then_ignore(just("=").padded_by(padding()))
         .then(choice((object, variant, type_arg)))
         .map(|(name, ty)| TypeDef { name, ty })
 }
 
-fn constant<'a>() -> impl Parser<'a, &'a str, (Identifier, f64), extra::Err<Rich<'a, char>>> {
-    just("const")
-        .ignore_then(identifier().padded())
-        .then_ignore(just("=").padded())
-        .then(text::int(10).to_slice().map(|s: &str| s.parse().unwrap()))
+fn constant<'a>()
+-> impl Parser<'a, &'a str, ((Identifier, Option<TypeArg>), Spanned<Expr>), extra::Err<Rich<'a, char>>>
+{
+    keyword("const")
+        .ignore_then(identifier().padded_by(padding()))
+        .then(
+            just(":")
+                .padded_by(padding())
+                .ignore_then(type_arg().padded_by(padding()))
+                .or_not(),
+        )
+        .then_ignore(just("=").padded_by(padding()))
+        .then(expr(block().boxed()).padded_by(padding()))
         .then_ignore(just(";"))
 }
 
@@ -821,55 +1327,66 @@ fn type_arg<'a>() -> impl Parser<'a, &'a str, TypeArg, extra::Err<Rich<'a, char>
     let mut type_parser = Recursive::declare();
 
     type_parser.define({
-        let bool = just("bool").to(TypeArg::Bool);
+        let bool = keyword("bool").to(TypeArg::Bool);
 
-        let p_f32 = just("f32").to(TypeArg::F32);
-        let p_f64 = just("f64").to(TypeArg::F64);
-        let p_u32 = just("u32").to(TypeArg::U32);
-        let p_u64 = just("u64").to(TypeArg::U64);
-        let p_i32 = just("i32").to(TypeArg::I32);
-        let p_i64 = just("i64").to(TypeArg::I64);
+        let p_f32 = keyword("f32").to(TypeArg::F32);
+        let p_f64 = keyword("f64").to(TypeArg::F64);
+        let p_u32 = keyword("u32").to(TypeArg::U32);
+        let p_u64 = keyword("u64").to(TypeArg::U64);
+        let p_i32 = keyword("i32").to(TypeArg::I32);
+        let p_i64 = keyword("i64").to(TypeArg::I64);
+        let p_u128 = keyword("u128").to(TypeArg::U128);
+        let p_i128 = keyword("i128").to(TypeArg::I128);
 
-        let string = just("string").to(TypeArg::String);
+        let string = keyword("string").to(TypeArg::String);
 
-        let intermediate = just("Intermediate")
-            .padded()
+        let intermediate = keyword("Intermediate")
+            .padded_by(padding())
             .ignore_then(
                 type_parser
                     .clone()
                     .map(Box::new)
-                    .then_ignore(just(',').padded())
+                    .then_ignore(just(',').padded_by(padding()))
                     .then(type_parser.clone().map(Box::new))
-                    .delimited_by(just('<').padded(), just('>').padded())
+                    .delimited_by(
+                        just('<').padded_by(padding()),
+                        just('>').padded_by(padding()),
+                    )
                     .clone(),
             )
             .map(|(abi, storage)| TypeArg::Intermediate { abi, storage });
 
-        let type_ref = identifier().padded().map(TypeRef).boxed();
+        let type_ref = identifier().padded_by(padding()).map(TypeRef).boxed();
 
         let type_application = type_ref
             .clone()
             .then(
                 type_parser
                     .clone()
-                    .separated_by(just(',').padded())
+                    .separated_by(just(',').padded_by(padding()))
                     .collect::<Vec<_>>()
-                    .delimited_by(just('<').padded(), just('>').padded()),
+                    .delimited_by(
+                        just('<').padded_by(padding()),
+                        just('>').padded_by(padding()),
+                    ),
             )
             .map(|(base, params)| TypeArg::TypeApplication(base, params))
             .boxed();
 
         let typed_bindings = typed_binding(type_parser.clone())
-            .separated_by(just(',').padded())
+            .separated_by(just(',').padded_by(padding()))
             .collect::<Vec<_>>()
             .boxed();
 
         let fn_type = typed_bindings
             .clone()
-            .delimited_by(just('(').padded(), just(')').padded())
+            .delimited_by(
+                just('(').padded_by(padding()),
+                just(')').padded_by(padding()),
+            )
             .then(
                 just("->")
-                    .padded()
+                    .padded_by(padding())
                     .ignore_then(type_parser.clone())
                     .or_not(),
             )
@@ -881,6 +1398,34 @@ fn type_arg<'a>() -> impl Parser<'a, &'a str, TypeArg, extra::Err<Rich<'a, char>
             })
             .boxed();
 
+        let tuple = type_parser
+            .clone()
+            .separated_by(just(',').padded_by(padding()))
+            .at_least(2)
+            .collect::<Vec<_>>()
+            .delimited_by(
+                just('(').padded_by(padding()),
+                just(')').padded_by(padding()),
+            )
+            .map(TypeArg::Tuple)
+            .boxed();
+
+        // expressions contain types themselves, so the length's parser is
+        // only built once an array type is found
+        let array_len = custom(|input| input.parse(expr(block().boxed())));
+
+        let array = type_parser
+            .clone()
+            .map(Box::new)
+            .then_ignore(just(';').padded_by(padding()))
+            .then(array_len.padded_by(padding()))
+            .delimited_by(
+                just('[').padded_by(padding()),
+                just(']').padded_by(padding()),
+            )
+            .map(|(elem, len)| TypeArg::Array(elem, ArrayLen::new(len)))
+            .boxed();
+
         choice((
             bool,
             p_f32,
@@ -889,9 +1434,13 @@ fn type_arg<'a>() -> impl Parser<'a, &'a str, TypeArg, extra::Err<Rich<'a, char>
             p_i32,
             p_u64,
             p_i64,
+            p_u128,
+            p_i128,
             string,
             intermediate,
             fn_type,
+            tuple,
+            array,
             type_application,
             type_ref.map(TypeArg::TypeRef),
         ))
@@ -901,6 +1450,71 @@ fn type_arg<'a>() -> impl Parser<'a, &'a str, TypeArg, extra::Err<Rich<'a, char>
     type_parser
 }
 
+/// Consecutive `///` lines, as the text of the doc comment they form.
+///
+/// `None` if there are none, or if a blank line separates them from what
+/// follows, since then they don't document it.
+fn doc_comment<'a>() -> impl Parser<'a, &'a str, Option<String>, extra::Err<Rich<'a, char>>> {
+    let line = text::inline_whitespace()
+        .ignore_then(just("///"))
+        .ignore_then(any().and_is(text::newline().not()).repeated().to_slice())
+        .then_ignore(text::newline());
+    let blank_line = text::inline_whitespace().then(text::newline());
+
+    line.repeated()
+        .collect::<Vec<&str>>()
+        .then(blank_line.or_not())
+        .then_ignore(text::whitespace())
+        .map(|(lines, blank_line)| {
+            (!lines.is_empty() && blank_line.is_none()).then(|| {
+                lines
+                    .iter()
+                    .map(|line| line.strip_prefix(' ').unwrap_or(line))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        })
+}
+
+/// Whitespace, `//` comments and `/* */` comments, which can go anywhere
+/// whitespace can.
+///
+/// `///` doc comments are left for [`doc_comment`] to pick up.
+fn padding<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+    let line_comment = just("//")
+        .then(just('/').not())
+        .then(any().and_is(text::newline().not()).repeated())
+        .ignored();
+
+    let comment = line_comment.or(block_comment());
+
+    text::whitespace()
+        .then(comment.then(text::whitespace()).repeated())
+        .ignored()
+}
+
+/// `/* ... */`, which can nest.
+///
+/// One left open runs to the end of the file, and is reported at its `/*`.
+fn block_comment<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone {
+    recursive(|block_comment| {
+        let commented = any().and_is(just("*/").not()).ignored();
+
+        just("/*")
+            .ignore_then(block_comment.or(commented).repeated())
+            .ignore_then(just("*/").or_not())
+            .validate(|close, extra, emitter| {
+                if close.is_none() {
+                    let start = extra.span().start;
+                    emitter.emit(Rich::custom(
+                        SimpleSpan::from(start..start + 2),
+                        "unterminated block comment",
+                    ));
+                }
+            })
+    })
+}
+
 fn comment<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> {
     just("//")
         .padded()
@@ -940,6 +1554,137 @@ mod tests {
 
         let input = r#"foo("bar").x(3, 4).y(1, 2, 3)"#;
         test_with_diagnostics(input, field_access_expr(expr(block().boxed()).boxed()));
+
+        let input = "a[2]";
+        let access = test_with_diagnostics(input, field_access_expr(expr(block().boxed()).boxed()));
+        assert!(matches!(access, FieldAccessExpression::Index { .. }));
+
+        let input = "storage.values[i + 1].x";
+        let access = test_with_diagnostics(input, field_access_expr(expr(block().boxed()).boxed()));
+        let FieldAccessExpression::FieldAccess { base, .. } = access else {
+            panic!("expected a field access");
+        };
+        assert!(matches!(*base, FieldAccessExpression::Index { .. }));
+    }
+
+    #[test]
+    fn parse_named_arguments() {
+        let parser = || application(expr(block().boxed()).boxed());
+
+        let args = test_with_diagnostics("(1, amount: 2, owner: Token::new(3))", parser());
+        assert_eq!(args.xs.len(), 1);
+        let names = args.named.iter().map(|(name, _)| name.raw.as_str());
+        assert_eq!(names.collect::<Vec<_>>(), ["amount", "owner"]);
+
+        // a namespaced positional argument isn't mistaken for a name
+        let args = test_with_diagnostics("(Token::new(3), x)", parser());
+        assert_eq!(args.xs.len(), 2);
+        assert!(args.named.is_empty());
+
+        // positional arguments can't follow named ones
+        assert!(parser().parse("(amount: 2, 1)").has_errors());
+    }
+
+    #[test]
+    fn parse_integer_literal() {
+        let parser = || primary_expr(expr(block().boxed()).boxed());
+
+        for (input, expected) in [
+            ("42", 42),
+            ("0xdeadbeef", 0xdeadbeef),
+            ("0xFF", 0xff),
+            ("0b1111", 0b1111),
+            ("0o17", 0o17),
+        ] {
+            let literal = test_with_diagnostics(input, parser());
+            assert!(
+                matches!(literal, PrimaryExpr::Number { literal, .. } if literal == expected),
+                "{input}: {literal:?}"
+            );
+        }
+
+        // out of range literals are errors rather than wrapping around
+        for input in ["0x100000000", "99999999999999999999", "-1"] {
+            let (_, errors) = parser().parse(input).into_output_errors();
+            assert!(!errors.is_empty(), "{input}");
+        }
+    }
+
+    #[test]
+    fn parse_number_suffix() {
+        let parser = || primary_expr(expr(block().boxed()).boxed());
+
+        for (input, expected, expected_ty) in [
+            ("5u32", 5, TypeArg::U32),
+            ("-3i32", -3, TypeArg::I32),
+            ("0xffffffffffffffffu64", u64::MAX.into(), TypeArg::U64),
+            ("-9223372036854775808i64", i64::MIN.into(), TypeArg::I64),
+        ] {
+            let literal = test_with_diagnostics(input, parser());
+            assert!(
+                matches!(&literal, PrimaryExpr::TypedNumber(literal, ty) if *literal == expected && *ty == expected_ty),
+                "{input}: {literal:?}"
+            );
+        }
+
+        // the suffix decides the range
+        for input in ["4294967296u32", "-1u32", "2147483648i32", "-1u64"] {
+            let (_, errors) = parser().parse(input).into_output_errors();
+            assert!(!errors.is_empty(), "{input}");
+        }
+    }
+
+    #[test]
+    fn parse_typed_constant() {
+        let input = "
+            const MAX: u64 = 5;
+            const N = 4;
+        ";
+
+        let program = test_with_diagnostics(input, starstream_program());
+
+        assert!(matches!(
+            &program.items[..],
+            [
+                Item {
+                    node: ProgramItem::Constant {
+                        ty: Some(TypeArg::U64),
+                        ..
+                    },
+                    ..
+                },
+                Item {
+                    node: ProgramItem::Constant { ty: None, .. },
+                    ..
+                },
+            ]
+        ));
+    }
+
+    #[test]
+    fn parse_char_literal() {
+        let parser = || primary_expr(expr(block().boxed()).boxed());
+
+        for (input, expected) in [
+            ("'a'", 'a'),
+            ("'\\n'", '\n'),
+            ("'\\''", '\''),
+            ("'\\\\'", '\\'),
+            ("'é'", 'é'),
+        ] {
+            let literal = test_with_diagnostics(input, parser());
+            assert!(
+                matches!(literal, PrimaryExpr::Char(c) if c == expected as u32),
+                "{input}: {literal:?}"
+            );
+        }
+
+        let errors = parser().parse("'ab'").into_errors();
+        assert!(errors.iter().any(|e| matches!(
+            e.reason(),
+            chumsky::error::RichReason::Custom(msg) if msg.contains("exactly one character")
+        )));
+        assert!(parser().parse("''").has_errors());
     }
 
     #[test]
@@ -958,6 +1703,63 @@ mod tests {
 
         let input = "Type::func(3)";
         test_with_diagnostics(input, expr(block().boxed()));
+
+        let input = "7 % 3";
+        let output = test_with_diagnostics(input, expr(block().boxed()));
+        assert!(matches!(output.node, Expr::Mod(..)));
+
+        let input = "a >= b";
+        let output = test_with_diagnostics(input, expr(block().boxed()));
+        assert!(matches!(output.node, Expr::GreaterEq(..)));
+    }
+
+    #[test]
+    fn parse_keyword_boundaries() {
+        let input = "let whiles: u32x = letter;";
+        let output = test_with_diagnostics(input, statement(expr(block().boxed()), block()));
+        assert!(matches!(
+            output,
+            Statement::BindVar { ref var, mutable: false, ty: Some(TypeArg::TypeRef(ref ty)), .. }
+                if var.raw == "whiles" && ty.0.raw == "u32x"
+        ));
+
+        let input = "let mutable = 1;";
+        let output = test_with_diagnostics(input, statement(expr(block().boxed()), block()));
+        assert!(matches!(
+            output,
+            Statement::BindVar { ref var, mutable: false, .. } if var.raw == "mutable"
+        ));
+
+        assert!(identifier().parse("while").has_errors());
+        assert!(identifier().parse("yield").has_errors());
+        assert!(
+            statement(expr(block().boxed()), block())
+                .parse("let while = 1;")
+                .has_errors()
+        );
+    }
+
+    #[test]
+    fn parse_resume_keyword() {
+        assert!(identifier().parse("resume").has_errors());
+        assert!(
+            statement(expr(block().boxed()), block())
+                .parse("let resume = 1;")
+                .has_errors()
+        );
+
+        let input = "resume x;";
+        let output = test_with_diagnostics(input, statement(expr(block().boxed()), block()));
+        assert!(matches!(output, Statement::Resume(Some(_))));
+
+        // still the name of the method that resumes a UTXO
+        let input = "input.resume(())";
+        let output = test_with_diagnostics(input, field_access_expr(expr(block().boxed())));
+        assert!(matches!(
+            output,
+            FieldAccessExpression::FieldAccess { ref field, .. }
+                if field.name.raw == "resume" && field.args.is_some()
+        ));
     }
 
     #[test]
@@ -974,6 +1776,108 @@ mod tests {
         test_with_diagnostics(input, main());
     }
 
+    #[test]
+    fn parse_labeled_loops() {
+        let input = "'outer: loop { loop { break 'outer; } }";
+        let output = test_with_diagnostics(input, statement(expr(block().boxed()), block()));
+
+        assert!(matches!(
+            output,
+            Statement::Labeled(LoopLabel { ref name, .. }, ref inner)
+                if name == "outer" && matches!(**inner, Statement::Loop(_))
+        ));
+
+        let input = "'retry: while (x) { continue 'retry; }";
+        let output = test_with_diagnostics(input, statement(expr(block().boxed()), block()));
+
+        assert!(matches!(
+            output,
+            Statement::Labeled(_, ref inner) if matches!(**inner, Statement::While(..))
+        ));
+
+        let input = "'rows: for i in 0..n { continue 'rows; }";
+        let output = test_with_diagnostics(input, statement(expr(block().boxed()), block()));
+
+        assert!(matches!(
+            output,
+            Statement::Labeled(_, ref inner) if matches!(**inner, Statement::For { .. })
+        ));
+
+        let input = "break;";
+        let output = test_with_diagnostics(input, statement(expr(block().boxed()), block()));
+
+        assert!(matches!(output, Statement::Break { label: None, .. }));
+
+        let input = "continue 'outer;";
+        let output = test_with_diagnostics(input, statement(expr(block().boxed()), block()));
+
+        assert!(matches!(
+            output,
+            Statement::Continue { label: Some(LoopLabel { ref name, .. }), .. } if name == "outer"
+        ));
+    }
+
+    #[test]
+    fn parse_for_loop() {
+        let input = "for i in 0..n + 1 { total = total + i; }";
+        let output = test_with_diagnostics(input, statement(expr(block().boxed()), block()));
+
+        let Statement::For {
+            var,
+            start,
+            end,
+            body,
+        } = output
+        else {
+            panic!("expected a for loop");
+        };
+        assert_eq!(var.raw, "i");
+        assert!(matches!(start.node, Expr::PrimaryExpr(_)));
+        assert!(matches!(end.node, Expr::Add(..)));
+        assert!(matches!(body, LoopBody::Block(_)));
+
+        // `in` is reserved, so it can't be the loop variable
+        assert!(
+            statement(expr(block().boxed()), block())
+                .parse("for in in 0..1 {}")
+                .has_errors()
+        );
+    }
+
+    #[test]
+    fn parse_loop_expression() {
+        let input = "let x = loop { break 5; };";
+        let output = test_with_diagnostics(input, statement(expr(block().boxed()), block()));
+
+        let Statement::BindVar { value, .. } = output else {
+            panic!("expected a let");
+        };
+        let Expr::BlockExpr(BlockExpr::Loop(LoopBody::Block(Block::Chain { head, .. }))) =
+            &value.node
+        else {
+            panic!("expected a loop expression");
+        };
+        assert!(matches!(
+            **head,
+            ExprOrStatement::Statement(Statement::Break { value: Some(_), .. })
+        ));
+
+        let input = "break 'outer x + 1;";
+        let output = test_with_diagnostics(input, statement(expr(block().boxed()), block()));
+
+        assert!(matches!(
+            output,
+            Statement::Break {
+                label: Some(_),
+                value: Some(Spanned {
+                    node: Expr::Add(..),
+                    ..
+                }),
+                ..
+            }
+        ));
+    }
+
     #[test]
     fn parse_block() {
         let input = "{ 4 }";
@@ -998,16 +1902,127 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_else_if() {
+        let input = "{ if (a) { 1 } else if (b) { 2 } else { 3 } }";
+        let output = test_with_diagnostics(input, block());
+
+        let Block::Chain { head, .. } = output else {
+            unreachable!()
+        };
+        let ExprOrStatement::Expr(Spanned {
+            node: Expr::BlockExpr(BlockExpr::IfThenElse(_, _, Some(otherwise))),
+            ..
+        }) = *head
+        else {
+            unreachable!()
+        };
+        let Block::Chain { head, tail } = *otherwise else {
+            unreachable!()
+        };
+        assert!(matches!(
+            *head,
+            ExprOrStatement::Expr(Spanned {
+                node: Expr::BlockExpr(BlockExpr::IfThenElse(_, _, Some(_))),
+                ..
+            })
+        ));
+        assert!(matches!(*tail, Block::Close { semicolon: false }));
+    }
+
     #[test]
     fn parse_storage() {
         let input = "storage { x: BigInt; y: F32; }";
         test_with_diagnostics(input, storage());
     }
 
+    #[test]
+    fn parse_trailing_comments() {
+        let input = "storage {
+            // the owner
+            owner: PublicKey; // who can spend it
+            count: u32 = 3 // starting value
+                + 4; // plus some more
+        }";
+        let storage = test_with_diagnostics(input, storage());
+        assert_eq!(storage.bindings.values.len(), 2);
+
+        let input = "abi Abi { // entry points
+            fn foo(): u32; // a query
+            fn bar(
+                Value, // the input
+                u32
+            ); // a mutation
+            effect Effect1(Value): u32; // an effect
+        }";
+        let abi = test_with_diagnostics(input, abi());
+        assert_eq!(abi.values.len(), 3);
+
+        let input = "(u32, // first
+            bool)";
+        test_with_diagnostics(input, type_arg());
+
+        let input = "script { fn f() { let x = g(1, // first
+            2); x } }";
+        test_with_diagnostics(input, script());
+    }
+
+    #[test]
+    fn parse_block_comments() {
+        let input = "/* header /* with a nested */ comment */
+            script {
+                /* before */ fn f(/* no arguments */): u32 /* after */ {
+                    1 /* plus */ + /* one */ 1
+                }
+            }
+            utxo U {
+                storage {
+                    count: u32; /* outer /* inner */ */
+                }
+            }";
+        let program = test_with_diagnostics(input, starstream_program());
+        assert_eq!(program.items.len(), 2);
+
+        // the error points at the `/*` that isn't closed
+        let input = "script { fn f() {} }\n/* open /* closed */";
+        let errors = starstream_program().parse(input).into_errors();
+        let opening = input.find("/* open").unwrap();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.span().start == opening && e.span().end == opening + 2),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn parse_storage_initializer() {
+        let input =
+            "storage { owner: PublicKey = default_owner(); count: u32; limit: u32 = 3 + 4; }";
+        let storage = test_with_diagnostics(input, storage());
+
+        assert_eq!(storage.bindings.values.len(), 3);
+        assert!(matches!(
+            storage.initializers[0].as_ref().map(|init| &init.node),
+            Some(Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(
+                PrimaryExpr::Ident(IdentifierExpr { args: Some(_), .. })
+            )))
+        ));
+        assert!(storage.initializers[1].is_none());
+        assert!(matches!(
+            storage.initializers[2].as_ref().map(|init| &init.node),
+            Some(Expr::Add(_, _))
+        ));
+    }
+
     #[test]
     fn parse_abi() {
         let input = "abi Abi { fn foo(): number; fn bar(Value); effect Effect1(Value): number; }";
         test_with_diagnostics(input, abi());
+
+        let input = "abi Abi { fn foo(): number; fn bar(x: Value): number { 3 } }";
+        let abi = test_with_diagnostics(input, abi());
+        assert!(matches!(abi.values[1], AbiElem::FnDef(_)));
     }
 
     #[test]
@@ -1016,6 +2031,17 @@ mod tests {
         test_with_diagnostics(input, r#impl());
     }
 
+    #[test]
+    fn parse_impl_associated_constant() {
+        let input = "impl Contract { const ID: u64 = 1003; fn id(): u64 { Contract::ID } const TWICE: u64 = ID * 2; }";
+        let r#impl = test_with_diagnostics(input, r#impl());
+        assert_eq!(r#impl.constants.len(), 2);
+        assert_eq!(r#impl.constants[0].name.raw, "ID");
+        assert!(matches!(r#impl.constants[0].ty, TypeArg::U64));
+        assert_eq!(r#impl.constants[1].name.raw, "TWICE");
+        assert_eq!(r#impl.definitions.len(), 1);
+    }
+
     #[test]
     fn parse_token() {
         let input = "token Token1 { bind { let mut caller = 3; } unbind { let x = 4 + 5; } }";
@@ -1034,6 +2060,229 @@ mod tests {
         test_with_diagnostics(input, starstream_program());
     }
 
+    #[test]
+    fn parse_program_recovers_from_broken_items() {
+        let input = "script {
+    fn f() { 1 + }
+}
+
+abi Abi {}
+
+utxo U {
+    main { let = 2; }
+}
+
+script { fn g() {} }
+";
+
+        let (program, reports) = crate::parse_program_collecting_errors(input);
+        assert_eq!(reports.len(), 2);
+
+        // the items around the broken ones are kept
+        let program = program.unwrap();
+        assert!(matches!(
+            program.items[..],
+            [
+                Item {
+                    node: ProgramItem::Abi(_),
+                    ..
+                },
+                Item {
+                    node: ProgramItem::Script(_),
+                    ..
+                },
+            ]
+        ));
+    }
+
+    #[test]
+    fn parse_when_attribute() {
+        let input = "#[when(debug)] script {} #[ when ( test ) ]\nabi Abi {} utxo U { main {} }";
+        let program = test_with_diagnostics(input, starstream_program());
+
+        let when = program
+            .items
+            .iter()
+            .map(|item| item.when.as_ref().map(|f| f.raw.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(when, [Some("debug"), Some("test"), None]);
+    }
+
+    #[test]
+    fn parse_doc_comments() {
+        let input = "
+            /// Entry points.
+            ///
+            ///  Indented.
+            script {
+                /// Does nothing.
+                fn f() {}
+
+                /// Not about g.
+
+                fn g() {}
+            }
+            utxo U {
+                storage {
+                    /// The count.
+                    count: u32;
+                }
+            }
+        ";
+        let program = test_with_diagnostics(input, starstream_program());
+
+        let docs = program
+            .items
+            .iter()
+            .map(|item| item.docs.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(docs, [Some("Entry points.\n\n Indented."), None]);
+
+        let ProgramItem::Script(script) = &program.items[0].node else {
+            panic!("expected a script");
+        };
+        assert_eq!(script.definitions[0].docs.as_deref(), Some("Does nothing."));
+        assert_eq!(script.definitions[1].docs, None);
+
+        let ProgramItem::Utxo(utxo) = &program.items[1].node else {
+            panic!("expected a utxo");
+        };
+        let UtxoItem::Storage(storage) = &utxo.items[0] else {
+            panic!("expected storage");
+        };
+        assert_eq!(storage.docs, [Some("The count.".to_owned())]);
+    }
+
+    #[test]
+    fn parse_match_expression() {
+        let input = "script { fn f(e: E): u32 { let y = match (e) { One() => { 1 }, Two(x, _y) => { x }, }; y } }";
+        let program = test_with_diagnostics(input, starstream_program());
+
+        let ProgramItem::Script(script) = &program.items[0].node else {
+            panic!("expected a script");
+        };
+        let Block::Chain { head, .. } = &script.definitions[0].body else {
+            panic!("expected a statement");
+        };
+        let ExprOrStatement::Statement(Statement::BindVar { value, .. }) = &**head else {
+            panic!("expected a let");
+        };
+        let Expr::BlockExpr(BlockExpr::Match(_, arms)) = &value.node else {
+            panic!("expected a match expression");
+        };
+        assert_eq!(arms.len(), 2);
+        assert_eq!(arms[0].variant.raw, "One");
+        assert!(arms[0].bindings.is_empty());
+        assert_eq!(
+            arms[1]
+                .bindings
+                .iter()
+                .map(|binding| binding.raw.as_str())
+                .collect::<Vec<_>>(),
+            ["x", "_y"]
+        );
+
+        // still a statement without a trailing `;`
+        test_with_diagnostics(
+            "script { fn f(e: E) { match (e) { One() => { g(); } } h(); } }",
+            starstream_program(),
+        );
+    }
+
+    #[test]
+    fn parse_try_expression() {
+        let input = "script { fn f(): u32 { let total = try { g() } with A::E(x: u32) { resume x; }; total } }";
+        let program = test_with_diagnostics(input, starstream_program());
+
+        let ProgramItem::Script(script) = &program.items[0].node else {
+            panic!("expected a script");
+        };
+        let Block::Chain { head, .. } = &script.definitions[0].body else {
+            panic!("expected a statement");
+        };
+        let ExprOrStatement::Statement(Statement::BindVar { value, .. }) = &**head else {
+            panic!("expected a let");
+        };
+        let Expr::BlockExpr(BlockExpr::TryWith(_, handlers)) = &value.node else {
+            panic!("expected a try expression");
+        };
+        assert_eq!(handlers.len(), 1);
+
+        // still a statement without a trailing `;`
+        test_with_diagnostics(
+            "script { fn f() { try { g(); } with A::E() { resume; } h(); } }",
+            starstream_program(),
+        );
+        // `try` is only a keyword on its own
+        test_with_diagnostics("script { fn f() { try_this(); } }", starstream_program());
+    }
+
+    #[test]
+    fn reparse_single_item() {
+        let input = "abi Abi {}\nscript { fn f() { 1 } }\nutxo Contract { main {} }";
+        let mut program = test_with_diagnostics(input, starstream_program());
+        assert_eq!(program.items.len(), 3);
+        // item spans include the whitespace the item parsers consume
+        assert_eq!(
+            input[program.items[1].span.into_range()].trim(),
+            "script { fn f() { 1 } }"
+        );
+
+        let untouched =
+            |program: &StarstreamProgram| [0, 2].map(|i| format!("{:?}", program.items[i].node));
+        let before = untouched(&program);
+
+        let edited = input.replace("{ 1 }", "{ 1 + 22 }");
+        let index = program.item_at(input.find("1 }").unwrap()).unwrap();
+        assert_eq!(index, 1);
+
+        let old = program.items[index].span;
+        let span = SimpleSpan::from(old.start..old.end + 5);
+        let item = crate::reparse_item(&edited, span).unwrap();
+        program.replace_item(index, item);
+
+        assert_eq!(untouched(&program), before);
+        assert_eq!(
+            edited[program.items[2].span.into_range()].trim(),
+            "utxo Contract { main {} }"
+        );
+
+        let ProgramItem::Script(script) = &program.items[1].node else {
+            panic!("expected a script");
+        };
+        let body = format!("{:?}", script.definitions[0].body);
+        assert!(body.contains("Add"));
+        assert!(body.contains("22"));
+
+        // the doc comment and attribute are re-parsed along with the item
+        let annotated = edited.replace("script", "/// Scripts.\n#[when(debug)]\nscript");
+        let span = SimpleSpan::from(old.start..old.end + 5 + 28);
+        let item = crate::reparse_item(&annotated, span).unwrap();
+        program.replace_item(index, item);
+        assert_eq!(program.items[1].docs.as_deref(), Some("Scripts."));
+        assert_eq!(
+            program.items[1].when.as_ref().map(|f| f.raw.as_str()),
+            Some("debug")
+        );
+        assert_eq!(
+            annotated[program.items[2].span.into_range()].trim(),
+            "utxo Contract { main {} }"
+        );
+        assert_eq!(program.item_at(annotated.find("///").unwrap()), Some(1));
+
+        // errors point into the whole file
+        let broken = input.replace("{ 1 }", "{ 1 + }");
+        let span = SimpleSpan::from(old.start..old.end + 2);
+        let errors = crate::reparse_item(&broken, span).unwrap_err();
+        assert!(!errors.is_empty());
+
+        // so do spans that no longer fit the file
+        let span = SimpleSpan::from(old.start..broken.len() + 1);
+        assert!(crate::reparse_item(&broken, span).is_err());
+        let span = SimpleSpan::from(old.end..old.start);
+        assert!(crate::reparse_item(&broken, span).is_err());
+    }
+
     #[test]
     fn parse_type_arg() {
         let input = "Intermediate<T, any>";
@@ -1044,6 +2293,38 @@ mod tests {
 
         let input = "(x: Int)";
         test_with_diagnostics(input, type_arg());
+
+        let input = "(u32, (bool, Int))";
+        let ty = test_with_diagnostics(input, type_arg());
+
+        assert!(matches!(ty, TypeArg::Tuple(elems) if elems.len() == 2));
+
+        let ty = test_with_diagnostics("u128", type_arg());
+        assert_eq!(ty, TypeArg::U128);
+
+        let ty = test_with_diagnostics("i128", type_arg());
+        assert_eq!(ty, TypeArg::I128);
+
+        let ty = test_with_diagnostics("[u32; 4]", type_arg());
+        let TypeArg::Array(elem, len) = ty else {
+            panic!("expected an array");
+        };
+        assert_eq!(*elem, TypeArg::U32);
+        assert!(matches!(
+            len.expr.node,
+            Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(PrimaryExpr::Number {
+                literal: 4,
+                ty: None
+            }))
+        ));
+
+        // lengths are constant expressions, evaluated while typechecking
+        let ty = test_with_diagnostics("[[bool; N]; (N + 1) * 2]", type_arg());
+        let TypeArg::Array(elem, len) = ty else {
+            panic!("expected an array");
+        };
+        assert!(matches!(*elem, TypeArg::Array(..)));
+        assert!(matches!(len.expr.node, Expr::Mul(..)));
     }
 
     #[test]
@@ -1051,6 +2332,19 @@ mod tests {
         let input = "typedef E = enum { One(), Two(x:Int) }";
         test_with_diagnostics(input, typedef());
 
+        let input = "typedef E = enum { One() = 0, Two(x: Int) = 5, Three() }";
+        let def = test_with_diagnostics(input, typedef());
+        let TypeDefRhs::Variant(Variant(variants)) = def.ty else {
+            panic!("expected an enum");
+        };
+        let discriminants = variants.iter().map(|v| v.2).collect::<Vec<_>>();
+        assert_eq!(discriminants, [Some(0), Some(5), None]);
+
+        // discriminants that don't fit are errors rather than panics
+        let input = "typedef E = enum { One() = 99999999999999999999999 }";
+        let (_, errors) = typedef().parse(input).into_output_errors();
+        assert!(!errors.is_empty());
+
         let input = "typedef E = { x: Int, y: String }";
         test_with_diagnostics(input, typedef());
 
@@ -0,0 +1,1577 @@
+//! Pretty-printer turning a parsed AST back into Starstream source.
+//!
+//! The output is meant to be accepted by [`crate::starstream_program`] and
+//! parse back into the same tree. Parentheses are only inserted where the
+//! operator precedence of the parser requires them, so printing a reparsed
+//! tree yields the same text again.
+
+use crate::ast::*;
+
+/// Print a whole program as Starstream source.
+pub fn pretty_print(program: &StarstreamProgram) -> String {
+    let mut printer = Printer::default();
+    printer.program(program);
+    printer.out
+}
+
+/// Print a type as it would be written in Starstream source.
+pub(crate) fn pretty_type_arg(ty: &TypeArg) -> String {
+    let mut printer = Printer::default();
+    printer.type_arg(ty);
+    printer.out
+}
+
+/// Binding power of prefix operators in the parser.
+const PREFIX_PRECEDENCE: u8 = 10;
+/// Binding power of anything that parses as a single atom.
+const ATOM_PRECEDENCE: u8 = 11;
+
+fn binary(expr: &Expr) -> Option<(&Spanned<Expr>, &'static str, &Spanned<Expr>, u8)> {
+    let (lhs, op, rhs, precedence) = match expr {
+        Expr::Mul(lhs, rhs) => (lhs, "*", rhs, 9),
+        Expr::Div(lhs, rhs) => (lhs, "/", rhs, 9),
+        Expr::Mod(lhs, rhs) => (lhs, "%", rhs, 9),
+        Expr::Add(lhs, rhs) => (lhs, "+", rhs, 8),
+        Expr::Sub(lhs, rhs) => (lhs, "-", rhs, 8),
+        Expr::LShift(lhs, rhs) => (lhs, "<<", rhs, 7),
+        Expr::RShift(lhs, rhs) => (lhs, ">>", rhs, 7),
+        Expr::LessThan(lhs, rhs) => (lhs, "<", rhs, 6),
+        Expr::GreaterThan(lhs, rhs) => (lhs, ">", rhs, 6),
+        Expr::LessEq(lhs, rhs) => (lhs, "<=", rhs, 6),
+        Expr::GreaterEq(lhs, rhs) => (lhs, ">=", rhs, 6),
+        Expr::Equals(lhs, rhs) => (lhs, "==", rhs, 5),
+        Expr::NotEquals(lhs, rhs) => (lhs, "!=", rhs, 5),
+        Expr::BitAnd(lhs, rhs) => (lhs, "&", rhs, 4),
+        Expr::BitXor(lhs, rhs) => (lhs, "^", rhs, 3),
+        Expr::BitOr(lhs, rhs) => (lhs, "|", rhs, 2),
+        Expr::And(lhs, rhs) => (lhs, "&&", rhs, 1),
+        Expr::Or(lhs, rhs) => (lhs, "||", rhs, 0),
+        _ => return None,
+    };
+
+    Some((lhs, op, rhs, precedence))
+}
+
+fn prefix(expr: &Expr) -> Option<(&'static str, &Spanned<Expr>)> {
+    match expr {
+        Expr::Neg(operand) => Some(("-", operand)),
+        Expr::Not(operand) => Some(("!", operand)),
+        Expr::BitNot(operand) => Some(("~", operand)),
+        _ => None,
+    }
+}
+
+fn precedence(expr: &Expr) -> u8 {
+    if let Some((_, _, _, precedence)) = binary(expr) {
+        precedence
+    } else if prefix(expr).is_some() {
+        PREFIX_PRECEDENCE
+    } else {
+        ATOM_PRECEDENCE
+    }
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    indent: usize,
+}
+
+impl Printer {
+    fn push(&mut self, s: &str) {
+        self.out.push_str(s);
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("  ");
+        }
+    }
+
+    fn separated<T>(&mut self, items: &[T], separator: &str, mut f: impl FnMut(&mut Self, &T)) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.push(separator);
+            }
+            f(self, item);
+        }
+    }
+
+    fn program(&mut self, program: &StarstreamProgram) {
+        for (i, item) in program.items.iter().enumerate() {
+            if i > 0 {
+                self.push("\n");
+            }
+            self.docs(&item.docs);
+            if let Some(feature) = &item.when {
+                self.push("#[when(");
+                self.push(&feature.raw);
+                self.push(")]\n");
+            }
+            self.item(&item.node);
+            self.push("\n");
+        }
+    }
+
+    fn docs(&mut self, docs: &Option<String>) {
+        let Some(docs) = docs else {
+            return;
+        };
+        for line in docs.split('\n') {
+            self.push("///");
+            if !line.is_empty() {
+                self.push(" ");
+                self.push(line);
+            }
+            self.newline();
+        }
+    }
+
+    fn item(&mut self, item: &ProgramItem) {
+        match item {
+            ProgramItem::Abi(abi) => self.abi(abi),
+            ProgramItem::Script(script) => {
+                self.push("script ");
+                self.fn_defs(&script.definitions);
+            }
+            ProgramItem::Utxo(utxo) => self.utxo(utxo),
+            ProgramItem::Token(token) => self.token(token),
+            ProgramItem::TypeDef(typedef) => self.typedef(typedef),
+            ProgramItem::Constant { name, ty, value } => {
+                self.push("const ");
+                self.push(&name.raw);
+                if let Some(ty) = ty {
+                    self.push(": ");
+                    self.type_arg(ty);
+                }
+                self.push(" = ");
+                self.expr(value);
+                self.push(";");
+            }
+        }
+    }
+
+    /// Print `{ ... }` with one entry per line, or `{}` if there are none.
+    fn braced<T>(&mut self, entries: &[T], mut f: impl FnMut(&mut Self, &T)) {
+        if entries.is_empty() {
+            self.push("{}");
+            return;
+        }
+
+        self.push("{");
+        self.indent += 1;
+        for entry in entries {
+            self.newline();
+            f(self, entry);
+        }
+        self.indent -= 1;
+        self.newline();
+        self.push("}");
+    }
+
+    fn fn_defs(&mut self, definitions: &[FnDef]) {
+        self.braced(definitions, Self::fn_def);
+    }
+
+    fn abi(&mut self, abi: &Abi) {
+        self.push("abi ");
+        self.push(&abi.name.raw);
+        self.push(" ");
+        self.braced(&abi.values, |this, value| match value {
+            AbiElem::FnDecl(FnDecl(sig)) => {
+                this.push("fn ");
+                this.sig(sig);
+                this.push(";");
+            }
+            AbiElem::FnDef(definition) => this.fn_def(definition),
+            AbiElem::EffectDecl(decl) => {
+                let (keyword, sig) = match decl {
+                    EffectDecl::EffectSig(sig) => ("effect ", sig),
+                    EffectDecl::EventSig(sig) => ("event ", sig),
+                    EffectDecl::ErrorSig(sig) => ("error ", sig),
+                };
+                this.push(keyword);
+                this.sig(sig);
+                this.push(";");
+            }
+        });
+    }
+
+    fn sig(&mut self, sig: &Sig) {
+        self.push(&sig.name.raw);
+        self.push("(");
+        self.separated(&sig.input_types, ", ", Self::type_arg);
+        self.push(")");
+        if let Some(output) = &sig.output_type {
+            self.push(": ");
+            self.type_arg(output);
+        }
+    }
+
+    fn fn_def(&mut self, definition: &FnDef) {
+        self.docs(&definition.docs);
+        self.push("fn ");
+        self.push(&definition.ident.raw);
+        self.push("(");
+        self.separated(&definition.inputs, ", ", |this, input| {
+            this.push(&input.name.raw);
+            this.push(": ");
+            this.type_arg(&input.ty);
+        });
+        self.push(")");
+        if let Some(output) = &definition.output {
+            self.push(": ");
+            self.type_arg(output);
+        }
+        if !definition.effects.is_empty() {
+            self.push(" / { ");
+            self.separated(&definition.effects, ", ", |this, effect| {
+                this.push(&effect.raw)
+            });
+            self.push(" }");
+        }
+        self.push(" ");
+        self.block(&definition.body);
+    }
+
+    fn utxo(&mut self, utxo: &Utxo) {
+        self.push("utxo ");
+        self.push(&utxo.name.raw);
+        self.push(" ");
+        self.braced(&utxo.items, |this, item| match item {
+            UtxoItem::Main(main) => {
+                this.push("main");
+                if let Some(type_sig) = &main.type_sig {
+                    this.push("(");
+                    this.typed_bindings(type_sig);
+                    this.push(")");
+                }
+                this.push(" ");
+                this.block(&main.block);
+            }
+            UtxoItem::Impl(r#impl) => {
+                this.push("impl ");
+                this.push(&r#impl.name.raw);
+                this.push(" ");
+                let items = r#impl
+                    .constants
+                    .iter()
+                    .map(Ok)
+                    .chain(r#impl.definitions.iter().map(Err))
+                    .collect::<Vec<_>>();
+                this.braced(&items, |this, item| match item {
+                    Ok(constant) => {
+                        this.push("const ");
+                        this.push(&constant.name.raw);
+                        this.push(": ");
+                        this.type_arg(&constant.ty);
+                        this.push(" = ");
+                        this.expr(&constant.value);
+                        this.push(";");
+                    }
+                    Err(definition) => this.fn_def(definition),
+                });
+            }
+            UtxoItem::Storage(storage) => {
+                this.push("storage ");
+                let fields = storage
+                    .bindings
+                    .values
+                    .iter()
+                    .zip(&storage.initializers)
+                    .enumerate()
+                    .collect::<Vec<_>>();
+                this.braced(&fields, |this, (i, ((name, ty), initializer))| {
+                    if let Some(docs) = storage.docs.get(*i) {
+                        this.docs(docs);
+                    }
+                    this.push(&name.raw);
+                    this.push(": ");
+                    this.type_arg(ty);
+                    if let Some(initializer) = initializer {
+                        this.push(" = ");
+                        this.expr(initializer);
+                    }
+                    this.push(";");
+                });
+            }
+            UtxoItem::Yield(ty) => {
+                this.push("Yield ");
+                this.type_arg(&ty.node);
+            }
+            UtxoItem::Resume(ty) => {
+                this.push("Resume ");
+                this.type_arg(&ty.node);
+            }
+        });
+    }
+
+    fn token(&mut self, token: &Token) {
+        self.push("token ");
+        self.push(&token.name.raw);
+        self.push(" ");
+        self.braced(&token.items, |this, item| {
+            let (keyword, block) = match item {
+                TokenItem::Bind(Bind(block, _)) => ("bind ", block),
+                TokenItem::Unbind(Unbind(block, _)) => ("unbind ", block),
+                TokenItem::Mint(Mint(block, _)) => ("mint ", block),
+            };
+            this.push(keyword);
+            this.block(block);
+        });
+    }
+
+    fn typedef(&mut self, typedef: &TypeDef) {
+        self.push("typedef ");
+        self.push(&typedef.name.raw);
+        self.push(" = ");
+        match &typedef.ty {
+            TypeDefRhs::TypeArg(ty) => self.type_arg(ty),
+            TypeDefRhs::Object(bindings) => self.object_type(bindings),
+            TypeDefRhs::Variant(Variant(variants)) => {
+                self.push("enum ");
+                self.braced(variants, |this, (name, bindings, discriminant)| {
+                    this.push(&name.raw);
+                    this.push("(");
+                    this.typed_bindings(bindings);
+                    this.push(")");
+                    if let Some(discriminant) = discriminant {
+                        this.push(" = ");
+                        this.push(&discriminant.to_string());
+                    }
+                });
+            }
+        }
+    }
+
+    fn object_type(&mut self, bindings: &TypedBindings) {
+        if bindings.values.is_empty() {
+            self.push("{}");
+        } else {
+            self.push("{ ");
+            self.typed_bindings(bindings);
+            self.push(" }");
+        }
+    }
+
+    fn typed_bindings(&mut self, bindings: &TypedBindings) {
+        self.separated(&bindings.values, ", ", |this, (name, ty)| {
+            this.push(&name.raw);
+            this.push(": ");
+            this.type_arg(ty);
+        });
+    }
+
+    fn type_arg(&mut self, ty: &TypeArg) {
+        match ty {
+            TypeArg::Unit => self.push("()"),
+            TypeArg::Bool => self.push("bool"),
+            TypeArg::F32 => self.push("f32"),
+            TypeArg::F64 => self.push("f64"),
+            TypeArg::U32 => self.push("u32"),
+            TypeArg::I32 => self.push("i32"),
+            TypeArg::U64 => self.push("u64"),
+            TypeArg::I64 => self.push("i64"),
+            TypeArg::U128 => self.push("u128"),
+            TypeArg::I128 => self.push("i128"),
+            TypeArg::String => self.push("string"),
+            TypeArg::Intermediate { abi, storage } => {
+                self.push("Intermediate<");
+                self.type_arg(abi);
+                self.push(", ");
+                self.type_arg(storage);
+                self.push(">");
+            }
+            TypeArg::TypeRef(TypeRef(name)) => self.push(&name.raw),
+            TypeArg::TypeApplication(TypeRef(name), params) => {
+                self.push(&name.raw);
+                self.push("<");
+                self.separated(params, ", ", Self::type_arg);
+                self.push(">");
+            }
+            TypeArg::FnType(FnType { inputs, output }) => {
+                self.push("(");
+                self.typed_bindings(inputs);
+                self.push(")");
+                if let Some(output) = output {
+                    self.push(" -> ");
+                    self.type_arg(output);
+                }
+            }
+            TypeArg::Ref(ty) => {
+                self.push("&");
+                self.type_arg(ty);
+            }
+            TypeArg::Tuple(types) => {
+                self.push("(");
+                self.separated(types, ", ", Self::type_arg);
+                self.push(")");
+            }
+            TypeArg::Array(elem, len) => {
+                self.push("[");
+                self.type_arg(elem);
+                self.push("; ");
+                self.expr(&len.expr);
+                self.push("]");
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        let Block::Chain { .. } = block else {
+            self.push("{}");
+            return;
+        };
+
+        self.push("{");
+        self.indent += 1;
+        let mut block = block;
+        while let Block::Chain { head, tail } = block {
+            self.newline();
+            match &**head {
+                ExprOrStatement::Statement(statement) => self.statement(statement),
+                ExprOrStatement::Expr(expr) => {
+                    self.expr(expr);
+                    // An `if`, `try` or `match` at the start of a statement ends where
+                    // its blocks do; anything else needs a `;` before the next
+                    // item.
+                    let ends_with_block = matches!(
+                        expr.node,
+                        Expr::BlockExpr(
+                            BlockExpr::IfThenElse(..)
+                                | BlockExpr::TryWith(..)
+                                | BlockExpr::Match(..)
+                        )
+                    );
+                    if !ends_with_block && matches!(**tail, Block::Chain { .. }) {
+                        self.push(";");
+                    }
+                }
+            }
+            block = tail;
+        }
+        if let Block::Close { semicolon: true } = block {
+            self.push(";");
+        }
+        self.indent -= 1;
+        self.newline();
+        self.push("}");
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::BindVar {
+                var,
+                mutable,
+                ty,
+                value,
+            } => {
+                self.push("let ");
+                if *mutable {
+                    self.push("mut ");
+                }
+                self.push(&var.raw);
+                if let Some(ty) = ty {
+                    self.push(": ");
+                    self.type_arg(ty);
+                }
+                self.push(" = ");
+                self.expr(value);
+                self.push(";");
+            }
+            Statement::Return(value) => self.keyword_statement("return", value.as_ref()),
+            Statement::Resume(value) => self.keyword_statement("resume", value.as_ref()),
+            Statement::Assign { var, expr } => {
+                self.field_access(var);
+                self.push(" = ");
+                self.expr(expr);
+                self.push(";");
+            }
+            Statement::While(cond, body) => {
+                self.push("while (");
+                self.expr(cond);
+                self.push(") ");
+                self.loop_body(body);
+            }
+            Statement::Loop(body) => {
+                self.push("loop ");
+                self.loop_body(body);
+            }
+            Statement::For {
+                var,
+                start,
+                end,
+                body,
+            } => {
+                self.push("for ");
+                self.push(&var.raw);
+                self.push(" in ");
+                self.expr(start);
+                self.push("..");
+                self.expr(end);
+                self.push(" ");
+                self.loop_body(body);
+            }
+            Statement::Labeled(label, statement) => {
+                self.push("'");
+                self.push(&label.name);
+                self.push(": ");
+                self.statement(statement);
+            }
+            Statement::Break { label, value, .. } => {
+                self.jump_statement("break", label.as_ref(), value.as_ref())
+            }
+            Statement::Continue { label, .. } => {
+                self.jump_statement("continue", label.as_ref(), None)
+            }
+        }
+    }
+
+    fn keyword_statement(&mut self, keyword: &str, value: Option<&Spanned<Expr>>) {
+        self.push(keyword);
+        if let Some(value) = value {
+            self.push(" ");
+            self.expr(value);
+        }
+        self.push(";");
+    }
+
+    fn jump_statement(
+        &mut self,
+        keyword: &str,
+        label: Option<&LoopLabel>,
+        value: Option<&Spanned<Expr>>,
+    ) {
+        self.push(keyword);
+        if let Some(label) = label {
+            self.push(" '");
+            self.push(&label.name);
+        }
+        if let Some(value) = value {
+            self.push(" ");
+            self.expr(value);
+        }
+        self.push(";");
+    }
+
+    fn loop_body(&mut self, body: &LoopBody) {
+        match body {
+            LoopBody::Statement(statement) => self.statement(statement),
+            LoopBody::Block(block) => self.block(block),
+            LoopBody::Expr(expr) => {
+                self.expr(expr);
+                self.push(";");
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Spanned<Expr>) {
+        if let Some((lhs, op, rhs, precedence)) = binary(&expr.node) {
+            // Everything is left-associative, so only the right operand needs
+            // parentheses at equal precedence.
+            self.operand(lhs, precedence);
+            self.push(" ");
+            self.push(op);
+            self.push(" ");
+            self.operand(rhs, precedence + 1);
+        } else if let Some((op, operand)) = prefix(&expr.node) {
+            self.push(op);
+            self.operand(operand, PREFIX_PRECEDENCE);
+        } else {
+            match &expr.node {
+                Expr::PrimaryExpr(field_access) => self.field_access(field_access),
+                Expr::BlockExpr(BlockExpr::Block(block)) => self.block(block),
+                Expr::BlockExpr(BlockExpr::TryWith(body, handlers)) => {
+                    self.push("try ");
+                    self.block(body);
+                    for (handler, block) in handlers {
+                        self.push(" with ");
+                        self.push(&handler.interface.raw);
+                        self.push("::");
+                        self.push(&handler.ident.raw);
+                        self.push("(");
+                        self.separated(&handler.args, ", ", |this, arg| {
+                            this.push(&arg.name.raw);
+                            if let Some(ty) = &arg.ty {
+                                this.push(": ");
+                                this.type_arg(ty);
+                            }
+                        });
+                        self.push(") ");
+                        self.block(block);
+                    }
+                }
+                Expr::BlockExpr(BlockExpr::Loop(body)) => {
+                    self.push("loop ");
+                    self.loop_body(body);
+                }
+                Expr::BlockExpr(BlockExpr::Match(scrutinee, arms)) => {
+                    self.push("match (");
+                    self.expr(scrutinee);
+                    self.push(") {");
+                    self.indent += 1;
+                    for arm in arms {
+                        self.newline();
+                        self.push(&arm.variant.raw);
+                        self.push("(");
+                        self.separated(&arm.bindings, ", ", |this, binding| {
+                            this.push(&binding.raw)
+                        });
+                        self.push(") => ");
+                        self.block(&arm.body);
+                        self.push(",");
+                    }
+                    self.indent -= 1;
+                    self.newline();
+                    self.push("}");
+                }
+                Expr::BlockExpr(BlockExpr::IfThenElse(cond, then, otherwise)) => {
+                    self.push("if (");
+                    self.expr(cond);
+                    self.push(") ");
+                    self.block(then);
+                    if let Some(otherwise) = otherwise {
+                        self.push(" else ");
+                        self.block(otherwise);
+                    }
+                }
+                _ => unreachable!("operators are handled above"),
+            }
+        }
+    }
+
+    fn operand(&mut self, expr: &Spanned<Expr>, min_precedence: u8) {
+        if precedence(&expr.node) < min_precedence {
+            self.push("(");
+            self.expr(expr);
+            self.push(")");
+        } else {
+            self.expr(expr);
+        }
+    }
+
+    fn field_access(&mut self, expr: &FieldAccessExpression) {
+        match expr {
+            FieldAccessExpression::PrimaryExpr(primary) => self.primary(primary),
+            FieldAccessExpression::FieldAccess { base, field } => {
+                self.field_access(base);
+                self.push(".");
+                self.identifier_expr(field);
+            }
+            FieldAccessExpression::Index { base, index } => {
+                self.field_access(base);
+                self.push("[");
+                self.expr(index);
+                self.push("]");
+            }
+        }
+    }
+
+    fn identifier_expr(&mut self, expr: &IdentifierExpr) {
+        self.push(&expr.name.raw);
+        if let Some(args) = &expr.args {
+            self.push("(");
+            self.separated(&args.xs, ", ", Self::expr);
+            if !args.xs.is_empty() && !args.named.is_empty() {
+                self.push(", ");
+            }
+            self.separated(&args.named, ", ", |this, (name, value)| {
+                this.push(&name.raw);
+                this.push(": ");
+                this.expr(value);
+            });
+            self.push(")");
+        }
+    }
+
+    fn namespaced(&mut self, namespaces: &[Identifier], ident: &IdentifierExpr) {
+        for namespace in namespaces {
+            self.push(&namespace.raw);
+            self.push("::");
+        }
+        self.identifier_expr(ident);
+    }
+
+    fn primary(&mut self, expr: &PrimaryExpr) {
+        match expr {
+            PrimaryExpr::Number { literal, .. } => self.push(&literal.to_string()),
+            PrimaryExpr::TypedNumber(literal, ty) => {
+                self.push(&literal.to_string());
+                self.type_arg(ty);
+            }
+            PrimaryExpr::Bool(value) => self.push(if *value { "true" } else { "false" }),
+            PrimaryExpr::Char(codepoint) => {
+                self.push("'");
+                match char::from_u32(*codepoint).unwrap_or(char::REPLACEMENT_CHARACTER) {
+                    '\n' => self.push("\\n"),
+                    '\r' => self.push("\\r"),
+                    '\t' => self.push("\\t"),
+                    '\0' => self.push("\\0"),
+                    '\\' => self.push("\\\\"),
+                    '\'' => self.push("\\'"),
+                    c => self.push(c.encode_utf8(&mut [0; 4])),
+                }
+                self.push("'");
+            }
+            PrimaryExpr::Ident(ident) => self.identifier_expr(ident),
+            PrimaryExpr::Namespace { namespaces, ident } => self.namespaced(namespaces, ident),
+            PrimaryExpr::ParExpr(expr) => {
+                self.push("(");
+                self.expr(expr);
+                self.push(")");
+            }
+            PrimaryExpr::Yield(value) => {
+                self.push("yield");
+                if let Some(value) = value {
+                    self.push(" ");
+                    self.expr(value);
+                }
+            }
+            PrimaryExpr::Raise { ident } => {
+                self.push("raise ");
+                self.identifier_expr(ident);
+            }
+            PrimaryExpr::RaiseNamespaced { namespaces, ident } => {
+                self.push("raise ");
+                self.namespaced(namespaces, ident);
+            }
+            PrimaryExpr::Object(ty, values) => {
+                self.type_arg(ty);
+                if values.is_empty() {
+                    self.push(" {}");
+                } else {
+                    self.push(" { ");
+                    self.separated(values, ", ", |this, (name, value)| {
+                        this.push(&name.raw);
+                        this.push(": ");
+                        this.expr(value);
+                    });
+                    self.push(" }");
+                }
+            }
+            PrimaryExpr::StringLiteral(value) => {
+                self.push("\"");
+                self.push(value);
+                self.push("\"");
+            }
+            PrimaryExpr::Tuple(values) => {
+                self.push("(");
+                self.separated(values, ", ", Self::expr);
+                self.push(")");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pretty_print;
+    use crate::{ast::*, format_reports, parse};
+    use chumsky::span::SimpleSpan;
+
+    /// Parse `source`, print it, and check that the printed form parses back
+    /// into the same tree.
+    fn roundtrip(source: &str) -> String {
+        let (ast, errors) = parse(source);
+        let Some(ast) = ast else {
+            panic!(
+                "failed to parse:\n{source}\n{}",
+                format_reports(source, &errors)
+            );
+        };
+        let printed = pretty_print(&ast);
+
+        let (reparsed, errors) = parse(&printed);
+        let Some(reparsed) = reparsed else {
+            panic!(
+                "failed to reparse:\n{printed}\n{}",
+                format_reports(&printed, &errors)
+            );
+        };
+        assert_eq!(
+            structure(&reparsed),
+            structure(&ast),
+            "tree changed, printed as:\n{printed}"
+        );
+
+        printed
+    }
+
+    /// `program` without its spans or the parentheses around expressions, so
+    /// that trees can be compared by structure alone.
+    ///
+    /// The printer adds parentheses wherever precedence needs them, which then
+    /// parse back as `ParExpr`s the original tree didn't have.
+    fn structure(program: &StarstreamProgram) -> String {
+        const PARENS: &str = "Spanned { node: PrimaryExpr(PrimaryExpr(ParExpr(";
+        const PARENS_END: &str = "))), span: _ }";
+
+        let mut debug = erase_spans(&format!("{program:?}"));
+        while let Some(start) = debug.find(PARENS) {
+            let inner = start + PARENS.len();
+            let end = inner + spanned_len(&debug[inner..]);
+            assert!(debug[end..].starts_with(PARENS_END));
+            let expr = debug[inner..end].to_owned();
+            debug.replace_range(start..end + PARENS_END.len(), &expr);
+        }
+        debug
+    }
+
+    /// `debug` with every span, printed as `start..end` and optionally
+    /// missing in identifiers, replaced by `_`.
+    fn erase_spans(debug: &str) -> String {
+        let mut out = String::new();
+        let mut rest = debug;
+        while let Some(i) = rest.find("..") {
+            let start = rest[..i]
+                .trim_end_matches(|c: char| c.is_ascii_digit())
+                .len();
+            let end = rest[i + 2..]
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or(rest.len(), |len| i + 2 + len);
+            if start < i && end > i + 2 {
+                out.push_str(&rest[..start]);
+                out.push('_');
+            } else {
+                out.push_str(&rest[..end]);
+            }
+            rest = &rest[end..];
+        }
+        out.push_str(rest);
+        out.replace("Some(_)", "_").replace("span: None", "span: _")
+    }
+
+    /// Length of the `Spanned { .. }` at the start of `debug`.
+    fn spanned_len(debug: &str) -> usize {
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+        for (i, c) in debug.char_indices() {
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == '"' {
+                    in_string = false;
+                }
+                continue;
+            }
+
+            match c {
+                '"' => in_string = true,
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return i + 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        panic!("unbalanced debug output: {debug}");
+    }
+
+    const SEED_CORPUS: &[&str] = &[
+        include_str!("../../grammar/examples/effect_handlers.star"),
+        include_str!("../../grammar/examples/event.star"),
+        include_str!("../../grammar/examples/hello_world.star"),
+        include_str!("../../grammar/examples/oracle.star"),
+        include_str!("../../grammar/examples/pay_to_public_key_hash.star"),
+        include_str!("../../grammar/examples/permissioned_usdc.star"),
+        include_str!("../../grammar/examples/simple_oracle.star"),
+        include_str!("../../grammar/examples/tokens.star"),
+        include_str!("../../grammar/examples/u128.star"),
+    ];
+
+    #[test]
+    fn roundtrip_seed_corpus() {
+        for source in SEED_CORPUS {
+            roundtrip(source);
+        }
+    }
+
+    #[test]
+    fn roundtrip_precedence() {
+        let printed =
+            roundtrip("const A = (1 + 2) * 3 - (4 - 5) - -a + !(b && c) || d == (e != f);");
+        assert!(printed.contains("(1 + 2) * 3 - (4 - 5) - -a + !(b && c) || d == (e != f)"));
+
+        let printed = roundtrip("const A = 1 + 2 * 3;");
+        assert!(printed.contains("1 + 2 * 3"));
+    }
+
+    #[test]
+    fn roundtrip_generated_programs() {
+        for seed in 1..=256 {
+            let program = Generator::new(seed).program();
+            let printed = pretty_print(&program);
+
+            let (reparsed, errors) = parse(&printed);
+            let Some(reparsed) = reparsed else {
+                panic!(
+                    "seed {seed} failed to reparse:\n{printed}\n{}",
+                    format_reports(&printed, &errors)
+                );
+            };
+            assert_eq!(
+                structure(&reparsed),
+                structure(&program),
+                "seed {seed} changed, printed as:\n{printed}"
+            );
+        }
+    }
+
+    const VARS: &[&str] = &["a", "b", "count", "owner", "amount", "x1", "value"];
+    const FUNCTIONS: &[&str] = &["foo", "bar", "assert", "compute"];
+    const NAMESPACES: &[&str] = &["Utxo", "Token1", "StarstreamEnv"];
+    const EFFECTS: &[&str] = &["Foo", "Bar", "Caller"];
+    const TYPES: &[&str] = &["Point", "Data", "PublicKey", "Option"];
+    const CONSTANTS: &[&str] = &["FEE", "ADMIN"];
+    const LABELS: &[&str] = &["outer", "inner"];
+    const STRINGS: &[&str] = &["", "hello", "Hello, world!"];
+    const CHARS: &[char] = &['a', 'é', '\n', '\0', '\'', '\\'];
+    const DOCS: &[&str] = &["Does a thing.", "", "Two\n lines."];
+
+    type BinaryOp = fn(Box<Spanned<Expr>>, Box<Spanned<Expr>>) -> Expr;
+
+    const BINARY_OPS: &[BinaryOp] = &[
+        Expr::Equals,
+        Expr::NotEquals,
+        Expr::LessThan,
+        Expr::GreaterThan,
+        Expr::LessEq,
+        Expr::GreaterEq,
+        Expr::Add,
+        Expr::Sub,
+        Expr::Mul,
+        Expr::Div,
+        Expr::Mod,
+        Expr::BitAnd,
+        Expr::BitOr,
+        Expr::BitXor,
+        Expr::LShift,
+        Expr::RShift,
+        Expr::And,
+        Expr::Or,
+    ];
+
+    fn spanned(node: Expr) -> Spanned<Expr> {
+        Spanned {
+            node,
+            span: SimpleSpan::from(0..0),
+        }
+    }
+
+    fn primary(expr: PrimaryExpr) -> Spanned<Expr> {
+        spanned(Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(expr)))
+    }
+
+    fn ident(name: &str) -> Identifier {
+        Identifier::new(name, None)
+    }
+
+    fn label(name: &str) -> LoopLabel {
+        LoopLabel {
+            name: name.to_string(),
+            span: SimpleSpan::from(0..0),
+        }
+    }
+
+    fn starts_with_neg(expr: &Expr) -> bool {
+        match expr {
+            Expr::Neg(_) => true,
+            _ => super::binary(expr).is_some_and(|(lhs, _, _, _)| starts_with_neg(&lhs.node)),
+        }
+    }
+
+    /// Deterministic generator of random, syntactically valid programs.
+    ///
+    /// Only produces trees the parser can yield, e.g. `if` only at the start
+    /// of a statement and tuples never with a single element.
+    struct Generator {
+        state: u64,
+        labels: Vec<&'static str>,
+        loop_depth: usize,
+    }
+
+    impl Generator {
+        fn new(seed: u64) -> Self {
+            Generator {
+                state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+                labels: Vec::new(),
+                loop_depth: 0,
+            }
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            // xorshift64*
+            self.state ^= self.state >> 12;
+            self.state ^= self.state << 25;
+            self.state ^= self.state >> 27;
+            self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next_u64() % n as u64) as usize
+        }
+
+        fn chance(&mut self) -> bool {
+            self.below(2) == 0
+        }
+
+        fn pick<T: Copy>(&mut self, xs: &[T]) -> T {
+            xs[self.below(xs.len())]
+        }
+
+        fn repeat<T>(&mut self, max: usize, mut f: impl FnMut(&mut Self) -> T) -> Vec<T> {
+            let n = self.below(max + 1);
+            (0..n).map(|_| f(self)).collect()
+        }
+
+        fn program(&mut self) -> StarstreamProgram {
+            let n = 1 + self.below(4);
+            let items = (0..n)
+                .map(|_| Item {
+                    node: self.item(),
+                    span: SimpleSpan::from(0..0),
+                    when: self.chance().then(|| ident(self.pick(VARS))),
+                    docs: self.docs(),
+                })
+                .collect();
+            StarstreamProgram { items }
+        }
+
+        fn docs(&mut self) -> Option<String> {
+            self.chance().then(|| self.pick(DOCS).to_owned())
+        }
+
+        fn item(&mut self) -> ProgramItem {
+            match self.below(6) {
+                0 => ProgramItem::Script(Script {
+                    definitions: self.repeat(3, Self::fn_def),
+                }),
+                1 => ProgramItem::Utxo(self.utxo()),
+                2 => ProgramItem::Abi(self.abi()),
+                3 => ProgramItem::TypeDef(self.typedef()),
+                4 => ProgramItem::Constant {
+                    name: ident(self.pick(CONSTANTS)),
+                    ty: (self.below(2) == 0).then(|| self.type_arg(1)),
+                    value: self.expr(2),
+                },
+                _ => ProgramItem::Token(Token {
+                    name: ident(self.pick(TYPES)),
+                    items: vec![
+                        TokenItem::Mint(Mint(self.block(2), ident("mint"))),
+                        TokenItem::Bind(Bind(self.block(2), ident("bind"))),
+                        TokenItem::Unbind(Unbind(self.block(2), ident("unbind"))),
+                    ],
+                }),
+            }
+        }
+
+        fn fn_def(&mut self) -> FnDef {
+            let ident = ident(self.pick(FUNCTIONS));
+            let inputs = self.repeat(3, |this| FnArgDeclaration {
+                name: Identifier::new(this.pick(VARS), None),
+                ty: this.type_arg(2),
+            });
+            let output = self.chance().then(|| self.type_arg(2));
+            let effects = self.repeat(2, |this| Identifier::new(this.pick(NAMESPACES), None));
+            let body = self.block(3);
+            let docs = self.docs();
+
+            FnDef {
+                ident,
+                inputs,
+                output,
+                body,
+                effects,
+                docs,
+            }
+        }
+
+        fn sig(&mut self) -> Sig {
+            Sig {
+                name: ident(self.pick(EFFECTS)),
+                input_types: self.repeat(2, |this| this.type_arg(1)),
+                output_type: self.chance().then(|| self.type_arg(1)),
+            }
+        }
+
+        fn utxo(&mut self) -> Utxo {
+            let name = ident(self.pick(TYPES));
+            let items = self.repeat(4, |this| match this.below(5) {
+                0 => UtxoItem::Main(Main {
+                    type_sig: this.chance().then(|| this.typed_bindings(2)),
+                    block: this.block(3),
+                    ident: ident("new"),
+                }),
+                1 => UtxoItem::Impl(Impl {
+                    name: ident(this.pick(TYPES)),
+                    constants: this.repeat(2, |this| AssociatedConst {
+                        name: ident(this.pick(CONSTANTS)),
+                        ty: this.type_arg(1),
+                        value: this.expr(2),
+                    }),
+                    definitions: this.repeat(2, Self::fn_def),
+                }),
+                2 => {
+                    let bindings = this.typed_bindings(3);
+                    let initializers = bindings
+                        .values
+                        .iter()
+                        .map(|_| this.chance().then(|| this.expr(2)))
+                        .collect();
+                    let docs = bindings.values.iter().map(|_| this.docs()).collect();
+                    UtxoItem::Storage(Storage {
+                        bindings,
+                        initializers,
+                        docs,
+                    })
+                }
+                3 => UtxoItem::Yield(Spanned {
+                    node: this.type_arg(1),
+                    span: SimpleSpan::from(0..0),
+                }),
+                _ => UtxoItem::Resume(Spanned {
+                    node: this.type_arg(1),
+                    span: SimpleSpan::from(0..0),
+                }),
+            });
+
+            Utxo { name, items }
+        }
+
+        fn abi(&mut self) -> Abi {
+            let name = ident(self.pick(TYPES));
+            let values = self.repeat(3, |this| match this.below(5) {
+                0 => AbiElem::FnDecl(FnDecl(this.sig())),
+                1 => AbiElem::FnDef(this.fn_def()),
+                2 => AbiElem::EffectDecl(EffectDecl::EffectSig(this.sig())),
+                3 => AbiElem::EffectDecl(EffectDecl::EventSig(this.sig())),
+                _ => AbiElem::EffectDecl(EffectDecl::ErrorSig(this.sig())),
+            });
+
+            Abi { name, values }
+        }
+
+        fn typedef(&mut self) -> TypeDef {
+            let name = ident(self.pick(TYPES));
+            let ty = match self.below(3) {
+                0 => TypeDefRhs::TypeArg(self.type_arg(2)),
+                1 => TypeDefRhs::Object(self.typed_bindings(3)),
+                _ => TypeDefRhs::Variant(Variant(self.repeat(3, |this| {
+                    let name = ident(this.pick(EFFECTS));
+                    let bindings = this.typed_bindings(2);
+                    let discriminant = this.chance().then(|| this.below(8) as u64);
+                    (name, bindings, discriminant)
+                }))),
+            };
+
+            TypeDef { name, ty }
+        }
+
+        fn typed_bindings(&mut self, max: usize) -> TypedBindings {
+            TypedBindings {
+                values: self.repeat(max, |this| (ident(this.pick(VARS)), this.type_arg(1))),
+            }
+        }
+
+        fn type_arg(&mut self, depth: usize) -> TypeArg {
+            let choice = if depth == 0 {
+                self.below(11)
+            } else {
+                self.below(16)
+            };
+
+            match choice {
+                0 => TypeArg::Bool,
+                1 => TypeArg::F32,
+                2 => TypeArg::F64,
+                3 => TypeArg::U32,
+                4 => TypeArg::I32,
+                5 => TypeArg::U64,
+                6 => TypeArg::I64,
+                7 => TypeArg::U128,
+                8 => TypeArg::I128,
+                9 => TypeArg::String,
+                10 => TypeArg::TypeRef(TypeRef(ident(self.pick(TYPES)))),
+                11 => TypeArg::Intermediate {
+                    abi: Box::new(self.type_arg(depth - 1)),
+                    storage: Box::new(self.type_arg(depth - 1)),
+                },
+                12 => TypeArg::TypeApplication(
+                    TypeRef(ident(self.pick(TYPES))),
+                    (0..1 + self.below(2))
+                        .map(|_| self.type_arg(depth - 1))
+                        .collect(),
+                ),
+                13 => TypeArg::FnType(FnType {
+                    inputs: TypedBindings {
+                        values: self
+                            .repeat(2, |this| (ident(this.pick(VARS)), this.type_arg(depth - 1))),
+                    },
+                    output: self.chance().then(|| Box::new(self.type_arg(depth - 1))),
+                }),
+                14 => TypeArg::Array(
+                    Box::new(self.type_arg(depth - 1)),
+                    ArrayLen::new(self.expr(1)),
+                ),
+                _ => TypeArg::Tuple(
+                    (0..2 + self.below(2))
+                        .map(|_| self.type_arg(depth - 1))
+                        .collect(),
+                ),
+            }
+        }
+
+        fn block(&mut self, depth: usize) -> Block {
+            let mut items = self.repeat(4, |this| this.block_item(depth));
+            let trailing = self.chance().then(|| self.expr(2));
+
+            let mut block = Block::Close { semicolon: false };
+            if let Some(expr) = trailing {
+                // `expr }` or `expr; }`
+                block = Block::Close {
+                    semicolon: self.chance(),
+                };
+                items.push(ExprOrStatement::Expr(expr));
+            } else if let Some(ExprOrStatement::Statement(Statement::Loop(LoopBody::Block(_)))) =
+                items.last()
+            {
+                // a `loop` ending a block is read as the value of the block
+                let Some(ExprOrStatement::Statement(Statement::Loop(body))) = items.pop() else {
+                    unreachable!();
+                };
+                items.push(ExprOrStatement::Expr(spanned(Expr::BlockExpr(
+                    BlockExpr::Loop(body),
+                ))));
+            }
+
+            for head in items.into_iter().rev() {
+                block = Block::Chain {
+                    head: Box::new(head),
+                    tail: Box::new(block),
+                };
+            }
+            block
+        }
+
+        fn block_item(&mut self, depth: usize) -> ExprOrStatement {
+            let choice = if depth == 0 {
+                self.below(5)
+            } else {
+                self.below(12)
+            };
+
+            let statement =
+                match choice {
+                    0 => Statement::BindVar {
+                        var: ident(self.pick(VARS)),
+                        mutable: self.chance(),
+                        ty: self.chance().then(|| self.type_arg(1)),
+                        value: self.value(),
+                    },
+                    1 => Statement::Assign {
+                        var: self.place(),
+                        expr: self.expr(2),
+                    },
+                    2 => Statement::Return(Some(self.keyword_operand())),
+                    3 => {
+                        let expr = if self.below(4) == 0 {
+                            self.yield_expr()
+                        } else {
+                            self.expr(2)
+                        };
+                        return ExprOrStatement::Expr(expr);
+                    }
+                    4 => Statement::Resume(Some(self.keyword_operand())),
+                    5 => {
+                        let cond = self.expr(2);
+                        let then = self.block(depth - 1);
+                        let otherwise = self.chance().then(|| Box::new(self.block(depth - 1)));
+                        return ExprOrStatement::Expr(spanned(Expr::BlockExpr(
+                            BlockExpr::IfThenElse(Box::new(cond), Box::new(then), otherwise),
+                        )));
+                    }
+                    6 => self.loop_statement(depth),
+                    7 => {
+                        let name = self.pick(LABELS);
+                        self.labels.push(name);
+                        let statement = self.loop_statement(depth);
+                        self.labels.pop();
+                        Statement::Labeled(label(name), Box::new(statement))
+                    }
+                    8 => {
+                        let body = self.block(depth - 1);
+                        let handlers = (0..1 + self.below(2))
+                            .map(|_| {
+                                let handler = EffectHandler {
+                                    interface: ident(self.pick(NAMESPACES)),
+                                    ident: ident(self.pick(EFFECTS)),
+                                    args: self.repeat(2, |this| EffectArgDeclaration {
+                                        name: ident(this.pick(VARS)),
+                                        ty: this.chance().then(|| this.type_arg(1)),
+                                    }),
+                                };
+                                (handler, self.block(depth - 1))
+                            })
+                            .collect();
+                        return ExprOrStatement::Expr(spanned(Expr::BlockExpr(
+                            BlockExpr::TryWith(Box::new(body), handlers),
+                        )));
+                    }
+                    9 => {
+                        let scrutinee = self.expr(2);
+                        let arms = (0..1 + self.below(2))
+                            .map(|_| MatchArm {
+                                variant: ident(self.pick(EFFECTS)),
+                                bindings: self.repeat(2, |this| ident(this.pick(VARS))),
+                                body: self.block(depth - 1),
+                            })
+                            .collect();
+                        return ExprOrStatement::Expr(spanned(Expr::BlockExpr(BlockExpr::Match(
+                            Box::new(scrutinee),
+                            arms,
+                        ))));
+                    }
+                    _ if self.loop_depth > 0 => self.jump(),
+                    _ => Statement::Assign {
+                        var: self.place(),
+                        expr: self.expr(1),
+                    },
+                };
+
+            ExprOrStatement::Statement(statement)
+        }
+
+        fn loop_statement(&mut self, depth: usize) -> Statement {
+            self.loop_depth += 1;
+            let kind = self.below(3);
+            let cond = (kind == 0).then(|| self.expr(2));
+            // TODO: `a..b {}` reads as an object literal of type `b`, so keep
+            // the end of a range in parentheses.
+            let range = (kind == 1).then(|| {
+                let start = self.expr(2);
+                let end = self.expr(2);
+                (start, primary(PrimaryExpr::ParExpr(Box::new(end))))
+            });
+            let body = match self.below(3) {
+                0 => LoopBody::Block(self.block(depth - 1)),
+                1 => LoopBody::Statement(Box::new(self.jump())),
+                _ => LoopBody::Expr(self.call()),
+            };
+            self.loop_depth -= 1;
+
+            match (cond, range) {
+                (Some(cond), _) => Statement::While(cond, body),
+                (_, Some((start, end))) => Statement::For {
+                    var: ident(self.pick(VARS)),
+                    start,
+                    end,
+                    body,
+                },
+                _ => Statement::Loop(body),
+            }
+        }
+
+        fn jump(&mut self) -> Statement {
+            let label = (!self.labels.is_empty() && self.chance()).then(|| {
+                let i = self.below(self.labels.len());
+                label(self.labels[i])
+            });
+            let span = SimpleSpan::from(0..0);
+            if self.chance() {
+                Statement::Break {
+                    label,
+                    value: None,
+                    span,
+                }
+            } else {
+                Statement::Continue { label, span }
+            }
+        }
+
+        /// The operand of `return` or `resume`.
+        ///
+        /// A leading `-` would be read as subtracting from an identifier
+        /// called `return`, so such operands get parenthesized.
+        fn keyword_operand(&mut self) -> Spanned<Expr> {
+            let expr = self.expr(2);
+            if starts_with_neg(&expr.node) {
+                primary(PrimaryExpr::ParExpr(Box::new(expr)))
+            } else {
+                expr
+            }
+        }
+
+        fn value(&mut self) -> Spanned<Expr> {
+            if self.below(5) == 0 {
+                self.yield_expr()
+            } else {
+                self.expr(3)
+            }
+        }
+
+        fn yield_expr(&mut self) -> Spanned<Expr> {
+            let value = self.chance().then(|| Box::new(self.expr(2)));
+            primary(PrimaryExpr::Yield(value))
+        }
+
+        fn place(&mut self) -> FieldAccessExpression {
+            let base = FieldAccessExpression::PrimaryExpr(PrimaryExpr::Ident(IdentifierExpr {
+                name: ident(self.pick(VARS)),
+                args: None,
+            }));
+            if self.chance() {
+                return base;
+            }
+
+            FieldAccessExpression::FieldAccess {
+                base: Box::new(base),
+                field: IdentifierExpr {
+                    name: ident(self.pick(VARS)),
+                    args: None,
+                },
+            }
+        }
+
+        fn args(&mut self, depth: usize) -> Arguments {
+            Arguments {
+                xs: self.repeat(3, |this| this.expr(depth)),
+                named: self.repeat(2, |this| (ident(this.pick(VARS)), this.expr(depth))),
+            }
+        }
+
+        fn call(&mut self) -> Spanned<Expr> {
+            let ident = IdentifierExpr {
+                name: ident(self.pick(FUNCTIONS)),
+                args: Some(self.args(1)),
+            };
+            primary(PrimaryExpr::Ident(ident))
+        }
+
+        fn expr(&mut self, depth: usize) -> Spanned<Expr> {
+            if depth == 0 {
+                return self.atom();
+            }
+
+            match self.below(12) {
+                0..=3 => {
+                    let op = self.pick(BINARY_OPS);
+                    let lhs = self.expr(depth - 1);
+                    let rhs = self.expr(depth - 1);
+                    spanned(op(Box::new(lhs), Box::new(rhs)))
+                }
+                4 => {
+                    let mut operand = self.expr(depth - 1);
+                    match self.below(3) {
+                        0 => {
+                            // TODO: the number literal rule accepts a leading
+                            // `-` too, so keep `-` away from literals.
+                            if let Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(
+                                PrimaryExpr::Number { .. },
+                            )) = operand.node
+                            {
+                                operand = self.var();
+                            }
+                            spanned(Expr::Neg(Box::new(operand)))
+                        }
+                        1 => spanned(Expr::Not(Box::new(operand))),
+                        _ => spanned(Expr::BitNot(Box::new(operand))),
+                    }
+                }
+                5 => primary(PrimaryExpr::ParExpr(Box::new(self.expr(depth - 1)))),
+                6 => {
+                    let ident = IdentifierExpr {
+                        name: ident(self.pick(FUNCTIONS)),
+                        args: Some(self.args(depth - 1)),
+                    };
+                    if self.chance() {
+                        primary(PrimaryExpr::Ident(ident))
+                    } else {
+                        primary(PrimaryExpr::Namespace {
+                            namespaces: vec![Identifier::new(self.pick(NAMESPACES), None)],
+                            ident,
+                        })
+                    }
+                }
+                7 => {
+                    let mut access =
+                        FieldAccessExpression::PrimaryExpr(PrimaryExpr::Ident(IdentifierExpr {
+                            name: ident(self.pick(VARS)),
+                            args: None,
+                        }));
+                    for _ in 0..1 + self.below(3) {
+                        if self.below(4) == 0 {
+                            access = FieldAccessExpression::Index {
+                                base: Box::new(access),
+                                index: Box::new(self.expr(depth - 1)),
+                            };
+                            continue;
+                        }
+
+                        let field = IdentifierExpr {
+                            name: ident(self.pick(FUNCTIONS)),
+                            args: self.chance().then(|| self.args(depth - 1)),
+                        };
+                        access = FieldAccessExpression::FieldAccess {
+                            base: Box::new(access),
+                            field,
+                        };
+                    }
+                    spanned(Expr::PrimaryExpr(access))
+                }
+                8 => {
+                    let n = if self.chance() { 0 } else { 2 + self.below(2) };
+                    let values = (0..n).map(|_| self.expr(depth - 1)).collect();
+                    primary(PrimaryExpr::Tuple(values))
+                }
+                9 => {
+                    let ty = TypeArg::TypeRef(TypeRef(ident(self.pick(TYPES))));
+                    let values =
+                        self.repeat(2, |this| (ident(this.pick(VARS)), this.expr(depth - 1)));
+                    primary(PrimaryExpr::Object(ty, values))
+                }
+                10 => {
+                    let ident = IdentifierExpr {
+                        name: ident(self.pick(EFFECTS)),
+                        args: Some(self.args(depth - 1)),
+                    };
+                    if self.chance() {
+                        primary(PrimaryExpr::Raise { ident })
+                    } else {
+                        primary(PrimaryExpr::RaiseNamespaced {
+                            namespaces: vec![Identifier::new(self.pick(NAMESPACES), None)],
+                            ident,
+                        })
+                    }
+                }
+                _ => self.atom(),
+            }
+        }
+
+        fn var(&mut self) -> Spanned<Expr> {
+            primary(PrimaryExpr::Ident(IdentifierExpr {
+                name: ident(self.pick(VARS)),
+                args: None,
+            }))
+        }
+
+        fn atom(&mut self) -> Spanned<Expr> {
+            match self.below(5) {
+                0 => primary(PrimaryExpr::Number {
+                    literal: self.below(1000) as u32,
+                    ty: None,
+                }),
+                1 => primary(PrimaryExpr::Bool(self.chance())),
+                2 => primary(PrimaryExpr::StringLiteral(self.pick(STRINGS).to_string())),
+                3 => primary(PrimaryExpr::Char(self.pick(CHARS) as u32)),
+                _ => self.var(),
+            }
+        }
+    }
+}
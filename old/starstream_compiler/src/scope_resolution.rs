@@ -4,8 +4,9 @@ use crate::symbols::{
 };
 use crate::{
     ast::{
-        Abi, AbiElem, Block, BlockExpr, EffectDecl, Expr, ExprOrStatement, FieldAccessExpression,
-        FnDef, FnType, Identifier, LoopBody, PrimaryExpr, ProgramItem, Script, Sig, Spanned,
+        Abi, AbiElem, Arguments, Block, BlockExpr, EffectDecl, EffectHandler, Expr,
+        ExprOrStatement, FieldAccessExpression, FnDef, FnType, Identifier, IdentifierExpr,
+        LoopBody, LoopLabel, MatchArm, PrimaryExpr, ProgramItem, Script, Sig, Spanned,
         StarstreamProgram, Statement, Token, TokenItem, TypeArg, TypeDef, TypeDefRhs, TypeRef,
         Utxo, UtxoItem,
     },
@@ -47,6 +48,8 @@ pub struct Scope {
     is_function_scope: Option<SymbolId>,
     is_type_scope: Option<SymbolId>,
     is_closure_scope: bool,
+    /// `Some` for the body of a loop, with the loop's label if it has one.
+    is_loop_scope: Option<Option<String>>,
 }
 
 pub const STARSTREAM_ENV: &str = "StarstreamEnv";
@@ -65,6 +68,13 @@ struct Visitor {
 
     global_bind_fn: Option<SymbolId>,
     global_unbind_fn: Option<SymbolId>,
+
+    // storage fields that a storage initializer being visited may not read,
+    // since they are assigned after it runs.
+    uninitialized_storage_fields: Option<Vec<String>>,
+
+    // variables of `for` loops, which only the loop itself may assign
+    for_loop_vars: HashSet<SymbolId>,
 }
 
 #[derive(Debug, Clone)]
@@ -86,6 +96,8 @@ impl Visitor {
             symbols: Symbols::default(),
             global_bind_fn: None,
             global_unbind_fn: None,
+            uninitialized_storage_fields: None,
+            for_loop_vars: HashSet::new(),
         }
     }
 
@@ -159,9 +171,9 @@ impl Visitor {
     // TODO: mostly just to get the examples working
     // these probably would have to be some sort of import?
     fn add_builtins(&mut self) {
-        self.push_type_declaration(&mut Identifier::new("Option", None), None);
-        self.push_type_declaration(&mut Identifier::new("any", None), None);
-        self.push_type_declaration(&mut Identifier::new("Value", None), None);
+        self.push_type_declaration(&mut Identifier::new("Option", None));
+        self.push_type_declaration(&mut Identifier::new("any", None));
+        self.push_type_declaration(&mut Identifier::new("Value", None));
 
         self.push_function_declaration(
             &mut Identifier::new("assert", None),
@@ -287,13 +299,12 @@ impl Visitor {
             vec![
                 // None in the 3rd element makes it a constructor
                 ("PayToPublicKeyHash", "new", None),
-                ("List", "new", None),
             ]
         };
 
         for (builtin, f, ty) in namespaces {
             let mut identifier = Identifier::new(builtin, None);
-            let type_id = self.push_type_declaration(&mut identifier, None);
+            let type_id = self.push_type_declaration(&mut identifier);
 
             self.push_type_scope(type_id);
 
@@ -311,8 +322,61 @@ impl Visitor {
             self.pop_scope();
         }
 
+        // lists live in the host, contracts only hold a handle to them. The
+        // first input of methods is the receiver.
+        let mut list = Identifier::new("List", None);
+        let list_id = self.push_type_declaration(&mut list);
+        let list_ty = TypeArg::TypeRef(TypeRef(list.clone()));
+
+        let methods = vec![
+            (
+                "new",
+                FuncInfo {
+                    inputs_ty: vec![],
+                    output_ty: Some(list_ty.clone()),
+                    mangled_name: Some("starstream_list_new".to_string()),
+                    is_imported: Some("env"),
+                    ..Default::default()
+                },
+            ),
+            (
+                "push",
+                FuncInfo {
+                    inputs_ty: vec![
+                        list_ty.clone(),
+                        TypeArg::Intermediate {
+                            abi: any.clone(),
+                            storage: any.clone(),
+                        },
+                    ],
+                    output_ty: None,
+                    mangled_name: Some("starstream_list_push".to_string()),
+                    is_imported: Some("env"),
+                    ..Default::default()
+                },
+            ),
+            (
+                "len",
+                FuncInfo {
+                    inputs_ty: vec![list_ty.clone()],
+                    output_ty: Some(TypeArg::U32),
+                    mangled_name: Some("starstream_list_len".to_string()),
+                    is_imported: Some("env"),
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        self.push_type_scope(list_id);
+
+        for (f, info) in methods {
+            self.push_function_declaration(&mut Identifier::new(f, None), info);
+        }
+
+        self.pop_scope();
+
         let mut identifier = Identifier::new("Intermediate", None);
-        let type_id = self.push_type_declaration(&mut identifier, None);
+        let type_id = self.push_type_declaration(&mut identifier);
         self.symbols.builtins.insert("Intermediate", type_id);
 
         self.push_type_scope(type_id);
@@ -402,7 +466,11 @@ impl Visitor {
 
         self.add_builtins();
 
-        let mut items = program.items.iter_mut().collect::<Vec<_>>();
+        let mut items = program
+            .items
+            .iter_mut()
+            .map(|item| &mut item.node)
+            .collect::<Vec<_>>();
 
         items.sort_by_key(|item| match item {
             ProgramItem::Abi(_abi) => 1,
@@ -413,13 +481,22 @@ impl Visitor {
             match item {
                 ProgramItem::TypeDef(type_def) => self.visit_type_def(type_def),
                 ProgramItem::Token(token) => {
-                    self.push_type_declaration(&mut token.name, None);
+                    self.push_type_declaration(&mut token.name);
                 }
                 ProgramItem::Script(_script) => (),
                 ProgramItem::Utxo(utxo) => {
-                    self.push_type_declaration(&mut utxo.name, None);
+                    let uid = self.push_type_declaration(&mut utxo.name);
+
+                    // declared up front, so `Utxo::NAME` can be used anywhere
+                    for item in &mut utxo.items {
+                        if let UtxoItem::Impl(utxo_impl) = item {
+                            for constant in &mut utxo_impl.constants {
+                                self.push_associated_constant_declaration(uid, &mut constant.name);
+                            }
+                        }
+                    }
                 }
-                ProgramItem::Constant { name, value: _ } => {
+                ProgramItem::Constant { name, .. } => {
                     self.push_constant_declaration(name);
                 }
                 ProgramItem::Abi(abi) => {
@@ -433,7 +510,7 @@ impl Visitor {
             ProgramItem::Token(_token) => 1,
             ProgramItem::Utxo(_utxo) => 2,
             ProgramItem::TypeDef(_type_def) => 3,
-            ProgramItem::Constant { name: _, value: _ } => 4,
+            ProgramItem::Constant { .. } => 4,
             ProgramItem::Script(_script) => 5,
         });
 
@@ -448,6 +525,12 @@ impl Visitor {
                 ProgramItem::Token(token) => {
                     self.visit_token(token);
                 }
+                ProgramItem::Constant { name: _, ty, value } => {
+                    if let Some(ty) = ty {
+                        self.visit_type_arg(ty);
+                    }
+                    self.visit_expr(value);
+                }
                 _ => (),
             }
         }
@@ -485,7 +568,7 @@ impl Visitor {
     }
 
     pub fn visit_utxo(&mut self, utxo: &mut Utxo) {
-        let uid = self.push_type_declaration(&mut utxo.name, None);
+        let uid = self.push_type_declaration(&mut utxo.name);
 
         // we need to put these into scope before doing anything else
         self.push_type_scope(uid);
@@ -518,16 +601,20 @@ impl Visitor {
         self.push_function_declaration(
             &mut Identifier::new("resume", None),
             FuncInfo {
-                inputs_ty: utxo
-                    .items
-                    .iter()
-                    .filter_map(|item| match item {
-                        UtxoItem::Resume(type_arg) => Some(type_arg.clone()),
-                        _ => None,
-                    })
-                    .chain(std::iter::once(TypeArg::Unit))
-                    .take(1)
-                    .map(|ty| TypeArg::Ref(Box::new(ty)))
+                // the receiver comes first, like any other method, so that the
+                // argument is checked against the declared `Resume` type
+                inputs_ty: std::iter::once(self_ty.clone())
+                    .chain(
+                        utxo.items
+                            .iter()
+                            .filter_map(|item| match item {
+                                UtxoItem::Resume(type_arg) => Some(type_arg.node.clone()),
+                                _ => None,
+                            })
+                            .chain(std::iter::once(TypeArg::Unit))
+                            .take(1)
+                            .map(|ty| TypeArg::Ref(Box::new(ty))),
+                    )
                     .collect(),
                 output_ty: Some(self_ty.clone()),
                 effects,
@@ -555,9 +642,55 @@ impl Visitor {
             },
         );
 
+        // abis implemented so far and the methods they provided, to reject
+        // duplicate impls and methods that would be ambiguous
+        let mut implemented_abis: HashMap<SymbolId, SimpleSpan> = HashMap::new();
+        let mut abi_methods: HashMap<String, SimpleSpan> = HashMap::new();
+
+        // `storage { a: T = expr; }` fields, run at the start of main
+        let storage_fields = utxo
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                UtxoItem::Storage(storage) => Some(
+                    storage
+                        .bindings
+                        .values
+                        .iter()
+                        .map(|(name, _ty)| name.clone())
+                        .zip(storage.initializers.iter().cloned()),
+                ),
+                _ => None,
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+
+        // without a main there's nowhere to run them
+        let has_main = utxo
+            .items
+            .iter()
+            .any(|item| matches!(item, UtxoItem::Main(_)));
+        if let Some((name, _)) = storage_fields
+            .iter()
+            .find(|(_, initializer)| initializer.is_some() && !has_main)
+        {
+            self.errors
+                .push(NameResolutionError::StorageInitializerWithoutMain {
+                    span: name.span.unwrap_or(SimpleSpan::from(0..0)),
+                });
+        }
+
+        self.check_unique_utxo_items(utxo);
+        let mut main_visited = false;
+
         for item in &mut utxo.items {
             match item {
                 UtxoItem::Main(main) => {
+                    // already reported by `check_unique_utxo_items`
+                    if std::mem::replace(&mut main_visited, true) {
+                        continue;
+                    }
+
                     if let Some(tys) = &mut main.type_sig {
                         for (_ident, ty) in &mut tys.values {
                             self.visit_type_arg(ty);
@@ -580,6 +713,13 @@ impl Visitor {
                                 .as_ref()
                                 .map(|args| args.values.iter().map(|arg| arg.1.clone()).collect())
                                 .unwrap_or(vec![]),
+                            inputs_names: main
+                                .type_sig
+                                .as_ref()
+                                .map(|args| {
+                                    args.values.iter().map(|arg| arg.0.raw.clone()).collect()
+                                })
+                                .unwrap_or(vec![]),
                             output_ty: Some(self_ty_ref.clone()),
                             effects,
                             locals: vec![],
@@ -593,6 +733,10 @@ impl Visitor {
 
                     self.declare_implicit_storage_var(uid, main.ident.uid.unwrap());
 
+                    // resolved before the arguments are declared, so that
+                    // initializers only see the utxo's scope
+                    let initializers = self.visit_storage_initializers(&storage_fields);
+
                     if let Some(tys) = &mut main.type_sig {
                         for (ident, _ty) in &mut tys.values {
                             self.push_var_declaration(
@@ -606,13 +750,91 @@ impl Visitor {
                     }
                     self.visit_block(&mut main.block, true);
                     self.pop_scope();
+
+                    for initializer in initializers.into_iter().rev() {
+                        let tail =
+                            std::mem::replace(&mut main.block, Block::Close { semicolon: false });
+                        main.block = Block::Chain {
+                            head: Box::new(ExprOrStatement::Statement(initializer)),
+                            tail: Box::new(tail),
+                        };
+                    }
                 }
                 UtxoItem::Impl(utxo_impl) => {
+                    for constant in &mut utxo_impl.constants {
+                        self.visit_type_arg(&mut constant.ty);
+                        self.visit_expr(&mut constant.value);
+                    }
+
+                    // `impl Utxo { ... }` adds to the utxo itself, not an abi
+                    if utxo_impl.name.raw == utxo.name.raw {
+                        utxo_impl.name.uid.replace(uid);
+
+                        self.visit_fn_defs(
+                            &mut utxo_impl.definitions,
+                            None,
+                            Some(utxo.name.clone()),
+                        );
+
+                        for definition in &utxo_impl.definitions {
+                            abi_methods.insert(
+                                definition.ident.raw.clone(),
+                                definition.ident.span.unwrap_or(SimpleSpan::from(0..0)),
+                            );
+                        }
+
+                        continue;
+                    }
+
                     let Some((abi, _)) = self.resolve_name(&mut utxo_impl.name, SymbolKind::Abi)
                     else {
-                        return;
+                        continue;
                     };
 
+                    let impl_span = utxo_impl.name.span.unwrap_or(SimpleSpan::from(0..0));
+
+                    if let Some(previous) = implemented_abis.insert(abi, impl_span) {
+                        self.errors.push(NameResolutionError::DuplicateImpl {
+                            ident: utxo_impl.name.clone(),
+                            previous,
+                        });
+
+                        continue;
+                    }
+
+                    let mut inherited = self.symbols.interfaces[&abi]
+                        .info
+                        .default_fns
+                        .values()
+                        .filter(|default| {
+                            !utxo_impl
+                                .definitions
+                                .iter()
+                                .any(|definition| definition.ident.raw == default.ident.raw)
+                        })
+                        .cloned()
+                        .collect::<Vec<_>>();
+
+                    // keep the output stable, since the order of the map isn't
+                    inherited.sort_by(|a, b| a.ident.raw.cmp(&b.ident.raw));
+
+                    utxo_impl.definitions.extend(inherited);
+
+                    // drop the conflicting definitions, otherwise they would
+                    // also be reported as redeclarations
+                    utxo_impl.definitions.retain(|definition| {
+                        let Some(previous) = abi_methods.get(&definition.ident.raw) else {
+                            return true;
+                        };
+
+                        self.errors.push(NameResolutionError::ConflictingAbiMethod {
+                            ident: definition.ident.clone(),
+                            previous: *previous,
+                        });
+
+                        false
+                    });
+
                     self.visit_fn_defs(
                         &mut utxo_impl.definitions,
                         Some(abi)
@@ -631,7 +853,7 @@ impl Visitor {
                             .get(&definition.ident.raw)
                         else {
                             self.push_not_found_error(definition.ident.span.unwrap());
-                            return;
+                            continue;
                         };
 
                         let impl_def = self
@@ -658,6 +880,13 @@ impl Visitor {
                         }
                     }
 
+                    for definition in &utxo_impl.definitions {
+                        abi_methods.insert(
+                            definition.ident.raw.clone(),
+                            definition.ident.span.unwrap_or(SimpleSpan::from(0..0)),
+                        );
+                    }
+
                     self.symbols
                         .types
                         .get_mut(&uid)
@@ -683,7 +912,6 @@ impl Visitor {
                         .storage
                         .replace(storage);
                 }
-                // TODO: check that there is only one yield and only one resume
                 UtxoItem::Yield(ty) => {
                     self.symbols
                         .types
@@ -691,7 +919,7 @@ impl Visitor {
                         .unwrap()
                         .info
                         .yield_ty
-                        .replace(ty.clone());
+                        .replace(ty.node.clone());
                 }
                 UtxoItem::Resume(ty) => {
                     self.symbols
@@ -700,7 +928,7 @@ impl Visitor {
                         .unwrap()
                         .info
                         .resume_ty
-                        .replace(ty.clone());
+                        .replace(ty.node.clone());
                 }
             }
         }
@@ -708,6 +936,78 @@ impl Visitor {
         self.pop_scope();
     }
 
+    /// A utxo has a single entry point and a single `Yield`/`Resume`
+    /// interface, so each of these can only be declared once.
+    fn check_unique_utxo_items(&mut self, utxo: &Utxo) {
+        let mut declared: HashMap<&str, SimpleSpan> = HashMap::new();
+
+        for item in &utxo.items {
+            let (name, span) = match item {
+                UtxoItem::Main(main) => ("main", main.ident.span.unwrap_or(SimpleSpan::from(0..0))),
+                UtxoItem::Yield(ty) => ("Yield", ty.span),
+                UtxoItem::Resume(ty) => ("Resume", ty.span),
+                UtxoItem::Impl(_) | UtxoItem::Storage(_) => continue,
+            };
+
+            if let Some(previous) = declared.get(name) {
+                self.push_redeclaration_error(&Identifier::new(name, Some(span)), *previous);
+            } else {
+                declared.insert(name, span);
+            }
+        }
+    }
+
+    /// Resolve the storage field initializers as `storage.a = expr;`
+    /// assignments, in declaration order.
+    ///
+    /// An initializer may only read the fields initialized before it, since
+    /// the others are not assigned until later in `main`.
+    fn visit_storage_initializers(
+        &mut self,
+        fields: &[(Identifier, Option<Spanned<Expr>>)],
+    ) -> Vec<Statement> {
+        // fields without an initializer stay in here, since only main itself
+        // assigns them
+        let mut uninitialized = fields
+            .iter()
+            .map(|(name, _)| name.raw.clone())
+            .collect::<Vec<_>>();
+
+        let mut statements = vec![];
+
+        for (name, initializer) in fields {
+            let Some(initializer) = initializer else {
+                continue;
+            };
+
+            let mut expr = initializer.clone();
+
+            self.uninitialized_storage_fields = Some(uninitialized.clone());
+            self.visit_expr(&mut expr);
+            self.uninitialized_storage_fields = None;
+
+            uninitialized.retain(|field| *field != name.raw);
+
+            let mut var = FieldAccessExpression::FieldAccess {
+                base: Box::new(FieldAccessExpression::PrimaryExpr(PrimaryExpr::Ident(
+                    IdentifierExpr {
+                        name: Identifier::new("storage", name.span),
+                        args: None,
+                    },
+                ))),
+                field: IdentifierExpr {
+                    name: name.clone(),
+                    args: None,
+                },
+            };
+            self.visit_secondary_expr(&mut var);
+
+            statements.push(Statement::Assign { var, expr });
+        }
+
+        statements
+    }
+
     fn declare_implicit_storage_var(&mut self, utxo_id: SymbolId, fn_id: SymbolId) {
         let mut implicit_storage_var = Identifier::new("storage", None);
         let storage_var = self.push_var_declaration(
@@ -870,7 +1170,7 @@ impl Visitor {
     }
 
     pub fn visit_type_def(&mut self, type_def: &mut TypeDef) {
-        self.push_type_declaration(&mut type_def.name, Some(type_def.ty.clone()));
+        let symbol = self.push_type_declaration(&mut type_def.name);
 
         match &mut type_def.ty {
             TypeDefRhs::TypeArg(type_arg) => self.visit_type_arg(type_arg),
@@ -882,20 +1182,44 @@ impl Visitor {
                 }
             }
             TypeDefRhs::Variant(variant) => {
-                for (variant, args) in &mut variant.0 {
-                    self.push_function_declaration(
+                let mut discriminants: HashMap<u64, SimpleSpan> = HashMap::new();
+                let values = variant.discriminants().collect::<Vec<_>>();
+
+                for ((variant, args, _), discriminant) in variant.0.iter_mut().zip(values) {
+                    let span = variant.span.unwrap_or(SimpleSpan::from(0..0));
+                    if let Some(previous) = discriminants.insert(discriminant, span) {
+                        self.errors
+                            .push(NameResolutionError::DuplicateDiscriminant {
+                                ident: variant.clone(),
+                                discriminant,
+                                previous,
+                            });
+                    }
+
+                    let constructor = self.push_function_declaration(
                         variant,
                         FuncInfo {
                             inputs_ty: args.values.iter().map(|arg| arg.1.clone()).collect(),
+                            inputs_names: args.values.iter().map(|arg| arg.0.raw.clone()).collect(),
                             output_ty: Some(TypeArg::TypeRef(TypeRef(type_def.name.clone()))),
                             effects: EffectSet::empty(),
                             locals: vec![],
+                            // cases without fields are represented by just
+                            // their discriminant, like a C enum
+                            is_constant: args.values.is_empty().then_some(discriminant),
                             ..Default::default()
                         },
                     );
+
+                    // so that `match` can find the cases of the type
+                    let type_info = self.symbols.types.get_mut(&symbol).unwrap();
+                    type_info.info.declarations.insert(constructor);
                 }
             }
         }
+
+        // stored once resolved, so that the types it mentions have their uids
+        self.symbols.set_type_def(symbol, Some(type_def.ty.clone()));
     }
 
     fn visit_fn_defs(
@@ -932,6 +1256,11 @@ impl Visitor {
                         .filter(|_| utxo.is_some())
                         .chain(definition.inputs.iter().map(|arg| arg.ty.clone()))
                         .collect(),
+                    inputs_names: definition
+                        .inputs
+                        .iter()
+                        .map(|arg| arg.name.raw.clone())
+                        .collect(),
                     output_ty: definition.output.clone(),
                     effects,
                     locals: vec![],
@@ -1013,10 +1342,55 @@ impl Visitor {
             SymbolInformation {
                 source: ident.raw.clone(),
                 span: ident.span,
-                info: ConstInfo { ty: None },
+                info: ConstInfo {
+                    ty: None,
+                    value: None,
+                },
+            },
+        );
+
+        symbol
+    }
+
+    fn push_associated_constant_declaration(
+        &mut self,
+        type_uid: SymbolId,
+        ident: &mut Identifier,
+    ) -> SymbolId {
+        let previous = self.symbols.types[&type_uid]
+            .info
+            .constants
+            .iter()
+            .map(|uid| &self.symbols.constants[uid])
+            .find(|constant| constant.source == ident.raw)
+            .map(|constant| constant.span.unwrap_or(SimpleSpan::from(0..0)));
+
+        if let Some(previous) = previous {
+            self.push_redeclaration_error(ident, previous);
+        }
+
+        let symbol = self.new_symbol(ident);
+
+        self.symbols.constants.insert(
+            symbol,
+            SymbolInformation {
+                source: ident.raw.clone(),
+                span: ident.span,
+                info: ConstInfo {
+                    ty: None,
+                    value: None,
+                },
             },
         );
 
+        self.symbols
+            .types
+            .get_mut(&type_uid)
+            .unwrap()
+            .info
+            .constants
+            .insert(symbol);
+
         symbol
     }
 
@@ -1074,11 +1448,7 @@ impl Visitor {
         symbol
     }
 
-    fn push_type_declaration(
-        &mut self,
-        ident: &mut Identifier,
-        type_def: Option<TypeDefRhs>,
-    ) -> SymbolId {
+    fn push_type_declaration(&mut self, ident: &mut Identifier) -> SymbolId {
         let symbol = self.new_symbol(ident);
 
         let scope = self.stack.last_mut().unwrap();
@@ -1089,16 +1459,7 @@ impl Visitor {
             SymbolInformation {
                 source: ident.raw.clone(),
                 span: ident.span,
-                info: TypeInfo {
-                    declarations: HashSet::new(),
-                    type_def,
-                    storage: None,
-                    yield_ty: None,
-                    resume_ty: None,
-                    interfaces: EffectSet::empty(),
-                    storage_ty: None,
-                    yield_fn: None,
-                },
+                info: TypeInfo::new(None),
             },
         );
 
@@ -1235,6 +1596,86 @@ impl Visitor {
         }
     }
 
+    fn visit_try_with(&mut self, block: &mut Block, items: &mut [(EffectHandler, Block)]) {
+        self.push_scope();
+
+        for (decl, body) in items {
+            let mut namespace = [&mut decl.interface];
+            self.resolve_name_in_namespace(&mut namespace, &mut decl.ident);
+
+            let Some(effect_id) = decl.ident.uid else {
+                return;
+            };
+
+            let effect_info = self.symbols.effects.get(&effect_id).unwrap();
+
+            let handler_id = self.push_function_declaration(
+                &mut decl.ident,
+                FuncInfo {
+                    inputs_ty: std::iter::once(TypeArg::U32)
+                        .chain(effect_info.info.inputs_ty.iter().cloned())
+                        .collect(),
+                    output_ty: effect_info.info.output_ty.clone(),
+                    effects: EffectSet::empty(),
+                    locals: vec![],
+                    is_effect_handler: Some(effect_id),
+                    ..Default::default()
+                },
+            );
+
+            self.symbols
+                .functions
+                .get_mut(&handler_id)
+                .unwrap()
+                .info
+                .mangled_name
+                .replace(format!(
+                    "starstream_handler_{}_{}",
+                    decl.ident.raw, handler_id.id
+                ));
+
+            self.push_closure_scope(decl.ident.uid.unwrap());
+
+            // not really used as a variable (although it would be
+            // a valid code transformation), but the types are only
+            // assigned to locals, so we need a dummy local for it.
+            let frame_var = self.push_var_declaration(
+                &mut Identifier::new("frame", None),
+                VarInfo {
+                    is_frame_pointer: true,
+                    ..Default::default()
+                },
+            );
+
+            // TODO: try to avoid the double lookup?
+            self.symbols
+                .functions
+                .get_mut(&handler_id)
+                .unwrap()
+                .info
+                .frame_var
+                .replace(frame_var);
+
+            for node in &mut decl.args {
+                self.push_var_declaration(
+                    &mut node.name,
+                    VarInfo {
+                        is_argument: true,
+                        ..Default::default()
+                    },
+                );
+            }
+
+            self.visit_block(body, false);
+
+            self.pop_scope();
+        }
+
+        self.visit_block(block, false);
+
+        self.pop_scope();
+    }
+
     fn visit_expr(&mut self, expr: &mut Spanned<Expr>) {
         match &mut expr.node {
             Expr::PrimaryExpr(secondary) => {
@@ -1251,6 +1692,19 @@ impl Visitor {
                 BlockExpr::Block(block) => {
                     self.visit_block(block, true);
                 }
+                BlockExpr::TryWith(block, items) => {
+                    self.visit_try_with(block, items);
+                }
+                BlockExpr::Loop(loop_body) => {
+                    self.visit_loop(None, loop_body);
+                }
+                BlockExpr::Match(scrutinee, arms) => {
+                    self.visit_expr(scrutinee);
+
+                    for arm in arms {
+                        self.visit_match_arm(arm);
+                    }
+                }
             },
             Expr::Equals(lhs, rhs)
             | Expr::NotEquals(lhs, rhs)
@@ -1308,115 +1762,217 @@ impl Visitor {
             Statement::Assign { var, expr } => {
                 self.visit_secondary_expr(var);
 
+                if let FieldAccessExpression::PrimaryExpr(PrimaryExpr::Ident(IdentifierExpr {
+                    name,
+                    args: None,
+                })) = var
+                {
+                    if name.uid.is_some_and(|uid| self.for_loop_vars.contains(&uid)) {
+                        self.errors.push(NameResolutionError::AssignToLoopVariable {
+                            ident: name.clone(),
+                        });
+                    }
+                }
+
                 self.visit_expr(expr);
             }
-            Statement::With(block, items) => {
-                self.push_scope();
-
-                for (decl, body) in items {
-                    let mut namespace = [&mut decl.interface];
-                    self.resolve_name_in_namespace(&mut namespace, &mut decl.ident);
+            Statement::While(expr, loop_body) => {
+                self.visit_expr(expr);
+                self.visit_loop(None, loop_body);
+            }
+            Statement::Loop(loop_body) => {
+                self.visit_loop(None, loop_body);
+            }
+            Statement::For {
+                var,
+                start,
+                end,
+                body,
+            } => self.visit_for(None, var, start, end, body),
+            Statement::Labeled(label, statement) => match &mut **statement {
+                Statement::While(expr, loop_body) => {
+                    self.visit_expr(expr);
+                    self.visit_loop(Some(label), loop_body);
+                }
+                Statement::Loop(loop_body) => self.visit_loop(Some(label), loop_body),
+                Statement::For {
+                    var,
+                    start,
+                    end,
+                    body,
+                } => self.visit_for(Some(label), var, start, end, body),
+                // the parser only labels loops
+                statement => self.visit_statement(statement),
+            },
+            Statement::Break { label, value, span } => {
+                if let Some(value) = value {
+                    self.visit_expr(value);
+                }
 
-                    let Some(effect_id) = decl.ident.uid else {
-                        return;
-                    };
+                self.resolve_loop_label(label.as_ref(), *span);
+            }
+            Statement::Continue { label, span } => {
+                self.resolve_loop_label(label.as_ref(), *span);
+            }
+        }
+    }
 
-                    let effect_info = self.symbols.effects.get(&effect_id).unwrap();
+    fn visit_loop(&mut self, label: Option<&LoopLabel>, loop_body: &mut LoopBody) {
+        self.stack.push(Scope {
+            is_loop_scope: Some(label.map(|label| label.name.clone())),
+            ..Default::default()
+        });
 
-                    let handler_id = self.push_function_declaration(
-                        &mut decl.ident,
-                        FuncInfo {
-                            inputs_ty: std::iter::once(TypeArg::U32)
-                                .chain(effect_info.info.inputs_ty.iter().cloned())
-                                .collect(),
-                            output_ty: effect_info.info.output_ty.clone(),
-                            effects: EffectSet::empty(),
-                            locals: vec![],
-                            is_effect_handler: Some(effect_id),
-                            ..Default::default()
-                        },
-                    );
+        self.visit_loop_body(loop_body);
 
-                    self.symbols
-                        .functions
-                        .get_mut(&handler_id)
-                        .unwrap()
-                        .info
-                        .mangled_name
-                        .replace(format!(
-                            "starstream_handler_{}_{}",
-                            decl.ident.raw, handler_id.id
-                        ));
+        self.pop_scope();
+    }
 
-                    self.push_closure_scope(decl.ident.uid.unwrap());
+    /// The bounds of a `for` are resolved outside the loop, while its variable
+    /// is only visible in the body.
+    fn visit_for(
+        &mut self,
+        label: Option<&LoopLabel>,
+        var: &mut Identifier,
+        start: &mut Spanned<Expr>,
+        end: &mut Spanned<Expr>,
+        loop_body: &mut LoopBody,
+    ) {
+        self.visit_expr(start);
+        self.visit_expr(end);
 
-                    // not really used as a variable (although it would be
-                    // a valid code transformation), but the types are only
-                    // assigned to locals, so we need a dummy local for it.
-                    let frame_var = self.push_var_declaration(
-                        &mut Identifier::new("frame", None),
-                        VarInfo {
-                            is_frame_pointer: true,
-                            ..Default::default()
-                        },
-                    );
+        self.stack.push(Scope {
+            is_loop_scope: Some(label.map(|label| label.name.clone())),
+            ..Default::default()
+        });
 
-                    // TODO: try to avoid the double lookup?
-                    self.symbols
-                        .functions
-                        .get_mut(&handler_id)
-                        .unwrap()
-                        .info
-                        .frame_var
-                        .replace(frame_var);
-
-                    for node in &mut decl.args {
-                        self.push_var_declaration(
-                            &mut node.name,
-                            VarInfo {
-                                is_argument: true,
-                                ..Default::default()
-                            },
-                        );
-                    }
+        let symbol = self.push_var_declaration(
+            var,
+            VarInfo {
+                mutable: false,
+                ..Default::default()
+            },
+        );
+        self.for_loop_vars.insert(symbol);
 
-                    self.visit_block(body, false);
+        self.visit_loop_body(loop_body);
 
-                    self.pop_scope();
-                }
+        self.pop_scope();
+    }
 
-                self.visit_block(block, false);
+    /// Resolve the case of an arm to its constructor, which has to be one of
+    /// the declarations of an enum, then bind its fields for the body.
+    fn visit_match_arm(&mut self, arm: &mut MatchArm) {
+        if let Some((constructor, _)) = self.resolve_name(&mut arm.variant, SymbolKind::Function) {
+            let is_variant = self.symbols.types.values().any(|ty| {
+                matches!(ty.info.type_def(), Some(TypeDefRhs::Variant(_)))
+                    && ty.info.declarations.contains(&constructor)
+            });
 
-                self.pop_scope();
-            }
-            Statement::While(expr, loop_body) => {
-                self.visit_expr(expr);
-                self.visit_loop_body(loop_body);
-            }
-            Statement::Loop(loop_body) => {
-                self.visit_loop_body(loop_body);
+            if !is_variant {
+                self.errors.push(NameResolutionError::NotAVariant {
+                    ident: arm.variant.clone(),
+                });
             }
         }
-    }
 
-    fn visit_loop_body(&mut self, loop_body: &mut LoopBody) {
-        match loop_body {
-            LoopBody::Statement(stmt) => self.visit_statement(stmt),
-            LoopBody::Block(block) => self.visit_block(block, true),
-            LoopBody::Expr(expr) => self.visit_expr(expr),
-        }
-    }
+        self.stack.push(Scope::default());
 
-    fn visit_secondary_expr(&mut self, expr: &mut FieldAccessExpression) {
-        match expr {
+        for binding in &mut arm.bindings {
+            self.push_var_declaration(
+                binding,
+                VarInfo {
+                    mutable: false,
+                    ..Default::default()
+                },
+            );
+        }
+
+        self.visit_block(&mut arm.body, false);
+
+        self.pop_scope();
+    }
+
+    /// Check that a `break` or `continue` has a loop to jump to, without
+    /// crossing into an enclosing function.
+    fn resolve_loop_label(&mut self, label: Option<&LoopLabel>, span: SimpleSpan) {
+        for scope in self.stack.iter().rev() {
+            if let Some(loop_label) = &scope.is_loop_scope {
+                match label {
+                    None => return,
+                    Some(label) if loop_label.as_ref() == Some(&label.name) => return,
+                    Some(_) => (),
+                }
+            }
+
+            if scope.is_function_scope.is_some() {
+                break;
+            }
+        }
+
+        self.errors.push(match label {
+            Some(label) => NameResolutionError::UndefinedLabel { span: label.span },
+            None => NameResolutionError::BreakOutsideLoop { span },
+        });
+    }
+
+    fn visit_loop_body(&mut self, loop_body: &mut LoopBody) {
+        match loop_body {
+            // a bare statement body still gets its own scope, so a `let`
+            // there doesn't leak past the loop
+            LoopBody::Statement(stmt) => {
+                self.push_scope();
+                self.visit_statement(stmt);
+                self.pop_scope();
+            }
+            LoopBody::Block(block) => self.visit_block(block, true),
+            LoopBody::Expr(expr) => self.visit_expr(expr),
+        }
+    }
+
+    fn visit_secondary_expr(&mut self, expr: &mut FieldAccessExpression) {
+        match expr {
             FieldAccessExpression::PrimaryExpr(primary_expr) => {
                 self.visit_primary_expr(primary_expr)
             }
             FieldAccessExpression::FieldAccess { base, field } => {
+                // the method isn't known until its receiver is typechecked
+                if let Some((name, _)) = field.args.iter().flat_map(|args| &args.named).next() {
+                    self.errors
+                        .push(NameResolutionError::NamedArgumentsNotAllowed {
+                            span: name.span.unwrap_or(SimpleSpan::from(0..0)),
+                        });
+                }
+
                 for arg in field.args.iter_mut().flat_map(|args| args.xs.iter_mut()) {
                     self.visit_expr(arg);
                 }
                 self.visit_secondary_expr(&mut *base);
+
+                if let (
+                    Some(uninitialized),
+                    FieldAccessExpression::PrimaryExpr(PrimaryExpr::Ident(IdentifierExpr {
+                        name,
+                        args: None,
+                    })),
+                ) = (&self.uninitialized_storage_fields, &**base)
+                {
+                    let is_storage = name
+                        .uid
+                        .and_then(|uid| self.symbols.vars.get(&uid))
+                        .is_some_and(|var| var.info.is_storage.is_some());
+
+                    if is_storage && uninitialized.contains(&field.name.raw) {
+                        self.errors
+                            .push(NameResolutionError::UninitializedStorageField {
+                                span: field.name.span.unwrap_or(SimpleSpan::from(0..0)),
+                            });
+                    }
+                }
+            }
+            FieldAccessExpression::Index { base, index } => {
+                self.visit_expr(index);
+                self.visit_secondary_expr(base);
             }
         }
     }
@@ -1424,7 +1980,9 @@ impl Visitor {
     fn visit_primary_expr(&mut self, expr: &mut PrimaryExpr) {
         match expr {
             PrimaryExpr::Number { .. } => (),
+            PrimaryExpr::TypedNumber(..) => (),
             PrimaryExpr::Bool(_) => (),
+            PrimaryExpr::Char(_) => (),
             PrimaryExpr::Ident(ident) | PrimaryExpr::Raise { ident } => {
                 self.resolve_name(
                     &mut ident.name,
@@ -1436,6 +1994,8 @@ impl Visitor {
                 );
 
                 if let Some(args) = &mut ident.args {
+                    self.resolve_named_arguments(&ident.name, args);
+
                     for expr in &mut args.xs {
                         self.visit_expr(expr);
                     }
@@ -1447,6 +2007,8 @@ impl Visitor {
 
                 // TODO: duplicated
                 if let Some(args) = &mut ident.args {
+                    self.resolve_named_arguments(&ident.name, args);
+
                     for expr in &mut args.xs {
                         self.visit_expr(expr);
                     }
@@ -1472,6 +2034,60 @@ impl Visitor {
         }
     }
 
+    /// Move the named arguments of a call to `callee` into the positional
+    /// ones, in the order the callee declares its parameters.
+    ///
+    /// Every parameter before the last one passed must be given, either by
+    /// position or by name. Arity is left to the typechecker.
+    fn resolve_named_arguments(&mut self, callee: &Identifier, args: &mut Arguments) {
+        if args.named.is_empty() {
+            return;
+        }
+
+        let names = callee
+            .uid
+            .and_then(|uid| self.symbols.functions.get(&uid))
+            .map(|f| f.info.inputs_names.clone())
+            .unwrap_or_default();
+
+        let positional = args.xs.len();
+        let mut slots = args.xs.drain(..).map(Some).collect::<Vec<_>>();
+        slots.resize(names.len().max(positional), None);
+
+        for (name, value) in args.named.drain(..) {
+            let Some(index) = names.iter().position(|param| *param == name.raw) else {
+                self.errors
+                    .push(NameResolutionError::UnknownArgument { ident: name });
+                continue;
+            };
+
+            if let Some(previous) = &slots[index] {
+                self.errors.push(NameResolutionError::DuplicateArgument {
+                    ident: name,
+                    previous: previous.span,
+                });
+                continue;
+            }
+
+            slots[index] = Some(value);
+        }
+
+        let passed = slots
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |last| last + 1);
+        for (index, slot) in slots[..passed].iter().enumerate() {
+            if slot.is_none() {
+                self.errors.push(NameResolutionError::MissingArgument {
+                    span: callee.span.unwrap_or(SimpleSpan::from(0..0)),
+                    name: names[index].clone(),
+                });
+            }
+        }
+
+        args.xs = slots.into_iter().flatten().collect();
+    }
+
     fn resolve_name_in_namespace<T>(&mut self, namespaces: &mut [T], ident: &mut Identifier)
     where
         T: AsMut<Identifier>,
@@ -1489,21 +2105,29 @@ impl Visitor {
         };
 
         let f = match kind {
-            SymbolKind::Type => self
-                .symbols
-                .types
-                .get(&namespace)
-                .unwrap()
-                .info
-                .declarations
-                .iter()
-                .find(|uid| {
-                    self.symbols
-                        .functions
-                        .get(uid)
-                        .map(|finfo| finfo.source == ident.raw)
-                        .unwrap_or(false)
-                }),
+            SymbolKind::Type => {
+                let type_info = &self.symbols.types.get(&namespace).unwrap().info;
+
+                type_info
+                    .declarations
+                    .iter()
+                    .find(|uid| {
+                        self.symbols
+                            .functions
+                            .get(uid)
+                            .map(|finfo| finfo.source == ident.raw)
+                            .unwrap_or(false)
+                    })
+                    .or_else(|| {
+                        type_info.constants.iter().find(|uid| {
+                            self.symbols
+                                .constants
+                                .get(uid)
+                                .map(|cinfo| cinfo.source == ident.raw)
+                                .unwrap_or(false)
+                        })
+                    })
+            }
             SymbolKind::Abi => self
                 .symbols
                 .interfaces
@@ -1532,9 +2156,34 @@ impl Visitor {
     fn visit_abi(&mut self, abi: &mut Abi, is_user_defined: bool) {
         let mut effects = HashSet::new();
         let mut fns = HashMap::new();
+        let mut default_fns = HashMap::new();
 
         for item in &mut abi.values {
             match item {
+                AbiElem::FnDef(definition) => {
+                    // the body is resolved once per impl that inherits it,
+                    // since it depends on the utxo's storage
+                    let mut sig = Sig {
+                        name: definition.ident.clone(),
+                        input_types: definition
+                            .inputs
+                            .iter()
+                            .map(|input| input.ty.clone())
+                            .collect(),
+                        output_type: definition.output.clone(),
+                    };
+
+                    for ty in &mut sig.input_types {
+                        self.visit_type_arg(ty);
+                    }
+
+                    if let Some(output_ty) = &mut sig.output_type {
+                        self.visit_type_arg(output_ty);
+                    }
+
+                    fns.insert(definition.ident.raw.clone(), sig);
+                    default_fns.insert(definition.ident.raw.clone(), definition.clone());
+                }
                 AbiElem::FnDecl(decl) => {
                     for ty in &mut decl.0.input_types {
                         self.visit_type_arg(ty);
@@ -1577,6 +2226,7 @@ impl Visitor {
             AbiInfo {
                 effects,
                 fns,
+                default_fns,
                 is_user_defined,
             },
         );
@@ -1593,6 +2243,8 @@ impl Visitor {
             TypeArg::I32 => (),
             TypeArg::U64 => (),
             TypeArg::I64 => (),
+            TypeArg::U128 => (),
+            TypeArg::I128 => (),
             TypeArg::Intermediate { abi, storage } => {
                 self.visit_type_arg(abi);
                 self.visit_type_arg(storage);
@@ -1617,6 +2269,16 @@ impl Visitor {
                 }
             }
             TypeArg::Ref(type_arg) => self.visit_type_arg(type_arg),
+            TypeArg::Tuple(elems) => {
+                for elem in elems {
+                    self.visit_type_arg(elem);
+                }
+            }
+            TypeArg::Array(elem, len) => {
+                self.visit_type_arg(elem);
+                self.visit_expr(&mut len.expr);
+                self.symbols.array_lens.push(len.clone());
+            }
         }
     }
 
@@ -1640,7 +2302,13 @@ impl Visitor {
 #[cfg(test)]
 mod tests {
     use super::do_scope_analysis;
-    use crate::ast::TypeArg;
+    use crate::{
+        ast::{
+            Block, Expr, ExprOrStatement, FieldAccessExpression, IdentifierExpr, PrimaryExpr,
+            ProgramItem, Statement, TypeArg, UtxoItem,
+        },
+        error::NameResolutionError,
+    };
     use ariadne::{Report, Source};
     use chumsky::Parser as _;
 
@@ -1777,15 +2445,11 @@ mod tests {
     }
 
     #[test]
-    fn shadowing() {
+    fn else_if_conditions_resolve() {
         let input = "
             script {
-              fn foo() {
-                let mut x = 5;
-                let y = 42;
-                let x = x + y;
-
-                x + x;
+              fn foo(a: bool, b: bool): u32 {
+                if (a) { 1 } else if (b) { 2 } else { 3 }
               }
             }
         ";
@@ -1794,46 +2458,34 @@ mod tests {
 
         let ast = do_scope_analysis(program);
 
-        match ast {
-            Err(_errors) => {
-                unreachable!();
-            }
-            Ok((_ast, table)) => {
-                let vars = table
-                    .vars
-                    .values()
-                    .filter(|info| info.source == "x")
-                    .collect::<Vec<_>>();
-
-                assert_eq!(vars.len(), 2);
+        assert!(ast.is_ok());
 
-                let first = vars
-                    .iter()
-                    .find(|info| info.info.wasm_local_index.unwrap() == 1)
-                    .unwrap();
+        let input = "
+            script {
+              fn foo(a: bool): u32 {
+                if (a) { 1 } else if (b) { 2 } else { 3 }
+              }
+            }
+        ";
 
-                let second = vars
-                    .iter()
-                    .find(|info| info.info.wasm_local_index.unwrap() == 3)
-                    .unwrap();
+        let program = crate::starstream_program().parse(input).unwrap();
 
-                assert!(first.info.mutable);
-                assert!(!second.info.mutable);
+        let ast = do_scope_analysis(program);
 
-                assert_eq!(table.vars.len(), 3);
-            }
-        }
+        assert!(matches!(
+            ast.err().as_deref(),
+            Some([NameResolutionError::NotFound { .. }])
+        ));
     }
 
     #[test]
-    fn script_function_order() {
+    fn loop_statement_body_scope() {
         let input = "
             script {
-              fn foo() {
-                  bar();
+              fn foo(c: bool): u32 {
+                while (c) let x = 1;
+                x
               }
-
-              fn bar() {}
             }
         ";
 
@@ -1841,25 +2493,17 @@ mod tests {
 
         let ast = do_scope_analysis(program);
 
-        match ast {
-            Err(errors) => {
-                for e in &errors {
-                    Report::from(e).eprint(Source::from(input)).unwrap();
-                }
-                unreachable!();
-            }
-            Ok((_ast, _table)) => {}
-        }
-    }
+        assert!(matches!(
+            ast.err().as_deref(),
+            Some([NameResolutionError::NotFound { .. }])
+        ));
 
-    #[test]
-    fn script_function_same_name_fails() {
         let input = "
             script {
-              fn foo() {
-              }
-
-              fn foo() {
+              fn foo(c: bool): u32 {
+                let x = 1;
+                while (c) let x = x + 1;
+                x
               }
             }
         ";
@@ -1868,37 +2512,35 @@ mod tests {
 
         let ast = do_scope_analysis(program);
 
-        match ast {
-            Err(_errors) => {
-                // for e in _errors {
-                //     e.eprint(Source::from(input)).unwrap();
-                // }
-            }
-            Ok((_ast, _table)) => {
-                unreachable!();
-            }
-        }
+        assert!(ast.is_ok());
     }
 
     #[test]
-    fn function_type_extraction() {
+    fn for_loop_variable_scope() {
         let input = "
-            abi Abi {
-                effect Effect1(bool): u32;
+            script {
+              fn foo(n: u32): u32 {
+                let mut total = 0;
+                for i in 0..n {
+                  total = total + i;
+                }
+                total
+              }
             }
+        ";
 
-            token MyToken {
-                mint {}
-            }
+        let program = crate::starstream_program().parse(input).unwrap();
 
-            script {
-              fn foo(): u32 / { Abi } {}
+        let ast = do_scope_analysis(program);
 
-              fn bar(i: u64): bool {}
+        assert!(ast.is_ok());
 
-              fn handler() {
-                try {}
-                with Abi::Effect1(x) { yield 4; }
+        // the loop variable isn't visible past the loop, nor in its bounds
+        let input = "
+            script {
+              fn foo(n: u32): u32 {
+                for i in 0..n { foo(i); }
+                i
               }
             }
         ";
@@ -1907,130 +2549,1092 @@ mod tests {
 
         let ast = do_scope_analysis(program);
 
-        match ast {
-            Err(errors) => {
-                for e in &errors {
-                    Report::from(e).eprint(Source::from(input)).unwrap();
-                }
-                panic!();
-            }
-            Ok((_ast, table)) => {
-                // unreachable!();
-                // dbg!(&table.functions);
-                // panic!();
-                //
-                let eff = table
-                    .effects
-                    .values()
-                    .find(|f| f.source == "Effect1")
-                    .unwrap();
+        assert!(matches!(
+            ast.err().as_deref(),
+            Some([NameResolutionError::NotFound { .. }])
+        ));
 
-                assert_eq!(eff.info.inputs_ty, vec![TypeArg::Bool]);
-                assert_eq!(eff.info.output_ty.clone().unwrap(), TypeArg::U32);
+        let input = "
+            script {
+              fn foo(n: u32) {
+                for i in 0..i { foo(n); }
+              }
+            }
+        ";
 
-                let foo = table
-                    .functions
-                    .values()
-                    .find(|f| f.source == "foo")
-                    .unwrap();
+        let program = crate::starstream_program().parse(input).unwrap();
 
-                assert_eq!(foo.info.inputs_ty, vec![]);
-                assert_eq!(foo.info.output_ty.clone().unwrap(), TypeArg::U32);
+        let ast = do_scope_analysis(program);
 
-                let bar = table
-                    .functions
-                    .values()
-                    .find(|f| f.source == "bar")
-                    .unwrap();
+        assert!(matches!(
+            ast.err().as_deref(),
+            Some([NameResolutionError::NotFound { .. }])
+        ));
 
-                assert_eq!(bar.info.inputs_ty, vec![TypeArg::U64]);
-                assert_eq!(bar.info.output_ty.clone().unwrap(), TypeArg::Bool);
+        // only the loop moves its variable along
+        let input = "
+            script {
+              fn foo(n: u32) {
+                for i in 0..n { i = i + 1; }
+              }
+            }
+        ";
 
-                let mint = table
-                    .functions
-                    .values()
-                    .find(|f| {
-                        f.info
-                            .mangled_name
-                            .as_ref()
-                            .map(|name| name.starts_with("starstream_mint_"))
-                            .unwrap_or(false)
-                    })
-                    .unwrap();
+        let program = crate::starstream_program().parse(input).unwrap();
 
-                assert_eq!(mint.info.inputs_ty, vec![TypeArg::U64]);
+        let ast = do_scope_analysis(program);
 
-                let TypeArg::Intermediate { .. } = mint.info.output_ty.clone().unwrap() else {
-                    panic!();
-                };
-            }
-        }
+        assert!(matches!(
+            ast.err().as_deref(),
+            Some([NameResolutionError::AssignToLoopVariable { .. }])
+        ));
     }
 
     #[test]
-    fn resolve_effect_handler_captures() {
+    fn match_arms() {
         let input = "
-            abi Abi {
-                effect Effect1(): u32;
-            }
+            typedef E = enum { One(), Two(x: u32) }
 
             script {
-              fn foo() {
-                let x = 3;
-                try {}
-                with Abi::Effect1() {
-                  let y = 5;
-
-                  resume x + y;
+              fn foo(e: E): u32 {
+                match (e) {
+                  One() => { 0 },
+                  Two(x) => { x },
+                }
+              }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let ast = do_scope_analysis(program);
+
+        assert!(ast.is_ok());
+
+        let input = "
+            typedef E = enum { One(), Two(x: u32) }
+
+            script {
+              fn foo(e: E): u32 {
+                match (e) {
+                  One() => { x },
+                  Two(x) => { x },
+                }
+              }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let ast = do_scope_analysis(program);
+
+        assert!(matches!(
+            ast.err().as_deref(),
+            Some([NameResolutionError::NotFound { .. }])
+        ));
+
+        let input = "
+            typedef E = enum { One(), Two(x: u32) }
+
+            script {
+              fn bar(): u32 { 1 }
+
+              fn foo(e: E): u32 {
+                match (e) {
+                  bar() => { 0 },
+                }
+              }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let ast = do_scope_analysis(program);
+
+        assert!(matches!(
+            ast.err().as_deref(),
+            Some([NameResolutionError::NotAVariant { .. }])
+        ));
+    }
+
+    #[test]
+    fn loop_labels() {
+        let input = "
+            script {
+              fn foo(c: bool) {
+                'outer: loop {
+                  while (c) {
+                    break 'outer;
+                  }
+                  continue;
+                }
+              }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        assert!(do_scope_analysis(program).is_ok());
+
+        let input = "
+            script {
+              fn foo() {
+                'outer: loop {
+                  break 'inner;
+                }
+              }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let ast = do_scope_analysis(program);
+
+        assert!(matches!(
+            ast.err().as_deref(),
+            Some([NameResolutionError::UndefinedLabel { .. }])
+        ));
+
+        let input = "
+            script {
+              fn foo() {
+                break;
+              }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let ast = do_scope_analysis(program);
+
+        assert!(matches!(
+            ast.err().as_deref(),
+            Some([NameResolutionError::BreakOutsideLoop { .. }])
+        ));
+    }
+
+    #[test]
+    fn shadowing() {
+        let input = "
+            script {
+              fn foo() {
+                let mut x = 5;
+                let y = 42;
+                let x = x + y;
+
+                x + x;
+              }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let ast = do_scope_analysis(program);
+
+        match ast {
+            Err(_errors) => {
+                unreachable!();
+            }
+            Ok((_ast, table)) => {
+                let vars = table
+                    .vars
+                    .values()
+                    .filter(|info| info.source == "x")
+                    .collect::<Vec<_>>();
+
+                assert_eq!(vars.len(), 2);
+
+                let first = vars
+                    .iter()
+                    .find(|info| info.info.wasm_local_index.unwrap() == 1)
+                    .unwrap();
+
+                let second = vars
+                    .iter()
+                    .find(|info| info.info.wasm_local_index.unwrap() == 3)
+                    .unwrap();
+
+                assert!(first.info.mutable);
+                assert!(!second.info.mutable);
+
+                assert_eq!(table.vars.len(), 3);
+            }
+        }
+    }
+
+    #[test]
+    fn script_function_order() {
+        let input = "
+            script {
+              fn foo() {
+                  bar();
+              }
+
+              fn bar() {}
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let ast = do_scope_analysis(program);
+
+        match ast {
+            Err(errors) => {
+                for e in &errors {
+                    Report::from(e).eprint(Source::from(input)).unwrap();
+                }
+                unreachable!();
+            }
+            Ok((_ast, _table)) => {}
+        }
+    }
+
+    #[test]
+    fn script_function_same_name_fails() {
+        let input = "
+            script {
+              fn foo() {
+              }
+
+              fn foo() {
+              }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let ast = do_scope_analysis(program);
+
+        match ast {
+            Err(_errors) => {
+                // for e in _errors {
+                //     e.eprint(Source::from(input)).unwrap();
+                // }
+            }
+            Ok((_ast, _table)) => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn function_type_extraction() {
+        let input = "
+            abi Abi {
+                effect Effect1(bool): u32;
+            }
+
+            token MyToken {
+                mint {}
+            }
+
+            script {
+              fn foo(): u32 / { Abi } {}
+
+              fn bar(i: u64): bool {}
+
+              fn handler() {
+                try {}
+                with Abi::Effect1(x) { yield 4; }
+              }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let ast = do_scope_analysis(program);
+
+        match ast {
+            Err(errors) => {
+                for e in &errors {
+                    Report::from(e).eprint(Source::from(input)).unwrap();
+                }
+                panic!();
+            }
+            Ok((_ast, table)) => {
+                // unreachable!();
+                // dbg!(&table.functions);
+                // panic!();
+                //
+                let eff = table
+                    .effects
+                    .values()
+                    .find(|f| f.source == "Effect1")
+                    .unwrap();
+
+                assert_eq!(eff.info.inputs_ty, vec![TypeArg::Bool]);
+                assert_eq!(eff.info.output_ty.clone().unwrap(), TypeArg::U32);
+
+                let foo = table
+                    .functions
+                    .values()
+                    .find(|f| f.source == "foo")
+                    .unwrap();
+
+                assert_eq!(foo.info.inputs_ty, vec![]);
+                assert_eq!(foo.info.output_ty.clone().unwrap(), TypeArg::U32);
+
+                let bar = table
+                    .functions
+                    .values()
+                    .find(|f| f.source == "bar")
+                    .unwrap();
+
+                assert_eq!(bar.info.inputs_ty, vec![TypeArg::U64]);
+                assert_eq!(bar.info.output_ty.clone().unwrap(), TypeArg::Bool);
+
+                let mint = table
+                    .functions
+                    .values()
+                    .find(|f| {
+                        f.info
+                            .mangled_name
+                            .as_ref()
+                            .map(|name| name.starts_with("starstream_mint_"))
+                            .unwrap_or(false)
+                    })
+                    .unwrap();
+
+                assert_eq!(mint.info.inputs_ty, vec![TypeArg::U64]);
+
+                let TypeArg::Intermediate { .. } = mint.info.output_ty.clone().unwrap() else {
+                    panic!();
+                };
+            }
+        }
+    }
+
+    #[test]
+    fn resolve_effect_handler_captures() {
+        let input = "
+            abi Abi {
+                effect Effect1(): u32;
+            }
+
+            script {
+              fn foo() {
+                let x = 3;
+                try {}
+                with Abi::Effect1() {
+                  let y = 5;
+
+                  resume x + y;
 
                   try {}
                   with Abi::Effect1() {
                       let z = 8;
 
-                      resume x + y + z;
-                  }
+                      resume x + y + z;
+                  }
+                }
+              }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let ast = do_scope_analysis(program);
+
+        let symbols = match ast {
+            Err(errors) => {
+                for e in &errors {
+                    let e = Report::from(e);
+                    e.eprint(Source::from(input)).unwrap();
+                }
+                unreachable!();
+            }
+            Ok((_ast, table)) => table,
+        };
+
+        let x = symbols
+            .vars
+            .values()
+            .find(|info| info.source == "x")
+            .unwrap();
+
+        assert!(x.info.is_captured);
+
+        let y = symbols
+            .vars
+            .values()
+            .find(|info| info.source == "y")
+            .unwrap();
+
+        assert!(y.info.is_captured);
+
+        let z = symbols
+            .vars
+            .values()
+            .find(|info| info.source == "z")
+            .unwrap();
+
+        assert!(!z.info.is_captured);
+    }
+
+    #[test]
+    fn abi_default_method_inherited() {
+        let input = "
+            abi Abi {
+                fn foo(): u32;
+                fn bar(): u32 {
+                    42
+                }
+            }
+
+            utxo U {
+                main {}
+
+                impl Abi {
+                    fn foo(): u32 {
+                        1
+                    }
+                }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let (ast, table) = match do_scope_analysis(program) {
+            Err(errors) => {
+                for e in &errors {
+                    Report::from(e).eprint(Source::from(input)).unwrap();
+                }
+                unreachable!();
+            }
+            Ok(result) => result,
+        };
+
+        let ProgramItem::Utxo(utxo) = &ast.items[1].node else {
+            unreachable!();
+        };
+
+        let UtxoItem::Impl(utxo_impl) = &utxo.items[1] else {
+            unreachable!();
+        };
+
+        let names = utxo_impl
+            .definitions
+            .iter()
+            .map(|definition| definition.ident.raw.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, vec!["foo", "bar"]);
+
+        let bar = table
+            .functions
+            .values()
+            .find(|info| info.source == "bar")
+            .unwrap();
+
+        assert!(bar.info.is_utxo_method.is_some());
+    }
+
+    #[test]
+    fn abi_default_method_overridden() {
+        let input = "
+            abi Abi {
+                fn bar(): u32 {
+                    42
+                }
+            }
+
+            utxo U {
+                main {}
+
+                impl Abi {
+                    fn bar(): u32 {
+                        1
+                    }
+                }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let (_ast, table) = match do_scope_analysis(program) {
+            Err(errors) => {
+                for e in &errors {
+                    Report::from(e).eprint(Source::from(input)).unwrap();
+                }
+                unreachable!();
+            }
+            Ok(result) => result,
+        };
+
+        let bars = table
+            .functions
+            .values()
+            .filter(|info| info.source == "bar")
+            .collect::<Vec<_>>();
+
+        assert_eq!(bars.len(), 1);
+
+        // the definition from the impl wins over the default one
+        assert!(bars[0].span.unwrap().start > input.find("utxo").unwrap());
+    }
+
+    #[test]
+    fn utxo_implements_multiple_abis() {
+        let input = "
+            abi A {
+                fn foo(): u32;
+            }
+
+            abi B {
+                fn bar(): u32;
+            }
+
+            utxo U {
+                main {}
+
+                impl A {
+                    fn foo(): u32 {
+                        1
+                    }
+                }
+
+                impl B {
+                    fn bar(): u32 {
+                        2
+                    }
                 }
-              }
             }
         ";
 
         let program = crate::starstream_program().parse(input).unwrap();
 
-        let ast = do_scope_analysis(program);
-
-        let symbols = match ast {
+        let (_ast, table) = match do_scope_analysis(program) {
             Err(errors) => {
                 for e in &errors {
-                    let e = Report::from(e);
-                    e.eprint(Source::from(input)).unwrap();
+                    Report::from(e).eprint(Source::from(input)).unwrap();
                 }
                 unreachable!();
             }
-            Ok((_ast, table)) => table,
+            Ok(result) => result,
         };
 
-        let x = symbols
-            .vars
+        let utxo = table
+            .types
             .values()
-            .find(|info| info.source == "x")
+            .find(|info| info.source == "U")
             .unwrap();
 
-        assert!(x.info.is_captured);
+        assert_eq!(utxo.info.interfaces.iter().count(), 2);
+    }
 
-        let y = symbols
-            .vars
-            .values()
-            .find(|info| info.source == "y")
-            .unwrap();
+    #[test]
+    fn utxo_duplicate_abi_impl() {
+        let input = "
+            abi A {
+                fn foo(): u32;
+            }
 
-        assert!(y.info.is_captured);
+            utxo U {
+                main {}
 
-        let z = symbols
-            .vars
+                impl A {
+                    fn foo(): u32 {
+                        1
+                    }
+                }
+
+                impl A {
+                    fn foo(): u32 {
+                        2
+                    }
+                }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let errors = do_scope_analysis(program).err().unwrap();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [NameResolutionError::DuplicateImpl { .. }]
+        ));
+    }
+
+    #[test]
+    fn utxo_conflicting_abi_methods() {
+        let input = "
+            abi A {
+                fn foo(): u32;
+            }
+
+            abi B {
+                fn foo(): u32;
+            }
+
+            utxo U {
+                main {}
+
+                impl A {
+                    fn foo(): u32 {
+                        1
+                    }
+                }
+
+                impl B {
+                    fn foo(): u32 {
+                        2
+                    }
+                }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let errors = do_scope_analysis(program).err().unwrap();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [NameResolutionError::ConflictingAbiMethod { .. }]
+        ));
+    }
+
+    #[test]
+    fn associated_constants() {
+        let input = "
+            const TWICE = U::ID * 2;
+
+            utxo U {
+                main {}
+
+                impl U {
+                    const ID: u64 = 1003;
+                }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let (ast, symbols) = do_scope_analysis(program).unwrap();
+
+        let ProgramItem::Utxo(utxo) = &ast.items[1].node else {
+            unreachable!()
+        };
+        let UtxoItem::Impl(utxo_impl) = &utxo.items[1] else {
+            unreachable!()
+        };
+        let id = utxo_impl.constants[0].name.uid.unwrap();
+        assert!(
+            symbols.types[&utxo.name.uid.unwrap()]
+                .info
+                .constants
+                .contains(&id)
+        );
+
+        // referenced through the namespace before the utxo is declared
+        let ProgramItem::Constant { value, .. } = &ast.items[0].node else {
+            unreachable!()
+        };
+        let Expr::Mul(lhs, _) = &value.node else {
+            unreachable!()
+        };
+        let Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(PrimaryExpr::Namespace {
+            namespaces: _,
+            ident,
+        })) = &lhs.node
+        else {
+            unreachable!()
+        };
+        assert_eq!(ident.name.uid, Some(id));
+    }
+
+    #[test]
+    fn duplicate_utxo_items() {
+        for items in [
+            "main {} main {}",
+            "Yield u32 Yield bool main {}",
+            "Resume u32 main {} Resume u32",
+        ] {
+            let input = format!("utxo U {{ {items} }}");
+
+            let program = crate::starstream_program().parse(&input).unwrap();
+
+            let errors = do_scope_analysis(program).err().unwrap();
+
+            assert!(
+                matches!(
+                    errors.as_slice(),
+                    [NameResolutionError::RedeclarationError { ident, previous }]
+                        if ident.span.unwrap().start > previous.start
+                ),
+                "{items}: {errors:?}"
+            );
+        }
+
+        let input = "utxo U { Yield u32 Resume bool main {} }";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        assert!(do_scope_analysis(program).is_ok());
+    }
+
+    #[test]
+    fn associated_constant_errors() {
+        let input = "
+            utxo U {
+                main {}
+
+                impl U {
+                    const ID: u64 = 1;
+                    const ID: u64 = 2;
+                }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let errors = do_scope_analysis(program).err().unwrap();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [NameResolutionError::RedeclarationError { .. }]
+        ));
+
+        // only reachable through the type
+        let input = "
+            script {
+                fn f(): u64 {
+                    ID
+                }
+            }
+
+            utxo U {
+                main {}
+
+                impl U {
+                    const ID: u64 = 1;
+                }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let errors = do_scope_analysis(program).err().unwrap();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [NameResolutionError::NotFound { .. }]
+        ));
+    }
+
+    #[test]
+    fn builtin_type_methods() {
+        let input = "
+            script {
+              fn foo(token: Intermediate<any, any>): u32 {
+                let list = List::new();
+                List::push(list, token);
+                List::len(list)
+              }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let (_ast, table) = match do_scope_analysis(program) {
+            Err(errors) => {
+                for e in &errors {
+                    Report::from(e).eprint(Source::from(input)).unwrap();
+                }
+                unreachable!();
+            }
+            Ok(result) => result,
+        };
+
+        let list = table
+            .types
             .values()
-            .find(|info| info.source == "z")
+            .find(|info| info.source == "List")
             .unwrap();
 
-        assert!(!z.info.is_captured);
+        let methods = list
+            .info
+            .declarations
+            .iter()
+            .map(|uid| table.functions[uid].source.as_str())
+            .collect::<std::collections::HashSet<_>>();
+
+        assert_eq!(methods, ["new", "push", "len"].into_iter().collect());
+
+        let input = "
+            script {
+              fn foo(): u32 {
+                let list = List::new();
+                List::pop(list)
+              }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        assert!(do_scope_analysis(program).is_err());
+    }
+
+    #[test]
+    fn storage_initializers() {
+        let input = "
+            const LIMIT = 3;
+
+            utxo U {
+                main {}
+
+                storage {
+                    limit: u32 = LIMIT;
+                    doubled: u32 = storage.limit * 2;
+                    count: u32;
+                }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let (ast, _symbols) = do_scope_analysis(program).unwrap();
+
+        let ProgramItem::Utxo(utxo) = &ast.items[1].node else {
+            unreachable!()
+        };
+        let UtxoItem::Main(main) = &utxo.items[0] else {
+            unreachable!()
+        };
+
+        // main starts with the initializers, in declaration order
+        let mut assigned = vec![];
+        let mut block = &main.block;
+        while let Block::Chain { head, tail } = block {
+            if let ExprOrStatement::Statement(Statement::Assign {
+                var: FieldAccessExpression::FieldAccess { base, field },
+                expr: _,
+            }) = &**head
+            {
+                let FieldAccessExpression::PrimaryExpr(PrimaryExpr::Ident(storage)) = &**base
+                else {
+                    unreachable!()
+                };
+                assert!(storage.name.uid.is_some());
+                assigned.push(field.name.raw.as_str());
+            }
+            block = tail;
+        }
+
+        assert_eq!(assigned, ["limit", "doubled"]);
+    }
+
+    #[test]
+    fn storage_initializer_order() {
+        let input = "
+            utxo U {
+                main {}
+
+                storage {
+                    a: u32 = storage.b + 1;
+                    b: u32 = 1;
+                }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let errors = do_scope_analysis(program).err().unwrap();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [NameResolutionError::UninitializedStorageField { .. }]
+        ));
+
+        // main's arguments aren't in scope for the initializers
+        let input = "
+            utxo U {
+                main(x: u32) {}
+
+                storage {
+                    a: u32 = x;
+                }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let errors = do_scope_analysis(program).err().unwrap();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [NameResolutionError::NotFound { .. }]
+        ));
+
+        // a utxo without main has nowhere to run them
+        let input = "
+            utxo U {
+                storage {
+                    a: u32;
+                    b: u32 = 1;
+                }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let errors = do_scope_analysis(program).err().unwrap();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [NameResolutionError::StorageInitializerWithoutMain { .. }]
+        ));
+    }
+
+    #[test]
+    fn named_arguments() {
+        let input = "
+            script {
+                fn transfer(from: u32, to: u32, amount: u32) {}
+
+                fn main() {
+                    transfer(1, amount: 3, to: 2);
+                }
+            }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let (program, _symbols) = do_scope_analysis(program).unwrap();
+
+        let ProgramItem::Script(script) = &program.items[0].node else {
+            panic!("expected a script");
+        };
+        let Block::Chain { head, tail: _ } = &script.definitions[1].body else {
+            panic!("expected a call");
+        };
+        let ExprOrStatement::Expr(call) = &**head else {
+            panic!("expected a call");
+        };
+        let Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(PrimaryExpr::Ident(
+            IdentifierExpr {
+                args: Some(args), ..
+            },
+        ))) = &call.node
+        else {
+            panic!("expected a call");
+        };
+
+        assert!(args.named.is_empty());
+        let literals =
+            args.xs
+                .iter()
+                .map(|arg| match &arg.node {
+                    Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(
+                        PrimaryExpr::Number { literal, .. },
+                    )) => *literal,
+                    other => panic!("unexpected argument {other:?}"),
+                })
+                .collect::<Vec<_>>();
+        assert_eq!(literals, [1, 2, 3]);
+    }
+
+    #[test]
+    fn named_argument_errors() {
+        let errors = |call: &str| {
+            let input = format!(
+                "
+                utxo U {{
+                    main {{}}
+
+                    impl A {{
+                        fn f(x: u32) {{}}
+                    }}
+                }}
+
+                abi A {{
+                    fn f(u32);
+                }}
+
+                script {{
+                    fn g(a: u32, b: u32) {{}}
+
+                    fn main() {{
+                        let u = U::new();
+                        {call};
+                    }}
+                }}
+                "
+            );
+
+            let program = crate::starstream_program().parse(&input).unwrap();
+
+            do_scope_analysis(program).err().unwrap_or_default()
+        };
+
+        assert!(matches!(
+            errors("g(c: 1)").as_slice(),
+            [NameResolutionError::UnknownArgument { .. }]
+        ));
+        assert!(matches!(
+            errors("g(1, a: 2)").as_slice(),
+            [NameResolutionError::DuplicateArgument { .. }]
+        ));
+        assert!(matches!(
+            errors("g(b: 1, b: 2)").as_slice(),
+            [
+                NameResolutionError::DuplicateArgument { .. },
+                NameResolutionError::MissingArgument { .. }
+            ]
+        ));
+        assert!(matches!(
+            errors("g(b: 2)").as_slice(),
+            [NameResolutionError::MissingArgument { .. }]
+        ));
+        assert!(matches!(
+            errors("u.f(x: 1)").as_slice(),
+            [NameResolutionError::NamedArgumentsNotAllowed { .. }]
+        ));
+        assert!(errors("g(b: 2, a: 1)").is_empty());
+    }
+
+    #[test]
+    fn enum_discriminants() {
+        let input = "
+            typedef E = enum { One() = 3, Two(x: u32) = 7, Three() }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let (_ast, symbols) = do_scope_analysis(program).unwrap();
+
+        let discriminant = |name: &str| {
+            symbols
+                .functions
+                .values()
+                .find(|f| f.source == name)
+                .unwrap()
+                .info
+                .is_constant
+        };
+
+        assert_eq!(discriminant("One"), Some(3));
+        // cases with fields aren't lowered to a constant
+        assert_eq!(discriminant("Two"), None);
+        assert_eq!(discriminant("Three"), Some(8));
+    }
+
+    #[test]
+    fn duplicate_enum_discriminant() {
+        let input = "
+            typedef E = enum { One() = 1, Two(), Three() = 2 }
+        ";
+
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        let errors = do_scope_analysis(program).err().unwrap();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [NameResolutionError::DuplicateDiscriminant {
+                discriminant: 2,
+                ..
+            }]
+        ));
     }
 }
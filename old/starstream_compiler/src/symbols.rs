@@ -1,9 +1,12 @@
 use crate::{
-    ast::{Sig, Storage, TypeArg, TypeDefRhs},
+    ast::{ArrayLen, FnDef, Sig, Storage, TypeArg, TypeDefRhs},
     typechecking::{ComparableType, EffectSet, TypeVar},
 };
 use chumsky::span::SimpleSpan;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::{
+    cell::OnceCell,
+    collections::{BTreeMap, HashMap, HashSet},
+};
 
 #[derive(Debug, Default)]
 pub struct Symbols {
@@ -20,6 +23,63 @@ pub struct Symbols {
 
     // stores unification results after type inference
     pub type_vars: HashMap<TypeVar, ComparableType>,
+
+    // the length of every array type, for type inference to evaluate
+    pub array_lens: Vec<ArrayLen>,
+}
+
+impl Symbols {
+    /// Replace the definition of the type `id`.
+    ///
+    /// Canonical forms embed the definitions of every type they mention, so
+    /// all the memoized ones are dropped, not just that of `id`.
+    pub fn set_type_def(&mut self, id: SymbolId, type_def: Option<TypeDefRhs>) {
+        for ty in self.types.values_mut() {
+            ty.info.canonical_form.take();
+        }
+
+        self.types.get_mut(&id).unwrap().info.type_def = type_def;
+    }
+
+    /// Every abi in scope, builtin ones included, sorted by name.
+    pub fn abis(&self) -> impl Iterator<Item = (&str, &AbiInfo)> {
+        let mut abis = self
+            .interfaces
+            .values()
+            .map(|abi| (abi.source.as_str(), &abi.info))
+            .collect::<Vec<_>>();
+        abis.sort_by_key(|(name, _)| *name);
+        abis.into_iter()
+    }
+
+    /// The effects, events and errors declared by the abi called `name`,
+    /// sorted by name. `None` if there is no such abi.
+    pub fn effects_of_abi(&self, name: &str) -> Option<impl Iterator<Item = (&str, &EffectInfo)>> {
+        let (_, abi) = self.abis().find(|(abi_name, _)| *abi_name == name)?;
+
+        let mut effects = abi
+            .effects
+            .iter()
+            .map(|effect_id| {
+                let effect = &self.effects[effect_id];
+                (effect.source.as_str(), &effect.info)
+            })
+            .collect::<Vec<_>>();
+        effects.sort_by_key(|(name, _)| *name);
+        Some(effects.into_iter())
+    }
+
+    /// The utxo types declared by the program, sorted by name.
+    pub fn utxo_types(&self) -> impl Iterator<Item = (&str, &TypeInfo)> {
+        let mut utxos = self
+            .types
+            .values()
+            .filter(|ty| ty.info.yield_fn.is_some())
+            .map(|ty| (ty.source.as_str(), &ty.info))
+            .collect::<Vec<_>>();
+        utxos.sort_by_key(|(name, _)| *name);
+        utxos.into_iter()
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -41,20 +101,53 @@ pub struct VarInfo {
 #[derive(Debug, Clone)]
 pub struct TypeInfo {
     pub declarations: HashSet<SymbolId>,
+    /// Associated constants, declared in the type's `impl` blocks.
+    pub constants: HashSet<SymbolId>,
 
     pub storage: Option<Storage>,
     pub storage_ty: Option<ComparableType>,
     // TODO: may want to separate typedefs from utxo and token types
-    pub type_def: Option<TypeDefRhs>,
+    type_def: Option<TypeDefRhs>,
+    /// Memoized canonical form of `type_def`, reset by
+    /// [`Symbols::set_type_def`].
+    canonical_form: OnceCell<ComparableType>,
     pub yield_ty: Option<TypeArg>,
     pub resume_ty: Option<TypeArg>,
     pub interfaces: EffectSet,
     pub yield_fn: Option<SymbolId>,
 }
 
+impl TypeInfo {
+    pub fn new(type_def: Option<TypeDefRhs>) -> Self {
+        Self {
+            declarations: HashSet::new(),
+            constants: HashSet::new(),
+            storage: None,
+            storage_ty: None,
+            type_def,
+            canonical_form: OnceCell::new(),
+            yield_ty: None,
+            resume_ty: None,
+            interfaces: EffectSet::empty(),
+            yield_fn: None,
+        }
+    }
+
+    pub fn type_def(&self) -> Option<&TypeDefRhs> {
+        self.type_def.as_ref()
+    }
+
+    pub fn canonical_form(&self) -> &OnceCell<ComparableType> {
+        &self.canonical_form
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct FuncInfo {
     pub inputs_ty: Vec<TypeArg>,
+    /// Declared parameter names, for named arguments. Doesn't include the
+    /// receiver of a method, and is empty if the parameters aren't named.
+    pub inputs_names: Vec<String>,
     pub output_ty: Option<TypeArg>,
 
     pub output_canonical_ty: Option<ComparableType>,
@@ -117,12 +210,37 @@ pub enum ArgOrConst {
 #[derive(Debug, Clone)]
 pub struct ConstInfo {
     pub ty: Option<ComparableType>,
+    /// Value of the initializer after constant evaluation.
+    pub value: Option<ConstValue>,
+}
+
+/// The value of a constant, within the range of its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstValue {
+    /// A signed integer.
+    I64(i64),
+    /// An unsigned integer.
+    U64(u64),
+    Bool(bool),
+}
+
+impl ConstValue {
+    /// The value as the bits of a wasm integer, `bool`s being 0 or 1.
+    pub fn bits(self) -> i64 {
+        match self {
+            ConstValue::I64(value) => value,
+            ConstValue::U64(value) => value as i64,
+            ConstValue::Bool(value) => value.into(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct AbiInfo {
     pub effects: HashSet<SymbolId>,
     pub fns: HashMap<String, Sig>,
+    /// Default method bodies, copied into impls that don't override them.
+    pub default_fns: HashMap<String, FnDef>,
 
     pub is_user_defined: bool,
 }
@@ -149,3 +267,94 @@ pub struct SymbolInformation<T> {
 pub struct SymbolId {
     pub id: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{SymbolId, Symbols};
+    use crate::{
+        ast::{Identifier, TypeArg, TypeDefRhs, TypeRef},
+        do_scope_analysis, starstream_program,
+        typechecking::{ComparableType, PrimitiveType},
+    };
+    use chumsky::Parser as _;
+
+    #[test]
+    fn list_oracle_abis() {
+        let input = include_str!("../../grammar/examples/oracle.star");
+        let program = starstream_program().parse(input).unwrap();
+        let (_, symbols) = do_scope_analysis(program).unwrap();
+
+        let abis = symbols.abis().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(abis, ["Oracle", "StarstreamEnv", "StarstreamToken"]);
+
+        let effects = symbols
+            .effects_of_abi("Oracle")
+            .unwrap()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(effects, ["Error"]);
+
+        let effects = symbols
+            .effects_of_abi("StarstreamEnv")
+            .unwrap()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            effects,
+            ["Caller", "CoordinationCode", "IsTxSignedBy", "ThisCode"]
+        );
+
+        assert!(symbols.effects_of_abi("Missing").is_none());
+
+        let utxos = symbols
+            .utxo_types()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(utxos, ["OracleContract", "PayToPublicKeyHash"]);
+    }
+
+    #[test]
+    fn canonical_form_memoized() {
+        let input = "
+            typedef Inner = { a: u32, b: bool }
+            typedef Outer = { x: Inner, y: Inner }
+        ";
+        let program = starstream_program().parse(input).unwrap();
+        let (_, mut symbols) = do_scope_analysis(program).unwrap();
+
+        let id_of = |symbols: &Symbols, name: &str| -> SymbolId {
+            symbols
+                .types
+                .iter()
+                .find(|(_, ty)| ty.source == name)
+                .map(|(id, _)| *id)
+                .unwrap()
+        };
+        let inner = id_of(&symbols, "Inner");
+        let outer = id_of(&symbols, "Outer");
+        let outer_ref = TypeArg::TypeRef(TypeRef(Identifier {
+            uid: Some(outer),
+            ..Identifier::new("Outer", None)
+        }));
+        let is_memoized = |symbols: &Symbols, id: SymbolId| {
+            symbols.types[&id].info.canonical_form().get().is_some()
+        };
+
+        assert!(!is_memoized(&symbols, outer));
+        let form = outer_ref.canonical_form(&symbols);
+        // expanding `Outer` expands `Inner` once, for both of its fields
+        assert!(is_memoized(&symbols, outer));
+        assert!(is_memoized(&symbols, inner));
+        assert_eq!(outer_ref.canonical_form(&symbols), form);
+
+        symbols.set_type_def(inner, Some(TypeDefRhs::TypeArg(TypeArg::U64)));
+        assert!(!is_memoized(&symbols, outer));
+        assert!(!is_memoized(&symbols, inner));
+
+        let u64 = ComparableType::Primitive(PrimitiveType::U64);
+        assert_eq!(
+            outer_ref.canonical_form(&symbols),
+            ComparableType::Product(vec![("x".to_owned(), u64.clone()), ("y".to_owned(), u64)])
+        );
+    }
+}
@@ -0,0 +1,465 @@
+use super::{
+    ComparableType, PrimitiveType,
+    error::{
+        error_const_division_by_zero, error_const_overflow, error_negative_array_length,
+        error_non_constant, error_type_mismatch,
+    },
+};
+use crate::{
+    ast::{
+        Expr, FieldAccessExpression, PrimaryExpr, ProgramItem, Spanned, StarstreamProgram, TypeArg,
+        UtxoItem,
+    },
+    error::TypeError,
+    symbols::{ConstValue, SymbolId},
+};
+use chumsky::span::SimpleSpan;
+use std::collections::{HashMap, HashSet};
+
+/// Compile-time evaluator for integer and boolean expressions.
+///
+/// Only literals, references to other `const` items, integer arithmetic
+/// (`+ - * / %`, shifts and bitwise operators), comparisons and boolean
+/// operators are allowed. Constants can reference each other regardless of
+/// declaration order, they are evaluated on demand.
+///
+/// Integers are evaluated exactly, and only have to fit the constant's type
+/// once it is folded. The default evaluator knows no constants, so it only
+/// evaluates literals.
+#[derive(Default)]
+pub struct ConstEvaluator<'a> {
+    definitions: HashMap<SymbolId, (&'a Spanned<Expr>, Option<&'a TypeArg>)>,
+    values: HashMap<SymbolId, ConstValue>,
+    in_progress: HashSet<SymbolId>,
+}
+
+/// An intermediate value of a constant expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Folded {
+    Int(i128),
+    Bool(bool),
+}
+
+impl Folded {
+    /// The type reported when the value is used where it doesn't fit.
+    fn ty(self) -> ComparableType {
+        match self {
+            Folded::Int(_) => ComparableType::u32(),
+            Folded::Bool(_) => ComparableType::boolean(),
+        }
+    }
+}
+
+impl From<ConstValue> for Folded {
+    fn from(value: ConstValue) -> Self {
+        match value {
+            ConstValue::I64(value) => Folded::Int(value.into()),
+            ConstValue::U64(value) => Folded::Int(value.into()),
+            ConstValue::Bool(value) => Folded::Bool(value),
+        }
+    }
+}
+
+impl<'a> ConstEvaluator<'a> {
+    pub fn new(program: &'a StarstreamProgram) -> Self {
+        let mut definitions = HashMap::new();
+
+        for item in &program.items {
+            match &item.node {
+                ProgramItem::Constant { name, ty, value } => {
+                    definitions.insert(name.uid.unwrap(), (value, ty.as_ref()));
+                }
+                ProgramItem::Utxo(utxo) => {
+                    for item in &utxo.items {
+                        if let UtxoItem::Impl(utxo_impl) = item {
+                            for constant in &utxo_impl.constants {
+                                definitions.insert(
+                                    constant.name.uid.unwrap(),
+                                    (&constant.value, Some(&constant.ty)),
+                                );
+                            }
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Self {
+            definitions,
+            values: HashMap::new(),
+            in_progress: HashSet::new(),
+        }
+    }
+
+    /// Evaluate every `const` item in the program.
+    pub fn eval_program(mut self) -> (HashMap<SymbolId, ConstValue>, Vec<TypeError>) {
+        let mut errors = vec![];
+
+        let mut constants = self.definitions.keys().copied().collect::<Vec<_>>();
+        constants.sort_by_key(|uid| uid.id);
+
+        for uid in constants {
+            let (expr, _) = self.definitions[&uid];
+
+            if let Err(error) = self.eval_constant(uid, expr.span) {
+                errors.push(error);
+            }
+        }
+
+        (self.values, errors)
+    }
+
+    pub fn eval(&mut self, expr: &Spanned<Expr>) -> Result<Folded, TypeError> {
+        let binop = |this: &mut Self,
+                     lhs: &Spanned<Expr>,
+                     rhs: &Spanned<Expr>,
+                     f: fn(i128, i128) -> Option<i128>|
+         -> Result<Folded, TypeError> {
+            let lhs = this.eval_int(lhs)?;
+            let rhs = this.eval_int(rhs)?;
+
+            f(lhs, rhs)
+                .map(Folded::Int)
+                .ok_or_else(|| error_const_overflow(expr.span))
+        };
+        let compare = |this: &mut Self,
+                       lhs: &Spanned<Expr>,
+                       rhs: &Spanned<Expr>,
+                       f: fn(&i128, &i128) -> bool|
+         -> Result<Folded, TypeError> {
+            let lhs = this.eval_int(lhs)?;
+            let rhs = this.eval_int(rhs)?;
+
+            Ok(Folded::Bool(f(&lhs, &rhs)))
+        };
+
+        match &expr.node {
+            Expr::PrimaryExpr(FieldAccessExpression::PrimaryExpr(primary)) => match primary {
+                PrimaryExpr::Number { literal, ty: _ } => Ok(Folded::Int(i128::from(*literal))),
+                PrimaryExpr::TypedNumber(literal, _) => Ok(Folded::Int(*literal)),
+                PrimaryExpr::Bool(value) => Ok(Folded::Bool(*value)),
+                PrimaryExpr::ParExpr(inner) => self.eval(inner),
+                PrimaryExpr::Ident(ident) | PrimaryExpr::Namespace { ident, .. }
+                    if ident.args.is_none() =>
+                {
+                    match ident.name.uid {
+                        Some(uid) if self.definitions.contains_key(&uid) => {
+                            self.eval_constant(uid, expr.span).map(Folded::from)
+                        }
+                        _ => Err(error_non_constant(expr.span)),
+                    }
+                }
+                _ => Err(error_non_constant(expr.span)),
+            },
+            Expr::Add(lhs, rhs) => binop(self, lhs, rhs, i128::checked_add),
+            Expr::Sub(lhs, rhs) => binop(self, lhs, rhs, i128::checked_sub),
+            Expr::Mul(lhs, rhs) => binop(self, lhs, rhs, i128::checked_mul),
+            Expr::Div(lhs, rhs) | Expr::Mod(lhs, rhs) => {
+                let l = self.eval_int(lhs)?;
+                let r = self.eval_int(rhs)?;
+
+                if r == 0 {
+                    return Err(error_const_division_by_zero(rhs.span));
+                }
+
+                let result = if matches!(expr.node, Expr::Div(_, _)) {
+                    l.checked_div(r)
+                } else {
+                    l.checked_rem(r)
+                };
+
+                result
+                    .map(Folded::Int)
+                    .ok_or_else(|| error_const_overflow(expr.span))
+            }
+            Expr::LShift(lhs, rhs) => binop(self, lhs, rhs, |l, r| {
+                u32::try_from(r).ok().and_then(|r| l.checked_shl(r))
+            }),
+            Expr::RShift(lhs, rhs) => binop(self, lhs, rhs, |l, r| {
+                u32::try_from(r).ok().and_then(|r| l.checked_shr(r))
+            }),
+            Expr::BitAnd(lhs, rhs) => binop(self, lhs, rhs, |l, r| Some(l & r)),
+            Expr::BitOr(lhs, rhs) => binop(self, lhs, rhs, |l, r| Some(l | r)),
+            Expr::BitXor(lhs, rhs) => binop(self, lhs, rhs, |l, r| Some(l ^ r)),
+            Expr::Neg(inner) => self
+                .eval_int(inner)?
+                .checked_neg()
+                .map(Folded::Int)
+                .ok_or_else(|| error_const_overflow(expr.span)),
+            Expr::BitNot(inner) => Ok(Folded::Int(!self.eval_int(inner)?)),
+            Expr::LessThan(lhs, rhs) => compare(self, lhs, rhs, i128::lt),
+            Expr::GreaterThan(lhs, rhs) => compare(self, lhs, rhs, i128::gt),
+            Expr::LessEq(lhs, rhs) => compare(self, lhs, rhs, i128::le),
+            Expr::GreaterEq(lhs, rhs) => compare(self, lhs, rhs, i128::ge),
+            Expr::Equals(lhs, rhs) | Expr::NotEquals(lhs, rhs) => {
+                let equal = self.eval(lhs)? == self.eval(rhs)?;
+
+                Ok(Folded::Bool(
+                    equal == matches!(expr.node, Expr::Equals(_, _)),
+                ))
+            }
+            Expr::Not(inner) => Ok(Folded::Bool(!self.eval_bool(inner)?)),
+            Expr::And(lhs, rhs) => Ok(Folded::Bool(self.eval_bool(lhs)? && self.eval_bool(rhs)?)),
+            Expr::Or(lhs, rhs) => Ok(Folded::Bool(self.eval_bool(lhs)? || self.eval_bool(rhs)?)),
+            _ => Err(error_non_constant(expr.span)),
+        }
+    }
+
+    /// Evaluate the length `N` of an array type `[T; N]`.
+    pub fn eval_array_len(&mut self, expr: &Spanned<Expr>) -> Result<usize, TypeError> {
+        let len = self.eval_int(expr)?;
+
+        if len < 0 {
+            return Err(error_negative_array_length(expr.span, len));
+        }
+
+        usize::try_from(len).map_err(|_| error_const_overflow(expr.span))
+    }
+
+    fn eval_int(&mut self, expr: &Spanned<Expr>) -> Result<i128, TypeError> {
+        match self.eval(expr)? {
+            Folded::Int(value) => Ok(value),
+            found => Err(error_type_mismatch(
+                expr.span,
+                &ComparableType::u32(),
+                &found.ty(),
+            )),
+        }
+    }
+
+    fn eval_bool(&mut self, expr: &Spanned<Expr>) -> Result<bool, TypeError> {
+        match self.eval(expr)? {
+            Folded::Bool(value) => Ok(value),
+            found => Err(error_type_mismatch(
+                expr.span,
+                &ComparableType::boolean(),
+                &found.ty(),
+            )),
+        }
+    }
+
+    fn eval_constant(&mut self, uid: SymbolId, span: SimpleSpan) -> Result<ConstValue, TypeError> {
+        if let Some(value) = self.values.get(&uid) {
+            return Ok(*value);
+        }
+
+        // a constant that depends on itself can't be folded
+        if !self.in_progress.insert(uid) {
+            return Err(error_non_constant(span));
+        }
+
+        let (definition, ty) = self.definitions[&uid];
+        let result = self.eval(definition);
+
+        self.in_progress.remove(&uid);
+
+        let value = to_const_value(result?, ty, definition.span)?;
+        self.values.insert(uid, value);
+
+        Ok(value)
+    }
+}
+
+/// Check that a folded value fits the type of its constant, which is `u32`
+/// if it has none.
+pub(crate) fn to_const_value(
+    value: Folded,
+    ty: Option<&TypeArg>,
+    span: SimpleSpan,
+) -> Result<ConstValue, TypeError> {
+    let overflow = |_| error_const_overflow(span);
+
+    match (value, ty.unwrap_or(&TypeArg::U32)) {
+        (Folded::Int(value), TypeArg::U32) => u32::try_from(value)
+            .map(|value| ConstValue::U64(value.into()))
+            .map_err(overflow),
+        (Folded::Int(value), TypeArg::U64) => {
+            u64::try_from(value).map(ConstValue::U64).map_err(overflow)
+        }
+        (Folded::Int(value), TypeArg::I32) => i32::try_from(value)
+            .map(|value| ConstValue::I64(value.into()))
+            .map_err(overflow),
+        (Folded::Int(value), TypeArg::I64) => {
+            i64::try_from(value).map(ConstValue::I64).map_err(overflow)
+        }
+        (Folded::Bool(value), TypeArg::Bool) => Ok(ConstValue::Bool(value)),
+        (found, ty) => {
+            let expected = match ty {
+                TypeArg::Bool => PrimitiveType::Bool,
+                TypeArg::U32 => PrimitiveType::U32,
+                TypeArg::U64 => PrimitiveType::U64,
+                TypeArg::I32 => PrimitiveType::I32,
+                TypeArg::I64 => PrimitiveType::I64,
+                // only integers and bools can be folded
+                _ => return Err(error_non_constant(span)),
+            };
+
+            Err(error_type_mismatch(
+                span,
+                &ComparableType::Primitive(expected),
+                &found.ty(),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConstEvaluator, Folded};
+    use crate::{
+        ast::{Block, ExprOrStatement, ProgramItem, StarstreamProgram},
+        do_scope_analysis,
+        error::TypeError,
+        symbols::ConstValue,
+    };
+    use chumsky::Parser as _;
+
+    fn scoped(input: &str) -> StarstreamProgram {
+        let program = crate::starstream_program().parse(input).unwrap();
+
+        do_scope_analysis(program).ok().unwrap().0
+    }
+
+    fn eval_tail_expr(program: &StarstreamProgram) -> Result<Folded, TypeError> {
+        let ProgramItem::Script(script) = &program.items.last().unwrap().node else {
+            panic!("expected a script");
+        };
+
+        let mut block = &script.definitions[0].body;
+        let expr = loop {
+            match block {
+                Block::Chain { head, tail } => match &**head {
+                    ExprOrStatement::Expr(expr) => break expr,
+                    ExprOrStatement::Statement(_) => block = tail,
+                },
+                Block::Close { .. } => panic!("expected a tail expression"),
+            }
+        };
+
+        ConstEvaluator::new(program).eval(expr)
+    }
+
+    #[test]
+    fn const_eval_folds_constants() {
+        let program = scoped(
+            "
+            const N = 4;
+            const M = N * 2;
+            const K = (M + N) % 5 << 2;
+            ",
+        );
+
+        let (values, errors) = ConstEvaluator::new(&program).eval_program();
+
+        assert!(errors.is_empty());
+
+        let mut values = values.into_iter().collect::<Vec<_>>();
+        values.sort_by_key(|(uid, _)| uid.id);
+
+        assert_eq!(
+            values.into_iter().map(|(_, v)| v).collect::<Vec<_>>(),
+            vec![ConstValue::U64(4), ConstValue::U64(8), ConstValue::U64(8)]
+        );
+    }
+
+    #[test]
+    fn const_eval_rejects_runtime_values() {
+        let program = scoped(
+            "
+            const N = 4;
+
+            script {
+                fn foo(x: u32): u32 {
+                    N * x
+                }
+            }
+            ",
+        );
+
+        assert!(matches!(
+            eval_tail_expr(&program),
+            Err(TypeError::NonConstantExpression { .. })
+        ));
+
+        let program = scoped(
+            "
+            const N = 4;
+
+            script {
+                fn foo(): u32 {
+                    N * 2
+                }
+            }
+            ",
+        );
+
+        assert_eq!(eval_tail_expr(&program).unwrap(), Folded::Int(8));
+    }
+
+    #[test]
+    fn const_eval_division_by_zero() {
+        let program = scoped(
+            "
+            const Z = 0;
+            const N = 4 / Z;
+            ",
+        );
+
+        let (_, errors) = ConstEvaluator::new(&program).eval_program();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [TypeError::ConstDivisionByZero { .. }]
+        ));
+    }
+
+    #[test]
+    fn const_eval_typed_constants() {
+        let program = scoped(
+            "
+            const MAX: u64 = (0xFFFFFFFF << 32) | 0xFFFFFFFF;
+            const NEG: i64 = -5;
+            const BIG: bool = MAX > 0xFFFFFFFF;
+            ",
+        );
+
+        let (values, errors) = ConstEvaluator::new(&program).eval_program();
+
+        assert!(errors.is_empty());
+
+        let mut values = values.into_iter().collect::<Vec<_>>();
+        values.sort_by_key(|(uid, _)| uid.id);
+
+        assert_eq!(
+            values.into_iter().map(|(_, v)| v).collect::<Vec<_>>(),
+            vec![
+                ConstValue::U64(u64::MAX),
+                ConstValue::I64(-5),
+                ConstValue::Bool(true),
+            ]
+        );
+    }
+
+    #[test]
+    fn const_eval_checks_range_and_type() {
+        let program = scoped(
+            "
+            const TOO_BIG: u64 = (0xFFFFFFFF << 32) * 2;
+            const UNSIGNED = 0 - 1;
+            const FLAG: bool = 1;
+            ",
+        );
+
+        let (_, errors) = ConstEvaluator::new(&program).eval_program();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [
+                TypeError::ConstOverflow { .. },
+                TypeError::ConstOverflow { .. },
+                TypeError::TypeMismatch { .. },
+            ]
+        ));
+    }
+}
@@ -25,6 +25,13 @@ pub(super) fn error_type_mismatch(
     }
 }
 
+pub(super) fn error_not_indexable(span: SimpleSpan, found: &ComparableType) -> TypeError {
+    TypeError::NotIndexable {
+        span,
+        found: found.clone(),
+    }
+}
+
 pub(super) fn error_non_signed(span: SimpleSpan, found: &ComparableType) -> TypeError {
     TypeError::NonSigned {
         span,
@@ -32,6 +39,18 @@ pub(super) fn error_non_signed(span: SimpleSpan, found: &ComparableType) -> Type
     }
 }
 
+pub(super) fn error_unsupported_128_bit_operator(
+    span: SimpleSpan,
+    op: &'static str,
+    found: &ComparableType,
+) -> TypeError {
+    TypeError::Unsupported128BitOperator {
+        span,
+        op,
+        found: found.clone(),
+    }
+}
+
 pub(super) fn error_variable_used_more_than_once(
     var: &SymbolInformation<VarInfo>,
     span1: SimpleSpan,
@@ -88,3 +107,42 @@ pub(super) fn error_missing_effect_handler(
         interface_name: interface_info.source.clone(),
     }
 }
+
+pub(super) fn error_non_constant(span: SimpleSpan) -> TypeError {
+    TypeError::NonConstantExpression { span }
+}
+
+pub(super) fn error_const_division_by_zero(span: SimpleSpan) -> TypeError {
+    TypeError::ConstDivisionByZero { span }
+}
+
+pub(super) fn error_const_overflow(span: SimpleSpan) -> TypeError {
+    TypeError::ConstOverflow { span }
+}
+
+pub(super) fn error_negative_array_length(span: SimpleSpan, found: i128) -> TypeError {
+    TypeError::NegativeArrayLength { span, found }
+}
+
+pub(super) fn error_unreachable_code(
+    span: SimpleSpan,
+    return_span: Option<SimpleSpan>,
+) -> TypeError {
+    TypeError::UnreachableCode { span, return_span }
+}
+
+pub(super) fn error_non_exhaustive_match(span: SimpleSpan, missing: Vec<String>) -> TypeError {
+    TypeError::NonExhaustiveMatch { span, missing }
+}
+
+pub(super) fn error_wrong_number_of_bindings(
+    span: SimpleSpan,
+    expected: usize,
+    found: usize,
+) -> TypeError {
+    TypeError::WrongNumberOfBindings {
+        span,
+        expected,
+        found,
+    }
+}
@@ -0,0 +1,386 @@
+use super::error::{error_unreachable_code, error_unused_variable};
+use crate::{
+    ast::{
+        Block, BlockExpr, Expr, ExprOrStatement, FieldAccessExpression, FnDef, LoopBody,
+        PrimaryExpr, ProgramItem, Spanned, StarstreamProgram, Statement, TokenItem, UtxoItem,
+    },
+    error::TypeError,
+    symbols::{SymbolId, Symbols},
+};
+use chumsky::span::SimpleSpan;
+use std::collections::HashSet;
+
+/// Post-resolution lints: unused `let` bindings and code following an
+/// unconditional `return`.
+///
+/// Everything reported here is a warning. Uses are the identifiers that scope
+/// resolution resolved to a variable, so this has to run after it.
+#[derive(Default)]
+pub struct Lints {
+    uses: HashSet<SymbolId>,
+    bindings: Vec<SymbolId>,
+    warnings: Vec<TypeError>,
+}
+
+impl Lints {
+    pub fn check_program(
+        mut self,
+        program: &StarstreamProgram,
+        symbols: &Symbols,
+    ) -> Vec<TypeError> {
+        for item in &program.items {
+            match &item.node {
+                ProgramItem::Script(script) => self.visit_fn_defs(&script.definitions),
+                ProgramItem::Utxo(utxo) => {
+                    for item in &utxo.items {
+                        match item {
+                            UtxoItem::Main(main) => self.visit_block(&main.block),
+                            UtxoItem::Impl(utxo_impl) => {
+                                self.visit_fn_defs(&utxo_impl.definitions)
+                            }
+                            UtxoItem::Storage(_) | UtxoItem::Yield(_) | UtxoItem::Resume(_) => (),
+                        }
+                    }
+                }
+                ProgramItem::Token(token) => {
+                    for item in &token.items {
+                        match item {
+                            TokenItem::Bind(bind) => self.visit_block(&bind.0),
+                            TokenItem::Unbind(unbind) => self.visit_block(&unbind.0),
+                            TokenItem::Mint(mint) => self.visit_block(&mint.0),
+                        }
+                    }
+                }
+                // abi default bodies are checked through the impls inheriting
+                // them
+                ProgramItem::Abi(_) | ProgramItem::TypeDef(_) | ProgramItem::Constant { .. } => (),
+            }
+        }
+
+        for binding in &self.bindings {
+            if self.uses.contains(binding) {
+                continue;
+            }
+
+            let Some(var) = symbols.vars.get(binding) else {
+                continue;
+            };
+
+            // linear variables are already rejected by the typechecker
+            let is_linear = var.info.ty.as_ref().is_some_and(|ty| ty.is_linear());
+
+            if var.span.is_some() && !is_linear {
+                self.warnings.push(error_unused_variable(var, false));
+            }
+        }
+
+        self.warnings
+    }
+
+    fn visit_fn_defs(&mut self, definitions: &[FnDef]) {
+        for definition in definitions {
+            self.visit_block(&definition.body);
+        }
+    }
+
+    fn visit_block(&mut self, mut block: &Block) {
+        let mut return_span = None;
+        let mut reported = false;
+
+        while let Block::Chain { head, tail } = block {
+            if let Some(return_span) = return_span.filter(|_| !reported) {
+                if let Some(span) = expr_or_statement_span(head) {
+                    self.warnings.push(error_unreachable_code(span, return_span));
+                    reported = true;
+                }
+            }
+
+            match &**head {
+                ExprOrStatement::Expr(expr) => self.visit_expr(expr),
+                ExprOrStatement::Statement(statement) => {
+                    if let Statement::Return(expr) = statement {
+                        return_span.get_or_insert(expr.as_ref().map(|expr| expr.span));
+                    }
+
+                    self.visit_statement(statement)
+                }
+            }
+
+            block = tail;
+        }
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::BindVar {
+                var,
+                mutable: _,
+                ty: _,
+                value,
+            } => {
+                self.visit_expr(value);
+
+                // `_x` opts out of the unused warning
+                if let Some(uid) = var.uid.filter(|_| !var.raw.starts_with('_')) {
+                    self.bindings.push(uid);
+                }
+            }
+            Statement::Return(expr) | Statement::Resume(expr) => {
+                if let Some(expr) = expr {
+                    self.visit_expr(expr);
+                }
+            }
+            Statement::Assign { var, expr } => {
+                self.visit_secondary_expr(var);
+                self.visit_expr(expr);
+            }
+            Statement::While(cond, loop_body) => {
+                self.visit_expr(cond);
+                self.visit_loop_body(loop_body);
+            }
+            Statement::Loop(loop_body) => self.visit_loop_body(loop_body),
+            Statement::For {
+                var,
+                start,
+                end,
+                body,
+            } => {
+                self.visit_expr(start);
+                self.visit_expr(end);
+
+                if let Some(uid) = var.uid.filter(|_| !var.raw.starts_with('_')) {
+                    self.bindings.push(uid);
+                }
+
+                self.visit_loop_body(body);
+            }
+            Statement::Labeled(_, statement) => self.visit_statement(statement),
+            Statement::Break { value, .. } => {
+                if let Some(value) = value {
+                    self.visit_expr(value);
+                }
+            }
+            Statement::Continue { .. } => (),
+        }
+    }
+
+    fn visit_loop_body(&mut self, loop_body: &LoopBody) {
+        match loop_body {
+            LoopBody::Statement(statement) => self.visit_statement(statement),
+            LoopBody::Block(block) => self.visit_block(block),
+            LoopBody::Expr(expr) => self.visit_expr(expr),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Spanned<Expr>) {
+        match &expr.node {
+            Expr::PrimaryExpr(secondary) => self.visit_secondary_expr(secondary),
+            Expr::BlockExpr(BlockExpr::IfThenElse(cond, if_true, if_false)) => {
+                self.visit_expr(cond);
+                self.visit_block(if_true);
+
+                if let Some(if_false) = if_false {
+                    self.visit_block(if_false);
+                }
+            }
+            Expr::BlockExpr(BlockExpr::Block(block)) => self.visit_block(block),
+            Expr::BlockExpr(BlockExpr::TryWith(block, handlers)) => {
+                self.visit_block(block);
+
+                for (_, body) in handlers {
+                    self.visit_block(body);
+                }
+            }
+            Expr::BlockExpr(BlockExpr::Loop(loop_body)) => self.visit_loop_body(loop_body),
+            Expr::BlockExpr(BlockExpr::Match(scrutinee, arms)) => {
+                self.visit_expr(scrutinee);
+
+                for arm in arms {
+                    for binding in &arm.bindings {
+                        if let Some(uid) = binding.uid.filter(|_| !binding.raw.starts_with('_')) {
+                            self.bindings.push(uid);
+                        }
+                    }
+
+                    self.visit_block(&arm.body);
+                }
+            }
+            Expr::Equals(lhs, rhs)
+            | Expr::NotEquals(lhs, rhs)
+            | Expr::LessThan(lhs, rhs)
+            | Expr::GreaterThan(lhs, rhs)
+            | Expr::LessEq(lhs, rhs)
+            | Expr::GreaterEq(lhs, rhs)
+            | Expr::Add(lhs, rhs)
+            | Expr::Sub(lhs, rhs)
+            | Expr::Mul(lhs, rhs)
+            | Expr::Div(lhs, rhs)
+            | Expr::Mod(lhs, rhs)
+            | Expr::BitAnd(lhs, rhs)
+            | Expr::BitOr(lhs, rhs)
+            | Expr::BitXor(lhs, rhs)
+            | Expr::LShift(lhs, rhs)
+            | Expr::RShift(lhs, rhs)
+            | Expr::And(lhs, rhs)
+            | Expr::Or(lhs, rhs) => {
+                self.visit_expr(lhs);
+                self.visit_expr(rhs);
+            }
+            Expr::Neg(inner) | Expr::BitNot(inner) | Expr::Not(inner) => self.visit_expr(inner),
+        }
+    }
+
+    fn visit_secondary_expr(&mut self, expr: &FieldAccessExpression) {
+        match expr {
+            FieldAccessExpression::PrimaryExpr(primary) => self.visit_primary_expr(primary),
+            FieldAccessExpression::FieldAccess { base, field } => {
+                for arg in field.args.iter().flat_map(|args| args.xs.iter()) {
+                    self.visit_expr(arg);
+                }
+
+                self.visit_secondary_expr(base);
+            }
+            FieldAccessExpression::Index { base, index } => {
+                self.visit_expr(index);
+                self.visit_secondary_expr(base);
+            }
+        }
+    }
+
+    fn visit_primary_expr(&mut self, expr: &PrimaryExpr) {
+        match expr {
+            PrimaryExpr::Number { .. }
+            | PrimaryExpr::TypedNumber(..)
+            | PrimaryExpr::Bool(_)
+            | PrimaryExpr::Char(_)
+            | PrimaryExpr::StringLiteral(_) => {}
+            PrimaryExpr::Ident(ident)
+            | PrimaryExpr::Raise { ident }
+            | PrimaryExpr::Namespace {
+                namespaces: _,
+                ident,
+            }
+            | PrimaryExpr::RaiseNamespaced {
+                namespaces: _,
+                ident,
+            } => {
+                if let Some(uid) = ident.name.uid {
+                    self.uses.insert(uid);
+                }
+
+                for arg in ident.args.iter().flat_map(|args| args.xs.iter()) {
+                    self.visit_expr(arg);
+                }
+            }
+            PrimaryExpr::ParExpr(inner) => self.visit_expr(inner),
+            PrimaryExpr::Yield(inner) => {
+                if let Some(inner) = inner {
+                    self.visit_expr(inner);
+                }
+            }
+            PrimaryExpr::Object(_, fields) => {
+                for (_, value) in fields {
+                    self.visit_expr(value);
+                }
+            }
+            PrimaryExpr::Tuple(values) => {
+                for value in values {
+                    self.visit_expr(value);
+                }
+            }
+        }
+    }
+}
+
+fn expr_or_statement_span(node: &ExprOrStatement) -> Option<SimpleSpan> {
+    match node {
+        ExprOrStatement::Expr(expr) => Some(expr.span),
+        ExprOrStatement::Statement(statement) => match statement {
+            Statement::BindVar { var, .. } => var.span,
+            Statement::Return(expr) | Statement::Resume(expr) => expr.as_ref().map(|e| e.span),
+            Statement::Assign { expr, .. } => Some(expr.span),
+            Statement::While(cond, _) => Some(cond.span),
+            Statement::Labeled(label, _) => Some(label.span),
+            Statement::Break { span, .. } | Statement::Continue { span, .. } => Some(*span),
+            Statement::For { var, .. } => var.span,
+            Statement::Loop(_) => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lints;
+    use crate::{do_scope_analysis, error::TypeError};
+    use chumsky::Parser as _;
+
+    fn lint(input: &str) -> Vec<TypeError> {
+        let program = crate::starstream_program().parse(input).unwrap();
+        let (program, symbols) = do_scope_analysis(program).ok().unwrap();
+
+        Lints::default().check_program(&program, &symbols)
+    }
+
+    #[test]
+    fn lint_unused_binding() {
+        let warnings = lint(
+            "
+            script {
+                fn foo(): u32 {
+                    let x = 1;
+                    2
+                }
+            }
+            ",
+        );
+
+        assert!(matches!(
+            warnings.as_slice(),
+            [TypeError::UnusedVariable {
+                is_error: false,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn lint_used_binding() {
+        let warnings = lint(
+            "
+            script {
+                fn foo(): u32 {
+                    let x = 1;
+                    let _y = 3;
+                    let z = x + 1;
+                    z
+                }
+            }
+            ",
+        );
+
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn lint_unreachable_code() {
+        let warnings = lint(
+            "
+            script {
+                fn foo(): u32 {
+                    return 1;
+                    let x = 2;
+                    x
+                }
+            }
+            ",
+        );
+
+        assert!(matches!(
+            warnings.as_slice(),
+            [TypeError::UnreachableCode {
+                return_span: Some(_),
+                ..
+            }]
+        ));
+    }
+}
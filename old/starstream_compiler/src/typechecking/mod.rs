@@ -1,27 +1,32 @@
+mod const_eval;
 mod effects;
 mod error;
 mod linear;
+mod lints;
 mod types;
 
 use crate::{
     ast::{
-        Block, BlockExpr, Expr, ExprOrStatement, FieldAccessExpression, FnDef, IdentifierExpr,
-        LoopBody, PrimaryExpr, ProgramItem, Script, Spanned, StarstreamProgram, Statement, Token,
-        TokenItem, Utxo, UtxoItem,
+        Block, BlockExpr, EffectHandler, Expr, ExprOrStatement, FieldAccessExpression, FnDef,
+        Identifier, IdentifierExpr, LoopBody, LoopLabel, MatchArm, PrimaryExpr, ProgramItem,
+        Script, Spanned, StarstreamProgram, Statement, Token, TokenItem, Utxo, UtxoItem,
     },
     error::TypeError,
     scope_resolution::STARSTREAM_ENV,
     symbols::{SymbolId, Symbols},
 };
 use chumsky::span::SimpleSpan;
+pub(crate) use const_eval::{ConstEvaluator, Folded, to_const_value};
 pub use effects::EffectSet;
 use ena::unify::{EqUnifyValue, InPlaceUnificationTable};
 use error::{
     error_effect_type_mismatch, error_field_not_found, error_invalid_return_type_for_utxo_main,
-    error_linear_variable_affine, error_missing_effect_handler, error_non_signed,
-    error_type_mismatch, error_unused_variable, error_variable_used_more_than_once,
+    error_linear_variable_affine, error_missing_effect_handler, error_non_exhaustive_match,
+    error_non_signed, error_not_indexable, error_type_mismatch, error_unsupported_128_bit_operator,
+    error_unused_variable, error_variable_used_more_than_once, error_wrong_number_of_bindings,
 };
 use linear::{ManyWitness, Multiplicity, ResourceTracker};
+use lints::Lints;
 use std::collections::{HashMap, HashSet};
 pub use types::{ComparableType, PrimitiveType, TypeVar};
 
@@ -48,15 +53,27 @@ pub struct TypeInference<'a> {
     current_coroutine: Vec<SymbolId>,
     current_function: Vec<SymbolId>,
     current_handler: Vec<SymbolId>,
+    current_loop: Vec<EnclosingLoop>,
 
     multiplicity_tracker: ResourceTracker<SymbolId, SimpleSpan>,
 
     // checks to do after unification
     utxo_main_block_constraints: Vec<(SimpleSpan, ComparableType)>,
     num_signed_constraints: Vec<(SimpleSpan, ComparableType)>,
+    narrow_operator_constraints: Vec<(SimpleSpan, &'static str, ComparableType)>,
     is_numeric: HashSet<TypeVar>,
 }
 
+/// A loop being checked, for the `break`s inside it to find.
+struct EnclosingLoop {
+    label: Option<String>,
+    /// The type of `v` in `break v;`. Loops used as statements only break
+    /// with `()`.
+    value_ty: ComparableType,
+    /// Whether any `break` targets this loop, otherwise it never ends.
+    broken: bool,
+}
+
 impl<'a> TypeInference<'a> {
     pub fn new(symbols: &'a mut Symbols) -> Self {
         Self {
@@ -65,12 +82,14 @@ impl<'a> TypeInference<'a> {
             warnings: vec![],
             unification_table: InPlaceUnificationTable::new(),
             num_signed_constraints: vec![],
+            narrow_operator_constraints: vec![],
             is_numeric: HashSet::new(),
             utxo_main_block_constraints: vec![],
             multiplicity_tracker: ResourceTracker::new(),
 
             current_function: vec![],
             current_handler: vec![],
+            current_loop: vec![],
             current_coroutine: vec![],
         }
     }
@@ -79,23 +98,63 @@ impl<'a> TypeInference<'a> {
         mut self,
         program: &mut StarstreamProgram,
     ) -> Result<Vec<TypeError>, Vec<TypeError>> {
+        let mut const_evaluator = ConstEvaluator::new(program);
+
+        // before anything takes the canonical form of an array type
+        for len in &self.symbols.array_lens {
+            match const_evaluator.eval_array_len(&len.expr) {
+                Ok(value) => {
+                    let _ = len.value.set(value);
+                }
+                Err(error) => self.errors.push(error),
+            }
+        }
+
+        let (const_values, const_errors) = const_evaluator.eval_program();
+        self.errors.extend(const_errors);
+
+        // constants can be used before they are declared
+        for item in &program.items {
+            let utxo = match &item.node {
+                ProgramItem::Utxo(utxo) => utxo,
+                ProgramItem::Constant { name, ty, value: _ } => {
+                    let uid = name.uid.unwrap();
+                    let ty = ty
+                        .as_ref()
+                        .map_or_else(ComparableType::u32, |ty| ty.canonical_form(self.symbols));
+                    let info = &mut self.symbols.constants.get_mut(&uid).unwrap().info;
+
+                    info.ty.replace(ty);
+                    info.value = const_values.get(&uid).copied();
+                    continue;
+                }
+                _ => continue,
+            };
+
+            for item in &utxo.items {
+                let UtxoItem::Impl(utxo_impl) = item else {
+                    continue;
+                };
+
+                for constant in &utxo_impl.constants {
+                    let uid = constant.name.uid.unwrap();
+                    let ty = constant.ty.canonical_form(self.symbols);
+                    let info = &mut self.symbols.constants.get_mut(&uid).unwrap().info;
+
+                    info.ty.replace(ty);
+                    info.value = const_values.get(&uid).copied();
+                }
+            }
+        }
+
         for item in &mut program.items {
-            match item {
+            match &mut item.node {
                 ProgramItem::Script(script) => self.visit_script(script),
                 ProgramItem::Utxo(utxo) => self.visit_utxo(utxo),
                 ProgramItem::Token(token) => self.visit_token(token),
                 ProgramItem::TypeDef(_type_def) => (),
-                // TODO: add these
-                ProgramItem::Constant { name, value: _ } => {
-                    self.symbols
-                        .constants
-                        .get_mut(&name.uid.unwrap())
-                        .unwrap()
-                        .info
-                        .ty
-                        // TODO: add proper type annotations plus parsing for other types
-                        .replace(ComparableType::u32());
-                }
+                // typed above, and checked while folding
+                ProgramItem::Constant { .. } => (),
                 ProgramItem::Abi(_abi) => (),
             }
         }
@@ -108,10 +167,25 @@ impl<'a> TypeInference<'a> {
 
         self.check_multiplicity_constraints();
 
+        for warning in Lints::default().check_program(program, self.symbols) {
+            let already_reported = match &warning {
+                TypeError::UnusedVariable { var_span, .. } => self.warnings.iter().any(|w| {
+                    matches!(w, TypeError::UnusedVariable { var_span: s, .. } if s == var_span)
+                }),
+                _ => false,
+            };
+
+            if !already_reported {
+                self.warnings.push(warning);
+            }
+        }
+
         self.check_utxo_main_block_ty();
 
         self.check_signed_types();
 
+        self.check_narrow_operators();
+
         if !self.errors.is_empty() {
             Err(self.errors)
         } else {
@@ -221,6 +295,10 @@ impl<'a> TypeInference<'a> {
             ComparableType::Ref(ty) => ComparableType::Ref(
                 Self::substitute(unification_table, (*ty).clone(), is_numeric).boxed(),
             ),
+            ComparableType::Array(elem, len) => ComparableType::Array(
+                Self::substitute(unification_table, *elem, is_numeric).boxed(),
+                len,
+            ),
         }
     }
 
@@ -277,6 +355,11 @@ impl<'a> TypeInference<'a> {
 
                 self.unify_ty_ty(span, &output_lhs, &output_rhs);
             }
+            (ComparableType::Array(lhs, lhs_len), ComparableType::Array(rhs, rhs_len))
+                if lhs_len == rhs_len =>
+            {
+                self.unify_ty_ty(span, &lhs, &rhs);
+            }
             (ComparableType::Utxo(lhs, _), ComparableType::Utxo(rhs, _)) if lhs == rhs => {}
             (ComparableType::Void, _) | (_, ComparableType::Void) => {}
             (ComparableType::Product(fields), ComparableType::Primitive(PrimitiveType::Unit))
@@ -328,6 +411,9 @@ impl<'a> TypeInference<'a> {
                 None => ComparableType::Var(self.unification_table.find(type_var)),
             },
             ComparableType::Ref(normalized_type) => self.follow_unified_variables(*normalized_type),
+            ComparableType::Array(elem, len) => {
+                ComparableType::Array(self.follow_unified_variables(*elem).boxed(), len)
+            }
         }
     }
 
@@ -344,11 +430,32 @@ impl<'a> TypeInference<'a> {
             match ty {
                 ComparableType::Primitive(PrimitiveType::I32) => (),
                 ComparableType::Primitive(PrimitiveType::I64) => (),
+                ComparableType::Primitive(PrimitiveType::I128) => (),
                 _ => self.push_error_non_signed(span, &ty),
             }
         }
     }
 
+    fn check_narrow_operators(&mut self) {
+        let mut narrow_operator_constraints = vec![];
+        std::mem::swap(
+            &mut narrow_operator_constraints,
+            &mut self.narrow_operator_constraints,
+        );
+
+        for (span, op, ty) in narrow_operator_constraints {
+            let ty = Self::substitute(&mut self.unification_table, ty, &self.is_numeric);
+
+            match ty {
+                ComparableType::Primitive(PrimitiveType::I128)
+                | ComparableType::Primitive(PrimitiveType::U128) => self
+                    .errors
+                    .push(error_unsupported_128_bit_operator(span, op, &ty)),
+                _ => (),
+            }
+        }
+    }
+
     fn check_utxo_main_block_ty(&mut self) {
         let mut utxo_main_block_constraints = vec![];
         std::mem::swap(
@@ -502,14 +609,24 @@ impl<'a> TypeInference<'a> {
                         .effects = interfaces.clone();
                 }
                 UtxoItem::Impl(utxo_impl) => {
+                    // `None` for an `impl` of the utxo itself
                     let abi = utxo_impl.name.uid.unwrap();
+                    let abi = self
+                        .symbols
+                        .interfaces
+                        .get(&abi)
+                        .filter(|interface| !interface.info.effects.is_empty())
+                        .map(|_| abi);
+
+                    // the declared type was taken as is above, so the value has
+                    // to be checked against it
+                    for constant in &mut utxo_impl.constants {
+                        let ty = constant.ty.canonical_form(self.symbols);
+                        self.check_expr(&mut constant.value, ty);
+                    }
+
                     for item in &mut utxo_impl.definitions {
-                        self.visit_fn_def(
-                            item,
-                            Some(abi)
-                                .filter(|_| !self.symbols.interfaces[&abi].info.effects.is_empty()),
-                            Some(uid),
-                        );
+                        self.visit_fn_def(item, abi, Some(uid));
                     }
                 }
                 UtxoItem::Storage(_storage) => (),
@@ -792,105 +909,265 @@ impl<'a> TypeInference<'a> {
 
                 effects.combine(self.check_expr(expr, lhs_ty))
             }
-            Statement::With(block, items) => {
-                let mut effects =
-                    self.check_block(block, ComparableType::Primitive(PrimitiveType::Unit));
+            Statement::While(expr, loop_body) => self.visit_while(None, expr, loop_body),
+            Statement::Loop(loop_body) => {
+                self.infer_loop(None, loop_body, ComparableType::unit()).1
+            }
+            Statement::For {
+                var,
+                start,
+                end,
+                body,
+            } => self.visit_for(None, var, start, end, body),
+            Statement::Labeled(label, statement) => match &mut **statement {
+                Statement::While(expr, loop_body) => self.visit_while(Some(label), expr, loop_body),
+                Statement::For {
+                    var,
+                    start,
+                    end,
+                    body,
+                } => self.visit_for(Some(label), var, start, end, body),
+                Statement::Loop(loop_body) => {
+                    let (_, effects) =
+                        self.infer_loop(Some(label), loop_body, ComparableType::unit());
+
+                    effects
+                }
+                // the parser only labels loops
+                statement => self.visit_statement(statement),
+            },
+            Statement::Break { label, value, span } => {
+                let label = label.as_ref().map(|label| &label.name);
+                let target = self
+                    .current_loop
+                    .iter_mut()
+                    .rev()
+                    .find(|target| label.is_none() || target.label.as_ref() == label);
+
+                // scope resolution reports breaks without a loop
+                let Some(target) = target else {
+                    return value
+                        .as_mut()
+                        .map(|value| self.infer_expr(value).1)
+                        .unwrap_or(EffectSet::empty());
+                };
 
-                let mut interfaces: HashMap<SymbolId, HashSet<SymbolId>> = HashMap::new();
+                target.broken = true;
+                let expected = target.value_ty.clone();
 
-                for (handler, block) in items {
-                    let symbol_id = handler.interface.uid.unwrap();
+                match value {
+                    Some(value) => self.check_expr(value, expected),
+                    None => {
+                        self.unify_ty_ty(*span, &expected, &ComparableType::unit());
 
-                    effects.remove(symbol_id);
+                        EffectSet::empty()
+                    }
+                }
+            }
+            Statement::Continue { .. } => EffectSet::empty(),
+        }
+    }
 
-                    self.current_handler.push(handler.ident.uid.unwrap());
+    fn visit_while(
+        &mut self,
+        label: Option<&LoopLabel>,
+        cond: &mut Spanned<Expr>,
+        loop_body: &mut LoopBody,
+    ) -> EffectSet {
+        let cond_effects = self.check_expr(cond, ComparableType::boolean());
 
-                    let fn_info = &self
-                        .symbols
-                        .functions
-                        .get(&handler.ident.uid.unwrap())
-                        .unwrap()
-                        .info;
+        cond_effects.combine(self.visit_statement_loop_body(label, loop_body))
+    }
 
-                    interfaces
-                        .entry(symbol_id)
-                        .or_default()
-                        .insert(fn_info.is_effect_handler.unwrap());
+    /// The loop variable of a `for` has the type of its bounds.
+    fn visit_for(
+        &mut self,
+        label: Option<&LoopLabel>,
+        var: &Identifier,
+        start: &mut Spanned<Expr>,
+        end: &mut Spanned<Expr>,
+        loop_body: &mut LoopBody,
+    ) -> EffectSet {
+        let (ty, start_effects) = self.infer_expr(start);
+        let end_effects = self.check_expr(end, ty.clone());
 
-                    self.symbols
-                        .vars
-                        .get_mut(&fn_info.frame_var.unwrap())
-                        .unwrap()
-                        .info
-                        .ty
-                        .replace(ComparableType::Primitive(PrimitiveType::I32));
+        let symbol_id = var.uid.unwrap();
 
-                    for (arg_ty_decl, arg_def) in fn_info
-                        .inputs_ty
-                        .iter()
-                        // skip the frame variable since its implicit
-                        .skip(1)
-                        .zip(handler.args.iter())
-                    {
-                        let ty = arg_ty_decl.canonical_form(self.symbols);
+        self.multiplicity_tracker.declare_variable(symbol_id);
+
+        self.symbols
+            .vars
+            .get_mut(&symbol_id)
+            .unwrap()
+            .info
+            .ty
+            .replace(ty);
 
-                        let var_info = self
-                            .symbols
-                            .vars
-                            .get_mut(&arg_def.name.uid.unwrap())
-                            .unwrap();
+        start_effects
+            .combine(end_effects)
+            .combine(self.visit_statement_loop_body(label, loop_body))
+    }
 
-                        var_info.info.ty.replace(ty);
-                        // TODO: check type in declaration matches type in definition
-                    }
+    /// The body of a loop used as a statement, which only breaks with `()`.
+    fn visit_statement_loop_body(
+        &mut self,
+        label: Option<&LoopLabel>,
+        loop_body: &mut LoopBody,
+    ) -> EffectSet {
+        self.current_loop.push(EnclosingLoop {
+            label: label.map(|label| label.name.clone()),
+            value_ty: ComparableType::unit(),
+            broken: false,
+        });
+
+        let loop_body_effects = match loop_body {
+            LoopBody::Statement(statement) => self.visit_statement(statement),
+            LoopBody::Block(block) => {
+                self.multiplicity_tracker.push_loop_scope();
+                let effects = self.infer_block(block).2;
+                self.multiplicity_tracker.pop_loop();
+
+                effects
+            }
+            LoopBody::Expr(spanned) => self.infer_expr(spanned).1,
+        };
 
-                    let (_, _, handler_effects) = self.infer_block(block);
+        self.current_loop.pop();
 
-                    self.current_handler.pop();
+        loop_body_effects
+    }
 
-                    effects = effects.combine(handler_effects);
-                }
+    /// Each arm binds the fields of its case, and together they have to cover
+    /// every case of the enum. The type of a `match` is that of its arms.
+    fn infer_match(
+        &mut self,
+        scrutinee: &mut Spanned<Expr>,
+        arms: &mut [MatchArm],
+    ) -> (ComparableType, EffectSet) {
+        let (scrutinee_ty, mut effects) = self.infer_expr(scrutinee);
 
-                for (interface, handlers) in interfaces {
-                    let interface_info = self.symbols.interfaces.get(&interface).unwrap();
+        let mut covered = HashSet::new();
+        let mut arms_ty = None;
 
-                    for handler in interface_info.info.effects.difference(&handlers) {
-                        let effect_info = self.symbols.effects.get(handler).unwrap();
+        for arm in arms.iter_mut() {
+            let span = arm.variant.span.unwrap_or(scrutinee.span);
 
-                        let span = effect_info.span.unwrap_or(SimpleSpan::from(0..0));
+            // scope resolution reports arms that don't name a case
+            let constructor = arm
+                .variant
+                .uid
+                .and_then(|uid| self.symbols.functions.get(&uid));
 
-                        self.errors.push(error_missing_effect_handler(
-                            span,
-                            effect_info,
-                            interface_info,
-                        ));
-                    }
+            let fields = if let Some(constructor) = constructor {
+                covered.insert(constructor.source.clone());
+
+                let enum_ty = constructor
+                    .info
+                    .output_ty
+                    .as_ref()
+                    .map(|ty| ty.canonical_form(self.symbols))
+                    .unwrap_or(ComparableType::unit());
+                let fields = constructor
+                    .info
+                    .inputs_ty
+                    .iter()
+                    .map(|ty| ty.canonical_form(self.symbols))
+                    .collect::<Vec<_>>();
+
+                self.unify_ty_ty(span, &enum_ty, &scrutinee_ty);
+
+                if fields.len() != arm.bindings.len() {
+                    self.errors.push(error_wrong_number_of_bindings(
+                        span,
+                        fields.len(),
+                        arm.bindings.len(),
+                    ));
                 }
 
-                effects
+                fields
+            } else {
+                vec![]
+            };
+
+            self.multiplicity_tracker.push_branch();
+
+            for (i, binding) in arm.bindings.iter().enumerate() {
+                let ty = fields.get(i).cloned().unwrap_or_else(|| self.new_ty_var());
+                let symbol_id = binding.uid.unwrap();
+
+                self.multiplicity_tracker.declare_variable(symbol_id);
+
+                self.symbols
+                    .vars
+                    .get_mut(&symbol_id)
+                    .unwrap()
+                    .info
+                    .ty
+                    .replace(ty);
             }
-            Statement::While(expr, loop_body) => {
-                let cond_effects = self.check_expr(expr, ComparableType::boolean());
 
-                let loop_body_effects = match loop_body {
-                    LoopBody::Statement(statement) => self.visit_statement(statement),
-                    LoopBody::Block(block) => {
-                        self.multiplicity_tracker.push_loop_scope();
-                        let effects = self.infer_block(block).2;
-                        self.multiplicity_tracker.pop_loop();
+            let body_effects = match &arms_ty {
+                Some(ty) => self.check_block(&mut arm.body, ty.clone()),
+                None => {
+                    let (_span, ty, body_effects) = self.infer_block(&mut arm.body);
+                    arms_ty.replace(ty);
+                    body_effects
+                }
+            };
+
+            effects = effects.combine(body_effects);
+        }
 
-                        effects
-                    }
-                    LoopBody::Expr(spanned) => self.infer_expr(spanned).1,
-                };
+        if !arms.is_empty() {
+            self.multiplicity_tracker.pop_branches(arms.len() as u32);
+        }
+
+        let scrutinee_ty =
+            Self::substitute(&mut self.unification_table, scrutinee_ty, &self.is_numeric);
+
+        if let ComparableType::Sum(variants) = &scrutinee_ty {
+            let missing = variants
+                .iter()
+                .map(|(name, _)| name)
+                .filter(|name| !covered.contains(*name))
+                .cloned()
+                .collect::<Vec<_>>();
 
-                cond_effects.combine(loop_body_effects)
+            if !missing.is_empty() {
+                self.errors
+                    .push(error_non_exhaustive_match(scrutinee.span, missing));
             }
-            Statement::Loop(loop_body) => match loop_body {
-                LoopBody::Statement(statement) => self.visit_statement(statement),
-                LoopBody::Block(block) => self.infer_block(block).2,
-                LoopBody::Expr(spanned) => self.infer_expr(spanned).1,
-            },
+        }
+
+        (arms_ty.unwrap_or(ComparableType::Void), effects)
+    }
+
+    /// The type of a `loop` is that of the values it breaks with, or `Void`
+    /// if nothing breaks out of it.
+    fn infer_loop(
+        &mut self,
+        label: Option<&LoopLabel>,
+        loop_body: &mut LoopBody,
+        value_ty: ComparableType,
+    ) -> (ComparableType, EffectSet) {
+        self.current_loop.push(EnclosingLoop {
+            label: label.map(|label| label.name.clone()),
+            value_ty,
+            broken: false,
+        });
+
+        let effects = match loop_body {
+            LoopBody::Statement(statement) => self.visit_statement(statement),
+            LoopBody::Block(block) => self.infer_block(block).2,
+            LoopBody::Expr(spanned) => self.infer_expr(spanned).1,
+        };
+
+        let target = self.current_loop.pop().unwrap();
+
+        if target.broken {
+            (target.value_ty, effects)
+        } else {
+            (ComparableType::Void, effects)
         }
     }
 
@@ -956,6 +1233,89 @@ impl<'a> TypeInference<'a> {
         effects
     }
 
+    /// The type of a `try` is that of its body. Handlers resume with the
+    /// output type of the effect they handle.
+    fn infer_try_with(
+        &mut self,
+        block: &mut Block,
+        items: &mut [(EffectHandler, Block)],
+    ) -> (ComparableType, EffectSet) {
+        let (_, ty, mut effects) = self.infer_block(block);
+
+        let mut interfaces: HashMap<SymbolId, HashSet<SymbolId>> = HashMap::new();
+
+        for (handler, block) in items {
+            let symbol_id = handler.interface.uid.unwrap();
+
+            effects.remove(symbol_id);
+
+            self.current_handler.push(handler.ident.uid.unwrap());
+
+            let fn_info = &self
+                .symbols
+                .functions
+                .get(&handler.ident.uid.unwrap())
+                .unwrap()
+                .info;
+
+            interfaces
+                .entry(symbol_id)
+                .or_default()
+                .insert(fn_info.is_effect_handler.unwrap());
+
+            self.symbols
+                .vars
+                .get_mut(&fn_info.frame_var.unwrap())
+                .unwrap()
+                .info
+                .ty
+                .replace(ComparableType::Primitive(PrimitiveType::I32));
+
+            for (arg_ty_decl, arg_def) in fn_info
+                .inputs_ty
+                .iter()
+                // skip the frame variable since its implicit
+                .skip(1)
+                .zip(handler.args.iter())
+            {
+                let ty = arg_ty_decl.canonical_form(self.symbols);
+
+                let var_info = self
+                    .symbols
+                    .vars
+                    .get_mut(&arg_def.name.uid.unwrap())
+                    .unwrap();
+
+                var_info.info.ty.replace(ty);
+                // TODO: check type in declaration matches type in definition
+            }
+
+            let (_, _, handler_effects) = self.infer_block(block);
+
+            self.current_handler.pop();
+
+            effects = effects.combine(handler_effects);
+        }
+
+        for (interface, handlers) in interfaces {
+            let interface_info = self.symbols.interfaces.get(&interface).unwrap();
+
+            for handler in interface_info.info.effects.difference(&handlers) {
+                let effect_info = self.symbols.effects.get(handler).unwrap();
+
+                let span = effect_info.span.unwrap_or(SimpleSpan::from(0..0));
+
+                self.errors.push(error_missing_effect_handler(
+                    span,
+                    effect_info,
+                    interface_info,
+                ));
+            }
+        }
+
+        (ty, effects)
+    }
+
     fn infer_expr(&mut self, expr: &mut Spanned<Expr>) -> (ComparableType, EffectSet) {
         match &mut expr.node {
             Expr::PrimaryExpr(field_access_expression) => {
@@ -990,6 +1350,13 @@ impl<'a> TypeInference<'a> {
 
                     (inferred.1, inferred.2)
                 }
+                BlockExpr::TryWith(block, items) => self.infer_try_with(block, items),
+                BlockExpr::Loop(loop_body) => {
+                    let value_ty = self.new_ty_var();
+
+                    self.infer_loop(None, loop_body, value_ty)
+                }
+                BlockExpr::Match(scrutinee, arms) => self.infer_match(scrutinee, arms),
             },
             Expr::Equals(lhs, rhs)
             | Expr::NotEquals(lhs, rhs)
@@ -1007,6 +1374,12 @@ impl<'a> TypeInference<'a> {
             | Expr::Div(lhs, rhs) => {
                 let (e1, effects1) = self.infer_expr(lhs);
                 let effects2 = self.check_expr(rhs, e1.clone());
+
+                if let Some(op) = narrow_operator(&expr.node) {
+                    self.narrow_operator_constraints
+                        .push((expr.span, op, e1.clone()));
+                }
+
                 (e1, effects1.combine(effects2))
             }
             Expr::BitOr(lhs, rhs)
@@ -1017,6 +1390,12 @@ impl<'a> TypeInference<'a> {
             | Expr::Mod(lhs, rhs) => {
                 let (lhs_ty, effects1) = self.infer_expr(lhs);
                 let effects2 = self.check_expr(rhs, lhs_ty.clone());
+
+                if let Some(op) = narrow_operator(&expr.node) {
+                    self.narrow_operator_constraints
+                        .push((expr.span, op, lhs_ty.clone()));
+                }
+
                 (lhs_ty, effects1.combine(effects2))
             }
             Expr::Neg(expr) => {
@@ -1058,6 +1437,9 @@ impl<'a> TypeInference<'a> {
 
                 (new_ty_var, EffectSet::empty())
             }
+            PrimaryExpr::TypedNumber(_, ty) => {
+                (ty.canonical_form(self.symbols), EffectSet::empty())
+            }
             PrimaryExpr::Bool(_) => (
                 ComparableType::Primitive(PrimitiveType::Bool),
                 EffectSet::empty(),
@@ -1066,6 +1448,10 @@ impl<'a> TypeInference<'a> {
                 ComparableType::Primitive(PrimitiveType::StrRef),
                 EffectSet::empty(),
             ),
+            PrimaryExpr::Char(_) => (
+                ComparableType::Primitive(PrimitiveType::U32),
+                EffectSet::empty(),
+            ),
             PrimaryExpr::Namespace {
                 namespaces: _,
                 ident,
@@ -1354,6 +1740,23 @@ impl<'a> TypeInference<'a> {
 
                 (ty, effects)
             }
+            FieldAccessExpression::Index { base, index } => {
+                let (ty, effects) = self.infer_field_access_expression(base);
+                let effects = effects.combine(self.check_expr(index, ComparableType::u32()));
+
+                let ty = Self::substitute(&mut self.unification_table, ty, &self.is_numeric);
+
+                match ty.deref_1() {
+                    ComparableType::Array(elem, _) => (*elem, effects),
+                    // already reported
+                    ComparableType::Void => (ComparableType::Void, effects),
+                    ty => {
+                        self.errors.push(error_not_indexable(index.span, &ty));
+
+                        (ComparableType::Void, effects)
+                    }
+                }
+            }
         }
     }
 
@@ -1437,7 +1840,12 @@ impl<'a> TypeInference<'a> {
             | (Expr::Div(lhs, rhs), expected)
             | (Expr::Mod(lhs, rhs), expected) => {
                 let effects_lhs = self.check_expr(lhs, expected.clone());
-                let effects_rhs = self.check_expr(rhs, expected);
+                let effects_rhs = self.check_expr(rhs, expected.clone());
+
+                if let Some(op) = narrow_operator(&expr.node) {
+                    self.narrow_operator_constraints
+                        .push((expr.span, op, expected));
+                }
 
                 effects_lhs.combine(effects_rhs)
             }
@@ -1465,6 +1873,16 @@ impl<'a> TypeInference<'a> {
     }
 }
 
+/// Operators that codegen only lowers for integers of up to 64 bits.
+fn narrow_operator(expr: &Expr) -> Option<&'static str> {
+    match expr {
+        Expr::Mul(_, _) => Some("*"),
+        Expr::Div(_, _) => Some("/"),
+        Expr::Mod(_, _) => Some("%"),
+        _ => None,
+    }
+}
+
 impl EqUnifyValue for ComparableType {}
 
 impl ena::unify::UnifyKey for TypeVar {
@@ -1684,6 +2102,25 @@ mod tests {
         typecheck_str_expect_error(input);
     }
 
+    #[test]
+    fn typecheck_short_circuit_operands() {
+        let input = r#"script {
+            fn foo(): bool {
+                1 && true
+            }
+        }"#;
+
+        typecheck_str_expect_error(input);
+
+        let input = r#"script {
+            fn foo(): bool {
+                false || 2
+            }
+        }"#;
+
+        typecheck_str_expect_error(input);
+    }
+
     #[test]
     fn typecheck_binops() {
         let input = r#"script {
@@ -1862,49 +2299,284 @@ mod tests {
     }
 
     #[test]
-    fn typecheck_effects() {
+    fn typecheck_match() {
         let input = r#"
-            abi Error {
-                effect E();
-            }
+            typedef E = enum { One(), Two(x: u32) }
 
             script {
-                fn foo() / { Error } {
-                    raise Error::E();
+                fn foo(e: E): u32 {
+                    match (e) {
+                        One() => { 0 },
+                        Two(x) => { x + 1 },
+                    }
                 }
+            }
+        "#;
 
-                fn bar() / { } {
-                    foo();
+        typecheck_str_expect_success(input);
+
+        // every case has to be covered
+        let input = r#"
+            typedef E = enum { One(), Two(x: u32) }
+
+            script {
+                fn foo(e: E): u32 {
+                    match (e) {
+                        Two(x) => { x },
+                    }
                 }
             }
         "#;
 
         typecheck_str_expect_error(input);
 
+        // arms bind exactly the fields of their case
         let input = r#"
-            abi Error {
-                effect E();
-            }
+            typedef E = enum { One(), Two(x: u32) }
 
-            abi Other {
-                effect E();
+            script {
+                fn foo(e: E): u32 {
+                    match (e) {
+                        One() => { 0 },
+                        Two(x, y) => { x },
+                    }
+                }
             }
+        "#;
+
+        typecheck_str_expect_error(input);
+
+        // and all arms have the same type
+        let input = r#"
+            typedef E = enum { One(), Two(x: u32) }
 
             script {
-                fn foo() / { Error } {
-                    raise Error::E();
+                fn foo(e: E): u32 {
+                    match (e) {
+                        One() => { true },
+                        Two(x) => { x },
+                    }
                 }
+            }
+        "#;
 
-                fn bar() / { Error, Other } {
-                    foo();
+        typecheck_str_expect_error(input);
+    }
+
+    #[test]
+    fn typecheck_array_index() {
+        let input = r#"
+            utxo U {
+                storage {
+                    values: [u32; 4];
+                }
+
+                main {
+                    storage.values[1] = storage.values[0] + 1;
+                    yield;
                 }
             }
         "#;
 
         typecheck_str_expect_success(input);
 
+        // elements have the array's element type
         let input = r#"
-            abi Error {
+            utxo U {
+                storage {
+                    values: [u32; 4];
+                }
+
+                main {
+                    storage.values[1] = true;
+                    yield;
+                }
+            }
+        "#;
+
+        typecheck_str_expect_error(input);
+
+        // only arrays can be indexed
+        let input = r#"
+            utxo U {
+                storage {
+                    value: u32;
+                }
+
+                main {
+                    storage.value[0] = 1;
+                    yield;
+                }
+            }
+        "#;
+
+        typecheck_str_expect_error(input);
+    }
+
+    #[test]
+    fn typecheck_array_length() {
+        let input = r#"
+            const N = 2;
+
+            utxo U {
+                storage {
+                    values: [u32; N * 2];
+                }
+
+                main {
+                    storage.values[3] = 1;
+                    yield;
+                }
+            }
+        "#;
+
+        let symbols = typecheck_str(input).unwrap();
+        assert!(!symbols.array_lens.is_empty());
+        assert!(symbols.array_lens.iter().all(|len| len.value() == 4));
+
+        // lengths have to be known at compile time
+        let input = r#"
+            script {
+                fn foo(n: u32) {
+                    let x: [u32; n] = 0;
+                }
+            }
+        "#;
+
+        typecheck_str_expect_error(input);
+
+        let input = r#"
+            utxo U {
+                storage {
+                    values: [u32; 1 - 2];
+                }
+
+                main {
+                    yield;
+                }
+            }
+        "#;
+
+        typecheck_str_expect_error(input);
+    }
+
+    #[test]
+    fn typecheck_loop_expression() {
+        let input = r#"
+            script {
+                fn first_over(limit: u32): u32 {
+                    let mut i: u32 = 0;
+                    let found = loop {
+                        i = i + 1;
+                        if (i > limit) {
+                            break i;
+                        }
+                    };
+                    found
+                }
+
+                fn tail(): u32 {
+                    loop {
+                        break 5;
+                    }
+                }
+
+                fn never(): u32 {
+                    loop {}
+                }
+            }
+        "#;
+
+        typecheck_str_expect_success(input);
+
+        // every `break` gives the loop the same type
+        let input = r#"
+            script {
+                fn foo(cond: bool): u32 {
+                    loop {
+                        if (cond) {
+                            break 1;
+                        }
+                        break true;
+                    }
+                }
+            }
+        "#;
+
+        typecheck_str_expect_error(input);
+
+        let input = r#"
+            script {
+                fn foo(): bool {
+                    let x: u32 = 1;
+                    loop {
+                        break x;
+                    }
+                }
+            }
+        "#;
+
+        typecheck_str_expect_error(input);
+
+        // loops used as statements break without a value
+        let input = r#"
+            script {
+                fn foo() {
+                    loop {
+                        break 1;
+                    }
+                    foo();
+                }
+            }
+        "#;
+
+        typecheck_str_expect_error(input);
+    }
+
+    #[test]
+    fn typecheck_effects() {
+        let input = r#"
+            abi Error {
+                effect E();
+            }
+
+            script {
+                fn foo() / { Error } {
+                    raise Error::E();
+                }
+
+                fn bar() / { } {
+                    foo();
+                }
+            }
+        "#;
+
+        typecheck_str_expect_error(input);
+
+        let input = r#"
+            abi Error {
+                effect E();
+            }
+
+            abi Other {
+                effect E();
+            }
+
+            script {
+                fn foo() / { Error } {
+                    raise Error::E();
+                }
+
+                fn bar() / { Error, Other } {
+                    foo();
+                }
+            }
+        "#;
+
+        typecheck_str_expect_success(input);
+
+        let input = r#"
+            abi Error {
                 effect E();
             }
 
@@ -1996,6 +2668,76 @@ mod tests {
         typecheck_str_expect_error(input);
     }
 
+    #[test]
+    fn typecheck_try_expression() {
+        let input = r#"
+            abi Counter {
+                effect Add(u32): u32;
+            }
+
+            script {
+                fn count() / { Counter } {
+                    let a: u32 = raise Counter::Add(1);
+                }
+
+                fn total(): u32 / {} {
+                    let mut sum: u32 = 0;
+                    let last = try {
+                        count();
+                        sum
+                    }
+                    with Counter::Add(x: u32) {
+                        sum = sum + x;
+                        resume sum;
+                    };
+                    last
+                }
+            }
+        "#;
+
+        typecheck_str_expect_success(input);
+
+        // the value of the `try` is that of its body
+        let input = r#"
+            abi Counter {
+                effect Add(u32): u32;
+            }
+
+            script {
+                fn total(): bool / {} {
+                    try {
+                        1
+                    }
+                    with Counter::Add(x: u32) {
+                        resume x;
+                    }
+                }
+            }
+        "#;
+
+        typecheck_str_expect_error(input);
+
+        // handlers resume with the effect's output type
+        let input = r#"
+            abi Counter {
+                effect Add(u32): u32;
+            }
+
+            script {
+                fn total(): u32 / {} {
+                    try {
+                        1
+                    }
+                    with Counter::Add(x: u32) {
+                        resume true;
+                    }
+                }
+            }
+        "#;
+
+        typecheck_str_expect_error(input);
+    }
+
     #[test]
     fn typecheck_nums() {
         let input = r#"script {
@@ -2169,6 +2911,59 @@ mod tests {
         typecheck_str_expect_error(input);
     }
 
+    #[test]
+    fn typecheck_utxo_resume_argument() {
+        let input = r#"
+        utxo U {
+            Resume u32
+
+            main {
+                yield;
+            }
+        }
+
+        script {
+            fn main(utxo: U) / { StarstreamEnv } {
+                utxo.resume(true);
+            }
+        }
+        "#;
+
+        typecheck_str_expect_error(input);
+
+        let input = r#"
+        utxo U {
+            main {
+                yield;
+            }
+        }
+
+        script {
+            fn main(utxo: U) / { StarstreamEnv } {
+                utxo.resume(());
+            }
+        }
+        "#;
+
+        typecheck_str_expect_success(input);
+
+        let input = r#"
+        utxo U {
+            main {
+                yield;
+            }
+        }
+
+        script {
+            fn main(utxo: U) / { StarstreamEnv } {
+                utxo.resume(5);
+            }
+        }
+        "#;
+
+        typecheck_str_expect_error(input);
+    }
+
     #[test]
     fn typecheck_utxo_methods() {
         let utxo = r#"
@@ -2234,6 +3029,162 @@ mod tests {
         typecheck_str_expect_success(input);
     }
 
+    #[test]
+    fn typecheck_number_suffix() {
+        let input = r#"
+        script {
+            fn f() {
+                let x = 1;
+                let y = 5u64 + x;
+                let z: u64 = x;
+            }
+        }"#;
+
+        typecheck_str_expect_success(input);
+
+        let input = r#"
+        script {
+            fn f() {
+                let x = 1;
+                let y = 5u64 + x;
+                let z: u32 = x;
+            }
+        }"#;
+
+        typecheck_str_expect_error(input);
+
+        let input = r#"
+        script {
+            fn f() {
+                let x: u32 = -3i32;
+            }
+        }"#;
+
+        typecheck_str_expect_error(input);
+    }
+
+    #[test]
+    fn typecheck_tuple_return() {
+        let input = r#"
+        script {
+            fn divmod(a: u32, b: u32): (u32, u32) {
+                (a / b, a % b)
+            }
+        }"#;
+
+        typecheck_str_expect_success(input);
+
+        let input = r#"
+        script {
+            fn divmod(a: u32, b: u32): (u32, bool) {
+                (a / b, a % b)
+            }
+        }"#;
+
+        typecheck_str_expect_error(input);
+    }
+
+    #[test]
+    fn typecheck_128_bit_integers() {
+        let input = r#"
+        script {
+            fn add(a: u128, b: u128): u128 {
+                let sum = a + b;
+                sum + 1
+            }
+
+            fn sub(a: i128, b: i128): i128 {
+                a - b
+            }
+
+            fn less(a: i128, b: i128): bool {
+                -a < b
+            }
+        }"#;
+
+        typecheck_str_expect_success(input);
+
+        let input = r#"
+        script {
+            fn mul(a: u128, b: u128): u128 {
+                let product = a * b;
+                product
+            }
+        }"#;
+
+        typecheck_str_expect_error(input);
+
+        let input = r#"
+        script {
+            fn rem(a: i128, b: i128): i128 {
+                a % b
+            }
+        }"#;
+
+        typecheck_str_expect_error(input);
+
+        let input = r#"
+        script {
+            fn add(a: u128, b: u64): u128 {
+                a + b
+            }
+        }"#;
+
+        typecheck_str_expect_error(input);
+    }
+
+    #[test]
+    fn typecheck_associated_constants() {
+        let input = r#"
+        utxo U {
+            main {}
+
+            impl U {
+                const SMALL: u32 = 3;
+                const BIG: u64 = 1003;
+                const FLAG: bool = U::SMALL < 4;
+            }
+        }"#;
+
+        typecheck_str_expect_success(input);
+
+        let input = r#"
+        utxo U {
+            main {}
+
+            impl U {
+                const SMALL: u32 = 3;
+                const BIG: u64 = U::SMALL;
+            }
+        }"#;
+
+        typecheck_str_expect_error(input);
+    }
+
+    #[test]
+    fn typecheck_builtin_methods() {
+        let input = r#"
+        script {
+            fn foo(token: Intermediate<any, any>): u32 {
+                let list = List::new();
+                list.push(token);
+                list.len()
+            }
+        }"#;
+
+        typecheck_str_expect_success(input);
+
+        let input = r#"
+        script {
+            fn foo(): bool {
+                let list = List::new();
+                list.len()
+            }
+        }"#;
+
+        typecheck_str_expect_error(input);
+    }
+
     #[test]
     fn typecheck_oracle_example() {
         let input = include_str!("../../../grammar/examples/oracle.star");
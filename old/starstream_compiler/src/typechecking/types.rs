@@ -13,6 +13,8 @@ pub enum PrimitiveType {
     I32,
     U64,
     I64,
+    U128,
+    I128,
     Bool,
     StrRef,
 }
@@ -33,6 +35,8 @@ pub enum ComparableType {
     Utxo(SymbolId, String),
     Var(TypeVar),
     Ref(Box<ComparableType>),
+    /// `[T; N]`
+    Array(Box<ComparableType>, usize),
 
     // Void as in the type with cardinality 0
     Void,
@@ -65,6 +69,8 @@ impl ComparableType {
                 | ComparableType::Primitive(PrimitiveType::I32)
                 | ComparableType::Primitive(PrimitiveType::U64)
                 | ComparableType::Primitive(PrimitiveType::I64)
+                | ComparableType::Primitive(PrimitiveType::U128)
+                | ComparableType::Primitive(PrimitiveType::I128)
         )
     }
 
@@ -75,6 +81,8 @@ impl ComparableType {
                 | ComparableType::Primitive(PrimitiveType::I32)
                 | ComparableType::Primitive(PrimitiveType::U64)
                 | ComparableType::Primitive(PrimitiveType::I64)
+                | ComparableType::Primitive(PrimitiveType::U128)
+                | ComparableType::Primitive(PrimitiveType::I128)
                 | ComparableType::Primitive(PrimitiveType::F32)
                 | ComparableType::Primitive(PrimitiveType::F64)
         )
@@ -116,7 +124,7 @@ impl ComparableType {
                 assert!(type_var != v, "recursive type");
             }
             ComparableType::Void => (),
-            ComparableType::Ref(ty) => ty.occurs_check(v),
+            ComparableType::Ref(ty) | ComparableType::Array(ty, _) => ty.occurs_check(v),
         }
     }
 
@@ -156,6 +164,8 @@ impl TypeArg {
             TypeArg::I32 => ComparableType::Primitive(PrimitiveType::I32),
             TypeArg::U64 => ComparableType::Primitive(PrimitiveType::U64),
             TypeArg::I64 => ComparableType::Primitive(PrimitiveType::I64),
+            TypeArg::U128 => ComparableType::Primitive(PrimitiveType::U128),
+            TypeArg::I128 => ComparableType::Primitive(PrimitiveType::I128),
             TypeArg::F32 => ComparableType::Primitive(PrimitiveType::F32),
             TypeArg::F64 => ComparableType::Primitive(PrimitiveType::F64),
             TypeArg::Intermediate { abi: _, storage: _ } => ComparableType::Intermediate,
@@ -167,22 +177,26 @@ impl TypeArg {
                 let symbol_id = type_ref.0.uid.unwrap();
                 let symbol = symbols.get(&symbol_id).unwrap();
 
-                if let Some(type_def) = &symbol.info.type_def {
-                    match type_def {
-                        TypeDefRhs::TypeArg(type_arg) => type_arg.canonical_form_tys(symbols),
-                        TypeDefRhs::Object(typed_bindings) => {
-                            typed_bindings_to_product(typed_bindings, symbols)
-                        }
-                        TypeDefRhs::Variant(variant) => ComparableType::Sum(
-                            variant
-                                .0
-                                .iter()
-                                .map(|(name, ty)| {
-                                    (name.raw.clone(), typed_bindings_to_product(ty, symbols))
-                                })
-                                .collect(),
-                        ),
-                    }
+                if let Some(type_def) = symbol.info.type_def() {
+                    // typedefs nest, so expand each one only once
+                    let canonical_form =
+                        symbol.info.canonical_form().get_or_init(|| match type_def {
+                            TypeDefRhs::TypeArg(type_arg) => type_arg.canonical_form_tys(symbols),
+                            TypeDefRhs::Object(typed_bindings) => {
+                                typed_bindings_to_product(typed_bindings, symbols)
+                            }
+                            TypeDefRhs::Variant(variant) => ComparableType::Sum(
+                                variant
+                                    .0
+                                    .iter()
+                                    .map(|(name, ty, _)| {
+                                        (name.raw.clone(), typed_bindings_to_product(ty, symbols))
+                                    })
+                                    .collect(),
+                            ),
+                        });
+
+                    canonical_form.clone()
                 } else {
                     ComparableType::Utxo(symbol_id, type_ref.0.raw.clone())
                 }
@@ -204,6 +218,17 @@ impl TypeArg {
             TypeArg::Ref(type_arg) => {
                 ComparableType::Ref(type_arg.canonical_form_tys(symbols).boxed())
             }
+            // same shape as the type inferred for tuple expressions
+            TypeArg::Tuple(elems) => ComparableType::Product(
+                elems
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| (i.to_string(), ty.canonical_form_tys(symbols)))
+                    .collect(),
+            ),
+            TypeArg::Array(elem, len) => {
+                ComparableType::Array(elem.canonical_form_tys(symbols).boxed(), len.value())
+            }
         }
     }
 
@@ -235,6 +260,8 @@ impl std::fmt::Display for PrimitiveType {
             PrimitiveType::I32 => write!(f, "i32"),
             PrimitiveType::U64 => write!(f, "u64"),
             PrimitiveType::I64 => write!(f, "i64"),
+            PrimitiveType::U128 => write!(f, "u128"),
+            PrimitiveType::I128 => write!(f, "i128"),
             PrimitiveType::Bool => write!(f, "bool"),
             PrimitiveType::StrRef => write!(f, "str"),
         }
@@ -288,6 +315,9 @@ impl std::fmt::Display for ComparableType {
             ComparableType::Ref(inner) => {
                 write!(f, "&{}", inner)
             }
+            ComparableType::Array(elem, len) => {
+                write!(f, "[{}; {}]", elem, len)
+            }
             ComparableType::Void => {
                 write!(f, "void")
             }
@@ -141,7 +141,10 @@ pub unsafe extern "C" fn run(input_len: usize, run: bool, prove: bool) {
     unsafe { set_ast(str_ast.as_ptr(), str_ast.len()) };
 
     // Compile to Wasm.
-    let (wasm, errors) = starstream_compiler::compile(&ast, symbols);
+    let (wasm, errors) = match starstream_compiler::compile(&ast, symbols) {
+        Ok(wasm) => (Some(wasm), vec![]),
+        Err(errors) => (None, errors),
+    };
     error_count += errors.len() as u32;
     write_reports(&mut compiler_output, code, &errors);
     unsafe {
@@ -179,7 +182,10 @@ pub unsafe extern "C" fn run(input_len: usize, run: bool, prove: bool) {
 
     let mut transaction = Transaction::new();
     let coordination_code = transaction.code_cache().load(wasm);
-    transaction.run_coordination_script(&coordination_code, "main", Vec::new());
+    if let Err(error) = transaction.run_coordination_script(&coordination_code, "main", Vec::new())
+    {
+        error!("Transaction failed: {error}");
+    }
 
     {
         let sequence_diagram = transaction.to_mermaid_diagram();
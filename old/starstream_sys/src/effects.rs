@@ -80,6 +80,7 @@ unsafe extern "C" {
         name: *const u8,
         name_len: usize,
         output_ptr: *mut (),
+        output_len: usize,
         not_null: *mut u8,
     );
 }
@@ -96,6 +97,7 @@ pub fn get_raised_effect_data<Effect>(name: &str) -> Option<Effect> {
             name.as_ptr(),
             name.len(),
             effect.as_mut_ptr() as *mut (),
+            size_of::<Effect>(),
             &mut not_null as *mut u8,
         );
 
@@ -107,6 +109,22 @@ pub fn get_raised_effect_data<Effect>(name: &str) -> Option<Effect> {
     }
 }
 
+#[link(wasm_import_module = "env")]
+unsafe extern "C" {
+    unsafe fn starstream_get_raised_effect_name(output_ptr: *mut u8, output_cap: usize) -> usize;
+}
+
+#[link(wasm_import_module = "env")]
+unsafe extern "C" {
+    unsafe fn starstream_is_effect_handled(name: *const u8, name_len: usize) -> u32;
+}
+
+/// Whether a raise of `name` would reach a handler, either one registered for
+/// it or a catch-all.
+pub fn is_effect_handled(name: &str) -> bool {
+    unsafe { starstream_is_effect_handled(name.as_ptr(), name.len()) != 0 }
+}
+
 #[link(wasm_import_module = "env")]
 unsafe extern "C" {
     unsafe fn starstream_resume_throwing_program(
@@ -136,6 +154,11 @@ pub trait Effect {
     fn raise(data: &Self::Input) -> Self::Output {
         crate::effects::raise::<Self::Input, Self::Output>(Self::NAME, data)
     }
+
+    /// Whether raising this effect would reach a handler.
+    fn is_handled() -> bool {
+        is_effect_handled(Self::NAME)
+    }
 }
 
 pub struct EffectHandler<'a, E: Effect> {
@@ -161,6 +184,50 @@ impl<E: Effect> Registrable for EffectHandler<'_, E> {
     }
 }
 
+/// Name the catch-all handler is registered under.
+const CATCH_ALL_EFFECT: &str = "*";
+
+/// Longest effect name a catch-all handler is told about in full.
+const MAX_EFFECT_NAME_LEN: usize = 64;
+
+/// Handler for every effect raised without a handler of its own.
+///
+/// It's given the name of the raised effect, and must resume the raising
+/// program itself with [`resume_throwing_program`], usually after reading the
+/// input with [`get_raised_effect_data`]. Exported as
+/// `starstream_catch_all_handle`.
+pub struct CatchAllHandler<'a> {
+    f: &'a dyn Fn(&str),
+}
+
+impl<'a> CatchAllHandler<'a> {
+    pub fn with<F>(f: &'a F) -> Self
+    where
+        F: Fn(&str),
+    {
+        Self { f }
+    }
+
+    pub fn handle(&self) {
+        let mut name = [0u8; MAX_EFFECT_NAME_LEN];
+        let len = unsafe { starstream_get_raised_effect_name(name.as_mut_ptr(), name.len()) };
+        let name = core::str::from_utf8(&name[..len.min(MAX_EFFECT_NAME_LEN)]).unwrap_or_default();
+
+        (self.f)(name);
+    }
+}
+
+impl Registrable for CatchAllHandler<'_> {
+    type DropGuard<'a>
+        = DropGuard<'a>
+    where
+        Self: 'a;
+
+    fn register(&self) -> Self::DropGuard<'_> {
+        register_effect_handler(CATCH_ALL_EFFECT, self)
+    }
+}
+
 impl<T: Registrable> Registrable for &T {
     type DropGuard<'a>
         = T::DropGuard<'a>
@@ -0,0 +1,235 @@
+//! Fixed-point decimals for amounts with a fractional part.
+
+use core::fmt;
+
+/// An unsigned decimal with `DECIMALS` digits after the point.
+///
+/// Stored as a `u64` count of `10^-DECIMALS` units, so it crosses the UTXO
+/// boundary as a plain `i64`. Products are computed in `u128` and rounded to
+/// the nearest unit, ties away from zero. `DECIMALS` is at most 19.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Fixed<const DECIMALS: u8> {
+    raw: u64,
+}
+
+impl<const DECIMALS: u8> Fixed<DECIMALS> {
+    /// The raw value of `1`.
+    pub const SCALE: u64 = {
+        assert!(DECIMALS <= 19, "Fixed supports at most 19 decimals");
+        10u64.pow(DECIMALS as u32)
+    };
+
+    pub const ZERO: Self = Self { raw: 0 };
+    pub const ONE: Self = Self { raw: Self::SCALE };
+    pub const MAX: Self = Self { raw: u64::MAX };
+
+    /// The value `raw * 10^-DECIMALS`.
+    #[inline]
+    pub const fn from_raw(raw: u64) -> Self {
+        Self { raw }
+    }
+
+    /// The number of `10^-DECIMALS` units in this value.
+    #[inline]
+    pub const fn to_raw(self) -> u64 {
+        self.raw
+    }
+
+    /// The whole number `int`, or `None` if it doesn't fit.
+    #[inline]
+    pub const fn from_int(int: u64) -> Option<Self> {
+        match int.checked_mul(Self::SCALE) {
+            Some(raw) => Some(Self { raw }),
+            None => None,
+        }
+    }
+
+    /// The integer part, rounding toward zero.
+    #[inline]
+    pub const fn trunc(self) -> u64 {
+        self.raw / Self::SCALE
+    }
+
+    /// The nearest whole number, ties away from zero.
+    #[inline]
+    pub const fn round(self) -> u64 {
+        let int = self.trunc();
+        if self.raw % Self::SCALE >= Self::SCALE - Self::SCALE / 2 {
+            int + 1
+        } else {
+            int
+        }
+    }
+
+    #[inline]
+    pub const fn checked_add(self, rhs: Self) -> Option<Self> {
+        match self.raw.checked_add(rhs.raw) {
+            Some(raw) => Some(Self { raw }),
+            None => None,
+        }
+    }
+
+    #[inline]
+    pub const fn checked_sub(self, rhs: Self) -> Option<Self> {
+        match self.raw.checked_sub(rhs.raw) {
+            Some(raw) => Some(Self { raw }),
+            None => None,
+        }
+    }
+
+    /// `self * rhs`, rounded to the nearest unit.
+    #[inline]
+    pub const fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let product = self.raw as u128 * rhs.raw as u128;
+        Self::from_wide(round_div(product, Self::SCALE as u128))
+    }
+
+    /// `self * rhs` for a whole number `rhs`. Exact, so no rounding happens.
+    #[inline]
+    pub const fn checked_mul_int(self, rhs: u64) -> Option<Self> {
+        match self.raw.checked_mul(rhs) {
+            Some(raw) => Some(Self { raw }),
+            None => None,
+        }
+    }
+
+    /// The same value with `TO` decimals, rounded to the nearest unit when
+    /// `TO < DECIMALS`, or `None` if it doesn't fit.
+    #[inline]
+    pub const fn rescale<const TO: u8>(self) -> Option<Fixed<TO>> {
+        let raw = if TO >= DECIMALS {
+            let factor = 10u128.pow((TO - DECIMALS) as u32);
+            self.raw as u128 * factor
+        } else {
+            let factor = 10u128.pow((DECIMALS - TO) as u32);
+            round_div(self.raw as u128, factor)
+        };
+        Fixed::<TO>::from_wide(raw)
+    }
+
+    #[inline]
+    const fn from_wide(raw: u128) -> Option<Self> {
+        if raw > u64::MAX as u128 {
+            None
+        } else {
+            Some(Self { raw: raw as u64 })
+        }
+    }
+}
+
+/// `n / d` rounded to the nearest integer, ties away from zero.
+#[inline]
+const fn round_div(n: u128, d: u128) -> u128 {
+    let q = n / d;
+    if n % d >= d - d / 2 { q + 1 } else { q }
+}
+
+impl<const DECIMALS: u8> fmt::Display for Fixed<DECIMALS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.trunc())?;
+        if DECIMALS > 0 {
+            let frac = self.raw % Self::SCALE;
+            write!(f, ".{frac:0width$}", width = DECIMALS as usize)?;
+        }
+        Ok(())
+    }
+}
+
+impl<const DECIMALS: u8> fmt::Debug for Fixed<DECIMALS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::Fixed;
+    use std::string::ToString;
+
+    type Usd = Fixed<2>;
+
+    #[test]
+    fn add_and_sub() {
+        let a = Usd::from_raw(150);
+        let b = Usd::from_raw(275);
+        assert_eq!(a.checked_add(b), Some(Usd::from_raw(425)));
+        assert_eq!(b.checked_sub(a), Some(Usd::from_raw(125)));
+        assert_eq!(a.checked_sub(b), None);
+        assert_eq!(Usd::MAX.checked_add(Usd::from_raw(1)), None);
+    }
+
+    #[test]
+    fn from_int() {
+        assert_eq!(Usd::from_int(3), Some(Usd::from_raw(300)));
+        assert_eq!(Usd::from_int(u64::MAX), None);
+        assert_eq!(Fixed::<0>::ONE.to_raw(), 1);
+        assert_eq!(Fixed::<19>::ONE.to_raw(), 10_000_000_000_000_000_000);
+    }
+
+    #[test]
+    fn mul() {
+        // 1.50 * 2.00 = 3.00
+        assert_eq!(
+            Usd::from_raw(150).checked_mul(Usd::from_raw(200)),
+            Some(Usd::from_raw(300))
+        );
+        assert_eq!(
+            Usd::from_raw(150).checked_mul_int(3),
+            Some(Usd::from_raw(450))
+        );
+        // The intermediate product is wider than u64, but the result fits.
+        let big = Fixed::<9>::from_int(10_000_000_000).unwrap();
+        assert_eq!(
+            big.checked_mul(Fixed::<9>::from_raw(500_000_000)),
+            Fixed::<9>::from_int(5_000_000_000)
+        );
+        assert_eq!(Usd::MAX.checked_mul(Usd::from_int(2).unwrap()), None);
+    }
+
+    #[test]
+    fn mul_rounds_to_nearest() {
+        // 0.15 * 0.15 = 0.0225, which rounds down
+        assert_eq!(
+            Usd::from_raw(15).checked_mul(Usd::from_raw(15)),
+            Some(Usd::from_raw(2))
+        );
+        // 0.05 * 0.50 = 0.025, a tie, which rounds up
+        assert_eq!(
+            Usd::from_raw(5).checked_mul(Usd::from_raw(50)),
+            Some(Usd::from_raw(3))
+        );
+        // 0.07 * 0.11 = 0.0077, which rounds up
+        assert_eq!(
+            Usd::from_raw(7).checked_mul(Usd::from_raw(11)),
+            Some(Usd::from_raw(1))
+        );
+    }
+
+    #[test]
+    fn round_and_trunc() {
+        assert_eq!(Usd::from_raw(249).trunc(), 2);
+        assert_eq!(Usd::from_raw(249).round(), 2);
+        assert_eq!(Usd::from_raw(250).round(), 3);
+        assert_eq!(Usd::from_raw(299).round(), 3);
+        assert_eq!(Fixed::<0>::from_raw(7).round(), 7);
+    }
+
+    #[test]
+    fn rescale() {
+        let a = Fixed::<4>::from_raw(12_345);
+        assert_eq!(a.rescale::<6>(), Some(Fixed::<6>::from_raw(1_234_500)));
+        assert_eq!(a.rescale::<3>(), Some(Fixed::<3>::from_raw(1_235)));
+        assert_eq!(a.rescale::<2>(), Some(Fixed::<2>::from_raw(123)));
+        assert_eq!(Fixed::<0>::MAX.rescale::<1>(), None);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Usd::from_raw(5).to_string(), "0.05");
+        assert_eq!(Usd::from_raw(12_345).to_string(), "123.45");
+        assert_eq!(Fixed::<0>::from_raw(42).to_string(), "42");
+    }
+}
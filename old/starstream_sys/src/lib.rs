@@ -6,8 +6,10 @@ use core::{fmt::Write, marker::PhantomData, mem::MaybeUninit, panic::PanicInfo};
 #[macro_use]
 pub mod static_coroutine;
 mod effects;
+mod fixed;
 
 pub use effects::*;
+pub use fixed::Fixed;
 
 #[macro_export]
 macro_rules! metadata {
@@ -161,6 +163,33 @@ macro_rules! panic_handler {
     };
 }
 
+#[link(wasm_import_module = "env")]
+unsafe extern "C" {
+    safe fn starstream_require(cond: bool, code: u32);
+}
+
+/// Stop the transaction with `code` unless `cond` holds.
+///
+/// Meant for preconditions checked up front: the host ends the transaction
+/// immediately, without formatting a panic message, and reports `code` as a
+/// failed precondition rather than an abort.
+#[inline]
+pub fn require(cond: bool, code: u32) {
+    starstream_require(cond, code);
+}
+
+/// Stop the transaction with the given error code unless a condition holds.
+///
+/// ```ignore
+/// starstream::require!(amount > 0, 1);
+/// ```
+#[macro_export]
+macro_rules! require {
+    ($cond:expr, $code:expr $(,)?) => {
+        $crate::require($cond, $code)
+    };
+}
+
 // ----------------------------------------------------------------------------
 // Common import environment
 
@@ -174,8 +203,21 @@ unsafe extern "C" {
     #[link_name = "starstream_coordination_code"]
     pub safe fn coordination_code() -> CodeHash;
 
+    /// Get the hash of the code that called into the current program, or
+    /// [`CodeHash::zero`] for the top-level coordination script.
+    #[link_name = "starstream_caller_code"]
+    pub safe fn caller_code() -> CodeHash;
+
+    /// Declare `len` bytes at `ptr` as a buffer the host may write into.
+    /// Only checked transactions enforce this.
+    #[link_name = "starstream_alloc"]
+    pub unsafe fn declare_buffer(ptr: *mut u8, len: usize);
+
     #[link_name = "starstream_keccak256"]
     unsafe fn precompile_keccak256(buf: *const u8, len: usize, result: *mut u8);
+
+    #[link_name = "starstream_tx_signers"]
+    unsafe fn starstream_tx_signers(return_addr: *mut [u8; 32], max: usize) -> usize;
 }
 
 #[inline]
@@ -185,6 +227,25 @@ pub fn keccak256(buf: &[u8]) -> [u8; 32] {
     out
 }
 
+/// Fill `out` with the public keys this transaction is signed by, and return
+/// how many there are in total, which may be more than fit in `out`.
+#[inline]
+pub fn tx_signers(out: &mut [[u8; 32]]) -> usize {
+    unsafe { starstream_tx_signers(out.as_mut_ptr(), out.len()) }
+}
+
+/// Whether this transaction is signed by the public key `key`.
+///
+/// Only the first [`MAX_TX_SIGNERS`] signers are considered.
+pub fn is_tx_signer(key: &[u8; 32]) -> bool {
+    let mut signers = [[0u8; 32]; MAX_TX_SIGNERS];
+    let count = tx_signers(&mut signers).min(MAX_TX_SIGNERS);
+    signers[..count].contains(key)
+}
+
+/// How many signers [`is_tx_signer`] looks through.
+pub const MAX_TX_SIGNERS: usize = 16;
+
 pub fn assert_tx_signed_by(_key: PublicKey) {
     // TODO: assert that this coordination-script-call is signed by `key`
 }
@@ -373,6 +434,7 @@ unsafe extern "C" {
         resume_arg: *mut (),
         resume_arg_size: usize,
     );
+    unsafe fn starstream_yield_layout(fields: *const YieldField, count: usize);
 }
 
 // yield = fn(a...) -> (b...)
@@ -401,6 +463,69 @@ pub fn sleep_mut<Resume, Yield>(data: &mut Yield) -> Resume {
     sleep(data)
 }
 
+/// A field of a [`YieldStruct`], which the host can read by name while the
+/// struct is yielded.
+///
+/// This is the `starstream_yield_layout` descriptor entry: the address and
+/// length of the name, then the offset and size of the field within the
+/// struct, as four `u32`s.
+#[repr(C)]
+pub struct YieldField {
+    name: *const u8,
+    name_len: usize,
+    offset: usize,
+    size: usize,
+}
+
+impl YieldField {
+    pub const fn new(name: &'static str, offset: usize, size: usize) -> Self {
+        YieldField {
+            name: name.as_ptr(),
+            name_len: name.len(),
+            offset,
+            size,
+        }
+    }
+}
+
+/// A struct that describes its fields to the host when yielded with
+/// [`yield_struct`]. Implement with [`yield_struct!`].
+///
+/// # Safety
+///
+/// Every field in `FIELDS` must lie within `Self`.
+pub unsafe trait YieldStruct {
+    const FIELDS: &'static [YieldField];
+}
+
+/// Like [`sleep`], but also lets the host read the fields of `data` by name.
+pub fn yield_struct<Resume, Yield: YieldStruct>(data: &Yield) -> Resume {
+    unsafe {
+        starstream_yield_layout(Yield::FIELDS.as_ptr(), Yield::FIELDS.len());
+    }
+    sleep(data)
+}
+
+#[macro_export]
+macro_rules! yield_struct {
+    ($name:ident { $($field:ident: $ty:ty),* $(,)? }) => {
+        // checks the fields have the given types
+        const _: fn(&$name) = |value| {
+            $(let _: &$ty = &value.$field;)*
+        };
+
+        unsafe impl $crate::YieldStruct for $name {
+            const FIELDS: &'static [$crate::YieldField] = &[$(
+                $crate::YieldField::new(
+                    stringify!($field),
+                    core::mem::offset_of!($name, $field),
+                    core::mem::size_of::<$ty>(),
+                ),
+            )*];
+        }
+    };
+}
+
 // ----------------------------------------------------------------------------
 // UTXO import (lib) interface
 
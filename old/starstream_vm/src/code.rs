@@ -3,63 +3,343 @@
 use std::{
     collections::HashMap,
     path::Path,
-    sync::{Arc, RwLock},
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
 };
 
 use sha2::{Sha256, digest::DynDigest};
-use wasmi::{Engine, Module};
+use tiny_keccak::Hasher;
+use wasmi::{Engine, ExternType, Module, core::ValueType};
 
-use crate::util::DisplayHex;
+use crate::{TransactionError, util::DisplayHex};
+
+/// The hash function used to content-address contract code.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Keccak256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub(crate) fn digest(self, data: &[u8]) -> [u8; 32] {
+        let mut hash = [0; 32];
+        match self {
+            HashAlgorithm::Sha256 => {
+                let mut hasher = Sha256::default();
+                hasher.update(data);
+                hasher.finalize_into(&mut hash[..]).unwrap();
+            }
+            HashAlgorithm::Keccak256 => {
+                let mut hasher = tiny_keccak::Keccak::v256();
+                hasher.update(data);
+                hasher.finalize(&mut hash);
+            }
+            HashAlgorithm::Blake3 => {
+                hash = *blake3::hash(data).as_bytes();
+            }
+        }
+        hash
+    }
+}
 
 /// A raw ID describing a contract in a content-addressible way.
+///
+/// The algorithm is part of the ID, so digests produced by different hash
+/// functions never compare equal.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
-pub struct CodeHash([u8; 32]);
+pub struct CodeHash {
+    algorithm: HashAlgorithm,
+    digest: [u8; 32],
+}
 
 impl CodeHash {
-    fn from_content(code: &[u8]) -> CodeHash {
-        // Currently this is just sha256 of the whole WASM file. There might
+    fn from_content(code: &[u8], algorithm: HashAlgorithm) -> CodeHash {
+        // Currently this is just a hash of the whole WASM file. There might
         // be stuff in the WASM file that we don't want to count or that isn't
         // reproducible and should exclude here, but that seems tricky.
-        let mut hash = [0; 32];
-        let mut hasher = Sha256::default();
-        hasher.update(code);
-        hasher.finalize_into(&mut hash[..]).unwrap();
-        CodeHash(hash)
+        CodeHash {
+            algorithm,
+            digest: algorithm.digest(code),
+        }
+    }
+
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
     }
 
     pub(crate) fn raw(&self) -> [u8; 32] {
-        self.0
+        self.digest
     }
 }
 
 impl std::fmt::Debug for CodeHash {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "CodeHash({})", DisplayHex(&self.0[..]))
+        write!(
+            f,
+            "CodeHash({:?}:{})",
+            self.algorithm,
+            DisplayHex(&self.digest[..])
+        )
+    }
+}
+
+/// An error compiling contract code.
+#[derive(Debug, Clone)]
+pub enum CodeError {
+    /// The blob is not a valid Wasm module.
+    Compile(String),
+    /// No code with this hash was loaded.
+    NotFound(CodeHash),
+}
+
+impl std::fmt::Display for CodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodeError::Compile(message) => write!(f, "invalid contract code: {message}"),
+            CodeError::NotFound(hash) => write!(f, "no contract code loaded for {hash:?}"),
+        }
+    }
+}
+
+impl std::error::Error for CodeError {}
+
+/// The imports and exports of a contract, as listed by
+/// [`ContractCode::describe`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleInfo {
+    pub imports: Vec<ImportInfo>,
+    pub exports: Vec<ExportInfo>,
+}
+
+/// A single import, with its type written in WAT syntax such as
+/// `(func (param i32) (result i64))`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportInfo {
+    pub module: String,
+    pub name: String,
+    pub ty: String,
+}
+
+/// A single export, with its type written in WAT syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportInfo {
+    pub name: String,
+    pub ty: String,
+}
+
+/// Prefix of the exports through which a UTXO can be queried without being
+/// changed.
+pub(crate) const QUERY_PREFIX: &str = "starstream_query_";
+
+/// An export that can be called to query a UTXO, as listed by
+/// [`ContractCode::query_exports`]. Types are written in WAT syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryExport {
+    pub name: String,
+    /// Includes the UTXO's own address as the first parameter.
+    pub params: Vec<String>,
+    pub result: Option<String>,
+}
+
+fn wat_value_type(ty: &ValueType) -> &'static str {
+    match ty {
+        ValueType::I32 => "i32",
+        ValueType::I64 => "i64",
+        ValueType::F32 => "f32",
+        ValueType::F64 => "f64",
+        ValueType::FuncRef => "funcref",
+        ValueType::ExternRef => "externref",
     }
 }
 
+fn wat_extern_type(ty: &ExternType) -> String {
+    match ty {
+        ExternType::Func(func) => {
+            let mut wat = String::from("(func");
+            if !func.params().is_empty() {
+                wat.push_str(" (param");
+                for param in func.params() {
+                    wat.push(' ');
+                    wat.push_str(wat_value_type(param));
+                }
+                wat.push(')');
+            }
+            if !func.results().is_empty() {
+                wat.push_str(" (result");
+                for result in func.results() {
+                    wat.push(' ');
+                    wat.push_str(wat_value_type(result));
+                }
+                wat.push(')');
+            }
+            wat.push(')');
+            wat
+        }
+        ExternType::Global(global) => {
+            let content = wat_value_type(&global.content());
+            if global.mutability().is_mut() {
+                format!("(global (mut {content}))")
+            } else {
+                format!("(global {content})")
+            }
+        }
+        ExternType::Table(table) => match table.maximum() {
+            Some(maximum) => format!(
+                "(table {} {maximum} {})",
+                table.minimum(),
+                wat_value_type(&table.element())
+            ),
+            None => format!(
+                "(table {} {})",
+                table.minimum(),
+                wat_value_type(&table.element())
+            ),
+        },
+        ExternType::Memory(memory) => {
+            let initial = u32::from(memory.initial_pages());
+            match memory.maximum_pages() {
+                Some(maximum) => format!("(memory {initial} {})", u32::from(maximum)),
+                None => format!("(memory {initial})"),
+            }
+        }
+    }
+}
+
+/// How many engines each [`ContractCode`] keeps a compiled module for.
+///
+/// Every transaction has its own engine, so without a limit the cache would
+/// hold on to one module and engine per transaction ever run.
+const MAX_CACHED_ENGINES: usize = 8;
+
 /// A loaded but not instantiated Wasm blob.
 pub struct ContractCode {
     wasm: Vec<u8>,
     hash: CodeHash,
+    /// Compiled on first use by each engine, since modules can't be shared
+    /// between engines. Least recently used first.
+    compiled: Mutex<Vec<(Engine, Result<Module, CodeError>)>>,
+    compilations: AtomicUsize,
 }
 
 impl ContractCode {
-    fn load(wasm: Vec<u8>) -> ContractCode {
+    fn load(wasm: Vec<u8>, algorithm: HashAlgorithm) -> ContractCode {
         ContractCode {
-            hash: CodeHash::from_content(&wasm),
+            hash: CodeHash::from_content(&wasm, algorithm),
             wasm,
+            compiled: Mutex::new(Vec::new()),
+            compilations: AtomicUsize::new(0),
+        }
+    }
+
+    fn compile(&self, engine: &Engine) -> Result<Module, CodeError> {
+        self.compilations.fetch_add(1, Ordering::Relaxed);
+        Module::new(engine, &self.wasm[..]).map_err(|e| CodeError::Compile(e.to_string()))
+    }
+
+    /// Compile the module, or get it from the cache if it was already compiled
+    /// for `engine`.
+    ///
+    /// Only the [`MAX_CACHED_ENGINES`] most recently used engines are
+    /// remembered.
+    pub fn try_module(&self, engine: &Engine) -> Result<Module, CodeError> {
+        let mut compiled = self.compiled.lock().unwrap();
+        if let Some(index) = compiled
+            .iter()
+            .position(|(cached_engine, _)| Engine::same(cached_engine, engine))
+        {
+            let entry = compiled.remove(index);
+            let module = entry.1.clone();
+            compiled.push(entry);
+            return module;
         }
+
+        let module = self.compile(engine);
+        if compiled.len() >= MAX_CACHED_ENGINES {
+            compiled.remove(0);
+        }
+        compiled.push((engine.clone(), module.clone()));
+        module
+    }
+
+    /// [`ContractCode::try_module`], failing the transaction if the code
+    /// doesn't compile.
+    pub(crate) fn module(&self, engine: &Engine) -> Result<Module, TransactionError> {
+        self.try_module(engine)
+            .map_err(|error| TransactionError::InvalidCode {
+                code: self.hash,
+                message: error.to_string(),
+            })
     }
 
-    pub(crate) fn module(&self, engine: &Engine) -> Module {
-        Module::new(engine, &self.wasm[..]).unwrap()
+    /// Number of times the Wasm blob was compiled so far.
+    #[cfg(feature = "test-utils")]
+    pub fn compilations(&self) -> usize {
+        self.compilations.load(Ordering::Relaxed)
     }
 
     pub fn hash(&self) -> CodeHash {
         self.hash
     }
 
+    /// List the imports and exports of the module, in the order they appear.
+    ///
+    /// The module is compiled for `engine`, so code its configuration rejects
+    /// fails here the same way it would when run.
+    pub fn describe(&self, engine: &Engine) -> Result<ModuleInfo, CodeError> {
+        let module = self.try_module(engine)?;
+        Ok(ModuleInfo {
+            imports: module
+                .imports()
+                .map(|import| ImportInfo {
+                    module: import.module().to_owned(),
+                    name: import.name().to_owned(),
+                    ty: wat_extern_type(import.ty()),
+                })
+                .collect(),
+            exports: module
+                .exports()
+                .map(|export| ExportInfo {
+                    name: export.name().to_owned(),
+                    ty: wat_extern_type(export.ty()),
+                })
+                .collect(),
+        })
+    }
+
+    /// List the exported functions UTXOs of this code can be queried
+    /// through, in the order they appear.
+    ///
+    /// These are the functions whose names start with `starstream_query_`
+    /// and that return at most one value. The module is compiled for `engine`.
+    pub fn query_exports(&self, engine: &Engine) -> Result<Vec<QueryExport>, CodeError> {
+        let module = self.try_module(engine)?;
+        Ok(module
+            .exports()
+            .filter(|export| export.name().starts_with(QUERY_PREFIX))
+            .filter_map(|export| match export.ty() {
+                ExternType::Func(func) if func.results().len() <= 1 => Some(QueryExport {
+                    name: export.name().to_owned(),
+                    params: func
+                        .params()
+                        .iter()
+                        .map(|param| wat_value_type(param).to_owned())
+                        .collect(),
+                    result: func
+                        .results()
+                        .first()
+                        .map(|result| wat_value_type(result).to_owned()),
+                }),
+                _ => None,
+            })
+            .collect())
+    }
+
     pub fn wasm(&self) -> &[u8] {
         &self.wasm
     }
@@ -76,12 +356,25 @@ impl std::fmt::Debug for ContractCode {
 /// A content-addressed cache of Wasm blobs.
 #[derive(Default)]
 pub struct CodeCache {
+    algorithm: HashAlgorithm,
     by_hash: RwLock<HashMap<CodeHash, Arc<ContractCode>>>,
 }
 
 impl CodeCache {
+    /// Create an empty cache which addresses code using `algorithm`.
+    pub fn with_algorithm(algorithm: HashAlgorithm) -> CodeCache {
+        CodeCache {
+            algorithm,
+            by_hash: Default::default(),
+        }
+    }
+
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
     pub fn load(&self, wasm: Vec<u8>) -> Arc<ContractCode> {
-        let result = Arc::new(ContractCode::load(wasm));
+        let result = Arc::new(ContractCode::load(wasm, self.algorithm));
         self.by_hash
             .write()
             .unwrap()
@@ -119,4 +412,22 @@ impl CodeCache {
             .expect("todo: load code by hash")
             .clone()
     }
+
+    /// Get code by hash, if it compiles for `engine`.
+    ///
+    /// Whether it compiled is remembered along with the module, so code that
+    /// doesn't is only compiled once for each engine however often it's asked
+    /// for, and fails the same way each time.
+    pub fn try_get(&self, hash: CodeHash, engine: &Engine) -> Result<Arc<ContractCode>, CodeError> {
+        let code = self
+            .by_hash
+            .read()
+            .unwrap()
+            .get(&hash)
+            .cloned()
+            .ok_or(CodeError::NotFound(hash))?;
+
+        code.try_module(engine)?;
+        Ok(code)
+    }
 }
@@ -1,33 +1,48 @@
 //! Starstream VM as a library.
 #![allow(dead_code)] // We're WIP enough that some dead code is to be expected.
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    time::Instant,
+};
 
-pub use code::{CodeCache, CodeHash, ContractCode};
+use code::QUERY_PREFIX;
+pub use code::{
+    CodeCache, CodeError, CodeHash, ContractCode, ExportInfo, HashAlgorithm, ImportInfo,
+    ModuleInfo, QueryExport,
+};
 use log::{debug, info, trace};
-use rand::RngCore;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use sha2::{Sha256, digest::DynDigest};
 use tiny_keccak::Hasher;
 use util::DisplayHex;
-pub use wasmi::Value;
 use wasmi::{
-    AsContext, AsContextMut, Caller, Config, Engine, ExternRef, ExternType, ImportType, Instance,
-    Linker, ResumableCall, Store, StoreContext, StoreContextMut, core::HostError,
+    AsContext, AsContextMut, Caller, Engine, ExternRef, ExternType, ImportType, Instance, Linker,
+    Memory, ResourceLimiter, ResumableCall, Store, StoreContext, StoreContextMut,
+    core::{HostError, TrapCode},
+    errors::{MemoryError, TableError},
 };
+pub use wasmi::{Config, StackLimits, Value};
 
 mod code;
 mod mermaid;
 mod mock_ledger;
 mod nebula;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 mod util;
+pub mod wasm_abi;
 
-fn memory<'a, T>(caller: &'a mut Caller<T>) -> (&'a mut [u8], &'a mut T) {
-    caller
+/// The memory of the contract calling a host function, and the host state.
+/// A trap if the contract doesn't export its memory.
+fn memory<'a, T>(caller: &'a mut Caller<T>) -> Result<(&'a mut [u8], &'a mut T), WasmiError> {
+    let memory = caller
         .get_export("memory")
-        .unwrap()
-        .into_memory()
-        .unwrap()
-        .data_and_store_mut(caller.as_context_mut())
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| WasmiError::new("contract doesn't export its memory"))?;
+    Ok(memory.data_and_store_mut(caller.as_context_mut()))
 }
 
 // ----------------------------------------------------------------------------
@@ -63,9 +78,7 @@ fn fake_import<T>(linker: &mut Linker<T>, import: &ImportType, message: &str) {
             import.module(),
             import.name(),
             func.clone(),
-            move |_caller, _inputs, _outputs| {
-                panic!("{}", message);
-            },
+            move |_caller, _inputs, _outputs| Err(WasmiError::new(message.clone())),
         );
         if !matches!(
             r,
@@ -76,14 +89,57 @@ fn fake_import<T>(linker: &mut Linker<T>, import: &ImportType, message: &str) {
     }
 }
 
+/// The error for an import of a `starstream_*` module that the host doesn't
+/// know how to fulfil.
+fn bad_import(code: &ContractCode, import: &ImportType) -> TransactionError {
+    TransactionError::LinkError {
+        code: code.hash(),
+        message: format!("bad import {}::{}", import.module(), import.name()),
+    }
+}
+
 // ----------------------------------------------------------------------------
 
 #[derive(Debug, Clone)]
 enum Interrupt {
     // Common
+    Abort,
+    RequireFailed {
+        code: u32,
+    },
     CoordinationCode {
         return_addr: u32,
     },
+    CallerCode {
+        return_addr: u32,
+    },
+    DeclareBuffer {
+        ptr: u32,
+        len: u32,
+    },
+    YieldLayout {
+        fields: Vec<YieldField>,
+    },
+    Event {
+        name: String,
+        data: Vec<u8>,
+    },
+    UndeclaredMemoryWrite {
+        address: u32,
+        len: usize,
+    },
+    MemoryBudgetExceeded,
+    /// Raised by the host on behalf of `program`, which ran out of fuel while
+    /// running `entry_point` of `code`. `None` if it ran out before the
+    /// program was registered.
+    OutOfFuel {
+        program: Option<ProgramIdx>,
+        code: CodeHash,
+        entry_point: String,
+    },
+    /// Raised by the host when a program can't go on at all, which ends the
+    /// transaction with the error.
+    Failed(TransactionError),
     RegisterEffectHandler {
         name: String,
         handler_addr: u32,
@@ -94,8 +150,13 @@ enum Interrupt {
     GetRaisedEffectData {
         name: String,
         output_ptr_data: u32,
+        output_len: u32,
         not_null: u32,
     },
+    GetRaisedEffectName {
+        output_ptr: u32,
+        output_cap: u32,
+    },
     ResumeThrowingProgram {
         name: String,
         input_ptr_data: u32,
@@ -125,10 +186,17 @@ enum Interrupt {
         method: String,
         inputs: Vec<Value>,
     },
+    // UTXO -> UTXO
+    UtxoQueryDirect {
+        utxo_id: UtxoId,
+        method: String,
+        inputs: Vec<Value>,
+    },
     // Coordination <- UTXO
     Yield {
         name: String,
         data: u32,
+        data_len: u32,
         resume_arg: u32,
         resume_arg_len: u32,
     },
@@ -151,6 +219,7 @@ enum Interrupt {
         code: CodeHash,
         entry_point: String,
         inputs: Vec<Value>,
+        amount: u64,
         // some id for the token contract
         // it should be something content-addressed eventually
         token_type_id: u64,
@@ -160,6 +229,9 @@ enum Interrupt {
         entry_point: String,
         inputs: Vec<Value>,
         token_id: TokenId,
+        // `starstream_try_bind`: the bind function returns a status first,
+        // and the token is only bound if it's zero.
+        fallible: bool,
     },
     TokenUnbind {
         token_id: TokenId,
@@ -170,6 +242,11 @@ enum Interrupt {
     TokenBurn {
         token_id: TokenId,
     },
+    // UTXO -> Token
+    TokenBurnBound {
+        token_id: TokenId,
+        entry_point: String,
+    },
     TokenSpend {
         token_id: TokenId,
         amount: u64,
@@ -179,6 +256,13 @@ enum Interrupt {
         data_len: u32,
         skip: u32,
     },
+    // Coordination -> consumed UTXO
+    CollectTokens {
+        utxo_id: UtxoId,
+        data: u32,
+        data_len: u32,
+        skip: u32,
+    },
 }
 
 type WasmiError = wasmi::core::Trap;
@@ -196,28 +280,136 @@ impl std::fmt::Display for Interrupt {
 
 impl HostError for Interrupt {}
 
-fn starstream_eprint<T>(mut caller: Caller<T>, ptr: u32, len: u32) {
-    let (memory, _) = memory(&mut caller);
-    let slice = &memory[ptr as usize..(ptr + len) as usize];
+/// A named field of a struct a UTXO yields, registered with
+/// `starstream_yield_layout`.
+#[derive(Debug, Clone)]
+struct YieldField {
+    name: String,
+    offset: u32,
+    size: u32,
+}
+
+/// Size of an entry of the `starstream_yield_layout` descriptor: the address
+/// and length of the field's name, then its offset and size within the
+/// struct, each a little-endian `u32`.
+const YIELD_FIELD_SIZE: usize = 16;
+
+/// Whether a call into wasm stopped because the transaction ran out of fuel.
+fn is_out_of_fuel(error: &wasmi::Error) -> bool {
+    matches!(error, wasmi::Error::Trap(trap) if trap.trap_code() == Some(TrapCode::OutOfFuel))
+}
+
+/// The outputs of a call into wasm that returned, or the [`Interrupt`] it
+/// suspended on.
+///
+/// Host functions only suspend with an `Interrupt`. Any other host error is
+/// reported as a trap of `program` rather than taking down the host.
+fn call_result(
+    resumable: &ResumableCall,
+    outputs: Vec<Value>,
+    program: ProgramIdx,
+    entry_point: &str,
+) -> Result<Vec<Value>, Interrupt> {
+    let ResumableCall::Resumable(invocation) = resumable else {
+        return Ok(outputs);
+    };
+    let host_error = invocation.host_error();
+    Err(match host_error.downcast_ref::<Interrupt>() {
+        Some(interrupt) => interrupt.clone(),
+        None => Interrupt::Failed(TransactionError::Trap {
+            program: program.0,
+            entry_point: entry_point.to_owned(),
+            message: host_error.to_string(),
+        }),
+    })
+}
+
+// ----------------------------------------------------------------------------
+// 128-bit integers
+
+// Wasm has no 128-bit integers, so `u128` and `i128` cross the FFI as two
+// `i64`s, low half first. Results use the same pair as a multivalue return.
+
+/// Split a 128-bit integer into its `(low, high)` wasm arguments.
+pub fn u128_to_wasm(value: u128) -> [Value; 2] {
+    [
+        Value::I64(value as u64 as i64),
+        Value::I64((value >> 64) as u64 as i64),
+    ]
+}
+
+/// Join a `(low, high)` pair of wasm values back into a 128-bit integer.
+pub fn u128_from_wasm(values: &[Value]) -> Option<u128> {
+    match values {
+        [low, high] => Some((high.i64()? as u64 as u128) << 64 | low.i64()? as u64 as u128),
+        _ => None,
+    }
+}
+
+/// The `len` bytes at `ptr` of a contract's `memory`, or a trap if any of
+/// them are out of bounds.
+fn slice_checked(memory: &[u8], ptr: u32, len: u32) -> Result<&[u8], WasmiError> {
+    let end = ptr.checked_add(len).ok_or(TrapCode::MemoryOutOfBounds)?;
+    memory
+        .get(ptr as usize..end as usize)
+        .ok_or_else(|| TrapCode::MemoryOutOfBounds.into())
+}
+
+/// Like [`slice_checked`], but also a trap if the bytes aren't UTF-8.
+fn str_checked(memory: &[u8], ptr: u32, len: u32) -> Result<&str, WasmiError> {
+    std::str::from_utf8(slice_checked(memory, ptr, len)?)
+        .map_err(|_| wasmi::core::Trap::new(format!("invalid UTF-8 at {ptr:#x}")))
+}
+
+/// Like [`slice_checked`], for the host to write into.
+fn slice_checked_mut(memory: &mut [u8], ptr: u32, len: u32) -> Result<&mut [u8], WasmiError> {
+    let end = ptr.checked_add(len).ok_or(TrapCode::MemoryOutOfBounds)?;
+    memory
+        .get_mut(ptr as usize..end as usize)
+        .ok_or_else(|| TrapCode::MemoryOutOfBounds.into())
+}
+
+fn starstream_eprint<T>(mut caller: Caller<T>, ptr: u32, len: u32) -> Result<(), WasmiError> {
+    let (memory, _) = memory(&mut caller)?;
+    let slice = slice_checked(memory, ptr, len)?;
     info!(target: "program", "{}", String::from_utf8_lossy(slice));
+    Ok(())
 }
 
 /// Fulfiller of imports from `env`.
 #[allow(clippy::unused_unit)] // False positive. `clippy --fix` breaks the code.
-fn starstream_env(linker: &mut Linker<TransactionInner>, module: &str, this_code: &ContractCode) {
+fn starstream_env(
+    linker: &mut Linker<TransactionInner>,
+    module: &str,
+    this_code: &ContractCode,
+) -> Result<(), TransactionError> {
     let this_code_hash = this_code.hash();
 
     linker
-        .func_wrap(module, "abort", || -> () {
-            panic!("contract called abort()");
+        .func_wrap(module, "abort", || -> Result<(), WasmiError> {
+            host(Interrupt::Abort)
         })
         .unwrap();
+    linker
+        .func_wrap(
+            module,
+            "starstream_require",
+            |cond: i32, code: u32| -> Result<(), WasmiError> {
+                trace!("starstream_require({cond}, {code})");
+                if cond != 0 {
+                    Ok(())
+                } else {
+                    host(Interrupt::RequireFailed { code })
+                }
+            },
+        )
+        .unwrap();
     linker
         .func_wrap(
             module,
             "eprint",
-            |caller: Caller<TransactionInner>, ptr: u32, len: u32| -> () {
-                starstream_eprint(caller, ptr, len);
+            |caller: Caller<TransactionInner>, ptr: u32, len: u32| -> Result<(), WasmiError> {
+                starstream_eprint(caller, ptr, len)
             },
         )
         .unwrap();
@@ -231,16 +423,63 @@ fn starstream_env(linker: &mut Linker<TransactionInner>, module: &str, this_code
             },
         )
         .unwrap();
+    linker
+        .func_wrap(
+            module,
+            "starstream_caller_code",
+            move |return_addr: u32| -> Result<(), WasmiError> {
+                trace!("starstream_caller_code({return_addr:#x})");
+                host(Interrupt::CallerCode { return_addr })
+            },
+        )
+        .unwrap();
+    linker
+        .func_wrap(
+            module,
+            "starstream_alloc",
+            move |ptr: u32, len: u32| -> Result<(), WasmiError> {
+                trace!("starstream_alloc({ptr:#x}, {len})");
+                host(Interrupt::DeclareBuffer { ptr, len })
+            },
+        )
+        .unwrap();
     linker
         .func_wrap(
             module,
             "starstream_this_code",
-            move |mut caller: Caller<TransactionInner>, return_addr: u32| {
+            move |mut caller: Caller<TransactionInner>,
+                  return_addr: u32|
+                  -> Result<(), WasmiError> {
                 trace!("starstream_this_code({return_addr:#x})");
-                let (memory, _) = memory(&mut caller);
+                let (memory, _) = memory(&mut caller)?;
                 let hash = this_code_hash.raw();
-                memory[return_addr as usize..return_addr as usize + hash.len()]
-                    .copy_from_slice(&hash);
+                slice_checked_mut(memory, return_addr, hash.len() as u32)?.copy_from_slice(&hash);
+                Ok(())
+            },
+        )
+        .unwrap();
+    linker
+        .func_wrap(
+            module,
+            "starstream_tx_signers",
+            |mut caller: Caller<TransactionInner>,
+             return_addr: u32,
+             max: u32|
+             -> Result<u32, WasmiError> {
+                trace!("starstream_tx_signers({return_addr:#x}, {max})");
+                let (memory, inner) = memory(&mut caller)?;
+                let count = inner.signers.len().min(max as usize);
+                let len = u32::try_from(count * size_of::<PublicKey>())
+                    .map_err(|_| TrapCode::MemoryOutOfBounds)?;
+                let output = slice_checked_mut(memory, return_addr, len)?;
+                for (signer, chunk) in inner
+                    .signers
+                    .iter()
+                    .zip(output.chunks_exact_mut(size_of::<PublicKey>()))
+                {
+                    chunk.copy_from_slice(&signer.0);
+                }
+                Ok(inner.signers.len() as u32)
             },
         )
         .unwrap();
@@ -248,15 +487,20 @@ fn starstream_env(linker: &mut Linker<TransactionInner>, module: &str, this_code
         .func_wrap(
             module,
             "starstream_keccak256",
-            |mut caller: Caller<TransactionInner>, ptr: u32, len: u32, return_addr: u32| {
+            |mut caller: Caller<TransactionInner>,
+             ptr: u32,
+             len: u32,
+             return_addr: u32|
+             -> Result<(), WasmiError> {
                 let mut hasher = tiny_keccak::Keccak::v256();
 
-                let (memory, _) = memory(&mut caller);
-                let slice = &memory[ptr as usize..(ptr + len) as usize];
+                let (memory, _) = memory(&mut caller)?;
+                let slice = slice_checked(memory, ptr, len)?;
 
                 hasher.update(slice);
 
-                hasher.finalize(&mut memory[return_addr as usize..return_addr as usize + 32]);
+                hasher.finalize(slice_checked_mut(memory, return_addr, 32)?);
+                Ok(())
             },
         )
         .unwrap();
@@ -266,9 +510,9 @@ fn starstream_env(linker: &mut Linker<TransactionInner>, module: &str, this_code
             module,
             "starstream_register_effect_handler",
             move |mut caller: Caller<TransactionInner>, ptr: u32, len: u32, handler_addr: i32| {
-                let (memory, _) = memory(&mut caller);
+                let (memory, _) = memory(&mut caller)?;
 
-                let name_slice = &memory[ptr as usize..(ptr + len) as usize];
+                let name_slice = slice_checked(memory, ptr, len)?;
 
                 host(Interrupt::RegisterEffectHandler {
                     name: String::from_utf8_lossy(name_slice).into_owned(),
@@ -283,9 +527,9 @@ fn starstream_env(linker: &mut Linker<TransactionInner>, module: &str, this_code
             module,
             "starstream_unregister_effect_handler",
             move |mut caller: Caller<TransactionInner>, ptr: u32, len: u32| {
-                let (memory, _) = memory(&mut caller);
+                let (memory, _) = memory(&mut caller)?;
 
-                let slice = &memory[ptr as usize..(ptr + len) as usize];
+                let slice = slice_checked(memory, ptr, len)?;
                 host(Interrupt::UnRegisterEffectHandler {
                     name: String::from_utf8_lossy(slice).into_owned(),
                 })
@@ -300,27 +544,57 @@ fn starstream_env(linker: &mut Linker<TransactionInner>, module: &str, this_code
                   ptr: u32,
                   len: u32,
                   output_ptr_data: u32,
+                  output_len: u32,
                   not_null: u32| {
-                let (memory, _) = memory(&mut caller);
+                let (memory, _) = memory(&mut caller)?;
 
-                let slice = &memory[ptr as usize..(ptr + len) as usize];
+                let slice = slice_checked(memory, ptr, len)?;
                 host(Interrupt::GetRaisedEffectData {
                     name: String::from_utf8_lossy(slice).into_owned(),
                     output_ptr_data,
+                    output_len,
                     not_null,
                 })
             },
         )
         .unwrap();
 
+    linker
+        .func_wrap(
+            module,
+            "starstream_get_raised_effect_name",
+            |output_ptr: u32, output_cap: u32| -> Result<u32, WasmiError> {
+                // Which raise is being handled depends on the calling program,
+                // which only the scheduler knows.
+                Err(wasmi::core::Trap::from(Interrupt::GetRaisedEffectName {
+                    output_ptr,
+                    output_cap,
+                }))
+            },
+        )
+        .unwrap();
+
+    linker
+        .func_wrap(
+            module,
+            "starstream_is_effect_handled",
+            |mut caller: Caller<TransactionInner>, ptr: u32, len: u32| -> Result<i32, WasmiError> {
+                let (memory, inner) = memory(&mut caller)?;
+
+                let name = String::from_utf8_lossy(slice_checked(memory, ptr, len)?);
+                Ok(inner.effect_handler(&name).is_some() as i32)
+            },
+        )
+        .unwrap();
+
     linker
         .func_wrap(
             module,
             "starstream_resume_throwing_program",
             move |mut caller: Caller<TransactionInner>, ptr: u32, len: u32, input_ptr_data: u32| {
-                let (memory, _) = memory(&mut caller);
+                let (memory, _) = memory(&mut caller)?;
 
-                let slice = &memory[ptr as usize..(ptr + len) as usize];
+                let slice = slice_checked(memory, ptr, len)?;
                 host(Interrupt::ResumeThrowingProgram {
                     name: String::from_utf8_lossy(slice).into_owned(),
                     input_ptr_data,
@@ -334,10 +608,13 @@ fn starstream_env(linker: &mut Linker<TransactionInner>, module: &str, this_code
             module,
             "starstream_get_token_type",
             |caller: Caller<TransactionInner>, token_id: i64| -> Result<u64, WasmiError> {
-                let token_id =
-                    TokenId::from_wasm(&Value::I64(token_id), caller.as_context()).unwrap();
+                let token_id = TokenId::from_guest(&Value::I64(token_id), caller.as_context())?;
 
-                let (_utxo, token) = caller.data().tokens.get(&token_id).unwrap();
+                let (_utxo, token) = caller
+                    .data()
+                    .tokens
+                    .get(&token_id)
+                    .ok_or(Interrupt::Failed(TransactionError::InvalidTokenHandle))?;
 
                 Ok(token.token_type_id)
             },
@@ -349,10 +626,13 @@ fn starstream_env(linker: &mut Linker<TransactionInner>, module: &str, this_code
             module,
             "starstream_get_token_amount",
             |caller: Caller<TransactionInner>, token_id: i64| -> Result<u64, WasmiError> {
-                let token_id =
-                    TokenId::from_wasm(&Value::I64(token_id), caller.as_context()).unwrap();
+                let token_id = TokenId::from_guest(&Value::I64(token_id), caller.as_context())?;
 
-                let (_utxo, token) = caller.data().tokens.get(&token_id).unwrap();
+                let (_utxo, token) = caller
+                    .data()
+                    .tokens
+                    .get(&token_id)
+                    .ok_or(Interrupt::Failed(TransactionError::InvalidTokenHandle))?;
 
                 Ok(token.amount)
             },
@@ -364,8 +644,7 @@ fn starstream_env(linker: &mut Linker<TransactionInner>, module: &str, this_code
             module,
             "starstream_token_burn",
             |caller: Caller<TransactionInner>, token_id: i64| -> Result<(), WasmiError> {
-                let token_id =
-                    TokenId::from_wasm(&Value::I64(token_id), caller.as_context()).unwrap();
+                let token_id = TokenId::from_guest(&Value::I64(token_id), caller.as_context())?;
                 host(Interrupt::TokenBurn { token_id })
             },
         )
@@ -379,8 +658,7 @@ fn starstream_env(linker: &mut Linker<TransactionInner>, module: &str, this_code
              token_id: i64,
              amount: i64|
              -> Result<i64, WasmiError> {
-                let token_id =
-                    TokenId::from_wasm(&Value::I64(token_id), caller.as_context()).unwrap();
+                let token_id = TokenId::from_guest(&Value::I64(token_id), caller.as_context())?;
                 host(Interrupt::TokenSpend {
                     token_id,
                     amount: amount as u64,
@@ -390,7 +668,54 @@ fn starstream_env(linker: &mut Linker<TransactionInner>, module: &str, this_code
         )
         .unwrap();
 
-    for import in this_code.module(linker.engine()).imports() {
+    linker
+        .func_wrap(
+            module,
+            "starstream_list_new",
+            |mut caller: Caller<TransactionInner>| -> i64 {
+                let inner = caller.data_mut();
+                let list = ScrambledRef::random(&mut inner.ids);
+                inner.lists.insert(list, Vec::new());
+                list.to_i64()
+            },
+        )
+        .unwrap();
+
+    linker
+        .func_wrap(
+            module,
+            "starstream_list_push",
+            |mut caller: Caller<TransactionInner>,
+             list: i64,
+             value: i64|
+             -> Result<(), WasmiError> {
+                caller
+                    .data_mut()
+                    .lists
+                    .get_mut(&ScrambledRef::from_i64(list))
+                    .ok_or(Interrupt::Failed(TransactionError::InvalidListHandle))?
+                    .push(value);
+                Ok(())
+            },
+        )
+        .unwrap();
+
+    linker
+        .func_wrap(
+            module,
+            "starstream_list_len",
+            |caller: Caller<TransactionInner>, list: i64| -> Result<u32, WasmiError> {
+                let list = caller
+                    .data()
+                    .lists
+                    .get(&ScrambledRef::from_i64(list))
+                    .ok_or(Interrupt::Failed(TransactionError::InvalidListHandle))?;
+                Ok(list.len() as u32)
+            },
+        )
+        .unwrap();
+
+    for import in this_code.module(linker.engine())?.imports() {
         if import.module() == "env" {
             // already handled by code above
         } else if let Some(rest) = import.module().strip_prefix("starstream_env:") {
@@ -406,43 +731,44 @@ fn starstream_env(linker: &mut Linker<TransactionInner>, module: &str, this_code
                             move |_caller, inputs, _outputs| {
                                 trace!("{rest}::{name}{inputs:?}");
 
-                                let program_id = match inputs[0] {
-                                    Value::I32(id) => id as u32,
-                                    _ => todo!(),
-                                };
-
-                                let id = match inputs[1] {
-                                    Value::I32(id) => id as u32,
-                                    _ => todo!(),
-                                };
-
-                                let frame = match inputs[2] {
-                                    Value::I32(id) => id as u32,
-                                    _ => todo!(),
+                                let [
+                                    Value::I32(program_id),
+                                    Value::I32(id),
+                                    Value::I32(frame),
+                                    ..,
+                                ] = *inputs
+                                else {
+                                    return Err(TrapCode::BadSignature.into());
                                 };
 
                                 host(Interrupt::CallEffectHandler {
                                     name: name.clone(),
-                                    handler_id: id,
-                                    frame,
-                                    program_id,
+                                    handler_id: id as u32,
+                                    frame: frame as u32,
+                                    program_id: program_id as u32,
                                     inputs: inputs.iter().skip(2).cloned().collect(),
                                 })
                             },
                         )
                         .unwrap();
                 } else {
-                    panic!("bad import {import:?}");
+                    return Err(bad_import(this_code, &import));
                 }
             } else {
-                panic!("bad import {import:?}");
+                return Err(bad_import(this_code, &import));
             }
         }
     }
+
+    Ok(())
 }
 
 /// Fulfiller of imports from `starstream_utxo_env`.
-fn starstream_utxo_env<T>(linker: &mut Linker<T>, module: &str, this_code: &ContractCode) {
+fn starstream_utxo_env<T>(
+    linker: &mut Linker<T>,
+    module: &str,
+    this_code: &ContractCode,
+) -> Result<(), TransactionError> {
     linker
         .func_wrap(
             module,
@@ -451,18 +777,15 @@ fn starstream_utxo_env<T>(linker: &mut Linker<T>, module: &str, this_code: &Cont
              name: u32,
              name_len: u32,
              data: u32,
-             _data_len: u32,
+             data_len: u32,
              resume_arg: u32,
              resume_arg_len: u32|
              -> Result<(), WasmiError> {
                 trace!("starstream_yield()");
                 host(Interrupt::Yield {
-                    name: std::str::from_utf8(
-                        &memory(&mut caller).0[name as usize..(name + name_len) as usize],
-                    )
-                    .unwrap()
-                    .to_owned(),
+                    name: str_checked(memory(&mut caller)?.0, name, name_len)?.to_owned(),
                     data,
+                    data_len,
                     resume_arg,
                     resume_arg_len,
                 })
@@ -484,11 +807,7 @@ fn starstream_utxo_env<T>(linker: &mut Linker<T>, module: &str, this_code: &Cont
              -> Result<(), WasmiError> {
                 trace!("starstream_raise()");
                 host(Interrupt::Raise {
-                    name: std::str::from_utf8(
-                        &memory(&mut caller).0[name as usize..(name + name_len) as usize],
-                    )
-                    .unwrap()
-                    .to_owned(),
+                    name: str_checked(memory(&mut caller)?.0, name, name_len)?.to_owned(),
                     data,
                     data_len,
                     resume_arg,
@@ -498,6 +817,35 @@ fn starstream_utxo_env<T>(linker: &mut Linker<T>, module: &str, this_code: &Cont
         )
         .unwrap();
 
+    linker
+        .func_wrap(
+            module,
+            "starstream_yield_layout",
+            |mut caller: Caller<T>, fields: u32, count: u32| -> Result<(), WasmiError> {
+                trace!("starstream_yield_layout({fields:#x}, {count})");
+                let memory = &*memory(&mut caller)?.0;
+                let descriptor_len = count
+                    .checked_mul(YIELD_FIELD_SIZE as u32)
+                    .ok_or(TrapCode::MemoryOutOfBounds)?;
+                let descriptor = slice_checked(memory, fields, descriptor_len)?;
+                let fields = descriptor
+                    .chunks_exact(YIELD_FIELD_SIZE)
+                    .map(|entry| {
+                        let word = |i: usize| {
+                            u32::from_le_bytes(entry[i * 4..i * 4 + 4].try_into().unwrap())
+                        };
+                        Ok(YieldField {
+                            name: str_checked(memory, word(0), word(1))?.to_owned(),
+                            offset: word(2),
+                            size: word(3),
+                        })
+                    })
+                    .collect::<Result<_, WasmiError>>()?;
+                host(Interrupt::YieldLayout { fields })
+            },
+        )
+        .unwrap();
+
     linker
         .func_wrap(
             module,
@@ -513,7 +861,7 @@ fn starstream_utxo_env<T>(linker: &mut Linker<T>, module: &str, this_code: &Cont
         )
         .unwrap();
 
-    for import in this_code.module(linker.engine()).imports() {
+    for import in this_code.module(linker.engine())?.imports() {
         if import.module() == "env" {
             // already handled by code above
         } else if let Some(rest) = import.module().strip_prefix("starstream_utxo_env:") {
@@ -529,74 +877,106 @@ fn starstream_utxo_env<T>(linker: &mut Linker<T>, module: &str, this_code: &Cont
                             move |mut caller, inputs, outputs| {
                                 trace!("{rest}::{name}{inputs:?} -> {outputs:?}");
 
-                                let name = match inputs[0] {
-                                    Value::I32(id) => id as u32,
-                                    _ => todo!(),
-                                };
-
-                                let name_len = match inputs[1] {
-                                    Value::I32(id) => id as u32,
-                                    _ => todo!(),
-                                };
-
-                                let data = match inputs[2] {
-                                    Value::I32(id) => id as u32,
-                                    _ => todo!(),
-                                };
-
-                                let resume_arg = match inputs[3] {
-                                    Value::I32(id) => id as u32,
-                                    _ => todo!(),
-                                };
-
-                                let resume_arg_len = match inputs[4] {
-                                    Value::I32(id) => id as u32,
-                                    _ => todo!(),
+                                let [
+                                    Value::I32(name),
+                                    Value::I32(name_len),
+                                    Value::I32(data),
+                                    Value::I32(data_len),
+                                    Value::I32(resume_arg),
+                                    Value::I32(resume_arg_len),
+                                ] = *inputs
+                                else {
+                                    return Err(TrapCode::BadSignature.into());
                                 };
 
                                 trace!("starstream_yield()");
                                 host(Interrupt::Yield {
-                                    name: std::str::from_utf8(
-                                        &memory(&mut caller).0
-                                            [name as usize..(name + name_len) as usize],
-                                    )
-                                    .unwrap()
+                                    name: str_checked(
+                                        memory(&mut caller)?.0,
+                                        name as u32,
+                                        name_len as u32,
+                                    )?
                                     .to_owned(),
-                                    data,
-                                    resume_arg,
-                                    resume_arg_len,
+                                    data: data as u32,
+                                    data_len: data_len as u32,
+                                    resume_arg: resume_arg as u32,
+                                    resume_arg_len: resume_arg_len as u32,
                                 })
                             },
                         )
                         .unwrap();
                 } else {
-                    panic!("bad import {import:?}");
+                    return Err(bad_import(this_code, &import));
                 }
             } else {
-                panic!("bad import {import:?}");
+                return Err(bad_import(this_code, &import));
             }
         }
     }
+
+    Ok(())
 }
 
 // ----------------------------------------------------------------------------
 
+/// The random key a UTXO or token handle is passed to contracts as.
+///
+/// Keys are drawn from all of `u64`, but cross the FFI as Wasm `i64`s, so
+/// about half of them look negative there. The conversions here reinterpret
+/// the bits and never change them.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScrambledRef(u64);
+
+impl ScrambledRef {
+    fn random(ids: &mut IdSource) -> ScrambledRef {
+        ScrambledRef(ids.0.next_u64())
+    }
+
+    /// The key with the bit pattern of `value`.
+    pub fn from_i64(value: i64) -> ScrambledRef {
+        ScrambledRef(u64::from_le_bytes(value.to_le_bytes()))
+    }
+
+    /// The key as a Wasm `i64` with the same bit pattern.
+    pub fn to_i64(self) -> i64 {
+        i64::from_le_bytes(self.0.to_le_bytes())
+    }
+
+    /// The key in `value`, if it is an `i64`.
+    pub fn from_wasm(value: &Value) -> Option<ScrambledRef> {
+        match value {
+            Value::I64(value) => Some(ScrambledRef::from_i64(*value)),
+            _ => None,
+        }
+    }
+
+    pub fn to_wasm(self) -> Value {
+        Value::I64(self.to_i64())
+    }
+}
+
+impl std::fmt::Debug for ScrambledRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ScrambledRef({:#018x})", self.0)
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 struct TokenId {
     bytes: [u8; 16],
 }
 
 impl TokenId {
-    fn random() -> TokenId {
+    fn random(ids: &mut IdSource) -> TokenId {
         let mut bytes = [0; 16];
-        rand::thread_rng().fill_bytes(&mut bytes);
+        ids.0.fill_bytes(&mut bytes);
         TokenId { bytes }
     }
 
     fn to_wasm_i64(self, mut store: StoreContextMut<TransactionInner>) -> Value {
-        let scrambled = rand::thread_rng().next_u64();
+        let scrambled = ScrambledRef::random(&mut store.data_mut().ids);
         store.data_mut().temporary_token_ids.insert(scrambled, self);
-        Value::I64(scrambled as i64)
+        scrambled.to_wasm()
     }
 
     fn to_wasm_externref(self, store: StoreContextMut<TransactionInner>) -> Value {
@@ -608,12 +988,22 @@ impl TokenId {
             Value::I64(scrambled) => store
                 .data()
                 .temporary_token_ids
-                .get(&(*scrambled as u64))
+                .get(&ScrambledRef::from_i64(*scrambled))
                 .copied(),
             Value::ExternRef(handle) => handle.data(store)?.downcast_ref::<TokenId>().copied(),
             _ => None,
         }
     }
+
+    /// Like [`TokenId::from_wasm`], for a handle a contract passed to a host
+    /// function: one that doesn't refer to a token fails the transaction.
+    fn from_guest(
+        value: &Value,
+        store: StoreContext<TransactionInner>,
+    ) -> Result<TokenId, WasmiError> {
+        TokenId::from_wasm(value, store)
+            .ok_or_else(|| Interrupt::Failed(TransactionError::InvalidTokenHandle).into())
+    }
 }
 
 impl std::fmt::Debug for TokenId {
@@ -632,19 +1022,25 @@ struct UtxoInstance {
 }
 */
 
-fn utxo_linker(engine: &Engine, utxo_code: &ContractCode) -> Linker<TransactionInner> {
+fn utxo_linker(
+    engine: &Engine,
+    utxo_code: &ContractCode,
+) -> Result<Linker<TransactionInner>, TransactionError> {
     let mut linker = Linker::<TransactionInner>::new(engine);
 
-    starstream_env(&mut linker, "env", utxo_code);
+    starstream_env(&mut linker, "env", utxo_code)?;
 
-    starstream_utxo_env(&mut linker, "starstream_utxo_env", utxo_code);
+    starstream_utxo_env(&mut linker, "starstream_utxo_env", utxo_code)?;
 
-    for import in utxo_code.module(engine).imports() {
+    for import in utxo_code.module(engine)?.imports() {
         if let ExternType::Func(func_ty) = import.ty() {
             if let Some(rest) = import.module().strip_prefix("starstream_token:") {
-                if import.name().starts_with("starstream_bind") {
+                if import.name().starts_with("starstream_bind")
+                    || import.name().starts_with("starstream_try_bind")
+                {
                     let name = import.name().to_owned();
                     let rest = rest.to_owned();
+                    let fallible = name.starts_with("starstream_try_bind");
                     linker
                         .func_new(
                             import.module(),
@@ -653,13 +1049,16 @@ fn utxo_linker(engine: &Engine, utxo_code: &ContractCode) -> Linker<TransactionI
                             move |caller, inputs, _outputs| {
                                 trace!("{rest}::{name}{inputs:?}");
 
-                                let token_id =
-                                    TokenId::from_wasm(&inputs[0], caller.as_context()).unwrap();
+                                let Some(token_id) = inputs.first() else {
+                                    return Err(TrapCode::BadSignature.into());
+                                };
+                                let token_id = TokenId::from_guest(token_id, caller.as_context())?;
 
                                 host(Interrupt::TokenBind {
                                     entry_point: name.clone(),
                                     inputs: inputs.to_vec(),
                                     token_id,
+                                    fallible,
                                 })
                             },
                         )
@@ -674,8 +1073,10 @@ fn utxo_linker(engine: &Engine, utxo_code: &ContractCode) -> Linker<TransactionI
                             func_ty.clone(),
                             move |caller, inputs, _outputs| {
                                 trace!("{rest}::{name}{inputs:?}");
-                                let token_id =
-                                    TokenId::from_wasm(&inputs[0], caller.as_context()).unwrap();
+                                let Some(token_id) = inputs.first() else {
+                                    return Err(TrapCode::BadSignature.into());
+                                };
+                                let token_id = TokenId::from_guest(token_id, caller.as_context())?;
                                 host(Interrupt::TokenUnbind {
                                     token_id,
                                     //hash,
@@ -684,40 +1085,95 @@ fn utxo_linker(engine: &Engine, utxo_code: &ContractCode) -> Linker<TransactionI
                             },
                         )
                         .unwrap();
+                } else if import.name().starts_with("starstream_burn") {
+                    let name = import.name().to_owned();
+                    let rest = rest.to_owned();
+                    linker
+                        .func_new(
+                            import.module(),
+                            import.name(),
+                            func_ty.clone(),
+                            move |caller, inputs, _outputs| {
+                                trace!("{rest}::{name}{inputs:?}");
+                                let Some(token_id) = inputs.first() else {
+                                    return Err(TrapCode::BadSignature.into());
+                                };
+                                let token_id = TokenId::from_guest(token_id, caller.as_context())?;
+                                host(Interrupt::TokenBurnBound {
+                                    token_id,
+                                    entry_point: name.clone(),
+                                })
+                            },
+                        )
+                        .unwrap();
                 }
+            } else if import.module().starts_with("starstream_utxo:")
+                && import.name().starts_with(QUERY_PREFIX)
+            {
+                // UTXOs may query the UTXOs they hold handles to directly, but
+                // anything that changes them still goes through coordination.
+                let name = import.name().to_owned();
+                linker
+                    .func_new(
+                        import.module(),
+                        import.name(),
+                        func_ty.clone(),
+                        move |caller, inputs, _outputs| {
+                            trace!("direct {name}{inputs:?}");
+                            let Some(utxo_id) = inputs.first() else {
+                                return Err(TrapCode::BadSignature.into());
+                            };
+                            let utxo_id = UtxoId::from_guest_i64(utxo_id, caller.as_context())?;
+                            host(Interrupt::UtxoQueryDirect {
+                                utxo_id,
+                                method: name.clone(),
+                                inputs: inputs[1..].to_vec(),
+                            })
+                        },
+                    )
+                    .unwrap();
             } else {
                 fake_import(&mut linker, &import, "not available in UTXO context");
             }
         }
     }
 
-    linker
+    Ok(linker)
 }
 
 // ----------------------------------------------------------------------------
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Utxo {
     program: ProgramIdx,
     tokens: HashMap<TokenId, Token>,
+    /// What the entry point was started with, every call into it since, and
+    /// the tokens it was asked to bind, to replay when loading a
+    /// [`UtxoSnapshot`].
+    inputs: Vec<Value>,
+    calls: Vec<UtxoCall>,
+    token_binds: Vec<BoundToken>,
 }
 
 // ----------------------------------------------------------------------------
 
-fn token_linker(engine: &Engine, token_code: &Arc<ContractCode>) -> Linker<TransactionInner> {
+fn token_linker(
+    engine: &Engine,
+    token_code: &Arc<ContractCode>,
+) -> Result<Linker<TransactionInner>, TransactionError> {
     let mut linker = Linker::new(engine);
 
-    starstream_env(&mut linker, "env", token_code);
+    starstream_env(&mut linker, "env", token_code)?;
 
-    starstream_utxo_env(&mut linker, "starstream_utxo_env", token_code);
+    starstream_utxo_env(&mut linker, "starstream_utxo_env", token_code)?;
 
-    for import in token_code.module(engine).imports() {
+    for import in token_code.module(engine)?.imports() {
         if import.module() != "starstream_utxo_env" {
             fake_import(&mut linker, &import, "Not available in token context");
         }
     }
 
-    linker
+    Ok(linker)
 }
 
 // ----------------------------------------------------------------------------
@@ -729,88 +1185,36 @@ struct Token {
     amount: u64,
 }
 
-/*
-impl Token {
-    fn mint(token_code: Arc<ContractCode>, mint_fn: &str, inputs: &[Value]) -> Token {
-        let burn_fn = mint_fn.replace("starstream_mint_", "starstream_burn_");
-        assert_ne!(mint_fn, burn_fn);
-
-        // Prepend struct return slot to inputs
-        let return_addr: usize = 16;
-        let inputs = std::iter::once(Value::I32(return_addr as i32))
-            .chain(inputs.iter().cloned())
-            .collect::<Vec<_>>();
-
-        let engine = Engine::default();
-        let mut store = Store::new(&engine, TokenInstance {});
-        let linker = token_linker(&engine, &token_code);
-        let instance = linker
-            .instantiate(&mut store, &token_code.module(&engine))
-            .unwrap()
-            .ensure_no_start(&mut store)
-            .unwrap();
-        let mint = instance.get_func(&mut store, &mint_fn).unwrap();
-        mint.call(&mut store, &inputs[..], &mut []).unwrap();
-
-        // Read id and amount
-        let memory = instance
-            .get_export(&store, "memory")
-            .unwrap()
-            .into_memory()
-            .unwrap()
-            .data(&store);
-        let mut cursor = &memory[return_addr..];
-        let id = cursor.read_u64::<LittleEndian>().unwrap();
-        let amount = cursor.read_u64::<LittleEndian>().unwrap();
-        Token {
-            code: token_code,
-
-            burn_fn,
-            id,
-            amount,
-        }
-    }
+// ----------------------------------------------------------------------------
 
-    fn burn(self, burn_fn: &str) {
-        assert_eq!(self.burn_fn, burn_fn);
+/// A public key that signed the transaction.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PublicKey(pub [u8; 32]);
 
-        let engine = Engine::default();
-        let mut store = Store::new(&engine, TokenInstance {});
-        let linker = token_linker(&engine, &self.code);
-        let instance = linker
-            .instantiate(&mut store, &self.code.module(&engine))
-            .unwrap()
-            .ensure_no_start(&mut store)
-            .unwrap();
-        let burn = instance.get_func(&mut store, burn_fn).unwrap();
-        burn.call(
-            &mut store,
-            &[Value::I64(self.id as i64), Value::I64(self.amount as i64)],
-            &mut [],
-        )
-        .unwrap();
+impl std::fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PublicKey({})", DisplayHex(&self.0[..]))
     }
 }
-*/
 
 // ----------------------------------------------------------------------------
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq)]
-struct UtxoId {
+#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UtxoId {
     bytes: [u8; 16],
 }
 
 impl UtxoId {
-    fn random() -> UtxoId {
+    fn random(ids: &mut IdSource) -> UtxoId {
         let mut bytes = [0; 16];
-        rand::thread_rng().fill_bytes(&mut bytes);
+        ids.0.fill_bytes(&mut bytes);
         UtxoId { bytes }
     }
 
     fn to_wasm_i64(self, mut store: StoreContextMut<TransactionInner>) -> Value {
-        let scrambled = rand::thread_rng().next_u64();
+        let scrambled = ScrambledRef::random(&mut store.data_mut().ids);
         store.data_mut().temporary_utxo_ids.insert(scrambled, self);
-        Value::I64(scrambled as i64)
+        scrambled.to_wasm()
     }
 
     fn to_wasm_externref(self, store: StoreContextMut<TransactionInner>) -> Value {
@@ -818,14 +1222,18 @@ impl UtxoId {
     }
 
     fn from_wasm_i64(value: &Value, store: StoreContext<TransactionInner>) -> Option<UtxoId> {
-        match value {
-            Value::I64(scrambled) => store
-                .data()
-                .temporary_utxo_ids
-                .get(&(*scrambled as u64))
-                .copied(),
-            _ => None,
-        }
+        let scrambled = ScrambledRef::from_wasm(value)?;
+        store.data().temporary_utxo_ids.get(&scrambled).copied()
+    }
+
+    /// Like [`UtxoId::from_wasm_i64`], for a handle a contract passed to a
+    /// host function: one that doesn't refer to a UTXO fails the transaction.
+    fn from_guest_i64(
+        value: &Value,
+        store: StoreContext<TransactionInner>,
+    ) -> Result<UtxoId, WasmiError> {
+        UtxoId::from_wasm_i64(value, store)
+            .ok_or_else(|| Interrupt::Failed(TransactionError::InvalidUtxoHandle).into())
     }
 
     fn from_wasm_externref(value: &Value, store: StoreContext<TransactionInner>) -> Option<UtxoId> {
@@ -842,51 +1250,205 @@ impl std::fmt::Debug for UtxoId {
     }
 }
 
-fn coordination_script_linker(
-    engine: &Engine,
-    code_cache: &Arc<CodeCache>,
-    coordination_code: Arc<ContractCode>,
-) -> Linker<TransactionInner> {
-    let mut linker = Linker::<TransactionInner>::new(engine);
+/// The raw bytes of a [`UtxoId`], which stay the same for as long as the
+/// UTXO exists, so they can be used to follow it across transactions.
+#[derive(
+    Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+pub struct UtxoIdBytes(pub [u8; 16]);
 
-    starstream_env(&mut linker, "env", &coordination_code);
+impl UtxoIdBytes {
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
 
-    linker
-        .func_wrap(
-            "starstream_utxo",
-            "starstream_status",
-            |caller: Caller<TransactionInner>, utxo_id: u64| -> Result<u32, WasmiError> {
-                trace!("starstream_status()");
-                let utxo_id =
-                    UtxoId::from_wasm_i64(&Value::I64(utxo_id as i64), caller.as_context())
-                        .expect("invalid utxo");
-                let to_program = caller.data().utxos[&utxo_id].program;
-                let n = if caller.data().programs[to_program.0].interrupt().is_some() {
-                    1
-                } else {
-                    0
-                };
-                Ok(n)
-            },
-        )
-        .unwrap();
+    /// The bytes as lowercase hex.
+    pub fn to_hex(&self) -> String {
+        DisplayHex(&self.0).to_string()
+    }
+}
 
-    let current_code_hash = coordination_code.hash();
-    for import in coordination_code.module(engine).imports() {
-        if import.module() == "env" {
-            // already handled by code above
-        } else if let Some(rest) = import.module().strip_prefix("starstream_utxo:") {
-            let rest = rest.to_owned();
-            if let ExternType::Func(func_ty) = import.ty() {
-                let name = import.name().to_owned();
-                if import.name().starts_with("starstream_new_") {
-                    let code_cache = code_cache.clone();
-                    linker
-                        .func_new(
-                            import.module(),
-                            import.name(),
-                            func_ty.clone(),
-                            move |_caller, inputs: &[Value], _outputs| -> Result<(), WasmiError> {
+impl From<UtxoId> for UtxoIdBytes {
+    fn from(id: UtxoId) -> Self {
+        UtxoIdBytes(id.bytes)
+    }
+}
+
+impl From<UtxoIdBytes> for UtxoId {
+    fn from(id: UtxoIdBytes) -> Self {
+        UtxoId { bytes: id.0 }
+    }
+}
+
+/// Commitment to the bytes a UTXO yielded.
+pub fn output_commitment(data: &[u8]) -> [u8; 32] {
+    let mut hasher = tiny_keccak::Keccak::v256();
+    let mut output = [0; 32];
+    hasher.update(data);
+    hasher.finalize(&mut output);
+    output
+}
+
+/// The UTXOs of a transaction at some point in time. See
+/// [`Transaction::utxo_state`].
+#[derive(Clone, Debug, Default)]
+pub struct UtxoState {
+    utxos: BTreeMap<UtxoId, UtxoOutputState>,
+}
+
+#[derive(Clone, Debug)]
+struct UtxoOutputState {
+    /// Commitment to the last yielded output, if it yielded at all.
+    output: Option<[u8; 32]>,
+    consumed: bool,
+}
+
+/// How a single UTXO changed between two [`UtxoState`]s, in the shape the IVC
+/// prover expects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtxoChange {
+    /// `None` if the UTXO was created in between.
+    pub output_before: Option<[u8; 32]>,
+    /// `None` if the UTXO was consumed in between.
+    pub output_after: Option<[u8; 32]>,
+    pub consumed: bool,
+}
+
+/// A suspended UTXO in a form that can be stored between transactions and
+/// brought back with [`Transaction::load_utxo`].
+///
+/// wasmi can't serialize a suspended call, so loading runs the UTXO's entry
+/// point again and replays every resume and mutation it got from coordination
+/// scripts. The tokens it was asked to bind are minted again first, under the
+/// same ids and handles, so that its binds find them. Its linear memory,
+/// exported globals and the resume argument it is waiting on are kept to check
+/// that the replay ended up where the UTXO was.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct UtxoSnapshot {
+    id: UtxoIdBytes,
+    code: CodeHash,
+    entry_point: String,
+    #[serde(with = "serde_value_vec")]
+    inputs: Vec<Value>,
+    calls: Vec<UtxoCall>,
+    token_binds: Vec<BoundToken>,
+    memory: Vec<u8>,
+    globals: Vec<(String, i64)>,
+    resume_arg: u32,
+    resume_arg_len: u32,
+}
+
+impl UtxoSnapshot {
+    /// The id of the UTXO, which it keeps once loaded.
+    pub fn id(&self) -> UtxoIdBytes {
+        self.id
+    }
+}
+
+/// A call a coordination script made into a UTXO.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+enum UtxoCall {
+    /// Resumed from its yield with `values`, and its resume argument written
+    /// to memory.
+    Resume {
+        #[serde(with = "serde_value_vec")]
+        values: Vec<Value>,
+        write_to_memory: Vec<MemorySegment>,
+    },
+    /// The mutation `method` called on its yielded object with `values`.
+    Mutate {
+        method: String,
+        #[serde(with = "serde_value_vec")]
+        values: Vec<Value>,
+    },
+}
+
+/// A token a UTXO was asked to bind through `handle`, and how it was minted.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+struct BoundToken {
+    handle: i64,
+    id: [u8; 16],
+    code: CodeHash,
+    entry_point: String,
+    #[serde(with = "serde_value_vec")]
+    inputs: Vec<Value>,
+    token_type_id: u64,
+    amount: u64,
+}
+
+/// One step in the life of a UTXO, as returned by [`Transaction::utxo_history`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UtxoEvent {
+    /// The UTXO yielded these bytes.
+    Yielded(Vec<u8>),
+    /// The UTXO was resumed with these bytes as its resume argument, which
+    /// is empty when resumed with `()`.
+    Resumed(Vec<u8>),
+}
+
+fn coordination_script_linker(
+    engine: &Engine,
+    code_cache: &Arc<CodeCache>,
+    coordination_code: Arc<ContractCode>,
+) -> Result<Linker<TransactionInner>, TransactionError> {
+    let mut linker = Linker::<TransactionInner>::new(engine);
+
+    starstream_env(&mut linker, "env", &coordination_code)?;
+
+    linker
+        .func_wrap(
+            "starstream_utxo",
+            "starstream_status",
+            |caller: Caller<TransactionInner>, utxo_id: i64| -> Result<u32, WasmiError> {
+                trace!("starstream_status()");
+                let utxo_id = UtxoId::from_guest_i64(&Value::I64(utxo_id), caller.as_context())?;
+                caller
+                    .data()
+                    .utxo_status(utxo_id)
+                    .ok_or_else(|| Interrupt::Failed(TransactionError::InvalidUtxoHandle).into())
+            },
+        )
+        .unwrap();
+
+    linker
+        .func_wrap(
+            "starstream_utxo",
+            "starstream_collect_tokens",
+            |caller: Caller<TransactionInner>,
+             utxo_id: i64,
+             data: u32,
+             data_len: u32,
+             skip: u32|
+             -> Result<u32, WasmiError> {
+                trace!("starstream_collect_tokens()");
+                let utxo_id = UtxoId::from_guest_i64(&Value::I64(utxo_id), caller.as_context())?;
+                host(Interrupt::CollectTokens {
+                    utxo_id,
+                    data,
+                    data_len,
+                    skip,
+                })
+                .map(|_| unreachable!())
+            },
+        )
+        .unwrap();
+
+    let current_code_hash = coordination_code.hash();
+    for import in coordination_code.module(engine)?.imports() {
+        if import.module() == "env" {
+            // already handled by code above
+        } else if let Some(rest) = import.module().strip_prefix("starstream_utxo:") {
+            let rest = rest.to_owned();
+            if let ExternType::Func(func_ty) = import.ty() {
+                let name = import.name().to_owned();
+                if import.name().starts_with("starstream_new_") {
+                    let code_cache = code_cache.clone();
+                    linker
+                        .func_new(
+                            import.module(),
+                            import.name(),
+                            func_ty.clone(),
+                            move |_caller, inputs: &[Value], _outputs| -> Result<(), WasmiError> {
                                 trace!("{rest}::{name}{inputs:?}");
                                 let code = if rest == "this" {
                                     current_code_hash
@@ -906,12 +1468,13 @@ fn coordination_script_linker(
 
                     // TODO: hacky
                     // get as a parameter instead
-                    let token_type_id: u64 = import
+                    let Some(token_type_id) = import
                         .name()
                         .strip_prefix("starstream_mint_")
-                        .unwrap()
-                        .parse()
-                        .unwrap();
+                        .and_then(|id| id.parse::<u64>().ok())
+                    else {
+                        return Err(bad_import(&coordination_code, &import));
+                    };
 
                     linker
                         .func_new(
@@ -921,6 +1484,10 @@ fn coordination_script_linker(
                             move |_caller, inputs, _outputs| {
                                 trace!("{rest}::{name}{inputs:?}");
 
+                                let Some(&Value::I64(amount)) = inputs.first() else {
+                                    return Err(TrapCode::BadSignature.into());
+                                };
+
                                 let code = if rest == "this" {
                                     current_code_hash
                                 } else {
@@ -931,6 +1498,7 @@ fn coordination_script_linker(
                                     code,
                                     entry_point: name.clone(),
                                     inputs: inputs.to_vec(),
+                                    amount: amount as u64,
                                     token_type_id,
                                 })
                             },
@@ -944,8 +1512,10 @@ fn coordination_script_linker(
                             func_ty.clone(),
                             move |caller, inputs, _outputs| {
                                 trace!("{name}{inputs:?}");
-                                let utxo_id =
-                                    UtxoId::from_wasm_i64(&inputs[0], caller.as_context()).unwrap();
+                                let Some(utxo_id) = inputs.first() else {
+                                    return Err(TrapCode::BadSignature.into());
+                                };
+                                let utxo_id = UtxoId::from_guest_i64(utxo_id, caller.as_context())?;
                                 host(Interrupt::UtxoResume {
                                     utxo_id,
                                     inputs: inputs.to_vec(),
@@ -953,7 +1523,7 @@ fn coordination_script_linker(
                             },
                         )
                         .unwrap();
-                } else if import.name().starts_with("starstream_query_") {
+                } else if import.name().starts_with(QUERY_PREFIX) {
                     linker
                         .func_new(
                             import.module(),
@@ -961,8 +1531,10 @@ fn coordination_script_linker(
                             func_ty.clone(),
                             move |caller, inputs, _outputs| {
                                 trace!("{rest}::{name}{inputs:?}");
-                                let utxo_id =
-                                    UtxoId::from_wasm_i64(&inputs[0], caller.as_context()).unwrap();
+                                let Some(utxo_id) = inputs.first() else {
+                                    return Err(TrapCode::BadSignature.into());
+                                };
+                                let utxo_id = UtxoId::from_guest_i64(utxo_id, caller.as_context())?;
                                 host(Interrupt::UtxoQuery {
                                     utxo_id,
                                     method: name.clone(),
@@ -979,8 +1551,10 @@ fn coordination_script_linker(
                             func_ty.clone(),
                             move |caller, inputs, _outputs| {
                                 trace!("{rest}::{name}{inputs:?}");
-                                let utxo_id =
-                                    UtxoId::from_wasm_i64(&inputs[0], caller.as_context()).unwrap();
+                                let Some(utxo_id) = inputs.first() else {
+                                    return Err(TrapCode::BadSignature.into());
+                                };
+                                let utxo_id = UtxoId::from_guest_i64(utxo_id, caller.as_context())?;
                                 host(Interrupt::UtxoMutate {
                                     utxo_id,
                                     method: name.clone(),
@@ -997,8 +1571,10 @@ fn coordination_script_linker(
                             func_ty.clone(),
                             move |caller, inputs, _outputs| {
                                 trace!("{rest}::{name}{inputs:?}");
-                                let utxo_id =
-                                    UtxoId::from_wasm_i64(&inputs[0], caller.as_context()).unwrap();
+                                let Some(utxo_id) = inputs.first() else {
+                                    return Err(TrapCode::BadSignature.into());
+                                };
+                                let utxo_id = UtxoId::from_guest_i64(utxo_id, caller.as_context())?;
                                 host(Interrupt::UtxoConsume {
                                     utxo_id,
                                     method: name.clone(),
@@ -1007,15 +1583,56 @@ fn coordination_script_linker(
                             },
                         )
                         .unwrap();
-                } else if import.name().starts_with("starstream_event_") {
-                    fake_import(&mut linker, &import, "TODO starstream_event_");
+                } else if import.name().starts_with("starstream_status_") {
+                    linker
+                        .func_new(
+                            import.module(),
+                            import.name(),
+                            func_ty.clone(),
+                            move |caller, inputs, outputs| {
+                                trace!("{rest}::{name}{inputs:?}");
+                                let ([utxo_id], [output]) = (inputs, outputs) else {
+                                    return Err(TrapCode::BadSignature.into());
+                                };
+                                let utxo_id = UtxoId::from_guest_i64(utxo_id, caller.as_context())?;
+                                let status = caller.data().utxo_status(utxo_id).ok_or(
+                                    Interrupt::Failed(TransactionError::InvalidUtxoHandle),
+                                )?;
+                                *output = Value::I32(status as i32);
+                                Ok(())
+                            },
+                        )
+                        .unwrap();
+                } else if let Some(event) = import.name().strip_prefix("starstream_event_") {
+                    let event = event.to_owned();
+                    linker
+                        .func_new(
+                            import.module(),
+                            import.name(),
+                            func_ty.clone(),
+                            move |mut caller, inputs, _outputs| {
+                                trace!("{rest}::{name}{inputs:?}");
+                                let (Some(Value::I32(ptr)), Some(Value::I32(len))) =
+                                    (inputs.first(), inputs.get(1))
+                                else {
+                                    return Err(TrapCode::BadSignature.into());
+                                };
+                                let (memory, _) = memory(&mut caller)?;
+                                let data = slice_checked(memory, *ptr as u32, *len as u32)?;
+                                host(Interrupt::Event {
+                                    name: event.clone(),
+                                    data: data.to_vec(),
+                                })
+                            },
+                        )
+                        .unwrap();
                 } else if import.name().starts_with("starstream_handle_") {
                     fake_import(&mut linker, &import, "TODO starstream_handle_");
                 } else {
-                    panic!("bad import {import:?}");
+                    return Err(bad_import(&coordination_code, &import));
                 }
             } else {
-                panic!("bad import {import:?}");
+                return Err(bad_import(&coordination_code, &import));
             }
         } else {
             // Permit out-of-scope imports so a single .wasm module can be used as multiple things.
@@ -1027,18 +1644,36 @@ fn coordination_script_linker(
         }
     }
 
-    linker
+    Ok(linker)
 }
 
 // ----------------------------------------------------------------------------
 
 /// Index into the list of programs loaded by a transaction.
-#[derive(PartialEq, Eq, Clone, Copy)]
-struct ProgramIdx(usize);
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ProgramIdx(usize);
 
 #[allow(non_upper_case_globals)]
 impl ProgramIdx {
-    const Root: ProgramIdx = ProgramIdx(usize::MAX);
+    /// The host, which starts coordination scripts and receives what they
+    /// return.
+    pub const Root: ProgramIdx = ProgramIdx(usize::MAX);
+
+    /// The index of the program, or `None` for the host.
+    pub fn index(self) -> Option<usize> {
+        match self {
+            ProgramIdx::Root => None,
+            ProgramIdx(other) => Some(other),
+        }
+    }
+
+    /// The index, or `null` for the host.
+    fn debug_json(self) -> serde_json::Value {
+        match self {
+            ProgramIdx::Root => serde_json::Value::Null,
+            ProgramIdx(other) => other.into(),
+        }
+    }
 }
 
 impl std::fmt::Debug for ProgramIdx {
@@ -1053,11 +1688,19 @@ impl std::fmt::Debug for ProgramIdx {
 struct TxProgram {
     return_to: ProgramIdx,
     return_is_token: Option<TokenId>,
+    // Set on fallible bind functions: the token to bind once they return a
+    // zero status.
+    binds_token: Option<TokenId>,
+    // Set on consume methods: the UTXO whose remaining tokens are released
+    // once the method returns.
+    consumes_utxo: Option<UtxoId>,
     yield_to: Option<ProgramIdx>,
     yield_to_constructor: Option<Value>,
 
     code: CodeHash,
     entry_point: String,
+    /// What `entry_point` was called with.
+    inputs: Vec<Value>,
     // Num outputs of root fn of `resumable`. wasmi knows this but doesn't expose it.
     num_outputs: usize,
 
@@ -1067,6 +1710,26 @@ struct TxProgram {
     resumable: ResumableCall,
 
     utxo: Option<UtxoId>,
+    /// Fuel spent executing this program, summed over all of its entries.
+    fuel: u64,
+
+    /// The program that created `instance`. Methods called on an instance
+    /// share its memory, so buffers are tracked on the owner.
+    instance_owner: ProgramIdx,
+    /// Regions of memory declared with `starstream_alloc`, which are the only
+    /// places the host may write to in checked transactions.
+    buffers: Vec<std::ops::Range<u32>>,
+    /// Fields of the struct the next yield passes, per
+    /// `starstream_yield_layout`.
+    next_yield_layout: Vec<YieldField>,
+    /// Fields of the struct the current yield passed, if it registered them.
+    yield_layout: Vec<YieldField>,
+    /// Set on queries: the object the UTXO yielded, as it was before the
+    /// query, which must find it unchanged when it returns.
+    query_snapshot: Option<MemorySegment>,
+    /// Set when a call that ran this instance failed. Its memory and call
+    /// stack can't be rolled back, so it can't be entered again.
+    invalidated: bool,
 }
 
 impl TxProgram {
@@ -1077,6 +1740,22 @@ impl TxProgram {
         }
     }
 
+    /// The scheduler links and current state of this program as JSON, for
+    /// [`Transaction::programs_debug_json`].
+    fn debug_json(&self, index: usize) -> serde_json::Value {
+        serde_json::json!({
+            "index": index,
+            "return_to": self.return_to.debug_json(),
+            "yield_to": self.yield_to.map(ProgramIdx::debug_json),
+            "code": format!("{:?}", self.code),
+            "entry_point": self.entry_point,
+            "utxo": self.utxo.map(|utxo| format!("{utxo:?}")),
+            "fuel": self.fuel,
+            "finished": matches!(self.resumable, ResumableCall::Finished),
+            "interrupt": self.interrupt().map(|interrupt| format!("{interrupt:?}")),
+        })
+    }
+
     fn hash(&self, store: &Store<TransactionInner>) -> MemoryHash {
         // Currently this is just sha256 of the whole WASM file. There might
         // be stuff in the WASM file that we don't want to count or that isn't
@@ -1084,15 +1763,15 @@ impl TxProgram {
         let mut hash = [0; 32];
         let mut hasher = Sha256::default();
 
-        // Hash linear memory.
-        hasher.update(
-            self.instance
-                .get_export(store, "memory")
-                .unwrap()
-                .into_memory()
-                .unwrap()
-                .data(store),
-        );
+        // Hash linear memory. Programs without one aren't started, but would
+        // have nothing to hash.
+        if let Some(memory) = self
+            .instance
+            .get_export(store, "memory")
+            .and_then(|export| export.into_memory())
+        {
+            hasher.update(memory.data(store));
+        }
 
         // TODO: include other things than just linear memory in the hash.
         // For stackful UTXOs, we'd need to hash in `self.resumable`.
@@ -1110,6 +1789,7 @@ impl std::fmt::Debug for TxProgram {
             .field("entry_point", &self.entry_point)
             .field("num_outputs", &self.num_outputs)
             .field("utxo", &self.utxo)
+            .field("fuel", &self.fuel)
             .field(
                 "interrupt",
                 &match &self.resumable {
@@ -1123,11 +1803,51 @@ impl std::fmt::Debug for TxProgram {
     }
 }
 
-struct MemorySegment {
+/// Bytes copied out of or into a program's memory at a given address.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+pub struct MemorySegment {
     address: u32,
     data: Vec<u8>,
 }
 
+impl MemorySegment {
+    pub fn new(address: u32, data: Vec<u8>) -> MemorySegment {
+        MemorySegment { address, data }
+    }
+
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The address as a little-endian u32, followed by the data prefixed
+    /// with its length as a little-endian u32.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + self.data.len());
+        bytes.extend_from_slice(&self.address.to_le_bytes());
+        bytes.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    pub fn commit(&self, algorithm: HashAlgorithm) -> [u8; 32] {
+        algorithm.digest(&self.canonical_bytes())
+    }
+
+    /// Commitment to a list of segments, which depends on their order.
+    pub fn commit_all(segments: &[MemorySegment], algorithm: HashAlgorithm) -> [u8; 32] {
+        let mut bytes = Vec::with_capacity(4 + 32 * segments.len());
+        bytes.extend_from_slice(&(segments.len() as u32).to_le_bytes());
+        for segment in segments {
+            bytes.extend_from_slice(&segment.commit(algorithm));
+        }
+        algorithm.digest(&bytes)
+    }
+}
+
 impl std::fmt::Debug for MemorySegment {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "({:#x}, {})", self.address, DisplayHex(&self.data))
@@ -1136,23 +1856,74 @@ impl std::fmt::Debug for MemorySegment {
 
 /// An event logged during a transaction's execution.
 pub struct Event {
-    /// The code in which the ABI declaring the event appeared.
+    /// The event's name, from the `starstream_event_` import it was emitted
+    /// through.
+    pub name: String,
+    /// The bytes passed along with the event.
+    pub data: Vec<u8>,
+    /// The program that emitted the event.
+    program: ProgramIdx,
+}
+
+/// The programs of a transaction, as returned by
+/// [`Transaction::dump_state`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionState {
+    pub programs: Vec<ProgramState>,
+}
+
+/// A program in a [`TransactionState`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramState {
+    /// The program's index in the transaction, in the order it was started.
+    pub index: usize,
     pub code: CodeHash,
-    /// The ABI block in which the event's declaration appeared.
-    pub abi: String,
-    /// The event's name.
-    pub event: String,
-    /// The arguments passed to the event.
-    pub args: Vec<Value>,
+    pub entry_point: String,
+    /// The UTXO this program runs as, if any.
+    pub utxo: Option<UtxoIdBytes>,
+    /// Whether the program is suspended and can still be resumed.
+    pub live: bool,
+}
+
+/// A token that was bound to a UTXO when it was consumed, as returned by
+/// [`Transaction::consumed_tokens`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsumedToken {
+    pub token_type_id: u64,
+    pub amount: u64,
+}
+
+/// An [`Event`] along with the program that emitted it, as returned by
+/// [`Transaction::take_events`].
+pub struct AttributedEvent {
+    pub event: Event,
+    /// The code of the program that emitted the event.
+    pub program_code: CodeHash,
+    /// The function the emitting program was started or called with.
+    pub entry_point: String,
 }
 
 const MAX_FUEL: u64 = u64::MAX;
 
+/// How many UTXO-to-UTXO queries may be nested inside one another.
+const MAX_DIRECT_QUERY_DEPTH: usize = 8;
+
+/// Name under which a catch-all effect handler is registered. It receives
+/// every raise that has no handler of its own.
+const CATCH_ALL_EFFECT: &str = "*";
+/// Export called on a catch-all handler, since it isn't tied to one effect.
+const CATCH_ALL_HANDLE: &str = "starstream_catch_all_handle";
+
+/// One handoff of control between two programs, or between a program and
+/// the host, as logged by [`Transaction::witnesses`].
 #[derive(Debug)]
-struct TxWitness {
+pub struct TxWitness {
     reply_to_witness: usize,
     /// Total fuel spent by the transaction as of the time of this witness.
     fuel: u64,
+    /// Fuel spent by `to_program` from this witness until it next stopped,
+    /// whether by yielding, returning, or calling out to the host.
+    program_fuel: u64,
     is_create: bool,
     is_destroy: bool,
 
@@ -1168,6 +1939,161 @@ struct TxWitness {
     write_to_memory: Vec<MemorySegment>,
 }
 
+impl TxWitness {
+    /// Total fuel spent by the transaction as of the time of this witness.
+    pub fn fuel(&self) -> u64 {
+        self.fuel
+    }
+
+    /// Fuel spent by [`Self::to_program`] from this witness until it next
+    /// stopped.
+    pub fn program_fuel(&self) -> u64 {
+        self.program_fuel
+    }
+
+    /// Whether this witness started [`Self::to_program`].
+    pub fn is_create(&self) -> bool {
+        self.is_create
+    }
+
+    /// Whether [`Self::from_program`] finished with this witness.
+    pub fn is_destroy(&self) -> bool {
+        self.is_destroy
+    }
+
+    pub fn from_program(&self) -> ProgramIdx {
+        self.from_program
+    }
+
+    pub fn from_state_after(&self) -> MemoryHash {
+        self.from_state_after
+    }
+
+    /// Memory segments read from [`Self::from_program`].
+    pub fn read_from_memory(&self) -> &[MemorySegment] {
+        &self.read_from_memory
+    }
+
+    /// The values passed along, like a program's arguments or results.
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    pub fn to_program(&self) -> ProgramIdx {
+        self.to_program
+    }
+
+    pub fn to_state_before(&self) -> MemoryHash {
+        self.to_state_before
+    }
+
+    /// Memory segments written to [`Self::to_program`].
+    pub fn write_to_memory(&self) -> &[MemorySegment] {
+        &self.write_to_memory
+    }
+
+    /// Commitment to everything that passes between the two programs, in a
+    /// canonical encoding. Fuel isn't included.
+    ///
+    /// Externrefs are only meaningful within `store`, so the UTXO or token
+    /// they refer to is committed instead.
+    fn commitment(&self, algorithm: HashAlgorithm, store: &Store<TransactionInner>) -> [u8; 32] {
+        let mut bytes = vec![u8::from(self.is_create), u8::from(self.is_destroy)];
+
+        bytes.extend_from_slice(&(self.from_program.0 as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.from_state_after.0);
+        bytes.extend_from_slice(&MemorySegment::commit_all(
+            &self.read_from_memory,
+            algorithm,
+        ));
+
+        bytes.extend_from_slice(&(self.values.len() as u32).to_le_bytes());
+        for value in &self.values {
+            bytes.push(match value {
+                Value::I32(_) => 0,
+                Value::I64(_) => 1,
+                Value::F32(_) => 2,
+                Value::F64(_) => 3,
+                Value::FuncRef(_) => 4,
+                Value::ExternRef(_) => 5,
+            });
+            match value {
+                Value::I32(n) => bytes.extend_from_slice(&n.to_le_bytes()),
+                Value::I64(n) => bytes.extend_from_slice(&n.to_le_bytes()),
+                Value::F32(n) => bytes.extend_from_slice(&n.to_bits().to_le_bytes()),
+                Value::F64(n) => bytes.extend_from_slice(&n.to_bits().to_le_bytes()),
+                // functions are never passed between programs
+                Value::FuncRef(_) => {}
+                Value::ExternRef(handle) => {
+                    let data = handle.data(store.as_context());
+                    if let Some(utxo) = data.and_then(|data| data.downcast_ref::<UtxoId>()) {
+                        bytes.push(0);
+                        bytes.extend_from_slice(&utxo.bytes);
+                    } else if let Some(token) = data.and_then(|data| data.downcast_ref::<TokenId>())
+                    {
+                        bytes.push(1);
+                        bytes.extend_from_slice(&token.bytes);
+                    } else {
+                        // null
+                        bytes.push(2);
+                    }
+                }
+            }
+        }
+
+        bytes.extend_from_slice(&(self.to_program.0 as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.to_state_before.0);
+        bytes.extend_from_slice(&MemorySegment::commit_all(&self.write_to_memory, algorithm));
+
+        algorithm.digest(&bytes)
+    }
+}
+
+/// The witnesses of a transaction in a serializable form, as returned by
+/// [`Transaction::witness_trace`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WitnessTrace {
+    /// In the order they were logged, so `fuel` never decreases.
+    pub witnesses: Vec<WitnessTraceEntry>,
+}
+
+/// A [`TxWitness`] with its programs as plain indices, `None` being the host.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WitnessTraceEntry {
+    pub fuel: u64,
+    pub program_fuel: u64,
+    pub is_create: bool,
+    pub is_destroy: bool,
+
+    pub from_program: Option<usize>,
+    pub from_state_after: MemoryHash,
+    pub read_from_memory: Vec<MemorySegment>,
+    #[serde(with = "serde_value_vec")]
+    pub values: Vec<Value>,
+
+    pub to_program: Option<usize>,
+    pub to_state_before: MemoryHash,
+    pub write_to_memory: Vec<MemorySegment>,
+}
+
+impl From<&TxWitness> for WitnessTraceEntry {
+    fn from(witness: &TxWitness) -> Self {
+        WitnessTraceEntry {
+            fuel: witness.fuel,
+            program_fuel: witness.program_fuel,
+            is_create: witness.is_create,
+            is_destroy: witness.is_destroy,
+            from_program: witness.from_program.index(),
+            from_state_after: witness.from_state_after,
+            read_from_memory: witness.read_from_memory.clone(),
+            values: witness.values.clone(),
+            to_program: witness.to_program.index(),
+            to_state_before: witness.to_state_before,
+            write_to_memory: witness.write_to_memory.clone(),
+        }
+    }
+}
+
 /// A row in the continuation table describing UTXO evolution.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ContinuationEntry {
@@ -1186,17 +2112,38 @@ pub struct ContinuationEntry {
     /// Hash of the UTXO's memory and attached state after the call.
     /// All zeroes means an ended UTXO.
     state_after: MemoryHash,
+    /// Fuel spent by the program during this call.
+    #[serde(default)]
+    fuel: u64,
+}
+
+impl ContinuationEntry {
+    /// Index of the program this entry belongs to.
+    pub fn program(&self) -> usize {
+        self.program
+    }
+
+    /// The entry point function, such as X_init or X_resume.
+    pub fn entry_point(&self) -> &str {
+        &self.entry_point
+    }
+
+    /// Fuel spent by the program during this call, not counting the fuel of
+    /// any other program it called into.
+    pub fn fuel(&self) -> u64 {
+        self.fuel
+    }
 }
 
 mod serde_value_vec {
-    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, ser::Error as _};
     use wasmi::{
-        Value,
+        ExternRef, FuncRef, Value,
         core::{F32, F64},
     };
 
-    // TODO: actually serialize here. The hard parts are FuncRef (we don't use it)
-    // and ExternRef (we'd *like* to use it for UTXOs, but maybe this isn't practical).
+    // References only mean something within the store they came from, so
+    // only null ones can be written out.
 
     #[derive(serde::Serialize, serde::Deserialize)]
     enum Value2 {
@@ -1204,19 +2151,26 @@ mod serde_value_vec {
         I64(i64),
         F32(u32),
         F64(u64),
+        NullFuncRef,
+        NullExternRef,
     }
 
     pub fn serialize<S: Serializer>(value: &Vec<Value>, ser: S) -> Result<S::Ok, S::Error> {
         let vec2 = value
             .iter()
             .map(|v| match v {
-                Value::I32(i) => Value2::I32(*i),
-                Value::I64(i) => Value2::I64(*i),
-                Value::F32(i) => Value2::F32(i.to_bits()),
-                Value::F64(i) => Value2::F64(i.to_bits()),
-                _ => todo!(),
+                Value::I32(i) => Ok(Value2::I32(*i)),
+                Value::I64(i) => Ok(Value2::I64(*i)),
+                Value::F32(i) => Ok(Value2::F32(i.to_bits())),
+                Value::F64(i) => Ok(Value2::F64(i.to_bits())),
+                Value::FuncRef(r) if r.is_null() => Ok(Value2::NullFuncRef),
+                Value::ExternRef(r) if r.is_null() => Ok(Value2::NullExternRef),
+                _ => Err(S::Error::custom(format_args!(
+                    "can't serialize a non-null {:?}",
+                    v.ty()
+                ))),
             })
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>, _>>()?;
         vec2.serialize(ser)
     }
 
@@ -1229,6 +2183,8 @@ mod serde_value_vec {
                 Value2::I64(i) => Value::I64(i),
                 Value2::F32(i) => Value::F32(F32::from_bits(i)),
                 Value2::F64(i) => Value::F64(F64::from_bits(i)),
+                Value2::NullFuncRef => Value::FuncRef(FuncRef::null()),
+                Value2::NullExternRef => Value::ExternRef(ExternRef::null()),
             })
             .collect())
     }
@@ -1242,88 +2198,748 @@ mod serde_value_vec {
 struct TransactionInner {
     utxos: HashMap<UtxoId, Utxo>,
     tokens: HashMap<TokenId, (Option<UtxoId>, Token)>,
-    temporary_utxo_ids: HashMap<u64, UtxoId>,
-    temporary_token_ids: HashMap<u64, TokenId>,
+    temporary_utxo_ids: HashMap<ScrambledRef, UtxoId>,
+    temporary_token_ids: HashMap<ScrambledRef, TokenId>,
+    /// The handles pushed to each list a contract created, by the handle of
+    /// the list.
+    lists: HashMap<ScrambledRef, Vec<i64>>,
 
     /// Programs this transaction has started or resumed.
     programs: Vec<TxProgram>,
     /// Call and return values between programs, logged for future ZK use.
     witnesses: Vec<TxWitness>,
-
+    /// The instance owners of the programs entered since the last
+    /// checkpoint, so a rollback knows which earlier programs the failed call
+    /// ran.
+    entered: HashSet<ProgramIdx>,
+
+    /// Handlers for each effect, in registration order. A raise is dispatched
+    /// to the most recently registered handler that is still in place, or to
+    /// a catch-all if the effect has none.
     registered_effect_handler: HashMap<String, Vec<(ProgramIdx, u32)>>,
-    raised_effects: HashMap<String, ProgramIdx>,
+    /// Raises that haven't been resumed yet, in the order they were raised.
+    ///
+    /// A raise can only happen while another is pending if it comes from
+    /// inside the handling of the earlier one, so a handler asking after an
+    /// effect is served the most recent pending raise of that name. Pending
+    /// raises of the same effect are thus resumed last-raised-first.
+    raised_effects: Vec<(String, ProgramIdx)>,
+    /// Stands in for the raising program during `Transaction::simulate_effect`.
+    simulated_effect: Option<SimulatedEffect>,
+    /// Commitments to each UTXO's yielded outputs, in order.
+    utxo_outputs: HashMap<UtxoId, Vec<[u8; 32]>>,
+    /// The tokens each consumed UTXO held, in id order. They are left unbound
+    /// for the coordination script to collect.
+    consumed_tokens: HashMap<UtxoId, Vec<TokenId>>,
 
     events: Vec<Event>,
-}
 
-/// An in-progress transaction and its traces. Contains all related Wasm execution.
-pub struct Transaction {
-    store: Store<TransactionInner>,
-    code_cache: Arc<CodeCache>,
-    // Used to keep the rust-based examples working without constraining the dsl
-    // where necessary. This is meant to be a temporary patch, since we probably
-    // are not going to keep using the rust examples eventually.
-    rust_compat: bool,
+    /// Keys the transaction is signed by, as told to contracts.
+    signers: Vec<PublicKey>,
+
+    memory_budget: MemoryBudget,
+
+    ids: IdSource,
 }
 
-impl Default for Transaction {
+/// Draws the ids of new UTXOs and tokens, and the keys their handles are
+/// passed to contracts as.
+///
+/// Seeded from the OS unless the transaction was built with
+/// [`TransactionBuilder::seed`], in which case the same calls always get the
+/// same ids. ChaCha20 is named rather than left to `StdRng`, whose algorithm
+/// may change between `rand` releases, so seeded ids stay the same too.
+struct IdSource(ChaCha20Rng);
+
+impl Default for IdSource {
     fn default() -> Self {
-        Self::new()
+        IdSource(ChaCha20Rng::from_entropy())
     }
 }
 
-impl Transaction {
-    /// Begin a new transaction with no dependencies.
-    pub fn new() -> Transaction {
-        let engine = Engine::new(Config::default().consume_fuel(true));
-        let mut store = Store::new(&engine, TransactionInner::default());
-        store.add_fuel(MAX_FUEL).unwrap();
-        Transaction {
-            store,
-            code_cache: Default::default(),
-            rust_compat: false,
+/// Caps the linear memory of all programs in a transaction combined, so that
+/// spawning many instances can't exhaust the host.
+struct MemoryBudget {
+    limit: usize,
+    used: usize,
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        MemoryBudget {
+            limit: usize::MAX,
+            used: 0,
         }
     }
+}
 
-    pub fn with_rust_compat(&mut self, rust_compat: bool) {
-        self.rust_compat = rust_compat;
+impl ResourceLimiter for MemoryBudget {
+    fn memory_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> Result<bool, MemoryError> {
+        // Called with `current` 0 when a memory is first instantiated. Denied
+        // instantiations fail, and denied growths return -1 to the contract.
+        let Some(used) = self
+            .used
+            .checked_add(desired - current)
+            .filter(|&used| used <= self.limit)
+        else {
+            debug!(
+                "memory budget of {} bytes exceeded: {} + {}",
+                self.limit,
+                self.used,
+                desired - current
+            );
+            return Ok(false);
+        };
+        self.used = used;
+        Ok(true)
     }
 
-    pub fn code_cache(&self) -> &Arc<CodeCache> {
-        &self.code_cache
+    fn table_growing(
+        &mut self,
+        _current: u32,
+        _desired: u32,
+        _maximum: Option<u32>,
+    ) -> Result<bool, TableError> {
+        Ok(true)
     }
 
-    pub fn add_utxo(&mut self, utxo: &mock_ledger::Utxo) -> Value {
-        let data = self.store.data_mut();
-
-        //
-        let program: ProgramIdx = todo!();
+    fn instances(&self) -> usize {
+        usize::MAX
+    }
 
-        let utxo = Utxo {
-            program,
-            // TODO: remember tokens
-            tokens: Default::default(),
-        };
+    fn tables(&self) -> usize {
+        usize::MAX
+    }
 
-        let id = UtxoId::random();
-        data.utxos.insert(id, utxo);
-        id.to_wasm_externref(self.store.as_context_mut())
+    fn memories(&self) -> usize {
+        usize::MAX
     }
+}
 
-    /// Run a coordination script in this transaction.
-    pub fn run_coordination_script(
+/// The state of a transaction before a top-level call, to return to if the
+/// call fails.
+///
+/// Programs that existed before the call are kept as they are: a suspended
+/// Wasm call stack can't be copied, so one the failed call resumed stays where
+/// the failure left it.
+struct Checkpoint {
+    utxos: HashMap<UtxoId, Utxo>,
+    tokens: HashMap<TokenId, (Option<UtxoId>, Token)>,
+    temporary_utxo_ids: HashMap<ScrambledRef, UtxoId>,
+    temporary_token_ids: HashMap<ScrambledRef, TokenId>,
+    lists: HashMap<ScrambledRef, Vec<i64>>,
+    programs: usize,
+    witnesses: usize,
+    registered_effect_handler: HashMap<String, Vec<(ProgramIdx, u32)>>,
+    raised_effects: Vec<(String, ProgramIdx)>,
+    utxo_outputs: HashMap<UtxoId, Vec<[u8; 32]>>,
+    consumed_tokens: HashMap<UtxoId, Vec<TokenId>>,
+    events: usize,
+}
+
+impl TransactionInner {
+    fn checkpoint(&mut self) -> Checkpoint {
+        self.entered.clear();
+        Checkpoint {
+            utxos: self.utxos.clone(),
+            tokens: self.tokens.clone(),
+            temporary_utxo_ids: self.temporary_utxo_ids.clone(),
+            temporary_token_ids: self.temporary_token_ids.clone(),
+            lists: self.lists.clone(),
+            programs: self.programs.len(),
+            witnesses: self.witnesses.len(),
+            registered_effect_handler: self.registered_effect_handler.clone(),
+            raised_effects: self.raised_effects.clone(),
+            utxo_outputs: self.utxo_outputs.clone(),
+            consumed_tokens: self.consumed_tokens.clone(),
+            events: self.events.len(),
+        }
+    }
+
+    /// Forget everything done since `checkpoint` was taken, by the call that
+    /// failed with `error`.
+    ///
+    /// Earlier programs the failed call ran are invalidated rather than
+    /// restored. The store keeps the instances the failed call created, so
+    /// their memory stays charged to the budget.
+    fn rollback(&mut self, checkpoint: Checkpoint, error: &mut TransactionError) {
+        // The programs the failed call started are dropped below, so the
+        // error can't point at them.
+        if let TransactionError::OutOfFuel { program, .. } = error {
+            *program = program.filter(|&program| program < checkpoint.programs);
+        }
+
+        for program in self.entered.drain() {
+            if program.0 < checkpoint.programs {
+                self.programs[program.0].invalidated = true;
+            }
+        }
+        self.utxos = checkpoint.utxos;
+        self.tokens = checkpoint.tokens;
+        self.temporary_utxo_ids = checkpoint.temporary_utxo_ids;
+        self.temporary_token_ids = checkpoint.temporary_token_ids;
+        self.lists = checkpoint.lists;
+        self.programs.truncate(checkpoint.programs);
+        self.witnesses.truncate(checkpoint.witnesses);
+        self.registered_effect_handler = checkpoint.registered_effect_handler;
+        self.raised_effects = checkpoint.raised_effects;
+        self.utxo_outputs = checkpoint.utxo_outputs;
+        self.consumed_tokens = checkpoint.consumed_tokens;
+        self.events.truncate(checkpoint.events);
+    }
+
+    /// Note that the instance of `program` is about to run, unless a rollback
+    /// invalidated it.
+    fn enter(&mut self, program: ProgramIdx) -> Result<(), TransactionError> {
+        let owner = self.programs[program.0].instance_owner;
+        if self.programs[owner.0].invalidated {
+            return Err(TransactionError::ProgramInvalidated {
+                program: owner.0,
+                utxo: self.programs[owner.0].utxo,
+            });
+        }
+        self.entered.insert(owner);
+        Ok(())
+    }
+
+    /// The program whose raise of `name` a handler would currently see.
+    fn pending_raise(&self, name: &str) -> Option<ProgramIdx> {
+        self.raised_effects
+            .iter()
+            .rev()
+            .find(|(effect, _)| effect == name)
+            .map(|&(_, program)| program)
+    }
+
+    /// The effect `handler` was called for. A handler returns to the program
+    /// that raised, which can only have one raise pending.
+    fn handled_raise(&self, handler: ProgramIdx) -> Option<&str> {
+        let raiser = self.programs[handler.0].return_to;
+        self.raised_effects
+            .iter()
+            .rev()
+            .find(|&&(_, program)| program == raiser)
+            .map(|(effect, _)| effect.as_str())
+    }
+
+    /// Whether `utxo_id` can still be resumed, as the `UtxoStatus` guests
+    /// expect: 1 if it's suspended on a yield or call, 0 once it has returned
+    /// or been consumed. `None` if it isn't a UTXO of this transaction.
+    fn utxo_status(&self, utxo_id: UtxoId) -> Option<u32> {
+        let program = self.utxos.get(&utxo_id)?.program;
+        Some(self.programs[program.0].interrupt().is_some() as u32)
+    }
+
+    /// The handler a raise of `name` is dispatched to and the export to call
+    /// on it: the most recent handler for `name`, or else the most recent
+    /// catch-all.
+    fn effect_handler(&self, name: &str) -> Option<(ProgramIdx, u32, String)> {
+        let handler = |name: &str| {
+            self.registered_effect_handler
+                .get(name)
+                .and_then(|handlers| handlers.last())
+                .copied()
+        };
+
+        if let Some((program, handler_address)) = handler(name) {
+            Some((program, handler_address, format!("{name}_handle")))
+        } else {
+            handler(CATCH_ALL_EFFECT).map(|(program, handler_address)| {
+                (program, handler_address, CATCH_ALL_HANDLE.to_owned())
+            })
+        }
+    }
+
+    /// Remove and return the pending raise of `name` a handler would see.
+    fn take_pending_raise(&mut self, name: &str) -> Option<ProgramIdx> {
+        let index = self
+            .raised_effects
+            .iter()
+            .rposition(|(effect, _)| effect == name)?;
+        Some(self.raised_effects.remove(index).1)
+    }
+}
+
+struct SimulatedEffect {
+    input: Vec<u8>,
+    output_len: u32,
+    output: Option<Vec<u8>>,
+}
+
+/// Ways a transaction can fail without taking down the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionError {
+    /// A contract called `abort()`, usually from its panic handler.
+    Aborted,
+    /// A contract's `require!` precondition did not hold. Unlike
+    /// [`TransactionError::Aborted`], this is a deliberate early exit and
+    /// carries the code the contract chose to report.
+    RequireFailed { code: u32 },
+    /// A UTXO was resumed, queried, mutated, or consumed after it was
+    /// already consumed or had finished, for example through a copied handle.
+    UtxoAlreadyConsumed { utxo: UtxoId },
+    /// In a checked transaction, the host was about to write `len` bytes at
+    /// `address`, outside of every buffer the contract declared.
+    UndeclaredMemoryWrite { address: u32, len: usize },
+    /// Instantiating another program would take the linear memory of the
+    /// whole transaction over its budget of `budget` bytes.
+    MemoryBudgetExceeded { budget: usize },
+    /// A call made with a deadline was still running when it passed.
+    Timeout,
+    /// The transaction ran out of fuel while `entry_point` of `code` was
+    /// running as its `program`th program, after spending `fuel_consumed`.
+    /// `program` is `None` if the program isn't registered, because it ran
+    /// out before it first suspended or the failed call that started it was
+    /// rolled back.
+    OutOfFuel {
+        program: Option<usize>,
+        code: CodeHash,
+        entry_point: String,
+        fuel_consumed: u64,
+    },
+    /// A UTXO directly queried `utxo` while `utxo` was itself waiting on that
+    /// chain of calls, which could observe it halfway through a method.
+    DirectQueryReentrancy { utxo: UtxoId },
+    /// A query of `utxo` changed the object it yielded. Only mutations and
+    /// consumes may do that.
+    QueryMutatedState { utxo: UtxoId },
+    /// Direct queries between UTXOs were nested more than `limit` deep.
+    DirectQueryTooDeep { limit: usize },
+    /// The data raised with `effect` claims `data_len` bytes at `data`, past
+    /// the end of the raising program's memory.
+    EffectDataOutOfBounds {
+        effect: String,
+        data: u32,
+        data_len: u32,
+    },
+    /// The data raised with `effect` is `data_len` bytes long, but the handler
+    /// asked for it into a buffer of `output_len` bytes.
+    EffectDataMismatch {
+        effect: String,
+        data_len: u32,
+        output_len: u32,
+    },
+    /// The `program`th program trapped while running `entry_point`, for
+    /// example by executing `unreachable` or dividing by zero.
+    Trap {
+        program: usize,
+        entry_point: String,
+        message: String,
+    },
+    /// `code` has no exported function called `name`.
+    MissingExport { code: CodeHash, name: String },
+    /// The host had to copy `len` bytes at `address` into the memory of the
+    /// `program`th program, but they don't fit.
+    MemoryOutOfBounds {
+        program: usize,
+        address: u32,
+        len: usize,
+    },
+    /// The `program`th program wasn't suspended in a way that allows what was
+    /// asked of it, like resuming it after it finished.
+    BadInterruptState { program: usize },
+    /// `code` could not be instantiated, usually because of an import the
+    /// host doesn't provide.
+    LinkError { code: CodeHash, message: String },
+    /// `code` is not a valid Wasm module.
+    InvalidCode { code: CodeHash, message: String },
+    /// No code with the hash `code` is in the code cache.
+    CodeNotFound { code: CodeHash },
+    /// The tokens of type `token_type_id` bound to `utxo` would add up to
+    /// more than `u64::MAX`.
+    TokenAmountOverflow { utxo: UtxoId, token_type_id: u64 },
+    /// Replaying the snapshot of `utxo` didn't bring it back to the state it
+    /// was in when the snapshot was taken.
+    SnapshotMismatch { utxo: UtxoId },
+    /// A program raised `name`, but no handler for it, nor a catch-all, is
+    /// registered.
+    UnhandledEffect { name: String },
+    /// The handler for a simulated raise of `name` returned without resuming.
+    EffectNotResumed { name: String },
+    /// The `program`th program, of `utxo` if it is one, ran in an earlier
+    /// call that failed, which left it in a state that can't be trusted.
+    ProgramInvalidated {
+        program: usize,
+        utxo: Option<UtxoId>,
+    },
+    /// A contract passed a UTXO handle to the host that doesn't refer to any
+    /// UTXO of this transaction.
+    InvalidUtxoHandle,
+    /// `utxo` tried to unbind or burn a token that isn't bound to it.
+    TokenNotBound { utxo: UtxoId },
+    /// A contract passed a token handle to the host that doesn't refer to any
+    /// token of this transaction, for example one that was already burned.
+    InvalidTokenHandle,
+    /// A contract passed a list handle to the host that doesn't refer to any
+    /// list of this transaction.
+    InvalidListHandle,
+    /// A token still bound to `utxo` was burned or split without unbinding it
+    /// first.
+    TokenStillBound { utxo: UtxoId },
+    /// A contract tried to split `requested` off a token worth only `amount`.
+    TokenAmountExceeded { amount: u64, requested: u64 },
+}
+
+impl std::fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransactionError::Aborted => f.write_str("contract called abort()"),
+            TransactionError::RequireFailed { code } => {
+                write!(f, "contract precondition failed with code {code}")
+            }
+            TransactionError::UtxoAlreadyConsumed { utxo } => {
+                write!(f, "{utxo:?} was already consumed")
+            }
+            TransactionError::UndeclaredMemoryWrite { address, len } => {
+                write!(
+                    f,
+                    "{len} bytes written at {address:#x} outside of any declared buffer"
+                )
+            }
+            TransactionError::MemoryBudgetExceeded { budget } => {
+                write!(f, "transaction memory budget of {budget} bytes exceeded")
+            }
+            TransactionError::Timeout => f.write_str("transaction ran past its deadline"),
+            TransactionError::OutOfFuel {
+                program,
+                code,
+                entry_point,
+                fuel_consumed,
+            } => {
+                write!(f, "ran out of fuel in {entry_point} of {code:?}")?;
+                if let Some(program) = program {
+                    write!(f, " (program {program})")?;
+                }
+                write!(f, " after {fuel_consumed}")
+            }
+            TransactionError::DirectQueryReentrancy { utxo } => {
+                write!(f, "direct query re-entered {utxo:?}")
+            }
+            TransactionError::QueryMutatedState { utxo } => {
+                write!(f, "query changed the state of {utxo:?}")
+            }
+            TransactionError::DirectQueryTooDeep { limit } => {
+                write!(f, "direct queries nested more than {limit} deep")
+            }
+            TransactionError::EffectDataOutOfBounds {
+                effect,
+                data,
+                data_len,
+            } => {
+                write!(
+                    f,
+                    "{effect} raised with {data_len} bytes at {data:#x}, out of bounds"
+                )
+            }
+            TransactionError::EffectDataMismatch {
+                effect,
+                data_len,
+                output_len,
+            } => {
+                write!(
+                    f,
+                    "{effect} raised with {data_len} bytes, but handled into a buffer of {output_len}"
+                )
+            }
+            TransactionError::Trap {
+                program,
+                entry_point,
+                message,
+            } => {
+                write!(f, "{entry_point} trapped (program {program}): {message}")
+            }
+            TransactionError::MissingExport { code, name } => {
+                write!(f, "{code:?} doesn't export {name}")
+            }
+            TransactionError::MemoryOutOfBounds {
+                program,
+                address,
+                len,
+            } => {
+                write!(
+                    f,
+                    "{len} bytes at {address:#x} are out of bounds of program {program}"
+                )
+            }
+            TransactionError::BadInterruptState { program } => {
+                write!(f, "program {program} is not suspended in the right state")
+            }
+            TransactionError::LinkError { code, message } => {
+                write!(f, "failed to instantiate {code:?}: {message}")
+            }
+            TransactionError::InvalidCode { code, message } => {
+                write!(f, "failed to compile {code:?}: {message}")
+            }
+            TransactionError::CodeNotFound { code } => {
+                write!(f, "no contract code loaded for {code:?}")
+            }
+            TransactionError::TokenAmountOverflow {
+                utxo,
+                token_type_id,
+            } => {
+                write!(
+                    f,
+                    "tokens of type {token_type_id} bound to {utxo:?} overflow a u64"
+                )
+            }
+            TransactionError::SnapshotMismatch { utxo } => {
+                write!(f, "replaying the snapshot of {utxo:?} diverged")
+            }
+            TransactionError::UnhandledEffect { name } => {
+                write!(f, "no handler registered for {name}")
+            }
+            TransactionError::EffectNotResumed { name } => {
+                write!(f, "handler for {name} returned without resuming")
+            }
+            TransactionError::ProgramInvalidated { program, utxo } => match utxo {
+                Some(utxo) => write!(f, "{utxo:?} was invalidated by a failed call"),
+                None => write!(f, "program {program} was invalidated by a failed call"),
+            },
+            TransactionError::InvalidUtxoHandle => f.write_str("invalid UTXO handle"),
+            TransactionError::TokenNotBound { utxo } => {
+                write!(f, "token is not bound to {utxo:?}")
+            }
+            TransactionError::InvalidTokenHandle => f.write_str("invalid token handle"),
+            TransactionError::InvalidListHandle => f.write_str("invalid list handle"),
+            TransactionError::TokenStillBound { utxo } => {
+                write!(f, "token is still bound to {utxo:?}")
+            }
+            TransactionError::TokenAmountExceeded { amount, requested } => {
+                write!(f, "can't split {requested} off a token worth {amount}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TransactionError {}
+
+/// An in-progress transaction and its traces. Contains all related Wasm execution.
+pub struct Transaction {
+    store: Store<TransactionInner>,
+    code_cache: Arc<CodeCache>,
+    // Used to keep the rust-based examples working without constraining the dsl
+    // where necessary. This is meant to be a temporary patch, since we probably
+    // are not going to keep using the rust examples eventually.
+    rust_compat: bool,
+    check_memory_writes: bool,
+    // Set while a call with a deadline is running.
+    deadline: Option<Instant>,
+}
+
+impl Default for Transaction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builder for a [`Transaction`] with non-default settings.
+#[derive(Default)]
+pub struct TransactionBuilder {
+    code_cache: Arc<CodeCache>,
+    config: Config,
+    rust_compat: bool,
+    check_memory_writes: bool,
+    memory_budget: Option<usize>,
+    fuel_limit: Option<u64>,
+    signers: Vec<PublicKey>,
+    seed: Option<[u8; 32]>,
+}
+
+impl TransactionBuilder {
+    /// Load contract code through `code_cache`.
+    pub fn code_cache(mut self, code_cache: Arc<CodeCache>) -> Self {
+        self.code_cache = code_cache;
+        self
+    }
+
+    /// Use `config` for the wasm engine.
+    ///
+    /// Fuel metering is always turned back on, since witnesses record the fuel
+    /// spent.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn rust_compat(mut self, rust_compat: bool) -> Self {
+        self.rust_compat = rust_compat;
+        self
+    }
+
+    /// Trap when the host writes to contract memory outside of the buffers
+    /// the contract declared with `starstream_alloc`.
+    pub fn check_memory_writes(mut self, check_memory_writes: bool) -> Self {
+        self.check_memory_writes = check_memory_writes;
+        self
+    }
+
+    /// Cap the linear memory of all programs in the transaction combined at
+    /// `bytes`.
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    /// Let the programs of the transaction spend `fuel` in total, after which
+    /// it fails with [`TransactionError::OutOfFuel`].
+    pub fn fuel_limit(mut self, fuel: u64) -> Self {
+        self.fuel_limit = Some(fuel);
+        self
+    }
+
+    /// Mark the transaction as signed by `signers`, which contracts can list
+    /// with `starstream_tx_signers`.
+    pub fn signers(mut self, signers: Vec<PublicKey>) -> Self {
+        self.signers = signers;
+        self
+    }
+
+    /// Draw UTXO and token ids from `seed` rather than the OS, so that
+    /// replaying the same calls gives the same ids and traces.
+    pub fn seed(mut self, seed: [u8; 32]) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn build(mut self) -> Transaction {
+        let engine = Engine::new(self.config.consume_fuel(true));
+        let mut store = Store::new(
+            &engine,
+            TransactionInner {
+                signers: self.signers,
+                ids: self
+                    .seed
+                    .map(|seed| IdSource(ChaCha20Rng::from_seed(seed)))
+                    .unwrap_or_default(),
+                ..Default::default()
+            },
+        );
+        store.add_fuel(self.fuel_limit.unwrap_or(MAX_FUEL)).unwrap();
+        if let Some(limit) = self.memory_budget {
+            store.data_mut().memory_budget.limit = limit;
+        }
+        store.limiter(|inner| &mut inner.memory_budget);
+        Transaction {
+            store,
+            code_cache: self.code_cache,
+            rust_compat: self.rust_compat,
+            check_memory_writes: self.check_memory_writes,
+            deadline: None,
+        }
+    }
+}
+
+impl Transaction {
+    /// Begin a new transaction with no dependencies.
+    pub fn new() -> Transaction {
+        Transaction::builder().build()
+    }
+
+    /// Begin a new transaction which fails with
+    /// [`TransactionError::UndeclaredMemoryWrite`] whenever the host writes to
+    /// contract memory outside of a buffer declared with `starstream_alloc`.
+    /// Meant for testing contracts against the host's memory layout.
+    pub fn new_checked() -> Transaction {
+        Transaction::builder().check_memory_writes(true).build()
+    }
+
+    pub fn builder() -> TransactionBuilder {
+        TransactionBuilder::default()
+    }
+
+    /// Begin a new transaction which loads contract code through `code_cache`.
+    pub fn with_code_cache(code_cache: Arc<CodeCache>) -> Transaction {
+        Transaction::builder().code_cache(code_cache).build()
+    }
+
+    /// Begin a new transaction whose wasm engine uses `config`. Fuel metering
+    /// stays on regardless.
+    pub fn with_config(config: Config) -> Transaction {
+        Transaction::builder().config(config).build()
+    }
+
+    /// Begin a new transaction which fails with
+    /// [`TransactionError::OutOfFuel`] once its programs have spent `limit`
+    /// fuel between them.
+    pub fn with_fuel_limit(limit: u64) -> Transaction {
+        Transaction::builder().fuel_limit(limit).build()
+    }
+
+    /// Begin a new transaction which fails with
+    /// [`TransactionError::MemoryBudgetExceeded`] rather than instantiate
+    /// programs past `bytes` of linear memory in total.
+    pub fn with_memory_budget(bytes: usize) -> Transaction {
+        Transaction::builder().memory_budget(bytes).build()
+    }
+
+    /// Begin a new transaction whose UTXO and token ids are drawn from
+    /// `seed`, so that it can be reproduced exactly.
+    pub fn with_seed(seed: [u8; 32]) -> Transaction {
+        Transaction::builder().seed(seed).build()
+    }
+
+    pub fn with_rust_compat(&mut self, rust_compat: bool) {
+        self.rust_compat = rust_compat;
+    }
+
+    pub fn code_cache(&self) -> &Arc<CodeCache> {
+        &self.code_cache
+    }
+
+    /// The wasm engine contract code is compiled for, as configured by
+    /// [`TransactionBuilder::config`].
+    pub fn engine(&self) -> &Engine {
+        self.store.engine()
+    }
+
+    /// Get code by hash from the code cache, if it compiles for this
+    /// transaction's engine.
+    pub fn try_get_code(&self, hash: CodeHash) -> Result<Arc<ContractCode>, CodeError> {
+        self.code_cache.try_get(hash, self.engine())
+    }
+
+    pub fn add_utxo(&mut self, utxo: &mock_ledger::Utxo) -> Value {
+        let data = self.store.data_mut();
+
+        //
+        let program: ProgramIdx = todo!();
+
+        let utxo = Utxo {
+            program,
+            // TODO: remember tokens
+            tokens: Default::default(),
+            inputs: vec![],
+            calls: vec![],
+            token_binds: vec![],
+        };
+
+        let id = UtxoId::random(&mut data.ids);
+        data.utxos.insert(id, utxo);
+        id.to_wasm_externref(self.store.as_context_mut())
+    }
+
+    /// Run a coordination script in this transaction.
+    ///
+    /// If the script fails, the UTXOs, tokens and witnesses it created are
+    /// discarded, while those of earlier calls are kept.
+    pub fn run_coordination_script(
         &mut self,
         coordination_code: &Arc<ContractCode>,
         entry_point: &str,
         mut inputs: Vec<Value>,
-    ) -> Value {
+    ) -> Result<Value, TransactionError> {
         debug!("run_coordination_script({entry_point:?}, {inputs:?})");
 
         let linker = coordination_script_linker(
             &self.store.engine().clone(),
             &self.code_cache,
             coordination_code.clone(),
-        );
+        )?;
 
         // Turn ExternRefs into numeric UTXO refs
         for value in &mut inputs {
@@ -1332,20 +2948,439 @@ impl Transaction {
             }
         }
 
-        let (mut from_program, mut result) = self.start_program(
+        let checkpoint = self.store.data_mut().checkpoint();
+        let (from_program, result) = self.start_program(
             ProgramIdx::Root,
             &linker,
             coordination_code,
             entry_point,
             inputs,
         );
-        // Main effect scheduler loop.
+        let mut result = self.run_scheduler(Some(coordination_code), from_program, result);
+        if let Err(error) = &mut result {
+            // Leave no trace of the partial call, so calls that succeeded
+            // earlier in this transaction are as they were.
+            self.store.data_mut().rollback(checkpoint, error);
+        }
+        result
+    }
+
+    /// Like [`Transaction::run_coordination_script`], but fails with
+    /// [`TransactionError::Timeout`] once `deadline` has passed.
+    ///
+    /// The clock is checked each time control passes between programs, so a
+    /// program that loops without calling the host is only bounded by fuel.
+    pub fn run_coordination_script_with_deadline(
+        &mut self,
+        coordination_code: &Arc<ContractCode>,
+        entry_point: &str,
+        inputs: Vec<Value>,
+        deadline: Instant,
+    ) -> Result<Value, TransactionError> {
+        self.deadline = Some(deadline);
+        let result = self.run_coordination_script(coordination_code, entry_point, inputs);
+        self.deadline = None;
+        result
+    }
+
+    /// Create a UTXO without going through a coordination script.
+    ///
+    /// The UTXO program is rooted at the transaction itself and runs until its
+    /// first yield. Returns the UTXO's externref, which can be passed to later
+    /// coordination scripts.
+    ///
+    /// Like [`Transaction::run_coordination_script`], a failed call leaves no
+    /// trace in the transaction.
+    pub fn new_utxo_direct(
+        &mut self,
+        code: &Arc<ContractCode>,
+        entry_point: &str,
+        inputs: Vec<Value>,
+    ) -> Result<Value, TransactionError> {
+        debug!("new_utxo_direct({entry_point:?}, {inputs:?})");
+
+        let checkpoint = self.store.data_mut().checkpoint();
+        let (from_program, result) = self.start_utxo(ProgramIdx::Root, code, entry_point, inputs);
+        let mut result = self.run_scheduler(None, from_program, result);
+        if let Err(error) = &mut result {
+            self.store.data_mut().rollback(checkpoint, error);
+        }
+        result
+    }
+
+    /// Capture `utxo_id` so it can be loaded into a later transaction with
+    /// [`Transaction::load_utxo`]. `None` unless the UTXO is suspended in a
+    /// yield.
+    pub fn snapshot_utxo(&self, utxo_id: UtxoId) -> Option<UtxoSnapshot> {
+        let data = self.store.data();
+        let utxo = data.utxos.get(&utxo_id)?;
+        let program = &data.programs[utxo.program.0];
+        let Some(Interrupt::Yield {
+            resume_arg,
+            resume_arg_len,
+            ..
+        }) = program.interrupt()
+        else {
+            return None;
+        };
+
+        Some(UtxoSnapshot {
+            id: utxo_id.into(),
+            code: program.code,
+            entry_point: program.entry_point.clone(),
+            inputs: utxo.inputs.clone(),
+            calls: utxo.calls.clone(),
+            token_binds: utxo.token_binds.clone(),
+            memory: self
+                .program_memory(utxo.program)
+                .ok()?
+                .data(&self.store)
+                .to_vec(),
+            globals: self.program_globals(utxo.program),
+            resume_arg: *resume_arg,
+            resume_arg_len: *resume_arg_len,
+        })
+    }
+
+    /// Bring back a UTXO captured with [`Transaction::snapshot_utxo`], under
+    /// the same id, by replaying its entry point and the calls made into it.
+    /// Its code, and that of the tokens it was asked to bind, must already be
+    /// in the code cache, or this fails with
+    /// [`TransactionError::CodeNotFound`]. Returns the UTXO's externref,
+    /// which can be passed to later coordination scripts.
+    ///
+    /// Fails with [`TransactionError::SnapshotMismatch`] if the replay doesn't
+    /// end up in the captured state, in which case the UTXO isn't loaded.
+    pub fn load_utxo(&mut self, snapshot: &UtxoSnapshot) -> Result<Value, TransactionError> {
+        debug!("load_utxo({:?})", snapshot.id);
+
+        let utxo_id = UtxoId::from(snapshot.id);
+        let code = self.load_code(snapshot.code)?;
+
+        let checkpoint = self.store.data_mut().checkpoint();
+        let mut result = self.replay_utxo(utxo_id, &code, snapshot);
+        if let Err(error) = &mut result {
+            self.store.data_mut().rollback(checkpoint, error);
+        }
+        result
+    }
+
+    /// Like [`Transaction::try_get_code`], failing the transaction.
+    fn load_code(&self, hash: CodeHash) -> Result<Arc<ContractCode>, TransactionError> {
+        self.try_get_code(hash).map_err(|error| match error {
+            CodeError::Compile(message) => TransactionError::InvalidCode {
+                code: hash,
+                message,
+            },
+            CodeError::NotFound(code) => TransactionError::CodeNotFound { code },
+        })
+    }
+
+    fn replay_utxo(
+        &mut self,
+        utxo_id: UtxoId,
+        code: &Arc<ContractCode>,
+        snapshot: &UtxoSnapshot,
+    ) -> Result<Value, TransactionError> {
+        // Mint the tokens again under the handles the UTXO knows them by.
+        for bound in &snapshot.token_binds {
+            let token_code = self.load_code(bound.code)?;
+            let linker = token_linker(self.store.engine(), &token_code)?;
+            let program = ProgramIdx(self.store.data().programs.len());
+            let (from_program, result) = self.start_program(
+                ProgramIdx::Root,
+                &linker,
+                &token_code,
+                &bound.entry_point,
+                bound.inputs.clone(),
+            );
+            self.run_scheduler(None, from_program, result)?;
+
+            let token_id = TokenId { bytes: bound.id };
+            let token = Token {
+                program,
+                token_type_id: bound.token_type_id,
+                amount: bound.amount,
+            };
+            let data = self.store.data_mut();
+            data.tokens.insert(token_id, (None, token));
+            data.temporary_token_ids
+                .insert(ScrambledRef::from_i64(bound.handle), token_id);
+        }
+
+        let (from_program, result) = self.start_utxo_as(
+            utxo_id,
+            ProgramIdx::Root,
+            code,
+            &snapshot.entry_point,
+            snapshot.inputs.clone(),
+        );
+        let handle = self.run_scheduler(None, from_program, result)?;
+
+        for call in &snapshot.calls {
+            let (from_program, result) = match call {
+                UtxoCall::Resume {
+                    values,
+                    write_to_memory,
+                } => {
+                    let program = self.live_utxo_program(utxo_id)?;
+                    self.store.data_mut().programs[program.0].return_to = ProgramIdx::Root;
+                    self.store.data_mut().programs[program.0].yield_to = Some(ProgramIdx::Root);
+                    self.store
+                        .data_mut()
+                        .utxos
+                        .get_mut(&utxo_id)
+                        .unwrap()
+                        .calls
+                        .push(call.clone());
+
+                    self.resume(
+                        ProgramIdx::Root,
+                        program,
+                        values.clone(),
+                        vec![],
+                        write_to_memory.clone(),
+                    )
+                }
+                UtxoCall::Mutate { method, values } => {
+                    self.mutate_utxo(ProgramIdx::Root, utxo_id, method.clone(), values.clone())?
+                }
+            };
+            self.run_scheduler(None, from_program, result)?;
+        }
+
+        let program = self.live_utxo_program(utxo_id)?;
+        let at_yield = matches!(
+            self.store.data().programs[program.0].interrupt(),
+            Some(Interrupt::Yield { resume_arg, resume_arg_len, .. })
+                if *resume_arg == snapshot.resume_arg && *resume_arg_len == snapshot.resume_arg_len
+        );
+        if !at_yield
+            || self.program_memory(program)?.data(&self.store) != snapshot.memory
+            || self.program_globals(program) != snapshot.globals
+        {
+            debug!("replay of {utxo_id:?} diverged from its snapshot");
+            return Err(TransactionError::SnapshotMismatch { utxo: utxo_id });
+        }
+
+        Ok(handle)
+    }
+
+    /// Drive a single raise/handle/resume cycle for the effect `name`, without
+    /// a program raising it.
+    ///
+    /// The most recently registered handler receives `input` as the raised
+    /// data, and the `output_len` bytes it resumes with are returned once the
+    /// handler has run to completion. Meant for unit testing effect handlers.
+    ///
+    /// Like [`Transaction::run_coordination_script`], a handler that fails
+    /// leaves no trace in the transaction, and the program it ran in can't be
+    /// called again.
+    pub fn simulate_effect(
+        &mut self,
+        name: &str,
+        input: &[u8],
+        output_len: u32,
+    ) -> Result<Vec<u8>, TransactionError> {
+        debug!("simulate_effect({name:?}, {})", DisplayHex(input));
+
+        let (handler_program, handler_address, method) =
+            self.store.data().effect_handler(name).ok_or_else(|| {
+                TransactionError::UnhandledEffect {
+                    name: name.to_owned(),
+                }
+            })?;
+
+        let checkpoint = self.store.data_mut().checkpoint();
+        self.store.data_mut().simulated_effect = Some(SimulatedEffect {
+            input: input.to_vec(),
+            output_len,
+            output: None,
+        });
+        self.store
+            .data_mut()
+            .raised_effects
+            .push((name.to_owned(), ProgramIdx::Root));
+
+        let (from_program, result) = self.call_method(
+            ProgramIdx::Root,
+            handler_program,
+            method,
+            vec![Value::I32(handler_address as i32)],
+        );
+        let mut outcome = self.run_scheduler(None, from_program, result);
+
+        let simulated = self.store.data_mut().simulated_effect.take().unwrap();
+        if let Err(error) = &mut outcome {
+            // Like a failed top-level call, the failed handler leaves no
+            // trace, its raise included.
+            self.store.data_mut().rollback(checkpoint, error);
+        } else if simulated.output.is_none() {
+            // Nothing is left to resume, so forget the raise.
+            self.store.data_mut().take_pending_raise(name);
+        }
+        outcome?;
+
+        simulated
+            .output
+            .ok_or_else(|| TransactionError::EffectNotResumed {
+                name: name.to_owned(),
+            })
+    }
+
+    /// The public keys this transaction is signed by.
+    pub fn signers(&self) -> &[PublicKey] {
+        &self.store.data().signers
+    }
+
+    /// The ID of a UTXO externref returned from this transaction.
+    pub fn utxo_id(&self, utxo: &Value) -> Option<UtxoId> {
+        UtxoId::from_wasm_externref(utxo, self.store.as_context())
+    }
+
+    /// The field `name` of the struct `utxo_id` is yielding, if it registered
+    /// the struct's layout with `starstream_yield_layout` before yielding.
+    pub fn peek_yield_field(&self, utxo_id: UtxoId, name: &str) -> Option<Vec<u8>> {
+        let program = self.store.data().utxos.get(&utxo_id)?.program;
+        let tx_program = &self.store.data().programs[program.0];
+        let Some(Interrupt::Yield { data, .. }) = tx_program.interrupt() else {
+            return None;
+        };
+        let field = tx_program
+            .yield_layout
+            .iter()
+            .find(|field| field.name == name)?;
+        self.try_read_program_memory(
+            program,
+            data.checked_add(field.offset)?,
+            field.size as usize,
+        )
+        .ok()
+    }
+
+    /// Every yield and resume of `utxo_id` in this transaction so far, in
+    /// order.
+    ///
+    /// Reconstructed from the witnesses: a yield is a witness out of the
+    /// UTXO's program that reads its memory, and the next witness back into
+    /// that program is the matching resume.
+    pub fn utxo_history(&self, utxo_id: UtxoId) -> Vec<UtxoEvent> {
+        let data = self.store.data();
+        let Some(utxo) = data.utxos.get(&utxo_id) else {
+            return vec![];
+        };
+
+        let mut history = vec![];
+        let mut yielded = false;
+        for witness in &data.witnesses {
+            if witness.from_program == utxo.program && !witness.read_from_memory.is_empty() {
+                history.push(UtxoEvent::Yielded(
+                    witness
+                        .read_from_memory
+                        .iter()
+                        .flat_map(|segment| segment.data.iter().copied())
+                        .collect(),
+                ));
+                yielded = true;
+            } else if witness.to_program == utxo.program && yielded {
+                history.push(UtxoEvent::Resumed(
+                    witness
+                        .write_to_memory
+                        .iter()
+                        .flat_map(|segment| segment.data.iter().copied())
+                        .collect(),
+                ));
+                yielded = false;
+            }
+        }
+        history
+    }
+
+    /// Snapshot the output and liveness of every UTXO in the transaction so
+    /// far, for use with [`Transaction::diff_utxo_state`].
+    pub fn utxo_state(&self) -> UtxoState {
+        let data = self.store.data();
+
+        UtxoState {
+            utxos: data
+                .utxos
+                .iter()
+                .map(|(id, utxo)| {
+                    let snapshot = UtxoOutputState {
+                        output: data
+                            .utxo_outputs
+                            .get(id)
+                            .and_then(|outputs| outputs.last())
+                            .copied(),
+                        consumed: data.programs[utxo.program.0].interrupt().is_none(),
+                    };
+                    (*id, snapshot)
+                })
+                .collect(),
+        }
+    }
+
+    /// The UTXOs created, updated or consumed between `before` and `after`.
+    pub fn diff_utxo_state(before: &UtxoState, after: &UtxoState) -> BTreeMap<UtxoId, UtxoChange> {
+        after
+            .utxos
+            .iter()
+            .filter_map(|(id, snapshot)| {
+                let previous = before.utxos.get(id);
+                let output_before = previous.and_then(|previous| previous.output);
+
+                if previous.is_some_and(|previous| previous.consumed) {
+                    return None;
+                }
+
+                let change = UtxoChange {
+                    output_before,
+                    output_after: snapshot.output.filter(|_| !snapshot.consumed),
+                    consumed: snapshot.consumed,
+                };
+
+                let unchanged = previous.is_some()
+                    && !change.consumed
+                    && change.output_before == change.output_after;
+
+                (!unchanged).then_some((*id, change))
+            })
+            .collect()
+    }
+
+    /// Main effect scheduler loop. Runs until control returns to the root.
+    fn run_scheduler(
+        &mut self,
+        coordination_code: Option<&Arc<ContractCode>>,
+        mut from_program: ProgramIdx,
+        mut result: Result<Vec<Value>, Interrupt>,
+    ) -> Result<Value, TransactionError> {
         loop {
+            if self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                debug!("{from_program:?} was interrupted by the deadline");
+                return Err(TransactionError::Timeout);
+            }
+
             (from_program, result) = match result {
                 // ------------------------------------------------------------
                 // Entry point returned
                 Ok(mut values) => {
                     // Program returned.
+                    let snapshot = self.store.data_mut().programs[from_program.0]
+                        .query_snapshot
+                        .take();
+                    if let Some(MemorySegment { address, data }) = snapshot {
+                        if self.try_read_program_memory(from_program, address, data.len())? != data
+                        {
+                            let utxo = self.store.data().programs[from_program.0].utxo.unwrap();
+                            debug!("{from_program:?} was a query, but changed {utxo:?}");
+                            return Err(TransactionError::QueryMutatedState { utxo });
+                        }
+                    }
+
                     let to_program = self.store.data_mut().programs[from_program.0].return_to;
                     if to_program == ProgramIdx::Root {
                         debug!("{from_program:?} -> {to_program:?}: {values:?}");
@@ -1370,6 +3405,7 @@ impl Transaction {
                         let to_state_before = self.hash_program(to_program);
                         self.store.data_mut().witnesses.push(TxWitness {
                             fuel,
+                            program_fuel: 0,
                             from_program,
                             from_state_after,
                             to_program: ProgramIdx::Root,
@@ -1382,7 +3418,7 @@ impl Transaction {
                             is_destroy: true,
                         });
 
-                        return result;
+                        return Ok(result);
                     }
 
                     if let Some(token_id) =
@@ -1391,13 +3427,71 @@ impl Transaction {
                         values = vec![token_id.to_wasm_i64(self.store.as_context_mut())];
                     }
 
+                    if let Some(utxo_id) = self.store.data().programs[from_program.0].consumes_utxo
+                    {
+                        // Whatever the consume method left bound outlives the
+                        // UTXO, unbound.
+                        let data = self.store.data_mut();
+                        let utxo = data.utxos.get_mut(&utxo_id).unwrap();
+                        let mut tokens = utxo.tokens.drain().map(|(id, _)| id).collect::<Vec<_>>();
+                        tokens.sort();
+                        data.consumed_tokens.insert(utxo_id, tokens);
+                    }
+
+                    if let Some(token_id) = self.store.data().programs[from_program.0].binds_token {
+                        // The bind function's status is passed back as-is so
+                        // the UTXO can act on a refusal.
+                        if matches!(values.first(), Some(Value::I32(0))) {
+                            let data = self.store.data();
+                            let utxo_id = data.programs[from_program.0].utxo.unwrap();
+                            let token = data.tokens[&token_id].1;
+                            self.bind_token(utxo_id, token_id, token)?;
+                        } else {
+                            debug!("{from_program:?} refused to bind {token_id:?}: {values:?}");
+                        }
+                    }
+
                     self.resume(from_program, to_program, values, vec![], vec![])
                 }
 
                 // ------------------------------------------------------------
                 // Common
+                Err(Interrupt::Abort) => {
+                    debug!("{from_program:?} called abort()");
+                    return Err(TransactionError::Aborted);
+                }
+                Err(Interrupt::MemoryBudgetExceeded) => {
+                    let budget = self.store.data().memory_budget.limit;
+                    debug!("{from_program:?} went over the memory budget of {budget} bytes");
+                    return Err(TransactionError::MemoryBudgetExceeded { budget });
+                }
+                Err(Interrupt::RequireFailed { code }) => {
+                    debug!("{from_program:?} failed a precondition with code {code}");
+                    return Err(TransactionError::RequireFailed { code });
+                }
+                Err(Interrupt::Failed(error)) => {
+                    debug!("{from_program:?} failed: {error}");
+                    return Err(error);
+                }
+                Err(Interrupt::OutOfFuel {
+                    program,
+                    code,
+                    entry_point,
+                }) => {
+                    debug!("{program:?} ran out of fuel in {entry_point}");
+                    return Err(TransactionError::OutOfFuel {
+                        program: program.map(|program| program.0),
+                        code,
+                        entry_point,
+                        fuel_consumed: self.store.fuel_consumed().unwrap(),
+                    });
+                }
                 Err(Interrupt::CoordinationCode { return_addr }) => {
                     let to_program = from_program;
+                    // Programs run without a coordination script, such as by
+                    // `new_utxo_direct`, see no code, like the caller of Root.
+                    let coordination_code =
+                        coordination_code.map_or([0; 32], |code| code.hash().raw());
                     self.resume(
                         from_program,
                         to_program,
@@ -1405,7 +3499,54 @@ impl Transaction {
                         vec![],
                         vec![MemorySegment {
                             address: return_addr,
-                            data: coordination_code.hash().raw().to_vec(),
+                            data: coordination_code.to_vec(),
+                        }],
+                    )
+                }
+                Err(Interrupt::DeclareBuffer { ptr, len }) => {
+                    let to_program = from_program;
+                    let owner = self.store.data().programs[from_program.0].instance_owner;
+                    self.store.data_mut().programs[owner.0]
+                        .buffers
+                        .push(ptr..ptr.saturating_add(len));
+                    self.resume(from_program, to_program, vec![], vec![], vec![])
+                }
+                Err(Interrupt::YieldLayout { fields }) => {
+                    let to_program = from_program;
+                    self.store.data_mut().programs[from_program.0].next_yield_layout = fields;
+                    self.resume(from_program, to_program, vec![], vec![], vec![])
+                }
+                Err(Interrupt::Event { name, data }) => {
+                    let to_program = from_program;
+                    self.store.data_mut().events.push(Event {
+                        name,
+                        data,
+                        program: from_program,
+                    });
+                    self.resume(from_program, to_program, vec![], vec![], vec![])
+                }
+                Err(Interrupt::UndeclaredMemoryWrite { address, len }) => {
+                    debug!("{from_program:?} was written {len} bytes at {address:#x}");
+                    return Err(TransactionError::UndeclaredMemoryWrite { address, len });
+                }
+                Err(Interrupt::CallerCode { return_addr }) => {
+                    let to_program = from_program;
+                    // The top-level coordination script was called by the
+                    // transaction itself, which has no code.
+                    let caller = self.store.data().programs[from_program.0].return_to;
+                    let caller_code = if caller == ProgramIdx::Root {
+                        [0; 32]
+                    } else {
+                        self.store.data().programs[caller.0].code.raw()
+                    };
+                    self.resume(
+                        from_program,
+                        to_program,
+                        vec![],
+                        vec![],
+                        vec![MemorySegment {
+                            address: return_addr,
+                            data: caller_code.to_vec(),
                         }],
                     )
                 }
@@ -1424,18 +3565,27 @@ impl Transaction {
                 Err(Interrupt::UnRegisterEffectHandler { name }) => {
                     let to_program = from_program;
 
-                    let effect_handlers = &mut self
+                    // Unregistering a handler the program never registered
+                    // is a contract bug.
+                    let Some(effect_handlers) = self
                         .store
                         .data_mut()
                         .registered_effect_handler
                         .get_mut(&name)
-                        .unwrap();
+                    else {
+                        return Err(TransactionError::BadInterruptState {
+                            program: from_program.0,
+                        });
+                    };
 
-                    let (index, _) = effect_handlers
+                    let Some(index) = effect_handlers
                         .iter()
-                        .enumerate()
-                        .find(|(_index, (program, _))| *program == from_program)
-                        .unwrap();
+                        .position(|(program, _)| *program == from_program)
+                    else {
+                        return Err(TransactionError::BadInterruptState {
+                            program: from_program.0,
+                        });
+                    };
 
                     effect_handlers.remove(index);
 
@@ -1444,30 +3594,61 @@ impl Transaction {
                 Err(Interrupt::GetRaisedEffectData {
                     name,
                     output_ptr_data,
+                    output_len,
                     not_null,
                 }) => {
                     let to_program = from_program;
 
-                    let throwing_program = self.store.data().raised_effects.get(&name);
+                    let throwing_program = self.store.data().pending_raise(&name);
 
                     let mut write_to_memory = vec![];
 
-                    if let Some(throwing_program) = throwing_program {
+                    if throwing_program == Some(ProgramIdx::Root) {
+                        let simulated = self.store.data().simulated_effect.as_ref().unwrap();
+                        if simulated.input.len() != output_len as usize {
+                            return Err(TransactionError::EffectDataMismatch {
+                                effect: name,
+                                data_len: simulated.input.len() as u32,
+                                output_len,
+                            });
+                        }
+
+                        write_to_memory.push(MemorySegment {
+                            address: not_null,
+                            data: vec![1u8],
+                        });
+
+                        write_to_memory.push(MemorySegment {
+                            address: output_ptr_data,
+                            data: simulated.input.clone(),
+                        });
+                    } else if let Some(throwing_program) = throwing_program {
                         let (data, data_len) =
                             match self.store.data().programs[throwing_program.0].interrupt() {
                                 Some(Interrupt::Raise { data, data_len, .. }) => (*data, *data_len),
-                                other => panic!("program didn't throw {other:?}"),
+                                _ => {
+                                    return Err(TransactionError::BadInterruptState {
+                                        program: throwing_program.0,
+                                    });
+                                }
                             };
 
-                        let throwed_data = self.store.data().programs[throwing_program.0]
-                            .instance
-                            .get_export(&self.store, "memory")
-                            .unwrap()
-                            .into_memory()
-                            .unwrap()
-                            .data(&self.store)
-                            [data as usize..data as usize + data_len as usize]
-                            .to_vec();
+                        let Ok(throwed_data) =
+                            self.try_read_program_memory(throwing_program, data, data_len as usize)
+                        else {
+                            return Err(TransactionError::EffectDataOutOfBounds {
+                                effect: name,
+                                data,
+                                data_len,
+                            });
+                        };
+                        if data_len != output_len {
+                            return Err(TransactionError::EffectDataMismatch {
+                                effect: name,
+                                data_len,
+                                output_len,
+                            });
+                        }
 
                         write_to_memory.push(MemorySegment {
                             address: not_null,
@@ -1487,14 +3668,75 @@ impl Transaction {
 
                     self.resume(from_program, to_program, vec![], vec![], write_to_memory)
                 }
+                Err(Interrupt::GetRaisedEffectName {
+                    output_ptr,
+                    output_cap,
+                }) => {
+                    let to_program = from_program;
+
+                    let name = self
+                        .store
+                        .data()
+                        .handled_raise(from_program)
+                        .unwrap_or_default()
+                        .to_owned();
+                    let len = name.len().min(output_cap as usize);
+
+                    self.resume(
+                        from_program,
+                        to_program,
+                        vec![Value::I32(name.len() as i32)],
+                        vec![],
+                        vec![MemorySegment {
+                            address: output_ptr,
+                            data: name.as_bytes()[..len].to_vec(),
+                        }],
+                    )
+                }
                 Err(Interrupt::ResumeThrowingProgram {
                     name,
                     input_ptr_data,
                 }) => {
-                    let throwing_program =
-                        self.store.data_mut().raised_effects.remove(&name).unwrap();
+                    let Some(throwing_program) = self.store.data_mut().take_pending_raise(&name)
+                    else {
+                        return Err(TransactionError::BadInterruptState {
+                            program: from_program.0,
+                        });
+                    };
                     let to_program = throwing_program;
 
+                    if throwing_program == ProgramIdx::Root {
+                        // Raised by simulate_effect, so there is nothing to
+                        // resume. Keep the output to hand back, and let the
+                        // handler run to completion as if the raiser had
+                        // given control back to it.
+                        let output_len = self
+                            .store
+                            .data()
+                            .simulated_effect
+                            .as_ref()
+                            .unwrap()
+                            .output_len;
+
+                        let output = self.try_read_program_memory(
+                            from_program,
+                            input_ptr_data,
+                            output_len as usize,
+                        )?;
+
+                        self.store
+                            .data_mut()
+                            .simulated_effect
+                            .as_mut()
+                            .unwrap()
+                            .output
+                            .replace(output);
+
+                        (from_program, result) =
+                            self.resume(from_program, from_program, vec![], vec![], vec![]);
+                        continue;
+                    }
+
                     let (output_ptr_data, data_len) =
                         match self.store.data().programs[throwing_program.0].interrupt() {
                             Some(Interrupt::Raise {
@@ -1502,32 +3744,37 @@ impl Transaction {
                                 resume_arg_len,
                                 ..
                             }) => (*resume_arg, *resume_arg_len),
-                            other => panic!("program didn't throw {other:?}"),
+                            _ => {
+                                return Err(TransactionError::BadInterruptState {
+                                    program: throwing_program.0,
+                                });
+                            }
                         };
 
-                    let caller_memory = self.store.data().programs[from_program.0]
-                        .instance
-                        .get_export(&self.store, "memory")
-                        .unwrap()
-                        .into_memory()
-                        .unwrap()
-                        .data(&self.store)
-                        [input_ptr_data as usize..input_ptr_data as usize + data_len as usize]
-                        // TODO: needed to avoid double borrow on the store
-                        // can we avoid this?
-                        .to_vec();
-
-                    let resumed_program_memory = self.store.data().programs[to_program.0]
-                        .instance
-                        .get_export(&self.store, "memory")
-                        .unwrap()
-                        .into_memory()
-                        .unwrap()
-                        .data_mut(&mut self.store);
+                    // TODO: copied to avoid double borrow on the store
+                    // can we avoid this?
+                    let caller_memory = self.try_read_program_memory(
+                        from_program,
+                        input_ptr_data,
+                        data_len as usize,
+                    )?;
+
+                    let resumed_program_memory =
+                        self.program_memory(to_program)?.data_mut(&mut self.store);
 
-                    resumed_program_memory
-                        [output_ptr_data as usize..output_ptr_data as usize + data_len as usize]
-                        .copy_from_slice(&caller_memory);
+                    if wasm_abi::write_bytes(
+                        resumed_program_memory,
+                        output_ptr_data,
+                        &caller_memory,
+                    )
+                    .is_err()
+                    {
+                        return Err(TransactionError::MemoryOutOfBounds {
+                            program: to_program.0,
+                            address: output_ptr_data,
+                            len: caller_memory.len(),
+                        });
+                    }
 
                     self.resume(from_program, to_program, vec![], vec![], vec![])
                 }
@@ -1539,28 +3786,10 @@ impl Transaction {
                     inputs,
                 }) => {
                     let code = self.code_cache.get(code_hash);
-                    let linker = utxo_linker(self.store.engine(), &code);
-                    let id = UtxoId::random();
-
-                    let (to_program, result) =
-                        self.start_program(from_program, &linker, &code, &entry_point, inputs);
-                    self.store.data_mut().programs[to_program.0].yield_to = Some(from_program);
-
-                    self.store.data_mut().programs[to_program.0].yield_to_constructor =
-                        Some(id.to_wasm_i64(self.store.as_context_mut()));
-
-                    self.store.data_mut().programs[to_program.0].utxo = Some(id);
-                    self.store.data_mut().utxos.insert(
-                        id,
-                        Utxo {
-                            program: to_program,
-                            tokens: Default::default(),
-                        },
-                    );
-                    (to_program, result)
+                    self.start_utxo(from_program, &code, &entry_point, inputs)
                 }
                 Err(Interrupt::UtxoResume { utxo_id, inputs }) => {
-                    let to_program = self.store.data().utxos[&utxo_id].program;
+                    let to_program = self.live_utxo_program(utxo_id)?;
 
                     // TODO: I think this is correct if the utxo is resumed
                     // from a coordination script, because there is a chance the
@@ -1579,93 +3808,190 @@ impl Transaction {
                                 resume_arg_len,
                                 ..
                             }) => (*resume_arg, *resume_arg_len),
-                            other => panic!("cannot query a UTXO in state {other:?}"),
+                            _ => {
+                                return Err(TransactionError::BadInterruptState {
+                                    program: to_program.0,
+                                });
+                            }
                         };
 
+                    // The pointer to the resume argument comes last. A UTXO
+                    // resumed with `()` has nothing to copy and may be resumed
+                    // without one.
                     let inputs_len = inputs.len();
-                    let copy_from = match inputs[inputs_len - 1] {
-                        Value::I32(n) => n as usize,
-                        Value::I64(n) => n as usize,
-                        _ => panic!("Expected pointer as the first argument in UtxoResume"),
+                    let write_to_memory = if resume_len == 0 {
+                        vec![]
+                    } else {
+                        let copy_from = match inputs.get(1..).and_then(|rest| rest.last()) {
+                            Some(Value::I32(n)) => *n as u32,
+                            Some(Value::I64(n)) => *n as u32,
+                            // The caller didn't pass the argument the UTXO
+                            // is waiting for.
+                            _ => {
+                                return Err(TransactionError::BadInterruptState {
+                                    program: from_program.0,
+                                });
+                            }
+                        };
+
+                        let caller_memory_data = self.try_read_program_memory(
+                            from_program,
+                            copy_from,
+                            resume_len as usize,
+                        )?;
+
+                        vec![MemorySegment {
+                            address: resume_arg,
+                            data: caller_memory_data,
+                        }]
                     };
 
-                    let caller_memory_data = self.store.data().programs[from_program.0]
-                        .instance
-                        .get_export(&self.store, "memory")
-                        .unwrap()
-                        .into_memory()
+                    let values: Vec<Value> = inputs
+                        .into_iter()
+                        .skip(1)
+                        .take(inputs_len.saturating_sub(2))
+                        .collect();
+                    self.store
+                        .data_mut()
+                        .utxos
+                        .get_mut(&utxo_id)
                         .unwrap()
-                        .data(&self.store)[copy_from..copy_from + resume_len as usize]
-                        .to_vec();
-
-                    let write_to_memory = vec![MemorySegment {
-                        address: resume_arg,
-                        data: caller_memory_data,
-                    }];
+                        .calls
+                        .push(UtxoCall::Resume {
+                            values: values.clone(),
+                            write_to_memory: write_to_memory.clone(),
+                        });
 
-                    self.resume(
-                        from_program,
-                        to_program,
-                        inputs.into_iter().skip(1).take(inputs_len - 2).collect(),
-                        vec![],
-                        write_to_memory,
-                    )
+                    self.resume(from_program, to_program, values, vec![], write_to_memory)
                 }
                 Err(Interrupt::UtxoQuery {
                     utxo_id,
                     method,
                     mut inputs,
                 }) => {
-                    let to_program = self.store.data().utxos[&utxo_id].program;
+                    let to_program = self.live_utxo_program(utxo_id)?;
 
+                    let yielded = match self.store.data().programs[to_program.0].interrupt() {
+                        Some(Interrupt::Yield { data, data_len, .. }) => Some((*data, *data_len)),
+                        _ => None,
+                    };
                     if self.rust_compat {
                         // Insert address of yielded object.
-                        let address = match self.store.data().programs[to_program.0].interrupt() {
-                            Some(Interrupt::Yield { data, .. }) => *data,
-                            other => panic!("cannot query a UTXO in state {other:?}"),
+                        let Some((address, _)) = yielded else {
+                            return Err(TransactionError::BadInterruptState {
+                                program: to_program.0,
+                            });
                         };
                         inputs.insert(0, Value::I32(address as i32));
                     }
-                    self.call_method(from_program, to_program, method, inputs)
-                    // TODO: either enforce non-mutation or drop the query/mutate split
+
+                    // Queries may only read the yielded object, which is
+                    // checked once the query returns.
+                    let snapshot = match yielded {
+                        Some((address, len)) => Some(MemorySegment {
+                            address,
+                            data: self.try_read_program_memory(
+                                to_program,
+                                address,
+                                len as usize,
+                            )?,
+                        }),
+                        None => None,
+                    };
+                    let (query_program, result) =
+                        self.call_method(from_program, to_program, method, inputs);
+                    if query_program != to_program {
+                        self.store.data_mut().programs[query_program.0].query_snapshot = snapshot;
+                    }
+                    (query_program, result)
                 }
-                Err(Interrupt::UtxoMutate {
+                Err(Interrupt::UtxoQueryDirect {
                     utxo_id,
                     method,
                     mut inputs,
                 }) => {
-                    let to_program = self.store.data().utxos[&utxo_id].program;
+                    self.check_direct_query(from_program, utxo_id)?;
+                    let to_program = self.live_utxo_program(utxo_id)?;
 
-                    // Insert address of yielded object.
+                    // Only a UTXO that yielded is at rest. One suspended on a
+                    // raise or a call is halfway through its own code.
                     let address = match self.store.data().programs[to_program.0].interrupt() {
                         Some(Interrupt::Yield { data, .. }) => *data,
-                        other => panic!("cannot mutate a UTXO in state {other:?}"),
+                        _ => {
+                            return Err(TransactionError::BadInterruptState {
+                                program: to_program.0,
+                            });
+                        }
                     };
-                    inputs.insert(0, Value::I32(address as i32));
+                    if self.rust_compat {
+                        // Insert address of yielded object.
+                        inputs.insert(0, Value::I32(address as i32));
+                    }
+                    // The method returns straight back to the querying UTXO,
+                    // without passing through the coordination script.
                     self.call_method(from_program, to_program, method, inputs)
                 }
+                Err(Interrupt::UtxoMutate {
+                    utxo_id,
+                    method,
+                    inputs,
+                }) => self.mutate_utxo(from_program, utxo_id, method, inputs)?,
                 Err(Interrupt::UtxoConsume {
                     utxo_id,
                     method,
                     mut inputs,
                 }) => {
-                    let to_program = self.store.data().utxos[&utxo_id].program;
+                    let to_program = self.live_utxo_program(utxo_id)?;
 
                     // Insert address of yielded object.
                     let address = match self.store.data().programs[to_program.0].interrupt() {
                         Some(Interrupt::Yield { data, .. }) => *data,
-                        other => panic!("cannot consume a UTXO in state {other:?}"),
+                        _ => {
+                            return Err(TransactionError::BadInterruptState {
+                                program: to_program.0,
+                            });
+                        }
                     };
                     inputs.insert(0, Value::I32(address as i32));
                     // Now throw away that object
                     self.store.data_mut().programs[to_program.0].resumable =
                         ResumableCall::Finished;
-                    self.call_method(from_program, to_program, method, inputs)
+
+                    let id = ProgramIdx(self.store.data().programs.len());
+                    let (to_program, result) =
+                        self.call_method(from_program, to_program, method, inputs);
+                    if let Some(program) = self.store.data_mut().programs.get_mut(id.0) {
+                        program.consumes_utxo = Some(utxo_id);
+                    }
+
+                    (to_program, result)
                 }
 
                 // ------------------------------------------------------------
                 // UTXOs can yield and call into tokens
-                Err(Interrupt::Yield { .. }) => {
+                Err(Interrupt::Yield { data, data_len, .. }) => {
+                    let output =
+                        self.try_read_program_memory(from_program, data, data_len as usize)?;
+
+                    let program = &mut self.store.data_mut().programs[from_program.0];
+                    program.yield_layout = std::mem::take(&mut program.next_yield_layout);
+
+                    if let Some(utxo_id) = self.store.data().programs[from_program.0].utxo {
+                        self.store
+                            .data_mut()
+                            .utxo_outputs
+                            .entry(utxo_id)
+                            .or_default()
+                            .push(output_commitment(&output));
+                    }
+
+                    // Yields always carry exactly one segment, even if it's
+                    // empty, which `utxo_history` relies on to find them.
+                    let read_from_memory = vec![MemorySegment {
+                        address: data,
+                        data: output,
+                    }];
+
                     let utxo_scrambled_id = self.store.data_mut().programs[from_program.0]
                         .yield_to_constructor
                         .take();
@@ -1680,20 +4006,48 @@ impl Transaction {
                         inputs.push(id);
                     }
 
-                    self.resume(from_program, to_program, inputs, vec![], vec![])
+                    if to_program == ProgramIdx::Root {
+                        // UTXO created by new_utxo_direct reached its first yield.
+                        let fuel = self.store.fuel_consumed().unwrap();
+                        let from_state_after = self.hash_program(from_program);
+                        let to_state_before = self.hash_program(to_program);
+                        self.store.data_mut().witnesses.push(TxWitness {
+                            fuel,
+                            program_fuel: 0,
+                            from_program,
+                            from_state_after,
+                            to_program,
+                            to_state_before,
+                            reply_to_witness: 0,
+                            values: inputs.clone(),
+                            read_from_memory,
+                            write_to_memory: Default::default(),
+                            is_create: false,
+                            is_destroy: false,
+                        });
+
+                        let utxo = inputs
+                            .first()
+                            .and_then(|id| UtxoId::from_wasm_i64(id, self.store.as_context()));
+
+                        return Ok(match utxo {
+                            Some(utxo) => utxo.to_wasm_externref(self.store.as_context_mut()),
+                            None => Value::I32(0),
+                        });
+                    }
+
+                    self.resume(from_program, to_program, inputs, read_from_memory, vec![])
                 }
                 Err(Interrupt::Raise { name, .. }) => {
-                    let (to_program, handler_address) =
-                        *self.store.data_mut().registered_effect_handler[&name]
-                            .last()
-                            .unwrap();
-
-                    let method = format!("{}_handle", name);
+                    let (to_program, handler_address, method) =
+                        self.store.data().effect_handler(&name).ok_or_else(|| {
+                            TransactionError::UnhandledEffect { name: name.clone() }
+                        })?;
 
                     self.store
                         .data_mut()
                         .raised_effects
-                        .insert(name, from_program);
+                        .push((name, from_program));
 
                     self.call_method(
                         from_program,
@@ -1722,29 +4076,33 @@ impl Transaction {
                     code,
                     entry_point,
                     inputs,
+                    amount,
                     token_type_id,
                 }) => {
                     let code = self.code_cache.get(code);
-                    let linker = token_linker(self.store.engine(), &code);
-                    let id = TokenId::random();
-
-                    let amount = match &inputs[0] {
-                        Value::I64(amount) => *amount as u64,
-                        _ => unreachable!(),
-                    };
+                    let linker = token_linker(self.store.engine(), &code)?;
+                    let id = TokenId::random(&mut self.store.data_mut().ids);
 
                     let (to_program, result) =
                         self.start_program(from_program, &linker, &code, &entry_point, inputs);
+                    // If the token's program never started, the failure goes
+                    // to the scheduler with nothing minted.
+                    if !matches!(
+                        result,
+                        Err(Interrupt::MemoryBudgetExceeded
+                            | Interrupt::OutOfFuel { .. }
+                            | Interrupt::Failed(_))
+                    ) {
+                        let token = Token {
+                            program: to_program,
+                            token_type_id,
+                            amount,
+                        };
 
-                    let token = Token {
-                        program: to_program,
-                        token_type_id,
-                        amount,
-                    };
-
-                    self.store.data_mut().tokens.insert(id, (None, token));
+                        self.store.data_mut().tokens.insert(id, (None, token));
 
-                    self.store.data_mut().programs[to_program.0].return_is_token = Some(id);
+                        self.store.data_mut().programs[to_program.0].return_is_token = Some(id);
+                    }
 
                     (to_program, result)
                 }
@@ -1752,23 +4110,54 @@ impl Transaction {
                     entry_point,
                     inputs,
                     token_id,
+                    fallible,
                 }) => {
-                    let utxo_id = self.store.data_mut().programs[from_program.0].utxo.unwrap();
-                    let (_, token) = self.store.data_mut().tokens.get(&token_id).unwrap();
+                    let Some(utxo_id) = self.store.data().programs[from_program.0].utxo else {
+                        return Err(TransactionError::BadInterruptState {
+                            program: from_program.0,
+                        });
+                    };
+                    let (_, token) = self
+                        .store
+                        .data()
+                        .tokens
+                        .get(&token_id)
+                        .ok_or(TransactionError::InvalidTokenHandle)?;
                     let token = *token;
 
+                    // Loading a snapshot of the UTXO mints the token again
+                    // under the same handle.
+                    if let Some(&Value::I64(handle)) = inputs.first() {
+                        let minted = &self.store.data().programs[token.program.0];
+                        let bound = BoundToken {
+                            handle,
+                            id: token_id.bytes,
+                            code: minted.code,
+                            entry_point: minted.entry_point.clone(),
+                            inputs: minted.inputs.clone(),
+                            token_type_id: token.token_type_id,
+                            amount: token.amount,
+                        };
+                        self.store
+                            .data_mut()
+                            .utxos
+                            .get_mut(&utxo_id)
+                            .unwrap()
+                            .token_binds
+                            .push(bound);
+                    }
+
                     let entry_point = format!("{}_{}", entry_point, token.token_type_id);
 
                     let (to_program, result) =
                         self.call_method(from_program, from_program, entry_point, inputs);
 
-                    self.store
-                        .data_mut()
-                        .utxos
-                        .get_mut(&utxo_id)
-                        .unwrap()
-                        .tokens
-                        .insert(token_id, token);
+                    if fallible {
+                        // Bound when the bind function returns successfully.
+                        self.store.data_mut().programs[to_program.0].binds_token = Some(token_id);
+                    } else {
+                        self.bind_token(utxo_id, token_id, token)?;
+                    }
 
                     (to_program, result)
                 }
@@ -1777,7 +4166,11 @@ impl Transaction {
                     entry_point: unbind_fn,
                 }) => {
                     // assume that only the utxo that owns the token can unbind it?
-                    let utxo_id = self.store.data_mut().programs[from_program.0].utxo.unwrap();
+                    let Some(utxo_id) = self.store.data().programs[from_program.0].utxo else {
+                        return Err(TransactionError::BadInterruptState {
+                            program: from_program.0,
+                        });
+                    };
 
                     let token = self
                         .store
@@ -1787,7 +4180,7 @@ impl Transaction {
                         .unwrap()
                         .tokens
                         .remove(&token_id)
-                        .unwrap();
+                        .ok_or(TransactionError::TokenNotBound { utxo: utxo_id })?;
 
                     let entry_point = format!("{}_{}", unbind_fn, token.token_type_id);
 
@@ -1798,28 +4191,72 @@ impl Transaction {
 
                     let data_mut = self.store.data_mut();
 
-                    if let Some((utxo, _)) = data_mut.tokens.remove(&token_id) {
-                        assert!(utxo.is_none(), "can't burn token without unbinding first");
+                    if let Some((Some(utxo), _)) = data_mut.tokens.get(&token_id) {
+                        return Err(TransactionError::TokenStillBound { utxo: *utxo });
                     }
+                    data_mut.tokens.remove(&token_id);
 
                     self.resume(from_program, to_program, vec![], vec![], vec![])
                 }
+                Err(Interrupt::TokenBurnBound {
+                    token_id,
+                    entry_point: burn_fn,
+                }) => {
+                    // only the utxo that holds the token can burn it
+                    let Some(utxo_id) = self.store.data().programs[from_program.0].utxo else {
+                        return Err(TransactionError::BadInterruptState {
+                            program: from_program.0,
+                        });
+                    };
+
+                    let token = self
+                        .store
+                        .data_mut()
+                        .utxos
+                        .get_mut(&utxo_id)
+                        .unwrap()
+                        .tokens
+                        .remove(&token_id)
+                        .ok_or(TransactionError::TokenNotBound { utxo: utxo_id })?;
+                    self.store.data_mut().tokens.remove(&token_id);
+
+                    // The token's own code decides whether the burn is valid.
+                    let code = self.store.data().programs[token.program.0].code;
+                    let code = self.code_cache.get(code);
+                    let linker = token_linker(self.store.engine(), &code)?;
+                    let entry_point = format!("{}_{}", burn_fn, token.token_type_id);
+                    let inputs = vec![
+                        token_id.to_wasm_i64(self.store.as_context_mut()),
+                        Value::I64(token.amount as i64),
+                    ];
+
+                    self.start_program(from_program, &linker, &code, &entry_point, inputs)
+                }
                 Err(Interrupt::TokenSpend { token_id, amount }) => {
                     let to_program = from_program;
 
                     let data_mut = self.store.data_mut();
 
-                    let new_token_id = TokenId::random();
-                    if let Some((utxo, token)) = data_mut.tokens.get_mut(&token_id) {
-                        assert!(utxo.is_none(), "can't split token without unbinding first");
+                    let new_token_id = TokenId::random(&mut data_mut.ids);
+                    let (utxo, token) = data_mut
+                        .tokens
+                        .get_mut(&token_id)
+                        .ok_or(TransactionError::InvalidTokenHandle)?;
+                    if let Some(utxo) = utxo {
+                        return Err(TransactionError::TokenStillBound { utxo: *utxo });
+                    }
 
-                        let mut new_token = *token;
-                        new_token.amount = amount;
+                    let mut new_token = *token;
+                    new_token.amount = amount;
 
-                        token.amount = token.amount.checked_sub(amount).unwrap();
+                    token.amount = token.amount.checked_sub(amount).ok_or(
+                        TransactionError::TokenAmountExceeded {
+                            amount: token.amount,
+                            requested: amount,
+                        },
+                    )?;
 
-                        data_mut.tokens.insert(new_token_id, (None, new_token));
-                    }
+                    data_mut.tokens.insert(new_token_id, (None, new_token));
 
                     let new_token_id = new_token_id.to_wasm_i64(self.store.as_context_mut());
 
@@ -1830,9 +4267,11 @@ impl Transaction {
                     data_len,
                     skip,
                 }) => {
-                    let to_program = from_program;
-
-                    let utxo_id = self.store.data().programs[from_program.0].utxo.unwrap();
+                    let Some(utxo_id) = self.store.data().programs[from_program.0].utxo else {
+                        return Err(TransactionError::BadInterruptState {
+                            program: from_program.0,
+                        });
+                    };
 
                     let tokens = {
                         let utxo = &self.store.data().utxos[&utxo_id];
@@ -1844,36 +4283,181 @@ impl Transaction {
                         tokens_sorted
                     };
 
-                    let mut count = 0;
-                    let mut raw = vec![];
+                    self.resume_with_token_ids(from_program, &tokens, data, data_len, skip)
+                }
+                Err(Interrupt::CollectTokens {
+                    utxo_id,
+                    data,
+                    data_len,
+                    skip,
+                }) => {
+                    // Empty until the UTXO is consumed.
+                    let tokens = self
+                        .store
+                        .data()
+                        .consumed_tokens
+                        .get(&utxo_id)
+                        .cloned()
+                        .unwrap_or_default();
 
-                    for token_id in tokens.iter().skip(skip as usize).take(data_len as usize) {
-                        let i = token_id.to_wasm_i64(self.store.as_context_mut());
+                    self.resume_with_token_ids(from_program, &tokens, data, data_len, skip)
+                }
+            }
+        }
+    }
 
-                        let i = match i {
-                            Value::I64(i) => i as u64,
-                            _ => unreachable!(),
-                        };
+    /// Write handles to `tokens`, from `skip` on, into the `data_len` slots
+    /// at `data`, and resume `program` with how many were written.
+    fn resume_with_token_ids(
+        &mut self,
+        program: ProgramIdx,
+        tokens: &[TokenId],
+        data: u32,
+        data_len: u32,
+        skip: u32,
+    ) -> (ProgramIdx, Result<Vec<Value>, Interrupt>) {
+        let mut count = 0;
+        let mut raw = vec![];
 
-                        raw.extend_from_slice(&i.to_le_bytes());
-                        count += 1;
-                    }
+        for token_id in tokens.iter().skip(skip as usize).take(data_len as usize) {
+            let i = token_id.to_wasm_i64(self.store.as_context_mut());
 
-                    let writes = vec![MemorySegment {
-                        address: data,
-                        data: raw,
-                    }];
+            raw.extend(wasm_abi::encode_value(&i).unwrap());
+            count += 1;
+        }
 
-                    self.resume(
-                        from_program,
-                        to_program,
-                        vec![Value::I32(count)],
-                        vec![],
-                        writes,
-                    )
-                }
+        let writes = vec![MemorySegment {
+            address: data,
+            data: raw,
+        }];
+
+        self.resume(program, program, vec![Value::I32(count)], vec![], writes)
+    }
+
+    /// Call the mutation `method` on the object `utxo_id` yielded, and note
+    /// the call for snapshots of the UTXO.
+    fn mutate_utxo(
+        &mut self,
+        from_program: ProgramIdx,
+        utxo_id: UtxoId,
+        method: String,
+        mut inputs: Vec<Value>,
+    ) -> Result<(ProgramIdx, Result<Vec<Value>, Interrupt>), TransactionError> {
+        let to_program = self.live_utxo_program(utxo_id)?;
+
+        // Insert address of yielded object.
+        let address = match self.store.data().programs[to_program.0].interrupt() {
+            Some(Interrupt::Yield { data, .. }) => *data,
+            _ => {
+                return Err(TransactionError::BadInterruptState {
+                    program: to_program.0,
+                });
+            }
+        };
+        self.store
+            .data_mut()
+            .utxos
+            .get_mut(&utxo_id)
+            .unwrap()
+            .calls
+            .push(UtxoCall::Mutate {
+                method: method.clone(),
+                values: inputs.clone(),
+            });
+
+        inputs.insert(0, Value::I32(address as i32));
+        Ok(self.call_method(from_program, to_program, method, inputs))
+    }
+
+    /// The program of a UTXO that can still be resumed.
+    fn live_utxo_program(&self, utxo_id: UtxoId) -> Result<ProgramIdx, TransactionError> {
+        let program = self.store.data().utxos[&utxo_id].program;
+        if self.store.data().programs[program.0].interrupt().is_none() {
+            return Err(TransactionError::UtxoAlreadyConsumed { utxo: utxo_id });
+        }
+        Ok(program)
+    }
+
+    /// Check that `from_program` may directly query `utxo_id`.
+    ///
+    /// Walks the chain of direct queries that led to `from_program`. The
+    /// target must not already be on it, including as the querying UTXO
+    /// itself, and the chain must stay within [`MAX_DIRECT_QUERY_DEPTH`].
+    fn check_direct_query(
+        &self,
+        from_program: ProgramIdx,
+        utxo_id: UtxoId,
+    ) -> Result<(), TransactionError> {
+        let programs = &self.store.data().programs;
+        let mut depth = 0;
+        let mut program = from_program;
+        while let Some(utxo) = programs[program.0].utxo {
+            if utxo == utxo_id {
+                return Err(TransactionError::DirectQueryReentrancy { utxo });
             }
+            let caller = programs[program.0].return_to;
+            if caller == ProgramIdx::Root || programs[caller.0].utxo.is_none() {
+                break;
+            }
+            depth += 1;
+            program = caller;
+        }
+        if depth >= MAX_DIRECT_QUERY_DEPTH {
+            return Err(TransactionError::DirectQueryTooDeep {
+                limit: MAX_DIRECT_QUERY_DEPTH,
+            });
         }
+        Ok(())
+    }
+
+    /// Copy `len` bytes at `offset` out of the memory of `program`.
+    ///
+    /// The range usually comes from the contract, so it may be out of bounds.
+    fn try_read_program_memory(
+        &self,
+        program: ProgramIdx,
+        offset: u32,
+        len: usize,
+    ) -> Result<Vec<u8>, TransactionError> {
+        let memory = self.program_memory(program)?;
+        wasm_abi::read_bytes(memory.data(&self.store), offset, len)
+            .map(<[u8]>::to_vec)
+            .map_err(|_| TransactionError::MemoryOutOfBounds {
+                program: program.0,
+                address: offset,
+                len,
+            })
+    }
+
+    fn program_memory(&self, program: ProgramIdx) -> Result<Memory, TransactionError> {
+        let tx_program = &self.store.data().programs[program.0];
+        tx_program
+            .instance
+            .get_export(&self.store, "memory")
+            .and_then(|export| export.into_memory())
+            .ok_or_else(|| TransactionError::MissingExport {
+                code: tx_program.code,
+                name: "memory".to_owned(),
+            })
+    }
+
+    /// The numeric globals a program exports, by name, as raw bits.
+    fn program_globals(&self, id: ProgramIdx) -> Vec<(String, i64)> {
+        let instance = self.store.data().programs[id.0].instance;
+        instance
+            .exports(&self.store)
+            .filter_map(|export| {
+                let name = export.name().to_owned();
+                let value = match export.into_global()?.get(&self.store) {
+                    Value::I32(n) => n as i64,
+                    Value::I64(n) => n,
+                    Value::F32(n) => n.to_bits() as i64,
+                    Value::F64(n) => n.to_bits() as i64,
+                    _ => return None,
+                };
+                Some((name, value))
+            })
+            .collect()
     }
 
     fn hash_program(&self, id: ProgramIdx) -> MemoryHash {
@@ -1884,6 +4468,59 @@ impl Transaction {
         }
     }
 
+    /// Instantiate a UTXO contract and register it under a fresh ID.
+    fn start_utxo(
+        &mut self,
+        from_program: ProgramIdx,
+        code: &Arc<ContractCode>,
+        entry_point: &str,
+        inputs: Vec<Value>,
+    ) -> (ProgramIdx, Result<Vec<Value>, Interrupt>) {
+        let id = UtxoId::random(&mut self.store.data_mut().ids);
+        self.start_utxo_as(id, from_program, code, entry_point, inputs)
+    }
+
+    /// Instantiate a UTXO contract and register it under `id`.
+    fn start_utxo_as(
+        &mut self,
+        id: UtxoId,
+        from_program: ProgramIdx,
+        code: &Arc<ContractCode>,
+        entry_point: &str,
+        inputs: Vec<Value>,
+    ) -> (ProgramIdx, Result<Vec<Value>, Interrupt>) {
+        let linker = match utxo_linker(self.store.engine(), code) {
+            Ok(linker) => linker,
+            Err(error) => return (from_program, Err(Interrupt::Failed(error))),
+        };
+
+        let (to_program, result) =
+            self.start_program(from_program, &linker, code, entry_point, inputs.clone());
+        if let Err(
+            Interrupt::MemoryBudgetExceeded | Interrupt::OutOfFuel { .. } | Interrupt::Failed(_),
+        ) = result
+        {
+            return (to_program, result);
+        }
+        self.store.data_mut().programs[to_program.0].yield_to = Some(from_program);
+
+        self.store.data_mut().programs[to_program.0].yield_to_constructor =
+            Some(id.to_wasm_i64(self.store.as_context_mut()));
+
+        self.store.data_mut().programs[to_program.0].utxo = Some(id);
+        self.store.data_mut().utxos.insert(
+            id,
+            Utxo {
+                program: to_program,
+                tokens: Default::default(),
+                inputs,
+                calls: vec![],
+                token_binds: vec![],
+            },
+        );
+        (to_program, result)
+    }
+
     /// Instantiate a new contract instance.
     fn start_program(
         &mut self,
@@ -1893,55 +4530,113 @@ impl Transaction {
         entry_point: &str,
         inputs: Vec<Value>,
     ) -> (ProgramIdx, Result<Vec<Value>, Interrupt>) {
-        let module = &code.module(self.store.engine());
-        let instance = linker
-            .instantiate(&mut self.store, module)
-            .unwrap()
-            .ensure_no_start(&mut self.store)
-            .unwrap();
+        let module = match code.module(self.store.engine()) {
+            Ok(module) => module,
+            Err(error) => return (from_program, Err(Interrupt::Failed(error))),
+        };
+        let instance = match linker.instantiate(&mut self.store, &module) {
+            Ok(instance) => match instance.ensure_no_start(&mut self.store) {
+                Ok(instance) => instance,
+                Err(e) => {
+                    // Programs are entered through their exports only.
+                    let error = TransactionError::LinkError {
+                        code: code.hash(),
+                        message: e.to_string(),
+                    };
+                    return (from_program, Err(Interrupt::Failed(error)));
+                }
+            },
+            Err(wasmi::Error::Memory(MemoryError::ResourceLimiterDeniedAllocation)) => {
+                // Nothing was started, so the interrupt is on the caller's
+                // behalf.
+                return (from_program, Err(Interrupt::MemoryBudgetExceeded));
+            }
+            Err(e) => {
+                let error = TransactionError::LinkError {
+                    code: code.hash(),
+                    message: e.to_string(),
+                };
+                return (from_program, Err(Interrupt::Failed(error)));
+            }
+        };
 
         let id = ProgramIdx(self.store.data_mut().programs.len());
         debug!("start: {from_program:?} -> {id:?} = {entry_point}{inputs:?}");
 
         let fuel = self.store.fuel_consumed().unwrap();
-        let main = instance
-            .get_func(&mut self.store, entry_point)
-            .expect(entry_point);
+        let Some(main) = instance.get_func(&mut self.store, entry_point) else {
+            let error = TransactionError::MissingExport {
+                code: code.hash(),
+                name: entry_point.to_owned(),
+            };
+            return (from_program, Err(Interrupt::Failed(error)));
+        };
+        // The host reads and writes program memory throughout.
+        if instance.get_export(&self.store, "memory").is_none() {
+            let error = TransactionError::MissingExport {
+                code: code.hash(),
+                name: "memory".to_owned(),
+            };
+            return (from_program, Err(Interrupt::Failed(error)));
+        }
         let num_outputs = main.ty(&mut self.store).results().len();
-        let mut outputs = [Value::from(ExternRef::null())];
-        let resumable = main
-            .call_resumable(&mut self.store, &inputs, &mut outputs[..num_outputs])
-            .unwrap();
+        let mut outputs = vec![Value::from(ExternRef::null()); num_outputs];
+        let resumable = match main.call_resumable(&mut self.store, &inputs, &mut outputs) {
+            Ok(resumable) => resumable,
+            Err(e) if is_out_of_fuel(&e) => {
+                // The program is never registered, so the interrupt is on
+                // the caller's behalf.
+                let interrupt = Interrupt::OutOfFuel {
+                    program: None,
+                    code: code.hash(),
+                    entry_point: entry_point.to_owned(),
+                };
+                return (from_program, Err(interrupt));
+            }
+            Err(e) => {
+                let error = TransactionError::Trap {
+                    program: id.0,
+                    entry_point: entry_point.to_owned(),
+                    message: e.to_string(),
+                };
+                return (from_program, Err(Interrupt::Failed(error)));
+            }
+        };
+        let program_fuel = self.store.fuel_consumed().unwrap() - fuel;
         assert_eq!(
             id.0,
             self.store.data_mut().programs.len(),
             "unexpected re-entrancy in start_program"
         );
-        let result = match &resumable {
-            ResumableCall::Finished => Ok(outputs[..num_outputs].to_vec()),
-            ResumableCall::Resumable(invocation) => Err(invocation
-                .host_error()
-                .downcast_ref::<Interrupt>()
-                .unwrap()
-                .clone()),
-        };
+        let result = call_result(&resumable, outputs, id, entry_point);
         debug!("= {result:?}");
         self.store.data_mut().programs.push(TxProgram {
             return_to: from_program,
             return_is_token: None,
+            binds_token: None,
+            consumes_utxo: None,
             yield_to: None,
             yield_to_constructor: None,
             code: code.hash(),
             entry_point: entry_point.to_owned(),
+            inputs: inputs.clone(),
             instance,
             num_outputs,
             resumable,
             utxo: None,
+            fuel: program_fuel,
+            instance_owner: id,
+            buffers: Vec::new(),
+            next_yield_layout: Vec::new(),
+            yield_layout: Vec::new(),
+            query_snapshot: None,
+            invalidated: false,
         });
         let from_state_after = self.hash_program(from_program);
         let to_state_before = self.hash_program(id);
         self.store.data_mut().witnesses.push(TxWitness {
             fuel,
+            program_fuel,
             from_program,
             from_state_after,
             to_program: id,
@@ -1966,26 +4661,57 @@ impl Transaction {
         read_from_memory: Vec<MemorySegment>,
         write_to_memory: Vec<MemorySegment>,
     ) -> (ProgramIdx, Result<Vec<Value>, Interrupt>) {
+        if let Err(error) = self.store.data_mut().enter(to_program) {
+            return (from_program, Err(Interrupt::Failed(error)));
+        }
+
+        if self.check_memory_writes {
+            let owner = self.store.data().programs[to_program.0].instance_owner;
+            let buffers = &self.store.data().programs[owner.0].buffers;
+            for MemorySegment { address, data } in &write_to_memory {
+                let end = address.saturating_add(data.len() as u32);
+                if !data.is_empty()
+                    && !buffers
+                        .iter()
+                        .any(|buffer| buffer.start <= *address && end <= buffer.end)
+                {
+                    let interrupt = Interrupt::UndeclaredMemoryWrite {
+                        address: *address,
+                        len: data.len(),
+                    };
+                    return (to_program, Err(interrupt));
+                }
+            }
+        }
+
         match std::mem::replace(
             &mut self.store.data_mut().programs[to_program.0].resumable,
             ResumableCall::Finished,
         ) {
-            ResumableCall::Finished => panic!("attempt to resume finished program"),
+            ResumableCall::Finished => {
+                let error = TransactionError::BadInterruptState {
+                    program: to_program.0,
+                };
+                (to_program, Err(Interrupt::Failed(error)))
+            }
             ResumableCall::Resumable(invocation) => {
                 debug!("resume: {from_program:?} -> {to_program:?} {inputs:?}");
 
                 if !write_to_memory.is_empty() {
                     // Commit memory writes.
-                    let instance = self.store.data_mut().programs[to_program.0].instance;
-                    let (memory, _) = instance
-                        .get_export(&mut self.store, "memory")
-                        .unwrap()
-                        .into_memory()
-                        .unwrap()
-                        .data_and_store_mut(&mut self.store);
+                    let memory = match self.program_memory(to_program) {
+                        Ok(memory) => memory.data_mut(&mut self.store),
+                        Err(error) => return (to_program, Err(Interrupt::Failed(error))),
+                    };
                     for &MemorySegment { address, ref data } in &write_to_memory {
-                        memory[address as usize..address as usize + data.len()]
-                            .copy_from_slice(data);
+                        if wasm_abi::write_bytes(memory, address, data).is_err() {
+                            let error = TransactionError::MemoryOutOfBounds {
+                                program: to_program.0,
+                                address,
+                                len: data.len(),
+                            };
+                            return (to_program, Err(Interrupt::Failed(error)));
+                        }
                         debug!("  {:#x}: {}", address, DisplayHex(data));
                     }
                 }
@@ -1994,22 +4720,39 @@ impl Transaction {
                 let from_state_after = self.hash_program(from_program);
                 let to_state_before = self.hash_program(to_program);
                 let num_outputs = self.store.data_mut().programs[to_program.0].num_outputs;
-                let mut outputs = [Value::from(ExternRef::null())];
-                let resumable = invocation
-                    .resume(&mut self.store, &inputs[..], &mut outputs[..num_outputs])
-                    .unwrap();
-                let result = match &resumable {
-                    ResumableCall::Finished => Ok(outputs[..num_outputs].to_vec()),
-                    ResumableCall::Resumable(invocation) => Err(invocation
-                        .host_error()
-                        .downcast_ref::<Interrupt>()
-                        .unwrap()
-                        .clone()),
+                let mut outputs = vec![Value::from(ExternRef::null()); num_outputs];
+                let resumable = match invocation.resume(&mut self.store, &inputs[..], &mut outputs)
+                {
+                    Ok(resumable) => resumable,
+                    Err(e) if is_out_of_fuel(&e) => {
+                        let program = &self.store.data().programs[to_program.0];
+                        let interrupt = Interrupt::OutOfFuel {
+                            program: Some(to_program),
+                            code: program.code,
+                            entry_point: program.entry_point.clone(),
+                        };
+                        return (to_program, Err(interrupt));
+                    }
+                    Err(e) => {
+                        let program = &self.store.data().programs[to_program.0];
+                        let error = TransactionError::Trap {
+                            program: to_program.0,
+                            entry_point: program.entry_point.clone(),
+                            message: e.to_string(),
+                        };
+                        return (to_program, Err(Interrupt::Failed(error)));
+                    }
                 };
+                let program_fuel = self.store.fuel_consumed().unwrap() - fuel;
+                let entry_point = &self.store.data().programs[to_program.0].entry_point;
+                let result = call_result(&resumable, outputs, to_program, entry_point);
                 debug!("= {result:?}");
-                self.store.data_mut().programs[to_program.0].resumable = resumable;
+                let program = &mut self.store.data_mut().programs[to_program.0];
+                program.resumable = resumable;
+                program.fuel += program_fuel;
                 self.store.data_mut().witnesses.push(TxWitness {
                     fuel,
+                    program_fuel,
                     from_program,
                     from_state_after,
                     to_program,
@@ -2034,52 +4777,80 @@ impl Transaction {
         method: String,
         inputs: Vec<Value>,
     ) -> (ProgramIdx, Result<Vec<Value>, Interrupt>) {
+        if let Err(error) = self.store.data_mut().enter(to_program) {
+            return (from_program, Err(Interrupt::Failed(error)));
+        }
+
         let code = self.store.data().programs[to_program.0].code;
         let instance = self.store.data().programs[to_program.0].instance;
 
         let id = ProgramIdx(self.store.data_mut().programs.len());
         debug!("call: {from_program:?} -> {to_program:?} -> {id:?} = {method}{inputs:?}");
 
-        let main = instance
-            .get_func(&mut self.store, &method)
-            .expect("no such method");
+        let Some(main) = instance.get_func(&mut self.store, &method) else {
+            let error = TransactionError::MissingExport { code, name: method };
+            return (from_program, Err(Interrupt::Failed(error)));
+        };
         let num_outputs = main.ty(&mut self.store).results().len();
-        let mut outputs = [Value::from(ExternRef::null())];
+        let mut outputs = vec![Value::from(ExternRef::null()); num_outputs];
         let fuel = self.store.fuel_consumed().unwrap();
         let from_state_after = self.hash_program(from_program);
         let to_state_before = self.hash_program(to_program);
-        let resumable = main
-            .call_resumable(&mut self.store, &inputs, &mut outputs[..num_outputs])
-            .unwrap();
+        let resumable = match main.call_resumable(&mut self.store, &inputs, &mut outputs) {
+            Ok(resumable) => resumable,
+            Err(e) if is_out_of_fuel(&e) => {
+                // The call is only registered once it suspends.
+                let interrupt = Interrupt::OutOfFuel {
+                    program: None,
+                    code,
+                    entry_point: method,
+                };
+                return (to_program, Err(interrupt));
+            }
+            Err(e) => {
+                let error = TransactionError::Trap {
+                    program: id.0,
+                    entry_point: method,
+                    message: e.to_string(),
+                };
+                return (to_program, Err(Interrupt::Failed(error)));
+            }
+        };
+        let program_fuel = self.store.fuel_consumed().unwrap() - fuel;
         assert_eq!(
             id.0,
             self.store.data_mut().programs.len(),
             "unexpected re-entrancy in Transaction::call_method"
         );
-        let result = match &resumable {
-            ResumableCall::Finished => Ok(outputs[..num_outputs].to_vec()),
-            ResumableCall::Resumable(invocation) => Err(invocation
-                .host_error()
-                .downcast_ref::<Interrupt>()
-                .unwrap()
-                .clone()),
-        };
+        let result = call_result(&resumable, outputs, id, &method);
         debug!("= {result:?}");
         let utxo = self.store.data().programs[to_program.0].utxo;
+        let instance_owner = self.store.data().programs[to_program.0].instance_owner;
         self.store.data_mut().programs.push(TxProgram {
             return_to: from_program,
             return_is_token: None,
+            binds_token: None,
+            consumes_utxo: None,
             yield_to: None,
             yield_to_constructor: None,
             code,
             entry_point: method.to_owned(),
+            inputs: inputs.clone(),
             num_outputs,
             instance,
             resumable,
             utxo,
+            fuel: program_fuel,
+            instance_owner,
+            buffers: Vec::new(),
+            next_yield_layout: Vec::new(),
+            yield_layout: Vec::new(),
+            query_snapshot: None,
+            invalidated: false,
         });
         self.store.data_mut().witnesses.push(TxWitness {
             fuel,
+            program_fuel,
             from_program,
             from_state_after,
             to_program: id,
@@ -2094,13 +4865,64 @@ impl Transaction {
         (id, result)
     }
 
+    /// Add a token to the ones bound to a UTXO, as long as the amounts of its
+    /// type still fit in a `u64`.
+    fn bind_token(
+        &mut self,
+        utxo_id: UtxoId,
+        token_id: TokenId,
+        token: Token,
+    ) -> Result<(), TransactionError> {
+        let data = self.store.data_mut();
+        let code = data.programs[token.program.0].code;
+        let utxo = data.utxos.get_mut(&utxo_id).unwrap();
+
+        let mut total = token.amount;
+        for other in utxo.tokens.values() {
+            if other.token_type_id == token.token_type_id
+                && data.programs[other.program.0].code == code
+            {
+                total = total.checked_add(other.amount).ok_or(
+                    TransactionError::TokenAmountOverflow {
+                        utxo: utxo_id,
+                        token_type_id: token.token_type_id,
+                    },
+                )?;
+            }
+        }
+
+        utxo.tokens.insert(token_id, token);
+        Ok(())
+    }
+
     /// Get the set of UTXOs existing in this transaction. String is the type name.
     pub fn utxos(&mut self) -> Vec<(Value, String)> {
+        let mut res = vec![];
+
+        for (utxo_id, entry_point) in self.live_utxos() {
+            res.push((
+                utxo_id.to_wasm_externref(self.store.as_context_mut()),
+                entry_point,
+            ));
+        }
+
+        res
+    }
+
+    /// Like [`Transaction::utxos`], but identifying each UTXO by the bytes of
+    /// its id rather than a handle only meaningful to this transaction.
+    pub fn live_utxo_ids(&self) -> Vec<(UtxoIdBytes, String)> {
+        self.live_utxos()
+            .into_iter()
+            .map(|(utxo_id, entry_point)| (utxo_id.into(), entry_point))
+            .collect()
+    }
+
+    /// The UTXOs that haven't finished, with their entry point.
+    fn live_utxos(&self) -> Vec<(UtxoId, String)> {
         let data = self.store.data();
 
-        let mut res = vec![];
-        let iter = data
-            .utxos
+        data.utxos
             .iter()
             .filter_map(|(utxo_id, utxo)| {
                 let tx_program = &data.programs[utxo.program.0];
@@ -2111,17 +4933,25 @@ impl Transaction {
                     None
                 }
             })
-            // TODO: can probably avoid this, but just do this for simplicity
-            .collect::<Vec<_>>();
+            .collect()
+    }
 
-        for (utxo_id, entry_point) in iter {
-            res.push((
-                utxo_id.to_wasm_externref(self.store.as_context_mut()),
-                entry_point,
-            ));
-        }
+    /// The tokens `utxo_id` held when it was consumed, in the order
+    /// `starstream_collect_tokens` hands them out. Empty if it hasn't been
+    /// consumed, and tokens burned since are left out.
+    pub fn consumed_tokens(&self, utxo_id: UtxoId) -> Vec<ConsumedToken> {
+        let data = self.store.data();
 
-        res
+        data.consumed_tokens
+            .get(&utxo_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|token_id| data.tokens.get(token_id))
+            .map(|(_, token)| ConsumedToken {
+                token_type_id: token.token_type_id,
+                amount: token.amount,
+            })
+            .collect()
     }
 
     /// Get events logged by this transaction so far.
@@ -2129,6 +4959,111 @@ impl Transaction {
         &self.store.data().events[..]
     }
 
+    /// Move out the events logged by this transaction so far, each with the
+    /// program that emitted it. Later calls only return events logged since.
+    pub fn take_events(&mut self) -> Vec<AttributedEvent> {
+        let inner = self.store.data_mut();
+        std::mem::take(&mut inner.events)
+            .into_iter()
+            .map(|event| {
+                let program = &inner.programs[event.program.0];
+                AttributedEvent {
+                    program_code: program.code,
+                    entry_point: program.entry_point.clone(),
+                    event,
+                }
+            })
+            .collect()
+    }
+
+    /// The code of every coordination script, UTXO and token this transaction
+    /// instantiated or called into, without duplicates, in order of first use.
+    pub fn code_hashes_used(&self) -> Vec<CodeHash> {
+        let mut seen = std::collections::HashSet::new();
+        self.store
+            .data()
+            .programs
+            .iter()
+            .map(|program| program.code)
+            .filter(|code| seen.insert(*code))
+            .collect()
+    }
+
+    /// Every program this transaction has run, as a JSON array in program
+    /// order.
+    ///
+    /// Each entry has the program's `index`, the `return_to` and `yield_to`
+    /// indices it hands control back to (`null` for the host), its `code`,
+    /// `entry_point` and `utxo`, the `fuel` it spent, whether it `finished`,
+    /// and the `interrupt` it is suspended on, if any. Meant for debuggers
+    /// that render the tree of coroutines; the shape of the strings is not
+    /// stable.
+    pub fn programs_debug_json(&self) -> serde_json::Value {
+        self.store
+            .data()
+            .programs
+            .iter()
+            .enumerate()
+            .map(|(index, program)| program.debug_json(index))
+            .collect()
+    }
+
+    /// The programs of this transaction, read from the scheduler's own
+    /// bookkeeping. Unlike querying the contracts, this runs no Wasm, so it
+    /// spends no fuel and can't trap.
+    pub fn dump_state(&self) -> TransactionState {
+        TransactionState {
+            programs: self
+                .store
+                .data()
+                .programs
+                .iter()
+                .enumerate()
+                .map(|(index, program)| ProgramState {
+                    index,
+                    code: program.code,
+                    entry_point: program.entry_point.clone(),
+                    utxo: program.utxo.map(UtxoIdBytes::from),
+                    live: program.interrupt().is_some(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Fuel spent by every program of this transaction so far.
+    pub fn fuel_consumed(&self) -> u64 {
+        self.store.fuel_consumed().unwrap()
+    }
+
+    /// Every witness logged so far, in chronological order: the `fuel` of
+    /// each is at least that of the one before it.
+    pub fn witnesses(&self) -> &[TxWitness] {
+        &self.store.data().witnesses
+    }
+
+    /// [`Transaction::witnesses`], in a form that can be serialized.
+    pub fn witness_trace(&self) -> WitnessTrace {
+        WitnessTrace {
+            witnesses: self
+                .witnesses()
+                .iter()
+                .map(WitnessTraceEntry::from)
+                .collect(),
+        }
+    }
+
+    /// Commitment to each witness logged so far, hashed with the code cache's
+    /// algorithm.
+    pub fn witness_commitments(&self) -> Vec<[u8; 32]> {
+        let algorithm = self.code_cache.algorithm();
+        self.store
+            .data()
+            .witnesses
+            .iter()
+            .map(|witness| witness.commitment(algorithm, &self.store))
+            .collect()
+    }
+
     pub fn map_continuations(&self) -> Vec<ContinuationEntry> {
         let mut result = Vec::new();
         let mut iter = self.store.data().witnesses.iter();
@@ -2144,6 +5079,7 @@ impl Transaction {
             entry_point: first_program.entry_point.clone(),
             input: first.values.clone(),
             state_after: MemoryHash::UNFINISHED,
+            fuel: first.program_fuel,
         });
         for each in iter {
             result.last_mut().unwrap().state_after = each.from_state_after;
@@ -2156,6 +5092,7 @@ impl Transaction {
                     entry_point: each_program.entry_point.clone(),
                     input: each.values.clone(),
                     state_after: MemoryHash::UNFINISHED,
+                    fuel: each.program_fuel,
                 });
             }
         }
@@ -2163,6 +5100,98 @@ impl Transaction {
         result
     }
 
+    /// Check the memory segments recorded in the witnesses against the
+    /// current memories of the programs that are still suspended.
+    ///
+    /// Methods run on the memory of the instance they were called on, so
+    /// segments are checked against the instance rather than the program that
+    /// logged them. Only the most recent witness touching each byte is checked,
+    /// since guest code is free to reuse a buffer once the host is done with
+    /// it, and nothing logged before an instance was last entered is, since any
+    /// program on it may have rewritten its memory since. A mismatch means the
+    /// scheduler logged a memory commit that never happened.
+    pub fn validate_witness_memory_consistency(&self) -> Result<(), String> {
+        let data = self.store.data();
+
+        // (instance owner, address) -> (witness, byte)
+        let mut expected = HashMap::new();
+
+        for (index, witness) in data.witnesses.iter().enumerate() {
+            if witness.to_program != ProgramIdx::Root {
+                let owner = data.programs[witness.to_program.0].instance_owner;
+                expected.retain(|&(program, _), _| program != owner.0);
+            }
+
+            for (program, segments) in [
+                (witness.from_program, &witness.read_from_memory),
+                (witness.to_program, &witness.write_to_memory),
+            ] {
+                if program == ProgramIdx::Root {
+                    continue;
+                }
+                let owner = data.programs[program.0].instance_owner;
+
+                for segment in segments {
+                    for (offset, byte) in segment.data.iter().enumerate() {
+                        expected
+                            .insert((owner.0, segment.address as usize + offset), (index, *byte));
+                    }
+                }
+            }
+        }
+
+        let mut expected = expected.into_iter().collect::<Vec<_>>();
+        expected.sort();
+
+        for ((program, address), (witness, byte)) in expected {
+            let tx_program = &data.programs[program];
+
+            if tx_program.interrupt().is_none() {
+                continue;
+            }
+
+            let actual = tx_program
+                .instance
+                .get_export(&self.store, "memory")
+                .and_then(|export| export.into_memory())
+                .and_then(|memory| memory.data(&self.store).get(address).copied());
+
+            if actual != Some(byte) {
+                return Err(format!(
+                    "witness {witness}: memory of program {program} at {address:#x} is {actual:?}, expected {byte:#04x}"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Test-only hook: flip the bits of the last memory segment written by
+    /// the scheduler, without touching the program memory itself.
+    ///
+    /// Returns false if no witness wrote to memory.
+    #[cfg(feature = "test-utils")]
+    pub fn corrupt_witness_memory_for_testing(&mut self) -> bool {
+        let segment = self
+            .store
+            .data_mut()
+            .witnesses
+            .iter_mut()
+            .rev()
+            .flat_map(|witness| witness.write_to_memory.iter_mut())
+            .find(|segment| !segment.data.is_empty());
+
+        match segment {
+            Some(segment) => {
+                for byte in &mut segment.data {
+                    *byte = !*byte;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn prove(&self) -> TransactionProof {
         self.do_nebula_stuff()
     }
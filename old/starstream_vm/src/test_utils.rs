@@ -0,0 +1,88 @@
+//! Helpers for assembling small Wasm modules in tests.
+
+use std::{fmt::Write, sync::Arc};
+
+use crate::{CodeCache, ContractCode};
+
+/// Builder for a minimal Wasm module with the given imports and exports.
+///
+/// The default module is empty: no imports, no functions, and no memory.
+/// Function types and bodies are written in WAT, with each function and
+/// import bound to `$name` so bodies can `call` them by name.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleBuilder {
+    imports: Vec<(String, String, String)>,
+    funcs: Vec<(String, String, String)>,
+    memory: Option<u32>,
+    data: Vec<(u32, Vec<u8>)>,
+}
+
+impl ModuleBuilder {
+    pub fn new() -> ModuleBuilder {
+        ModuleBuilder::default()
+    }
+
+    /// Import `module.name` as a function of type `ty`, such as
+    /// `(param i32) (result i64)`.
+    pub fn import(mut self, module: &str, name: &str, ty: &str) -> Self {
+        self.imports
+            .push((module.to_owned(), name.to_owned(), ty.to_owned()));
+        self
+    }
+
+    /// Define and export a function named `name` of type `ty`.
+    pub fn func(mut self, name: &str, ty: &str, body: &str) -> Self {
+        self.funcs
+            .push((name.to_owned(), ty.to_owned(), body.to_owned()));
+        self
+    }
+
+    /// Export a memory named `memory` with `pages` initial pages.
+    pub fn memory(mut self, pages: u32) -> Self {
+        self.memory = Some(pages);
+        self
+    }
+
+    /// Initialize memory at `offset` with `bytes`. Requires a memory.
+    pub fn data(mut self, offset: u32, bytes: &[u8]) -> Self {
+        self.data.push((offset, bytes.to_vec()));
+        self
+    }
+
+    /// Render the module as WAT source.
+    pub fn to_wat(&self) -> String {
+        let mut wat = String::from("(module\n");
+        for (module, name, ty) in &self.imports {
+            writeln!(wat, "  (import {module:?} {name:?} (func ${name} {ty}))").unwrap();
+        }
+        if let Some(pages) = self.memory {
+            writeln!(wat, "  (memory (export \"memory\") {pages})").unwrap();
+        }
+        for (offset, bytes) in &self.data {
+            write!(wat, "  (data (i32.const {offset}) \"").unwrap();
+            for byte in bytes {
+                write!(wat, "\\{byte:02x}").unwrap();
+            }
+            wat.push_str("\")\n");
+        }
+        for (name, ty, body) in &self.funcs {
+            writeln!(
+                wat,
+                "  (func ${name} (export {name:?}) {ty}\n    {body}\n  )"
+            )
+            .unwrap();
+        }
+        wat.push(')');
+        wat
+    }
+
+    /// Assemble the module into a Wasm binary.
+    pub fn build(&self) -> Vec<u8> {
+        wat::parse_str(self.to_wat()).expect("invalid WAT")
+    }
+
+    /// Assemble the module and load it into `code_cache`.
+    pub fn load(&self, code_cache: &CodeCache) -> Arc<ContractCode> {
+        code_cache.load(self.build())
+    }
+}
@@ -0,0 +1,149 @@
+//! The little-endian layout of values in contract linear memory.
+//!
+//! Host functions and the scheduler move values in and out of contract memory
+//! through these helpers, so bounds checking happens in one place.
+
+use wasmi::{
+    Value,
+    core::{F32, F64, ValueType},
+};
+
+/// An access to contract memory that can't be served.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiError {
+    /// `len` bytes at `offset` don't fit in a memory of `memory_len` bytes.
+    OutOfBounds {
+        offset: u32,
+        len: usize,
+        memory_len: usize,
+    },
+    /// References have no representation in linear memory.
+    Unsupported(ValueType),
+}
+
+impl std::fmt::Display for AbiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AbiError::OutOfBounds {
+                offset,
+                len,
+                memory_len,
+            } => write!(
+                f,
+                "{len} bytes at {offset:#x} are outside of a {memory_len} byte memory"
+            ),
+            AbiError::Unsupported(ty) => write!(f, "{ty:?} values can't be stored in memory"),
+        }
+    }
+}
+
+impl std::error::Error for AbiError {}
+
+/// The number of bytes a value of type `ty` takes in memory, which is also
+/// its alignment.
+pub fn size_of(ty: ValueType) -> Result<usize, AbiError> {
+    match ty {
+        ValueType::I32 | ValueType::F32 => Ok(4),
+        ValueType::I64 | ValueType::F64 => Ok(8),
+        ValueType::FuncRef | ValueType::ExternRef => Err(AbiError::Unsupported(ty)),
+    }
+}
+
+fn range(memory_len: usize, offset: u32, len: usize) -> Result<std::ops::Range<usize>, AbiError> {
+    let start = offset as usize;
+    start
+        .checked_add(len)
+        .filter(|&end| end <= memory_len)
+        .map(|end| start..end)
+        .ok_or(AbiError::OutOfBounds {
+            offset,
+            len,
+            memory_len,
+        })
+}
+
+/// Borrow `len` bytes of `memory` starting at `offset`.
+pub fn read_bytes(memory: &[u8], offset: u32, len: usize) -> Result<&[u8], AbiError> {
+    Ok(&memory[range(memory.len(), offset, len)?])
+}
+
+/// Overwrite the bytes of `memory` starting at `offset` with `data`.
+pub fn write_bytes(memory: &mut [u8], offset: u32, data: &[u8]) -> Result<(), AbiError> {
+    let range = range(memory.len(), offset, data.len())?;
+    memory[range].copy_from_slice(data);
+    Ok(())
+}
+
+/// Read a value of type `ty` stored at `offset`.
+pub fn read_value(memory: &[u8], offset: u32, ty: ValueType) -> Result<Value, AbiError> {
+    let bytes = read_bytes(memory, offset, size_of(ty)?)?;
+    Ok(match ty {
+        ValueType::I32 => Value::I32(i32::from_le_bytes(bytes.try_into().unwrap())),
+        ValueType::I64 => Value::I64(i64::from_le_bytes(bytes.try_into().unwrap())),
+        ValueType::F32 => Value::F32(F32::from_bits(u32::from_le_bytes(
+            bytes.try_into().unwrap(),
+        ))),
+        ValueType::F64 => Value::F64(F64::from_bits(u64::from_le_bytes(
+            bytes.try_into().unwrap(),
+        ))),
+        ValueType::FuncRef | ValueType::ExternRef => unreachable!(),
+    })
+}
+
+/// The in-memory representation of `value`.
+pub fn encode_value(value: &Value) -> Result<Vec<u8>, AbiError> {
+    Ok(match value {
+        Value::I32(n) => n.to_le_bytes().to_vec(),
+        Value::I64(n) => n.to_le_bytes().to_vec(),
+        Value::F32(n) => n.to_bits().to_le_bytes().to_vec(),
+        Value::F64(n) => n.to_bits().to_le_bytes().to_vec(),
+        Value::FuncRef(_) | Value::ExternRef(_) => return Err(AbiError::Unsupported(value.ty())),
+    })
+}
+
+/// Store `value` at `offset`.
+pub fn write_value(memory: &mut [u8], offset: u32, value: &Value) -> Result<(), AbiError> {
+    write_bytes(memory, offset, &encode_value(value)?)
+}
+
+/// The offset of each field of a struct laid out like a C struct, with every
+/// field aligned to its own size, along with the size of the whole struct.
+fn struct_layout(fields: &[ValueType]) -> Result<(Vec<u32>, u32), AbiError> {
+    let mut offsets = Vec::with_capacity(fields.len());
+    let mut end = 0;
+    let mut align = 1;
+    for &ty in fields {
+        let size = size_of(ty)? as u32;
+        let offset = end.next_multiple_of(size);
+        offsets.push(offset);
+        end = offset + size;
+        align = align.max(size);
+    }
+    Ok((offsets, end.next_multiple_of(align)))
+}
+
+/// Read a struct with the given field types stored at `offset`.
+pub fn decode_struct(
+    memory: &[u8],
+    offset: u32,
+    fields: &[ValueType],
+) -> Result<Vec<Value>, AbiError> {
+    let (offsets, size) = struct_layout(fields)?;
+    let bytes = read_bytes(memory, offset, size as usize)?;
+    fields
+        .iter()
+        .zip(offsets)
+        .map(|(&ty, field_offset)| read_value(bytes, field_offset, ty))
+        .collect()
+}
+
+/// The in-memory representation of a struct with `fields` as its fields.
+pub fn encode_struct(fields: &[Value]) -> Result<Vec<u8>, AbiError> {
+    let types = fields.iter().map(Value::ty).collect::<Vec<_>>();
+    let (offsets, size) = struct_layout(&types)?;
+    let mut bytes = vec![0; size as usize];
+    for (value, field_offset) in fields.iter().zip(offsets) {
+        write_value(&mut bytes, field_offset, value)?;
+    }
+    Ok(bytes)
+}
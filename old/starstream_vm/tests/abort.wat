@@ -0,0 +1,25 @@
+(module
+  (import "starstream_utxo:wat:abort" "starstream_new_main" (func $make_utxo (result i64)))
+  (import "env" "abort" (func $abort))
+
+  (memory $mem 1)
+
+  ;; aborts directly from the coordination script
+  (func $coord
+    call $abort
+  )
+
+  ;; aborts from inside a utxo
+  (func $coord_utxo (result i64)
+    call $make_utxo
+  )
+
+  (func $main
+    call $abort
+  )
+
+  (export "starstream_new_main" (func $main))
+  (export "coord" (func $coord))
+  (export "coord_utxo" (func $coord_utxo))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,12 @@
+(module
+  (import "starstream_utxo:this" "starstream_bogus" (func $bogus))
+
+  (memory $mem 1)
+
+  (func $coord
+    (call $bogus)
+  )
+
+  (export "coord" (func $coord))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,38 @@
+(module
+  (import "starstream_utxo:this" "starstream_mint_1" (func $mint (param i64) (result i64)))
+  (import "env" "starstream_caller_code" (func $caller_code (param i32)))
+  (import "env" "starstream_this_code" (func $this_code (param i32)))
+  (import "env" "starstream_require" (func $require (param i32 i32)))
+
+  (memory $mem 1)
+
+  ;; coordination script from the same code as the token
+  (func $coord (result i32)
+    (drop (call $mint (i64.const 10)))
+    (call $caller_code (i32.const 0))
+    ;; the transaction itself has no code
+    (i64.eqz
+      (i64.or
+        (i64.or (i64.load (i32.const 0)) (i64.load (i32.const 8)))
+        (i64.or (i64.load (i32.const 16)) (i64.load (i32.const 24)))))
+  )
+
+  ;; only code from this module may mint
+  (func $mint_1 (param $amount i64)
+    (call $caller_code (i32.const 0))
+    (call $this_code (i32.const 32))
+    (call $require
+      (i32.and
+        (i32.and
+          (i64.eq (i64.load (i32.const 0)) (i64.load (i32.const 32)))
+          (i64.eq (i64.load (i32.const 8)) (i64.load (i32.const 40))))
+        (i32.and
+          (i64.eq (i64.load (i32.const 16)) (i64.load (i32.const 48)))
+          (i64.eq (i64.load (i32.const 24)) (i64.load (i32.const 56)))))
+      (i32.const 1))
+  )
+
+  (export "coord" (func $coord))
+  (export "starstream_mint_1" (func $mint_1))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,80 @@
+(module
+  (import "starstream_utxo:wat:catch_all" "starstream_new_thrower" (func $new_thrower (result i64)))
+  (import "starstream_utxo:wat:catch_all" "starstream_new_f_thrower" (func $new_f_thrower (result i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+  (import "starstream_utxo_env" "starstream_raise" (func $raise (param i32 i32 i32 i32 i32 i32)))
+  (import "env" "starstream_register_effect_handler" (func $register (param i32 i32 i32)))
+  (import "env" "starstream_is_effect_handled" (func $is_handled (param i32 i32) (result i32)))
+  (import "env" "starstream_get_raised_effect_name" (func $get_name (param i32 i32) (result i32)))
+  (import "env" "starstream_get_raised_effect_data" (func $get_data (param i32 i32 i32 i32 i32)))
+  (import "env" "starstream_resume_throwing_program" (func $resume_thrower (param i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "*EF")
+
+  ;; only a catch-all is registered, with handler address 7
+  (func $coord (result i64)
+    (call $register (i32.const 0) (i32.const 1) (i32.const 7))
+    (call $new_thrower)
+  )
+
+  ;; the catch-all is registered with handler address 8, and F has a handler
+  ;; of its own
+  (func $coord_nested (result i64)
+    (call $register (i32.const 0) (i32.const 1) (i32.const 8))
+    (call $register (i32.const 2) (i32.const 1) (i32.const 0))
+    (call $new_thrower)
+  )
+
+  ;; nothing is registered
+  (func $coord_unhandled (result i64)
+    (call $new_thrower)
+  )
+
+  ;; raises E if it's handled and yields what it was resumed with, otherwise
+  ;; yields 0
+  (func $thrower
+    (i32.store (i32.const 104) (i32.const 0))
+    (if (call $is_handled (i32.const 1) (i32.const 1))
+      (then
+        (i32.store (i32.const 100) (i32.const 5))
+        (call $raise (i32.const 1) (i32.const 1) (i32.const 100) (i32.const 4) (i32.const 104) (i32.const 4))
+      )
+    )
+    (call $yield (i32.const 1) (i32.const 1) (i32.const 104) (i32.const 4) (i32.const 0) (i32.const 0))
+  )
+
+  ;; raises F, whose handler returns without resuming it, then yields
+  (func $f_thrower
+    (call $raise (i32.const 2) (i32.const 1) (i32.const 100) (i32.const 4) (i32.const 104) (i32.const 4))
+    (call $yield (i32.const 2) (i32.const 1) (i32.const 104) (i32.const 4) (i32.const 0) (i32.const 0))
+  )
+
+  (func $f_handle (param $handler i32))
+
+  ;; resumes with the raised data times the handler address, after checking
+  ;; that it's E being handled, even if F was raised in the meantime
+  (func $catch_all_handle (param $handler i32)
+    (if (i32.eq (local.get $handler) (i32.const 8))
+      (then (drop (call $new_f_thrower)))
+    )
+    (if (i32.ne (call $get_name (i32.const 200) (i32.const 16)) (i32.const 1))
+      (then unreachable)
+    )
+    (if (i32.ne (i32.load8_u (i32.const 200)) (i32.load8_u (i32.const 1)))
+      (then unreachable)
+    )
+    (call $get_data (i32.const 200) (i32.const 1) (i32.const 204) (i32.const 4) (i32.const 208))
+    (i32.store (i32.const 212) (i32.mul (i32.load (i32.const 204)) (local.get $handler)))
+    (call $resume_thrower (i32.const 200) (i32.const 1) (i32.const 212))
+  )
+
+  (export "starstream_new_thrower" (func $thrower))
+  (export "coord" (func $coord))
+  (export "starstream_new_f_thrower" (func $f_thrower))
+  (export "coord_nested" (func $coord_nested))
+  (export "coord_unhandled" (func $coord_unhandled))
+  (export "F_handle" (func $f_handle))
+  (export "starstream_catch_all_handle" (func $catch_all_handle))
+  (export "memory" (memory $mem))
+)
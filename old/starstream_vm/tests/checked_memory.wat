@@ -0,0 +1,21 @@
+(module
+  (import "env" "starstream_alloc" (func $alloc (param i32 i32)))
+  (import "env" "starstream_coordination_code" (func $coordination_code (param i32)))
+
+  (memory $mem 1)
+  (export "memory" (memory $mem))
+
+  ;; the code hash lands inside the declared buffer
+  (func $coord_ok
+    (call $alloc (i32.const 64) (i32.const 32))
+    (call $coordination_code (i32.const 64))
+  )
+  (export "coord_ok" (func $coord_ok))
+
+  ;; off by 8: the last 8 bytes of the code hash spill past the buffer
+  (func $coord_bad
+    (call $alloc (i32.const 64) (i32.const 32))
+    (call $coordination_code (i32.const 72))
+  )
+  (export "coord_bad" (func $coord_bad))
+)
@@ -0,0 +1,83 @@
+(module
+  (import "starstream_utxo:this" "starstream_mint_1" (func $mint (param i64) (result i64)))
+  (import "starstream_utxo:this" "starstream_new_main" (func $make_utxo (param i64 i64) (result i64)))
+  (import "starstream_utxo:this" "starstream_consume_burn" (func $burn (param i64)))
+  (import "starstream_utxo:this" "starstream_consume_unbind_first" (func $unbind_first (param i64)))
+  (import "starstream_utxo" "starstream_collect_tokens" (func $collect_tokens (param i64 i32 i32 i32) (result i32)))
+  (import "starstream_token:this" "starstream_bind_Token" (func $bind_token (param i64)))
+  (import "starstream_token:this" "starstream_unbind_Token" (func $unbind_token (param i64)))
+  (import "env" "starstream_get_token_amount" (func $get_token_amount (param i64) (result i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+
+  ;; consumes a UTXO holding tokens of 10 and 20, and collects both
+  (func $coord (result i64)
+    (local $utxo i64)
+    (local.set $utxo (call $make_utxo (call $mint (i64.const 10)) (call $mint (i64.const 20))))
+
+    ;; nothing to collect while it's alive
+    (if (i32.ne (call $collect_tokens (local.get $utxo) (i32.const 0) (i32.const 4) (i32.const 0)) (i32.const 0))
+      (then unreachable))
+
+    (call $burn (local.get $utxo))
+
+    (if (i32.ne (call $collect_tokens (local.get $utxo) (i32.const 0) (i32.const 4) (i32.const 0)) (i32.const 2))
+      (then unreachable))
+
+    ;; the handles are to the now unbound tokens
+    (if (i64.ne
+          (i64.add
+            (call $get_token_amount (i64.load (i32.const 0)))
+            (call $get_token_amount (i64.load (i32.const 8))))
+          (i64.const 30))
+      (then unreachable))
+
+    (local.get $utxo)
+  )
+
+  ;; the consume method unbinds the token of 10 itself, which leaves only
+  ;; the other one to collect
+  (func $coord_unbinding (result i64)
+    (local $utxo i64)
+    (local.set $utxo (call $make_utxo (call $mint (i64.const 10)) (call $mint (i64.const 20))))
+
+    (call $unbind_first (local.get $utxo))
+
+    (if (i32.ne (call $collect_tokens (local.get $utxo) (i32.const 0) (i32.const 4) (i32.const 0)) (i32.const 1))
+      (then unreachable))
+    (if (i64.ne (call $get_token_amount (i64.load (i32.const 0))) (i64.const 20))
+      (then unreachable))
+
+    (local.get $utxo)
+  )
+
+  (func $mint_1 (param $amount i64))
+
+  (func $bind (param $token i64))
+
+  (func $unbind)
+
+  (func $main (param $first i64) (param $second i64)
+    (i64.store (i32.const 32) (local.get $first))
+    (call $bind_token (local.get $first))
+    (call $bind_token (local.get $second))
+    (call $yield (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0))
+  )
+
+  (func $consume (param $self i32))
+
+  (func $consume_unbind_first (param $self i32)
+    (call $unbind_token (i64.load (i32.const 32)))
+  )
+
+  (export "starstream_mint_1" (func $mint_1))
+  (export "starstream_bind_Token_1" (func $bind))
+  (export "starstream_new_main" (func $main))
+  (export "starstream_unbind_Token_1" (func $unbind))
+  (export "starstream_consume_burn" (func $consume))
+  (export "starstream_consume_unbind_first" (func $consume_unbind_first))
+  (export "coord" (func $coord))
+  (export "coord_unbinding" (func $coord_unbinding))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,18 @@
+(module
+  (import "env" "starstream_coordination_code" (func $coordination_code (param i32)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "main")
+  ;; filled with a non-zero pattern so a missing write shows up
+  (data (i32.const 32) "\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff\ff")
+
+  ;; yields the code hash of the coordination script that created it
+  (func $main
+    (call $coordination_code (i32.const 32))
+    (call $yield (i32.const 0) (i32.const 4) (i32.const 32) (i32.const 32) (i32.const 0) (i32.const 0))
+  )
+
+  (export "starstream_new_main_new" (func $main))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,26 @@
+(module
+  (memory $mem 1)
+
+  (func $recurse (param $n i32) (result i32)
+    local.get $n
+    i32.eqz
+    if (result i32)
+      i32.const 0
+    else
+      local.get $n
+      i32.const 1
+      i32.sub
+      call $recurse
+      i32.const 1
+      i32.add
+    end
+  )
+
+  (func $coord (result i32)
+    i32.const 1000
+    call $recurse
+  )
+
+  (export "coord" (func $coord))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,90 @@
+(module
+  (import "starstream_utxo:this" "starstream_new_source" (func $new_source (param i32) (result i64)))
+  (import "starstream_utxo:this" "starstream_new_doubler" (func $new_doubler (param i64) (result i64)))
+  (import "starstream_utxo:this" "starstream_query_get" (func $get (param i64) (result i32)))
+  (import "starstream_utxo:this" "starstream_query_relay" (func $relay (param i64 i64) (result i32)))
+  (import "starstream_utxo:this" "starstream_new_raiser" (func $new_raiser (result i64)))
+  (import "starstream_utxo:this" "starstream_resume_raiser" (func $resume (param i64 i32)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+  (import "starstream_utxo_env" "starstream_raise" (func $raise (param i32 i32 i32 i32 i32 i32)))
+  (import "env" "starstream_register_effect_handler" (func $register (param i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "P")
+
+  ;; a doubler reads its source's value without going through coordination
+  (func $coord (result i32)
+    (call $get (call $new_doubler (call $new_source (i32.const 21))))
+  )
+
+  ;; asks the doubler to query the source on our behalf
+  (func $coord_relay (result i32)
+    (local $source i64)
+    (local.set $source (call $new_source (i32.const 5)))
+    (call $relay (call $new_doubler (local.get $source)) (local.get $source))
+  )
+
+  ;; asks the doubler to query itself
+  (func $coord_reentrant (result i32)
+    (local $doubler i64)
+    (local.set $doubler (call $new_doubler (call $new_source (i32.const 5))))
+    (call $relay (local.get $doubler) (local.get $doubler))
+  )
+
+  ;; asks the doubler to query a UTXO while it waits on its own raise
+  (func $coord_mid_raise
+    (call $register (i32.const 0) (i32.const 1) (i32.const 0))
+    (i64.store (i32.const 200) (call $new_doubler (call $new_source (i32.const 5))))
+    (i64.store (i32.const 208) (call $new_raiser))
+    (call $resume (i64.load (i32.const 208)) (i32.const 0))
+  )
+
+  (func $p_handle (param $handler i32)
+    (drop (call $relay (i64.load (i32.const 200)) (i64.load (i32.const 208))))
+  )
+
+  (func $sleep
+    (loop $forever
+      (call $yield (i32.const 0) (i32.const 0) (i32.const 100) (i32.const 4) (i32.const 0) (i32.const 0))
+      (br $forever)
+    )
+  )
+
+  (func $source (param $value i32)
+    (i32.store (i32.const 100) (local.get $value))
+    (call $sleep)
+  )
+
+  (func $doubler (param $source i64)
+    (i32.store (i32.const 100) (i32.mul (call $get (local.get $source)) (i32.const 2)))
+    (call $sleep)
+  )
+
+  ;; yields once, then raises P when resumed
+  (func $raiser
+    (i32.store (i32.const 100) (i32.const 7))
+    (call $yield (i32.const 0) (i32.const 0) (i32.const 100) (i32.const 4) (i32.const 0) (i32.const 0))
+    (call $raise (i32.const 0) (i32.const 1) (i32.const 100) (i32.const 4) (i32.const 104) (i32.const 4))
+    (call $sleep)
+  )
+
+  (func $get_value (result i32)
+    (i32.load (i32.const 100))
+  )
+
+  (func $relay_value (param $other i64) (result i32)
+    (call $get (local.get $other))
+  )
+
+  (export "coord" (func $coord))
+  (export "coord_relay" (func $coord_relay))
+  (export "coord_reentrant" (func $coord_reentrant))
+  (export "coord_mid_raise" (func $coord_mid_raise))
+  (export "P_handle" (func $p_handle))
+  (export "starstream_new_source" (func $source))
+  (export "starstream_new_doubler" (func $doubler))
+  (export "starstream_new_raiser" (func $raiser))
+  (export "starstream_query_get" (func $get_value))
+  (export "starstream_query_relay" (func $relay_value))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,28 @@
+(module
+  (import "starstream_utxo:wat:double_consume" "starstream_new_main" (func $make_utxo (result i64)))
+  (import "starstream_utxo:wat:double_consume" "starstream_consume_burn" (func $burn (param i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+
+  ;; consumes the same handle twice
+  (func $coord
+    (local $utxo i64)
+    call $make_utxo
+    local.tee $utxo
+    call $burn
+    local.get $utxo
+    call $burn
+  )
+
+  (func $main
+    (call $yield (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0))
+  )
+
+  (func $consume (param $self i32))
+
+  (export "starstream_new_main" (func $main))
+  (export "starstream_consume_burn" (func $consume))
+  (export "coord" (func $coord))
+  (export "memory" (memory $mem))
+)
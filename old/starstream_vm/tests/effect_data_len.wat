@@ -0,0 +1,47 @@
+(module
+  (import "starstream_utxo:wat:effect_data_len" "starstream_new_thrower" (func $new_thrower (param i32) (result i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+  (import "starstream_utxo_env" "starstream_raise" (func $raise (param i32 i32 i32 i32 i32 i32)))
+  (import "env" "starstream_register_effect_handler" (func $register (param i32 i32 i32)))
+  (import "env" "starstream_get_raised_effect_data" (func $get_data (param i32 i32 i32 i32 i32)))
+  (import "env" "starstream_resume_throwing_program" (func $resume_thrower (param i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "E")
+
+  ;; raises E with 4 bytes of data
+  (func $coord_exact (result i64)
+    (call $register (i32.const 0) (i32.const 1) (i32.const 0))
+    (call $new_thrower (i32.const 4))
+  )
+
+  ;; raises E claiming far more data than its memory holds
+  (func $coord_oversized (result i64)
+    (call $register (i32.const 0) (i32.const 1) (i32.const 0))
+    (call $new_thrower (i32.const 0x7fffffff))
+  )
+
+  ;; raises E with 8 bytes of data, more than the handler's buffer
+  (func $coord_mismatch (result i64)
+    (call $register (i32.const 0) (i32.const 1) (i32.const 0))
+    (call $new_thrower (i32.const 8))
+  )
+
+  (func $thrower (param $data_len i32)
+    (call $raise (i32.const 0) (i32.const 1) (i32.const 100) (local.get $data_len) (i32.const 0) (i32.const 0))
+    (call $yield (i32.const 0) (i32.const 1) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0))
+  )
+
+  ;; the handler's buffer at 200 only fits 4 bytes
+  (func $e_handle (param $handler i32)
+    (call $get_data (i32.const 0) (i32.const 1) (i32.const 200) (i32.const 4) (i32.const 204))
+    (call $resume_thrower (i32.const 0) (i32.const 1) (i32.const 208))
+  )
+
+  (export "starstream_new_thrower" (func $thrower))
+  (export "coord_exact" (func $coord_exact))
+  (export "coord_oversized" (func $coord_oversized))
+  (export "coord_mismatch" (func $coord_mismatch))
+  (export "E_handle" (func $e_handle))
+  (export "memory" (memory $mem))
+)
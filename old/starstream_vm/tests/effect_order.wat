@@ -0,0 +1,43 @@
+(module
+  (import "starstream_utxo:wat:effect_order" "starstream_new_thrower" (func $new_thrower (param i32) (result i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+  (import "starstream_utxo_env" "starstream_raise" (func $raise (param i32 i32 i32 i32 i32 i32)))
+  (import "env" "starstream_register_effect_handler" (func $register (param i32 i32 i32)))
+  (import "env" "starstream_get_raised_effect_data" (func $get_data (param i32 i32 i32 i32 i32)))
+  (import "env" "starstream_resume_throwing_program" (func $resume_thrower (param i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "E")
+
+  (func $coord (result i64)
+    (call $register (i32.const 0) (i32.const 1) (i32.const 0))
+    (call $new_thrower (i32.const 1))
+  )
+
+  ;; raises E with its id, then yields whatever it was resumed with
+  (func $thrower (param $id i32)
+    (i32.store (i32.const 100) (local.get $id))
+    (call $raise (i32.const 0) (i32.const 1) (i32.const 100) (i32.const 4) (i32.const 104) (i32.const 4))
+    (call $yield (i32.const 0) (i32.const 1) (i32.const 104) (i32.const 4) (i32.const 0) (i32.const 0))
+  )
+
+  ;; resumes each thrower with 10 times its id. While handling thrower 1, it
+  ;; creates thrower 2, so both raises of E are pending at once.
+  (func $e_handle (param $handler i32)
+    (call $get_data (i32.const 0) (i32.const 1) (i32.const 200) (i32.const 4) (i32.const 204))
+    (if (i32.eq (i32.load (i32.const 200)) (i32.const 1))
+      (then
+        (drop (call $new_thrower (i32.const 2)))
+        ;; thrower 2 was resumed by the nested handler, so this sees thrower 1 again
+        (call $get_data (i32.const 0) (i32.const 1) (i32.const 200) (i32.const 4) (i32.const 204))
+      )
+    )
+    (i32.store (i32.const 208) (i32.mul (i32.load (i32.const 200)) (i32.const 10)))
+    (call $resume_thrower (i32.const 0) (i32.const 1) (i32.const 208))
+  )
+
+  (export "starstream_new_thrower" (func $thrower))
+  (export "coord" (func $coord))
+  (export "E_handle" (func $e_handle))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,13 @@
+(module
+  (import "starstream_utxo:this" "starstream_event_minted" (func $minted (param i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 16) "supply")
+
+  (func $coord
+    (call $minted (i32.const 16) (i32.const 6))
+  )
+
+  (export "coord" (func $coord))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,38 @@
+(module
+  (import "starstream_utxo:wat:fuel_attribution" "starstream_new_light" (func $new_light (result i64)))
+  (import "starstream_utxo:wat:fuel_attribution" "starstream_new_heavy" (func $new_heavy (result i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "main")
+
+  (func $coord_create_light (result i64)
+    call $new_light
+  )
+
+  (func $coord_create_heavy (result i64)
+    call $new_heavy
+  )
+
+  ;; yields straight away
+  (func $light
+    (call $yield (i32.const 0) (i32.const 4) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0))
+  )
+
+  ;; spins for a while before yielding
+  (func $heavy
+    (local $i i32)
+    (local.set $i (i32.const 1000))
+    (loop $spin
+      (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+      (br_if $spin (local.get $i))
+    )
+    (call $yield (i32.const 0) (i32.const 4) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0))
+  )
+
+  (export "starstream_new_light" (func $light))
+  (export "starstream_new_heavy" (func $heavy))
+  (export "coord_create_light" (func $coord_create_light))
+  (export "coord_create_heavy" (func $coord_create_heavy))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,43 @@
+(module
+  (import "env" "eprint" (func $eprint (param i32 i32)))
+  (import "env" "starstream_this_code" (func $this_code (param i32)))
+  (import "env" "starstream_keccak256" (func $keccak256 (param i32 i32 i32)))
+  (import "env" "starstream_is_effect_handled" (func $is_effect_handled (param i32 i32) (result i32)))
+
+  (memory $mem 1)
+
+  ;; ptr + len overflows
+  (func $coord_eprint
+    (call $eprint (i32.const -1) (i32.const 2))
+  )
+
+  ;; the hash doesn't fit at the end of memory
+  (func $coord_this_code
+    (call $this_code (i32.const -1))
+  )
+
+  (func $coord_keccak256_input
+    (call $keccak256 (i32.const -1) (i32.const -1) (i32.const 0))
+  )
+
+  (func $coord_keccak256_output
+    (call $keccak256 (i32.const 0) (i32.const 16) (i32.const 65530))
+  )
+
+  (func $coord_effect_name (result i32)
+    (call $is_effect_handled (i32.const 65536) (i32.const 1))
+  )
+
+  ;; in bounds, for comparison
+  (func $coord_ok
+    (call $keccak256 (i32.const 0) (i32.const 16) (i32.const 32))
+  )
+
+  (export "coord_eprint" (func $coord_eprint))
+  (export "coord_this_code" (func $coord_this_code))
+  (export "coord_keccak256_input" (func $coord_keccak256_input))
+  (export "coord_keccak256_output" (func $coord_keccak256_output))
+  (export "coord_effect_name" (func $coord_effect_name))
+  (export "coord_ok" (func $coord_ok))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,22 @@
+(module
+  (import "starstream_utxo:wat:memory_budget" "starstream_new_main" (func $make_utxo (result i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+
+  ;; every instance takes a page, so this runs out eventually
+  (func $coord
+    (loop $spawn
+      (drop (call $make_utxo))
+      (br $spawn)
+    )
+  )
+
+  (func $main
+    (call $yield (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0))
+  )
+
+  (export "starstream_new_main" (func $main))
+  (export "coord" (func $coord))
+  (export "memory" (memory $mem))
+)
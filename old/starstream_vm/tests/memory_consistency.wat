@@ -0,0 +1,74 @@
+(module
+  (import "starstream_utxo:wat:memory_consistency" "starstream_new_main" (func $make_utxo (result i64)))
+  (import "starstream_utxo:wat:memory_consistency" "starstream_resume_" (func $resume (param i64 i32)))
+  (import "starstream_utxo:wat:memory_consistency" "starstream_new_counter" (func $make_counter (result i64)))
+  (import "starstream_utxo:wat:memory_consistency" "starstream_mutate_bump" (func $bump (param i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "main")
+  ;; resume argument copied by the host into the utxo's memory
+  (data (i32.const 16) "\de\ad\be\ef")
+
+  (func $coord
+    (local $utxo i64)
+    call $make_utxo
+    local.tee $utxo
+    i32.const 16
+    call $resume
+  )
+
+  (func $main
+    ;; the resume argument lands at 32
+    i32.const 0
+    i32.const 4
+    i32.const 0
+    i32.const 0
+    i32.const 32
+    i32.const 4
+    call $yield
+    ;; stay alive after being resumed
+    i32.const 0
+    i32.const 4
+    i32.const 0
+    i32.const 0
+    i32.const 48
+    i32.const 4
+    call $yield
+  )
+
+  (func $coord_mutate
+    call $make_counter
+    call $bump
+  )
+
+  ;; yields a counter at 64, which the mutation then changes in place
+  (func $counter
+    i32.const 64
+    i32.const 7
+    i32.store
+    i32.const 0
+    i32.const 4
+    i32.const 64
+    i32.const 4
+    i32.const 0
+    i32.const 0
+    call $yield
+  )
+
+  (func $bump_counter (param $self i32)
+    local.get $self
+    local.get $self
+    i32.load
+    i32.const 1
+    i32.add
+    i32.store
+  )
+
+  (export "starstream_new_main" (func $main))
+  (export "starstream_new_counter" (func $counter))
+  (export "starstream_mutate_bump" (func $bump_counter))
+  (export "coord" (func $coord))
+  (export "coord_mutate" (func $coord_mutate))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,43 @@
+(module
+  (import "starstream_utxo:this" "starstream_mint_1" (func $mint (param i64) (result i64)))
+  (import "starstream_utxo:this" "starstream_new_main" (func $make_utxo (param i64) (result i64)))
+  (import "starstream_token:this" "starstream_bind_Token" (func $bind (param i64)))
+  (import "env" "starstream_get_token_amount" (func $get_token_amount (param i64) (result i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 48) "y")
+
+  ;; address 16 holds the script's own data across the mint
+  (func $coord_keeps_data (result i32)
+    (i32.store (i32.const 16) (i32.const 1234))
+    (drop (call $mint (i64.const 10)))
+    (i32.load (i32.const 16))
+  )
+
+  (func $coord (result i64)
+    (call $make_utxo (call $mint (i64.const 10)))
+  )
+
+  ;; the token's own instance uses address 16 too
+  (func $mint_1 (param $amount i64)
+    (i64.store (i32.const 16) (i64.const -1))
+  )
+
+  (func $bind_1 (param $token i64))
+
+  ;; yields the amount of the token it holds
+  (func $main (param $token i64)
+    (i64.store (i32.const 16) (i64.const -1))
+    (call $bind (local.get $token))
+    (i64.store (i32.const 0) (call $get_token_amount (local.get $token)))
+    (call $yield (i32.const 48) (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 0) (i32.const 0))
+  )
+
+  (export "starstream_mint_1" (func $mint_1))
+  (export "starstream_bind_Token_1" (func $bind_1))
+  (export "starstream_new_main" (func $main))
+  (export "coord_keeps_data" (func $coord_keeps_data))
+  (export "coord" (func $coord))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,61 @@
+(module
+  (import "starstream_utxo:this" "starstream_new_counter" (func $new_counter (result i64)))
+  (import "starstream_utxo:this" "starstream_new_spinner" (func $new_spinner (result i64)))
+  (import "starstream_utxo:this" "starstream_mutate_spin" (func $spin (param i64)))
+  (import "starstream_utxo:this" "starstream_new_sleeper" (func $new_sleeper (result i64)))
+  (import "starstream_utxo:this" "starstream_resume_sleeper" (func $wake (param i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+
+  ;; creating the UTXO is cheap, but the method never returns
+  (func $coord_spin
+    (call $spin (call $new_counter))
+  )
+
+  ;; the UTXO never gets as far as its first yield
+  (func $coord_create_spinner (result i64)
+    (call $new_spinner)
+  )
+
+  (func $coord_create_sleeper (result i64)
+    (call $new_sleeper)
+  )
+
+  ;; the UTXO, created by an earlier call, spins once resumed
+  (func $coord_wake (param $utxo i64)
+    (call $wake (local.get $utxo))
+  )
+
+  (func $forever
+    (loop $again
+      (br $again)
+    )
+  )
+
+  (func $counter
+    (loop $sleep
+      (call $yield (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0))
+      (br $sleep)
+    )
+  )
+
+  (func $sleeper
+    (call $yield (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0))
+    (call $forever)
+  )
+
+  (func $spin_method
+    (call $forever)
+  )
+
+  (export "coord_spin" (func $coord_spin))
+  (export "coord_create_spinner" (func $coord_create_spinner))
+  (export "coord_create_sleeper" (func $coord_create_sleeper))
+  (export "coord_wake" (func $coord_wake))
+  (export "starstream_new_counter" (func $counter))
+  (export "starstream_new_spinner" (func $forever))
+  (export "starstream_new_sleeper" (func $sleeper))
+  (export "starstream_mutate_spin" (func $spin_method))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,41 @@
+(module
+  (import "starstream_utxo:this" "starstream_new_counter" (func $new_counter (result i64)))
+  (import "starstream_utxo:this" "starstream_query_get" (func $get (param i64) (result i32)))
+  (import "starstream_utxo:this" "starstream_query_bump" (func $bump (param i64) (result i32)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+
+  (func $coord_get (result i32)
+    (call $get (call $new_counter))
+  )
+
+  (func $coord_bump (result i32)
+    (call $bump (call $new_counter))
+  )
+
+  ;; yields the counter at 100
+  (func $counter
+    (i32.store (i32.const 100) (i32.const 7))
+    (call $yield (i32.const 0) (i32.const 0) (i32.const 100) (i32.const 4) (i32.const 0) (i32.const 0))
+  )
+
+  ;; scratch memory outside of the yielded object is fair game
+  (func $get_value (result i32)
+    (i32.store (i32.const 200) (i32.load (i32.const 100)))
+    (i32.load (i32.const 200))
+  )
+
+  ;; declared as a query, but changes the counter
+  (func $bump_value (result i32)
+    (i32.store (i32.const 100) (i32.add (i32.load (i32.const 100)) (i32.const 1)))
+    (i32.load (i32.const 100))
+  )
+
+  (export "coord_get" (func $coord_get))
+  (export "coord_bump" (func $coord_bump))
+  (export "starstream_new_counter" (func $counter))
+  (export "starstream_query_get" (func $get_value))
+  (export "starstream_query_bump" (func $bump_value))
+  (export "memory" (memory $mem))
+)
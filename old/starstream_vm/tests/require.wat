@@ -0,0 +1,34 @@
+(module
+  (import "starstream_utxo:wat:require" "starstream_new_main" (func $make_utxo (result i64)))
+  (import "env" "starstream_require" (func $require (param i32 i32)))
+
+  (memory $mem 1)
+
+  ;; a precondition that holds lets execution continue
+  (func $coord_ok (result i32)
+    (call $require (i32.const 1) (i32.const 7))
+    i32.const 5
+  )
+
+  ;; fails directly from the coordination script
+  (func $coord
+    (call $require (i32.const 1) (i32.const 7))
+    (call $require (i32.const 0) (i32.const 42))
+    unreachable
+  )
+
+  ;; fails from inside a utxo
+  (func $coord_utxo (result i64)
+    call $make_utxo
+  )
+
+  (func $main
+    (call $require (i32.const 0) (i32.const 9))
+  )
+
+  (export "starstream_new_main" (func $main))
+  (export "coord_ok" (func $coord_ok))
+  (export "coord" (func $coord))
+  (export "coord_utxo" (func $coord_utxo))
+  (export "memory" (memory $mem))
+)
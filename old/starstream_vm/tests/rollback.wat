@@ -0,0 +1,48 @@
+(module
+  (import "starstream_utxo:wat:rollback" "starstream_new_main" (func $make_utxo (result i64)))
+  (import "starstream_utxo:wat:rollback" "starstream_resume_" (func $resume (param i64 i32)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+  (import "env" "starstream_require" (func $require (param i32 i32)))
+
+  (memory $mem 1)
+
+  (func $coord (result i64)
+    call $make_utxo
+  )
+
+  ;; creates a UTXO, then fails
+  (func $coord_fail
+    (drop (call $make_utxo))
+    (call $require (i32.const 0) (i32.const 9))
+  )
+
+  ;; resumes a UTXO from an earlier call, then fails
+  (func $coord_resume_fail (param $utxo i64)
+    (call $resume (local.get $utxo) (i32.const 0))
+    (call $require (i32.const 0) (i32.const 9))
+  )
+
+  (func $coord_resume (param $utxo i64)
+    (call $resume (local.get $utxo) (i32.const 0))
+  )
+
+  (func $main
+    (loop $forever
+      (call $yield (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0))
+      (br $forever)
+    )
+  )
+
+  ;; fails before its first yield
+  (func $main_fail
+    (call $require (i32.const 0) (i32.const 9))
+  )
+
+  (export "starstream_new_main" (func $main))
+  (export "starstream_new_main_fail" (func $main_fail))
+  (export "coord" (func $coord))
+  (export "coord_fail" (func $coord_fail))
+  (export "coord_resume_fail" (func $coord_resume_fail))
+  (export "coord_resume" (func $coord_resume))
+  (export "memory" (memory $mem))
+)
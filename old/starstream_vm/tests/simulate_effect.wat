@@ -0,0 +1,97 @@
+(module
+  (import "env" "starstream_register_effect_handler" (func $register (param i32 i32 i32)))
+  (import "env" "starstream_get_raised_effect_data" (func $get_data (param i32 i32 i32 i32 i32)))
+  (import "env" "starstream_resume_throwing_program" (func $resume_thrower (param i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "IsBlacklisted")
+  (data (i32.const 16) "Ignored")
+  (data (i32.const 32) "Failing")
+  (data (i32.const 48) "Counted")
+  (data (i32.const 112) "Count")
+
+  ;; registers the handlers and leaves them in place
+  (func $coord
+    i32.const 0
+    i32.const 13
+    i32.const 0
+    call $register
+    i32.const 16
+    i32.const 7
+    i32.const 0
+    call $register
+    i32.const 32
+    i32.const 7
+    i32.const 0
+    call $register
+    i32.const 48
+    i32.const 7
+    i32.const 0
+    call $register
+    i32.const 112
+    i32.const 5
+    i32.const 0
+    call $register
+  )
+
+  ;; an address is blacklisted when it's 3
+  (func $is_blacklisted_handle (param $handler i32)
+    i32.const 0
+    i32.const 13
+    i32.const 64
+    i32.const 4
+    i32.const 60
+    call $get_data
+
+    i32.const 80
+    i32.const 64
+    i32.load
+    i32.const 3
+    i32.eq
+    i32.store8
+
+    i32.const 0
+    i32.const 13
+    i32.const 80
+    call $resume_thrower
+  )
+
+  ;; counts how often it was handled once the raiser is resumed
+  (func $counted_handle (param $handler i32)
+    i32.const 48
+    i32.const 7
+    i32.const 96
+    call $resume_thrower
+
+    i32.const 96
+    i32.const 96
+    i32.load
+    i32.const 1
+    i32.add
+    i32.store
+  )
+
+  ;; resumes with the count kept by Counted
+  (func $count_handle (param $handler i32)
+    i32.const 112
+    i32.const 5
+    i32.const 96
+    call $resume_thrower
+  )
+
+  ;; returns without resuming
+  (func $ignored_handle (param $handler i32))
+
+  ;; traps before resuming
+  (func $failing_handle (param $handler i32)
+    unreachable
+  )
+
+  (export "coord" (func $coord))
+  (export "IsBlacklisted_handle" (func $is_blacklisted_handle))
+  (export "Counted_handle" (func $counted_handle))
+  (export "Count_handle" (func $count_handle))
+  (export "Ignored_handle" (func $ignored_handle))
+  (export "Failing_handle" (func $failing_handle))
+  (export "memory" (memory $mem))
+)
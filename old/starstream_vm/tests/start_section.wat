@@ -0,0 +1,11 @@
+(module
+  (memory $mem 1)
+
+  (func $init)
+
+  (func $coord)
+
+  (start $init)
+  (export "coord" (func $coord))
+  (export "memory" (memory $mem))
+)
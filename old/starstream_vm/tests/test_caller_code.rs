@@ -0,0 +1,36 @@
+use starstream_vm::{test_utils::ModuleBuilder, *};
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:caller_code");
+
+    let result = tx.run_coordination_script(&contract, "coord", vec![]);
+    assert_eq!(result.unwrap().i32(), Some(1));
+
+    let mut tx = Transaction::new();
+
+    // Same entry point, but minted from code the token doesn't trust.
+    let intruder = ModuleBuilder::new()
+        .import(
+            "starstream_utxo:wat:caller_code",
+            "starstream_mint_1",
+            "(param i64) (result i64)",
+        )
+        .memory(1)
+        .func(
+            "coord",
+            "",
+            "(drop (call $starstream_mint_1 (i64.const 10)))",
+        )
+        .load(tx.code_cache());
+
+    let result = tx.run_coordination_script(&intruder, "coord", vec![]);
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::RequireFailed { code: 1 }
+    );
+}
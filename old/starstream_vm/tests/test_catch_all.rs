@@ -0,0 +1,39 @@
+use starstream_vm::*;
+
+fn run(entry_point: &str) -> Option<[u8; 32]> {
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:catch_all");
+
+    let before = tx.utxo_state();
+    let utxo = tx
+        .run_coordination_script(&contract, entry_point, vec![])
+        .unwrap();
+    let id = tx.utxo_id(&utxo).unwrap();
+
+    let changes = Transaction::diff_utxo_state(&before, &tx.utxo_state());
+    changes[&id].output_after
+}
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    // E has no handler of its own, so the catch-all is invoked for it, with
+    // the address it was registered with.
+    assert_eq!(run("coord"), Some(output_commitment(&35u32.to_le_bytes())));
+
+    // F is still pending when the catch-all asks, but it's E the catch-all
+    // was called for.
+    assert_eq!(
+        run("coord_nested"),
+        Some(output_commitment(&40u32.to_le_bytes()))
+    );
+
+    // Without the catch-all, the UTXO sees that E isn't handled and doesn't
+    // raise it.
+    assert_eq!(
+        run("coord_unhandled"),
+        Some(output_commitment(&0u32.to_le_bytes()))
+    );
+}
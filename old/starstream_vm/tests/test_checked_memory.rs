@@ -0,0 +1,28 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new_checked();
+    let contract = tx.code_cache().load_debug("wat:checked_memory");
+    let result = tx.run_coordination_script(&contract, "coord_ok", vec![]);
+    assert!(result.is_ok(), "{result:?}");
+
+    let mut tx = Transaction::new_checked();
+    let contract = tx.code_cache().load_debug("wat:checked_memory");
+    let result = tx.run_coordination_script(&contract, "coord_bad", vec![]);
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::UndeclaredMemoryWrite {
+            address: 72,
+            len: 32
+        }
+    );
+
+    // Unchecked transactions don't care where the host writes.
+    let mut tx = Transaction::new();
+    let contract = tx.code_cache().load_debug("wat:checked_memory");
+    let result = tx.run_coordination_script(&contract, "coord_bad", vec![]);
+    assert!(result.is_ok(), "{result:?}");
+}
@@ -0,0 +1,96 @@
+use starstream_vm::*;
+use wasmi::Engine;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let cache = CodeCache::default();
+    let engine = Engine::default();
+
+    // malformed Wasm is only rejected once it's compiled
+    let malformed = cache.load(b"\0asm but not really".to_vec());
+    let error = malformed.try_module(&engine).unwrap_err();
+    assert!(matches!(error, CodeError::Compile(_)));
+    assert!(malformed.try_module(&engine).is_err());
+
+    let code = cache.load(wat::parse_str("(module (memory 1))").unwrap());
+    assert_eq!(code.compilations(), 0);
+
+    code.try_module(&engine).unwrap();
+    code.try_module(&engine).unwrap();
+    code.try_module(&engine).unwrap();
+    assert_eq!(code.compilations(), 1);
+
+    // each engine compiles its own module, once
+    let other_engine = Engine::default();
+    code.try_module(&other_engine).unwrap();
+    code.try_module(&other_engine).unwrap();
+    code.try_module(&engine).unwrap();
+    assert_eq!(code.compilations(), 2);
+
+    // engines nobody used for a while are forgotten, rather than kept around
+    // for every transaction ever run
+    let engines = (0..64).map(|_| Engine::default()).collect::<Vec<_>>();
+    for engine in &engines {
+        code.try_module(engine).unwrap();
+    }
+    assert_eq!(code.compilations(), 66);
+    code.try_module(engines.last().unwrap()).unwrap();
+    assert_eq!(code.compilations(), 66);
+    code.try_module(&engine).unwrap();
+    assert_eq!(code.compilations(), 67);
+}
+
+#[test]
+pub fn run_malformed() {
+    let mut tx = Transaction::new();
+
+    let malformed = tx.code_cache().load(b"\0asm but not really".to_vec());
+    let result = tx.run_coordination_script(&malformed, "main", vec![]);
+    assert!(matches!(
+        result,
+        Err(TransactionError::InvalidCode { code, .. }) if code == malformed.hash()
+    ));
+}
+
+#[test]
+pub fn cached_by_hash() {
+    let tx = Transaction::new();
+    let cache = tx.code_cache();
+
+    // malformed Wasm is compiled once, then the error is remembered
+    let malformed = cache.load(b"\0asm but not really".to_vec());
+    let error = tx.try_get_code(malformed.hash()).unwrap_err();
+    assert!(matches!(error, CodeError::Compile(_)));
+    let error = tx.try_get_code(malformed.hash()).unwrap_err();
+    assert!(matches!(error, CodeError::Compile(_)));
+    assert_eq!(malformed.compilations(), 1);
+
+    // checking the code leaves the module behind for the transaction to run
+    let code = cache.load(wat::parse_str("(module (memory 1))").unwrap());
+    tx.try_get_code(code.hash()).unwrap();
+    tx.try_get_code(code.hash()).unwrap();
+    code.try_module(tx.engine()).unwrap();
+    assert_eq!(code.compilations(), 1);
+
+    let unknown = CodeCache::default().load(wat::parse_str("(module)").unwrap());
+    let error = tx.try_get_code(unknown.hash()).unwrap_err();
+    assert!(matches!(error, CodeError::NotFound(_)));
+}
+
+#[test]
+pub fn checked_with_transaction_config() {
+    let wasm = wat::parse_str("(module (func (result i32 i32) i32.const 1 i32.const 2))").unwrap();
+
+    let mut config = Config::default();
+    config.wasm_multi_value(false);
+    let tx = Transaction::with_config(config);
+    let code = tx.code_cache().load(wasm.clone());
+    let error = tx.try_get_code(code.hash()).unwrap_err();
+    assert!(matches!(error, CodeError::Compile(_)));
+
+    let tx = Transaction::new();
+    let code = tx.code_cache().load(wasm);
+    tx.try_get_code(code.hash()).unwrap();
+}
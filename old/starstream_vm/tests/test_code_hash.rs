@@ -0,0 +1,34 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let empty = wat::parse_str("(module)").unwrap();
+    let memory = wat::parse_str("(module (memory 1))").unwrap();
+
+    for algorithm in [
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Keccak256,
+        HashAlgorithm::Blake3,
+    ] {
+        let cache = CodeCache::with_algorithm(algorithm);
+
+        let a = cache.load(empty.clone());
+        let b = cache.load(memory.clone());
+
+        assert_eq!(a.hash().algorithm(), algorithm);
+        assert_ne!(a.hash(), b.hash());
+        // content addressing is deterministic
+        assert_eq!(a.hash(), cache.load(empty.clone()).hash());
+    }
+
+    let keccak = CodeCache::with_algorithm(HashAlgorithm::Keccak256).load(empty.clone());
+    let blake3 = CodeCache::with_algorithm(HashAlgorithm::Blake3).load(empty.clone());
+
+    assert_ne!(keccak.hash(), blake3.hash());
+    assert_ne!(
+        format!("{:?}", keccak.hash()),
+        format!("{:?}", blake3.hash())
+    );
+}
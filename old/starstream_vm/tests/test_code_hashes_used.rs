@@ -0,0 +1,30 @@
+use starstream_vm::{test_utils::ModuleBuilder, *};
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+    assert!(tx.code_hashes_used().is_empty());
+
+    let utxo_code = tx.code_cache().load_debug("wat:double_consume");
+    let coordination = ModuleBuilder::new()
+        .import(
+            "starstream_utxo:wat:double_consume",
+            "starstream_new_main",
+            "(result i64)",
+        )
+        .memory(1)
+        .func("coord", "(result i64)", "(call $starstream_new_main)")
+        .load(tx.code_cache());
+
+    tx.run_coordination_script(&coordination, "coord", vec![])
+        .unwrap();
+    tx.run_coordination_script(&coordination, "coord", vec![])
+        .unwrap();
+
+    assert_eq!(
+        tx.code_hashes_used(),
+        vec![coordination.hash(), utxo_code.hash()]
+    );
+}
@@ -44,7 +44,8 @@ pub fn main() {
 
     let contract = tx.code_cache().load_file(&output_path);
 
-    tx.run_coordination_script(&contract, "main", vec![]);
+    tx.run_coordination_script(&contract, "main", vec![])
+        .unwrap();
 
     // tx.prove();
 }
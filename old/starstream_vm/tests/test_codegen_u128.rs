@@ -0,0 +1,56 @@
+use starstream_vm::*;
+use tempfile::TempDir;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let output_dir = TempDir::new().unwrap();
+
+    let mut output_path = output_dir.path().to_path_buf();
+    output_path.push("codegen.wasm");
+
+    let output = std::process::Command::new("cargo")
+        .arg("run")
+        .arg("--bin")
+        .arg("starstream")
+        .arg("compile")
+        .arg("-c")
+        .arg("grammar/examples/u128.star")
+        .arg("-o")
+        .arg(&output_path)
+        .current_dir("../")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_file(&output_path);
+
+    // the low half carries into the high half instead of overflowing
+    let a = u64::MAX as u128;
+    let b = 1;
+    let inputs = [a, b, a + b]
+        .into_iter()
+        .flat_map(u128_to_wasm)
+        .collect::<Vec<_>>();
+
+    let result = tx
+        .run_coordination_script(&contract, "main", inputs)
+        .unwrap();
+    assert_eq!(result.i32(), Some(1));
+
+    let inputs = [a, b, 0]
+        .into_iter()
+        .flat_map(u128_to_wasm)
+        .collect::<Vec<_>>();
+
+    let result = tx
+        .run_coordination_script(&contract, "main", inputs)
+        .unwrap();
+    assert_eq!(result.i32(), Some(0));
+
+    assert_eq!(u128_from_wasm(&u128_to_wasm(a + b)), Some(a + b));
+}
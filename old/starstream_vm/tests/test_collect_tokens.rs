@@ -0,0 +1,44 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:collect_tokens");
+
+    let utxo = tx
+        .run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+
+    let mut tokens = tx.consumed_tokens(utxo_id);
+    tokens.sort_by_key(|token| token.amount);
+    assert_eq!(
+        tokens,
+        vec![
+            ConsumedToken {
+                token_type_id: 1,
+                amount: 10
+            },
+            ConsumedToken {
+                token_type_id: 1,
+                amount: 20
+            },
+        ]
+    );
+
+    // Tokens the consume method unbinds itself aren't left to collect.
+    let utxo = tx
+        .run_coordination_script(&contract, "coord_unbinding", vec![])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+    assert_eq!(
+        tx.consumed_tokens(utxo_id),
+        vec![ConsumedToken {
+            token_type_id: 1,
+            amount: 20
+        }]
+    );
+}
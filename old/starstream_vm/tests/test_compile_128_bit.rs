@@ -0,0 +1,84 @@
+use starstream_vm::*;
+
+const SOURCE: &str = r#"
+script {
+  fn less_u128(a: u128, b: u128): bool {
+    a < b
+  }
+
+  fn at_most_u128(a: u128, b: u128): bool {
+    a <= b
+  }
+
+  fn greater_i128(a: i128, b: i128): bool {
+    a > b
+  }
+
+  fn at_least_i128(a: i128, b: i128): bool {
+    a >= b
+  }
+
+  fn negates_to(a: i128, expected: i128): bool {
+    -a == expected
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    let mut run = |name: &str, args: &[u128]| {
+        tx.run_coordination_script(
+            &contract,
+            name,
+            args.iter().copied().flat_map(u128_to_wasm).collect(),
+        )
+        .unwrap()
+        .i32()
+    };
+
+    // The high halves decide, even when the low halves disagree.
+    let low_max = u64::MAX as u128;
+    let high_one = 1u128 << 64;
+
+    assert_eq!(run("less_u128", &[low_max, high_one]), Some(1));
+    assert_eq!(run("less_u128", &[high_one, low_max]), Some(0));
+    assert_eq!(run("less_u128", &[high_one, high_one + 1]), Some(1));
+    assert_eq!(run("less_u128", &[high_one, high_one]), Some(0));
+    assert_eq!(run("at_most_u128", &[high_one, high_one]), Some(1));
+    assert_eq!(run("at_most_u128", &[u128::MAX, low_max]), Some(0));
+
+    // -1 has every bit set, which an unsigned comparison would put last.
+    let minus_one = -1i128 as u128;
+
+    assert_eq!(run("greater_i128", &[1, minus_one]), Some(1));
+    assert_eq!(run("greater_i128", &[minus_one, 1]), Some(0));
+    assert_eq!(
+        run("greater_i128", &[i128::MIN as u128, minus_one]),
+        Some(0)
+    );
+    assert_eq!(run("at_least_i128", &[minus_one, minus_one]), Some(1));
+    assert_eq!(run("at_least_i128", &[low_max, high_one]), Some(0));
+
+    // Negating borrows from the high half unless the low half is zero.
+    assert_eq!(run("negates_to", &[1, minus_one]), Some(1));
+    assert_eq!(
+        run("negates_to", &[high_one, (-(1i128 << 64)) as u128]),
+        Some(1)
+    );
+    assert_eq!(
+        run("negates_to", &[low_max, (-(u64::MAX as i128)) as u128]),
+        Some(1)
+    );
+    assert_eq!(run("negates_to", &[0, 0]), Some(1));
+    assert_eq!(run("negates_to", &[1, 1]), Some(0));
+}
@@ -0,0 +1,62 @@
+use starstream_vm::*;
+
+const SOURCE: &str = r#"
+abi Table {
+  fn get(i: u32): u32;
+}
+
+utxo Squares {
+  storage {
+    values: [u32; 4];
+  }
+
+  main(offset: u32) {
+    for i in 0..4 {
+      storage.values[i] = i * i + offset;
+    }
+    yield;
+  }
+
+  impl Table {
+    fn get(i: u32): u32 {
+      storage.values[i]
+    }
+  }
+}
+
+script {
+  fn read(i: u32): u32 / { StarstreamEnv } {
+    let squares = Squares::new(10);
+    squares.get(i)
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    let result = tx
+        .run_coordination_script(&contract, "read", vec![Value::I32(0)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(10));
+
+    let result = tx
+        .run_coordination_script(&contract, "read", vec![Value::I32(3)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(19));
+
+    // past the end of the array traps instead of reading the next field
+    assert!(
+        tx.run_coordination_script(&contract, "read", vec![Value::I32(4)])
+            .is_err()
+    );
+}
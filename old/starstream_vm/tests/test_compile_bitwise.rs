@@ -0,0 +1,70 @@
+use starstream_vm::*;
+
+const SOURCE: &str = r#"
+script {
+  fn and32(a: u32, b: u32): u32 {
+    a & b
+  }
+
+  fn xor32(a: u32, b: u32): u32 {
+    a ^ b
+  }
+
+  fn and64(a: u64, b: u64): u64 {
+    a & b
+  }
+
+  fn xor64(a: u64, b: u64): u64 {
+    a ^ b
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    let result = tx
+        .run_coordination_script(
+            &contract,
+            "and32",
+            vec![Value::I32(0b1100), Value::I32(0b1010)],
+        )
+        .unwrap();
+    assert_eq!(result.i32(), Some(0b1000));
+
+    let result = tx
+        .run_coordination_script(
+            &contract,
+            "xor32",
+            vec![Value::I32(0b1100), Value::I32(0b1010)],
+        )
+        .unwrap();
+    assert_eq!(result.i32(), Some(0b0110));
+
+    let result = tx
+        .run_coordination_script(
+            &contract,
+            "and64",
+            vec![Value::I64(0b1100), Value::I64(0b1010)],
+        )
+        .unwrap();
+    assert_eq!(result.i64(), Some(0b1000));
+
+    let result = tx
+        .run_coordination_script(
+            &contract,
+            "xor64",
+            vec![Value::I64(0b1100), Value::I64(0b1010)],
+        )
+        .unwrap();
+    assert_eq!(result.i64(), Some(0b0110));
+}
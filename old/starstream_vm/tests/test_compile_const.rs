@@ -0,0 +1,63 @@
+use starstream_vm::*;
+
+const SOURCE: &str = r#"
+const ALMOST_MAX: u64 = (0xFFFFFFFF << 32) | 0xFFFFFFFE;
+const ENABLED: bool = ALMOST_MAX > 0xFFFFFFFF;
+const N = 4;
+
+script {
+  fn almost_max(): u64 {
+    ALMOST_MAX
+  }
+
+  fn enabled(): bool {
+    ENABLED
+  }
+
+  fn below_max(x: u64): bool {
+    ENABLED && x < ALMOST_MAX
+  }
+
+  fn times_n(x: u32): u32 {
+    x * N
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    let result = tx
+        .run_coordination_script(&contract, "almost_max", vec![])
+        .unwrap();
+    assert_eq!(result.i64(), Some((u64::MAX - 1) as i64));
+
+    let result = tx
+        .run_coordination_script(&contract, "enabled", vec![])
+        .unwrap();
+    assert_eq!(result.i32(), Some(1));
+
+    let result = tx
+        .run_coordination_script(&contract, "below_max", vec![Value::I64(i64::MAX)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(1));
+
+    let result = tx
+        .run_coordination_script(&contract, "below_max", vec![Value::I64(-1)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(0));
+
+    let result = tx
+        .run_coordination_script(&contract, "times_n", vec![Value::I32(3)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(12));
+}
@@ -0,0 +1,51 @@
+use starstream_compiler::Optimizations;
+use starstream_vm::*;
+
+const SOURCE: &str = r#"
+script {
+  fn sum(): u32 {
+    2147483647 + 1
+  }
+
+  fn shifted(x: u32): u32 {
+    x >> (30 + 1)
+  }
+
+  fn decided(): bool {
+    !(1 + 1 == 2) || 3 < 2
+  }
+}
+"#;
+
+/// Folding constants changes the code, but not what it does.
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    for fold_constants in [false, true] {
+        let optimizations = Optimizations { fold_constants };
+        let wasm = starstream_compiler::compile_to_wasm_with(SOURCE, &[], optimizations)
+            .unwrap_or_else(|errors| {
+                panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+            });
+
+        let mut tx = Transaction::new();
+
+        let contract = tx.code_cache().load(wasm);
+
+        let result = tx
+            .run_coordination_script(&contract, "sum", vec![])
+            .unwrap();
+        assert_eq!(result.i32(), Some(i32::MIN));
+
+        let result = tx
+            .run_coordination_script(&contract, "shifted", vec![Value::I32(-1)])
+            .unwrap();
+        assert_eq!(result.i32(), Some(1));
+
+        let result = tx
+            .run_coordination_script(&contract, "decided", vec![])
+            .unwrap();
+        assert_eq!(result.i32(), Some(0));
+    }
+}
@@ -0,0 +1,64 @@
+use starstream_vm::*;
+
+const SOURCE: &str = r#"
+script {
+  fn sum_below(n: u32): u32 {
+    let mut total = 0;
+    for i in 0..n {
+      total = total + i;
+    }
+    total
+  }
+
+  fn sum_odd_below(n: u32): u32 {
+    let mut total = 0;
+    for i in 0..n {
+      if (i % 2 == 0) {
+        continue;
+      }
+      total = total + i;
+    }
+    total
+  }
+
+  fn count_while_shrinking(n: u32): u32 {
+    let mut end = n;
+    let mut count = 0;
+    for _i in 0..end {
+      end = 0;
+      count = count + 1;
+    }
+    count
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    let result = tx
+        .run_coordination_script(&contract, "sum_below", vec![Value::I32(5)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(10));
+
+    // `continue` still moves on to the next value
+    let result = tx
+        .run_coordination_script(&contract, "sum_odd_below", vec![Value::I32(5)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(4));
+
+    // the end of the range is evaluated once, before the loop
+    let result = tx
+        .run_coordination_script(&contract, "count_while_shrinking", vec![Value::I32(5)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(5));
+}
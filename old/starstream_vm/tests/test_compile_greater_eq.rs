@@ -0,0 +1,32 @@
+use starstream_vm::*;
+
+const SOURCE: &str = r#"
+script {
+  fn at_least(a: u32, b: u32): bool {
+    a >= b
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    let result = tx
+        .run_coordination_script(&contract, "at_least", vec![Value::I32(3), Value::I32(3)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(1));
+
+    let result = tx
+        .run_coordination_script(&contract, "at_least", vec![Value::I32(2), Value::I32(3)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(0));
+}
@@ -0,0 +1,42 @@
+use starstream_vm::*;
+
+/// Lists are kept by the host, the contract only holds a handle to one.
+const SOURCE: &str = r#"
+script {
+  fn count(a: Intermediate<any, any>, b: Intermediate<any, any>): u32 {
+    let list = List::new();
+    list.push(a);
+    List::push(list, b);
+    list.len()
+  }
+
+  fn len(list: List): u32 {
+    list.len()
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    let count = tx
+        .run_coordination_script(&contract, "count", vec![Value::I64(1), Value::I64(2)])
+        .unwrap();
+    assert_eq!(count.i32(), Some(2));
+
+    let result = tx.run_coordination_script(&contract, "len", vec![Value::I64(42)]);
+    assert!(
+        matches!(result, Err(TransactionError::InvalidListHandle)),
+        "{:?}",
+        result.err()
+    );
+}
@@ -0,0 +1,66 @@
+use starstream_vm::*;
+
+const SOURCE: &str = r#"
+typedef Shape = enum {
+  Empty(),
+  Square(side: u32),
+  Rect(width: u32, height: u32)
+}
+
+script {
+  fn empty_area(): u32 {
+    let shape = Empty();
+    match (shape) {
+      Empty() => { 0 },
+      Square(side) => { side * side },
+      Rect(width, height) => { width * height },
+    }
+  }
+
+  fn square_area(n: u32): u32 {
+    let shape = Square(n);
+    match (shape) {
+      Empty() => { 0 },
+      Square(side) => { side * side },
+      Rect(width, height) => { width * height },
+    }
+  }
+
+  fn rect_area(n: u32): u32 {
+    let shape = Rect(n, n + 1);
+    match (shape) {
+      Empty() => { 0 },
+      Square(side) => { side * side },
+      Rect(width, height) => { width * height },
+    }
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    let result = tx
+        .run_coordination_script(&contract, "empty_area", vec![])
+        .unwrap();
+    assert_eq!(result.i32(), Some(0));
+
+    let result = tx
+        .run_coordination_script(&contract, "square_area", vec![Value::I32(3)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(9));
+
+    let result = tx
+        .run_coordination_script(&contract, "rect_area", vec![Value::I32(3)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(12));
+}
@@ -0,0 +1,37 @@
+use starstream_vm::*;
+
+const SOURCE: &str = r#"
+script {
+  fn rem_u32(a: u32, b: u32): u32 {
+    a % b
+  }
+
+  fn rem_i32(a: i32, b: i32): i32 {
+    a % b
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    let result = tx
+        .run_coordination_script(&contract, "rem_u32", vec![Value::I32(7), Value::I32(3)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(1));
+
+    // the remainder takes the sign of the dividend
+    let result = tx
+        .run_coordination_script(&contract, "rem_i32", vec![Value::I32(-7), Value::I32(3)])
+        .unwrap();
+    assert_eq!(result.i32(), Some(-1));
+}
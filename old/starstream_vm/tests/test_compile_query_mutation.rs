@@ -0,0 +1,69 @@
+use starstream_vm::*;
+
+/// Every method of a compiled UTXO is a query, so `bump` isn't allowed to
+/// change the storage the counter yielded.
+const SOURCE: &str = r#"
+abi CounterAbi {
+  fn get(): u32;
+  fn bump(): u32;
+}
+
+utxo Counter {
+  storage {
+    value: u32;
+  }
+
+  main {
+    storage.value = 7;
+    yield;
+  }
+
+  impl CounterAbi {
+    fn get(): u32 {
+      storage.value
+    }
+
+    fn bump(): u32 {
+      storage.value = storage.value + 1;
+      storage.value
+    }
+  }
+}
+
+script {
+  fn get(): u32 {
+    let counter = Counter::new();
+    counter.get()
+  }
+
+  fn bump(): u32 {
+    let counter = Counter::new();
+    counter.bump()
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    let value = tx
+        .run_coordination_script(&contract, "get", vec![])
+        .unwrap();
+    assert_eq!(value.i32(), Some(7));
+
+    let result = tx.run_coordination_script(&contract, "bump", vec![]);
+    assert!(
+        matches!(result, Err(TransactionError::QueryMutatedState { .. })),
+        "{:?}",
+        result.err()
+    );
+}
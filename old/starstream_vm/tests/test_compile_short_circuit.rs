@@ -0,0 +1,69 @@
+use starstream_vm::*;
+
+/// Each method's right operand raises `Check`, whose handler only accepts
+/// the one call that isn't short-circuited.
+const SOURCE: &str = r#"
+abi A {
+  effect Check(u32): bool;
+}
+
+abi Probe {
+  fn and_false(): bool;
+  fn or_true(): bool;
+  fn and_true(): bool;
+}
+
+utxo Checker {
+  main {
+    yield;
+  }
+
+  impl A {}
+
+  impl Probe {
+    fn and_false(): bool {
+      false && raise A::Check(1)
+    }
+
+    fn or_true(): bool {
+      true || raise A::Check(2)
+    }
+
+    fn and_true(): bool {
+      true && raise A::Check(3)
+    }
+  }
+}
+
+script {
+  fn main() / { StarstreamEnv } {
+    try {
+      let checker = Checker::new();
+
+      assert(!checker.and_false());
+      assert(checker.or_true());
+      assert(checker.and_true());
+    }
+    with A::Check(i: u32) {
+      assert(i == 3);
+      resume true;
+    }
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    tx.run_coordination_script(&contract, "main", vec![])
+        .unwrap();
+}
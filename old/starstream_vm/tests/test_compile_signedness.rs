@@ -0,0 +1,75 @@
+use starstream_vm::*;
+
+const SOURCE: &str = r#"
+script {
+  fn less_u64(a: u64, b: u64): bool {
+    a < b
+  }
+
+  fn at_least_u64(a: u64, b: u64): bool {
+    a >= b
+  }
+
+  fn less_i64(a: i64, b: i64): bool {
+    a < b
+  }
+
+  fn div_u64(a: u64, b: u64): u64 {
+    a / b
+  }
+
+  fn shr_u64(a: u64, b: u64): u64 {
+    a >> b
+  }
+
+  fn shr_i64(a: i64, b: i64): i64 {
+    a >> b
+  }
+
+  fn sum_less_u64(a: u64, b: u64, c: u64): bool {
+    a + b < c
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    let mut run = |name: &str, args: &[i64]| {
+        tx.run_coordination_script(
+            &contract,
+            name,
+            args.iter().map(|&n| Value::I64(n)).collect(),
+        )
+        .unwrap()
+    };
+
+    // u64::MAX is -1 as an i64, which a signed comparison would put first.
+    let max = u64::MAX as i64;
+    let above_i64_max = (i64::MAX as u64 + 1) as i64;
+
+    assert_eq!(run("less_u64", &[1, max]).i32(), Some(1));
+    assert_eq!(run("less_u64", &[max, 1]).i32(), Some(0));
+    assert_eq!(run("less_u64", &[i64::MAX, above_i64_max]).i32(), Some(1));
+    assert_eq!(
+        run("at_least_u64", &[above_i64_max, i64::MAX]).i32(),
+        Some(1)
+    );
+    assert_eq!(run("less_i64", &[-1, 1]).i32(), Some(1));
+
+    assert_eq!(run("div_u64", &[max, 2]).i64(), Some(i64::MAX));
+    assert_eq!(run("shr_u64", &[max, 1]).i64(), Some(i64::MAX));
+    assert_eq!(run("shr_i64", &[-2, 1]).i64(), Some(-1));
+
+    // Sums keep their signedness.
+    assert_eq!(run("sum_less_u64", &[i64::MAX, 1, max]).i32(), Some(1));
+}
@@ -0,0 +1,51 @@
+use starstream_vm::*;
+
+/// The PayToPublicKeyHash example, with a way to read the owner back out.
+const SOURCE: &str = r#"
+abi HasOwner {
+  fn get_owner(): PublicKey;
+}
+
+utxo PayToPublicKeyHash {
+  storage {
+    owner: PublicKey;
+  }
+
+  main(owner: PublicKey) {
+    storage.owner = owner;
+    yield;
+    assert(IsTxSignedBy(owner));
+  }
+
+  impl HasOwner {
+    fn get_owner(): PublicKey {
+      storage.owner
+    }
+  }
+}
+
+script {
+  fn main(): PublicKey / { StarstreamEnv } {
+    let input = PayToPublicKeyHash::new(113);
+    input.get_owner()
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    let owner = tx
+        .run_coordination_script(&contract, "main", vec![])
+        .unwrap();
+    assert!(matches!(owner, Value::I32(113)));
+}
@@ -0,0 +1,51 @@
+use starstream_vm::*;
+
+const SOURCE: &str = r#"
+utxo Counter {
+  storage {
+    value: u32;
+  }
+
+  main(start: u32) {
+    storage.value = start;
+    yield;
+  }
+}
+
+script {
+  fn create(start: u32): Counter {
+    Counter::new(start)
+  }
+}
+"#;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let wasm = starstream_compiler::compile_to_wasm(SOURCE, &[]).unwrap_or_else(|errors| {
+        panic!("{}", starstream_compiler::format_reports(SOURCE, &errors))
+    });
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load(wasm);
+
+    let start = tx.utxo_state();
+
+    let utxo = tx
+        .run_coordination_script(&contract, "create", vec![Value::I32(7)])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+
+    // the commitment is to the storage the UTXO yielded
+    let changes = Transaction::diff_utxo_state(&start, &tx.utxo_state());
+    assert_eq!(
+        changes[&utxo_id],
+        UtxoChange {
+            output_before: None,
+            output_after: Some(output_commitment(&7u32.to_le_bytes())),
+            consumed: false,
+        }
+    );
+}
@@ -0,0 +1,23 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:coordination_code");
+
+    // No coordination script is running, so there is no code to report.
+    let before = tx.utxo_state();
+    let utxo = tx
+        .new_utxo_direct(&contract, "starstream_new_main_new", vec![])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+
+    let changes = Transaction::diff_utxo_state(&before, &tx.utxo_state());
+    assert_eq!(
+        changes[&utxo_id].output_after,
+        Some(output_commitment(&[0; 32]))
+    );
+}
@@ -0,0 +1,65 @@
+use std::time::{Duration, Instant};
+
+use starstream_vm::{test_utils::ModuleBuilder, *};
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let mut tx = Transaction::new();
+
+    tx.code_cache().load_debug("wat:direct_query");
+    let coordination = ModuleBuilder::new()
+        .import(
+            "starstream_utxo:wat:direct_query",
+            "starstream_new_source",
+            "(param i32) (result i64)",
+        )
+        .import(
+            "starstream_utxo:wat:direct_query",
+            "starstream_query_get",
+            "(param i64) (result i32)",
+        )
+        .memory(1)
+        .func(
+            "coord",
+            "(result i32)",
+            "(call $starstream_query_get (call $starstream_new_source (i32.const 7)))",
+        )
+        // Queries the same UTXO forever. There is plenty of fuel for that, so
+        // only the deadline stops it.
+        .func(
+            "coord_spin",
+            "(local $utxo i64)",
+            "(local.set $utxo (call $starstream_new_source (i32.const 7)))
+            (loop $spin
+              (drop (call $starstream_query_get (local.get $utxo)))
+              (br $spin))",
+        )
+        .load(tx.code_cache());
+
+    let start = Instant::now();
+    let result = tx.run_coordination_script_with_deadline(
+        &coordination,
+        "coord_spin",
+        vec![],
+        start + Duration::from_millis(200),
+    );
+    assert_eq!(result.unwrap_err(), TransactionError::Timeout);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(tx.utxos().is_empty());
+
+    // A call that finishes in time is unaffected, and the deadline doesn't
+    // carry over to later calls.
+    let value = tx
+        .run_coordination_script_with_deadline(
+            &coordination,
+            "coord",
+            vec![],
+            Instant::now() + Duration::from_secs(60),
+        )
+        .unwrap();
+    assert!(matches!(value, Value::I32(7)));
+    tx.run_coordination_script(&coordination, "coord", vec![])
+        .unwrap();
+}
@@ -0,0 +1,105 @@
+use starstream_vm::*;
+
+#[test]
+pub fn wat() {
+    let tx = Transaction::new();
+    let contract = tx.code_cache().load_debug("wat:require");
+    let info = contract.describe(tx.engine()).unwrap();
+
+    assert!(info.imports.contains(&ImportInfo {
+        module: "env".to_owned(),
+        name: "starstream_require".to_owned(),
+        ty: "(func (param i32 i32))".to_owned(),
+    }));
+    assert!(info.exports.contains(&ExportInfo {
+        name: "memory".to_owned(),
+        ty: "(memory 1)".to_owned(),
+    }));
+}
+
+#[test]
+pub fn wat_query_exports() {
+    let tx = Transaction::new();
+    let contract = tx.code_cache().load_debug("wat:direct_query");
+
+    assert_eq!(
+        contract.query_exports(tx.engine()).unwrap(),
+        [
+            QueryExport {
+                name: "starstream_query_get".to_owned(),
+                params: vec![],
+                result: Some("i32".to_owned()),
+            },
+            QueryExport {
+                name: "starstream_query_relay".to_owned(),
+                params: vec!["i64".to_owned()],
+                result: Some("i32".to_owned()),
+            },
+        ]
+    );
+}
+
+#[ignore]
+#[test]
+pub fn example_contract() {
+    std::process::Command::new("cargo")
+        .arg("build")
+        .arg("-p")
+        .arg("example_contract")
+        .status()
+        .unwrap();
+
+    let tx = Transaction::new();
+    let contract = tx.code_cache().load_debug("example_contract");
+    let info = contract.describe(tx.engine()).unwrap();
+
+    assert!(info.imports.contains(&ImportInfo {
+        module: "starstream_utxo_env".to_owned(),
+        name: "starstream_yield".to_owned(),
+        ty: "(func (param i32 i32 i32 i32 i32 i32))".to_owned(),
+    }));
+    assert!(info.imports.iter().any(|import| {
+        import.module == "starstream_utxo:example_contract"
+            && import.name == "starstream_new_StarToken_new"
+    }));
+    assert!(info.exports.iter().any(|export| export.name == "star_mint"));
+}
+
+#[ignore]
+#[test]
+pub fn example_contract_query_exports() {
+    std::process::Command::new("cargo")
+        .arg("build")
+        .arg("-p")
+        .arg("example_contract")
+        .status()
+        .unwrap();
+
+    let tx = Transaction::new();
+    let contract = tx.code_cache().load_debug("example_contract");
+    let queries = contract.query_exports(tx.engine()).unwrap();
+
+    let query = |name: &str| queries.iter().find(|query| query.name == name).unwrap();
+
+    assert_eq!(
+        query("starstream_query_MyMain_get_supply"),
+        &QueryExport {
+            name: "starstream_query_MyMain_get_supply".to_owned(),
+            params: vec!["i32".to_owned()],
+            result: Some("i32".to_owned()),
+        }
+    );
+    assert_eq!(
+        query("starstream_query_StarToken_get_amount").result,
+        Some("i64".to_owned())
+    );
+    // PublicKey is returned through a pointer rather than as a value
+    assert_eq!(
+        query("starstream_query_StarToken_get_owner"),
+        &QueryExport {
+            name: "starstream_query_StarToken_get_owner".to_owned(),
+            params: vec!["i32".to_owned(), "i32".to_owned()],
+            result: None,
+        }
+    );
+}
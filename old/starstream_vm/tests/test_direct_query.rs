@@ -0,0 +1,34 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:direct_query");
+
+    let doubled = tx
+        .run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+    assert!(matches!(doubled, Value::I32(42)));
+
+    let relayed = tx
+        .run_coordination_script(&contract, "coord_relay", vec![])
+        .unwrap();
+    assert!(matches!(relayed, Value::I32(5)));
+
+    let result = tx.run_coordination_script(&contract, "coord_reentrant", vec![]);
+    assert!(matches!(
+        result,
+        Err(TransactionError::DirectQueryReentrancy { .. })
+    ));
+
+    // The raiser is suspended halfway through its main, not at a yield.
+    let result = tx.run_coordination_script(&contract, "coord_mid_raise", vec![]);
+    assert!(
+        matches!(result, Err(TransactionError::BadInterruptState { .. })),
+        "{:?}",
+        result.err()
+    );
+}
@@ -0,0 +1,16 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:double_consume");
+
+    let result = tx.run_coordination_script(&contract, "coord", vec![]);
+    assert!(matches!(
+        result,
+        Err(TransactionError::UtxoAlreadyConsumed { .. })
+    ));
+}
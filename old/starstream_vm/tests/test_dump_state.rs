@@ -0,0 +1,51 @@
+use starstream_vm::{test_utils::ModuleBuilder, *};
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+    assert_eq!(tx.dump_state(), TransactionState { programs: vec![] });
+
+    let utxo_code = tx.code_cache().load_debug("wat:double_consume");
+    let coordination = ModuleBuilder::new()
+        .import(
+            "starstream_utxo:wat:double_consume",
+            "starstream_new_main",
+            "(result i64)",
+        )
+        .memory(1)
+        .func("coord", "(result i64)", "(call $starstream_new_main)")
+        .load(tx.code_cache());
+
+    let utxo = tx
+        .run_coordination_script(&coordination, "coord", vec![])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+
+    // Reading the state doesn't run anything.
+    let fuel = tx.fuel_consumed();
+    let state = tx.dump_state();
+    assert_eq!(tx.fuel_consumed(), fuel);
+    assert_eq!(tx.dump_state(), state);
+
+    assert_eq!(
+        state.programs,
+        vec![
+            ProgramState {
+                index: 0,
+                code: coordination.hash(),
+                entry_point: "coord".to_owned(),
+                utxo: None,
+                live: false,
+            },
+            ProgramState {
+                index: 1,
+                code: utxo_code.hash(),
+                entry_point: "starstream_new_main".to_owned(),
+                utxo: Some(utxo_id.into()),
+                live: true,
+            },
+        ]
+    );
+}
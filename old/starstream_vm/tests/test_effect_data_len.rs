@@ -0,0 +1,36 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:effect_data_len");
+
+    tx.run_coordination_script(&contract, "coord_exact", vec![])
+        .unwrap();
+
+    let result = tx.run_coordination_script(&contract, "coord_oversized", vec![]);
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::EffectDataOutOfBounds {
+            effect: "E".to_owned(),
+            data: 100,
+            data_len: 0x7fffffff,
+        }
+    );
+
+    let result = tx.run_coordination_script(&contract, "coord_mismatch", vec![]);
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::EffectDataMismatch {
+            effect: "E".to_owned(),
+            data_len: 8,
+            output_len: 4,
+        }
+    );
+
+    // Neither failed call left a UTXO behind.
+    assert_eq!(tx.utxos().len(), 1);
+}
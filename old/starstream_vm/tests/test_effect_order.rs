@@ -0,0 +1,30 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:effect_order");
+
+    let before = tx.utxo_state();
+    let first = tx
+        .run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+    let first_id = tx.utxo_id(&first).unwrap();
+
+    // The nested raise is resumed first, then the outer one is still there to
+    // be resumed by its own handler.
+    let changes = Transaction::diff_utxo_state(&before, &tx.utxo_state());
+    assert_eq!(changes.len(), 2);
+    assert_eq!(
+        changes[&first_id].output_after,
+        Some(output_commitment(&10u32.to_le_bytes()))
+    );
+    let (_, second) = changes.iter().find(|(id, _)| **id != first_id).unwrap();
+    assert_eq!(
+        second.output_after,
+        Some(output_commitment(&20u32.to_le_bytes()))
+    );
+}
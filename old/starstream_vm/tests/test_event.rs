@@ -0,0 +1,18 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:event");
+
+    tx.run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+
+    let events = tx.events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].name, "minted");
+    assert_eq!(events[0].data, b"supply");
+}
@@ -18,25 +18,54 @@ pub fn main() {
 
     let example_contract = tx.code_cache().load_debug("example_contract");
 
-    tx.run_coordination_script(&example_contract, "produce_effect", vec![]);
+    tx.run_coordination_script(&example_contract, "produce_effect", vec![])
+        .unwrap();
     dbg!(&tx);
 
-    let a = tx.run_coordination_script(&example_contract, "star_mint", vec![Value::I64(17)]);
-    let b = tx.run_coordination_script(&example_contract, "star_mint", vec![Value::I64(20)]);
-    let c = tx.run_coordination_script(&example_contract, "star_combine", vec![a, b]);
-    tx.run_coordination_script(&example_contract, "star_split", vec![c, Value::I64(5)]);
+    let a = tx
+        .run_coordination_script(&example_contract, "star_mint", vec![Value::I64(17)])
+        .unwrap();
+    let b = tx
+        .run_coordination_script(&example_contract, "star_mint", vec![Value::I64(20)])
+        .unwrap();
+    let c = tx
+        .run_coordination_script(&example_contract, "star_combine", vec![a, b])
+        .unwrap();
+    tx.run_coordination_script(&example_contract, "star_split", vec![c, Value::I64(5)])
+        .unwrap();
     dbg!(&tx);
 
-    let nft_contract = tx.run_coordination_script(&example_contract, "new_nft", vec![]);
+    let nft_contract = tx
+        .run_coordination_script(&example_contract, "new_nft", vec![])
+        .unwrap();
     tx.run_coordination_script(
         &example_contract,
         "star_nft_mint_to",
         vec![nft_contract.clone() /* owner */],
-    );
+    )
+    .unwrap();
     tx.run_coordination_script(
         &example_contract,
         "star_nft_mint_count",
         vec![nft_contract, /* owner, */ Value::I64(4)],
-    );
+    )
+    .unwrap();
     dbg!(&tx);
+
+    // 1000.000000 at 1.5% interest
+    let savings = tx
+        .run_coordination_script(
+            &example_contract,
+            "savings_open",
+            vec![Value::I64(1_000_000_000)],
+        )
+        .unwrap();
+    let balance = tx
+        .run_coordination_script(
+            &example_contract,
+            "savings_accrue",
+            vec![savings, Value::I64(15_000)],
+        )
+        .unwrap();
+    assert!(matches!(balance, Value::I64(1_015_000_000)));
 }
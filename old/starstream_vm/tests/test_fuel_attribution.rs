@@ -0,0 +1,28 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:fuel_attribution");
+
+    tx.run_coordination_script(&contract, "coord_create_light", vec![])
+        .unwrap();
+    tx.run_coordination_script(&contract, "coord_create_heavy", vec![])
+        .unwrap();
+
+    let continuations = tx.map_continuations();
+    let fuel_of = |entry_point: &str| {
+        continuations
+            .iter()
+            .find(|entry| entry.entry_point() == entry_point)
+            .unwrap_or_else(|| panic!("no continuation for {entry_point}"))
+            .fuel()
+    };
+
+    let light = fuel_of("starstream_new_light");
+    let heavy = fuel_of("starstream_new_heavy");
+    assert!(heavy > light, "heavy: {heavy}, light: {light}");
+}
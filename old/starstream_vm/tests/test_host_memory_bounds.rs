@@ -0,0 +1,29 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:host_memory_bounds");
+
+    tx.run_coordination_script(&contract, "coord_ok", vec![])
+        .unwrap();
+
+    // Out of range pointers trap the contract instead of panicking the host.
+    for entry_point in [
+        "coord_eprint",
+        "coord_this_code",
+        "coord_keccak256_input",
+        "coord_keccak256_output",
+        "coord_effect_name",
+    ] {
+        let result = tx.run_coordination_script(&contract, entry_point, vec![]);
+        assert!(
+            matches!(result, Err(TransactionError::Trap { .. })),
+            "{entry_point}: {:?}",
+            result.err()
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:utxo_changes");
+
+    let utxo = tx
+        .run_coordination_script(&contract, "coord_create", vec![])
+        .unwrap();
+    let utxo_id = UtxoIdBytes::from(tx.utxo_id(&utxo).unwrap());
+
+    let created = tx.live_utxo_ids();
+    assert_eq!(created.len(), 1);
+    assert_eq!(created[0].0, utxo_id);
+    assert_eq!(created[0].0.to_hex().len(), 32);
+
+    // still alive after being resumed, under the same id
+    tx.run_coordination_script(&contract, "coord_resume", vec![utxo.clone()])
+        .unwrap();
+    assert_eq!(tx.live_utxo_ids(), created);
+
+    // and gone once it finishes
+    tx.run_coordination_script(&contract, "coord_resume", vec![utxo])
+        .unwrap();
+    assert!(tx.live_utxo_ids().is_empty());
+}
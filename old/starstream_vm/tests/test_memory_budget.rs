@@ -0,0 +1,21 @@
+use starstream_vm::*;
+
+const PAGE: usize = 64 * 1024;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    // Room for the coordination script and three UTXOs.
+    let mut tx = Transaction::with_memory_budget(4 * PAGE);
+
+    let contract = tx.code_cache().load_debug("wat:memory_budget");
+
+    let result = tx.run_coordination_script(&contract, "coord", vec![]);
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::MemoryBudgetExceeded { budget: 4 * PAGE }
+    );
+    // The failed call is rolled back, UTXOs included.
+    assert_eq!(tx.utxos().len(), 0);
+}
@@ -0,0 +1,32 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:memory_consistency");
+
+    tx.run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+
+    tx.validate_witness_memory_consistency().unwrap();
+
+    assert!(tx.corrupt_witness_memory_for_testing());
+    assert!(tx.validate_witness_memory_consistency().is_err());
+}
+
+#[test]
+pub fn mutated_yield() {
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:memory_consistency");
+
+    // The mutation rewrites the object the UTXO yielded, which is no longer
+    // what the yield read.
+    tx.run_coordination_script(&contract, "coord_mutate", vec![])
+        .unwrap();
+
+    tx.validate_witness_memory_consistency().unwrap();
+}
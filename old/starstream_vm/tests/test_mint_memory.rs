@@ -0,0 +1,26 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:mint_memory");
+
+    // Minting doesn't write into the caller's memory.
+    let data = tx
+        .run_coordination_script(&contract, "coord_keeps_data", vec![])
+        .unwrap();
+    assert_eq!(data.i32(), Some(1234));
+
+    // Nor does it depend on what the token's code keeps at any address.
+    let utxo = tx
+        .run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+    assert_eq!(
+        tx.utxo_history(utxo_id),
+        vec![UtxoEvent::Yielded(10u64.to_le_bytes().to_vec())]
+    );
+}
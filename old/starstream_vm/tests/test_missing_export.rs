@@ -0,0 +1,33 @@
+use starstream_vm::{test_utils::ModuleBuilder, *};
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:abort");
+
+    let result = tx.run_coordination_script(&contract, "no_such_entry_point", vec![]);
+    assert_eq!(
+        result.err(),
+        Some(TransactionError::MissingExport {
+            code: contract.hash(),
+            name: "no_such_entry_point".to_owned(),
+        })
+    );
+
+    // The host can't exchange data with a contract that has no memory.
+    let no_memory = ModuleBuilder::new()
+        .func("coord", "", "")
+        .load(tx.code_cache());
+
+    let result = tx.run_coordination_script(&no_memory, "coord", vec![]);
+    assert_eq!(
+        result.err(),
+        Some(TransactionError::MissingExport {
+            code: no_memory.hash(),
+            name: "memory".to_owned(),
+        })
+    );
+}
@@ -0,0 +1,16 @@
+use starstream_vm::{test_utils::ModuleBuilder, *};
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let code = ModuleBuilder::new()
+        .memory(1)
+        .func("main", "", "nop")
+        .load(tx.code_cache());
+
+    let result = tx.run_coordination_script(&code, "main", vec![]).unwrap();
+    assert!(matches!(result, Value::I32(0)));
+}
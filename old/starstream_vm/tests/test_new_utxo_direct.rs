@@ -0,0 +1,35 @@
+use starstream_vm::*;
+use wasmi::Value;
+
+#[ignore]
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    std::process::Command::new("cargo")
+        .arg("build")
+        .arg("-p")
+        .arg("example_contract_permissioned")
+        .status()
+        .unwrap();
+
+    let mut tx = Transaction::new();
+    tx.with_rust_compat(true);
+
+    let contract = tx.code_cache().load_debug("example_contract_permissioned");
+
+    // no coordination script involved in creating the utxo
+    let utxo = tx
+        .new_utxo_direct(
+            &contract,
+            "starstream_new_PayToPublicKeyHash_new",
+            vec![Value::I32(42)],
+        )
+        .unwrap();
+
+    let owner = tx
+        .run_coordination_script(&contract, "pay_to_public_key_hash_owner", vec![utxo])
+        .unwrap();
+
+    assert_eq!(owner.i32(), Some(42));
+}
@@ -0,0 +1,59 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::builder().fuel_limit(100_000).build();
+    let contract = tx.code_cache().load_debug("wat:out_of_fuel");
+    let result = tx.run_coordination_script(&contract, "coord_spin", vec![]);
+    assert!(
+        matches!(
+            &result,
+            Err(TransactionError::OutOfFuel { code, entry_point, .. })
+                if *code == contract.hash() && entry_point == "starstream_mutate_spin"
+        ),
+        "{result:?}"
+    );
+
+    let mut tx = Transaction::builder().fuel_limit(100_000).build();
+    let contract = tx.code_cache().load_debug("wat:out_of_fuel");
+    let result = tx.run_coordination_script(&contract, "coord_create_spinner", vec![]);
+    assert!(
+        matches!(
+            &result,
+            Err(TransactionError::OutOfFuel { program: None, entry_point, .. })
+                if entry_point == "starstream_new_spinner"
+        ),
+        "{result:?}"
+    );
+
+    // Only a program that outlives the failed call has an index to report.
+    let mut tx = Transaction::builder().fuel_limit(100_000).build();
+    let contract = tx.code_cache().load_debug("wat:out_of_fuel");
+    let sleeper = tx
+        .run_coordination_script(&contract, "coord_create_sleeper", vec![])
+        .unwrap();
+    let result = tx.run_coordination_script(&contract, "coord_wake", vec![sleeper]);
+    assert!(
+        matches!(
+            &result,
+            Err(TransactionError::OutOfFuel { program: Some(1), entry_point, .. })
+                if entry_point == "starstream_new_sleeper"
+        ),
+        "{result:?}"
+    );
+
+    // The error reports how much of the limit was spent before running out.
+    let mut tx = Transaction::with_fuel_limit(100_000);
+    let contract = tx.code_cache().load_debug("wat:out_of_fuel");
+    let result = tx.run_coordination_script(&contract, "coord_spin", vec![]);
+    assert!(
+        matches!(
+            &result,
+            Err(TransactionError::OutOfFuel { fuel_consumed, .. })
+                if *fuel_consumed > 0 && *fuel_consumed <= 100_000
+        ),
+        "{result:?}"
+    );
+}
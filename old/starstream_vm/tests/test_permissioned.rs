@@ -22,47 +22,59 @@ pub fn main() {
 
     // as a simplification (using i32 instead of public keys), the empty list
     // technically blacklists the set {0, i32::MAX}
-    let head = tx.run_coordination_script(&contract, "blacklist_empty", vec![]);
+    let head = tx
+        .run_coordination_script(&contract, "blacklist_empty", vec![])
+        .unwrap();
 
     // first we insert in order: [3, 5, 7]
-    let new_node = tx.run_coordination_script(
-        &contract,
-        "blacklist_insert",
-        vec![head.clone(), Value::I32(3)],
-    );
+    let new_node = tx
+        .run_coordination_script(
+            &contract,
+            "blacklist_insert",
+            vec![head.clone(), Value::I32(3)],
+        )
+        .unwrap();
 
-    let new_node =
-        tx.run_coordination_script(&contract, "blacklist_insert", vec![new_node, Value::I32(5)]);
+    let new_node = tx
+        .run_coordination_script(&contract, "blacklist_insert", vec![new_node, Value::I32(5)])
+        .unwrap();
 
-    let _new_node =
-        tx.run_coordination_script(&contract, "blacklist_insert", vec![new_node, Value::I32(7)]);
+    let _new_node = tx
+        .run_coordination_script(&contract, "blacklist_insert", vec![new_node, Value::I32(7)])
+        .unwrap();
 
     // the list currently has [3,5,7], so this would be inserted at index 1.
     // find_prev_node should return the address of the utxo with the key of 3.
     let new_key = 6;
     let prev_node = find_prev_node(&mut tx, &contract, new_key);
 
-    let _new_node = tx.run_coordination_script(
-        &contract,
-        "blacklist_insert",
-        vec![prev_node, Value::I32(new_key)],
-    );
+    let _new_node = tx
+        .run_coordination_script(
+            &contract,
+            "blacklist_insert",
+            vec![prev_node, Value::I32(new_key)],
+        )
+        .unwrap();
 
-    let minter = tx.run_coordination_script(&contract, "token_mint_new", vec![]);
+    let minter = tx
+        .run_coordination_script(&contract, "token_mint_new", vec![])
+        .unwrap();
 
     let mint_to = 4;
     let proof_to = find_prev_node(&mut tx, &contract, mint_to);
 
-    let minted_token = tx.run_coordination_script(
-        &contract,
-        "token_mint_to",
-        vec![
-            minter.clone(),
-            Value::I32(mint_to),
-            Value::I32(100),
-            proof_to,
-        ],
-    );
+    let minted_token = tx
+        .run_coordination_script(
+            &contract,
+            "token_mint_to",
+            vec![
+                minter.clone(),
+                Value::I32(mint_to),
+                Value::I32(100),
+                proof_to,
+            ],
+        )
+        .unwrap();
 
     // blacklist: [3, 5, 6, 7]
     let from = 4;
@@ -73,18 +85,20 @@ pub fn main() {
     let proof_from = find_prev_node(&mut tx, &contract, from);
     let proof_to = find_prev_node(&mut tx, &contract, to);
 
-    let _transfer_output = tx.run_coordination_script(
-        &contract,
-        "transfer_permissioned_token",
-        vec![
-            minted_token,
-            proof_from,
-            proof_to,
-            Value::I32(to),
-            // amount to transfer
-            Value::I32(50),
-        ],
-    );
+    let _transfer_output = tx
+        .run_coordination_script(
+            &contract,
+            "transfer_permissioned_token",
+            vec![
+                minted_token,
+                proof_from,
+                proof_to,
+                Value::I32(to),
+                // amount to transfer
+                Value::I32(50),
+            ],
+        )
+        .unwrap();
 
     let utxos = tx
         .utxos()
@@ -92,17 +106,21 @@ pub fn main() {
         .filter(|(_, entry_point)| entry_point == "starstream_new_PayToPublicKeyHash_new")
         .collect::<Vec<_>>();
 
-    let owner0 = tx.run_coordination_script(
-        &contract,
-        "pay_to_public_key_hash_owner",
-        vec![utxos[0].0.clone()],
-    );
+    let owner0 = tx
+        .run_coordination_script(
+            &contract,
+            "pay_to_public_key_hash_owner",
+            vec![utxos[0].0.clone()],
+        )
+        .unwrap();
 
-    let owner1 = tx.run_coordination_script(
-        &contract,
-        "pay_to_public_key_hash_owner",
-        vec![utxos[1].0.clone()],
-    );
+    let owner1 = tx
+        .run_coordination_script(
+            &contract,
+            "pay_to_public_key_hash_owner",
+            vec![utxos[1].0.clone()],
+        )
+        .unwrap();
 
     dbg!(owner0);
     dbg!(owner1);
@@ -116,7 +134,9 @@ fn find_prev_node(tx: &mut Transaction, contract: &Arc<ContractCode>, new_key: i
         .collect::<Vec<_>>();
 
     utxos.sort_unstable_by_key(|(utxo_id, _entry_point)| {
-        match tx.run_coordination_script(contract, "blacklist_node_get_key", vec![utxo_id.clone()])
+        match tx
+            .run_coordination_script(contract, "blacklist_node_get_key", vec![utxo_id.clone()])
+            .unwrap()
         {
             Value::I32(i) => i,
             _ => unreachable!(),
@@ -124,7 +144,9 @@ fn find_prev_node(tx: &mut Transaction, contract: &Arc<ContractCode>, new_key: i
     });
 
     let Err(insert_at) = utxos.binary_search_by_key(&new_key, |(utxo_id, _entry_point)| {
-        match tx.run_coordination_script(contract, "blacklist_node_get_key", vec![utxo_id.clone()])
+        match tx
+            .run_coordination_script(contract, "blacklist_node_get_key", vec![utxo_id.clone()])
+            .unwrap()
         {
             Value::I32(i) => i,
             _ => unreachable!(),
@@ -0,0 +1,58 @@
+use starstream_vm::{test_utils::ModuleBuilder, *};
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+    assert_eq!(tx.programs_debug_json(), serde_json::json!([]));
+
+    tx.code_cache().load_debug("wat:double_consume");
+    let coordination = ModuleBuilder::new()
+        .import(
+            "starstream_utxo:wat:double_consume",
+            "starstream_new_main",
+            "(result i64)",
+        )
+        .memory(1)
+        .func("coord", "(result i64)", "(call $starstream_new_main)")
+        .load(tx.code_cache());
+
+    tx.run_coordination_script(&coordination, "coord", vec![])
+        .unwrap();
+    tx.run_coordination_script(&coordination, "coord", vec![])
+        .unwrap();
+
+    let json = tx.programs_debug_json();
+    let programs = json.as_array().unwrap();
+    assert_eq!(programs.len(), 4);
+
+    // Each coordination script returns to the host, and each UTXO to the
+    // coordination script that created it.
+    let return_to: Vec<_> = programs.iter().map(|p| p["return_to"].clone()).collect();
+    assert_eq!(
+        return_to,
+        vec![
+            serde_json::Value::Null,
+            0.into(),
+            serde_json::Value::Null,
+            2.into()
+        ]
+    );
+
+    for (index, program) in programs.iter().enumerate() {
+        assert_eq!(program["index"], index);
+    }
+    assert_eq!(programs[0]["entry_point"], "coord");
+    assert_eq!(programs[0]["finished"], true);
+    assert!(programs[0]["interrupt"].is_null());
+    assert_eq!(programs[1]["entry_point"], "starstream_new_main");
+    assert_eq!(programs[1]["finished"], false);
+    assert!(programs[1]["utxo"].is_string());
+    assert!(
+        programs[1]["interrupt"]
+            .as_str()
+            .unwrap()
+            .starts_with("Yield")
+    );
+}
@@ -16,7 +16,8 @@ pub fn main() {
 
     let example_contract = tx.code_cache().load_debug("example_contract");
 
-    tx.run_coordination_script(&example_contract, "produce_and_consume", vec![]);
+    tx.run_coordination_script(&example_contract, "produce_and_consume", vec![])
+        .unwrap();
     dbg!(&tx);
     dbg!(tx.map_continuations());
 
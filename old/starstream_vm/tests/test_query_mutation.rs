@@ -0,0 +1,22 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:query_mutation");
+
+    let value = tx
+        .run_coordination_script(&contract, "coord_get", vec![])
+        .unwrap();
+    assert_eq!(value.i32(), Some(7));
+
+    let result = tx.run_coordination_script(&contract, "coord_bump", vec![]);
+    assert!(
+        matches!(result, Err(TransactionError::QueryMutatedState { .. })),
+        "{:?}",
+        result.err()
+    );
+}
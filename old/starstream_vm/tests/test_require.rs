@@ -0,0 +1,33 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:require");
+
+    let result = tx.run_coordination_script(&contract, "coord_ok", vec![]);
+    assert_eq!(result.unwrap().i32(), Some(5));
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:require");
+
+    let result = tx.run_coordination_script(&contract, "coord", vec![]);
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::RequireFailed { code: 42 }
+    );
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:require");
+
+    let result = tx.run_coordination_script(&contract, "coord_utxo", vec![]);
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::RequireFailed { code: 9 }
+    );
+}
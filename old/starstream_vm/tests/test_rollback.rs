@@ -0,0 +1,107 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:rollback");
+
+    let utxo = tx
+        .run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+    let witnesses = tx.witness_commitments();
+
+    let result = tx.run_coordination_script(&contract, "coord_fail", vec![]);
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::RequireFailed { code: 9 }
+    );
+
+    // Only the first call's UTXO is left, and the second call left no witnesses.
+    let utxos = tx.utxos();
+    assert_eq!(utxos.len(), 1);
+    assert_eq!(tx.utxo_id(&utxos[0].0), Some(utxo_id));
+    assert_eq!(tx.witness_commitments(), witnesses);
+
+    // The transaction can carry on.
+    tx.run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+    assert_eq!(tx.utxos().len(), 2);
+}
+
+#[test]
+pub fn new_utxo_direct() {
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:rollback");
+
+    tx.new_utxo_direct(&contract, "starstream_new_main", vec![])
+        .unwrap();
+    let witnesses = tx.witness_commitments();
+
+    // A UTXO that fails before its first yield is discarded, like a failed
+    // coordination script.
+    let result = tx.new_utxo_direct(&contract, "starstream_new_main_fail", vec![]);
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::RequireFailed { code: 9 }
+    );
+    assert_eq!(tx.utxos().len(), 1);
+    assert_eq!(tx.witness_commitments(), witnesses);
+}
+
+#[test]
+pub fn resumed_utxo() {
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:rollback");
+
+    let utxo = tx
+        .run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+    tx.run_coordination_script(&contract, "coord_resume", vec![utxo.clone()])
+        .unwrap();
+
+    // The failed call ran the UTXO, which can't be rolled back, so it can't be
+    // used again.
+    let result = tx.run_coordination_script(&contract, "coord_resume_fail", vec![utxo.clone()]);
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::RequireFailed { code: 9 }
+    );
+    let result = tx.run_coordination_script(&contract, "coord_resume", vec![utxo]);
+    assert!(matches!(
+        result,
+        Err(TransactionError::ProgramInvalidated { utxo: Some(utxo), .. }) if utxo == utxo_id
+    ));
+}
+
+#[test]
+pub fn memory_budget() {
+    const PAGE: usize = 64 * 1024;
+
+    // Room for two calls that each start a UTXO.
+    let mut tx = Transaction::with_memory_budget(4 * PAGE);
+
+    let contract = tx.code_cache().load_debug("wat:rollback");
+
+    tx.run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+
+    // The instances of a failed call stay in the store, so their memory still
+    // counts against the budget.
+    let result = tx.run_coordination_script(&contract, "coord_fail", vec![]);
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::RequireFailed { code: 9 }
+    );
+    let result = tx.run_coordination_script(&contract, "coord", vec![]);
+    assert_eq!(
+        result.unwrap_err(),
+        TransactionError::MemoryBudgetExceeded { budget: 4 * PAGE }
+    );
+}
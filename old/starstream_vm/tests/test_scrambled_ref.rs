@@ -0,0 +1,34 @@
+use starstream_vm::{ScrambledRef, Value};
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    for value in [
+        0,
+        1,
+        -1,
+        i64::MIN,
+        i64::MAX,
+        i64::MIN + 1,
+        -0x0123_4567_89ab_cdef,
+    ] {
+        let scrambled = ScrambledRef::from_i64(value);
+        assert_eq!(scrambled.to_i64(), value);
+
+        let wasm = scrambled.to_wasm();
+        assert!(matches!(wasm, Value::I64(v) if v == value));
+        assert_eq!(ScrambledRef::from_wasm(&wasm), Some(scrambled));
+    }
+
+    // Keys with the high bit set are negative as i64, but are still shown
+    // and ordered as the unsigned key.
+    let high = ScrambledRef::from_i64(i64::MIN);
+    let max = ScrambledRef::from_i64(-1);
+    assert_eq!(format!("{high:?}"), "ScrambledRef(0x8000000000000000)");
+    assert_eq!(format!("{max:?}"), "ScrambledRef(0xffffffffffffffff)");
+    assert!(ScrambledRef::from_i64(i64::MAX) < high);
+    assert!(high < max);
+
+    assert_eq!(ScrambledRef::from_wasm(&Value::I32(-1)), None);
+}
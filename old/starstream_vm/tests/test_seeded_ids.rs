@@ -0,0 +1,57 @@
+use starstream_vm::*;
+
+/// Creates two UTXOs, returning the handles the script got for them and the
+/// ids of the UTXOs left in the transaction.
+fn run(mut tx: Transaction) -> (Vec<Option<i64>>, Vec<UtxoId>) {
+    let contract = tx.code_cache().load_debug("wat:utxo_changes");
+
+    let handles = (0..2)
+        .map(|_| {
+            tx.run_coordination_script(&contract, "coord_create", vec![])
+                .unwrap()
+                .i64()
+        })
+        .collect();
+
+    let mut ids = tx
+        .utxos()
+        .iter()
+        .map(|(utxo, _)| tx.utxo_id(utxo).unwrap())
+        .collect::<Vec<_>>();
+    ids.sort();
+
+    (handles, ids)
+}
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let (handles, ids) = run(Transaction::with_seed([7; 32]));
+    assert_eq!(ids.len(), 2);
+
+    // same seed, same ids and handles
+    assert_eq!(run(Transaction::with_seed([7; 32])), (handles, ids.clone()));
+
+    // otherwise they differ
+    let (_, other_ids) = run(Transaction::with_seed([8; 32]));
+    assert_ne!(other_ids, ids);
+}
+
+/// The ids a seed gives are part of reproducing a transaction, so they must
+/// not change between builds.
+#[test]
+pub fn golden() {
+    let (_, ids) = run(Transaction::with_seed([7; 32]));
+    let ids = ids
+        .into_iter()
+        .map(|id| UtxoIdBytes::from(id).to_hex())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ids,
+        [
+            "3216a4020764bde545c143dbb9609c22",
+            "f400927857aaf64114f561baacb37970",
+        ]
+    );
+}
@@ -0,0 +1,74 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:simulate_effect");
+
+    tx.run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+
+    let output = tx
+        .simulate_effect("IsBlacklisted", &3i32.to_le_bytes(), 1)
+        .unwrap();
+    assert_eq!(output, vec![1]);
+
+    let output = tx
+        .simulate_effect("IsBlacklisted", &4i32.to_le_bytes(), 1)
+        .unwrap();
+    assert_eq!(output, vec![0]);
+
+    // The handler finishes after resuming, like it would for a real raise.
+    let output = tx.simulate_effect("Counted", &[], 4).unwrap();
+    assert_eq!(output, 0u32.to_le_bytes());
+    let output = tx.simulate_effect("Counted", &[], 4).unwrap();
+    assert_eq!(output, 1u32.to_le_bytes());
+    let output = tx.simulate_effect("Count", &[], 4).unwrap();
+    assert_eq!(output, 2u32.to_le_bytes());
+
+    let result = tx.simulate_effect("Ignored", &[], 1);
+    assert_eq!(
+        result,
+        Err(TransactionError::EffectNotResumed {
+            name: "Ignored".to_owned(),
+        })
+    );
+
+    // A handler that returns without resuming doesn't leave its raise
+    // pending.
+    let output = tx
+        .simulate_effect("IsBlacklisted", &3i32.to_le_bytes(), 1)
+        .unwrap();
+    assert_eq!(output, vec![1]);
+}
+
+#[test]
+pub fn failing_handler() {
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:simulate_effect");
+
+    tx.run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+    tx.simulate_effect("Counted", &[], 4).unwrap();
+    let witnesses = tx.witness_commitments();
+    let events = tx.events().len();
+
+    let result = tx.simulate_effect("Failing", &[], 1);
+    assert!(matches!(result, Err(TransactionError::Trap { .. })));
+
+    // The transaction is as it was before the handler ran.
+    assert_eq!(tx.witness_commitments(), witnesses);
+    assert_eq!(tx.events().len(), events);
+
+    // The handlers share the failed one's instance, which can't be trusted
+    // after it trapped.
+    let result = tx.simulate_effect("IsBlacklisted", &3i32.to_le_bytes(), 1);
+    assert!(matches!(
+        result,
+        Err(TransactionError::ProgramInvalidated { .. })
+    ));
+}
@@ -8,6 +8,7 @@ pub fn main() {
 
     let example_contract = tx.code_cache().load_debug("wat:status");
 
-    tx.run_coordination_script(&example_contract, "coord", vec![]);
+    tx.run_coordination_script(&example_contract, "coord", vec![])
+        .unwrap();
     dbg!(&tx);
 }
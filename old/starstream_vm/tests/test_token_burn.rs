@@ -0,0 +1,33 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:token_burn");
+
+    // The token is bound, then burned, and the UTXO no longer holds it.
+    let utxo = tx
+        .run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+    assert_eq!(
+        tx.utxo_history(utxo_id),
+        vec![UtxoEvent::Yielded(vec![1, 0, 0, 0, 0, 0, 0, 0])]
+    );
+}
+
+#[test]
+pub fn burn_twice() {
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:token_burn");
+
+    let result = tx.run_coordination_script(&contract, "coord_twice", vec![]);
+    assert!(matches!(
+        result,
+        Err(TransactionError::TokenNotBound { .. })
+    ));
+}
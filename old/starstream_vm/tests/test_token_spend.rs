@@ -0,0 +1,27 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:token_spend");
+
+    let left = tx
+        .run_coordination_script(&contract, "coord_ok", vec![])
+        .unwrap();
+    assert_eq!(left.i64(), Some(6));
+
+    let result = tx.run_coordination_script(&contract, "coord_overspend", vec![]);
+    assert!(matches!(
+        result,
+        Err(TransactionError::TokenAmountExceeded {
+            amount: 10,
+            requested: 11,
+        })
+    ));
+
+    let result = tx.run_coordination_script(&contract, "coord_bad_handle", vec![]);
+    assert!(matches!(result, Err(TransactionError::InvalidTokenHandle)));
+}
@@ -0,0 +1,31 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:try_bind");
+
+    // Status 0, and the token is bound.
+    let utxo = tx
+        .run_coordination_script(&contract, "coord_ok", vec![])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+    assert_eq!(
+        tx.utxo_history(utxo_id),
+        vec![UtxoEvent::Yielded(vec![0, 0, 0, 0, 1, 0, 0, 0])]
+    );
+
+    // The bind is refused, but the UTXO gets to see that and carries on
+    // without the token.
+    let utxo = tx
+        .run_coordination_script(&contract, "coord_blacklisted", vec![])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+    assert_eq!(
+        tx.utxo_history(utxo_id),
+        vec![UtxoEvent::Yielded(vec![1, 0, 0, 0, 0, 0, 0, 0])]
+    );
+}
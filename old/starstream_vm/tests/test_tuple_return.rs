@@ -0,0 +1,17 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:tuple_return");
+
+    let result = tx
+        .run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+
+    // divmod(17, 5) = (3, 2)
+    assert_eq!(result.i32(), Some(32));
+}
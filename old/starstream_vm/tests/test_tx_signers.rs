@@ -0,0 +1,28 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let signers = vec![PublicKey([1; 32]), PublicKey([2; 32])];
+
+    let mut tx = Transaction::builder().signers(signers.clone()).build();
+    let contract = tx.code_cache().load_debug("wat:tx_signers");
+
+    let result = tx.run_coordination_script(&contract, "coord_signed_a", vec![]);
+    assert_eq!(result.unwrap().i32(), Some(1));
+
+    let result = tx.run_coordination_script(&contract, "coord_signed_c", vec![]);
+    assert_eq!(result.unwrap().i32(), Some(0));
+
+    let result = tx.run_coordination_script(&contract, "coord_count", vec![]);
+    assert_eq!(result.unwrap().i32(), Some(2));
+
+    assert_eq!(tx.signers(), &signers[..]);
+
+    // Unsigned by default.
+    let mut tx = Transaction::new();
+    let contract = tx.code_cache().load_debug("wat:tx_signers");
+    let result = tx.run_coordination_script(&contract, "coord_signed_a", vec![]);
+    assert_eq!(result.unwrap().i32(), Some(0));
+}
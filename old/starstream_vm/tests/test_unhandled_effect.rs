@@ -0,0 +1,28 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    for entry_point in ["coord", "coord_unregistered"] {
+        let mut tx = Transaction::new();
+
+        let contract = tx.code_cache().load_debug("wat:unhandled_effect");
+
+        let result = tx.run_coordination_script(&contract, entry_point, vec![]);
+        assert!(
+            matches!(
+                &result,
+                Err(TransactionError::UnhandledEffect { name }) if name == "IsBlacklisted"
+            ),
+            "{entry_point}: {result:?}"
+        );
+    }
+
+    // Simulating an effect nobody handles fails the same way.
+    let mut tx = Transaction::new();
+    assert!(matches!(
+        tx.simulate_effect("IsBlacklisted", &[], 0),
+        Err(TransactionError::UnhandledEffect { .. })
+    ));
+}
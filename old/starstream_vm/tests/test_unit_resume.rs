@@ -0,0 +1,42 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:unit_resume");
+
+    // Resume type is (), resumed without a pointer.
+    let unit = tx
+        .run_coordination_script(&contract, "coord_create_unit", vec![])
+        .unwrap();
+    let unit_id = tx.utxo_id(&unit).unwrap();
+    let before = tx.utxo_state();
+
+    tx.run_coordination_script(&contract, "coord_resume_unit", vec![unit])
+        .unwrap();
+
+    let changes = Transaction::diff_utxo_state(&before, &tx.utxo_state());
+    assert_eq!(
+        changes[&unit_id].output_after,
+        Some(output_commitment(b"done"))
+    );
+
+    // Resume type is u32, copied from the caller's memory.
+    let value = tx
+        .run_coordination_script(&contract, "coord_create_value", vec![])
+        .unwrap();
+    let value_id = tx.utxo_id(&value).unwrap();
+    let before = tx.utxo_state();
+
+    tx.run_coordination_script(&contract, "coord_resume_value", vec![value])
+        .unwrap();
+
+    let changes = Transaction::diff_utxo_state(&before, &tx.utxo_state());
+    assert_eq!(
+        changes[&value_id].output_after,
+        Some(output_commitment(&42u32.to_le_bytes()))
+    );
+}
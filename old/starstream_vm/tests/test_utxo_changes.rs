@@ -0,0 +1,61 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:utxo_changes");
+
+    let start = tx.utxo_state();
+
+    let utxo = tx
+        .run_coordination_script(&contract, "coord_create", vec![])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+
+    let created = tx.utxo_state();
+
+    let changes = Transaction::diff_utxo_state(&start, &created);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(
+        changes[&utxo_id],
+        UtxoChange {
+            output_before: None,
+            output_after: Some(output_commitment(b"first")),
+            consumed: false,
+        }
+    );
+
+    // nothing happened in between
+    assert!(Transaction::diff_utxo_state(&created, &tx.utxo_state()).is_empty());
+
+    tx.run_coordination_script(&contract, "coord_resume", vec![utxo.clone()])
+        .unwrap();
+
+    let resumed = tx.utxo_state();
+
+    let changes = Transaction::diff_utxo_state(&created, &resumed);
+    assert_eq!(
+        changes[&utxo_id],
+        UtxoChange {
+            output_before: Some(output_commitment(b"first")),
+            output_after: Some(output_commitment(b"second")),
+            consumed: false,
+        }
+    );
+
+    tx.run_coordination_script(&contract, "coord_resume", vec![utxo])
+        .unwrap();
+
+    let changes = Transaction::diff_utxo_state(&start, &tx.utxo_state());
+    assert_eq!(
+        changes[&utxo_id],
+        UtxoChange {
+            output_before: None,
+            output_after: None,
+            consumed: true,
+        }
+    );
+}
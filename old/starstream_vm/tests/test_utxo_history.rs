@@ -0,0 +1,28 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:utxo_history");
+
+    let utxo = tx
+        .run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+
+    assert_eq!(
+        tx.utxo_history(utxo_id),
+        vec![
+            UtxoEvent::Yielded(b"init".to_vec()),
+            UtxoEvent::Resumed(vec![1, 2, 3, 4]),
+            UtxoEvent::Yielded(vec![1, 2, 3, 4]),
+            UtxoEvent::Resumed(vec![5, 6, 7, 8]),
+            UtxoEvent::Yielded(vec![5, 6, 7, 8]),
+        ]
+    );
+
+    tx.validate_witness_memory_consistency().unwrap();
+}
@@ -0,0 +1,83 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+    let contract = tx.code_cache().load_debug("wat:utxo_changes");
+
+    let utxo = tx
+        .run_coordination_script(&contract, "coord_create", vec![])
+        .unwrap();
+    tx.run_coordination_script(&contract, "coord_resume", vec![utxo.clone()])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+    let history = tx.utxo_history(utxo_id);
+
+    let snapshot = tx.snapshot_utxo(utxo_id).unwrap();
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let snapshot: UtxoSnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(snapshot.id(), UtxoIdBytes::from(utxo_id));
+
+    // Its code has to be loaded first.
+    let mut tx = Transaction::new();
+    assert_eq!(
+        tx.load_utxo(&snapshot).unwrap_err(),
+        TransactionError::CodeNotFound {
+            code: contract.hash()
+        }
+    );
+    assert!(tx.live_utxo_ids().is_empty());
+
+    // A later transaction picks it up where it was, under the same id.
+    let mut tx = Transaction::new();
+    let contract = tx.code_cache().load_debug("wat:utxo_changes");
+
+    let utxo = tx.load_utxo(&snapshot).unwrap();
+    assert_eq!(tx.utxo_id(&utxo), Some(utxo_id));
+    assert_eq!(tx.utxo_history(utxo_id), history);
+
+    // The last resume finishes it.
+    tx.run_coordination_script(&contract, "coord_resume", vec![utxo])
+        .unwrap();
+    assert!(tx.live_utxo_ids().is_empty());
+    assert_eq!(tx.snapshot_utxo(utxo_id).map(|s| s.id()), None);
+}
+
+#[test]
+pub fn mutated_with_tokens() {
+    let mut tx = Transaction::new();
+    let contract = tx.code_cache().load_debug("wat:utxo_snapshot_tokens");
+
+    let utxo = tx
+        .run_coordination_script(&contract, "coord_create", vec![])
+        .unwrap();
+    tx.run_coordination_script(&contract, "coord_add", vec![utxo.clone()])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+
+    let snapshot = tx.snapshot_utxo(utxo_id).unwrap();
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let snapshot: UtxoSnapshot = serde_json::from_str(&json).unwrap();
+
+    // The mutation is replayed, and the token is bound again.
+    let mut tx = Transaction::new();
+    let contract = tx.code_cache().load_debug("wat:utxo_snapshot_tokens");
+
+    let utxo = tx.load_utxo(&snapshot).unwrap();
+    let value = tx
+        .run_coordination_script(&contract, "coord_get", vec![utxo.clone()])
+        .unwrap();
+    assert_eq!(value.i32(), Some(6));
+
+    tx.run_coordination_script(&contract, "coord_finish", vec![utxo])
+        .unwrap();
+    assert_eq!(
+        tx.consumed_tokens(utxo_id),
+        vec![ConsumedToken {
+            token_type_id: 1,
+            amount: 10
+        }]
+    );
+}
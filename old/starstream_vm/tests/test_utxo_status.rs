@@ -0,0 +1,29 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:utxo_status");
+
+    let utxo = tx
+        .run_coordination_script(&contract, "coord_create", vec![])
+        .unwrap();
+
+    // Yielded, so still alive.
+    let status = tx
+        .run_coordination_script(&contract, "coord_status", vec![utxo.clone()])
+        .unwrap();
+    assert_eq!(status.i32(), Some(1));
+
+    tx.run_coordination_script(&contract, "coord_burn", vec![utxo.clone()])
+        .unwrap();
+
+    // Consumed, so no longer.
+    let status = tx
+        .run_coordination_script(&contract, "coord_status", vec![utxo])
+        .unwrap();
+    assert_eq!(status.i32(), Some(0));
+}
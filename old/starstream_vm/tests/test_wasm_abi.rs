@@ -0,0 +1,84 @@
+use starstream_vm::{
+    Value,
+    wasm_abi::{self, AbiError},
+};
+use wasmi::core::{F32, F64, ValueType};
+
+fn assert_same(a: &Value, b: &Value) {
+    match (a, b) {
+        (Value::I32(a), Value::I32(b)) => assert_eq!(a, b),
+        (Value::I64(a), Value::I64(b)) => assert_eq!(a, b),
+        (Value::F32(a), Value::F32(b)) => assert_eq!(a.to_bits(), b.to_bits()),
+        (Value::F64(a), Value::F64(b)) => assert_eq!(a.to_bits(), b.to_bits()),
+        _ => panic!("{a:?} != {b:?}"),
+    }
+}
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let values = [
+        Value::I32(-0x1234_5678),
+        Value::I64(0x0102_0304_0506_0708),
+        Value::F32(F32::from(1.5f32)),
+        Value::F64(F64::from(-2.25f64)),
+    ];
+
+    // Each value type round-trips, little-endian.
+    let mut memory = vec![0u8; 64];
+    for value in &values {
+        wasm_abi::write_value(&mut memory, 20, value).unwrap();
+        let read = wasm_abi::read_value(&memory, 20, value.ty()).unwrap();
+        assert_same(&read, value);
+    }
+    wasm_abi::write_value(&mut memory, 0, &Value::I32(0x0403_0201)).unwrap();
+    assert_eq!(&memory[..4], &[1, 2, 3, 4]);
+
+    // Reads and writes touching the last byte are fine, one past it is not.
+    assert!(wasm_abi::read_value(&memory, 56, ValueType::I64).is_ok());
+    assert_eq!(
+        wasm_abi::read_value(&memory, 57, ValueType::I64).unwrap_err(),
+        AbiError::OutOfBounds {
+            offset: 57,
+            len: 8,
+            memory_len: 64,
+        }
+    );
+    assert!(wasm_abi::write_value(&mut memory, 61, &Value::I32(0)).is_err());
+    assert!(wasm_abi::read_bytes(&memory, u32::MAX, 2).is_err());
+    assert!(wasm_abi::write_bytes(&mut memory, 64, &[]).is_ok());
+
+    // Structs align each field to its own size.
+    let fields = [Value::I32(7), Value::I64(-1), Value::I32(9)];
+    let encoded = wasm_abi::encode_struct(&fields).unwrap();
+    assert_eq!(encoded.len(), 24);
+    assert_eq!(&encoded[..4], &7i32.to_le_bytes());
+    assert_eq!(&encoded[8..16], &(-1i64).to_le_bytes());
+    assert_eq!(&encoded[16..20], &9i32.to_le_bytes());
+
+    wasm_abi::write_bytes(&mut memory, 8, &encoded).unwrap();
+    let decoded = wasm_abi::decode_struct(
+        &memory,
+        8,
+        &[ValueType::I32, ValueType::I64, ValueType::I32],
+    )
+    .unwrap();
+    for (decoded, field) in decoded.iter().zip(&fields) {
+        assert_same(decoded, field);
+    }
+    assert!(matches!(
+        wasm_abi::decode_struct(
+            &memory,
+            48,
+            &[ValueType::I32, ValueType::I64, ValueType::I32]
+        ),
+        Err(AbiError::OutOfBounds { .. })
+    ));
+
+    // References don't live in linear memory.
+    assert_eq!(
+        wasm_abi::read_value(&memory, 0, ValueType::ExternRef).unwrap_err(),
+        AbiError::Unsupported(ValueType::ExternRef)
+    );
+}
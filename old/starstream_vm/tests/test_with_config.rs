@@ -0,0 +1,24 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:deep_recursion");
+
+    let result = tx.run_coordination_script(&contract, "coord", vec![]);
+    assert_eq!(result.unwrap().i32(), Some(1000));
+
+    let mut config = Config::default();
+    config.set_stack_limits(StackLimits::new(256, 64 * 1024, 100).unwrap());
+
+    let mut tx = Transaction::with_config(config);
+
+    let contract = tx.code_cache().load_debug("wat:deep_recursion");
+
+    // recursing 1000 deep is over the limit, so the call traps
+    let result = tx.run_coordination_script(&contract, "coord", vec![]);
+    assert!(matches!(result, Err(TransactionError::Trap { .. })));
+}
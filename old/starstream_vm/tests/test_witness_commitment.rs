@@ -0,0 +1,95 @@
+use starstream_vm::*;
+
+fn run(input: i32) -> Vec<[u8; 32]> {
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:simulate_effect");
+
+    tx.run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+    tx.simulate_effect("IsBlacklisted", &input.to_le_bytes(), 1)
+        .unwrap();
+
+    tx.witness_commitments()
+}
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    // Identical runs log identical witnesses.
+    let first = run(3);
+    assert!(!first.is_empty());
+    assert_eq!(first, run(3));
+
+    // The raised data is written to the handler's memory, so it's committed.
+    let other = run(4);
+    assert_eq!(first.len(), other.len());
+    assert_ne!(first, other);
+
+    // Segments are committed in order.
+    let a = MemorySegment::new(0x10, vec![1, 2, 3]);
+    let b = MemorySegment::new(0x20, vec![4]);
+    for algorithm in [
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Keccak256,
+        HashAlgorithm::Blake3,
+    ] {
+        assert_eq!(
+            MemorySegment::commit_all(&[a.clone(), b.clone()], algorithm),
+            MemorySegment::commit_all(&[a.clone(), b.clone()], algorithm),
+        );
+        assert_ne!(
+            MemorySegment::commit_all(&[a.clone(), b.clone()], algorithm),
+            MemorySegment::commit_all(&[b.clone(), a.clone()], algorithm),
+        );
+    }
+
+    // Address and length are part of the encoding.
+    assert_eq!(a.canonical_bytes(), [0x10, 0, 0, 0, 3, 0, 0, 0, 1, 2, 3]);
+    assert_ne!(
+        MemorySegment::new(0x10, vec![1]).commit(HashAlgorithm::Sha256),
+        MemorySegment::new(0x11, vec![1]).commit(HashAlgorithm::Sha256),
+    );
+}
+
+#[test]
+pub fn externref_values() {
+    let mut tx = Transaction::new();
+
+    // a null externref is returned to the transaction, so it's a witness value
+    let contract = tx.code_cache().load(
+        wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "main") (result externref) ref.null extern))"#,
+        )
+        .unwrap(),
+    );
+
+    let result = tx
+        .run_coordination_script(&contract, "main", vec![])
+        .unwrap();
+    assert!(matches!(result, Value::ExternRef(_)));
+
+    assert_eq!(tx.witness_commitments().len(), tx.witnesses().len());
+
+    // null references survive a round trip through the trace
+    let json = serde_json::to_string(&tx.witness_trace()).unwrap();
+    let trace: WitnessTrace = serde_json::from_str(&json).unwrap();
+    let last = trace.witnesses.last().unwrap();
+    assert!(matches!(&last.values[..], [Value::ExternRef(r)] if r.is_null()));
+
+    // while live ones only mean something inside the transaction
+    let code = tx.code_cache().load_debug("wat:rollback");
+    let utxo = tx
+        .new_utxo_direct(&code, "starstream_new_main", vec![])
+        .unwrap();
+    assert!(
+        serde_json::to_string(&WitnessTraceEntry {
+            values: vec![utxo],
+            ..last.clone()
+        })
+        .is_err()
+    );
+}
@@ -0,0 +1,50 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:unit_resume");
+
+    let unit = tx
+        .run_coordination_script(&contract, "coord_create_unit", vec![])
+        .unwrap();
+    tx.run_coordination_script(&contract, "coord_resume_unit", vec![unit])
+        .unwrap();
+
+    // Coordination scripts 0 and 2 each hand control to UTXO 1 and back.
+    let edges = tx
+        .witnesses()
+        .iter()
+        .map(|witness| (witness.from_program().index(), witness.to_program().index()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        edges,
+        vec![
+            (None, Some(0)),
+            (Some(0), Some(1)),
+            (Some(1), Some(0)),
+            (Some(0), None),
+            (None, Some(2)),
+            (Some(2), Some(1)),
+            (Some(1), Some(2)),
+            (Some(2), None),
+        ]
+    );
+    assert!(tx.witnesses()[0].is_create());
+    assert!(tx.witnesses()[3].is_destroy());
+    assert!(
+        tx.witnesses()
+            .windows(2)
+            .all(|pair| pair[0].fuel() <= pair[1].fuel())
+    );
+
+    let trace = tx.witness_trace();
+    assert_eq!(trace.witnesses.len(), edges.len());
+    let json = serde_json::to_string(&trace).unwrap();
+    let trace: WitnessTrace = serde_json::from_str(&json).unwrap();
+    assert_eq!(trace.witnesses[1].from_program, Some(0));
+    assert_eq!(trace.witnesses[1].to_program, Some(1));
+}
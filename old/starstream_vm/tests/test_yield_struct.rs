@@ -0,0 +1,38 @@
+use starstream_vm::*;
+
+#[test]
+pub fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:yield_struct");
+
+    let utxo = tx
+        .run_coordination_script(&contract, "coord", vec![])
+        .unwrap();
+    let utxo_id = tx.utxo_id(&utxo).unwrap();
+
+    assert_eq!(
+        tx.peek_yield_field(utxo_id, "x"),
+        Some(7u32.to_le_bytes().to_vec())
+    );
+    assert_eq!(
+        tx.peek_yield_field(utxo_id, "y"),
+        Some(0x0102030405060708u64.to_le_bytes().to_vec())
+    );
+    assert_eq!(tx.peek_yield_field(utxo_id, "z"), None);
+}
+
+#[test]
+pub fn bad_layout() {
+    let mut tx = Transaction::new();
+
+    let contract = tx.code_cache().load_debug("wat:yield_struct");
+
+    let result = tx.run_coordination_script(&contract, "coord_bad_name", vec![]);
+    assert!(matches!(result, Err(TransactionError::Trap { .. })));
+
+    let result = tx.run_coordination_script(&contract, "coord_bad_layout", vec![]);
+    assert!(matches!(result, Err(TransactionError::Trap { .. })));
+}
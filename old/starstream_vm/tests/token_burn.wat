@@ -0,0 +1,55 @@
+(module
+  (import "starstream_utxo:this" "starstream_mint_1" (func $mint (param i64) (result i64)))
+  (import "starstream_utxo:this" "starstream_new_main" (func $make_utxo (param i64) (result i64)))
+  (import "starstream_utxo:this" "starstream_new_twice" (func $make_twice (param i64) (result i64)))
+  (import "starstream_token:this" "starstream_bind_Token" (func $bind (param i64)))
+  (import "starstream_token:this" "starstream_burn_Token" (func $burn (param i64)))
+  (import "starstream_utxo_env" "starstream_get_tokens" (func $get_tokens (param i32 i32 i32) (result i32)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 48) "y")
+
+  (func $coord (result i64)
+    (call $make_utxo (call $mint (i64.const 10)))
+  )
+
+  (func $coord_twice (result i64)
+    (call $make_twice (call $mint (i64.const 10)))
+  )
+
+  (func $mint_1 (param $amount i64))
+
+  (func $bind_1 (param $token i64))
+
+  ;; the token's code sees the whole amount being burned
+  (func $burn_1 (param $token i64) (param $amount i64)
+    (if (i64.ne (local.get $amount) (i64.const 10))
+      (then unreachable))
+  )
+
+  ;; yields how many tokens it held before and after the burn
+  (func $main (param $token i64)
+    (call $bind (local.get $token))
+    (i32.store (i32.const 0) (call $get_tokens (i32.const 16) (i32.const 1) (i32.const 0)))
+    (call $burn (local.get $token))
+    (i32.store (i32.const 4) (call $get_tokens (i32.const 16) (i32.const 1) (i32.const 0)))
+    (call $yield (i32.const 48) (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 0) (i32.const 0))
+  )
+
+  ;; burns the token a second time, once it's no longer bound
+  (func $twice (param $token i64)
+    (call $bind (local.get $token))
+    (call $burn (local.get $token))
+    (call $burn (local.get $token))
+  )
+
+  (export "starstream_mint_1" (func $mint_1))
+  (export "starstream_bind_Token_1" (func $bind_1))
+  (export "starstream_burn_Token_1" (func $burn_1))
+  (export "starstream_new_main" (func $main))
+  (export "starstream_new_twice" (func $twice))
+  (export "coord" (func $coord))
+  (export "coord_twice" (func $coord_twice))
+  (export "memory" (memory $mem))
+)
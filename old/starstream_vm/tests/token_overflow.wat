@@ -0,0 +1,29 @@
+(module
+  (import "starstream_utxo:this" "starstream_mint_1" (func $mint (param i64) (result i64)))
+  (import "starstream_utxo:this" "starstream_new_main" (func $make_utxo (param i64 i64) (result i64)))
+  (import "starstream_token:this" "starstream_bind_Token" (func $bind_token (param i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+
+  ;; two tokens of u64::MAX - 1 each
+  (func $coord (result i64)
+    (call $make_utxo (call $mint (i64.const -2)) (call $mint (i64.const -2)))
+  )
+
+  (func $mint_1 (param $amount i64))
+
+  (func $bind (param $token i64))
+
+  (func $main (param $first i64) (param $second i64)
+    (call $bind_token (local.get $first))
+    (call $bind_token (local.get $second))
+    (call $yield (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0))
+  )
+
+  (export "starstream_mint_1" (func $mint_1))
+  (export "starstream_bind_Token_1" (func $bind))
+  (export "starstream_new_main" (func $main))
+  (export "coord" (func $coord))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,31 @@
+(module
+  (import "starstream_utxo:this" "starstream_mint_1" (func $mint (param i64) (result i64)))
+  (import "env" "starstream_token_spend" (func $spend (param i64 i64) (result i64)))
+  (import "env" "starstream_get_token_amount" (func $get_token_amount (param i64) (result i64)))
+
+  (memory $mem 1)
+
+  ;; splits 4 off a token of 10, and returns what's left of it
+  (func $coord_ok (result i64)
+    (local $token i64)
+    (local.set $token (call $mint (i64.const 10)))
+    (drop (call $spend (local.get $token) (i64.const 4)))
+    (call $get_token_amount (local.get $token))
+  )
+
+  (func $coord_overspend (result i64)
+    (call $spend (call $mint (i64.const 10)) (i64.const 11))
+  )
+
+  (func $coord_bad_handle (result i64)
+    (call $get_token_amount (i64.const 1234))
+  )
+
+  (func $mint_1 (param $amount i64))
+
+  (export "starstream_mint_1" (func $mint_1))
+  (export "coord_ok" (func $coord_ok))
+  (export "coord_overspend" (func $coord_overspend))
+  (export "coord_bad_handle" (func $coord_bad_handle))
+  (export "memory" (memory $mem))
+)
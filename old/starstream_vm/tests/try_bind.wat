@@ -0,0 +1,41 @@
+(module
+  (import "starstream_utxo:this" "starstream_mint_1" (func $mint (param i64) (result i64)))
+  (import "starstream_utxo:this" "starstream_new_main" (func $make_utxo (param i32 i64) (result i64)))
+  (import "starstream_token:this" "starstream_try_bind_Token" (func $try_bind (param i64) (result i32)))
+  (import "starstream_utxo_env" "starstream_get_tokens" (func $get_tokens (param i32 i32 i32) (result i32)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 48) "y")
+  (global $owner (mut i32) (i32.const 0))
+
+  (func $coord_ok (result i64)
+    (call $make_utxo (i32.const 1) (call $mint (i64.const 10)))
+  )
+
+  (func $coord_blacklisted (result i64)
+    (call $make_utxo (i32.const 666) (call $mint (i64.const 10)))
+  )
+
+  (func $mint_1 (param $amount i64))
+
+  ;; refuses owner 666 instead of trapping
+  (func $bind (param $token i64) (result i32)
+    (i32.eq (global.get $owner) (i32.const 666))
+  )
+
+  ;; yields the bind status and how many tokens it ended up holding
+  (func $main (param $owner i32) (param $token i64)
+    (global.set $owner (local.get $owner))
+    (i32.store (i32.const 0) (call $try_bind (local.get $token)))
+    (i32.store (i32.const 4) (call $get_tokens (i32.const 16) (i32.const 1) (i32.const 0)))
+    (call $yield (i32.const 48) (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 0) (i32.const 0))
+  )
+
+  (export "starstream_mint_1" (func $mint_1))
+  (export "starstream_try_bind_Token_1" (func $bind))
+  (export "starstream_new_main" (func $main))
+  (export "coord_ok" (func $coord_ok))
+  (export "coord_blacklisted" (func $coord_blacklisted))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,46 @@
+(module
+  (import "starstream_utxo:wat:tuple_return" "starstream_new_main" (func $make_utxo (result i64)))
+  (import "starstream_utxo:wat:tuple_return" "starstream_query_divmod" (func $query_divmod (param i64 i32 i32) (result i32 i32)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "main")
+
+  (func $coord (result i32)
+    (local $rem i32)
+    call $make_utxo
+    i32.const 17
+    i32.const 5
+    call $query_divmod
+    ;; pack (quotient, remainder) as quotient * 10 + remainder
+    local.set $rem
+    i32.const 10
+    i32.mul
+    local.get $rem
+    i32.add
+  )
+
+  (func $divmod (param $a i32) (param $b i32) (result i32 i32)
+    local.get $a
+    local.get $b
+    i32.div_u
+    local.get $a
+    local.get $b
+    i32.rem_u
+  )
+
+  (func $main
+    i32.const 0
+    i32.const 4
+    i32.const 0
+    i32.const 0
+    i32.const 0
+    i32.const 0
+    call $yield
+  )
+
+  (export "starstream_new_main" (func $main))
+  (export "starstream_query_divmod" (func $divmod))
+  (export "coord" (func $coord))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,53 @@
+(module
+  (import "env" "starstream_tx_signers" (func $tx_signers (param i32 i32) (result i32)))
+
+  (memory $mem 1)
+
+  ;; key A = [1; 32], key C = [3; 32]
+  (data (i32.const 0) "\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01")
+  (data (i32.const 32) "\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03\03")
+
+  ;; whether the 32-byte key at $key is among the first 8 signers
+  (func $is_signer (param $key i32) (result i32)
+    (local $count i32)
+    (local $entry i32)
+    (local.set $count (call $tx_signers (i32.const 1024) (i32.const 8)))
+    (if (i32.gt_u (local.get $count) (i32.const 8))
+      (then (local.set $count (i32.const 8))))
+    (local.set $entry (i32.const 1024))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $count)))
+        (if
+          (i32.and
+            (i32.and
+              (i64.eq (i64.load (local.get $key)) (i64.load (local.get $entry)))
+              (i64.eq (i64.load offset=8 (local.get $key)) (i64.load offset=8 (local.get $entry))))
+            (i32.and
+              (i64.eq (i64.load offset=16 (local.get $key)) (i64.load offset=16 (local.get $entry)))
+              (i64.eq (i64.load offset=24 (local.get $key)) (i64.load offset=24 (local.get $entry)))))
+          (then (return (i32.const 1))))
+        (local.set $entry (i32.add (local.get $entry) (i32.const 32)))
+        (local.set $count (i32.sub (local.get $count) (i32.const 1)))
+        (br $next)))
+    (i32.const 0)
+  )
+
+  (func $coord_signed_a (result i32)
+    (call $is_signer (i32.const 0))
+  )
+
+  (func $coord_signed_c (result i32)
+    (call $is_signer (i32.const 32))
+  )
+
+  ;; room for only one key, but the total count is still returned
+  (func $coord_count (result i32)
+    (call $tx_signers (i32.const 2048) (i32.const 1))
+  )
+
+  (export "coord_signed_a" (func $coord_signed_a))
+  (export "coord_signed_c" (func $coord_signed_c))
+  (export "coord_count" (func $coord_count))
+  (export "memory" (memory $mem))
+)
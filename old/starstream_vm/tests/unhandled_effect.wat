@@ -0,0 +1,32 @@
+(module
+  (import "starstream_utxo:wat:unhandled_effect" "starstream_new_thrower" (func $new_thrower (result i64)))
+  (import "starstream_utxo_env" "starstream_raise" (func $raise (param i32 i32 i32 i32 i32 i32)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+  (import "env" "starstream_register_effect_handler" (func $register (param i32 i32 i32)))
+  (import "env" "starstream_unregister_effect_handler" (func $unregister (param i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "IsBlacklisted")
+
+  ;; nothing is registered
+  (func $coord (result i64)
+    (call $new_thrower)
+  )
+
+  ;; a handler is registered, but gone again by the time of the raise
+  (func $coord_unregistered (result i64)
+    (call $register (i32.const 0) (i32.const 13) (i32.const 0))
+    (call $unregister (i32.const 0) (i32.const 13))
+    (call $new_thrower)
+  )
+
+  (func $thrower
+    (call $raise (i32.const 0) (i32.const 13) (i32.const 100) (i32.const 4) (i32.const 104) (i32.const 4))
+    (call $yield (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0))
+  )
+
+  (export "starstream_new_thrower" (func $thrower))
+  (export "coord" (func $coord))
+  (export "coord_unregistered" (func $coord_unregistered))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,53 @@
+(module
+  (import "starstream_utxo:wat:unit_resume" "starstream_new_main_unit" (func $make_unit (result i64)))
+  (import "starstream_utxo:wat:unit_resume" "starstream_new_main_value" (func $make_value (result i64)))
+  (import "starstream_utxo:wat:unit_resume" "starstream_resume_unit" (func $resume_unit (param i64)))
+  (import "starstream_utxo:wat:unit_resume" "starstream_resume_value" (func $resume_value (param i64 i32)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "main")
+  (data (i32.const 16) "ready")
+  (data (i32.const 32) "done")
+
+  (func $coord_create_unit (result i64)
+    call $make_unit
+  )
+
+  ;; resumed with only the UTXO handle, no pointer
+  (func $coord_resume_unit (param $utxo i64)
+    local.get $utxo
+    call $resume_unit
+  )
+
+  (func $coord_create_value (result i64)
+    call $make_value
+  )
+
+  (func $coord_resume_value (param $utxo i64)
+    (i32.store (i32.const 256) (i32.const 42))
+    local.get $utxo
+    i32.const 256
+    call $resume_value
+  )
+
+  ;; resume type is (), then yields "done"
+  (func $main_unit
+    (call $yield (i32.const 0) (i32.const 4) (i32.const 16) (i32.const 5) (i32.const 0) (i32.const 0))
+    (call $yield (i32.const 0) (i32.const 4) (i32.const 32) (i32.const 4) (i32.const 0) (i32.const 0))
+  )
+
+  ;; resume type is a u32 written to 128, then yields it back
+  (func $main_value
+    (call $yield (i32.const 0) (i32.const 4) (i32.const 16) (i32.const 5) (i32.const 128) (i32.const 4))
+    (call $yield (i32.const 0) (i32.const 4) (i32.const 128) (i32.const 4) (i32.const 0) (i32.const 0))
+  )
+
+  (export "starstream_new_main_unit" (func $main_unit))
+  (export "starstream_new_main_value" (func $main_value))
+  (export "coord_create_unit" (func $coord_create_unit))
+  (export "coord_resume_unit" (func $coord_resume_unit))
+  (export "coord_create_value" (func $coord_create_value))
+  (export "coord_resume_value" (func $coord_resume_value))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,43 @@
+(module
+  (import "starstream_utxo:wat:utxo_changes" "starstream_new_main" (func $make_utxo (result i64)))
+  (import "starstream_utxo:wat:utxo_changes" "starstream_resume_" (func $resume (param i64 i32)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "main")
+  (data (i32.const 16) "first")
+  (data (i32.const 32) "second")
+
+  (func $coord_create (result i64)
+    call $make_utxo
+  )
+
+  (func $coord_resume (param $utxo i64)
+    local.get $utxo
+    i32.const 0
+    call $resume
+  )
+
+  ;; yields twice, then finishes on the last resume
+  (func $main
+    i32.const 0
+    i32.const 4
+    i32.const 16
+    i32.const 5
+    i32.const 64
+    i32.const 0
+    call $yield
+    i32.const 0
+    i32.const 4
+    i32.const 32
+    i32.const 6
+    i32.const 64
+    i32.const 0
+    call $yield
+  )
+
+  (export "starstream_new_main" (func $main))
+  (export "coord_create" (func $coord_create))
+  (export "coord_resume" (func $coord_resume))
+  (export "memory" (memory $mem))
+)
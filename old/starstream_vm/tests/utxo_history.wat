@@ -0,0 +1,34 @@
+(module
+  (import "starstream_utxo:wat:utxo_history" "starstream_new_main" (func $make_utxo (result i64)))
+  (import "starstream_utxo:wat:utxo_history" "starstream_resume_" (func $resume (param i64 i32)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "init")
+  (data (i32.const 16) "\01\02\03\04")
+  (data (i32.const 20) "\05\06\07\08")
+  (data (i32.const 48) "y")
+
+  (func $coord (result i64)
+    (local $utxo i64)
+    call $make_utxo
+    local.tee $utxo
+    i32.const 16
+    call $resume
+    local.get $utxo
+    i32.const 20
+    call $resume
+    local.get $utxo
+  )
+
+  ;; yields whatever it was last resumed with
+  (func $main
+    (call $yield (i32.const 48) (i32.const 1) (i32.const 0) (i32.const 4) (i32.const 32) (i32.const 4))
+    (call $yield (i32.const 48) (i32.const 1) (i32.const 32) (i32.const 4) (i32.const 32) (i32.const 4))
+    (call $yield (i32.const 48) (i32.const 1) (i32.const 32) (i32.const 4) (i32.const 0) (i32.const 0))
+  )
+
+  (export "starstream_new_main" (func $main))
+  (export "coord" (func $coord))
+  (export "memory" (memory $mem))
+)
@@ -0,0 +1,61 @@
+(module
+  (import "starstream_utxo:this" "starstream_mint_1" (func $mint (param i64) (result i64)))
+  (import "starstream_utxo:this" "starstream_new_main" (func $make_utxo (param i64) (result i64)))
+  (import "starstream_utxo:this" "starstream_mutate_add" (func $add (param i64 i32)))
+  (import "starstream_utxo:this" "starstream_query_get" (func $get (param i64) (result i32)))
+  (import "starstream_utxo:this" "starstream_consume_finish" (func $finish (param i64)))
+  (import "starstream_token:this" "starstream_bind_Token" (func $bind (param i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 48) "y")
+
+  (func $coord_create (result i64)
+    (call $make_utxo (call $mint (i64.const 10)))
+  )
+
+  (func $coord_add (param $utxo i64)
+    (call $add (local.get $utxo) (i32.const 5))
+  )
+
+  (func $coord_get (param $utxo i64) (result i32)
+    (call $get (local.get $utxo))
+  )
+
+  (func $coord_finish (param $utxo i64)
+    (call $finish (local.get $utxo))
+  )
+
+  (func $mint_1 (param $amount i64))
+
+  (func $bind_1 (param $token i64))
+
+  ;; binds its token, then yields a counter starting at 1
+  (func $main (param $token i64)
+    (call $bind (local.get $token))
+    (i32.store (i32.const 0) (i32.const 1))
+    (call $yield (i32.const 48) (i32.const 1) (i32.const 0) (i32.const 4) (i32.const 0) (i32.const 0))
+  )
+
+  (func $add_value (param $self i32) (param $n i32)
+    (i32.store (local.get $self) (i32.add (i32.load (local.get $self)) (local.get $n)))
+  )
+
+  (func $get_value (result i32)
+    (i32.load (i32.const 0))
+  )
+
+  (func $finish_value (param $self i32))
+
+  (export "starstream_mint_1" (func $mint_1))
+  (export "starstream_bind_Token_1" (func $bind_1))
+  (export "starstream_new_main" (func $main))
+  (export "starstream_mutate_add" (func $add_value))
+  (export "starstream_query_get" (func $get_value))
+  (export "starstream_consume_finish" (func $finish_value))
+  (export "coord_create" (func $coord_create))
+  (export "coord_add" (func $coord_add))
+  (export "coord_get" (func $coord_get))
+  (export "coord_finish" (func $coord_finish))
+  (export "memory" (memory $mem))
+)
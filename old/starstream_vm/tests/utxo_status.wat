@@ -0,0 +1,33 @@
+(module
+  (import "starstream_utxo:wat:utxo_status" "starstream_new_Thing_new" (func $make_thing (result i64)))
+  (import "starstream_utxo:wat:utxo_status" "starstream_status_Thing" (func $status (param i64) (result i32)))
+  (import "starstream_utxo:wat:utxo_status" "starstream_consume_Thing_burn" (func $burn (param i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+
+  (func $coord_create (result i64)
+    call $make_thing
+  )
+
+  (func $coord_status (param $utxo i64) (result i32)
+    (call $status (local.get $utxo))
+  )
+
+  (func $coord_burn (param $utxo i64)
+    (call $burn (local.get $utxo))
+  )
+
+  (func $new
+    (call $yield (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0))
+  )
+
+  (func $consume (param $self i32))
+
+  (export "starstream_new_Thing_new" (func $new))
+  (export "starstream_consume_Thing_burn" (func $consume))
+  (export "coord_create" (func $coord_create))
+  (export "coord_status" (func $coord_status))
+  (export "coord_burn" (func $coord_burn))
+  (export "memory" (memory $mem))
+)
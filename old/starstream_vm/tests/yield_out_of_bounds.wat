@@ -0,0 +1,19 @@
+(module
+  (import "starstream_utxo:wat:yield_out_of_bounds" "starstream_new_main" (func $make_utxo (result i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+
+  (memory $mem 1)
+
+  (func $coord (result i64)
+    call $make_utxo
+  )
+
+  ;; yields twice as many bytes as its memory holds
+  (func $main
+    (call $yield (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 0x20000) (i32.const 0) (i32.const 0))
+  )
+
+  (export "starstream_new_main" (func $main))
+  (export "coord" (func $coord))
+  (export "memory" (memory $mem))
+)
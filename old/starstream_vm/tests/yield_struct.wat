@@ -0,0 +1,53 @@
+(module
+  (import "starstream_utxo:wat:yield_struct" "starstream_new_point" (func $new_point (result i64)))
+  (import "starstream_utxo:wat:yield_struct" "starstream_new_bad_name" (func $new_bad_name (result i64)))
+  (import "starstream_utxo:wat:yield_struct" "starstream_new_bad_layout" (func $new_bad_layout (result i64)))
+  (import "starstream_utxo_env" "starstream_yield" (func $yield (param i32 i32 i32 i32 i32 i32)))
+  (import "starstream_utxo_env" "starstream_yield_layout" (func $yield_layout (param i32 i32)))
+
+  (memory $mem 1)
+  (data (i32.const 0) "Point")
+  ;; struct Point { x: u32, y: u64 }
+  (data (i32.const 16) "\07\00\00\00\00\00\00\00\08\07\06\05\04\03\02\01")
+  (data (i32.const 32) "xy")
+  ;; [name, name_len, offset, size] for x, then y
+  (data (i32.const 48) "\20\00\00\00\01\00\00\00\00\00\00\00\04\00\00\00")
+  (data (i32.const 64) "\21\00\00\00\01\00\00\00\08\00\00\00\08\00\00\00")
+  ;; a field whose name isn't UTF-8
+  (data (i32.const 80) "\ff")
+  (data (i32.const 96) "\50\00\00\00\01\00\00\00\00\00\00\00\04\00\00\00")
+
+  (func $coord (result i64)
+    call $new_point
+  )
+
+  (func $coord_bad_name (result i64)
+    call $new_bad_name
+  )
+
+  (func $coord_bad_layout (result i64)
+    call $new_bad_layout
+  )
+
+  (func $point
+    (call $yield_layout (i32.const 48) (i32.const 2))
+    (call $yield (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 16) (i32.const 0) (i32.const 0))
+  )
+
+  (func $bad_name
+    (call $yield_layout (i32.const 96) (i32.const 1))
+  )
+
+  ;; more fields than fit in memory
+  (func $bad_layout
+    (call $yield_layout (i32.const 48) (i32.const 0x10000))
+  )
+
+  (export "starstream_new_point" (func $point))
+  (export "starstream_new_bad_name" (func $bad_name))
+  (export "starstream_new_bad_layout" (func $bad_layout))
+  (export "coord_bad_name" (func $coord_bad_name))
+  (export "coord_bad_layout" (func $coord_bad_layout))
+  (export "coord" (func $coord))
+  (export "memory" (memory $mem))
+)